@@ -0,0 +1,161 @@
+use std::mem::discriminant;
+
+use polyepoxide_core::{oxide, Bond};
+
+use crate::freebusy::{BusyPeriod, BusyType};
+use crate::recurrence::RecurrenceRule;
+use crate::time::DateTimeValue;
+
+/// A stretch of time during which the owner is available (AVAILABLE)
+#[oxide]
+pub struct AvailableSlot {
+    pub uid: String,
+    pub summary: Option<String>,
+    pub start: DateTimeValue,
+    pub end: DateTimeValue,
+    pub recurrence_rule: Option<Bond<RecurrenceRule>>,
+}
+
+/// Availability component (VAVAILABILITY, RFC 7953)
+///
+/// Between `start` and `end` (unbounded if absent) the owner is busy with
+/// `busy_type`, except during the `available` slots.
+#[oxide]
+pub struct Availability {
+    pub uid: String,
+    pub summary: Option<String>,
+    pub busy_type: BusyType,
+    pub start: Option<DateTimeValue>,
+    pub end: Option<DateTimeValue>,
+    /// 1 is highest, 9 lowest; 0 means undefined and ranks below 9.
+    pub priority: u8,
+    pub available: Vec<Bond<AvailableSlot>>,
+    pub created: i64,
+    pub last_modified: i64,
+    pub sequence: u32,
+}
+
+impl Availability {
+    /// Ordering key where lower wins.
+    fn rank(&self) -> u8 {
+        if self.priority == 0 {
+            10
+        } else {
+            self.priority
+        }
+    }
+
+    /// The component's period clipped to `[start, end)`, if they overlap.
+    fn window(&self, start: i64, end: i64) -> Option<(i64, i64)> {
+        let from = self
+            .start
+            .as_ref()
+            .map_or(start, |s| s.timestamp().max(start));
+        let to = self.end.as_ref().map_or(end, |e| e.timestamp().min(end));
+        (from < to).then_some((from, to))
+    }
+
+    /// Available intervals overlapping `[start, end)`, clipped to it.
+    ///
+    /// Slots that aren't loaded are skipped.
+    fn available_within(&self, start: i64, end: i64) -> Vec<(i64, i64)> {
+        let mut intervals = Vec::new();
+        for slot in self.available.iter().filter_map(|bond| bond.value()) {
+            let slot_start = slot.start.timestamp();
+            let length = slot.end.timestamp() - slot_start;
+            if length <= 0 {
+                continue;
+            }
+            let starts = match slot.recurrence_rule.as_ref().and_then(|rule| rule.value()) {
+                Some(rule) => rule.occurrences_from(&slot.start, start - length + 1, end),
+                None => vec![slot_start],
+            };
+            intervals.extend(
+                starts
+                    .into_iter()
+                    .map(|s| (s.max(start), (s + length).min(end)))
+                    .filter(|(s, e)| s < e),
+            );
+        }
+        intervals
+    }
+}
+
+/// Computes the busy periods that availability components imply for
+/// `[start, end)`.
+///
+/// Where components overlap, the one with the highest priority applies; at
+/// equal priority, time available in any of them is free. Time outside every
+/// component is left unspecified and produces no period. Adjacent periods of
+/// the same type are merged.
+pub fn availability_busy_periods(
+    components: &[&Availability],
+    start: i64,
+    end: i64,
+) -> Vec<BusyPeriod> {
+    struct Clipped<'a> {
+        component: &'a Availability,
+        window: (i64, i64),
+        available: Vec<(i64, i64)>,
+    }
+
+    let clipped: Vec<Clipped> = components
+        .iter()
+        .filter_map(|component| {
+            let window = component.window(start, end)?;
+            Some(Clipped {
+                component,
+                window,
+                available: component.available_within(window.0, window.1),
+            })
+        })
+        .collect();
+
+    let mut bounds = vec![start, end];
+    for c in &clipped {
+        bounds.extend([c.window.0, c.window.1]);
+        for &(s, e) in &c.available {
+            bounds.extend([s, e]);
+        }
+    }
+    bounds.sort_unstable();
+    bounds.dedup();
+
+    let mut periods: Vec<BusyPeriod> = Vec::new();
+    for segment in bounds.windows(2) {
+        let (from, to) = (segment[0], segment[1]);
+        let covering: Vec<&Clipped> = clipped
+            .iter()
+            .filter(|c| c.window.0 <= from && to <= c.window.1)
+            .collect();
+        let Some(best) = covering.iter().map(|c| c.component.rank()).min() else {
+            continue;
+        };
+        let winners: Vec<&&Clipped> = covering
+            .iter()
+            .filter(|c| c.component.rank() == best)
+            .collect();
+        let free = winners
+            .iter()
+            .any(|c| c.available.iter().any(|&(s, e)| s <= from && to <= e));
+        let busy_type = winners[0].component.busy_type.clone();
+        if free || matches!(busy_type, BusyType::Free) {
+            continue;
+        }
+
+        match periods.last_mut() {
+            Some(last)
+                if last.end == from
+                    && discriminant(&last.busy_type) == discriminant(&busy_type) =>
+            {
+                last.end = to;
+            }
+            _ => periods.push(BusyPeriod {
+                start: from,
+                end: to,
+                busy_type,
+            }),
+        }
+    }
+    periods
+}
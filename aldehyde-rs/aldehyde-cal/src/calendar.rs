@@ -1,7 +1,11 @@
+use std::mem::discriminant;
+
 use polyepoxide_core::{oxide, Bond};
 
+use crate::availability::{availability_busy_periods, Availability};
 use crate::event::CalendarEvent;
-use crate::freebusy::FreeBusy;
+use crate::freebusy::{BusyPeriod, BusyType, Conflict, FreeBusy};
+use crate::journal::CalendarJournal;
 use crate::todo::CalendarTodo;
 
 /// A calendar (collection of components)
@@ -11,5 +15,126 @@ pub struct Calendar {
     pub description: Option<String>,
     pub events: Vec<Bond<CalendarEvent>>,
     pub todos: Vec<Bond<CalendarTodo>>,
+    pub journals: Vec<Bond<CalendarJournal>>,
+    pub availability: Vec<Bond<Availability>>,
     pub freebusy: Option<Bond<FreeBusy>>,
 }
+
+impl Calendar {
+    /// Occurrences of the loaded events overlapping `[start, end)`, sorted by
+    /// start time and then uid.
+    fn occurrences(&self, start: i64, end: i64) -> Vec<(i64, i64, &CalendarEvent)> {
+        let mut found: Vec<(i64, i64, &CalendarEvent)> = self
+            .events
+            .iter()
+            .filter_map(|bond| bond.value())
+            .flat_map(|event| {
+                event
+                    .occurrences(start, end)
+                    .into_iter()
+                    .map(move |(s, e)| (s, e, event))
+            })
+            .collect();
+        found.sort_by(|a, b| (a.0, &a.2.uid).cmp(&(b.0, &b.2.uid)));
+        found
+    }
+
+    /// Computes the busy periods within `[start, end)`: the time taken by
+    /// events, expanding recurrences, and the time availability components
+    /// mark busy (see [`availability_busy_periods`](crate::availability_busy_periods)).
+    ///
+    /// Events take precedence, so their time is `Busy` even where availability
+    /// gives another type. Overlapping event occurrences and adjacent periods
+    /// of the same type are merged. Components that aren't loaded are skipped.
+    pub fn free_busy(&self, start: i64, end: i64) -> FreeBusy {
+        let events = self.event_periods(start, end);
+        let availability: Vec<&Availability> = self
+            .availability
+            .iter()
+            .filter_map(|bond| bond.value())
+            .collect();
+
+        let mut periods = events.clone();
+        for period in availability_busy_periods(&availability, start, end) {
+            let mut from = period.start;
+            for event in &events {
+                if event.start >= period.end {
+                    break;
+                }
+                if event.start > from {
+                    periods.push(BusyPeriod {
+                        start: from,
+                        end: event.start,
+                        busy_type: period.busy_type.clone(),
+                    });
+                }
+                from = from.max(event.end);
+            }
+            if from < period.end {
+                periods.push(BusyPeriod {
+                    start: from,
+                    end: period.end,
+                    busy_type: period.busy_type,
+                });
+            }
+        }
+        periods.sort_by_key(|period| period.start);
+
+        let mut merged: Vec<BusyPeriod> = Vec::new();
+        for period in periods {
+            match merged.last_mut() {
+                Some(last)
+                    if last.end == period.start
+                        && discriminant(&last.busy_type) == discriminant(&period.busy_type) =>
+                {
+                    last.end = period.end;
+                }
+                _ => merged.push(period),
+            }
+        }
+        FreeBusy {
+            start,
+            end,
+            periods: merged,
+        }
+    }
+
+    /// Time taken by events within `[start, end)`, with overlapping and
+    /// adjacent occurrences merged.
+    fn event_periods(&self, start: i64, end: i64) -> Vec<BusyPeriod> {
+        let mut periods: Vec<BusyPeriod> = Vec::new();
+        for (s, e, _) in self.occurrences(start, end) {
+            let (s, e) = (s.max(start), e.min(end));
+            match periods.last_mut() {
+                Some(last) if s <= last.end => last.end = last.end.max(e),
+                _ => periods.push(BusyPeriod {
+                    start: s,
+                    end: e,
+                    busy_type: BusyType::Busy,
+                }),
+            }
+        }
+        periods
+    }
+
+    /// Reports each pair of overlapping occurrences of different events
+    /// within `[start, end)`, in order of the later start.
+    pub fn find_conflicts(&self, start: i64, end: i64) -> Vec<Conflict> {
+        let occurrences = self.occurrences(start, end);
+        let mut conflicts = Vec::new();
+        for (i, &(s, e, event)) in occurrences.iter().enumerate() {
+            for &(other_s, other_e, other) in &occurrences[..i] {
+                if other_e <= s || other.uid == event.uid {
+                    continue;
+                }
+                conflicts.push(Conflict {
+                    first: other.uid.clone(),
+                    second: event.uid.clone(),
+                    start: s,
+                    end: e.min(other_e),
+                });
+            }
+        }
+        conflicts
+    }
+}
@@ -3,7 +3,7 @@ use polyepoxide_core::{oxide, Bond};
 use crate::alarm::Alarm;
 use crate::attendee::{Attendee, Organizer};
 use crate::recurrence::RecurrenceRule;
-use crate::time::DateTimeValue;
+use crate::time::{DateTimeValue, SECONDS_PER_DAY};
 
 pub type EventUid = String;
 
@@ -25,3 +25,40 @@ pub struct CalendarEvent {
     pub last_modified: i64,
     pub sequence: u32,
 }
+
+impl CalendarEvent {
+    /// Length of each occurrence in seconds. Without an end, events on a date
+    /// last the day and timed events take no time, as in RFC 5545.
+    pub fn length(&self) -> i64 {
+        match (&self.end, &self.start) {
+            (Some(end), start) => end.timestamp() - start.timestamp(),
+            (None, DateTimeValue::Date(_)) => SECONDS_PER_DAY,
+            (None, DateTimeValue::DateTime(_)) => 0,
+        }
+    }
+
+    /// Start and end of the occurrences that overlap `[start, end)`, in order.
+    ///
+    /// Occurrences listed in `recurrence_exceptions` are left out; an
+    /// unloaded rule counts as no recurrence. With the `tz` feature, a series
+    /// in a known timezone keeps its local time across daylight saving
+    /// changes. Events taking no time never
+    /// overlap and produce nothing.
+    pub fn occurrences(&self, start: i64, end: i64) -> Vec<(i64, i64)> {
+        let first = self.start.timestamp();
+        let length = self.length();
+        if length <= 0 {
+            return Vec::new();
+        }
+        let starts = match self.recurrence_rule.as_ref().and_then(|rule| rule.value()) {
+            Some(rule) => rule.occurrences_from(&self.start, start - length + 1, end),
+            None => vec![first],
+        };
+        starts
+            .into_iter()
+            .filter(|s| !self.recurrence_exceptions.contains(s))
+            .map(|s| (s, s + length))
+            .filter(|&(s, e)| s < end && e > start)
+            .collect()
+    }
+}
@@ -1,5 +1,7 @@
 use polyepoxide_core::oxide;
 
+use crate::event::EventUid;
+
 /// Type of busy period
 #[oxide]
 pub enum BusyType {
@@ -24,3 +26,16 @@ pub struct FreeBusy {
     pub end: i64,
     pub periods: Vec<BusyPeriod>,
 }
+
+/// Two events whose occurrences overlap, found by
+/// [`Calendar::find_conflicts`](crate::Calendar::find_conflicts).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    /// The event whose occurrence starts first, or has the smaller uid if
+    /// both start together
+    pub first: EventUid,
+    pub second: EventUid,
+    /// Overlap of the two occurrences
+    pub start: i64,
+    pub end: i64,
+}
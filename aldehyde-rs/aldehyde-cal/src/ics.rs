@@ -0,0 +1,1015 @@
+//! iCalendar (RFC 5545) text format for event, journal and availability
+//! components.
+//!
+//! Alarms of events aren't read or written.
+//!
+//! Times are written in UTC. Local times qualified with TZID are converted
+//! to UTC when the `tz` feature is enabled and the TZID is an IANA timezone;
+//! otherwise they're read as if they were UTC, since VTIMEZONE definitions
+//! aren't resolved here. Either way the TZID is kept as the display timezone.
+
+use polyepoxide_core::{Bond, Cid};
+
+use crate::attendee::{Attendee, AttendeeRole, CalendarUserType, Organizer, ParticipationStatus};
+use crate::availability::{Availability, AvailableSlot};
+use crate::event::CalendarEvent;
+use crate::freebusy::BusyType;
+use crate::journal::{CalendarJournal, JournalStatus};
+use crate::recurrence::{Frequency, RecurrenceRule, Weekday};
+use crate::time::{DateTime, DateTimeValue, DateValue, SECONDS_PER_DAY};
+
+const PRODID: &str = "-//krcz//aldehyde-cal//EN";
+
+/// Longest content line, in octets, before folding.
+const MAX_LINE: usize = 75;
+
+/// Error reading or writing iCalendar text.
+#[derive(Debug, thiserror::Error)]
+pub enum IcsError {
+    #[error("line {line}: {message}")]
+    Syntax { line: usize, message: String },
+    #[error("{component} is missing required property {property}")]
+    MissingProperty {
+        component: &'static str,
+        property: &'static str,
+    },
+    #[error("bond {0} is not loaded")]
+    Unresolved(Cid),
+}
+
+/// A component read from or written to iCalendar text.
+#[derive(Debug, Clone)]
+pub enum IcsComponent {
+    Event(CalendarEvent),
+    Journal(CalendarJournal),
+    Availability(Availability),
+}
+
+/// Serializes components as a complete VCALENDAR object.
+pub fn write_calendar(components: &[IcsComponent]) -> Result<String, IcsError> {
+    let mut out = Writer::default();
+    out.line("BEGIN:VCALENDAR");
+    out.line("VERSION:2.0");
+    out.line(&format!("PRODID:{}", PRODID));
+    for component in components {
+        match component {
+            IcsComponent::Event(event) => write_event(&mut out, event)?,
+            IcsComponent::Journal(journal) => write_journal(&mut out, journal),
+            IcsComponent::Availability(availability) => write_availability(&mut out, availability)?,
+        }
+    }
+    out.line("END:VCALENDAR");
+    Ok(out.finish())
+}
+
+/// Serializes a journal entry as a VJOURNAL component.
+pub fn journal_to_ics(journal: &CalendarJournal) -> String {
+    let mut out = Writer::default();
+    write_journal(&mut out, journal);
+    out.finish()
+}
+
+/// Serializes an availability as a VAVAILABILITY component.
+///
+/// Its slots and their recurrence rules must be loaded.
+pub fn availability_to_ics(availability: &Availability) -> Result<String, IcsError> {
+    let mut out = Writer::default();
+    write_availability(&mut out, availability)?;
+    Ok(out.finish())
+}
+
+/// Reads the VEVENT, VJOURNAL and VAVAILABILITY components of iCalendar text.
+///
+/// Components may be wrapped in VCALENDAR objects or stand alone; other
+/// component types are skipped, as are VEVENTs overriding single occurrences
+/// of a recurring event (with a RECURRENCE-ID).
+pub fn parse_ics(input: &str) -> Result<Vec<IcsComponent>, IcsError> {
+    let nodes = parse_nodes(input)?;
+    let mut components = Vec::new();
+    for child in unwrap_calendars(&nodes) {
+        match child.name.as_str() {
+            "VEVENT" if child.get("RECURRENCE-ID").is_none() => {
+                components.push(IcsComponent::Event(read_event(child)?))
+            }
+            "VJOURNAL" => components.push(IcsComponent::Journal(read_journal(child)?)),
+            "VAVAILABILITY" => {
+                components.push(IcsComponent::Availability(read_availability(child)?))
+            }
+            _ => {}
+        }
+    }
+    Ok(components)
+}
+
+/// Whether iCalendar text has parts of events that `parse_ics` skips:
+/// alarms, or overrides of single occurrences. Writing its events back
+/// would lose them.
+pub fn has_unread_event_parts(input: &str) -> Result<bool, IcsError> {
+    let nodes = parse_nodes(input)?;
+    Ok(unwrap_calendars(&nodes).any(|child| {
+        child.name == "VEVENT"
+            && (child.get("RECURRENCE-ID").is_some() || !child.children.is_empty())
+    }))
+}
+
+/// Components inside VCALENDAR objects, and ones standing alone.
+fn unwrap_calendars(nodes: &[Node]) -> impl Iterator<Item = &Node> {
+    nodes.iter().flat_map(|node| {
+        if node.name == "VCALENDAR" {
+            node.children.as_slice()
+        } else {
+            std::slice::from_ref(node)
+        }
+    })
+}
+
+// Writing
+
+#[derive(Default)]
+struct Writer {
+    out: String,
+}
+
+impl Writer {
+    /// Appends a content line, folded at 75 octets.
+    fn line(&mut self, line: &str) {
+        let mut width = 0;
+        for c in line.chars() {
+            if width + c.len_utf8() > MAX_LINE {
+                self.out.push_str("\r\n ");
+                width = 1;
+            }
+            self.out.push(c);
+            width += c.len_utf8();
+        }
+        self.out.push_str("\r\n");
+    }
+
+    fn text(&mut self, name: &str, value: &str) {
+        self.line(&format!("{}:{}", name, escape_text(value)));
+    }
+
+    fn date_time(&mut self, name: &str, value: &DateTimeValue) {
+        match value {
+            DateTimeValue::Date(date) => {
+                self.line(&format!("{};VALUE=DATE:{}", name, format_date(date)))
+            }
+            DateTimeValue::DateTime(datetime) => self.timestamp(name, datetime.utc_timestamp),
+        }
+    }
+
+    fn timestamp(&mut self, name: &str, timestamp: i64) {
+        self.line(&format!("{}:{}", name, format_timestamp(timestamp)));
+    }
+
+    fn finish(self) -> String {
+        self.out
+    }
+}
+
+fn write_event(out: &mut Writer, event: &CalendarEvent) -> Result<(), IcsError> {
+    out.line("BEGIN:VEVENT");
+    out.text("UID", &event.uid);
+    out.timestamp("DTSTAMP", event.last_modified);
+    out.date_time("DTSTART", &event.start);
+    if let Some(end) = &event.end {
+        out.date_time("DTEND", end);
+    }
+    out.text("SUMMARY", &event.summary);
+    if let Some(description) = &event.description {
+        out.text("DESCRIPTION", description);
+    }
+    if let Some(location) = &event.location {
+        out.text("LOCATION", location);
+    }
+    if let Some(rule) = &event.recurrence_rule {
+        let rule = rule.value().ok_or(IcsError::Unresolved(rule.cid()))?;
+        out.line(&format!("RRULE:{}", format_rrule(rule)));
+    }
+    for &exception in &event.recurrence_exceptions {
+        match event.start {
+            DateTimeValue::Date(_) => out.line(&format!(
+                "EXDATE;VALUE=DATE:{}",
+                format_date(&DateValue::from_timestamp(exception))
+            )),
+            DateTimeValue::DateTime(_) => out.timestamp("EXDATE", exception),
+        }
+    }
+    if let Some(organizer) = &event.organizer {
+        let organizer = organizer
+            .value()
+            .ok_or(IcsError::Unresolved(organizer.cid()))?;
+        let name = organizer.common_name.as_deref().map(quote_param);
+        let name = name.map_or_else(String::new, |name| format!(";CN={}", name));
+        out.line(&format!("ORGANIZER{}:mailto:{}", name, organizer.email));
+    }
+    for bond in &event.attendees {
+        let attendee = bond.value().ok_or(IcsError::Unresolved(bond.cid()))?;
+        let mut params = String::new();
+        if let Some(name) = &attendee.common_name {
+            params.push_str(&format!(";CN={}", quote_param(name)));
+        }
+        let role = match attendee.role {
+            AttendeeRole::Chair => "CHAIR",
+            AttendeeRole::ReqParticipant => "REQ-PARTICIPANT",
+            AttendeeRole::OptParticipant => "OPT-PARTICIPANT",
+            AttendeeRole::NonParticipant => "NON-PARTICIPANT",
+        };
+        let status = match attendee.status {
+            ParticipationStatus::NeedsAction => "NEEDS-ACTION",
+            ParticipationStatus::Accepted => "ACCEPTED",
+            ParticipationStatus::Declined => "DECLINED",
+            ParticipationStatus::Tentative => "TENTATIVE",
+        };
+        let user_type = match attendee.user_type {
+            CalendarUserType::Individual => "INDIVIDUAL",
+            CalendarUserType::Group => "GROUP",
+            CalendarUserType::Resource => "RESOURCE",
+            CalendarUserType::Room => "ROOM",
+            CalendarUserType::Unknown => "UNKNOWN",
+        };
+        params.push_str(&format!(
+            ";ROLE={};PARTSTAT={};CUTYPE={}",
+            role, status, user_type
+        ));
+        if attendee.rsvp {
+            params.push_str(";RSVP=TRUE");
+        }
+        out.line(&format!("ATTENDEE{}:mailto:{}", params, attendee.email));
+    }
+    out.timestamp("CREATED", event.created);
+    out.timestamp("LAST-MODIFIED", event.last_modified);
+    out.line(&format!("SEQUENCE:{}", event.sequence));
+    out.line("END:VEVENT");
+    Ok(())
+}
+
+fn write_journal(out: &mut Writer, journal: &CalendarJournal) {
+    out.line("BEGIN:VJOURNAL");
+    out.text("UID", &journal.uid);
+    out.timestamp("DTSTAMP", journal.last_modified);
+    if let Some(start) = &journal.start {
+        out.date_time("DTSTART", start);
+    }
+    if let Some(summary) = &journal.summary {
+        out.text("SUMMARY", summary);
+    }
+    for description in &journal.descriptions {
+        out.text("DESCRIPTION", description);
+    }
+    if let Some(status) = &journal.status {
+        let status = match status {
+            JournalStatus::Draft => "DRAFT",
+            JournalStatus::Final => "FINAL",
+            JournalStatus::Cancelled => "CANCELLED",
+        };
+        out.line(&format!("STATUS:{}", status));
+    }
+    if !journal.categories.is_empty() {
+        let categories: Vec<String> = journal.categories.iter().map(|c| escape_text(c)).collect();
+        out.line(&format!("CATEGORIES:{}", categories.join(",")));
+    }
+    out.timestamp("CREATED", journal.created);
+    out.timestamp("LAST-MODIFIED", journal.last_modified);
+    out.line(&format!("SEQUENCE:{}", journal.sequence));
+    out.line("END:VJOURNAL");
+}
+
+fn write_availability(out: &mut Writer, availability: &Availability) -> Result<(), IcsError> {
+    out.line("BEGIN:VAVAILABILITY");
+    out.text("UID", &availability.uid);
+    out.timestamp("DTSTAMP", availability.last_modified);
+    let busy_type = match availability.busy_type {
+        BusyType::Busy => Some("BUSY"),
+        BusyType::BusyUnavailable => Some("BUSY-UNAVAILABLE"),
+        BusyType::BusyTentative => Some("BUSY-TENTATIVE"),
+        // Not a valid BUSYTYPE; left out so readers apply the default
+        BusyType::Free => None,
+    };
+    if let Some(busy_type) = busy_type {
+        out.line(&format!("BUSYTYPE:{}", busy_type));
+    }
+    if let Some(start) = &availability.start {
+        out.date_time("DTSTART", start);
+    }
+    if let Some(end) = &availability.end {
+        out.date_time("DTEND", end);
+    }
+    if availability.priority != 0 {
+        out.line(&format!("PRIORITY:{}", availability.priority));
+    }
+    if let Some(summary) = &availability.summary {
+        out.text("SUMMARY", summary);
+    }
+    out.timestamp("CREATED", availability.created);
+    out.timestamp("LAST-MODIFIED", availability.last_modified);
+    out.line(&format!("SEQUENCE:{}", availability.sequence));
+
+    for bond in &availability.available {
+        let slot = bond.value().ok_or(IcsError::Unresolved(bond.cid()))?;
+        out.line("BEGIN:AVAILABLE");
+        out.text("UID", &slot.uid);
+        out.timestamp("DTSTAMP", availability.last_modified);
+        out.date_time("DTSTART", &slot.start);
+        out.date_time("DTEND", &slot.end);
+        if let Some(rule) = &slot.recurrence_rule {
+            let rule = rule.value().ok_or(IcsError::Unresolved(rule.cid()))?;
+            out.line(&format!("RRULE:{}", format_rrule(rule)));
+        }
+        if let Some(summary) = &slot.summary {
+            out.text("SUMMARY", summary);
+        }
+        out.line("END:AVAILABLE");
+    }
+    out.line("END:VAVAILABILITY");
+    Ok(())
+}
+
+fn escape_text(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            ';' => out.push_str("\\;"),
+            ',' => out.push_str("\\,"),
+            '\n' => out.push_str("\\n"),
+            '\r' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Quotes a parameter value, dropping the quotes it can't contain.
+fn quote_param(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', ""))
+}
+
+fn format_date(date: &DateValue) -> String {
+    format!("{:04}{:02}{:02}", date.year, date.month, date.day)
+}
+
+fn format_timestamp(timestamp: i64) -> String {
+    let date = DateValue::from_timestamp(timestamp);
+    let seconds = timestamp.rem_euclid(SECONDS_PER_DAY);
+    format!(
+        "{}T{:02}{:02}{:02}Z",
+        format_date(&date),
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+fn format_rrule(rule: &RecurrenceRule) -> String {
+    let frequency = match rule.frequency {
+        Frequency::Daily => "DAILY",
+        Frequency::Weekly => "WEEKLY",
+        Frequency::Monthly => "MONTHLY",
+        Frequency::Yearly => "YEARLY",
+    };
+    let mut parts = vec![format!("FREQ={}", frequency)];
+    if rule.interval > 1 {
+        parts.push(format!("INTERVAL={}", rule.interval));
+    }
+    if let Some(count) = rule.count {
+        parts.push(format!("COUNT={}", count));
+    }
+    if let Some(until) = rule.until {
+        parts.push(format!("UNTIL={}", format_timestamp(until)));
+    }
+    if !rule.by_day.is_empty() {
+        let days: Vec<&str> = rule.by_day.iter().map(weekday_code).collect();
+        parts.push(format!("BYDAY={}", days.join(",")));
+    }
+    if !rule.by_month_day.is_empty() {
+        let days: Vec<String> = rule.by_month_day.iter().map(|d| d.to_string()).collect();
+        parts.push(format!("BYMONTHDAY={}", days.join(",")));
+    }
+    if !rule.by_month.is_empty() {
+        let months: Vec<String> = rule.by_month.iter().map(|m| m.to_string()).collect();
+        parts.push(format!("BYMONTH={}", months.join(",")));
+    }
+    parts.join(";")
+}
+
+fn weekday_code(day: &Weekday) -> &'static str {
+    match day {
+        Weekday::Monday => "MO",
+        Weekday::Tuesday => "TU",
+        Weekday::Wednesday => "WE",
+        Weekday::Thursday => "TH",
+        Weekday::Friday => "FR",
+        Weekday::Saturday => "SA",
+        Weekday::Sunday => "SU",
+    }
+}
+
+// Reading
+
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+    line: usize,
+}
+
+impl Property {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn error(&self, message: impl Into<String>) -> IcsError {
+        IcsError::Syntax {
+            line: self.line,
+            message: message.into(),
+        }
+    }
+}
+
+struct Node {
+    name: String,
+    properties: Vec<Property>,
+    children: Vec<Node>,
+}
+
+impl Node {
+    fn get(&self, name: &str) -> Option<&Property> {
+        self.properties.iter().find(|p| p.name == name)
+    }
+
+    fn all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Property> + 'a {
+        self.properties.iter().filter(move |p| p.name == name)
+    }
+
+    fn require(&self, component: &'static str, name: &'static str) -> Result<&Property, IcsError> {
+        self.get(name).ok_or(IcsError::MissingProperty {
+            component,
+            property: name,
+        })
+    }
+
+    fn text(&self, name: &str) -> Option<String> {
+        self.get(name).map(|p| unescape_text(&p.value))
+    }
+
+    fn timestamp(&self, name: &str) -> Result<Option<i64>, IcsError> {
+        self.get(name)
+            .map(|p| parse_date_time(p).map(|value| value.timestamp()))
+            .transpose()
+    }
+
+    fn date_time(&self, name: &str) -> Result<Option<DateTimeValue>, IcsError> {
+        self.get(name).map(parse_date_time).transpose()
+    }
+
+    fn number<T: std::str::FromStr>(&self, name: &str) -> Result<Option<T>, IcsError> {
+        self.get(name)
+            .map(|p| {
+                p.value
+                    .trim()
+                    .parse()
+                    .map_err(|_| p.error(format!("invalid {}", p.name)))
+            })
+            .transpose()
+    }
+}
+
+/// Unfolds content lines, returning each with its starting line number.
+fn unfold(input: &str) -> Vec<(usize, String)> {
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    for (index, raw) in input.split('\n').enumerate() {
+        let raw = raw.strip_suffix('\r').unwrap_or(raw);
+        if let Some(rest) = raw.strip_prefix([' ', '\t']) {
+            if let Some((_, last)) = lines.last_mut() {
+                last.push_str(rest);
+                continue;
+            }
+        }
+        if !raw.is_empty() {
+            lines.push((index + 1, raw.to_string()));
+        }
+    }
+    lines
+}
+
+fn parse_property(line: usize, text: &str) -> Result<Property, IcsError> {
+    let syntax = |message: &str| IcsError::Syntax {
+        line,
+        message: message.to_string(),
+    };
+
+    // The value starts at the first colon outside a quoted parameter value
+    let mut quoted = false;
+    let colon = text
+        .char_indices()
+        .find(|&(_, c)| {
+            if c == '"' {
+                quoted = !quoted;
+            }
+            c == ':' && !quoted
+        })
+        .map(|(i, _)| i)
+        .ok_or_else(|| syntax("missing ':'"))?;
+    let (head, value) = (&text[..colon], &text[colon + 1..]);
+
+    let mut segments = split_unquoted(head, ';').into_iter();
+    let name = segments.next().unwrap_or_default().to_ascii_uppercase();
+    if name.is_empty() {
+        return Err(syntax("missing property name"));
+    }
+    let mut params = Vec::new();
+    for segment in segments {
+        let (key, value) = segment
+            .split_once('=')
+            .ok_or_else(|| syntax("parameter without '='"))?;
+        params.push((
+            key.to_ascii_uppercase(),
+            value.trim_matches('"').to_string(),
+        ));
+    }
+
+    Ok(Property {
+        name,
+        params,
+        value: value.to_string(),
+        line,
+    })
+}
+
+fn split_unquoted(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        if c == '"' {
+            quoted = !quoted;
+        } else if c == separator && !quoted {
+            parts.push(&text[start..i]);
+            start = i + 1;
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+fn parse_nodes(input: &str) -> Result<Vec<Node>, IcsError> {
+    let mut roots = Vec::new();
+    let mut stack: Vec<Node> = Vec::new();
+    for (line, text) in unfold(input) {
+        let property = parse_property(line, &text)?;
+        match property.name.as_str() {
+            "BEGIN" => stack.push(Node {
+                name: property.value.trim().to_ascii_uppercase(),
+                properties: Vec::new(),
+                children: Vec::new(),
+            }),
+            "END" => {
+                let name = property.value.trim().to_ascii_uppercase();
+                let node = stack
+                    .pop()
+                    .filter(|node| node.name == name)
+                    .ok_or_else(|| property.error(format!("unexpected END:{}", name)))?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(node),
+                    None => roots.push(node),
+                }
+            }
+            _ => stack
+                .last_mut()
+                .ok_or_else(|| property.error("property outside a component"))?
+                .properties
+                .push(property),
+        }
+    }
+    if let Some(open) = stack.last() {
+        return Err(IcsError::Syntax {
+            line: input.lines().count(),
+            message: format!("BEGIN:{} is never closed", open.name),
+        });
+    }
+    Ok(roots)
+}
+
+fn unescape_text(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n' | 'N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Splits a list of TEXT values on unescaped commas.
+fn split_text_list(value: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            ',' => {
+                items.push(unescape_text(&value[start..i]));
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(unescape_text(&value[start..]));
+    items
+}
+
+fn parse_date_time(property: &Property) -> Result<DateTimeValue, IcsError> {
+    let value = property.value.trim();
+    let invalid = || property.error(format!("invalid date-time {:?}", value));
+    let date_only = property
+        .param("VALUE")
+        .is_some_and(|v| v.eq_ignore_ascii_case("DATE"))
+        || !value.contains('T');
+
+    let (date, time) = match value.split_once('T') {
+        Some((date, time)) => (date, Some(time)),
+        None => (value, None),
+    };
+    let date = parse_date(date).ok_or_else(invalid)?;
+    if date_only {
+        return Ok(DateTimeValue::Date(date));
+    }
+
+    let time = time.ok_or_else(invalid)?;
+    let (time, utc) = match time.strip_suffix('Z') {
+        Some(time) => (time, true),
+        None => (time, false),
+    };
+    if time.len() != 6 || !time.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let field = |range: std::ops::Range<usize>| time[range].parse::<i64>().unwrap_or_default();
+    let (hour, minute, second) = (field(0..2), field(2..4), field(4..6));
+    if hour > 23 || minute > 59 || second > 60 {
+        return Err(invalid());
+    }
+
+    let time = date.to_timestamp() + hour * 3600 + minute * 60 + second;
+    Ok(DateTimeValue::DateTime(match property.param("TZID") {
+        Some(tzid) if !utc => local_date_time(time, tzid),
+        _ => DateTime {
+            utc_timestamp: time,
+            timezone: "UTC".to_string(),
+        },
+    }))
+}
+
+fn local_date_time(local: i64, tzid: &str) -> DateTime {
+    #[cfg(feature = "tz")]
+    if let Ok(datetime) = DateTime::from_local(local, tzid) {
+        return datetime;
+    }
+    DateTime {
+        utc_timestamp: local,
+        timezone: tzid.to_string(),
+    }
+}
+
+fn parse_date(value: &str) -> Option<DateValue> {
+    if value.len() != 8 || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let date = DateValue {
+        year: value[0..4].parse().ok()?,
+        month: value[4..6].parse().ok()?,
+        day: value[6..8].parse().ok()?,
+    };
+    let valid = (1..=12).contains(&date.month)
+        && date.day >= 1
+        && date.day <= crate::time::days_in_month(date.year, date.month);
+    valid.then_some(date)
+}
+
+/// Parses a DURATION value (e.g. `PT1H30M`, `P1W`) into seconds.
+fn parse_duration(property: &Property) -> Result<i64, IcsError> {
+    let value = property.value.trim();
+    let invalid = || property.error(format!("invalid duration {:?}", value));
+    let (negative, rest) = match value.as_bytes().first() {
+        Some(b'-') => (true, &value[1..]),
+        Some(b'+') => (false, &value[1..]),
+        _ => (false, value),
+    };
+    let rest = rest.strip_prefix('P').ok_or_else(invalid)?;
+
+    let mut seconds = 0i64;
+    let mut digits = String::new();
+    let mut in_time = false;
+    for c in rest.chars() {
+        match c {
+            '0'..='9' => digits.push(c),
+            'T' if digits.is_empty() => in_time = true,
+            _ => {
+                let amount: i64 = digits.parse().map_err(|_| invalid())?;
+                digits.clear();
+                let unit = match (c, in_time) {
+                    ('W', false) => 7 * SECONDS_PER_DAY,
+                    ('D', false) => SECONDS_PER_DAY,
+                    ('H', true) => 3600,
+                    ('M', true) => 60,
+                    ('S', true) => 1,
+                    _ => return Err(invalid()),
+                };
+                seconds += amount * unit;
+            }
+        }
+    }
+    if !digits.is_empty() {
+        return Err(invalid());
+    }
+    Ok(if negative { -seconds } else { seconds })
+}
+
+fn parse_rrule(property: &Property) -> Result<RecurrenceRule, IcsError> {
+    let mut rule = RecurrenceRule {
+        frequency: Frequency::Daily,
+        interval: 1,
+        count: None,
+        until: None,
+        by_day: Vec::new(),
+        by_month_day: Vec::new(),
+        by_month: Vec::new(),
+    };
+    let mut frequency = None;
+    for part in property.value.trim().split(';').filter(|p| !p.is_empty()) {
+        let (key, value) = part
+            .split_once('=')
+            .ok_or_else(|| property.error(format!("invalid RRULE part {:?}", part)))?;
+        let invalid = || property.error(format!("invalid RRULE {}={}", key, value));
+        match key.to_ascii_uppercase().as_str() {
+            "FREQ" => {
+                frequency = Some(match value.to_ascii_uppercase().as_str() {
+                    "DAILY" => Frequency::Daily,
+                    "WEEKLY" => Frequency::Weekly,
+                    "MONTHLY" => Frequency::Monthly,
+                    "YEARLY" => Frequency::Yearly,
+                    _ => return Err(invalid()),
+                })
+            }
+            "INTERVAL" => rule.interval = value.parse().map_err(|_| invalid())?,
+            "COUNT" => rule.count = Some(value.parse().map_err(|_| invalid())?),
+            "UNTIL" => {
+                let until = Property {
+                    name: "UNTIL".to_string(),
+                    params: Vec::new(),
+                    value: value.to_string(),
+                    line: property.line,
+                };
+                rule.until = Some(parse_date_time(&until)?.timestamp());
+            }
+            "BYDAY" => {
+                rule.by_day = value
+                    .split(',')
+                    .map(|day| parse_weekday(day).ok_or_else(invalid))
+                    .collect::<Result<_, _>>()?
+            }
+            "BYMONTHDAY" => {
+                rule.by_month_day = value
+                    .split(',')
+                    .map(|day| day.parse().map_err(|_| invalid()))
+                    .collect::<Result<_, _>>()?
+            }
+            "BYMONTH" => {
+                rule.by_month = value
+                    .split(',')
+                    .map(|month| month.parse().map_err(|_| invalid()))
+                    .collect::<Result<_, _>>()?
+            }
+            // WKST and other parts don't affect the supported expansions
+            _ => {}
+        }
+    }
+    rule.frequency = frequency.ok_or_else(|| property.error("RRULE without FREQ"))?;
+    Ok(rule)
+}
+
+fn parse_weekday(code: &str) -> Option<Weekday> {
+    Some(match code.trim().to_ascii_uppercase().as_str() {
+        "MO" => Weekday::Monday,
+        "TU" => Weekday::Tuesday,
+        "WE" => Weekday::Wednesday,
+        "TH" => Weekday::Thursday,
+        "FR" => Weekday::Friday,
+        "SA" => Weekday::Saturday,
+        "SU" => Weekday::Sunday,
+        _ => return None,
+    })
+}
+
+fn read_event(node: &Node) -> Result<CalendarEvent, IcsError> {
+    const COMPONENT: &str = "VEVENT";
+    let uid = unescape_text(&node.require(COMPONENT, "UID")?.value);
+    let stamp = node.timestamp("DTSTAMP")?.unwrap_or_default();
+    let start = parse_date_time(node.require(COMPONENT, "DTSTART")?)?;
+    let end = match (node.get("DTEND"), node.get("DURATION")) {
+        (Some(end), _) => Some(parse_date_time(end)?),
+        (None, Some(duration)) => Some(shift(&start, parse_duration(duration)?)),
+        (None, None) => None,
+    };
+    let mut recurrence_exceptions = Vec::new();
+    for property in node.all("EXDATE") {
+        for value in property.value.split(',') {
+            let exception = Property {
+                name: property.name.clone(),
+                params: property.params.clone(),
+                value: value.to_string(),
+                line: property.line,
+            };
+            recurrence_exceptions.push(parse_date_time(&exception)?.timestamp());
+        }
+    }
+    let organizer = node.get("ORGANIZER").map(|p| Organizer {
+        email: mail_address(&p.value),
+        common_name: p.param("CN").map(str::to_string),
+    });
+    let attendees = node.all("ATTENDEE").map(read_attendee).map(Bond::new);
+    let recurrence_rule = node.get("RRULE").map(parse_rrule).transpose()?;
+
+    Ok(CalendarEvent {
+        uid,
+        summary: node.text("SUMMARY").unwrap_or_default(),
+        description: node.text("DESCRIPTION"),
+        location: node.text("LOCATION"),
+        start,
+        end,
+        recurrence_rule: recurrence_rule.map(Bond::new),
+        recurrence_exceptions,
+        organizer: organizer.map(Bond::new),
+        attendees: attendees.collect(),
+        alarms: Vec::new(),
+        created: node.timestamp("CREATED")?.unwrap_or(stamp),
+        last_modified: node.timestamp("LAST-MODIFIED")?.unwrap_or(stamp),
+        sequence: node.number("SEQUENCE")?.unwrap_or_default(),
+    })
+}
+
+/// Reads an ATTENDEE, taking unknown parameter values as their defaults.
+fn read_attendee(property: &Property) -> Attendee {
+    let param = |name| {
+        property
+            .param(name)
+            .map(|value| value.to_ascii_uppercase())
+            .unwrap_or_default()
+    };
+    Attendee {
+        email: mail_address(&property.value),
+        common_name: property.param("CN").map(str::to_string),
+        status: match param("PARTSTAT").as_str() {
+            "ACCEPTED" => ParticipationStatus::Accepted,
+            "DECLINED" => ParticipationStatus::Declined,
+            "TENTATIVE" => ParticipationStatus::Tentative,
+            _ => ParticipationStatus::NeedsAction,
+        },
+        role: match param("ROLE").as_str() {
+            "CHAIR" => AttendeeRole::Chair,
+            "OPT-PARTICIPANT" => AttendeeRole::OptParticipant,
+            "NON-PARTICIPANT" => AttendeeRole::NonParticipant,
+            _ => AttendeeRole::ReqParticipant,
+        },
+        user_type: match param("CUTYPE").as_str() {
+            "" | "INDIVIDUAL" => CalendarUserType::Individual,
+            "GROUP" => CalendarUserType::Group,
+            "RESOURCE" => CalendarUserType::Resource,
+            "ROOM" => CalendarUserType::Room,
+            _ => CalendarUserType::Unknown,
+        },
+        rsvp: param("RSVP") == "TRUE",
+    }
+}
+
+/// The address of a `mailto:` URI; other URIs are kept whole.
+fn mail_address(uri: &str) -> String {
+    let uri = uri.trim();
+    match uri.get(..7) {
+        Some(scheme) if scheme.eq_ignore_ascii_case("mailto:") => uri[7..].to_string(),
+        _ => uri.to_string(),
+    }
+}
+
+fn read_journal(node: &Node) -> Result<CalendarJournal, IcsError> {
+    const COMPONENT: &str = "VJOURNAL";
+    let uid = unescape_text(&node.require(COMPONENT, "UID")?.value);
+    let stamp = node.timestamp("DTSTAMP")?.unwrap_or_default();
+    let status = node
+        .get("STATUS")
+        .map(|p| match p.value.trim().to_ascii_uppercase().as_str() {
+            "DRAFT" => Ok(JournalStatus::Draft),
+            "FINAL" => Ok(JournalStatus::Final),
+            "CANCELLED" => Ok(JournalStatus::Cancelled),
+            other => Err(p.error(format!("invalid STATUS {:?}", other))),
+        })
+        .transpose()?;
+
+    Ok(CalendarJournal {
+        uid,
+        summary: node.text("SUMMARY"),
+        descriptions: node
+            .all("DESCRIPTION")
+            .map(|p| unescape_text(&p.value))
+            .collect(),
+        start: node.date_time("DTSTART")?,
+        status,
+        categories: node
+            .all("CATEGORIES")
+            .flat_map(|p| split_text_list(&p.value))
+            .collect(),
+        created: node.timestamp("CREATED")?.unwrap_or(stamp),
+        last_modified: node.timestamp("LAST-MODIFIED")?.unwrap_or(stamp),
+        sequence: node.number("SEQUENCE")?.unwrap_or_default(),
+    })
+}
+
+fn read_availability(node: &Node) -> Result<Availability, IcsError> {
+    const COMPONENT: &str = "VAVAILABILITY";
+    let uid = unescape_text(&node.require(COMPONENT, "UID")?.value);
+    let stamp = node.timestamp("DTSTAMP")?.unwrap_or_default();
+    let busy_type = match node.get("BUSYTYPE") {
+        None => BusyType::BusyUnavailable,
+        Some(p) => match p.value.trim().to_ascii_uppercase().as_str() {
+            "BUSY" => BusyType::Busy,
+            "BUSY-UNAVAILABLE" => BusyType::BusyUnavailable,
+            "BUSY-TENTATIVE" => BusyType::BusyTentative,
+            other => return Err(p.error(format!("invalid BUSYTYPE {:?}", other))),
+        },
+    };
+    let start = node.date_time("DTSTART")?;
+    let end = match (&start, node.get("DURATION")) {
+        (Some(start), Some(duration)) if node.get("DTEND").is_none() => {
+            Some(shift(start, parse_duration(duration)?))
+        }
+        _ => node.date_time("DTEND")?,
+    };
+
+    let available = node
+        .children
+        .iter()
+        .filter(|child| child.name == "AVAILABLE")
+        .map(|child| read_available(child).map(Bond::new))
+        .collect::<Result<_, _>>()?;
+
+    Ok(Availability {
+        uid,
+        summary: node.text("SUMMARY"),
+        busy_type,
+        start,
+        end,
+        priority: node.number("PRIORITY")?.unwrap_or_default(),
+        available,
+        created: node.timestamp("CREATED")?.unwrap_or(stamp),
+        last_modified: node.timestamp("LAST-MODIFIED")?.unwrap_or(stamp),
+        sequence: node.number("SEQUENCE")?.unwrap_or_default(),
+    })
+}
+
+fn read_available(node: &Node) -> Result<AvailableSlot, IcsError> {
+    const COMPONENT: &str = "AVAILABLE";
+    let start = parse_date_time(node.require(COMPONENT, "DTSTART")?)?;
+    let end = match (node.get("DTEND"), node.get("DURATION")) {
+        (Some(end), _) => parse_date_time(end)?,
+        (None, Some(duration)) => shift(&start, parse_duration(duration)?),
+        (None, None) => {
+            return Err(IcsError::MissingProperty {
+                component: COMPONENT,
+                property: "DTEND",
+            })
+        }
+    };
+    let recurrence_rule = node.get("RRULE").map(parse_rrule).transpose()?;
+
+    Ok(AvailableSlot {
+        uid: unescape_text(&node.require(COMPONENT, "UID")?.value),
+        summary: node.text("SUMMARY"),
+        start,
+        end,
+        recurrence_rule: recurrence_rule.map(Bond::new),
+    })
+}
+
+/// Moves a start time by a duration, keeping its form and timezone.
+fn shift(start: &DateTimeValue, seconds: i64) -> DateTimeValue {
+    let timestamp = start.timestamp() + seconds;
+    match start {
+        DateTimeValue::Date(_) => DateTimeValue::Date(DateValue::from_timestamp(timestamp)),
+        DateTimeValue::DateTime(datetime) => DateTimeValue::DateTime(DateTime {
+            utc_timestamp: timestamp,
+            timezone: datetime.timezone.clone(),
+        }),
+    }
+}
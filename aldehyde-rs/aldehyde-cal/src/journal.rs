@@ -0,0 +1,27 @@
+use polyepoxide_core::oxide;
+
+use crate::time::DateTimeValue;
+
+pub type JournalUid = String;
+
+/// Journal status (STATUS)
+#[oxide]
+pub enum JournalStatus {
+    Draft,
+    Final,
+    Cancelled,
+}
+
+/// Calendar journal entry (VJOURNAL)
+#[oxide]
+pub struct CalendarJournal {
+    pub uid: JournalUid,
+    pub summary: Option<String>,
+    pub descriptions: Vec<String>,
+    pub start: Option<DateTimeValue>,
+    pub status: Option<JournalStatus>,
+    pub categories: Vec<String>,
+    pub created: i64,
+    pub last_modified: i64,
+    pub sequence: u32,
+}
@@ -2,18 +2,28 @@
 
 pub mod alarm;
 pub mod attendee;
+pub mod availability;
 pub mod calendar;
 pub mod event;
 pub mod freebusy;
+pub mod ics;
+pub mod journal;
 pub mod recurrence;
 pub mod time;
 pub mod todo;
+#[cfg(feature = "tz")]
+pub mod tz;
 
 pub use alarm::{Alarm, AlarmAction, AlarmTrigger};
 pub use attendee::{Attendee, AttendeeRole, CalendarUserType, Organizer, ParticipationStatus};
+pub use availability::{availability_busy_periods, Availability, AvailableSlot};
 pub use calendar::Calendar;
 pub use event::{CalendarEvent, EventUid};
-pub use freebusy::{BusyPeriod, BusyType, FreeBusy};
+pub use freebusy::{BusyPeriod, BusyType, Conflict, FreeBusy};
+pub use ics::{has_unread_event_parts, parse_ics, write_calendar, IcsComponent, IcsError};
+pub use journal::{CalendarJournal, JournalStatus, JournalUid};
 pub use recurrence::{Frequency, RecurrenceRule, Weekday};
 pub use time::{DateTime, DateTimeValue, DateValue, Duration, TimezoneId};
 pub use todo::{CalendarTodo, TodoStatus, TodoUid};
+#[cfg(feature = "tz")]
+pub use tz::{parse_timezone, UnknownTimezone};
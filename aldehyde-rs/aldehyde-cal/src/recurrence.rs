@@ -1,5 +1,11 @@
 use polyepoxide_core::oxide;
 
+use crate::time::{
+    civil_from_days, days_from_civil, days_in_month, DateTimeValue, SECONDS_PER_DAY,
+};
+#[cfg(feature = "tz")]
+use crate::tz::{local_from_utc, parse_timezone, utc_from_local};
+
 /// Recurrence frequency
 #[oxide]
 pub enum Frequency {
@@ -32,3 +38,226 @@ pub struct RecurrenceRule {
     pub by_month_day: Vec<i8>,
     pub by_month: Vec<u8>,
 }
+
+/// Upper bound on periods (days, weeks, ...) examined while expanding a rule,
+/// so rules that can never match (e.g. day 31 in February) terminate.
+const MAX_PERIODS: i64 = 100_000;
+
+impl Weekday {
+    const ALL: [Weekday; 7] = [
+        Weekday::Monday,
+        Weekday::Tuesday,
+        Weekday::Wednesday,
+        Weekday::Thursday,
+        Weekday::Friday,
+        Weekday::Saturday,
+        Weekday::Sunday,
+    ];
+
+    /// Days after Monday.
+    pub fn index(&self) -> i64 {
+        match self {
+            Weekday::Monday => 0,
+            Weekday::Tuesday => 1,
+            Weekday::Wednesday => 2,
+            Weekday::Thursday => 3,
+            Weekday::Friday => 4,
+            Weekday::Saturday => 5,
+            Weekday::Sunday => 6,
+        }
+    }
+
+    /// Weekday of a day counted from 1970-01-01, which was a Thursday.
+    pub fn of_day(days: i64) -> Self {
+        Self::ALL[(days + 3).rem_euclid(7) as usize].clone()
+    }
+}
+
+impl RecurrenceRule {
+    /// Start times of the occurrences of a series beginning at `start` that
+    /// fall within `[range_start, range_end)`.
+    ///
+    /// `start` is always the first occurrence. BYDAY, BYMONTHDAY and BYMONTH
+    /// expand or filter candidates as in RFC 5545 for the common cases;
+    /// ordinal weekdays ("last Friday") and BYSETPOS aren't supported. Dates
+    /// are computed in UTC; `occurrences_in` (`tz` feature) computes them in
+    /// a timezone.
+    pub fn occurrences(&self, start: i64, range_start: i64, range_end: i64) -> Vec<i64> {
+        let interval = self.interval.max(1) as i64;
+        let time_of_day = start.rem_euclid(SECONDS_PER_DAY);
+        let start_day = start.div_euclid(SECONDS_PER_DAY);
+        let (start_year, start_month, start_dom) = civil_from_days(start_day);
+
+        let mut found = Vec::new();
+        let mut emitted = 0u32;
+        for period in 0..MAX_PERIODS {
+            let days = match self.frequency {
+                Frequency::Daily => vec![start_day + period * interval],
+                Frequency::Weekly => {
+                    let week =
+                        start_day - Weekday::of_day(start_day).index() + period * 7 * interval;
+                    if self.by_day.is_empty() {
+                        vec![week + Weekday::of_day(start_day).index()]
+                    } else {
+                        let mut days: Vec<i64> =
+                            self.by_day.iter().map(|d| week + d.index()).collect();
+                        days.sort_unstable();
+                        days
+                    }
+                }
+                Frequency::Monthly => {
+                    let months = start_month as i64 - 1 + period * interval;
+                    let year = start_year + months.div_euclid(12) as i32;
+                    let month = months.rem_euclid(12) as u8 + 1;
+                    self.days_in(year, month, start_dom)
+                }
+                Frequency::Yearly => {
+                    let year = start_year + (period * interval) as i32;
+                    let months = if self.by_month.is_empty() {
+                        vec![start_month]
+                    } else {
+                        let mut months = self.by_month.clone();
+                        months.sort_unstable();
+                        months
+                    };
+                    months
+                        .into_iter()
+                        .filter(|m| (1..=12).contains(m))
+                        .flat_map(|month| self.days_in(year, month, start_dom))
+                        .collect()
+                }
+            };
+
+            for day in days {
+                if !self.matches(day) {
+                    continue;
+                }
+                let time = day * SECONDS_PER_DAY + time_of_day;
+                if time < start {
+                    continue;
+                }
+                if self.until.is_some_and(|until| time > until) || time >= range_end {
+                    return found;
+                }
+                if time >= range_start {
+                    found.push(time);
+                }
+                emitted += 1;
+                if self.count.is_some_and(|count| emitted >= count) {
+                    return found;
+                }
+            }
+        }
+        found
+    }
+
+    /// Like [`occurrences`](Self::occurrences), but with the wall-clock time
+    /// of `start` kept in `timezone` across daylight saving changes.
+    #[cfg(feature = "tz")]
+    pub fn occurrences_in(
+        &self,
+        start: i64,
+        timezone: chrono_tz::Tz,
+        range_start: i64,
+        range_end: i64,
+    ) -> Vec<i64> {
+        let local = |utc: i64| local_from_utc(timezone, utc);
+        let mut rule = self.clone();
+        rule.until = self.until.map(local);
+        // Offsets are less than a day, so the widened range covers every
+        // occurrence within the original one
+        rule.occurrences(
+            local(start),
+            range_start.saturating_sub(SECONDS_PER_DAY),
+            range_end.saturating_add(SECONDS_PER_DAY),
+        )
+        .into_iter()
+        .map(|time| utc_from_local(timezone, time))
+        .filter(|time| (range_start..range_end).contains(time))
+        .collect()
+    }
+
+    /// Expands a series beginning at `start`. With the `tz` feature, times in
+    /// a known timezone recur at the same local time; otherwise at the same
+    /// time of day in UTC.
+    pub(crate) fn occurrences_from(
+        &self,
+        start: &DateTimeValue,
+        range_start: i64,
+        range_end: i64,
+    ) -> Vec<i64> {
+        #[cfg(feature = "tz")]
+        if let DateTimeValue::DateTime(datetime) = start {
+            if let Ok(timezone) = parse_timezone(&datetime.timezone) {
+                return self.occurrences_in(
+                    datetime.utc_timestamp,
+                    timezone,
+                    range_start,
+                    range_end,
+                );
+            }
+        }
+        self.occurrences(start.timestamp(), range_start, range_end)
+    }
+
+    /// Candidate days of a month, for monthly and yearly rules.
+    fn days_in(&self, year: i32, month: u8, start_dom: u8) -> Vec<i64> {
+        let first = days_from_civil(year, month, 1);
+        let length = days_in_month(year, month) as i64;
+        let mut days: Vec<i64> = if !self.by_month_day.is_empty() {
+            self.by_month_day
+                .iter()
+                .map(|&d| {
+                    if d < 0 {
+                        length + d as i64 + 1
+                    } else {
+                        d as i64
+                    }
+                })
+                .filter(|d| (1..=length).contains(d))
+                .map(|d| first + d - 1)
+                .collect()
+        } else if !self.by_day.is_empty() {
+            (first..first + length)
+                .filter(|&day| self.on_by_day(day))
+                .collect()
+        } else if start_dom as i64 <= length {
+            vec![first + start_dom as i64 - 1]
+        } else {
+            Vec::new()
+        };
+        days.sort_unstable();
+        days.dedup();
+        days
+    }
+
+    /// Applies the BY* parts that limit rather than expand this frequency.
+    fn matches(&self, day: i64) -> bool {
+        let (year, month, dom) = civil_from_days(day);
+        if !self.by_month.is_empty() && !self.by_month.contains(&month) {
+            return false;
+        }
+        match self.frequency {
+            Frequency::Daily => {
+                let length = days_in_month(year, month) as i8;
+                (self.by_day.is_empty() || self.on_by_day(day))
+                    && (self.by_month_day.is_empty()
+                        || self
+                            .by_month_day
+                            .iter()
+                            .any(|&d| d == dom as i8 || (d < 0 && length + d + 1 == dom as i8)))
+            }
+            // Monthly and yearly rules with both BYMONTHDAY and BYDAY keep
+            // the month days that fall on one of the weekdays
+            Frequency::Monthly | Frequency::Yearly => {
+                self.by_month_day.is_empty() || self.by_day.is_empty() || self.on_by_day(day)
+            }
+            Frequency::Weekly => true,
+        }
+    }
+
+    fn on_by_day(&self, day: i64) -> bool {
+        let weekday = Weekday::of_day(day).index();
+        self.by_day.iter().any(|d| d.index() == weekday)
+    }
+}
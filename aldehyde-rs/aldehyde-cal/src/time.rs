@@ -31,3 +31,73 @@ pub enum DateTimeValue {
     Date(DateValue),
     DateTime(DateTime),
 }
+
+pub(crate) const SECONDS_PER_DAY: i64 = 86_400;
+
+impl DateValue {
+    /// Midnight UTC at the start of this date, as a Unix timestamp.
+    pub fn to_timestamp(&self) -> i64 {
+        days_from_civil(self.year, self.month, self.day) * SECONDS_PER_DAY
+    }
+
+    /// The UTC date containing a Unix timestamp.
+    pub fn from_timestamp(timestamp: i64) -> Self {
+        let (year, month, day) = civil_from_days(timestamp.div_euclid(SECONDS_PER_DAY));
+        Self { year, month, day }
+    }
+}
+
+impl DateTimeValue {
+    /// Start instant as a Unix timestamp. Dates are taken at midnight UTC.
+    pub fn timestamp(&self) -> i64 {
+        match self {
+            DateTimeValue::Date(date) => date.to_timestamp(),
+            DateTimeValue::DateTime(datetime) => datetime.utc_timestamp,
+        }
+    }
+}
+
+impl Duration {
+    /// Signed length in seconds.
+    pub fn as_seconds(&self) -> i64 {
+        if self.negative {
+            -self.seconds
+        } else {
+            self.seconds
+        }
+    }
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date.
+pub(crate) fn days_from_civil(year: i32, month: u8, day: u8) -> i64 {
+    let y = year as i64 - (month <= 2) as i64;
+    let m = month as i64;
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * (m + if m > 2 { -3 } else { 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Inverse of `days_from_civil`.
+pub(crate) fn civil_from_days(days: i64) -> (i32, u8, u8) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u8;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u8;
+    let year = (yoe + era * 400 + (month <= 2) as i64) as i32;
+    (year, month, day)
+}
+
+pub(crate) fn days_in_month(year: i32, month: u8) -> u8 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        _ if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        _ => 28,
+    }
+}
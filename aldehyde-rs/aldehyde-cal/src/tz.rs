@@ -0,0 +1,75 @@
+//! IANA timezones: validating timezone ids and converting between local
+//! and UTC times.
+//!
+//! Local times are counted like Unix timestamps, in seconds since
+//! 1970-01-01 00:00 on the wall clock.
+
+use chrono::{LocalResult, Offset, TimeZone};
+use chrono_tz::Tz;
+
+use crate::time::{DateTime, SECONDS_PER_DAY};
+
+/// Timezone id not found in the IANA database.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("unknown timezone {0}")]
+pub struct UnknownTimezone(pub String);
+
+/// Looks up a timezone id such as "Europe/Warsaw".
+pub fn parse_timezone(id: &str) -> Result<Tz, UnknownTimezone> {
+    id.parse().map_err(|_| UnknownTimezone(id.to_string()))
+}
+
+impl DateTime {
+    /// Creates a date-time, checking the timezone id.
+    pub fn new(utc_timestamp: i64, timezone: &str) -> Result<Self, UnknownTimezone> {
+        parse_timezone(timezone)?;
+        Ok(Self {
+            utc_timestamp,
+            timezone: timezone.to_string(),
+        })
+    }
+
+    /// Creates a date-time from a local time in `timezone`, as RFC 5545
+    /// resolves them: an ambiguous time is the first of the two, and a time
+    /// skipped by a clock change is taken with the offset before it.
+    pub fn from_local(local: i64, timezone: &str) -> Result<Self, UnknownTimezone> {
+        let tz = parse_timezone(timezone)?;
+        Ok(Self {
+            utc_timestamp: utc_from_local(tz, local),
+            timezone: timezone.to_string(),
+        })
+    }
+
+    /// Returns the time on the wall clock of the timezone.
+    pub fn local_timestamp(&self) -> Result<i64, UnknownTimezone> {
+        let tz = parse_timezone(&self.timezone)?;
+        Ok(local_from_utc(tz, self.utc_timestamp))
+    }
+}
+
+/// Offsets of times chrono can't represent are taken as zero.
+fn offset_at(tz: Tz, utc: i64) -> i64 {
+    chrono::DateTime::from_timestamp(utc, 0).map_or(0, |time| {
+        let offset = tz.offset_from_utc_datetime(&time.naive_utc());
+        offset.fix().local_minus_utc().into()
+    })
+}
+
+pub(crate) fn local_from_utc(tz: Tz, utc: i64) -> i64 {
+    utc.saturating_add(offset_at(tz, utc))
+}
+
+pub(crate) fn utc_from_local(tz: Tz, local: i64) -> i64 {
+    let Some(time) = chrono::DateTime::from_timestamp(local, 0) else {
+        return local;
+    };
+    let offset = match tz.offset_from_local_datetime(&time.naive_utc()) {
+        LocalResult::Single(offset) | LocalResult::Ambiguous(offset, _) => {
+            offset.fix().local_minus_utc().into()
+        }
+        // Clocks never change twice within a day, so a day earlier the
+        // offset before the gap applies
+        LocalResult::None => offset_at(tz, local - SECONDS_PER_DAY),
+    };
+    local - offset
+}
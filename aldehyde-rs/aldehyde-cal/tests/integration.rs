@@ -1,7 +1,9 @@
 use aldehyde_cal::{
-    Alarm, AlarmAction, AlarmTrigger, Attendee, AttendeeRole, Calendar, CalendarEvent,
-    CalendarTodo, CalendarUserType, DateTime, DateTimeValue, DateValue, Duration, Frequency,
-    Organizer, ParticipationStatus, RecurrenceRule, TodoStatus, Weekday,
+    availability_busy_periods, has_unread_event_parts, parse_ics, write_calendar, Alarm,
+    AlarmAction, AlarmTrigger, Attendee, AttendeeRole, Availability, AvailableSlot, BusyType,
+    Calendar, CalendarEvent, CalendarJournal, CalendarTodo, CalendarUserType, DateTime,
+    DateTimeValue, DateValue, Duration, Frequency, IcsComponent, JournalStatus, Organizer,
+    ParticipationStatus, RecurrenceRule, TodoStatus, Weekday,
 };
 use polyepoxide_core::{Bond, Oxide, Solvent};
 
@@ -234,6 +236,8 @@ fn full_calendar() {
         description: Some("My personal calendar".to_string()),
         events: vec![Bond::from_cell(event_cell)],
         todos: vec![Bond::from_cell(todo_cell)],
+        journals: vec![],
+        availability: vec![],
         freebusy: None,
     };
 
@@ -271,3 +275,524 @@ fn serialization_roundtrip() {
     assert_eq!(restored.uid, event.uid);
     assert_eq!(restored.summary, event.summary);
 }
+
+// 2024-01-01T00:00:00Z, a Monday
+const MONDAY: i64 = 1704067200;
+const HOUR: i64 = 3600;
+const DAY: i64 = 24 * HOUR;
+
+fn utc(timestamp: i64) -> DateTimeValue {
+    DateTimeValue::DateTime(DateTime {
+        utc_timestamp: timestamp,
+        timezone: "UTC".to_string(),
+    })
+}
+
+fn working_hours() -> Availability {
+    let weekdays = RecurrenceRule {
+        frequency: Frequency::Weekly,
+        interval: 1,
+        count: None,
+        until: None,
+        by_day: vec![
+            Weekday::Monday,
+            Weekday::Tuesday,
+            Weekday::Wednesday,
+            Weekday::Thursday,
+            Weekday::Friday,
+        ],
+        by_month_day: vec![],
+        by_month: vec![],
+    };
+    Availability {
+        uid: "work".to_string(),
+        summary: Some("Office hours".to_string()),
+        busy_type: BusyType::BusyUnavailable,
+        start: Some(utc(MONDAY)),
+        end: None,
+        priority: 0,
+        available: vec![Bond::new(AvailableSlot {
+            uid: "work-slot".to_string(),
+            summary: None,
+            start: utc(MONDAY + 9 * HOUR),
+            end: utc(MONDAY + 17 * HOUR),
+            recurrence_rule: Some(Bond::new(weekdays)),
+        })],
+        created: MONDAY,
+        last_modified: MONDAY,
+        sequence: 0,
+    }
+}
+
+#[test]
+fn weekly_rule_occurrences() {
+    let rule = RecurrenceRule {
+        frequency: Frequency::Weekly,
+        interval: 1,
+        count: Some(4),
+        until: None,
+        by_day: vec![Weekday::Monday, Weekday::Friday],
+        by_month_day: vec![],
+        by_month: vec![],
+    };
+    let start = MONDAY + 9 * HOUR;
+
+    let all = rule.occurrences(start, 0, i64::MAX);
+    assert_eq!(
+        all,
+        vec![start, start + 4 * DAY, start + 7 * DAY, start + 11 * DAY]
+    );
+
+    let windowed = rule.occurrences(start, start + DAY, start + 8 * DAY);
+    assert_eq!(windowed, vec![start + 4 * DAY, start + 7 * DAY]);
+}
+
+#[test]
+fn monthly_rule_counts_from_month_end() {
+    let rule = RecurrenceRule {
+        frequency: Frequency::Monthly,
+        interval: 1,
+        count: Some(3),
+        until: None,
+        by_day: vec![],
+        by_month_day: vec![-1],
+        by_month: vec![],
+    };
+    let occurrences: Vec<DateValue> = rule
+        .occurrences(MONDAY, 0, i64::MAX)
+        .into_iter()
+        .map(DateValue::from_timestamp)
+        .collect();
+    let days: Vec<(u8, u8)> = occurrences.iter().map(|d| (d.month, d.day)).collect();
+    assert_eq!(days, vec![(1, 31), (2, 29), (3, 31)]);
+}
+
+#[test]
+fn availability_leaves_working_hours_free() {
+    let work = working_hours();
+    let periods = availability_busy_periods(&[&work], MONDAY, MONDAY + 2 * DAY);
+
+    let spans: Vec<(i64, i64)> = periods.iter().map(|p| (p.start, p.end)).collect();
+    assert_eq!(
+        spans,
+        vec![
+            (MONDAY, MONDAY + 9 * HOUR),
+            (MONDAY + 17 * HOUR, MONDAY + DAY + 9 * HOUR),
+            (MONDAY + DAY + 17 * HOUR, MONDAY + 2 * DAY),
+        ]
+    );
+    assert!(periods
+        .iter()
+        .all(|p| matches!(p.busy_type, BusyType::BusyUnavailable)));
+}
+
+#[test]
+fn higher_priority_availability_wins() {
+    let work = working_hours();
+    let lunch = Availability {
+        uid: "lunch".to_string(),
+        summary: None,
+        busy_type: BusyType::Busy,
+        start: Some(utc(MONDAY + DAY + 12 * HOUR)),
+        end: Some(utc(MONDAY + DAY + 13 * HOUR)),
+        priority: 1,
+        available: vec![],
+        created: MONDAY,
+        last_modified: MONDAY,
+        sequence: 0,
+    };
+
+    let periods =
+        availability_busy_periods(&[&work, &lunch], MONDAY + DAY, MONDAY + DAY + 17 * HOUR);
+
+    let spans: Vec<(i64, i64)> = periods.iter().map(|p| (p.start, p.end)).collect();
+    assert_eq!(
+        spans,
+        vec![
+            (MONDAY + DAY, MONDAY + DAY + 9 * HOUR),
+            (MONDAY + DAY + 12 * HOUR, MONDAY + DAY + 13 * HOUR),
+        ]
+    );
+    assert!(matches!(periods[1].busy_type, BusyType::Busy));
+}
+
+#[test]
+fn free_busy_applies_availability() {
+    let event = |uid: &str, from: i64, to: i64| CalendarEvent {
+        uid: uid.to_string(),
+        summary: uid.to_string(),
+        description: None,
+        location: None,
+        start: utc(MONDAY + from * HOUR),
+        end: Some(utc(MONDAY + to * HOUR)),
+        recurrence_rule: None,
+        recurrence_exceptions: vec![],
+        organizer: None,
+        attendees: vec![],
+        alarms: vec![],
+        created: MONDAY,
+        last_modified: MONDAY,
+        sequence: 0,
+    };
+    let calendar = Calendar {
+        name: "Work".to_string(),
+        description: None,
+        events: [
+            event("early", 8, 9),
+            event("design", 10, 11),
+            event("late call", 16, 18),
+        ]
+        .into_iter()
+        .map(Bond::new)
+        .collect(),
+        todos: vec![],
+        journals: vec![],
+        availability: vec![Bond::new(working_hours())],
+        freebusy: None,
+    };
+
+    // Events outside working hours cut into the BUSY-UNAVAILABLE blocks
+    let free_busy = calendar.free_busy(MONDAY, MONDAY + DAY);
+    let periods: Vec<(i64, i64, bool)> = free_busy
+        .periods
+        .iter()
+        .map(|p| {
+            let unavailable = matches!(p.busy_type, BusyType::BusyUnavailable);
+            (p.start, p.end, unavailable)
+        })
+        .collect();
+    assert_eq!(
+        periods,
+        vec![
+            (MONDAY, MONDAY + 8 * HOUR, true),
+            (MONDAY + 8 * HOUR, MONDAY + 9 * HOUR, false),
+            (MONDAY + 10 * HOUR, MONDAY + 11 * HOUR, false),
+            (MONDAY + 16 * HOUR, MONDAY + 18 * HOUR, false),
+            (MONDAY + 18 * HOUR, MONDAY + DAY, true),
+        ]
+    );
+}
+
+#[test]
+fn free_busy_and_conflicts_expand_recurrences() {
+    let event = |uid: &str, start: DateTimeValue, end: Option<DateTimeValue>| CalendarEvent {
+        uid: uid.to_string(),
+        summary: uid.to_string(),
+        description: None,
+        location: None,
+        start,
+        end,
+        recurrence_rule: None,
+        recurrence_exceptions: vec![],
+        organizer: None,
+        attendees: vec![],
+        alarms: vec![],
+        created: MONDAY,
+        last_modified: MONDAY,
+        sequence: 0,
+    };
+    let mut standup = event(
+        "standup",
+        utc(MONDAY + 9 * HOUR),
+        Some(utc(MONDAY + 9 * HOUR + 1800)),
+    );
+    standup.recurrence_rule = Some(Bond::new(RecurrenceRule {
+        frequency: Frequency::Daily,
+        interval: 1,
+        count: None,
+        until: None,
+        by_day: vec![],
+        by_month_day: vec![],
+        by_month: vec![],
+    }));
+    standup.recurrence_exceptions = vec![MONDAY + DAY + 9 * HOUR];
+    let events = [
+        standup,
+        event(
+            "review",
+            utc(MONDAY + 9 * HOUR + 900),
+            Some(utc(MONDAY + 10 * HOUR)),
+        ),
+        event(
+            "lunch",
+            utc(MONDAY + 12 * HOUR),
+            Some(utc(MONDAY + 13 * HOUR)),
+        ),
+        // No end: a date lasts the day, a date-time takes no time
+        event(
+            "offsite",
+            DateTimeValue::Date(DateValue::from_timestamp(MONDAY + 2 * DAY)),
+            None,
+        ),
+        event("reminder", utc(MONDAY + 12 * HOUR), None),
+    ];
+    let calendar = Calendar {
+        name: "Work".to_string(),
+        description: None,
+        events: events.into_iter().map(Bond::new).collect(),
+        todos: vec![],
+        journals: vec![],
+        availability: vec![],
+        freebusy: None,
+    };
+
+    let free_busy = calendar.free_busy(MONDAY, MONDAY + 3 * DAY);
+    let spans: Vec<(i64, i64)> = free_busy.periods.iter().map(|p| (p.start, p.end)).collect();
+    assert_eq!(
+        spans,
+        vec![
+            (MONDAY + 9 * HOUR, MONDAY + 10 * HOUR),
+            (MONDAY + 12 * HOUR, MONDAY + 13 * HOUR),
+            (MONDAY + 2 * DAY, MONDAY + 3 * DAY),
+        ]
+    );
+    assert_eq!((free_busy.start, free_busy.end), (MONDAY, MONDAY + 3 * DAY));
+
+    let conflicts = calendar.find_conflicts(MONDAY, MONDAY + 3 * DAY);
+    let conflicts: Vec<(&str, &str, i64, i64)> = conflicts
+        .iter()
+        .map(|c| (c.first.as_str(), c.second.as_str(), c.start, c.end))
+        .collect();
+    assert_eq!(
+        conflicts,
+        vec![
+            (
+                "standup",
+                "review",
+                MONDAY + 9 * HOUR + 900,
+                MONDAY + 9 * HOUR + 1800
+            ),
+            (
+                "offsite",
+                "standup",
+                MONDAY + 2 * DAY + 9 * HOUR,
+                MONDAY + 2 * DAY + 9 * HOUR + 1800
+            ),
+        ]
+    );
+}
+
+#[test]
+fn ics_roundtrip() {
+    let journal = CalendarJournal {
+        uid: "journal-001".to_string(),
+        summary: Some("Retro; notes, part 1".to_string()),
+        descriptions: vec![
+            "Went well:\nshipping".to_string(),
+            "A long entry that goes well past the seventy-five octet limit of a content line"
+                .to_string(),
+        ],
+        start: Some(DateTimeValue::Date(DateValue {
+            year: 2024,
+            month: 1,
+            day: 5,
+        })),
+        status: Some(JournalStatus::Final),
+        categories: vec!["work".to_string(), "a,b".to_string()],
+        created: MONDAY,
+        last_modified: MONDAY + HOUR,
+        sequence: 2,
+    };
+    let components = vec![
+        IcsComponent::Journal(journal),
+        IcsComponent::Availability(working_hours()),
+    ];
+
+    let text = write_calendar(&components).unwrap();
+    assert!(text.lines().all(|line| line.len() <= 76));
+    let parsed = parse_ics(&text).unwrap();
+    assert_eq!(parsed.len(), 2);
+
+    let IcsComponent::Journal(journal) = &parsed[0] else {
+        panic!("Expected journal");
+    };
+    assert_eq!(journal.summary.as_deref(), Some("Retro; notes, part 1"));
+    assert_eq!(journal.descriptions[0], "Went well:\nshipping");
+    assert!(journal.descriptions[1].ends_with("of a content line"));
+    assert_eq!(journal.categories, vec!["work", "a,b"]);
+    assert!(matches!(journal.status, Some(JournalStatus::Final)));
+    assert_eq!(journal.last_modified, MONDAY + HOUR);
+    assert_eq!(journal.sequence, 2);
+
+    let IcsComponent::Availability(availability) = &parsed[1] else {
+        panic!("Expected availability");
+    };
+    assert_eq!(availability.to_bytes(), working_hours().to_bytes());
+}
+
+#[test]
+fn event_ics_roundtrip() {
+    let event = CalendarEvent {
+        uid: "standup".to_string(),
+        summary: "Standup, daily".to_string(),
+        description: Some("Yesterday; today".to_string()),
+        location: Some("Room 1".to_string()),
+        start: utc(MONDAY + 9 * HOUR),
+        end: Some(utc(MONDAY + 9 * HOUR + 900)),
+        recurrence_rule: Some(Bond::new(RecurrenceRule {
+            frequency: Frequency::Weekly,
+            interval: 1,
+            count: None,
+            until: None,
+            by_day: vec![Weekday::Monday, Weekday::Wednesday],
+            by_month_day: vec![],
+            by_month: vec![],
+        })),
+        recurrence_exceptions: vec![MONDAY + 2 * DAY + 9 * HOUR],
+        organizer: Some(Bond::new(Organizer {
+            email: "ada@example.com".to_string(),
+            common_name: Some("Ada: Lovelace".to_string()),
+        })),
+        attendees: vec![Bond::new(Attendee {
+            email: "bob@example.com".to_string(),
+            common_name: None,
+            status: ParticipationStatus::Tentative,
+            role: AttendeeRole::OptParticipant,
+            user_type: CalendarUserType::Individual,
+            rsvp: true,
+        })],
+        alarms: vec![],
+        created: MONDAY,
+        last_modified: MONDAY + HOUR,
+        sequence: 3,
+    };
+
+    let text = write_calendar(&[IcsComponent::Event(event.clone())]).unwrap();
+    assert!(text.contains("ORGANIZER;CN=\"Ada: Lovelace\":mailto:ada@example.com\r\n"));
+    assert!(!has_unread_event_parts(&text).unwrap());
+    let parsed = parse_ics(&text).unwrap();
+    let IcsComponent::Event(read) = &parsed[0] else {
+        panic!("Expected event");
+    };
+    assert_eq!(read.to_bytes(), event.to_bytes());
+
+    // Occurrence overrides are skipped; EXDATE may list several dates
+    let text = "BEGIN:VCALENDAR\r\n\
+        BEGIN:VEVENT\r\n\
+        UID:holiday\r\n\
+        DTSTAMP:20240101T000000Z\r\n\
+        DTSTART;VALUE=DATE:20240101\r\n\
+        DURATION:P1D\r\n\
+        RRULE:FREQ=DAILY;COUNT=5\r\n\
+        EXDATE;VALUE=DATE:20240102,20240104\r\n\
+        END:VEVENT\r\n\
+        BEGIN:VEVENT\r\n\
+        UID:holiday\r\n\
+        RECURRENCE-ID;VALUE=DATE:20240103\r\n\
+        DTSTART;VALUE=DATE:20240103\r\n\
+        END:VEVENT\r\n\
+        END:VCALENDAR\r\n";
+    assert!(has_unread_event_parts(text).unwrap());
+    let parsed = parse_ics(text).unwrap();
+    assert_eq!(parsed.len(), 1);
+    let IcsComponent::Event(holiday) = &parsed[0] else {
+        panic!("Expected event");
+    };
+    assert_eq!(holiday.summary, "");
+    assert_eq!(
+        holiday.occurrences(MONDAY, MONDAY + 7 * DAY),
+        vec![
+            (MONDAY, MONDAY + DAY),
+            (MONDAY + 2 * DAY, MONDAY + 3 * DAY),
+            (MONDAY + 4 * DAY, MONDAY + 5 * DAY),
+        ]
+    );
+}
+
+#[test]
+fn parses_external_availability() {
+    let text = "BEGIN:VCALENDAR\r\n\
+        VERSION:2.0\r\n\
+        BEGIN:VAVAILABILITY\r\n\
+        UID:ext\r\n\
+        DTSTAMP:20240101T000000Z\r\n\
+        DTSTART;TZID=Europe/Warsaw:20240101T000000\r\n\
+        DURATION:P1W\r\n\
+        BEGIN:AVAILABLE\r\n\
+        UID:ext-slot\r\n\
+        DTSTART:20240101T090000Z\r\n\
+        DURATION:PT8H\r\n\
+        RRULE:FREQ=DAILY;CO\r\n UNT=5\r\n\
+        END:AVAILABLE\r\n\
+        END:VAVAILABILITY\r\n\
+        END:VCALENDAR\r\n";
+
+    let parsed = parse_ics(text).unwrap();
+    let IcsComponent::Availability(availability) = &parsed[0] else {
+        panic!("Expected availability");
+    };
+    assert!(matches!(availability.busy_type, BusyType::BusyUnavailable));
+    // Midnight in Warsaw is an hour before midnight UTC once TZIDs are resolved
+    let offset = if cfg!(feature = "tz") { HOUR } else { 0 };
+    assert_eq!(
+        availability.end.as_ref().unwrap().timestamp(),
+        MONDAY + 7 * DAY - offset
+    );
+
+    let slot = availability.available[0].value().unwrap();
+    assert_eq!(slot.end.timestamp(), MONDAY + 17 * HOUR);
+    let rule = slot.recurrence_rule.as_ref().unwrap().value().unwrap();
+    assert_eq!(rule.count, Some(5));
+}
+
+#[cfg(feature = "tz")]
+#[test]
+fn local_times_follow_daylight_saving() {
+    use aldehyde_cal::{parse_timezone, UnknownTimezone};
+
+    // Midnight UTC on 2024-03-25, 2024-03-31 and 2024-10-27; Warsaw moves
+    // from UTC+1 to UTC+2 on 03-31 at 02:00 and back on 10-27 at 03:00
+    const MARCH_25: i64 = 1711324800;
+    const MARCH_31: i64 = 1711843200;
+    const OCTOBER_27: i64 = 1729987200;
+
+    let skipped = DateTime::from_local(MARCH_31 + 2 * HOUR + 1800, "Europe/Warsaw").unwrap();
+    assert_eq!(skipped.utc_timestamp, MARCH_31 + HOUR + 1800);
+    let repeated = DateTime::from_local(OCTOBER_27 + 2 * HOUR + 1800, "Europe/Warsaw").unwrap();
+    assert_eq!(repeated.utc_timestamp, OCTOBER_27 + 1800);
+    assert_eq!(repeated.local_timestamp(), Ok(OCTOBER_27 + 2 * HOUR + 1800));
+    assert_eq!(
+        DateTime::new(0, "Mars/Olympus_Mons").unwrap_err(),
+        UnknownTimezone("Mars/Olympus_Mons".to_string())
+    );
+
+    // Weekly at 09:00 in Warsaw, across the change to summer time
+    let rule = RecurrenceRule {
+        frequency: Frequency::Weekly,
+        interval: 1,
+        count: Some(3),
+        until: None,
+        by_day: vec![],
+        by_month_day: vec![],
+        by_month: vec![],
+    };
+    let start = MARCH_25 + 8 * HOUR;
+    let warsaw = parse_timezone("Europe/Warsaw").unwrap();
+    assert_eq!(
+        rule.occurrences_in(start, warsaw, 0, i64::MAX),
+        vec![start, start + 7 * DAY - HOUR, start + 14 * DAY - HOUR]
+    );
+    let slot = AvailableSlot {
+        uid: "slot".to_string(),
+        summary: None,
+        start: DateTimeValue::DateTime(DateTime::new(start, "Europe/Warsaw").unwrap()),
+        end: utc(start + HOUR),
+        recurrence_rule: Some(Bond::new(rule)),
+    };
+    let availability = Availability {
+        available: vec![Bond::new(slot)],
+        start: Some(utc(MARCH_25)),
+        end: Some(utc(MARCH_25 + 14 * DAY)),
+        ..working_hours()
+    };
+    let periods =
+        availability_busy_periods(&[&availability], MARCH_25 + 7 * DAY, MARCH_25 + 8 * DAY);
+    let spans: Vec<(i64, i64)> = periods.iter().map(|p| (p.start, p.end)).collect();
+    assert_eq!(
+        spans,
+        vec![
+            (MARCH_25 + 7 * DAY, MARCH_25 + 7 * DAY + 7 * HOUR),
+            (MARCH_25 + 7 * DAY + 8 * HOUR, MARCH_25 + 8 * DAY),
+        ]
+    );
+}
@@ -0,0 +1,252 @@
+//! Requests to a CalDAV calendar collection.
+
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::header::{CONTENT_TYPE, ETAG, IF_MATCH, IF_NONE_MATCH};
+use reqwest::{Method, StatusCode, Url};
+
+use crate::multistatus::SyncReport;
+
+const SYNC_COLLECTION: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:sync-collection xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+  <d:sync-token>{token}</d:sync-token>
+  <d:sync-level>1</d:sync-level>
+  <d:prop>
+    <d:getetag/>
+    <c:calendar-data/>
+  </d:prop>
+</d:sync-collection>"#;
+
+/// Error from CalDAV requests.
+#[derive(Debug, thiserror::Error)]
+pub enum CalDavError {
+    #[error("request failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("server responded {status}: {message}")]
+    Status { status: StatusCode, message: String },
+    #[error("invalid multistatus response: {0}")]
+    Xml(String),
+    #[error("invalid URL {href}: {message}")]
+    Url { href: String, message: String },
+    #[error("{0} was changed on the server")]
+    PreconditionFailed(String),
+    #[error("the server no longer accepts the sync token")]
+    InvalidSyncToken,
+}
+
+/// The requests a sync makes to a calendar collection. Implemented by
+/// `CalDavClient`; tests put an in-memory collection in its place.
+pub trait CalendarCollection {
+    /// Returns the collection URL, ending with '/'.
+    fn collection(&self) -> &str;
+
+    /// Returns the href under which a new event with `uid` is stored.
+    fn resource_href(&self, uid: &str) -> Result<String, CalDavError>;
+
+    /// Reports changes since `token`, or every resource without one.
+    fn sync_collection(&self, token: Option<&str>) -> Result<SyncReport, CalDavError>;
+
+    /// Stores iCalendar text at `href`, replacing only the version with
+    /// `etag` or creating it if `etag` is unset, and returns the new ETag.
+    fn put(
+        &self,
+        href: &str,
+        ics: String,
+        etag: Option<&str>,
+    ) -> Result<Option<String>, CalDavError>;
+
+    /// Deletes the resource at `href`, only if it still has `etag` when
+    /// given.
+    fn delete(&self, href: &str, etag: Option<&str>) -> Result<(), CalDavError>;
+}
+
+/// A calendar collection on a CalDAV server.
+pub struct CalDavClient {
+    collection: String,
+    client: Client,
+    credentials: Option<(String, String)>,
+}
+
+impl CalDavClient {
+    /// Connects to the collection at `collection`, such as
+    /// `https://cloud.example.com/remote.php/dav/calendars/alice/personal/`.
+    pub fn new(collection: impl Into<String>) -> Self {
+        Self::with_client(collection, Client::new())
+    }
+
+    /// Connects with a preconfigured client, e.g. with timeouts or auth
+    /// headers.
+    pub fn with_client(collection: impl Into<String>, client: Client) -> Self {
+        let mut collection = collection.into();
+        // Hrefs of the collection's resources resolve against it as a directory
+        if !collection.ends_with('/') {
+            collection.push('/');
+        }
+        Self {
+            collection,
+            client,
+            credentials: None,
+        }
+    }
+
+    /// Authenticates every request with HTTP basic auth, as Nextcloud and
+    /// Fastmail app passwords are used.
+    pub fn with_basic_auth(mut self, user: impl Into<String>, password: impl Into<String>) -> Self {
+        self.credentials = Some((user.into(), password.into()));
+        self
+    }
+
+    /// Returns the collection URL, ending with '/'.
+    pub fn collection(&self) -> &str {
+        &self.collection
+    }
+
+    /// Resolves an href, usually an absolute path, against the collection.
+    pub fn resolve(&self, href: &str) -> Result<Url, CalDavError> {
+        Url::parse(&self.collection)
+            .and_then(|base| base.join(href))
+            .map_err(|e| CalDavError::Url {
+                href: href.to_string(),
+                message: e.to_string(),
+            })
+    }
+
+    /// Returns the href under which a new event is stored: its uid, with
+    /// characters that would change the path replaced, plus ".ics".
+    pub fn resource_href(&self, uid: &str) -> Result<String, CalDavError> {
+        let name: String = uid
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' | '@' => c,
+                _ => '-',
+            })
+            .collect();
+        Ok(self.resolve(&format!("{}.ics", name))?.path().to_string())
+    }
+
+    fn request(&self, method: Method, url: Url) -> RequestBuilder {
+        let request = self.client.request(method, url);
+        match &self.credentials {
+            Some((user, password)) => request.basic_auth(user, Some(password)),
+            None => request,
+        }
+    }
+
+    /// Reports changes since `token`, or every resource without one.
+    ///
+    /// Fails with `InvalidSyncToken` if the server has forgotten the token;
+    /// a sync without one starts over.
+    pub fn sync_collection(&self, token: Option<&str>) -> Result<SyncReport, CalDavError> {
+        let body = SYNC_COLLECTION.replace("{token}", &escape(token.unwrap_or("")));
+        let report = Method::from_bytes(b"REPORT").expect("REPORT is a valid method");
+        let response = self
+            .request(report, self.resolve(&self.collection)?)
+            .header("Depth", "0")
+            .header(CONTENT_TYPE, "application/xml; charset=utf-8")
+            .body(body)
+            .send()?;
+        let status = response.status();
+        if status == StatusCode::FORBIDDEN || status == StatusCode::CONFLICT {
+            let message = response.text().unwrap_or_default();
+            if message.contains("valid-sync-token") {
+                return Err(CalDavError::InvalidSyncToken);
+            }
+            return Err(CalDavError::Status { status, message });
+        }
+        SyncReport::parse(&success(response)?.text()?)
+    }
+
+    /// Stores iCalendar text at `href` and returns the new ETag if the
+    /// server sent one.
+    ///
+    /// With `etag` the resource is only replaced if it still has that ETag,
+    /// and without one it's only created if it doesn't exist; otherwise
+    /// this fails with `PreconditionFailed`.
+    pub fn put(
+        &self,
+        href: &str,
+        ics: String,
+        etag: Option<&str>,
+    ) -> Result<Option<String>, CalDavError> {
+        let request = self
+            .request(Method::PUT, self.resolve(href)?)
+            .header(CONTENT_TYPE, "text/calendar; charset=utf-8")
+            .body(ics);
+        let request = match etag {
+            Some(etag) => request.header(IF_MATCH, etag),
+            None => request.header(IF_NONE_MATCH, "*"),
+        };
+        let response = precondition(request.send()?, href)?;
+        let etag = response
+            .headers()
+            .get(ETAG)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        success(response)?;
+        Ok(etag)
+    }
+
+    /// Deletes the resource at `href`, only if it still has `etag` when
+    /// given. A resource that's already gone is not an error.
+    pub fn delete(&self, href: &str, etag: Option<&str>) -> Result<(), CalDavError> {
+        let mut request = self.request(Method::DELETE, self.resolve(href)?);
+        if let Some(etag) = etag {
+            request = request.header(IF_MATCH, etag);
+        }
+        let response = precondition(request.send()?, href)?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(());
+        }
+        success(response)?;
+        Ok(())
+    }
+}
+
+impl CalendarCollection for CalDavClient {
+    fn collection(&self) -> &str {
+        CalDavClient::collection(self)
+    }
+
+    fn resource_href(&self, uid: &str) -> Result<String, CalDavError> {
+        CalDavClient::resource_href(self, uid)
+    }
+
+    fn sync_collection(&self, token: Option<&str>) -> Result<SyncReport, CalDavError> {
+        CalDavClient::sync_collection(self, token)
+    }
+
+    fn put(
+        &self,
+        href: &str,
+        ics: String,
+        etag: Option<&str>,
+    ) -> Result<Option<String>, CalDavError> {
+        CalDavClient::put(self, href, ics, etag)
+    }
+
+    fn delete(&self, href: &str, etag: Option<&str>) -> Result<(), CalDavError> {
+        CalDavClient::delete(self, href, etag)
+    }
+}
+
+fn precondition(response: Response, href: &str) -> Result<Response, CalDavError> {
+    match response.status() {
+        StatusCode::PRECONDITION_FAILED => Err(CalDavError::PreconditionFailed(href.to_string())),
+        _ => Ok(response),
+    }
+}
+
+/// Turns responses other than 2xx into errors carrying the body's text.
+fn success(response: Response) -> Result<Response, CalDavError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let message = response.text().unwrap_or_default();
+    Err(CalDavError::Status { status, message })
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
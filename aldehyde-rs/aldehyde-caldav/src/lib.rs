@@ -0,0 +1,12 @@
+//! Aldehyde CalDAV - syncing aldehyde calendars with CalDAV servers
+//! (RFC 4791) using collection synchronization (RFC 6578)
+
+pub mod client;
+pub mod multistatus;
+pub mod state;
+pub mod sync;
+
+pub use client::{CalDavClient, CalDavError, CalendarCollection};
+pub use multistatus::{RemoteObject, SyncReport};
+pub use state::{SyncState, SyncedResource};
+pub use sync::{CalDavSync, SyncError, SyncSummary};
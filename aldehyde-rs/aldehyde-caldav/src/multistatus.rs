@@ -0,0 +1,97 @@
+//! Reading WebDAV multistatus responses to sync-collection reports.
+
+use roxmltree::{Document, Node};
+
+use crate::client::CalDavError;
+
+const DAV: &str = "DAV:";
+const CALDAV: &str = "urn:ietf:params:xml:ns:caldav";
+
+/// A calendar object resource as reported by the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteObject {
+    pub href: String,
+    pub etag: String,
+    pub calendar_data: String,
+}
+
+/// Changes to a collection since a sync token, or all of its resources when
+/// synced without one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncReport {
+    /// Token to pass to the next sync.
+    pub sync_token: String,
+    pub changed: Vec<RemoteObject>,
+    /// Hrefs of resources deleted since the token.
+    pub removed: Vec<String>,
+}
+
+impl SyncReport {
+    /// Parses a multistatus response body.
+    ///
+    /// Responses without calendar data, such as the one for the collection
+    /// itself, are skipped.
+    pub fn parse(xml: &str) -> Result<Self, CalDavError> {
+        let document = Document::parse(xml).map_err(|e| CalDavError::Xml(e.to_string()))?;
+        let root = document.root_element();
+        if !root.has_tag_name((DAV, "multistatus")) {
+            return Err(CalDavError::Xml(format!(
+                "expected multistatus, found {}",
+                root.tag_name().name()
+            )));
+        }
+        let sync_token = child(root, DAV, "sync-token")
+            .and_then(|node| node.text())
+            .ok_or_else(|| CalDavError::Xml("multistatus has no sync-token".to_string()))?;
+
+        let mut changed = Vec::new();
+        let mut removed = Vec::new();
+        for response in root
+            .children()
+            .filter(|n| n.has_tag_name((DAV, "response")))
+        {
+            let Some(href) = child(response, DAV, "href").and_then(|n| n.text()) else {
+                continue;
+            };
+            let href = href.trim().to_string();
+            // A status directly in the response, rather than in a propstat,
+            // reports the resource as a whole
+            if child(response, DAV, "status").is_some_and(|n| status_code(n) == Some(404)) {
+                removed.push(href);
+                continue;
+            }
+            let Some(prop) = response
+                .children()
+                .filter(|n| n.has_tag_name((DAV, "propstat")))
+                .filter(|n| child(*n, DAV, "status").and_then(status_code) == Some(200))
+                .find_map(|n| child(n, DAV, "prop"))
+            else {
+                continue;
+            };
+            let etag = child(prop, DAV, "getetag").and_then(|n| n.text());
+            let data = child(prop, CALDAV, "calendar-data").and_then(|n| n.text());
+            if let (Some(etag), Some(data)) = (etag, data) {
+                changed.push(RemoteObject {
+                    href,
+                    etag: etag.trim().to_string(),
+                    calendar_data: data.to_string(),
+                });
+            }
+        }
+
+        Ok(Self {
+            sync_token: sync_token.trim().to_string(),
+            changed,
+            removed,
+        })
+    }
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, ns: &str, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.has_tag_name((ns, name)))
+}
+
+/// Reads the code of a status line such as "HTTP/1.1 404 Not Found".
+fn status_code(node: Node) -> Option<u16> {
+    node.text()?.split_whitespace().nth(1)?.parse().ok()
+}
@@ -0,0 +1,26 @@
+use aldehyde_cal::{CalendarEvent, EventUid};
+use polyepoxide_core::{oxide, Bond};
+
+/// A calendar object resource as of the last sync
+#[oxide]
+pub struct SyncedResource {
+    pub href: String,
+    /// Unset if the server didn't return one for our last write
+    pub etag: Option<String>,
+    pub uid: EventUid,
+    /// The event as last read from or written to the server; a calendar
+    /// event with another CID has been changed locally since
+    pub event: Bond<CalendarEvent>,
+    /// The server's version has alarms or overrides of single occurrences,
+    /// which aren't read and would be lost by pushing the event
+    pub unread_parts: bool,
+}
+
+/// What a calendar was last synced to
+#[oxide]
+pub struct SyncState {
+    /// URL of the CalDAV collection
+    pub collection: String,
+    pub sync_token: Option<String>,
+    pub resources: Vec<SyncedResource>,
+}
@@ -0,0 +1,353 @@
+//! Two-way sync between a calendar kept in a ref and a CalDAV collection.
+//!
+//! A sync pulls the changes the server reports since the last sync token,
+//! then pushes the events created, changed or removed locally since the last
+//! sync. An event changed on both sides keeps the server's version. Only
+//! events are synced; todos, journals and availability stay local.
+//!
+//! Alarms and overrides of single occurrences (RECURRENCE-ID) aren't read.
+//! Pulled events keep their local alarms, and local changes to an event
+//! whose server version has either aren't pushed, so they're never
+//! overwritten.
+//!
+//! The sync token and what each resource held are kept in a second ref, so
+//! a sync that fails part way is picked up by the next one.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use aldehyde_cal::{
+    has_unread_event_parts, parse_ics, write_calendar, Calendar, CalendarEvent, IcsComponent,
+    IcsError,
+};
+use polyepoxide_core::{Bond, Cell, Cid, LoadError, Oxide, PersistError, RefStore, Solvent};
+
+use crate::client::{CalDavClient, CalDavError, CalendarCollection};
+use crate::state::{SyncState, SyncedResource};
+
+/// Levels loaded below the calendar: events and their rules, organizers,
+/// attendees and alarms.
+const LOAD_DEPTH: usize = 2;
+
+/// Error from syncing a calendar.
+#[derive(Debug, thiserror::Error)]
+pub enum SyncError<E> {
+    #[error(transparent)]
+    CalDav(#[from] CalDavError),
+    #[error("invalid calendar data at {href}")]
+    Ics {
+        href: String,
+        #[source]
+        source: IcsError,
+    },
+    #[error("ref {0:?} was moved by another writer")]
+    Conflict(String),
+    #[error(transparent)]
+    Load(#[from] LoadError<E>),
+    #[error(transparent)]
+    Persist(#[from] PersistError<E>),
+    #[error("store error: {0}")]
+    Store(E),
+}
+
+/// What a sync changed, in events.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SyncSummary {
+    /// Created or replaced locally from the server
+    pub pulled: usize,
+    /// Removed locally after being deleted on the server
+    pub removed: usize,
+    /// Created or replaced on the server
+    pub pushed: usize,
+    /// Deleted on the server after being removed locally
+    pub deleted: usize,
+    /// Local changes given up for the server's version, or not pushed
+    /// because the server's version changed during the sync
+    pub conflicts: usize,
+    /// Local changes not pushed because they would overwrite alarms or
+    /// overrides of single occurrences on the server
+    pub held_back: usize,
+}
+
+/// A calendar whose root is kept in a ref of `store`, synced with a CalDAV
+/// collection.
+pub struct CalDavSync<S, C = CalDavClient> {
+    store: S,
+    solvent: Solvent,
+    client: C,
+    calendar_ref: String,
+    state_ref: String,
+    calendar: Arc<Cell<Calendar>>,
+    /// What the refs pointed to when last read or moved.
+    calendar_head: Option<Cid>,
+    state_head: Option<Cid>,
+    state: SyncState,
+}
+
+impl<S: RefStore, C: CalendarCollection> CalDavSync<S, C> {
+    /// Opens the calendar `calendar_ref` points to, or an empty one named
+    /// after the collection if it's unset. `state_ref` keeps the sync state;
+    /// a state recorded for another collection is ignored.
+    pub fn open(
+        store: S,
+        client: C,
+        calendar_ref: impl Into<String>,
+        state_ref: impl Into<String>,
+    ) -> Result<Self, SyncError<S::Error>> {
+        let calendar_ref = calendar_ref.into();
+        let state_ref = state_ref.into();
+        let mut solvent = Solvent::new();
+
+        let calendar_head = store.get_ref(&calendar_ref).map_err(SyncError::Store)?;
+        let calendar = match &calendar_head {
+            Some(cid) => solvent.load(cid, &store, LOAD_DEPTH)?,
+            None => solvent.add(Calendar {
+                name: client.collection().to_string(),
+                description: None,
+                events: Vec::new(),
+                todos: Vec::new(),
+                journals: Vec::new(),
+                availability: Vec::new(),
+                freebusy: None,
+            }),
+        };
+
+        let state_head = store.get_ref(&state_ref).map_err(SyncError::Store)?;
+        let state = match &state_head {
+            Some(cid) => {
+                let cell: Arc<Cell<SyncState>> = solvent.load(cid, &store, 0)?;
+                Some(cell.value().clone())
+            }
+            None => None,
+        };
+        let state = state
+            .filter(|state| state.collection == client.collection())
+            .unwrap_or_else(|| SyncState {
+                collection: client.collection().to_string(),
+                sync_token: None,
+                resources: Vec::new(),
+            });
+
+        Ok(Self {
+            store,
+            solvent,
+            client,
+            calendar_ref,
+            state_ref,
+            calendar,
+            calendar_head,
+            state_head,
+            state,
+        })
+    }
+
+    /// Returns the current calendar.
+    pub fn calendar(&self) -> &Calendar {
+        self.calendar.value()
+    }
+
+    /// Returns the state recorded by the last sync.
+    pub fn state(&self) -> &SyncState {
+        &self.state
+    }
+
+    /// Pulls remote changes, pushes local ones and records the new sync
+    /// token. The calendar ref only moves if events changed.
+    pub fn sync(&mut self) -> Result<SyncSummary, SyncError<S::Error>> {
+        let mut summary = SyncSummary::default();
+        let (report, full) = match self
+            .client
+            .sync_collection(self.state.sync_token.as_deref())
+        {
+            Err(CalDavError::InvalidSyncToken) => (self.client.sync_collection(None)?, true),
+            report => (report?, self.state.sync_token.is_none()),
+        };
+        let mut events = self.calendar().events.clone();
+        let mut resources: BTreeMap<String, SyncedResource> = self
+            .state
+            .resources
+            .iter()
+            .map(|resource| (resource.href.clone(), resource.clone()))
+            .collect();
+
+        let mut removed = report.removed.clone();
+        // A full report lists every resource, so the ones missing are gone
+        if full {
+            removed.extend(
+                resources
+                    .keys()
+                    .filter(|href| !report.changed.iter().any(|o| o.href == **href))
+                    .cloned(),
+            );
+        }
+        for href in removed {
+            let Some(resource) = resources.remove(&href) else {
+                continue;
+            };
+            if let Some(i) = position(&events, &resource.uid) {
+                if events[i].cid() != resource.event.cid() {
+                    summary.conflicts += 1;
+                }
+                events.remove(i);
+                summary.removed += 1;
+            }
+        }
+
+        for object in report.changed {
+            let recorded = resources.get(&object.href);
+            if recorded.is_some_and(|r| r.etag.as_deref() == Some(object.etag.as_str())) {
+                continue;
+            }
+            let event = read_event(&object.calendar_data).map_err(|source| SyncError::Ics {
+                href: object.href.clone(),
+                source,
+            })?;
+            let Some((mut event, unread_parts)) = event else {
+                continue;
+            };
+            let uid = event.uid.clone();
+            let local = position(&events, &uid);
+            // Alarms aren't read, so the ones set locally stay
+            if let Some(current) = local.and_then(|i| events[i].value()) {
+                event.alarms = current.alarms.clone();
+            }
+            let bond = self.solvent.bond(event);
+            match local {
+                Some(i) => {
+                    let changed_locally = recorded.is_none_or(|r| r.event.cid() != events[i].cid());
+                    if changed_locally && events[i].cid() != bond.cid() {
+                        summary.conflicts += 1;
+                    }
+                    events[i] = bond.clone();
+                }
+                None => events.push(bond.clone()),
+            }
+            summary.pulled += 1;
+            resources.insert(
+                object.href.clone(),
+                SyncedResource {
+                    href: object.href,
+                    etag: Some(object.etag),
+                    uid,
+                    event: bond,
+                    unread_parts,
+                },
+            );
+        }
+
+        for bond in &events {
+            let Some(event) = bond.value() else {
+                continue;
+            };
+            let (href, etag) = match resources.values().find(|r| r.uid == event.uid) {
+                Some(r) if r.event.cid() == bond.cid() => continue,
+                Some(r) if r.unread_parts => {
+                    summary.held_back += 1;
+                    continue;
+                }
+                Some(r) => (r.href.clone(), r.etag.clone()),
+                None => (self.client.resource_href(&event.uid)?, None),
+            };
+            let ics = write_calendar(&[IcsComponent::Event(event.clone())]).map_err(|source| {
+                SyncError::Ics {
+                    href: href.clone(),
+                    source,
+                }
+            })?;
+            match self.client.put(&href, ics, etag.as_deref()) {
+                Ok(etag) => {
+                    summary.pushed += 1;
+                    resources.insert(
+                        href.clone(),
+                        SyncedResource {
+                            href,
+                            etag,
+                            uid: event.uid.clone(),
+                            event: bond.clone(),
+                            unread_parts: false,
+                        },
+                    );
+                }
+                Err(CalDavError::PreconditionFailed(_)) => summary.conflicts += 1,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        let deleted: Vec<SyncedResource> = resources
+            .values()
+            .filter(|r| position(&events, &r.uid).is_none())
+            .cloned()
+            .collect();
+        for resource in deleted {
+            match self.client.delete(&resource.href, resource.etag.as_deref()) {
+                Ok(()) => {
+                    summary.deleted += 1;
+                    resources.remove(&resource.href);
+                }
+                Err(CalDavError::PreconditionFailed(_)) => summary.conflicts += 1,
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        if events
+            .iter()
+            .map(Bond::cid)
+            .ne(self.calendar().events.iter().map(Bond::cid))
+        {
+            let mut calendar = self.calendar().clone();
+            calendar.events = events;
+            let cell = self.solvent.add(calendar);
+            let cid = self.commit(&self.calendar_ref, self.calendar_head.as_ref(), &cell)?;
+            self.calendar_head = Some(cid);
+            self.calendar = cell;
+        }
+        let state = SyncState {
+            collection: self.client.collection().to_string(),
+            sync_token: Some(report.sync_token),
+            resources: resources.into_values().collect(),
+        };
+        let cell = self.solvent.add(state.clone());
+        let cid = self.commit(&self.state_ref, self.state_head.as_ref(), &cell)?;
+        self.state_head = Some(cid);
+        self.state = state;
+        Ok(summary)
+    }
+
+    fn commit<T: Oxide>(
+        &self,
+        name: &str,
+        head: Option<&Cid>,
+        cell: &Cell<T>,
+    ) -> Result<Cid, SyncError<S::Error>> {
+        self.solvent.persist_cell(cell, &self.store)?;
+        let cid = cell.cid();
+        let moved = self
+            .store
+            .compare_and_set_ref(name, head, &cid)
+            .map_err(SyncError::Store)?;
+        if !moved {
+            return Err(SyncError::Conflict(name.to_string()));
+        }
+        Ok(cid)
+    }
+}
+
+fn position(events: &[Bond<CalendarEvent>], uid: &str) -> Option<usize> {
+    events
+        .iter()
+        .position(|bond| bond.value().is_some_and(|event| event.uid == uid))
+}
+
+/// Reads the event of a calendar object resource, and whether the resource
+/// has parts of it that weren't read; resources holding only todos or
+/// journals have none.
+fn read_event(data: &str) -> Result<Option<(CalendarEvent, bool)>, IcsError> {
+    parse_ics(data)?
+        .into_iter()
+        .find_map(|component| match component {
+            IcsComponent::Event(event) => Some(event),
+            _ => None,
+        })
+        .map(|event| Ok((event, has_unread_event_parts(data)?)))
+        .transpose()
+}
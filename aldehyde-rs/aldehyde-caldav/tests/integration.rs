@@ -0,0 +1,445 @@
+use std::cell::{Cell as StdCell, RefCell};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use aldehyde_cal::{Alarm, AlarmAction, AlarmTrigger, Calendar, Duration};
+use aldehyde_caldav::{
+    CalDavClient, CalDavError, CalDavSync, CalendarCollection, RemoteObject, SyncReport,
+    SyncSummary,
+};
+use polyepoxide_core::{Cell, MemoryStore, RefStore, Solvent};
+
+#[test]
+fn parses_sync_collection_report() {
+    let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:multistatus xmlns:d="DAV:" xmlns:cal="urn:ietf:params:xml:ns:caldav">
+  <d:response>
+    <d:href>/dav/calendars/alice/personal/</d:href>
+    <d:propstat>
+      <d:prop><d:getetag/></d:prop>
+      <d:status>HTTP/1.1 404 Not Found</d:status>
+    </d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>/dav/calendars/alice/personal/standup.ics</d:href>
+    <d:propstat>
+      <d:prop>
+        <d:getetag>"3-abc"</d:getetag>
+        <cal:calendar-data><![CDATA[BEGIN:VCALENDAR
+END:VCALENDAR
+]]></cal:calendar-data>
+      </d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status>
+    </d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>/dav/calendars/alice/personal/old.ics</d:href>
+    <d:status>HTTP/1.1 404 Not Found</d:status>
+  </d:response>
+  <d:sync-token>http://example.com/sync/42</d:sync-token>
+</d:multistatus>"#;
+
+    let report = SyncReport::parse(xml).unwrap();
+    assert_eq!(report.sync_token, "http://example.com/sync/42");
+    assert_eq!(
+        report.changed,
+        vec![RemoteObject {
+            href: "/dav/calendars/alice/personal/standup.ics".to_string(),
+            etag: "\"3-abc\"".to_string(),
+            calendar_data: "BEGIN:VCALENDAR\nEND:VCALENDAR\n".to_string(),
+        }]
+    );
+    assert_eq!(
+        report.removed,
+        vec!["/dav/calendars/alice/personal/old.ics"]
+    );
+
+    let error = SyncReport::parse("<d:error xmlns:d=\"DAV:\"/>").unwrap_err();
+    assert!(matches!(error, CalDavError::Xml(_)));
+}
+
+#[test]
+fn new_events_are_stored_under_their_uid() {
+    let client = CalDavClient::new("https://cloud.example.com/dav/calendars/alice/personal");
+    assert_eq!(
+        client.collection(),
+        "https://cloud.example.com/dav/calendars/alice/personal/"
+    );
+    assert_eq!(
+        client.resource_href("1234@example.com").unwrap(),
+        "/dav/calendars/alice/personal/1234@example.com.ics"
+    );
+    assert_eq!(
+        client.resource_href("a/b?c").unwrap(),
+        "/dav/calendars/alice/personal/a-b-c.ics"
+    );
+    assert_eq!(
+        client
+            .resolve("/dav/calendars/alice/personal/x.ics")
+            .unwrap()
+            .as_str(),
+        "https://cloud.example.com/dav/calendars/alice/personal/x.ics"
+    );
+}
+
+const CALENDAR: &str = "calendar";
+const STATE: &str = "calendar-sync";
+
+/// An in-memory collection whose sync tokens count changes to it.
+#[derive(Default)]
+struct Server {
+    version: StdCell<u64>,
+    /// Href to ETag, data and the version that last changed it
+    objects: RefCell<BTreeMap<String, (String, String, u64)>>,
+    removed: RefCell<BTreeMap<String, u64>>,
+    /// Tokens before this version are no longer accepted
+    oldest_token: StdCell<u64>,
+}
+
+impl Server {
+    fn store(&self, href: &str, data: String) -> String {
+        let version = self.version.get() + 1;
+        self.version.set(version);
+        let etag = format!("\"{}\"", version);
+        self.objects
+            .borrow_mut()
+            .insert(href.to_string(), (etag.clone(), data, version));
+        self.removed.borrow_mut().remove(href);
+        etag
+    }
+
+    fn remove(&self, href: &str) {
+        let version = self.version.get() + 1;
+        self.version.set(version);
+        self.objects.borrow_mut().remove(href);
+        self.removed.borrow_mut().insert(href.to_string(), version);
+    }
+
+    fn data(&self, href: &str) -> Option<String> {
+        self.objects
+            .borrow()
+            .get(href)
+            .map(|(_, data, _)| data.clone())
+    }
+
+    fn forget_tokens(&self) {
+        self.oldest_token.set(self.version.get() + 1);
+    }
+}
+
+impl CalendarCollection for &Server {
+    fn collection(&self) -> &str {
+        "https://cal.example.com/alice/"
+    }
+
+    fn resource_href(&self, uid: &str) -> Result<String, CalDavError> {
+        Ok(format!("/alice/{}.ics", uid))
+    }
+
+    fn sync_collection(&self, token: Option<&str>) -> Result<SyncReport, CalDavError> {
+        let since = match token {
+            Some(token) => {
+                let since: u64 = token.trim_start_matches('v').parse().unwrap();
+                if since < self.oldest_token.get() {
+                    return Err(CalDavError::InvalidSyncToken);
+                }
+                since
+            }
+            None => 0,
+        };
+        let changed = self
+            .objects
+            .borrow()
+            .iter()
+            .filter(|(_, (_, _, version))| *version > since)
+            .map(|(href, (etag, data, _))| RemoteObject {
+                href: href.clone(),
+                etag: etag.clone(),
+                calendar_data: data.clone(),
+            })
+            .collect();
+        let removed = self
+            .removed
+            .borrow()
+            .iter()
+            .filter(|(_, version)| token.is_some() && **version > since)
+            .map(|(href, _)| href.clone())
+            .collect();
+        Ok(SyncReport {
+            sync_token: format!("v{}", self.version.get()),
+            changed,
+            removed,
+        })
+    }
+
+    fn put(
+        &self,
+        href: &str,
+        ics: String,
+        etag: Option<&str>,
+    ) -> Result<Option<String>, CalDavError> {
+        let current = self
+            .objects
+            .borrow()
+            .get(href)
+            .map(|(etag, _, _)| etag.clone());
+        if current.as_deref() != etag {
+            return Err(CalDavError::PreconditionFailed(href.to_string()));
+        }
+        Ok(Some(self.store(href, ics)))
+    }
+
+    fn delete(&self, href: &str, etag: Option<&str>) -> Result<(), CalDavError> {
+        let current = self
+            .objects
+            .borrow()
+            .get(href)
+            .map(|(etag, _, _)| etag.clone());
+        match (current, etag) {
+            (None, _) => Ok(()),
+            (Some(current), Some(etag)) if current != etag => {
+                Err(CalDavError::PreconditionFailed(href.to_string()))
+            }
+            _ => {
+                self.remove(href);
+                Ok(())
+            }
+        }
+    }
+}
+
+fn event_ics(uid: &str, summary: &str, extra: &str) -> String {
+    format!(
+        "BEGIN:VCALENDAR\r\n\
+         BEGIN:VEVENT\r\n\
+         UID:{}\r\n\
+         DTSTAMP:20240101T000000Z\r\n\
+         DTSTART:20240101T090000Z\r\n\
+         SUMMARY:{}\r\n\
+         {}\
+         END:VEVENT\r\n\
+         END:VCALENDAR\r\n",
+        uid, summary, extra
+    )
+}
+
+/// Changes the calendar in the store, as another program would between
+/// syncs.
+fn edit(store: &MemoryStore, change: impl FnOnce(&mut Calendar, &mut Solvent)) {
+    let mut solvent = Solvent::new();
+    let head = store.get_ref(CALENDAR).unwrap().unwrap();
+    let cell: Arc<Cell<Calendar>> = solvent.load(&head, store, 2).unwrap();
+    let mut calendar = cell.value().clone();
+    change(&mut calendar, &mut solvent);
+    let cell = solvent.add(calendar);
+    solvent.persist_cell(&cell, store).unwrap();
+    store.set_ref(CALENDAR, &cell.cid()).unwrap();
+}
+
+fn rename(calendar: &mut Calendar, solvent: &mut Solvent, uid: &str, summary: &str) {
+    let bond = calendar
+        .events
+        .iter_mut()
+        .find(|bond| bond.value().is_some_and(|event| event.uid == uid))
+        .unwrap();
+    let mut event = bond.value().unwrap().clone();
+    event.summary = summary.to_string();
+    *bond = solvent.bond(event);
+}
+
+fn summaries(sync: &CalDavSync<&MemoryStore, &Server>) -> Vec<String> {
+    sync.calendar()
+        .events
+        .iter()
+        .map(|bond| bond.value().unwrap().summary.clone())
+        .collect()
+}
+
+#[test]
+fn sync_pulls_pushes_and_deletes() {
+    let store = MemoryStore::new();
+    let server = Server::default();
+    server.store("/alice/standup.ics", event_ics("standup", "Standup", ""));
+
+    let mut sync = CalDavSync::open(&store, &server, CALENDAR, STATE).unwrap();
+    let summary = sync.sync().unwrap();
+    assert_eq!(
+        summary,
+        SyncSummary {
+            pulled: 1,
+            ..Default::default()
+        }
+    );
+    assert_eq!(summaries(&sync), vec!["Standup"]);
+    assert_eq!(sync.state().sync_token.as_deref(), Some("v1"));
+    assert_eq!(sync.state().resources[0].etag.as_deref(), Some("\"1\""));
+
+    // The refs hold what the sync committed
+    let head = store.get_ref(CALENDAR).unwrap().unwrap();
+    let state_head = store.get_ref(STATE).unwrap().unwrap();
+    let reopened = CalDavSync::open(&store, &server, CALENDAR, STATE).unwrap();
+    assert_eq!(summaries(&reopened), vec!["Standup"]);
+    assert_eq!(reopened.state().sync_token, sync.state().sync_token);
+    assert_eq!(reopened.state().resources.len(), 1);
+
+    // Without changes neither ref moves
+    assert_eq!(sync.sync().unwrap(), SyncSummary::default());
+    assert_eq!(store.get_ref(CALENDAR).unwrap(), Some(head));
+    assert_eq!(store.get_ref(STATE).unwrap(), Some(state_head));
+
+    edit(&store, |calendar, solvent| {
+        rename(calendar, solvent, "standup", "Daily standup");
+        let mut review = calendar.events[0].value().unwrap().clone();
+        review.uid = "review".to_string();
+        review.summary = "Review".to_string();
+        calendar.events.push(solvent.bond(review));
+    });
+    let mut sync = CalDavSync::open(&store, &server, CALENDAR, STATE).unwrap();
+    assert_eq!(
+        sync.sync().unwrap(),
+        SyncSummary {
+            pushed: 2,
+            ..Default::default()
+        }
+    );
+    assert!(server
+        .data("/alice/standup.ics")
+        .unwrap()
+        .contains("SUMMARY:Daily standup"));
+    assert!(server
+        .data("/alice/review.ics")
+        .unwrap()
+        .contains("SUMMARY:Review"));
+    // Pushed versions come back from the server unchanged
+    assert_eq!(sync.sync().unwrap(), SyncSummary::default());
+
+    server.remove("/alice/standup.ics");
+    assert_eq!(
+        sync.sync().unwrap(),
+        SyncSummary {
+            removed: 1,
+            ..Default::default()
+        }
+    );
+    assert_eq!(summaries(&sync), vec!["Review"]);
+
+    edit(&store, |calendar, _| calendar.events.clear());
+    let mut sync = CalDavSync::open(&store, &server, CALENDAR, STATE).unwrap();
+    assert_eq!(
+        sync.sync().unwrap(),
+        SyncSummary {
+            deleted: 1,
+            ..Default::default()
+        }
+    );
+    assert_eq!(server.data("/alice/review.ics"), None);
+    assert!(sync.state().resources.is_empty());
+}
+
+#[test]
+fn server_wins_conflicting_changes() {
+    let store = MemoryStore::new();
+    let server = Server::default();
+    server.store("/alice/standup.ics", event_ics("standup", "Standup", ""));
+    CalDavSync::open(&store, &server, CALENDAR, STATE)
+        .unwrap()
+        .sync()
+        .unwrap();
+
+    edit(&store, |calendar, solvent| {
+        rename(calendar, solvent, "standup", "Local")
+    });
+    server.store("/alice/standup.ics", event_ics("standup", "Remote", ""));
+    let mut sync = CalDavSync::open(&store, &server, CALENDAR, STATE).unwrap();
+    assert_eq!(
+        sync.sync().unwrap(),
+        SyncSummary {
+            pulled: 1,
+            conflicts: 1,
+            ..Default::default()
+        }
+    );
+    assert_eq!(summaries(&sync), vec!["Remote"]);
+    assert!(server
+        .data("/alice/standup.ics")
+        .unwrap()
+        .contains("SUMMARY:Remote"));
+
+    // Another writer moving the calendar ref during a sync fails it
+    edit(&store, |calendar, solvent| {
+        rename(calendar, solvent, "standup", "Elsewhere")
+    });
+    server.store("/alice/review.ics", event_ics("review", "Review", ""));
+    assert!(sync.sync().is_err());
+}
+
+#[test]
+fn forgotten_sync_token_resyncs_everything() {
+    let store = MemoryStore::new();
+    let server = Server::default();
+    server.store("/alice/standup.ics", event_ics("standup", "Standup", ""));
+    let mut sync = CalDavSync::open(&store, &server, CALENDAR, STATE).unwrap();
+    sync.sync().unwrap();
+
+    server.remove("/alice/standup.ics");
+    server.store("/alice/review.ics", event_ics("review", "Review", ""));
+    server.forget_tokens();
+    assert_eq!(
+        sync.sync().unwrap(),
+        SyncSummary {
+            pulled: 1,
+            removed: 1,
+            ..Default::default()
+        }
+    );
+    assert_eq!(summaries(&sync), vec!["Review"]);
+    assert_eq!(sync.state().sync_token.as_deref(), Some("v3"));
+}
+
+#[test]
+fn unread_parts_are_never_overwritten() {
+    let store = MemoryStore::new();
+    let server = Server::default();
+    let alarm = "BEGIN:VALARM\r\nACTION:DISPLAY\r\nTRIGGER:-PT10M\r\nEND:VALARM\r\n";
+    server.store("/alice/standup.ics", event_ics("standup", "Standup", alarm));
+    let mut sync = CalDavSync::open(&store, &server, CALENDAR, STATE).unwrap();
+    sync.sync().unwrap();
+    assert!(sync.state().resources[0].unread_parts);
+
+    edit(&store, |calendar, solvent| {
+        let bond = &mut calendar.events[0];
+        let mut event = bond.value().unwrap().clone();
+        event.summary = "Local".to_string();
+        event.alarms.push(solvent.bond(Alarm {
+            action: AlarmAction::Display,
+            trigger: AlarmTrigger::BeforeStart(Duration {
+                seconds: 300,
+                negative: false,
+            }),
+            description: None,
+            repeat_count: None,
+            repeat_duration: None,
+        }));
+        *bond = solvent.bond(event);
+    });
+    let mut sync = CalDavSync::open(&store, &server, CALENDAR, STATE).unwrap();
+    assert_eq!(
+        sync.sync().unwrap(),
+        SyncSummary {
+            held_back: 1,
+            ..Default::default()
+        }
+    );
+    assert!(server
+        .data("/alice/standup.ics")
+        .unwrap()
+        .contains("BEGIN:VALARM"));
+
+    // A pulled version keeps the alarms set locally
+    server.store("/alice/standup.ics", event_ics("standup", "Remote", alarm));
+    let summary = sync.sync().unwrap();
+    assert_eq!((summary.pulled, summary.conflicts), (1, 1));
+    let event = sync.calendar().events[0].value().unwrap();
+    assert_eq!(event.summary, "Remote");
+    assert_eq!(event.alarms.len(), 1);
+}
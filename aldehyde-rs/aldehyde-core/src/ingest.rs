@@ -0,0 +1,259 @@
+//! Turning image files into photos, with EXIF data and thumbnails.
+
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use exif::{In, Tag, Value};
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageFormat};
+use polyepoxide_core::{Blob, Bond, ByteString, IngestError, IngestPolicy};
+
+use crate::photo::{ExifData, ExifTag, ExifValue, Photo};
+
+/// Error from ingesting an image file.
+#[derive(Debug, thiserror::Error)]
+pub enum PhotoError {
+    #[error("failed to read {}", .path.display())]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error(transparent)]
+    Ingest(#[from] IngestError),
+    #[error("image error: {0}")]
+    Image(#[from] image::ImageError),
+}
+
+/// How photos are ingested.
+#[derive(Debug, Clone)]
+pub struct PhotoOptions {
+    policy: IngestPolicy,
+    /// Longest edge of each thumbnail, in pixels.
+    thumbnail_sizes: Vec<u32>,
+}
+
+impl Default for PhotoOptions {
+    fn default() -> Self {
+        Self {
+            policy: IngestPolicy::default(),
+            thumbnail_sizes: vec![256, 1024],
+        }
+    }
+}
+
+impl PhotoOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Screens files against `policy` before reading them as images.
+    pub fn with_policy(mut self, policy: IngestPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Generates a thumbnail for each size, given as the longest edge in
+    /// pixels. Sizes not smaller than the photo are skipped.
+    pub fn with_thumbnail_sizes(mut self, sizes: impl IntoIterator<Item = u32>) -> Self {
+        self.thumbnail_sizes = sizes.into_iter().collect();
+        self
+    }
+}
+
+/// Reads an image file into a photo with its dimensions, EXIF data and
+/// JPEG thumbnails, the full-resolution content chunked into a blob.
+///
+/// Thumbnails are rotated upright according to the EXIF orientation; the
+/// content is kept as it is.
+pub fn ingest_photo(path: &Path, options: &PhotoOptions) -> Result<Photo, PhotoError> {
+    let data = std::fs::read(path).map_err(|source| PhotoError::Read {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let format = image::guess_format(&data)?;
+    let filename = path
+        .file_name()
+        .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+    let mime_type = format.to_mime_type();
+    options.policy.screen(&filename, mime_type, &data)?;
+
+    let image = image::load_from_memory_with_format(&data, format)?;
+    let exif = exif::Reader::new()
+        .read_from_container(&mut Cursor::new(&data))
+        .ok();
+
+    let mut upright = image.clone();
+    if let Some(orientation) = exif
+        .as_ref()
+        .and_then(|e| e.get_field(Tag::Orientation, In::PRIMARY))
+        .and_then(|f| f.value.get_uint(0))
+        .and_then(|v| Orientation::from_exif(v as u8))
+    {
+        upright.apply_orientation(orientation);
+    }
+    let stem = path
+        .file_stem()
+        .map_or_else(String::new, |s| s.to_string_lossy().into_owned());
+    let mut thumbnails = Vec::new();
+    for &size in &options.thumbnail_sizes {
+        if size >= image.width().max(image.height()) {
+            continue;
+        }
+        thumbnails.push(Bond::new(thumbnail(&upright, size, &stem)?));
+    }
+
+    Ok(Photo {
+        filename,
+        mime_type: mime_type.to_string(),
+        width: Some(image.width()),
+        height: Some(image.height()),
+        exif: exif.as_ref().map(|e| Bond::new(exif_data(e))),
+        thumbnails,
+        content: Bond::new(Blob::new(&data)),
+    })
+}
+
+fn thumbnail(image: &DynamicImage, size: u32, stem: &str) -> Result<Photo, PhotoError> {
+    // JPEG has no alpha channel
+    let small = DynamicImage::ImageRgb8(image.thumbnail(size, size).to_rgb8());
+    let mut bytes = Cursor::new(Vec::new());
+    small.write_to(&mut bytes, ImageFormat::Jpeg)?;
+    Ok(Photo {
+        filename: format!("{}-{}.jpg", stem, size),
+        mime_type: ImageFormat::Jpeg.to_mime_type().to_string(),
+        width: Some(small.width()),
+        height: Some(small.height()),
+        exif: None,
+        thumbnails: Vec::new(),
+        content: Bond::new(Blob::new(bytes.get_ref())),
+    })
+}
+
+/// Converts the tags of the primary image; tags of the embedded thumbnail
+/// and floating-point values are left out.
+fn exif_data(exif: &exif::Exif) -> ExifData {
+    let tags = exif
+        .fields()
+        .filter(|f| f.ifd_num == In::PRIMARY)
+        .filter_map(|f| {
+            let values = exif_values(&f.value);
+            (!values.is_empty()).then_some(ExifTag {
+                id: f.tag.number(),
+                values,
+            })
+        })
+        .collect();
+    let ascii = |tag| match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Ascii(parts) => parts
+            .first()
+            .map(|s| String::from_utf8_lossy(s).trim().to_string()),
+        _ => None,
+    };
+    // "2024:01:15 10:30:00" to ISO 8601
+    let date_taken = ascii(Tag::DateTimeOriginal).and_then(|d| {
+        let (date, time) = d.split_once(' ')?;
+        Some(format!("{}T{}", date.replace(':', "-"), time))
+    });
+    let coordinate = |tag, reference, negative: &str| {
+        let Value::Rational(parts) = &exif.get_field(tag, In::PRIMARY)?.value else {
+            return None;
+        };
+        let degrees = parts
+            .iter()
+            .zip([1.0, 60.0, 3600.0])
+            .map(|(part, divisor)| part.to_f64() / divisor)
+            .sum::<f64>();
+        if ascii(reference).as_deref() == Some(negative) {
+            Some(-degrees)
+        } else {
+            Some(degrees)
+        }
+    };
+
+    ExifData {
+        tags,
+        camera_make: ascii(Tag::Make),
+        camera_model: ascii(Tag::Model),
+        date_taken,
+        gps_latitude: coordinate(Tag::GPSLatitude, Tag::GPSLatitudeRef, "S"),
+        gps_longitude: coordinate(Tag::GPSLongitude, Tag::GPSLongitudeRef, "W"),
+    }
+}
+
+fn exif_values(value: &Value) -> Vec<ExifValue> {
+    match value {
+        Value::Byte(v) => v.iter().map(|&b| ExifValue::Byte(b)).collect(),
+        Value::Ascii(v) => v
+            .iter()
+            .map(|s| ExifValue::Ascii(String::from_utf8_lossy(s).into_owned()))
+            .collect(),
+        Value::Short(v) => v.iter().map(|&s| ExifValue::Short(s)).collect(),
+        Value::Long(v) => v.iter().map(|&l| ExifValue::Long(l)).collect(),
+        Value::Rational(v) => v
+            .iter()
+            .map(|r| ExifValue::Rational {
+                num: r.num,
+                denom: r.denom,
+            })
+            .collect(),
+        Value::SByte(v) => v.iter().map(|&b| ExifValue::SLong(b.into())).collect(),
+        Value::Undefined(bytes, _) => {
+            vec![ExifValue::Undefined(Bond::new(ByteString(bytes.clone())))]
+        }
+        Value::SShort(v) => v.iter().map(|&s| ExifValue::SLong(s.into())).collect(),
+        Value::SLong(v) => v.iter().map(|&l| ExifValue::SLong(l)).collect(),
+        Value::SRational(v) => v
+            .iter()
+            .map(|r| ExifValue::SRational {
+                num: r.num,
+                denom: r.denom,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbImage;
+
+    #[test]
+    fn reads_dimensions_and_makes_thumbnails() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("beach.png");
+        RgbImage::from_pixel(600, 300, image::Rgb([200, 180, 40]))
+            .save(&path)
+            .unwrap();
+
+        let options = PhotoOptions::new().with_thumbnail_sizes([64, 256, 1024]);
+        let photo = ingest_photo(&path, &options).unwrap();
+        assert_eq!(photo.filename, "beach.png");
+        assert_eq!(photo.mime_type, "image/png");
+        assert_eq!((photo.width, photo.height), (Some(600), Some(300)));
+        assert!(photo.exif.is_none());
+
+        // The 1024 thumbnail would be larger than the photo
+        let thumbnails: Vec<_> = photo
+            .thumbnails
+            .iter()
+            .map(|bond| {
+                let thumbnail = bond.value().unwrap();
+                (
+                    thumbnail.filename.as_str(),
+                    thumbnail.mime_type.as_str(),
+                    thumbnail.width,
+                    thumbnail.height,
+                )
+            })
+            .collect();
+        assert_eq!(
+            thumbnails,
+            [
+                ("beach-64.jpg", "image/jpeg", Some(64), Some(32)),
+                ("beach-256.jpg", "image/jpeg", Some(256), Some(128)),
+            ]
+        );
+    }
+}
@@ -1,5 +1,9 @@
 //! Aldehyde Core - Shared types for the Aldehyde life management system
 
+#[cfg(feature = "image")]
+mod ingest;
 pub mod photo;
 
+#[cfg(feature = "image")]
+pub use ingest::{ingest_photo, PhotoError, PhotoOptions};
 pub use photo::{ExifData, ExifTag, ExifValue, Photo};
@@ -1,4 +1,6 @@
-use polyepoxide_core::{oxide, Bond, ByteString};
+use polyepoxide_core::{
+    oxide, Blob, BlobReader, Bond, ByteString, IngestError, IngestPolicy, Store,
+};
 
 /// EXIF value types as defined in the EXIF standard
 #[oxide]
@@ -34,7 +36,7 @@ pub struct ExifData {
     pub gps_longitude: Option<f64>,
 }
 
-/// Photo with full metadata and inline content
+/// Photo with full metadata and chunked content
 #[oxide]
 pub struct Photo {
     pub filename: String,
@@ -43,5 +45,38 @@ pub struct Photo {
     pub height: Option<u32>,
     pub exif: Option<Bond<ExifData>>,
     pub thumbnails: Vec<Bond<Photo>>,
-    pub content: Bond<ByteString>,
+    pub content: Bond<Blob>,
+}
+
+impl Photo {
+    /// Creates a photo from file contents after screening them against `policy`.
+    ///
+    /// Dimensions, EXIF data, and thumbnails are left for the caller to fill in;
+    /// `ingest_photo` (`image` feature) fills them in from an image file.
+    pub fn ingest(
+        policy: &IngestPolicy,
+        filename: impl Into<String>,
+        mime_type: impl Into<String>,
+        data: Vec<u8>,
+    ) -> Result<Self, IngestError> {
+        let filename = filename.into();
+        let mime_type = mime_type.into();
+        policy.screen(&filename, &mime_type, &data)?;
+        Ok(Photo {
+            filename,
+            mime_type,
+            width: None,
+            height: None,
+            exif: None,
+            thumbnails: Vec::new(),
+            content: Bond::new(Blob::new(&data)),
+        })
+    }
+
+    /// Streams the content, loading chunks from `store` as they're read.
+    ///
+    /// Returns `None` if the content manifest isn't loaded.
+    pub fn content_reader<'a, S: Store>(&'a self, store: &'a S) -> Option<BlobReader<'a, S>> {
+        Some(self.content.value()?.reader(store))
+    }
 }
@@ -0,0 +1,79 @@
+//! Errors reported by the command line, with hints on how to fix them.
+
+use std::path::PathBuf;
+
+use aldehyde_inventory::{ItemId, ServiceError};
+use miette::{Diagnostic, GraphicalReportHandler};
+use polyepoxide_any::AnyStoreError;
+use polyepoxide_core::IngestError;
+use thiserror::Error;
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum InventoryError {
+    #[error("unknown store type: {0}")]
+    #[diagnostic(
+        code(inventory::unknown_store_type),
+        help("use `--store fjall` or `--store rocks`")
+    )]
+    UnknownStoreType(String),
+
+    #[error("failed to open {store} store at {}", .path.display())]
+    #[diagnostic(
+        code(inventory::open_store),
+        help("check that --path points to a store of the type given by --store, and that no other process holds it open")
+    )]
+    OpenStore {
+        store: String,
+        path: PathBuf,
+        #[source]
+        source: AnyStoreError,
+    },
+
+    #[error("no item with id {0}")]
+    #[diagnostic(
+        code(inventory::unknown_item),
+        help("run `aldehyde-inventory-tool item list` or `search` to find item ids")
+    )]
+    UnknownItem(ItemId),
+
+    #[error("no item is labeled {0}")]
+    #[diagnostic(
+        code(inventory::unknown_label),
+        help("add the label with `aldehyde-inventory-tool label add <ID> <CODE>`")
+    )]
+    UnknownLabel(String),
+
+    #[error(transparent)]
+    #[diagnostic(code(inventory::service))]
+    Service(ServiceError<AnyStoreError>),
+
+    #[error("failed to read {}", .path.display())]
+    #[diagnostic(code(inventory::read))]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error(transparent)]
+    #[diagnostic(code(inventory::ingest))]
+    Ingest(#[from] IngestError),
+}
+
+impl From<ServiceError<AnyStoreError>> for InventoryError {
+    fn from(error: ServiceError<AnyStoreError>) -> Self {
+        match error {
+            ServiceError::UnknownItem(id) => InventoryError::UnknownItem(id),
+            error => InventoryError::Service(error),
+        }
+    }
+}
+
+/// Prints an error with its causes and help text to stderr.
+pub fn report(error: &InventoryError) {
+    let mut out = String::new();
+    match GraphicalReportHandler::new().render_report(&mut out, error) {
+        Ok(()) => eprint!("{}", out),
+        Err(_) => eprintln!("Error: {}", error),
+    }
+}
@@ -0,0 +1,214 @@
+//! `item` subcommands: adding, listing and showing items.
+
+use aldehyde_inventory::{AttributeValue, Item, ItemId, Price};
+use clap::Subcommand;
+
+use crate::error::InventoryError;
+use crate::Service;
+
+#[derive(Subcommand)]
+pub enum ItemCommand {
+    /// Add an item and print its id
+    Add {
+        name: String,
+
+        #[arg(long)]
+        description: Option<String>,
+
+        /// Id to use instead of a random UUID, e.g. a label printed on the item
+        #[arg(long)]
+        id: Option<ItemId>,
+
+        /// Id of the container to place the item in
+        #[arg(long = "in")]
+        location: Option<ItemId>,
+
+        /// How many of the item there are
+        #[arg(long)]
+        quantity: Option<f64>,
+
+        /// Unit of the quantity, e.g. m or kg
+        #[arg(long, requires = "quantity")]
+        unit: Option<String>,
+
+        /// Tag to add to the item; may be repeated
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+
+        /// Date the item was acquired, e.g. 2024-01-15
+        #[arg(long)]
+        acquired: Option<String>,
+
+        /// Price paid, e.g. 12.50
+        #[arg(long, value_parser = parse_amount, requires = "currency")]
+        price: Option<i64>,
+
+        /// Currency of the price, e.g. EUR
+        #[arg(long, requires = "price")]
+        currency: Option<String>,
+
+        /// Custom attribute as key=value; may be repeated
+        #[arg(long = "attribute", value_parser = parse_attribute)]
+        attributes: Vec<(String, AttributeValue)>,
+    },
+
+    /// List items: id, name and the id of their container
+    List,
+
+    /// List the items with exactly the given name, as in `item list`
+    Find { name: String },
+
+    /// Show an item with its container, contents, photos and events
+    Show { id: ItemId },
+}
+
+pub fn run(service: &mut Service, command: ItemCommand) -> Result<(), InventoryError> {
+    match command {
+        ItemCommand::Add {
+            name,
+            description,
+            id,
+            location,
+            quantity,
+            unit,
+            tags,
+            acquired,
+            price,
+            currency,
+            attributes,
+        } => {
+            // Checked first so a missing container doesn't leave the item added
+            if let Some(location) = &location {
+                if service.item(location).is_none() {
+                    return Err(InventoryError::UnknownItem(location.clone()));
+                }
+            }
+            let id = id.unwrap_or_else(|| ItemId::new(uuid::Uuid::new_v4().to_string()));
+            service.add_item(Item {
+                description,
+                quantity,
+                unit,
+                tags,
+                acquired,
+                price: price
+                    .zip(currency)
+                    .map(|(amount, currency)| Price { amount, currency }),
+                attributes: attributes.into_iter().collect(),
+                ..Item::new(id.clone(), name)
+            })?;
+            if location.is_some() {
+                service.move_item(&id, location)?;
+            }
+            println!("{}", id);
+        }
+        ItemCommand::List => {
+            for item in service.inventory().items() {
+                print_row(service, item);
+            }
+        }
+        ItemCommand::Find { name } => {
+            for item in service.find_by_name(&name)? {
+                print_row(service, item);
+            }
+        }
+        ItemCommand::Show { id } => show(service, &id)?,
+    }
+    Ok(())
+}
+
+/// Prints an item as a line of `item list`.
+pub fn print_row(service: &Service, item: &Item) {
+    let location = service.location(&item.id).map_or("", ItemId::as_str);
+    println!("{}\t{}\t{}", item.id, item.name, location);
+}
+
+/// Prints the chain of containers an item is in, innermost first.
+pub fn print_location(service: &Service, id: &ItemId) -> Result<(), InventoryError> {
+    let chain = service.location_of(id)?;
+    match chain.is_empty() {
+        true => println!("in: top level"),
+        false => {
+            let chain: Vec<String> = chain.into_iter().map(label).collect();
+            println!("in: {}", chain.join(" > "));
+        }
+    }
+    Ok(())
+}
+
+fn show(service: &mut Service, id: &ItemId) -> Result<(), InventoryError> {
+    let Some(item) = service.find_by_id(id)? else {
+        return Err(InventoryError::UnknownItem(id.clone()));
+    };
+    println!("{}\t{}", item.id, item.name);
+    if let Some(description) = &item.description {
+        println!("{}", description);
+    }
+    if let Some(quantity) = item.quantity {
+        match &item.unit {
+            Some(unit) => println!("quantity: {} {}", quantity, unit),
+            None => println!("quantity: {}", quantity),
+        }
+    }
+    if !item.tags.is_empty() {
+        println!("tags: {}", item.tags.join(", "));
+    }
+    if let Some(acquired) = &item.acquired {
+        println!("acquired: {}", acquired);
+    }
+    if let Some(price) = &item.price {
+        println!("price: {}", price);
+    }
+    for (key, value) in &item.attributes {
+        println!("attribute: {}={}", key, value);
+    }
+    for label in service.labels_of(id) {
+        println!("label: {} ({})", label.code, label.symbology);
+    }
+    print_location(service, id)?;
+    if let Some(loan) = service.loans().into_iter().find(|loan| loan.item_id == *id) {
+        println!("lent to: {}", loan.to);
+    }
+    for content in service.contents_of(id, false)? {
+        println!("contains: {}", label(content));
+    }
+
+    for photo in service.photos(id)? {
+        let photo = photo.value();
+        println!("photo: {} ({})", photo.filename, photo.mime_type);
+    }
+
+    if let Some(log) = service.inventory().events.value() {
+        for event in log.events.iter().filter_map(|e| e.value()) {
+            if event.item_id == *id {
+                println!("event: {}\t{:?}", event.timestamp, event.kind);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// An item's name followed by its id.
+fn label(item: &Item) -> String {
+    format!("{} ({})", item.name, item.id)
+}
+
+/// Parses a `key=value` attribute.
+pub fn parse_attribute(s: &str) -> Result<(String, AttributeValue), String> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected key=value, got {}", s))?;
+    let Ok(value) = value.parse();
+    Ok((key.to_string(), value))
+}
+
+/// Parses an amount with up to two decimals into hundredths.
+fn parse_amount(s: &str) -> Result<i64, String> {
+    let invalid = || format!("expected an amount such as 12.50, got {}", s);
+    let (whole, fraction) = s.split_once('.').unwrap_or((s, ""));
+    if fraction.len() > 2 || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let whole: u32 = whole.parse().map_err(|_| invalid())?;
+    let fraction: i64 = format!("{:0<2}", fraction).parse().map_err(|_| invalid())?;
+    Ok(i64::from(whole) * 100 + fraction)
+}
@@ -0,0 +1,46 @@
+//! `label` subcommands: labeling items and finding them by scanned codes.
+
+use aldehyde_inventory::{ItemId, Symbology};
+use clap::Subcommand;
+
+use crate::error::InventoryError;
+use crate::{item, Service};
+
+#[derive(Subcommand)]
+pub enum LabelCommand {
+    /// Label an item with a code and print the new root CID
+    Add {
+        /// Id of the item
+        id: ItemId,
+
+        /// Content of the barcode or QR code
+        code: String,
+
+        /// One of qr, datamatrix, code128, code39, ean13 or upca
+        #[arg(long, default_value = "qr")]
+        symbology: Symbology,
+    },
+
+    /// Show the item a scanned code identifies, as in `item list`, and where it is
+    Find { code: String },
+}
+
+pub fn run(service: &mut Service, command: LabelCommand) -> Result<(), InventoryError> {
+    match command {
+        LabelCommand::Add {
+            id,
+            code,
+            symbology,
+        } => {
+            println!("{}", service.add_label(&id, code, symbology)?);
+        }
+        LabelCommand::Find { code } => {
+            let Some(found) = service.find_by_label(&code) else {
+                return Err(InventoryError::UnknownLabel(code));
+            };
+            item::print_row(service, found);
+            item::print_location(service, &found.id)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,257 @@
+//! Command line for a home inventory kept in a polyepoxide store.
+
+mod error;
+mod item;
+mod label;
+mod photo;
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use aldehyde_inventory::{AttributeValue, InventoryService, Item, ItemId};
+use clap::{Parser, Subcommand};
+use polyepoxide_any::AnyStore;
+
+use error::InventoryError;
+
+type Service = InventoryService<AnyStore>;
+
+#[derive(Parser)]
+#[command(name = "aldehyde-inventory-tool")]
+#[command(about = "Home inventory kept in a polyepoxide store")]
+struct Cli {
+    /// Store type: fjall or rocks
+    #[arg(long, default_value = "fjall")]
+    store: String,
+
+    /// Path to the store
+    #[arg(long)]
+    path: PathBuf,
+
+    /// Ref holding the inventory's root, moved by every change
+    #[arg(long = "ref", default_value = "inventory/head")]
+    ref_name: String,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Add, list and show items
+    Item {
+        #[command(subcommand)]
+        command: item::ItemCommand,
+    },
+
+    /// Place an item inside another one, or at the top level, and print the new root CID
+    Place {
+        /// Id of the item to move
+        id: ItemId,
+
+        /// Id of the container; the top level if not given
+        #[arg(long = "in")]
+        location: Option<ItemId>,
+    },
+
+    /// List the containers an item is in, innermost first
+    Where {
+        /// Id of the item
+        id: ItemId,
+    },
+
+    /// List the items placed in a container, as in `item list`
+    Contents {
+        /// Id of the container
+        id: ItemId,
+
+        /// Also list the items inside those, each followed by its own contents
+        #[arg(long)]
+        recursive: bool,
+    },
+
+    /// Label items with barcodes or QR codes, and find items by scanned codes
+    Label {
+        #[command(subcommand)]
+        command: label::LabelCommand,
+    },
+
+    /// Attach photos to items
+    Photo {
+        #[command(subcommand)]
+        command: photo::PhotoCommand,
+    },
+
+    /// Log an item as lent to someone and print the new root CID
+    Lend {
+        /// Id of the item
+        id: ItemId,
+
+        /// Who the item is lent to
+        to: String,
+
+        #[arg(long)]
+        note: Option<String>,
+    },
+
+    /// Log a lent item as returned and print the new root CID
+    Return {
+        /// Id of the item
+        id: ItemId,
+    },
+
+    /// List items currently lent out, in the order they were lent: id, name, borrower,
+    /// timestamp in milliseconds and note
+    Loans {
+        /// Only list items lent to this person
+        #[arg(long)]
+        to: Option<String>,
+    },
+
+    /// List logged events, oldest first: timestamp in milliseconds, item, kind, target and note
+    Events {
+        /// Only list events of this item
+        #[arg(long)]
+        item: Option<ItemId>,
+    },
+
+    /// List items matching all of the given filters, as in `item list`
+    Search {
+        /// Text contained in the id, name or description, ignoring case
+        query: Option<String>,
+
+        /// Tag the items must carry; may be repeated
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+
+        /// Attribute as key=value the items must have; may be repeated
+        #[arg(long = "attribute", value_parser = item::parse_attribute)]
+        attributes: Vec<(String, AttributeValue)>,
+    },
+
+    /// Report duplicate ids, references to missing items and placement cycles
+    Check,
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            error::report(&err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<(), InventoryError> {
+    let store = open_store(&cli.store, &cli.path)?;
+    let mut service = InventoryService::open(store, cli.ref_name)?;
+
+    match cli.command {
+        Command::Item { command } => item::run(&mut service, command)?,
+        Command::Place { id, location } => {
+            println!("{}", service.move_item(&id, location)?);
+        }
+        Command::Where { id } => {
+            for container in service.location_of(&id)? {
+                println!("{}\t{}", container.id, container.name);
+            }
+        }
+        Command::Contents { id, recursive } => {
+            for item in service.contents_of(&id, recursive)? {
+                item::print_row(&service, item);
+            }
+        }
+        Command::Label { command } => label::run(&mut service, command)?,
+        Command::Photo { command } => photo::run(&mut service, command)?,
+        Command::Lend { id, to, note } => {
+            println!("{}", service.lend(&id, to, note)?);
+        }
+        Command::Return { id } => {
+            println!("{}", service.return_item(&id)?);
+        }
+        Command::Loans { to } => {
+            for loan in service.loans() {
+                if to.as_ref().is_some_and(|to| *to != loan.to) {
+                    continue;
+                }
+                let name = service
+                    .item(&loan.item_id)
+                    .map_or("", |item| item.name.as_str());
+                println!(
+                    "{}\t{}\t{}\t{}\t{}",
+                    loan.item_id,
+                    name,
+                    loan.to,
+                    loan.since,
+                    loan.note.as_deref().unwrap_or("")
+                );
+            }
+        }
+        Command::Events { item } => {
+            let Some(log) = service.inventory().events.value() else {
+                return Ok(());
+            };
+            for event in log.events.iter().filter_map(|e| e.value()) {
+                if item.as_ref().is_some_and(|id| *id != event.item_id) {
+                    continue;
+                }
+                println!(
+                    "{}\t{}\t{:?}\t{}\t{}",
+                    event.timestamp,
+                    event.item_id,
+                    event.kind,
+                    event.target_id.as_deref().unwrap_or(""),
+                    event.note.as_deref().unwrap_or("")
+                );
+            }
+        }
+        Command::Search {
+            query,
+            tags,
+            attributes,
+        } => {
+            let query = query.map(|q| q.to_lowercase());
+            let contains = |item: &Item, query: &str| {
+                [
+                    Some(item.id.as_str()),
+                    Some(item.name.as_str()),
+                    item.description.as_deref(),
+                ]
+                .into_iter()
+                .flatten()
+                .any(|text| text.to_lowercase().contains(query))
+            };
+            let matches = |item: &Item| {
+                query.as_deref().is_none_or(|query| contains(item, query))
+                    && tags.iter().all(|tag| item.has_tag(tag))
+                    && attributes
+                        .iter()
+                        .all(|(key, value)| item.attribute(key) == Some(value))
+            };
+            for item in service.inventory().items().filter(|item| matches(item)) {
+                item::print_row(&service, item);
+            }
+        }
+        Command::Check => {
+            for error in service.inventory().validate() {
+                println!("{}", error);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn open_store(store_type: &str, path: &Path) -> Result<AnyStore, InventoryError> {
+    let store = match store_type.to_lowercase().as_str() {
+        "fjall" => AnyStore::open_fjall(path),
+        "rocks" | "rocksdb" => AnyStore::open_rocks(path),
+        _ => return Err(InventoryError::UnknownStoreType(store_type.to_string())),
+    };
+    store.map_err(|source| InventoryError::OpenStore {
+        store: store_type.to_string(),
+        path: path.to_path_buf(),
+        source,
+    })
+}
@@ -0,0 +1,104 @@
+//! `photo` subcommands.
+
+use std::path::{Path, PathBuf};
+
+use aldehyde_inventory::{ItemId, Photo};
+use clap::{Args, Subcommand};
+use polyepoxide_core::IngestPolicy;
+
+use crate::error::InventoryError;
+use crate::Service;
+
+#[derive(Subcommand)]
+pub enum PhotoCommand {
+    /// Attach an image file to an item and print the new root CID
+    Attach {
+        /// Id of the item
+        id: ItemId,
+
+        file: PathBuf,
+
+        /// MIME type; guessed from the file extension if not given
+        #[arg(long)]
+        mime_type: Option<String>,
+
+        #[command(flatten)]
+        screening: ScreeningArgs,
+    },
+}
+
+/// Checks a photo must pass before it is stored.
+#[derive(Args)]
+pub struct ScreeningArgs {
+    /// Largest file accepted, in bytes
+    #[arg(long)]
+    max_size: Option<usize>,
+
+    /// MIME type accepted, such as image/jpeg or image/*; may be repeated. Any type if not given
+    #[arg(long = "allow-type")]
+    allowed_types: Vec<String>,
+
+    /// Scanner command, split on whitespace; the file is piped to its stdin and exit status 0
+    /// means clean
+    #[arg(long)]
+    scanner: Option<String>,
+
+    /// Directory keeping files flagged by the scanner
+    #[arg(long, requires = "scanner")]
+    quarantine: Option<PathBuf>,
+}
+
+impl ScreeningArgs {
+    fn policy(self) -> IngestPolicy {
+        let mut policy = IngestPolicy::new().with_allowed_mime_types(self.allowed_types);
+        if let Some(max_size) = self.max_size {
+            policy = policy.with_max_size(max_size);
+        }
+        if let Some(scanner) = &self.scanner {
+            policy = policy.with_scanner(scanner.split_whitespace());
+        }
+        if let Some(dir) = self.quarantine {
+            policy = policy.with_quarantine_dir(dir);
+        }
+        policy
+    }
+}
+
+pub fn run(service: &mut Service, command: PhotoCommand) -> Result<(), InventoryError> {
+    match command {
+        PhotoCommand::Attach {
+            id,
+            file,
+            mime_type,
+            screening,
+        } => {
+            let data = std::fs::read(&file).map_err(|source| InventoryError::Read {
+                path: file.clone(),
+                source,
+            })?;
+            let filename = file
+                .file_name()
+                .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+            let mime_type = mime_type.unwrap_or_else(|| guess_mime_type(&file).to_string());
+            let photo = Photo::ingest(&screening.policy(), filename, mime_type, data)?;
+            println!("{}", service.attach_photo(&id, photo)?);
+        }
+    }
+    Ok(())
+}
+
+fn guess_mime_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "heic" => "image/heic",
+        "tif" | "tiff" => "image/tiff",
+        _ => "application/octet-stream",
+    }
+}
@@ -11,6 +11,8 @@ pub enum EventKind {
     PhotoAdded,
     ItemPlaced,
     ItemRemoved,
+    ItemLent { to: String },
+    ItemReturned,
 }
 
 /// A logged event
@@ -28,3 +30,38 @@ pub struct Event {
 pub struct EventLog {
     pub events: Vec<Bond<Event>>,
 }
+
+/// An item lent out and not yet returned
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loan {
+    pub item_id: ItemId,
+    pub to: String,
+    /// Timestamp of the lending event
+    pub since: u64,
+    pub note: Option<String>,
+}
+
+impl EventLog {
+    /// Returns the items currently lent out, in the order they were lent.
+    ///
+    /// Events not loaded are skipped.
+    pub fn loans(&self) -> Vec<Loan> {
+        let mut loans: Vec<Loan> = Vec::new();
+        for event in self.events.iter().filter_map(|b| b.value()) {
+            match &event.kind {
+                EventKind::ItemLent { to } => {
+                    loans.retain(|loan| loan.item_id != event.item_id);
+                    loans.push(Loan {
+                        item_id: event.item_id.clone(),
+                        to: to.clone(),
+                        since: event.timestamp,
+                        note: event.note.clone(),
+                    });
+                }
+                EventKind::ItemReturned => loans.retain(|loan| loan.item_id != event.item_id),
+                _ => {}
+            }
+        }
+        loans
+    }
+}
@@ -1,9 +1,12 @@
+use std::collections::HashSet;
+
 use aldehyde_core::Photo;
 use polyepoxide_core::{oxide, Bond};
 
 use crate::event::EventLog;
 use crate::item::{Item, ItemId};
-use crate::placement::PlacementMap;
+use crate::label::LabelRegistry;
+use crate::placement::{CycleError, PlacementMap};
 
 /// Association between an item and its photos
 #[oxide]
@@ -25,4 +28,116 @@ pub struct Inventory {
     pub placements: Bond<PlacementMap>,
     pub photos: Bond<PhotoRegistry>,
     pub events: Bond<EventLog>,
+    /// None until the first label is added
+    #[oxide(default)]
+    pub labels: Option<Bond<LabelRegistry>>,
+}
+
+/// A broken invariant of an inventory, found by [`Inventory::validate`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum IntegrityError {
+    #[error("more than one item has id {0}")]
+    DuplicateId(ItemId),
+    #[error("{0} is placed more than once")]
+    DuplicatePlacement(ItemId),
+    #[error("more than one label has code {0}")]
+    DuplicateLabel(String),
+    #[error("{referrer} refers to missing item {id}")]
+    Dangling { id: ItemId, referrer: &'static str },
+    #[error(transparent)]
+    Cycle(#[from] CycleError),
+}
+
+impl Inventory {
+    /// Returns the loaded items, in the order they were added.
+    pub fn items(&self) -> impl Iterator<Item = &Item> {
+        self.items.iter().filter_map(|b| b.value())
+    }
+
+    /// Returns the loaded items carrying `tag`.
+    pub fn tagged<'a>(&'a self, tag: &'a str) -> impl Iterator<Item = &'a Item> {
+        self.items().filter(move |item| item.has_tag(tag))
+    }
+
+    /// Reports duplicate ids and label codes, references to missing items
+    /// and placement cycles, each cycle once.
+    ///
+    /// Only loaded values are checked.
+    pub fn validate(&self) -> Vec<IntegrityError> {
+        let mut errors = Vec::new();
+        let mut ids = HashSet::new();
+        let mut unique = Vec::new();
+        for item in self.items() {
+            match ids.insert(&item.id) {
+                true => unique.push(&item.id),
+                false => errors.push(IntegrityError::DuplicateId(item.id.clone())),
+            }
+        }
+        let dangling = |id: &ItemId, referrer: &'static str| {
+            (!ids.contains(id)).then(|| IntegrityError::Dangling {
+                id: id.clone(),
+                referrer,
+            })
+        };
+
+        let placements = self.placements.value();
+        let mut placed = HashSet::new();
+        for placement in placements.iter().flat_map(|m| &m.placements) {
+            let Some(placement) = placement.value() else {
+                continue;
+            };
+            errors.extend(dangling(&placement.item_id, "a placement"));
+            if let Some(location) = &placement.location_id {
+                errors.extend(dangling(location, "the location of a placement"));
+            }
+            if !placed.insert(&placement.item_id) {
+                errors.push(IntegrityError::DuplicatePlacement(
+                    placement.item_id.clone(),
+                ));
+            }
+        }
+        for attachment in self.photos.value().iter().flat_map(|r| &r.attachments) {
+            if let Some(attachment) = attachment.value() {
+                errors.extend(dangling(&attachment.item_id, "a photo attachment"));
+            }
+        }
+        for event in self.events.value().iter().flat_map(|l| &l.events) {
+            if let Some(event) = event.value() {
+                errors.extend(dangling(&event.item_id, "an event"));
+                if let Some(target) = &event.target_id {
+                    errors.extend(dangling(target, "the target of an event"));
+                }
+            }
+        }
+        let mut codes = HashSet::new();
+        let labels = self.labels.iter().filter_map(|b| b.value());
+        for label in labels.flat_map(|r| &r.labels).filter_map(|b| b.value()) {
+            if let Some(item) = label.item.value() {
+                errors.extend(dangling(&item.id, "a label"));
+            }
+            if !codes.insert(&label.code) {
+                errors.push(IntegrityError::DuplicateLabel(label.code.clone()));
+            }
+        }
+
+        // Every item on a cycle fails `location_of` naming itself; the
+        // cycle is reported for its smallest id
+        if let Some(placements) = placements {
+            for id in unique {
+                if !matches!(placements.location_of(id), Err(CycleError(c)) if c == *id) {
+                    continue;
+                }
+                let mut smallest = id;
+                let mut current = placements.location(id);
+                while let Some(container) = current.filter(|c| *c != id) {
+                    smallest = smallest.min(container);
+                    current = placements.location(container);
+                }
+                if smallest == id {
+                    errors.push(IntegrityError::Cycle(CycleError(id.clone())));
+                }
+            }
+        }
+        errors
+    }
 }
@@ -1,12 +1,207 @@
-use polyepoxide_core::oxide;
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::fmt;
+use std::str::FromStr;
+
+use polyepoxide_core::{oxide, BondMapper, BondVisitor, Oxide, Structure};
+use serde::{Deserialize, Serialize};
 
 /// Stable identifier for items across versions (UUID format)
-pub type ItemId = String;
+///
+/// Encoded as a plain string, so values written with `String` ids still
+/// decode.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ItemId(String);
+
+impl ItemId {
+    pub fn new(id: impl Into<String>) -> Self {
+        Self(id.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Oxide for ItemId {
+    fn schema() -> Structure {
+        Structure::Unicode
+    }
+
+    fn visit_bonds(&self, _visitor: &mut dyn BondVisitor) {}
+
+    fn map_bonds(&self, _mapper: &mut impl BondMapper) -> Self {
+        self.clone()
+    }
+}
+
+impl fmt::Display for ItemId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl FromStr for ItemId {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::new(s))
+    }
+}
+
+impl From<String> for ItemId {
+    fn from(id: String) -> Self {
+        Self(id)
+    }
+}
+
+impl From<&str> for ItemId {
+    fn from(id: &str) -> Self {
+        Self::new(id)
+    }
+}
+
+impl PartialEq<str> for ItemId {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for ItemId {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+/// Value of a custom item attribute
+#[oxide]
+#[derive(PartialEq)]
+pub enum AttributeValue {
+    Text(String),
+    Integer(i64),
+    Number(f64),
+    Boolean(bool),
+}
+
+impl fmt::Display for AttributeValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AttributeValue::Text(text) => f.write_str(text),
+            AttributeValue::Integer(n) => write!(f, "{}", n),
+            AttributeValue::Number(n) => write!(f, "{}", n),
+            AttributeValue::Boolean(b) => write!(f, "{}", b),
+        }
+    }
+}
+
+/// Reads `true`/`false` and numbers as such, anything else as text.
+impl FromStr for AttributeValue {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(if let Ok(b) = s.parse() {
+            AttributeValue::Boolean(b)
+        } else if let Ok(n) = s.parse() {
+            AttributeValue::Integer(n)
+        } else if let Ok(n) = s.parse() {
+            AttributeValue::Number(n)
+        } else {
+            AttributeValue::Text(s.to_string())
+        })
+    }
+}
+
+/// Price paid for an item
+#[oxide]
+#[derive(PartialEq, Eq)]
+pub struct Price {
+    /// In hundredths of the currency unit, e.g. cents
+    pub amount: i64,
+    /// ISO 4217 code, e.g. "EUR"
+    pub currency: String,
+}
+
+impl fmt::Display for Price {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.amount < 0 { "-" } else { "" };
+        let amount = self.amount.unsigned_abs();
+        write!(
+            f,
+            "{}{}.{:02} {}",
+            sign,
+            amount / 100,
+            amount % 100,
+            self.currency
+        )
+    }
+}
 
 /// Core item in the inventory
+///
+/// Fields after `description` were added later and default when missing
+/// from stored items.
 #[oxide]
 pub struct Item {
     pub id: ItemId,
     pub name: String,
     pub description: Option<String>,
+    /// How many of the item there are, in `unit` if given
+    #[oxide(default)]
+    pub quantity: Option<f64>,
+    #[oxide(default)]
+    pub unit: Option<String>,
+    #[oxide(default)]
+    pub tags: Vec<String>,
+    #[oxide(default)]
+    pub acquired: Option<String>, // ISO 8601 date
+    #[oxide(default)]
+    pub price: Option<Price>,
+    #[oxide(default)]
+    pub attributes: BTreeMap<String, AttributeValue>,
+}
+
+impl Item {
+    pub fn new(id: impl Into<ItemId>, name: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            name: name.into(),
+            description: None,
+            quantity: None,
+            unit: None,
+            tags: Vec::new(),
+            acquired: None,
+            price: None,
+            attributes: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn with_quantity(mut self, quantity: f64, unit: Option<String>) -> Self {
+        self.quantity = Some(quantity);
+        self.unit = unit;
+        self
+    }
+
+    pub fn with_tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    pub fn with_attribute(mut self, key: impl Into<String>, value: AttributeValue) -> Self {
+        self.attributes.insert(key.into(), value);
+        self
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    pub fn attribute(&self, key: &str) -> Option<&AttributeValue> {
+        self.attributes.get(key)
+    }
 }
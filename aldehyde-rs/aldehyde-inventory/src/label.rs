@@ -0,0 +1,77 @@
+use std::fmt;
+use std::str::FromStr;
+
+use polyepoxide_core::{oxide, Bond};
+
+use crate::item::Item;
+
+/// Barcode or 2D code format of a printed label
+#[derive(Copy, PartialEq, Eq)]
+#[oxide]
+pub enum Symbology {
+    QrCode,
+    DataMatrix,
+    Code128,
+    Code39,
+    Ean13,
+    UpcA,
+}
+
+impl fmt::Display for Symbology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Symbology::QrCode => "qr",
+            Symbology::DataMatrix => "datamatrix",
+            Symbology::Code128 => "code128",
+            Symbology::Code39 => "code39",
+            Symbology::Ean13 => "ean13",
+            Symbology::UpcA => "upca",
+        })
+    }
+}
+
+/// Symbology name not known to [`Symbology::from_str`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("unknown symbology {0}, expected one of qr, datamatrix, code128, code39, ean13, upca")]
+pub struct UnknownSymbology(pub String);
+
+/// Parses the names printed by `Display`, ignoring case.
+impl FromStr for Symbology {
+    type Err = UnknownSymbology;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "qr" => Ok(Symbology::QrCode),
+            "datamatrix" => Ok(Symbology::DataMatrix),
+            "code128" => Ok(Symbology::Code128),
+            "code39" => Ok(Symbology::Code39),
+            "ean13" => Ok(Symbology::Ean13),
+            "upca" => Ok(Symbology::UpcA),
+            _ => Err(UnknownSymbology(s.to_string())),
+        }
+    }
+}
+
+/// A scannable code identifying an item
+#[oxide]
+pub struct Label {
+    pub code: String,
+    pub symbology: Symbology,
+    pub item: Bond<Item>,
+}
+
+/// All labels, each code used once
+#[oxide]
+pub struct LabelRegistry {
+    pub labels: Vec<Bond<Label>>,
+}
+
+impl LabelRegistry {
+    /// Returns the label with the given code. Labels not loaded are skipped.
+    pub fn find(&self, code: &str) -> Option<&Label> {
+        self.labels
+            .iter()
+            .filter_map(|b| b.value())
+            .find(|label| label.code == code)
+    }
+}
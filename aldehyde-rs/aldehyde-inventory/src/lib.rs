@@ -3,12 +3,16 @@
 pub mod event;
 pub mod inventory;
 pub mod item;
+pub mod label;
 pub mod placement;
+pub mod service;
 
-pub use event::{Event, EventKind, EventLog};
-pub use inventory::{Inventory, ItemPhotos, PhotoRegistry};
-pub use item::{Item, ItemId};
-pub use placement::{Placement, PlacementMap};
+pub use event::{Event, EventKind, EventLog, Loan};
+pub use inventory::{IntegrityError, Inventory, ItemPhotos, PhotoRegistry};
+pub use item::{AttributeValue, Item, ItemId, Price};
+pub use label::{Label, LabelRegistry, Symbology, UnknownSymbology};
+pub use placement::{CycleError, Placement, PlacementMap};
+pub use service::{InventoryService, ServiceError};
 
 // Re-export photo types from core
 pub use aldehyde_core::{ExifData, ExifTag, ExifValue, Photo};
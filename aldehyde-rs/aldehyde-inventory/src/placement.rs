@@ -1,3 +1,5 @@
+use std::collections::{HashMap, HashSet};
+
 use polyepoxide_core::{oxide, Bond};
 
 use crate::item::ItemId;
@@ -14,3 +16,83 @@ pub struct Placement {
 pub struct PlacementMap {
     pub placements: Vec<Bond<Placement>>,
 }
+
+/// Placements that lead from an item back to itself, found while walking
+/// the hierarchy.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("placements of {0} form a cycle")]
+pub struct CycleError(pub ItemId);
+
+impl PlacementMap {
+    /// Returns the container an item is placed in, None if it's top-level
+    /// or not placed.
+    ///
+    /// Placements not loaded are skipped, here and in the other queries.
+    pub fn location(&self, item_id: &ItemId) -> Option<&ItemId> {
+        self.loaded()
+            .find(|p| p.item_id == *item_id)
+            .and_then(|p| p.location_id.as_ref())
+    }
+
+    /// Returns the containers an item is in, innermost first, e.g. box,
+    /// shelf, room.
+    pub fn location_of(&self, item_id: &ItemId) -> Result<Vec<&ItemId>, CycleError> {
+        let mut chain: Vec<&ItemId> = Vec::new();
+        let mut current = self.location(item_id);
+        while let Some(container) = current {
+            if container == item_id || chain.contains(&container) {
+                return Err(CycleError(container.clone()));
+            }
+            chain.push(container);
+            current = self.location(container);
+        }
+        Ok(chain)
+    }
+
+    /// Returns the items placed in a container, and with `recursive` the
+    /// items inside those too, each followed by its own contents.
+    pub fn contents_of(
+        &self,
+        item_id: &ItemId,
+        recursive: bool,
+    ) -> Result<Vec<&ItemId>, CycleError> {
+        // As in `location`, only an item's first placement counts
+        let mut placed = HashSet::new();
+        let mut children: HashMap<&ItemId, Vec<&ItemId>> = HashMap::new();
+        for placement in self.loaded() {
+            if !placed.insert(&placement.item_id) {
+                continue;
+            }
+            if let Some(location) = &placement.location_id {
+                children
+                    .entry(location)
+                    .or_default()
+                    .push(&placement.item_id);
+            }
+        }
+
+        let mut contents = Vec::new();
+        let mut seen = HashSet::new();
+        let mut stack: Vec<&ItemId> = children
+            .get(item_id)
+            .into_iter()
+            .flatten()
+            .rev()
+            .copied()
+            .collect();
+        while let Some(item) = stack.pop() {
+            if item == item_id || !seen.insert(item) {
+                return Err(CycleError(item.clone()));
+            }
+            contents.push(item);
+            if recursive {
+                stack.extend(children.get(item).into_iter().flatten().rev().copied());
+            }
+        }
+        Ok(contents)
+    }
+
+    fn loaded(&self) -> impl Iterator<Item = &Placement> {
+        self.placements.iter().filter_map(|b| b.value())
+    }
+}
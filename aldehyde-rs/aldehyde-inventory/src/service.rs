@@ -0,0 +1,448 @@
+//! Inventory operations on a store, with the current root kept in a ref.
+//!
+//! Every mutation builds a new `Inventory` root sharing the unchanged parts
+//! of the previous one, persists it and moves the ref to it. Roots failing
+//! `Inventory::validate` are not written.
+//!
+//! Items are indexed by id and name as roots are persisted, so they can be
+//! found without comparing every item. An inventory stored before the
+//! indexes existed is indexed when opened.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use aldehyde_core::Photo;
+use polyepoxide_core::{
+    Bond, Cell, Cid, IndexError, IndexStore, Indexes, LoadError, Oxide, PersistError, RefStore,
+    Solvent,
+};
+
+use crate::event::{Event, EventKind, EventLog, Loan};
+use crate::inventory::{Inventory, ItemPhotos, PhotoRegistry};
+use crate::item::{Item, ItemId};
+use crate::label::{Label, LabelRegistry, Symbology};
+use crate::placement::{CycleError, Placement, PlacementMap};
+
+/// Levels loaded below the root: the collections and their entries. Photos
+/// stay in the store.
+const LOAD_DEPTH: usize = 2;
+
+/// Index of items by id.
+const ITEM_ID_INDEX: &str = "item-id";
+
+/// Index of items by name.
+const ITEM_NAME_INDEX: &str = "item-name";
+
+/// Error from an inventory operation.
+#[derive(Debug, thiserror::Error)]
+pub enum ServiceError<E> {
+    #[error("no item with id {0}")]
+    UnknownItem(ItemId),
+    #[error("an item with id {0} already exists")]
+    DuplicateItem(ItemId),
+    #[error("label {0} is already in use")]
+    DuplicateLabel(String),
+    #[error("{item} is already lent to {to}")]
+    AlreadyLent { item: ItemId, to: String },
+    #[error("{0} isn't lent out")]
+    NotLent(ItemId),
+    #[error("{item} can't be placed in {location}, which is inside it")]
+    Cycle { item: ItemId, location: ItemId },
+    #[error(transparent)]
+    Placements(#[from] CycleError),
+    #[error("ref {0:?} was moved by another writer")]
+    Conflict(String),
+    #[error(transparent)]
+    Load(#[from] LoadError<E>),
+    #[error(transparent)]
+    Persist(#[from] PersistError<E>),
+    #[error(transparent)]
+    Index(#[from] IndexError<E>),
+    #[error("store error: {0}")]
+    Store(E),
+}
+
+/// An inventory whose root is kept in a ref of `store`.
+pub struct InventoryService<S> {
+    store: S,
+    solvent: Solvent,
+    indexes: Indexes,
+    head_ref: String,
+    root: Arc<Cell<Inventory>>,
+    /// What the ref pointed to when last read or moved; the empty inventory
+    /// isn't persisted until it changes.
+    head: Option<Cid>,
+}
+
+impl<S: RefStore + IndexStore> InventoryService<S> {
+    /// Opens the inventory `head_ref` points to, or an empty one if it's unset.
+    pub fn open(store: S, head_ref: impl Into<String>) -> Result<Self, ServiceError<S::Error>> {
+        let head_ref = head_ref.into();
+        let mut solvent = Solvent::new();
+        solvent.add_validator(|inventory: &Inventory| {
+            let errors: Vec<String> = inventory.validate().iter().map(|e| e.to_string()).collect();
+            match errors.is_empty() {
+                true => Ok(()),
+                false => Err(errors.join("; ")),
+            }
+        });
+        let mut indexes = Indexes::new();
+        indexes
+            .define::<Item>(ITEM_ID_INDEX, "id")
+            .expect("items have an id");
+        indexes
+            .define::<Item>(ITEM_NAME_INDEX, "name")
+            .expect("items have a name");
+        let head = store.get_ref(&head_ref).map_err(ServiceError::Store)?;
+        let root = match &head {
+            Some(cid) => solvent.load(cid, &store, LOAD_DEPTH)?,
+            None => {
+                let empty = Inventory {
+                    items: Vec::new(),
+                    placements: solvent.bond(PlacementMap {
+                        placements: Vec::new(),
+                    }),
+                    photos: solvent.bond(PhotoRegistry {
+                        attachments: Vec::new(),
+                    }),
+                    events: solvent.bond(EventLog { events: Vec::new() }),
+                    labels: None,
+                };
+                solvent.add(empty)
+            }
+        };
+        // Indexing is checked on one item, as a whole root is indexed at once
+        if let Some(first) = root.value().items.first().and_then(Bond::value) {
+            let indexed = indexes.find(&store, ITEM_ID_INDEX, first.id.as_str())?;
+            if indexed.is_empty() {
+                indexes.index_cell(&root, &store)?;
+            }
+        }
+        Ok(Self {
+            store,
+            solvent,
+            indexes,
+            head_ref,
+            root,
+            head,
+        })
+    }
+
+    /// Returns the current root.
+    pub fn inventory(&self) -> &Inventory {
+        self.root.value()
+    }
+
+    /// Returns the CID of the current root, which changes with every mutation.
+    pub fn snapshot(&self) -> Cid {
+        self.root.cid()
+    }
+
+    /// Returns the item with the given id.
+    pub fn item(&self, id: &ItemId) -> Option<&Item> {
+        self.inventory()
+            .items
+            .iter()
+            .map(loaded)
+            .find(|item| item.id == *id)
+    }
+
+    /// Returns the item with the given id, looked up in the id index.
+    pub fn find_by_id(&self, id: &ItemId) -> Result<Option<&Item>, ServiceError<S::Error>> {
+        let cids = self.indexes.find(&self.store, ITEM_ID_INDEX, id.as_str())?;
+        Ok(self.current(&cids).into_iter().next())
+    }
+
+    /// Returns the items named exactly `name`, looked up in the name index.
+    pub fn find_by_name(&self, name: &str) -> Result<Vec<&Item>, ServiceError<S::Error>> {
+        let cids = self.indexes.find(&self.store, ITEM_NAME_INDEX, name)?;
+        Ok(self.current(&cids))
+    }
+
+    /// Returns the container an item is placed in, None if it's top-level.
+    pub fn location(&self, id: &ItemId) -> Option<&ItemId> {
+        loaded(&self.inventory().placements).location(id)
+    }
+
+    /// Returns the containers an item is in, innermost first.
+    pub fn location_of(&self, id: &ItemId) -> Result<Vec<&Item>, ServiceError<S::Error>> {
+        self.require(id)?;
+        let chain = loaded(&self.inventory().placements).location_of(id)?;
+        self.items_of(chain)
+    }
+
+    /// Returns the items placed in a container, and with `recursive` the
+    /// items inside those too, each followed by its own contents.
+    pub fn contents_of(
+        &self,
+        id: &ItemId,
+        recursive: bool,
+    ) -> Result<Vec<&Item>, ServiceError<S::Error>> {
+        self.require(id)?;
+        let contents = loaded(&self.inventory().placements).contents_of(id, recursive)?;
+        self.items_of(contents)
+    }
+
+    /// Returns the item a scanned code identifies.
+    pub fn find_by_label(&self, code: &str) -> Option<&Item> {
+        let label = loaded(self.inventory().labels.as_ref()?).find(code)?;
+        self.inventory()
+            .items
+            .iter()
+            .find(|b| b.cid() == label.item.cid())
+            .map(loaded)
+    }
+
+    /// Returns the labels of an item.
+    pub fn labels_of(&self, id: &ItemId) -> Vec<&Label> {
+        let (Some(registry), Some(item)) = (&self.inventory().labels, self.item_bond(id)) else {
+            return Vec::new();
+        };
+        loaded(registry)
+            .labels
+            .iter()
+            .map(loaded)
+            .filter(|label| label.item.cid() == item.cid())
+            .collect()
+    }
+
+    /// Returns the items currently lent out, in the order they were lent.
+    pub fn loans(&self) -> Vec<Loan> {
+        loaded(&self.inventory().events).loans()
+    }
+
+    /// Returns the photos attached to an item, loading them without their
+    /// content.
+    pub fn photos(&mut self, id: &ItemId) -> Result<Vec<Arc<Cell<Photo>>>, ServiceError<S::Error>> {
+        let cids: Vec<Cid> = loaded(&self.inventory().photos)
+            .attachments
+            .iter()
+            .map(loaded)
+            .filter(|a| a.item_id == *id)
+            .flat_map(|a| a.photos.iter().map(Bond::cid))
+            .collect();
+        cids.iter()
+            .map(|cid| {
+                self.solvent
+                    .load(cid, &self.store, 0)
+                    .map_err(ServiceError::from)
+            })
+            .collect()
+    }
+
+    /// Adds a top-level item, logging its creation.
+    pub fn add_item(&mut self, item: Item) -> Result<Cid, ServiceError<S::Error>> {
+        if self.item(&item.id).is_some() {
+            return Err(ServiceError::DuplicateItem(item.id));
+        }
+        let mut root = self.inventory().clone();
+        let event = new_event(&item.id, EventKind::ItemCreated, None);
+        root.items.push(self.solvent.bond(item));
+        self.append_event(&mut root, event);
+        self.commit(root)
+    }
+
+    /// Places an item inside `location`, or at the top level if None,
+    /// logging the move.
+    pub fn move_item(
+        &mut self,
+        id: &ItemId,
+        location: Option<ItemId>,
+    ) -> Result<Cid, ServiceError<S::Error>> {
+        self.require(id)?;
+        if let Some(location) = &location {
+            self.require(location)?;
+            let placements = loaded(&self.inventory().placements);
+            if location == id || placements.location_of(location)?.contains(&id) {
+                return Err(ServiceError::Cycle {
+                    item: id.clone(),
+                    location: location.clone(),
+                });
+            }
+        }
+
+        let mut root = self.inventory().clone();
+        let mut map = loaded(&root.placements).clone();
+        map.placements.retain(|p| loaded(p).item_id != *id);
+        map.placements.push(self.solvent.bond(Placement {
+            item_id: id.clone(),
+            location_id: location.clone(),
+        }));
+        root.placements = self.solvent.bond(map);
+        self.append_event(&mut root, new_event(id, EventKind::ItemPlaced, location));
+        self.commit(root)
+    }
+
+    /// Attaches a photo to an item, logging it.
+    pub fn attach_photo(
+        &mut self,
+        id: &ItemId,
+        photo: Photo,
+    ) -> Result<Cid, ServiceError<S::Error>> {
+        self.require(id)?;
+        let mut root = self.inventory().clone();
+        let mut registry = loaded(&root.photos).clone();
+        let photo = self.solvent.bond(photo);
+        match registry
+            .attachments
+            .iter()
+            .position(|a| loaded(a).item_id == *id)
+        {
+            Some(i) => {
+                let mut attachment = loaded(&registry.attachments[i]).clone();
+                attachment.photos.push(photo);
+                registry.attachments[i] = self.solvent.bond(attachment);
+            }
+            None => registry.attachments.push(self.solvent.bond(ItemPhotos {
+                item_id: id.clone(),
+                photos: vec![photo],
+            })),
+        }
+        root.photos = self.solvent.bond(registry);
+        self.append_event(&mut root, new_event(id, EventKind::PhotoAdded, None));
+        self.commit(root)
+    }
+
+    /// Labels an item with a code, which must not be in use yet.
+    pub fn add_label(
+        &mut self,
+        id: &ItemId,
+        code: impl Into<String>,
+        symbology: Symbology,
+    ) -> Result<Cid, ServiceError<S::Error>> {
+        let code = code.into();
+        let Some(item) = self.item_bond(id).cloned() else {
+            return Err(ServiceError::UnknownItem(id.clone()));
+        };
+        if self.find_by_label(&code).is_some() {
+            return Err(ServiceError::DuplicateLabel(code));
+        }
+        let mut root = self.inventory().clone();
+        let mut registry = match &root.labels {
+            Some(registry) => loaded(registry).clone(),
+            None => LabelRegistry { labels: Vec::new() },
+        };
+        registry.labels.push(self.solvent.bond(Label {
+            code,
+            symbology,
+            item,
+        }));
+        root.labels = Some(self.solvent.bond(registry));
+        self.commit(root)
+    }
+
+    /// Logs an item as lent to someone.
+    pub fn lend(
+        &mut self,
+        id: &ItemId,
+        to: impl Into<String>,
+        note: Option<String>,
+    ) -> Result<Cid, ServiceError<S::Error>> {
+        self.require(id)?;
+        if let Some(loan) = self.loans().into_iter().find(|loan| loan.item_id == *id) {
+            return Err(ServiceError::AlreadyLent {
+                item: loan.item_id,
+                to: loan.to,
+            });
+        }
+        let mut root = self.inventory().clone();
+        let mut event = new_event(id, EventKind::ItemLent { to: to.into() }, None);
+        event.note = note;
+        self.append_event(&mut root, event);
+        self.commit(root)
+    }
+
+    /// Logs a lent item as returned.
+    pub fn return_item(&mut self, id: &ItemId) -> Result<Cid, ServiceError<S::Error>> {
+        self.require(id)?;
+        if !self.loans().iter().any(|loan| loan.item_id == *id) {
+            return Err(ServiceError::NotLent(id.clone()));
+        }
+        let mut root = self.inventory().clone();
+        self.append_event(&mut root, new_event(id, EventKind::ItemReturned, None));
+        self.commit(root)
+    }
+
+    /// Appends an event to the log.
+    pub fn log_event(&mut self, event: Event) -> Result<Cid, ServiceError<S::Error>> {
+        let mut root = self.inventory().clone();
+        self.append_event(&mut root, event);
+        self.commit(root)
+    }
+
+    fn require(&self, id: &ItemId) -> Result<(), ServiceError<S::Error>> {
+        match self.item(id) {
+            Some(_) => Ok(()),
+            None => Err(ServiceError::UnknownItem(id.clone())),
+        }
+    }
+
+    fn item_bond(&self, id: &ItemId) -> Option<&Bond<Item>> {
+        self.inventory().items.iter().find(|b| loaded(b).id == *id)
+    }
+
+    /// Keeps the items of the current root among stored ones; indexes also
+    /// point to the versions earlier roots held.
+    fn current(&self, cids: &[Cid]) -> Vec<&Item> {
+        let cids: HashSet<&Cid> = cids.iter().collect();
+        self.inventory()
+            .items
+            .iter()
+            .filter(|bond| cids.contains(&bond.cid()))
+            .map(loaded)
+            .collect()
+    }
+
+    fn items_of(&self, ids: Vec<&ItemId>) -> Result<Vec<&Item>, ServiceError<S::Error>> {
+        ids.into_iter()
+            .map(|id| {
+                self.item(id)
+                    .ok_or_else(|| ServiceError::UnknownItem(id.clone()))
+            })
+            .collect()
+    }
+
+    fn append_event(&mut self, root: &mut Inventory, event: Event) {
+        let mut log = loaded(&root.events).clone();
+        log.events.push(self.solvent.bond(event));
+        root.events = self.solvent.bond(log);
+    }
+
+    fn commit(&mut self, root: Inventory) -> Result<Cid, ServiceError<S::Error>> {
+        let cell = self.solvent.add(root);
+        self.indexes
+            .persist_cell(&self.solvent, &cell, &self.store)?;
+        let cid = cell.cid();
+        let moved = self
+            .store
+            .compare_and_set_ref(&self.head_ref, self.head.as_ref(), &cid)
+            .map_err(ServiceError::Store)?;
+        if !moved {
+            return Err(ServiceError::Conflict(self.head_ref.clone()));
+        }
+        self.head = Some(cid);
+        self.root = cell;
+        Ok(cid)
+    }
+}
+
+/// Values down to `LOAD_DEPTH` are loaded on open, and new ones are bonded
+/// resolved.
+fn loaded<T: Oxide>(bond: &Bond<T>) -> &T {
+    bond.value()
+        .expect("inventory entries should be loaded with the root")
+}
+
+fn new_event(id: &ItemId, kind: EventKind, target_id: Option<ItemId>) -> Event {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64);
+    Event {
+        item_id: id.clone(),
+        kind,
+        timestamp,
+        target_id,
+        note: None,
+    }
+}
@@ -4,18 +4,14 @@ use aldehyde_inventory::{
     Event, EventKind, EventLog, ExifData, ExifTag, ExifValue, Inventory, Item, Photo,
     PhotoRegistry, Placement, PlacementMap,
 };
-use polyepoxide_core::{Bond, ByteString, Oxide, Solvent};
+use polyepoxide_core::{Blob, Bond, Oxide, Solvent};
 use std::sync::Arc;
 
 #[test]
 fn create_item() {
     let mut solvent = Solvent::new();
 
-    let item = Item {
-        id: "item-001".to_string(),
-        name: "Blue Widget".to_string(),
-        description: Some("A small blue widget".to_string()),
-    };
+    let item = Item::new("item-001", "Blue Widget").with_description("A small blue widget");
 
     let cell = solvent.add(item);
     assert_eq!(cell.value().name, "Blue Widget");
@@ -27,7 +23,7 @@ fn create_item() {
 fn create_photo_with_content() {
     let mut solvent = Solvent::new();
 
-    let content = ByteString::new(vec![0x89, 0x50, 0x4E, 0x47]); // PNG magic bytes
+    let content = Blob::new(&[0x89, 0x50, 0x4E, 0x47]); // PNG magic bytes
     let content_cell = solvent.add(content);
 
     let photo = Photo {
@@ -78,7 +74,7 @@ fn create_photo_with_exif() {
     };
     let exif_cell = solvent.add(exif);
 
-    let content = ByteString::new(vec![0xFF, 0xD8, 0xFF]); // JPEG magic
+    let content = Blob::new(&[0xFF, 0xD8, 0xFF]); // JPEG magic
     let content_cell = solvent.add(content);
 
     let photo = Photo {
@@ -106,21 +102,9 @@ fn placement_hierarchy() {
     let mut solvent = Solvent::new();
 
     // Room -> Shelf -> Box hierarchy
-    let room = Item {
-        id: "room-001".to_string(),
-        name: "Storage Room".to_string(),
-        description: None,
-    };
-    let shelf = Item {
-        id: "shelf-001".to_string(),
-        name: "Metal Shelf".to_string(),
-        description: None,
-    };
-    let box_item = Item {
-        id: "box-001".to_string(),
-        name: "Cardboard Box".to_string(),
-        description: Some("Contains electronics".to_string()),
-    };
+    let room = Item::new("room-001", "Storage Room");
+    let shelf = Item::new("shelf-001", "Metal Shelf");
+    let box_item = Item::new("box-001", "Cardboard Box").with_description("Contains electronics");
 
     let _room_cell = solvent.add(room);
     let _shelf_cell = solvent.add(shelf);
@@ -128,17 +112,17 @@ fn placement_hierarchy() {
 
     // Shelf is inside Room
     let shelf_placement = Placement {
-        item_id: "shelf-001".to_string(),
-        location_id: Some("room-001".to_string()),
+        item_id: "shelf-001".into(),
+        location_id: Some("room-001".into()),
     };
     // Box is on Shelf
     let box_placement = Placement {
-        item_id: "box-001".to_string(),
-        location_id: Some("shelf-001".to_string()),
+        item_id: "box-001".into(),
+        location_id: Some("shelf-001".into()),
     };
     // Room is top-level
     let room_placement = Placement {
-        item_id: "room-001".to_string(),
+        item_id: "room-001".into(),
         location_id: None,
     };
 
@@ -163,7 +147,7 @@ fn event_logging() {
     let mut solvent = Solvent::new();
 
     let create_event = Event {
-        item_id: "item-001".to_string(),
+        item_id: "item-001".into(),
         kind: EventKind::ItemCreated,
         timestamp: 1704067200000, // 2024-01-01 00:00:00 UTC
         target_id: None,
@@ -171,7 +155,7 @@ fn event_logging() {
     };
 
     let scan_event = Event {
-        item_id: "item-001".to_string(),
+        item_id: "item-001".into(),
         kind: EventKind::ItemScanned,
         timestamp: 1704153600000, // 2024-01-02 00:00:00 UTC
         target_id: None,
@@ -179,10 +163,10 @@ fn event_logging() {
     };
 
     let place_event = Event {
-        item_id: "item-001".to_string(),
+        item_id: "item-001".into(),
         kind: EventKind::ItemPlaced,
         timestamp: 1704240000000, // 2024-01-03 00:00:00 UTC
-        target_id: Some("shelf-001".to_string()),
+        target_id: Some("shelf-001".into()),
         note: None,
     };
 
@@ -218,27 +202,19 @@ fn full_inventory() {
     let mut solvent = Solvent::new();
 
     // Create items
-    let item1 = Item {
-        id: "item-001".to_string(),
-        name: "Laptop".to_string(),
-        description: Some("Work laptop".to_string()),
-    };
-    let item2 = Item {
-        id: "item-002".to_string(),
-        name: "Desk".to_string(),
-        description: None,
-    };
+    let item1 = Item::new("item-001", "Laptop").with_description("Work laptop");
+    let item2 = Item::new("item-002", "Desk");
 
     let item1_cell = solvent.add(item1);
     let item2_cell = solvent.add(item2);
 
     // Create placements
     let p1 = Placement {
-        item_id: "item-001".to_string(),
-        location_id: Some("item-002".to_string()), // Laptop on desk
+        item_id: "item-001".into(),
+        location_id: Some("item-002".into()), // Laptop on desk
     };
     let p2 = Placement {
-        item_id: "item-002".to_string(),
+        item_id: "item-002".into(),
         location_id: None, // Desk is top-level
     };
 
@@ -258,7 +234,7 @@ fn full_inventory() {
 
     // Create event log
     let event = Event {
-        item_id: "item-001".to_string(),
+        item_id: "item-001".into(),
         kind: EventKind::ItemCreated,
         timestamp: 1704067200000,
         target_id: None,
@@ -280,6 +256,7 @@ fn full_inventory() {
         placements: Bond::from_cell(placements_cell),
         photos: Bond::from_cell(photos_cell),
         events: Bond::from_cell(events_cell),
+        labels: None,
     };
 
     let inventory_cell = solvent.add(inventory);
@@ -291,22 +268,14 @@ fn full_inventory() {
     assert!(inventory_cell.value().events.is_resolved());
 
     // Content-addressed: same item should have same CID
-    let item1_dup = Item {
-        id: "item-001".to_string(),
-        name: "Laptop".to_string(),
-        description: Some("Work laptop".to_string()),
-    };
+    let item1_dup = Item::new("item-001", "Laptop").with_description("Work laptop");
     let item1_dup_cell = solvent.add(item1_dup);
     assert_eq!(item1_cell.cid(), item1_dup_cell.cid());
 }
 
 #[test]
 fn serialization_roundtrip() {
-    let item = Item {
-        id: "test-item".to_string(),
-        name: "Test Item".to_string(),
-        description: Some("A test".to_string()),
-    };
+    let item = Item::new("test-item", "Test Item").with_description("A test");
 
     let bytes = item.to_bytes();
     let restored: Item = Item::from_bytes(&bytes).unwrap();
@@ -332,10 +301,349 @@ fn schema_generation() {
 
     let event_kind_schema = EventKind::schema();
     match &event_kind_schema {
-        Structure::Enum(variants) => {
-            assert!(variants.contains(&"ItemCreated".to_string()));
-            assert!(variants.contains(&"ItemScanned".to_string()));
+        Structure::Tagged(variants) => {
+            assert!(variants.contains_key("ItemCreated"));
+            assert!(variants.contains_key("ItemScanned"));
+            assert!(variants.contains_key("ItemLent"));
         }
-        _ => panic!("Expected Enum structure for EventKind"),
+        _ => panic!("Expected Tagged structure for EventKind"),
+    }
+}
+
+#[test]
+fn service_mutations_persist_new_roots() {
+    use aldehyde_inventory::{InventoryService, ItemId, ServiceError};
+    use polyepoxide_core::{IngestPolicy, MemoryStore, RefStore};
+
+    let store = MemoryStore::new();
+    let mut service = InventoryService::open(&store, "inventory/head").unwrap();
+    let empty = service.snapshot();
+
+    let [room, boxed, lamp] = ["room", "box", "lamp"].map(ItemId::from);
+    for (id, name) in [(&room, "Storage Room"), (&boxed, "Box"), (&lamp, "Lamp")] {
+        service.add_item(Item::new(id.clone(), name)).unwrap();
+    }
+    service.move_item(&boxed, Some(room.clone())).unwrap();
+    service.move_item(&lamp, Some(boxed.clone())).unwrap();
+    assert!(matches!(
+        service.move_item(&room, Some(lamp.clone())),
+        Err(ServiceError::Cycle { .. })
+    ));
+    let photo = Photo::ingest(
+        &IngestPolicy::new(),
+        "lamp.jpg",
+        "image/jpeg",
+        vec![0xFF; 16],
+    )
+    .unwrap();
+    let head = service.attach_photo(&lamp, photo).unwrap();
+    assert_ne!(head, empty);
+    assert_eq!(store.get_ref("inventory/head").unwrap(), Some(head));
+
+    let reopened = InventoryService::open(&store, "inventory/head").unwrap();
+    assert_eq!(reopened.snapshot(), head);
+    assert_eq!(reopened.location(&lamp), Some(&boxed));
+    assert_eq!(reopened.location(&room), None);
+    let inventory = reopened.inventory();
+    assert_eq!(inventory.items.len(), 3);
+    assert_eq!(inventory.photos.value().unwrap().attachments.len(), 1);
+    let kinds: Vec<_> = inventory
+        .events
+        .value()
+        .unwrap()
+        .events
+        .iter()
+        .map(|e| e.value().unwrap().kind.clone())
+        .collect();
+    assert_eq!(kinds.len(), 6);
+    assert_eq!(kinds[5], EventKind::PhotoAdded);
+}
+
+#[test]
+fn placement_queries() {
+    use aldehyde_inventory::{CycleError, ItemId};
+
+    let mut solvent = Solvent::new();
+    let mut map = |pairs: &[(&str, Option<&str>)]| PlacementMap {
+        placements: pairs
+            .iter()
+            .map(|(item, location)| {
+                solvent.bond(Placement {
+                    item_id: (*item).into(),
+                    location_id: location.map(ItemId::from),
+                })
+            })
+            .collect(),
+    };
+    let id = |id: &str| ItemId::from(id);
+
+    let house = map(&[
+        ("room", None),
+        ("shelf", Some("room")),
+        ("box", Some("shelf")),
+        ("lamp", Some("room")),
+        ("cable", Some("box")),
+    ]);
+    assert_eq!(
+        house.location_of(&id("cable")).unwrap(),
+        ["box", "shelf", "room"]
+    );
+    assert!(house.location_of(&id("room")).unwrap().is_empty());
+    assert_eq!(
+        house.contents_of(&id("room"), false).unwrap(),
+        ["shelf", "lamp"]
+    );
+    assert_eq!(
+        house.contents_of(&id("room"), true).unwrap(),
+        ["shelf", "box", "cable", "lamp"]
+    );
+
+    let looped = map(&[("a", Some("b")), ("b", Some("c")), ("c", Some("a"))]);
+    assert_eq!(looped.location_of(&id("a")), Err(CycleError(id("a"))));
+    assert!(looped.contents_of(&id("a"), true).is_err());
+    assert_eq!(looped.contents_of(&id("a"), false).unwrap(), ["c"]);
+}
+
+#[test]
+fn validate_reports_broken_references() {
+    use aldehyde_inventory::{CycleError, IntegrityError};
+
+    let mut solvent = Solvent::new();
+    let items = ["a", "b", "a"].map(|id| solvent.bond(Item::new(id, id)));
+    let placements: Vec<_> = [("a", "b"), ("b", "a"), ("c", "a")]
+        .iter()
+        .map(|(item, location)| {
+            solvent.bond(Placement {
+                item_id: (*item).into(),
+                location_id: Some((*location).into()),
+            })
+        })
+        .collect();
+    let event = solvent.bond(Event {
+        item_id: "d".into(),
+        kind: EventKind::ItemScanned,
+        timestamp: 1704067200000,
+        target_id: None,
+        note: None,
+    });
+    let inventory = Inventory {
+        items: items.into(),
+        placements: solvent.bond(PlacementMap { placements }),
+        photos: solvent.bond(PhotoRegistry {
+            attachments: vec![],
+        }),
+        events: solvent.bond(EventLog {
+            events: vec![event],
+        }),
+        labels: None,
+    };
+
+    assert_eq!(
+        inventory.validate(),
+        [
+            IntegrityError::DuplicateId("a".into()),
+            IntegrityError::Dangling {
+                id: "c".into(),
+                referrer: "a placement"
+            },
+            IntegrityError::Dangling {
+                id: "d".into(),
+                referrer: "an event"
+            },
+            IntegrityError::Cycle(CycleError("a".into())),
+        ]
+    );
+}
+
+#[test]
+fn item_details_and_queries() {
+    use aldehyde_inventory::{AttributeValue, Price};
+    use polyepoxide_core::oxide;
+
+    // Items as stored before quantities, tags and attributes were added
+    #[oxide]
+    struct OldItem {
+        id: String,
+        name: String,
+        description: Option<String>,
+    }
+    let old = OldItem {
+        id: "cable".to_string(),
+        name: "HDMI Cable".to_string(),
+        description: None,
+    };
+    let cable = Item::from_bytes(&old.to_bytes())
+        .unwrap()
+        .with_quantity(3.0, None)
+        .with_tag("electronics")
+        .with_attribute("length_m", "1.5".parse().unwrap());
+    assert_eq!(cable.tags, ["electronics"]);
+    assert_eq!(
+        cable.attribute("length_m"),
+        Some(&AttributeValue::Number(1.5))
+    );
+
+    let mut tv = Item::new("tv", "Television")
+        .with_tag("electronics")
+        .with_attribute("category", "living room".parse().unwrap());
+    tv.price = Some(Price {
+        amount: 49999,
+        currency: "EUR".to_string(),
+    });
+    let tv = Item::from_bytes(&tv.to_bytes()).unwrap();
+    assert_eq!(tv.price.as_ref().unwrap().to_string(), "499.99 EUR");
+
+    let mut solvent = Solvent::new();
+    let inventory = Inventory {
+        items: vec![
+            solvent.bond(cable),
+            solvent.bond(tv),
+            solvent.bond(Item::new("sofa", "Sofa")),
+        ],
+        placements: solvent.bond(PlacementMap { placements: vec![] }),
+        photos: solvent.bond(PhotoRegistry {
+            attachments: vec![],
+        }),
+        events: solvent.bond(EventLog { events: vec![] }),
+        labels: None,
+    };
+    let tagged: Vec<_> = inventory.tagged("electronics").map(|i| &i.id).collect();
+    assert_eq!(tagged, ["cable", "tv"]);
+    let category = AttributeValue::Text("living room".to_string());
+    let ids: Vec<_> = inventory
+        .items()
+        .filter(|i| i.attribute("category") == Some(&category))
+        .map(|i| &i.id)
+        .collect();
+    assert_eq!(ids, ["tv"]);
+}
+
+#[test]
+fn labels_identify_items() {
+    use aldehyde_inventory::{InventoryService, ItemId, ServiceError, Symbology};
+    use polyepoxide_core::MemoryStore;
+
+    let store = MemoryStore::new();
+    let mut service = InventoryService::open(&store, "inventory/head").unwrap();
+    let [shelf, boxed] = ["shelf", "box"].map(ItemId::from);
+    service.add_item(Item::new(shelf.clone(), "Shelf")).unwrap();
+    service.add_item(Item::new(boxed.clone(), "Box")).unwrap();
+    service.move_item(&boxed, Some(shelf.clone())).unwrap();
+    service
+        .add_label(&boxed, "BOX-0042", Symbology::QrCode)
+        .unwrap();
+    service
+        .add_label(&boxed, "4006381333931", "EAN13".parse().unwrap())
+        .unwrap();
+    assert!(matches!(
+        service.add_label(&shelf, "BOX-0042", Symbology::Code128),
+        Err(ServiceError::DuplicateLabel(_))
+    ));
+    assert!(matches!(
+        service.add_label(&"lamp".into(), "LAMP-1", Symbology::QrCode),
+        Err(ServiceError::UnknownItem(_))
+    ));
+
+    let reopened = InventoryService::open(&store, "inventory/head").unwrap();
+    let found = reopened.find_by_label("BOX-0042").unwrap();
+    assert_eq!(found.id, boxed);
+    assert_eq!(reopened.location(&found.id), Some(&shelf));
+    assert!(reopened.find_by_label("BOX-0043").is_none());
+    let labels: Vec<_> = reopened
+        .labels_of(&boxed)
+        .iter()
+        .map(|l| format!("{} {}", l.symbology, l.code))
+        .collect();
+    assert_eq!(labels, ["qr BOX-0042", "ean13 4006381333931"]);
+    assert!(reopened.labels_of(&shelf).is_empty());
+}
+
+#[test]
+fn loans_follow_lend_and_return_events() {
+    use aldehyde_inventory::{InventoryService, ItemId, ServiceError};
+    use polyepoxide_core::MemoryStore;
+
+    let store = MemoryStore::new();
+    let mut service = InventoryService::open(&store, "inventory/head").unwrap();
+    let [drill, ladder, saw] = ["drill", "ladder", "saw"].map(ItemId::from);
+    for id in [&drill, &ladder, &saw] {
+        service
+            .add_item(Item::new(id.clone(), id.as_str()))
+            .unwrap();
+    }
+
+    service.lend(&drill, "Alice", None).unwrap();
+    service
+        .lend(&ladder, "Bob", Some("for the gutters".to_string()))
+        .unwrap();
+    assert!(matches!(
+        service.lend(&drill, "Bob", None),
+        Err(ServiceError::AlreadyLent { to, .. }) if to == "Alice"
+    ));
+    assert!(matches!(
+        service.return_item(&saw),
+        Err(ServiceError::NotLent(_))
+    ));
+    service.return_item(&drill).unwrap();
+    service.lend(&drill, "Carol", None).unwrap();
+
+    let reopened = InventoryService::open(&store, "inventory/head").unwrap();
+    let loans = reopened.loans();
+    let lent: Vec<_> = loans
+        .iter()
+        .map(|loan| (loan.item_id.as_str(), loan.to.as_str()))
+        .collect();
+    assert_eq!(lent, [("ladder", "Bob"), ("drill", "Carol")]);
+    assert_eq!(loans[0].note.as_deref(), Some("for the gutters"));
+    let kinds: Vec<_> = reopened.inventory().events.value().unwrap().events[3..]
+        .iter()
+        .map(|e| e.value().unwrap().kind.clone())
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            EventKind::ItemLent {
+                to: "Alice".to_string()
+            },
+            EventKind::ItemLent {
+                to: "Bob".to_string()
+            },
+            EventKind::ItemReturned,
+            EventKind::ItemLent {
+                to: "Carol".to_string()
+            },
+        ]
+    );
+}
+
+#[test]
+fn service_finds_items_through_indexes() {
+    use aldehyde_inventory::{InventoryService, ItemId};
+    use polyepoxide_core::{IndexStore, MemoryStore};
+
+    let store = MemoryStore::new();
+    let mut service = InventoryService::open(&store, "inventory/head").unwrap();
+    let [left, right, lamp] = ["left", "right", "lamp"].map(ItemId::from);
+    for (id, name) in [(&left, "Glove"), (&right, "Glove"), (&lamp, "Lamp")] {
+        service.add_item(Item::new(id.clone(), name)).unwrap();
+    }
+
+    let gloves: Vec<_> = service
+        .find_by_name("Glove")
+        .unwrap()
+        .iter()
+        .map(|item| item.id.as_str())
+        .collect();
+    assert_eq!(gloves, ["left", "right"]);
+    assert!(service.find_by_name("glove").unwrap().is_empty());
+    assert_eq!(service.find_by_id(&lamp).unwrap().unwrap().name, "Lamp");
+    assert!(service.find_by_id(&"desk".into()).unwrap().is_none());
+
+    // An inventory stored without index entries is indexed when opened
+    for (name, _) in store.list_index_entries("").unwrap() {
+        store.delete_index_entry(&name).unwrap();
     }
+    let reopened = InventoryService::open(&store, "inventory/head").unwrap();
+    assert_eq!(reopened.find_by_id(&lamp).unwrap().unwrap().name, "Lamp");
+    assert_eq!(reopened.find_by_name("Glove").unwrap().len(), 2);
 }
@@ -0,0 +1,236 @@
+//! A store whose backend, fjall or RocksDB, is chosen at runtime, such as
+//! from a command line flag or a config file.
+
+use std::io::Read;
+use std::path::Path;
+
+use polyepoxide_core::{Batch, Cid, GcStats, IndexStore, RefStore, Store};
+use polyepoxide_fjall::FjallStore;
+use polyepoxide_rocks::RocksStore;
+use serde::Deserialize;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum AnyStoreError {
+    #[error("fjall error: {0}")]
+    Fjall(#[from] polyepoxide_fjall::FjallError),
+    #[error("rocks error: {0}")]
+    Rocks(#[from] polyepoxide_rocks::RocksError),
+}
+
+/// Storage backend of an `AnyStore`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StoreType {
+    #[default]
+    Fjall,
+    Rocks,
+}
+
+impl std::str::FromStr for StoreType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "fjall" => Ok(StoreType::Fjall),
+            "rocks" | "rocksdb" => Ok(StoreType::Rocks),
+            _ => Err(format!("unknown store type: {}", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for StoreType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StoreType::Fjall => write!(f, "fjall"),
+            StoreType::Rocks => write!(f, "rocks"),
+        }
+    }
+}
+
+/// Runtime-dispatched store.
+pub enum AnyStore {
+    Fjall(FjallStore),
+    Rocks(RocksStore),
+}
+
+impl AnyStore {
+    pub fn open(store_type: StoreType, path: impl AsRef<Path>) -> Result<Self, AnyStoreError> {
+        match store_type {
+            StoreType::Fjall => Self::open_fjall(path),
+            StoreType::Rocks => Self::open_rocks(path),
+        }
+    }
+
+    pub fn open_fjall(path: impl AsRef<Path>) -> Result<Self, AnyStoreError> {
+        Ok(Self::Fjall(FjallStore::open(path)?))
+    }
+
+    pub fn open_rocks(path: impl AsRef<Path>) -> Result<Self, AnyStoreError> {
+        Ok(Self::Rocks(RocksStore::open(path)?))
+    }
+}
+
+impl Store for AnyStore {
+    type Error = AnyStoreError;
+
+    fn get(&self, cid: &Cid) -> Result<Option<Vec<u8>>, Self::Error> {
+        match self {
+            AnyStore::Fjall(s) => s.get(cid).map_err(Into::into),
+            AnyStore::Rocks(s) => s.get(cid).map_err(Into::into),
+        }
+    }
+
+    fn get_reader(&self, cid: &Cid) -> Result<Option<Box<dyn Read + '_>>, Self::Error> {
+        match self {
+            AnyStore::Fjall(s) => s.get_reader(cid).map_err(Into::into),
+            AnyStore::Rocks(s) => s.get_reader(cid).map_err(Into::into),
+        }
+    }
+
+    fn put(&self, cid: &Cid, value: &[u8]) -> Result<(), Self::Error> {
+        match self {
+            AnyStore::Fjall(s) => s.put(cid, value).map_err(Into::into),
+            AnyStore::Rocks(s) => s.put(cid, value).map_err(Into::into),
+        }
+    }
+
+    fn put_schema(&self, cid: &Cid, value: &[u8]) -> Result<(), Self::Error> {
+        match self {
+            AnyStore::Fjall(s) => s.put_schema(cid, value).map_err(Into::into),
+            AnyStore::Rocks(s) => s.put_schema(cid, value).map_err(Into::into),
+        }
+    }
+
+    fn has(&self, cid: &Cid) -> Result<bool, Self::Error> {
+        match self {
+            AnyStore::Fjall(s) => s.has(cid).map_err(Into::into),
+            AnyStore::Rocks(s) => s.has(cid).map_err(Into::into),
+        }
+    }
+
+    fn get_many(&self, cids: &[Cid]) -> Result<Vec<Option<Vec<u8>>>, Self::Error> {
+        match self {
+            AnyStore::Fjall(s) => s.get_many(cids).map_err(Into::into),
+            AnyStore::Rocks(s) => s.get_many(cids).map_err(Into::into),
+        }
+    }
+
+    fn put_many(&self, nodes: &[(&Cid, &[u8])]) -> Result<(), Self::Error> {
+        match self {
+            AnyStore::Fjall(s) => s.put_many(nodes).map_err(Into::into),
+            AnyStore::Rocks(s) => s.put_many(nodes).map_err(Into::into),
+        }
+    }
+
+    fn write_batch(&self, batch: &Batch) -> Result<(), Self::Error> {
+        match self {
+            AnyStore::Fjall(s) => s.write_batch(batch).map_err(Into::into),
+            AnyStore::Rocks(s) => s.write_batch(batch).map_err(Into::into),
+        }
+    }
+
+    fn delete(&self, cid: &Cid) -> Result<(), Self::Error> {
+        match self {
+            AnyStore::Fjall(s) => s.delete(cid).map_err(Into::into),
+            AnyStore::Rocks(s) => s.delete(cid).map_err(Into::into),
+        }
+    }
+
+    fn delete_many(&self, cids: &[Cid]) -> Result<(), Self::Error> {
+        match self {
+            AnyStore::Fjall(s) => s.delete_many(cids).map_err(Into::into),
+            AnyStore::Rocks(s) => s.delete_many(cids).map_err(Into::into),
+        }
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = Result<Cid, Self::Error>> + '_> {
+        match self {
+            AnyStore::Fjall(s) => Box::new(s.iter().map(|r| r.map_err(Into::into))),
+            AnyStore::Rocks(s) => Box::new(s.iter().map(|r| r.map_err(Into::into))),
+        }
+    }
+
+    fn gc(&self, roots: &[Cid]) -> Result<GcStats, Self::Error> {
+        match self {
+            AnyStore::Fjall(s) => s.gc(roots).map_err(Into::into),
+            AnyStore::Rocks(s) => s.gc(roots).map_err(Into::into),
+        }
+    }
+
+    fn compact(&self) -> Result<(), Self::Error> {
+        match self {
+            AnyStore::Fjall(s) => s.compact().map_err(Into::into),
+            AnyStore::Rocks(s) => s.compact().map_err(Into::into),
+        }
+    }
+}
+
+impl RefStore for AnyStore {
+    fn get_ref(&self, name: &str) -> Result<Option<Cid>, Self::Error> {
+        match self {
+            AnyStore::Fjall(s) => s.get_ref(name).map_err(Into::into),
+            AnyStore::Rocks(s) => s.get_ref(name).map_err(Into::into),
+        }
+    }
+
+    fn set_ref(&self, name: &str, cid: &Cid) -> Result<(), Self::Error> {
+        match self {
+            AnyStore::Fjall(s) => s.set_ref(name, cid).map_err(Into::into),
+            AnyStore::Rocks(s) => s.set_ref(name, cid).map_err(Into::into),
+        }
+    }
+
+    fn delete_ref(&self, name: &str) -> Result<(), Self::Error> {
+        match self {
+            AnyStore::Fjall(s) => s.delete_ref(name).map_err(Into::into),
+            AnyStore::Rocks(s) => s.delete_ref(name).map_err(Into::into),
+        }
+    }
+
+    fn list_refs(&self, prefix: &str) -> Result<Vec<(String, Cid)>, Self::Error> {
+        match self {
+            AnyStore::Fjall(s) => s.list_refs(prefix).map_err(Into::into),
+            AnyStore::Rocks(s) => s.list_refs(prefix).map_err(Into::into),
+        }
+    }
+
+    fn compare_and_set_ref(
+        &self,
+        name: &str,
+        expected: Option<&Cid>,
+        new: &Cid,
+    ) -> Result<bool, Self::Error> {
+        match self {
+            AnyStore::Fjall(s) => s
+                .compare_and_set_ref(name, expected, new)
+                .map_err(Into::into),
+            AnyStore::Rocks(s) => s
+                .compare_and_set_ref(name, expected, new)
+                .map_err(Into::into),
+        }
+    }
+}
+
+impl IndexStore for AnyStore {
+    fn set_index_entry(&self, name: &str, cid: &Cid) -> Result<(), Self::Error> {
+        match self {
+            AnyStore::Fjall(s) => s.set_index_entry(name, cid).map_err(Into::into),
+            AnyStore::Rocks(s) => s.set_index_entry(name, cid).map_err(Into::into),
+        }
+    }
+
+    fn delete_index_entry(&self, name: &str) -> Result<(), Self::Error> {
+        match self {
+            AnyStore::Fjall(s) => s.delete_index_entry(name).map_err(Into::into),
+            AnyStore::Rocks(s) => s.delete_index_entry(name).map_err(Into::into),
+        }
+    }
+
+    fn list_index_entries(&self, prefix: &str) -> Result<Vec<(String, Cid)>, Self::Error> {
+        match self {
+            AnyStore::Fjall(s) => s.list_index_entries(prefix).map_err(Into::into),
+            AnyStore::Rocks(s) => s.list_index_entries(prefix).map_err(Into::into),
+        }
+    }
+}
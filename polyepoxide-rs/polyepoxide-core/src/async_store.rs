@@ -1,7 +1,7 @@
 use cid::Cid;
 use std::future::Future;
 
-use crate::Store;
+use crate::store::{Batch, Store};
 
 /// Async CID-keyed store for oxide bytes.
 ///
@@ -15,6 +15,19 @@ pub trait AsyncStore: Send + Sync {
     fn async_get(&self, cid: &Cid) -> impl Future<Output = Result<Option<Vec<u8>>, Self::Error>> + Send;
     fn async_put(&self, cid: &Cid, value: &[u8]) -> impl Future<Output = Result<(), Self::Error>> + Send;
     fn async_has(&self, cid: &Cid) -> impl Future<Output = Result<bool, Self::Error>> + Send;
+    fn async_delete(&self, cid: &Cid) -> impl Future<Output = Result<(), Self::Error>> + Send;
+
+    /// Lists the CIDs of everything in the store.
+    fn async_list_cids(&self) -> impl Future<Output = Result<Vec<Cid>, Self::Error>> + Send;
+
+    /// Stores a schema node - default impl calls async_put().
+    fn async_put_schema(
+        &self,
+        cid: &Cid,
+        value: &[u8],
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        self.async_put(cid, value)
+    }
 
     /// Batch get - default impl calls async_get() in sequence.
     fn async_get_many(
@@ -45,6 +58,34 @@ pub trait AsyncStore: Send + Sync {
         }
     }
 
+    /// Writes a batch - default impl puts the schemas, then the values.
+    fn async_write_batch(
+        &self,
+        batch: &Batch,
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        let batch = batch.clone();
+        async move {
+            for (cid, value) in batch.schemas() {
+                self.async_put_schema(cid, value).await?;
+            }
+            self.async_put_many(&batch.values().collect::<Vec<_>>()).await
+        }
+    }
+
+    /// Batch delete - default impl calls async_delete() in sequence.
+    fn async_delete_many(
+        &self,
+        cids: &[Cid],
+    ) -> impl Future<Output = Result<(), Self::Error>> + Send {
+        let cids = cids.to_vec();
+        async move {
+            for cid in &cids {
+                self.async_delete(cid).await?;
+            }
+            Ok(())
+        }
+    }
+
     /// Batch has - default impl calls async_has() in sequence.
     fn async_has_many(
         &self,
@@ -73,9 +114,37 @@ impl<S: Store + Send + Sync> AsyncStore for S {
         self.put(cid, value)
     }
 
+    async fn async_put_schema(&self, cid: &Cid, value: &[u8]) -> Result<(), Self::Error> {
+        self.put_schema(cid, value)
+    }
+
     async fn async_has(&self, cid: &Cid) -> Result<bool, Self::Error> {
         self.has(cid)
     }
+
+    async fn async_get_many(&self, cids: &[Cid]) -> Result<Vec<Option<Vec<u8>>>, Self::Error> {
+        self.get_many(cids)
+    }
+
+    async fn async_put_many(&self, nodes: &[(&Cid, &[u8])]) -> Result<(), Self::Error> {
+        self.put_many(nodes)
+    }
+
+    async fn async_write_batch(&self, batch: &Batch) -> Result<(), Self::Error> {
+        self.write_batch(batch)
+    }
+
+    async fn async_delete(&self, cid: &Cid) -> Result<(), Self::Error> {
+        self.delete(cid)
+    }
+
+    async fn async_delete_many(&self, cids: &[Cid]) -> Result<(), Self::Error> {
+        self.delete_many(cids)
+    }
+
+    async fn async_list_cids(&self) -> Result<Vec<Cid>, Self::Error> {
+        self.list_cids()
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,326 @@
+//! Chunked binary content with content-defined chunk boundaries.
+//!
+//! Storing a large file as one `ByteString` means any edit produces an
+//! entirely new block, and fixed-size chunks don't help either: an insertion
+//! shifts every boundary after it. FastCDC places boundaries where a rolling
+//! hash over the last bytes matches a mask, so they move with the content and
+//! the chunks around an edit keep their CIDs.
+
+use std::collections::HashSet;
+use std::io::{self, Read};
+
+use serde::{Deserialize, Serialize};
+
+use crate::bond::Bond;
+use crate::oxide::{compute_cid, BondMapper, BondVisitor, ByteString, Oxide};
+use crate::schema::{IntType, Structure};
+use crate::store::Store;
+
+/// No boundary is placed before this many bytes of a chunk.
+pub const MIN_CHUNK_SIZE: usize = 2 * 1024;
+/// Target average chunk size.
+pub const AVG_CHUNK_SIZE: usize = 8 * 1024;
+/// Chunks are cut here if no boundary was found earlier.
+pub const MAX_CHUNK_SIZE: usize = 64 * 1024;
+
+// Normalized chunking: a stricter mask below the average size and a looser
+// one above it concentrate chunk sizes around the average. The masks use the
+// high bits, which depend on the last 64 bytes rather than only the last few.
+const MASK_SMALL: u64 = !0 << (64 - 15);
+const MASK_LARGE: u64 = !0 << (64 - 11);
+
+/// Random values for the gear hash. They determine every chunk boundary, and
+/// so every chunk CID: changing them breaks deduplication with existing data.
+static GEAR: [u64; 256] = gear_table();
+
+const fn gear_table() -> [u64; 256] {
+    // SplitMix64 with a fixed seed
+    let mut table = [0u64; 256];
+    let mut state: u64 = 0;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+}
+
+/// Returns the length of the first chunk of `data`.
+fn cut_point(data: &[u8]) -> usize {
+    if data.len() <= MIN_CHUNK_SIZE {
+        return data.len();
+    }
+    let normal = AVG_CHUNK_SIZE.min(data.len());
+    let max = MAX_CHUNK_SIZE.min(data.len());
+
+    let mut hash = 0u64;
+    for (i, &byte) in data.iter().enumerate().take(max).skip(MIN_CHUNK_SIZE) {
+        hash = (hash << 1).wrapping_add(GEAR[byte as usize]);
+        let mask = if i < normal { MASK_SMALL } else { MASK_LARGE };
+        if hash & mask == 0 {
+            return i + 1;
+        }
+    }
+    max
+}
+
+/// Splits `data` into content-defined chunks.
+pub fn cdc_chunks(data: &[u8]) -> Vec<&[u8]> {
+    let mut chunks = Vec::new();
+    let mut rest = data;
+    while !rest.is_empty() {
+        let (chunk, tail) = rest.split_at(cut_point(rest));
+        chunks.push(chunk);
+        rest = tail;
+    }
+    chunks
+}
+
+/// Error from writing a blob to a store.
+#[derive(Debug, thiserror::Error)]
+pub enum BlobError<E> {
+    #[error("read error: {0}")]
+    Io(#[from] io::Error),
+    #[error("store error: {0}")]
+    Store(E),
+}
+
+/// Binary content stored as a list of chunks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Blob {
+    /// Total length in bytes.
+    pub size: u64,
+    pub chunks: Vec<Bond<ByteString>>,
+}
+
+impl Blob {
+    /// Splits `data` into content-defined chunks.
+    pub fn new(data: &[u8]) -> Self {
+        Blob {
+            size: data.len() as u64,
+            chunks: cdc_chunks(data)
+                .into_iter()
+                .map(|chunk| Bond::new(ByteString::from(chunk)))
+                .collect(),
+        }
+    }
+
+    /// Chunks `reader` into `store` as it's read, buffering at most one
+    /// `MAX_CHUNK_SIZE` window. The returned blob's chunks are unresolved.
+    pub fn write<S: Store>(
+        mut reader: impl Read,
+        store: &S,
+    ) -> Result<Self, BlobError<S::Error>> {
+        let mut buf = Vec::with_capacity(MAX_CHUNK_SIZE);
+        let mut chunks = Vec::new();
+        let mut size = 0;
+        loop {
+            // cut_point only looks at the first MAX_CHUNK_SIZE bytes, so a full
+            // window cuts where chunking the whole content would
+            let missing = MAX_CHUNK_SIZE - buf.len();
+            reader.by_ref().take(missing as u64).read_to_end(&mut buf)?;
+            if buf.is_empty() {
+                break;
+            }
+            let len = cut_point(&buf);
+            let bytes = ByteString::from(&buf[..len]).to_bytes();
+            let cid = compute_cid(&bytes);
+            store.put(&cid, &bytes).map_err(BlobError::Store)?;
+            chunks.push(Bond::from_cid(cid));
+            size += len as u64;
+            buf.drain(..len);
+        }
+        Ok(Blob { size, chunks })
+    }
+
+    /// Reassembles the content, or returns `None` if a chunk isn't resolved.
+    pub fn to_vec(&self) -> Option<Vec<u8>> {
+        let mut data = Vec::with_capacity(self.size as usize);
+        for chunk in &self.chunks {
+            data.extend_from_slice(chunk.value()?.as_bytes());
+        }
+        Some(data)
+    }
+
+    /// Streams the content, loading unresolved chunks from `store` one at a time.
+    pub fn reader<'a, S: Store>(&'a self, store: &'a S) -> BlobReader<'a, S> {
+        BlobReader {
+            blob: self,
+            store,
+            next: 0,
+            chunk: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+/// Reader over a blob's content, returned by [`Blob::reader`].
+pub struct BlobReader<'a, S> {
+    blob: &'a Blob,
+    store: &'a S,
+    /// Index of the next chunk to load.
+    next: usize,
+    chunk: Vec<u8>,
+    pos: usize,
+}
+
+impl<S: Store> BlobReader<'_, S> {
+    fn load(&self, bond: &Bond<ByteString>) -> io::Result<Vec<u8>> {
+        if let Some(chunk) = bond.value() {
+            return Ok(chunk.as_bytes().to_vec());
+        }
+        let cid = bond.cid();
+        let reader = self
+            .store
+            .get_reader(&cid)
+            .map_err(io::Error::other)?
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, format!("chunk {cid} not found"))
+            })?;
+        // Decoding from the reader skips copying the encoded chunk out of
+        // backends that read from their own buffers
+        let chunk: ByteString =
+            serde_ipld_dagcbor::from_reader(reader).map_err(io::Error::other)?;
+        Ok(chunk.into_vec())
+    }
+}
+
+impl<S: Store> Read for BlobReader<'_, S> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.chunk.len() {
+            let Some(bond) = self.blob.chunks.get(self.next) else {
+                return Ok(0);
+            };
+            self.chunk = self.load(bond)?;
+            self.next += 1;
+            self.pos = 0;
+        }
+        let n = out.len().min(self.chunk.len() - self.pos);
+        out[..n].copy_from_slice(&self.chunk[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+impl Oxide for Blob {
+    fn schema() -> Structure {
+        Structure::record([
+            ("size", Structure::Int(IntType::U64)),
+            ("chunks", Structure::sequence(Structure::bond(Structure::ByteString))),
+        ])
+    }
+
+    fn visit_bonds(&self, visitor: &mut dyn BondVisitor) {
+        self.chunks.visit_bonds(visitor);
+    }
+
+    fn map_bonds(&self, mapper: &mut impl BondMapper) -> Self {
+        Blob {
+            size: self.size,
+            chunks: self.chunks.map_bonds(mapper),
+        }
+    }
+}
+
+/// Stored bytes of a set of blobs under fixed-size and content-defined
+/// chunking, counting each distinct chunk once.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DedupReport {
+    pub blobs: usize,
+    pub total_bytes: u64,
+    pub fixed_chunks: usize,
+    pub fixed_bytes: u64,
+    pub cdc_chunks: usize,
+    pub cdc_bytes: u64,
+}
+
+impl DedupReport {
+    /// Chunks every blob both ways; fixed chunks are `AVG_CHUNK_SIZE` long.
+    pub fn measure<'a>(blobs: impl IntoIterator<Item = &'a [u8]>) -> Self {
+        let mut report = DedupReport::default();
+        let mut fixed = HashSet::new();
+        let mut cdc = HashSet::new();
+        for data in blobs {
+            report.blobs += 1;
+            report.total_bytes += data.len() as u64;
+            for chunk in data.chunks(AVG_CHUNK_SIZE) {
+                if fixed.insert(compute_cid(chunk)) {
+                    report.fixed_chunks += 1;
+                    report.fixed_bytes += chunk.len() as u64;
+                }
+            }
+            for chunk in cdc_chunks(data) {
+                if cdc.insert(compute_cid(chunk)) {
+                    report.cdc_chunks += 1;
+                    report.cdc_bytes += chunk.len() as u64;
+                }
+            }
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryStore;
+
+    /// Deterministic pseudo-random bytes, so chunk boundaries are reproducible.
+    fn noise(len: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn chunks_reassemble_within_bounds() {
+        let data = noise(500_000, 1);
+        let chunks = cdc_chunks(&data);
+        assert_eq!(chunks.concat(), data);
+        let (last, rest) = chunks.split_last().unwrap();
+        assert!(last.len() <= MAX_CHUNK_SIZE);
+        assert!(rest.iter().all(|c| (MIN_CHUNK_SIZE..=MAX_CHUNK_SIZE).contains(&c.len())));
+
+        let blob = Blob::new(&data);
+        assert_eq!(blob.chunks.len(), chunks.len());
+        assert_eq!(blob.to_vec().unwrap(), data);
+    }
+
+    #[test]
+    fn insertion_keeps_most_chunks() {
+        let original = noise(300_000, 2);
+        let mut edited = original.clone();
+        edited.splice(1000..1000, noise(100, 3));
+
+        let report = DedupReport::measure([original.as_slice(), edited.as_slice()]);
+        assert_eq!(report.total_bytes, 600_100);
+        // Fixed chunks all shift after the insertion; CDC ones realign
+        assert!(report.fixed_bytes > 590_000);
+        assert!(report.cdc_bytes < 350_000);
+    }
+
+    #[test]
+    fn streamed_write_matches_in_memory_chunking() {
+        let data = noise(300_000, 4);
+        let store = MemoryStore::new();
+        let streamed = Blob::write(data.as_slice(), &store).unwrap();
+
+        let chunks: Vec<_> = Blob::new(&data).chunks.iter().map(Bond::cid).collect();
+        assert_eq!(streamed.chunks.iter().map(Bond::cid).collect::<Vec<_>>(), chunks);
+        assert_eq!(streamed.size, 300_000);
+
+        let mut read = Vec::new();
+        streamed.reader(&store).read_to_end(&mut read).unwrap();
+        assert_eq!(read, data);
+    }
+}
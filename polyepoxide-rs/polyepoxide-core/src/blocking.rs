@@ -0,0 +1,133 @@
+//! Running a blocking `Store` off the async executor.
+
+use std::sync::Arc;
+
+use cid::Cid;
+
+use crate::async_store::AsyncStore;
+use crate::store::{Batch, Store};
+
+/// An `AsyncStore` that runs each call of a blocking store on tokio's
+/// blocking thread pool.
+///
+/// Every `Store` is an `AsyncStore` already, but that runs disk reads on the
+/// executor thread, stalling every other task polled by it. This adapter
+/// keeps them off it, at the cost of copying arguments into the blocking
+/// task. Panics in the store are resumed in the caller.
+pub struct BlockingStoreAdapter<S> {
+    store: Arc<S>,
+}
+
+impl<S> BlockingStoreAdapter<S> {
+    pub fn new(store: S) -> Self {
+        Self::from_arc(Arc::new(store))
+    }
+
+    /// Wraps a store that is also used directly elsewhere.
+    pub fn from_arc(store: Arc<S>) -> Self {
+        Self { store }
+    }
+
+    pub fn inner(&self) -> &Arc<S> {
+        &self.store
+    }
+}
+
+impl<S: Store + Send + Sync + 'static> BlockingStoreAdapter<S> {
+    async fn run<T: Send + 'static>(
+        &self,
+        call: impl FnOnce(&S) -> Result<T, S::Error> + Send + 'static,
+    ) -> Result<T, S::Error> {
+        let store = self.store.clone();
+        match tokio::task::spawn_blocking(move || call(&store)).await {
+            Ok(result) => result,
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+}
+
+impl<S: Store + Send + Sync + 'static> AsyncStore for BlockingStoreAdapter<S> {
+    type Error = S::Error;
+
+    async fn async_get(&self, cid: &Cid) -> Result<Option<Vec<u8>>, Self::Error> {
+        let cid = *cid;
+        self.run(move |store| store.get(&cid)).await
+    }
+
+    async fn async_put(&self, cid: &Cid, value: &[u8]) -> Result<(), Self::Error> {
+        let (cid, value) = (*cid, value.to_vec());
+        self.run(move |store| store.put(&cid, &value)).await
+    }
+
+    async fn async_put_schema(&self, cid: &Cid, value: &[u8]) -> Result<(), Self::Error> {
+        let (cid, value) = (*cid, value.to_vec());
+        self.run(move |store| store.put_schema(&cid, &value)).await
+    }
+
+    async fn async_has(&self, cid: &Cid) -> Result<bool, Self::Error> {
+        let cid = *cid;
+        self.run(move |store| store.has(&cid)).await
+    }
+
+    async fn async_delete(&self, cid: &Cid) -> Result<(), Self::Error> {
+        let cid = *cid;
+        self.run(move |store| store.delete(&cid)).await
+    }
+
+    async fn async_list_cids(&self) -> Result<Vec<Cid>, Self::Error> {
+        self.run(|store| store.list_cids()).await
+    }
+
+    async fn async_get_many(&self, cids: &[Cid]) -> Result<Vec<Option<Vec<u8>>>, Self::Error> {
+        let cids = cids.to_vec();
+        self.run(move |store| store.get_many(&cids)).await
+    }
+
+    async fn async_put_many(&self, nodes: &[(&Cid, &[u8])]) -> Result<(), Self::Error> {
+        let nodes: Vec<(Cid, Vec<u8>)> = nodes.iter().map(|(k, v)| (**k, v.to_vec())).collect();
+        self.run(move |store| {
+            let nodes: Vec<_> = nodes.iter().map(|(k, v)| (k, v.as_slice())).collect();
+            store.put_many(&nodes)
+        })
+        .await
+    }
+
+    async fn async_write_batch(&self, batch: &Batch) -> Result<(), Self::Error> {
+        let batch = batch.clone();
+        self.run(move |store| store.write_batch(&batch)).await
+    }
+
+    async fn async_delete_many(&self, cids: &[Cid]) -> Result<(), Self::Error> {
+        let cids = cids.to_vec();
+        self.run(move |store| store.delete_many(&cids)).await
+    }
+
+    async fn async_has_many(&self, cids: &[Cid]) -> Result<Vec<bool>, Self::Error> {
+        let cids = cids.to_vec();
+        self.run(move |store| cids.iter().map(|cid| store.has(cid)).collect())
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oxide::compute_cid;
+    use crate::MemoryStore;
+
+    #[tokio::test]
+    async fn runs_calls_on_the_blocking_pool() {
+        let store = BlockingStoreAdapter::new(MemoryStore::new());
+        let cid = compute_cid(b"test");
+
+        store.async_put(&cid, b"hello").await.unwrap();
+        assert_eq!(
+            store.async_get(&cid).await.unwrap(),
+            Some(b"hello".to_vec())
+        );
+        let missing = compute_cid(b"missing");
+        let has = store.async_has_many(&[cid, missing]).await.unwrap();
+        assert_eq!(has, [true, false]);
+        assert!(store.inner().has(&cid).unwrap());
+    }
+}
@@ -5,6 +5,8 @@ use std::sync::Arc;
 use crate::cell::Cell;
 use crate::oxide::{BondMapper, BondVisitor, Oxide};
 use crate::schema::Structure;
+use crate::solvent::{LoadError, Solvent};
+use crate::store::Store;
 
 /// A typed reference from one oxide to another.
 ///
@@ -63,6 +65,22 @@ impl<T: Oxide> Bond<T> {
     pub fn value(&self) -> Option<&T> {
         self.cell().map(|c| c.value())
     }
+
+    /// Resolves the bond on demand, loading the target from `store` into
+    /// `solvent` unless it's already there.
+    ///
+    /// Only the target itself is loaded: its own bonds stay as they are, so
+    /// a long chain can be walked one step at a time.
+    pub fn resolve_with<S: Store>(
+        &mut self,
+        solvent: &mut Solvent,
+        store: &S,
+    ) -> Result<&Arc<Cell<T>>, LoadError<S::Error>> {
+        if let Bond::Unresolved(cid) = *self {
+            *self = Bond::Resolved(solvent.load(&cid, store, 0)?);
+        }
+        Ok(self.cell().expect("bond was just resolved"))
+    }
 }
 
 impl<T: Oxide> Clone for Bond<T> {
@@ -151,4 +169,22 @@ mod tests {
         assert!(!recovered.is_resolved());
         assert_eq!(recovered.cid(), cid);
     }
+
+    #[test]
+    fn resolve_with_loads_one_step() {
+        let mut source = crate::Solvent::new();
+        let inner = source.bond(vec![1u64]);
+        let outer = source.add(vec![inner]);
+        let store = crate::MemoryStore::new();
+        source.persist_cell(&outer, &store).unwrap();
+
+        let mut solvent = crate::Solvent::new();
+        let mut bond: Bond<Vec<Bond<Vec<u64>>>> = Bond::from_cid(outer.cid());
+        let cell = bond.resolve_with(&mut solvent, &store).unwrap();
+        assert!(!cell.value()[0].is_resolved());
+        assert!(bond.is_resolved());
+
+        let mut next = bond.value().unwrap()[0].clone();
+        assert_eq!(next.resolve_with(&mut solvent, &store).unwrap().value(), &vec![1]);
+    }
 }
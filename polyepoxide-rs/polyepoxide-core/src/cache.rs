@@ -0,0 +1,270 @@
+//! Read-through caching of a slow store.
+//!
+//! `CachedStore` puts a fast store, such as a `MemoryStore` or a local disk
+//! store, in front of a slow one such as a remote peer. Blocks never change
+//! under their CID, so cached copies never go stale and need no
+//! invalidation.
+
+use cid::Cid;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::async_store::AsyncStore;
+use crate::store::Batch;
+
+/// Error from a cached store.
+#[derive(Debug, thiserror::Error)]
+pub enum CacheError<F, S> {
+    #[error("cache store error: {0}")]
+    Fast(F),
+    #[error("backing store error: {0}")]
+    Slow(S),
+}
+
+/// Counters of reads served so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Reads answered by the fast store.
+    pub hits: u64,
+    /// Reads passed on to the slow store.
+    pub misses: u64,
+    /// Blocks copied into the fast store after a miss.
+    pub fills: u64,
+}
+
+impl CacheStats {
+    /// Fraction of reads answered by the fast store, 0 before any read.
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
+}
+
+/// A store that reads through a fast store and writes to both.
+///
+/// Blocks read from the slow store are copied into the fast one, as values
+/// even when they are schemas. The slow store stays authoritative: writes
+/// reach it first and listing only asks it. The fast store is never
+/// trimmed, so bound it by wrapping a store that evicts or by running
+/// `gc` on it.
+pub struct CachedStore<F, S> {
+    fast: F,
+    slow: S,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    fills: AtomicU64,
+}
+
+impl<F: AsyncStore, S: AsyncStore> CachedStore<F, S> {
+    pub fn new(fast: F, slow: S) -> Self {
+        Self {
+            fast,
+            slow,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            fills: AtomicU64::new(0),
+        }
+    }
+
+    pub fn fast(&self) -> &F {
+        &self.fast
+    }
+
+    pub fn slow(&self) -> &S {
+        &self.slow
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            fills: self.fills.load(Ordering::Relaxed),
+        }
+    }
+
+    fn count(&self, counter: &AtomicU64, n: usize) {
+        counter.fetch_add(n as u64, Ordering::Relaxed);
+    }
+}
+
+impl<F: AsyncStore, S: AsyncStore> AsyncStore for CachedStore<F, S> {
+    type Error = CacheError<F::Error, S::Error>;
+
+    async fn async_get(&self, cid: &Cid) -> Result<Option<Vec<u8>>, Self::Error> {
+        if let Some(bytes) = self.fast.async_get(cid).await.map_err(CacheError::Fast)? {
+            self.count(&self.hits, 1);
+            return Ok(Some(bytes));
+        }
+        self.count(&self.misses, 1);
+        let bytes = self.slow.async_get(cid).await.map_err(CacheError::Slow)?;
+        if let Some(bytes) = &bytes {
+            self.fast
+                .async_put(cid, bytes)
+                .await
+                .map_err(CacheError::Fast)?;
+            self.count(&self.fills, 1);
+        }
+        Ok(bytes)
+    }
+
+    async fn async_get_many(&self, cids: &[Cid]) -> Result<Vec<Option<Vec<u8>>>, Self::Error> {
+        let mut results = self
+            .fast
+            .async_get_many(cids)
+            .await
+            .map_err(CacheError::Fast)?;
+        let missing: Vec<usize> = (0..cids.len()).filter(|&i| results[i].is_none()).collect();
+        self.count(&self.hits, cids.len() - missing.len());
+        if missing.is_empty() {
+            return Ok(results);
+        }
+        self.count(&self.misses, missing.len());
+
+        let missing_cids: Vec<Cid> = missing.iter().map(|&i| cids[i]).collect();
+        let fetched = self
+            .slow
+            .async_get_many(&missing_cids)
+            .await
+            .map_err(CacheError::Slow)?;
+        let fills: Vec<(&Cid, &[u8])> = missing_cids
+            .iter()
+            .zip(&fetched)
+            .filter_map(|(cid, bytes)| Some((cid, bytes.as_deref()?)))
+            .collect();
+        self.fast
+            .async_put_many(&fills)
+            .await
+            .map_err(CacheError::Fast)?;
+        self.count(&self.fills, fills.len());
+
+        for (i, bytes) in missing.into_iter().zip(fetched) {
+            results[i] = bytes;
+        }
+        Ok(results)
+    }
+
+    async fn async_put(&self, cid: &Cid, value: &[u8]) -> Result<(), Self::Error> {
+        self.slow
+            .async_put(cid, value)
+            .await
+            .map_err(CacheError::Slow)?;
+        self.fast
+            .async_put(cid, value)
+            .await
+            .map_err(CacheError::Fast)
+    }
+
+    async fn async_put_schema(&self, cid: &Cid, value: &[u8]) -> Result<(), Self::Error> {
+        self.slow
+            .async_put_schema(cid, value)
+            .await
+            .map_err(CacheError::Slow)?;
+        self.fast
+            .async_put_schema(cid, value)
+            .await
+            .map_err(CacheError::Fast)
+    }
+
+    async fn async_put_many(&self, nodes: &[(&Cid, &[u8])]) -> Result<(), Self::Error> {
+        self.slow
+            .async_put_many(nodes)
+            .await
+            .map_err(CacheError::Slow)?;
+        self.fast
+            .async_put_many(nodes)
+            .await
+            .map_err(CacheError::Fast)
+    }
+
+    async fn async_write_batch(&self, batch: &Batch) -> Result<(), Self::Error> {
+        self.slow
+            .async_write_batch(batch)
+            .await
+            .map_err(CacheError::Slow)?;
+        self.fast
+            .async_write_batch(batch)
+            .await
+            .map_err(CacheError::Fast)
+    }
+
+    async fn async_has(&self, cid: &Cid) -> Result<bool, Self::Error> {
+        if self.fast.async_has(cid).await.map_err(CacheError::Fast)? {
+            return Ok(true);
+        }
+        self.slow.async_has(cid).await.map_err(CacheError::Slow)
+    }
+
+    async fn async_has_many(&self, cids: &[Cid]) -> Result<Vec<bool>, Self::Error> {
+        let mut results = self
+            .fast
+            .async_has_many(cids)
+            .await
+            .map_err(CacheError::Fast)?;
+        let missing: Vec<usize> = (0..cids.len()).filter(|&i| !results[i]).collect();
+        if missing.is_empty() {
+            return Ok(results);
+        }
+        let missing_cids: Vec<Cid> = missing.iter().map(|&i| cids[i]).collect();
+        let found = self
+            .slow
+            .async_has_many(&missing_cids)
+            .await
+            .map_err(CacheError::Slow)?;
+        for (i, has) in missing.into_iter().zip(found) {
+            results[i] = has;
+        }
+        Ok(results)
+    }
+
+    /// Deletes from the fast store first, so a failure never leaves a cached
+    /// copy of a block the slow store no longer has.
+    async fn async_delete(&self, cid: &Cid) -> Result<(), Self::Error> {
+        self.fast
+            .async_delete(cid)
+            .await
+            .map_err(CacheError::Fast)?;
+        self.slow.async_delete(cid).await.map_err(CacheError::Slow)
+    }
+
+    async fn async_delete_many(&self, cids: &[Cid]) -> Result<(), Self::Error> {
+        self.fast
+            .async_delete_many(cids)
+            .await
+            .map_err(CacheError::Fast)?;
+        self.slow
+            .async_delete_many(cids)
+            .await
+            .map_err(CacheError::Slow)
+    }
+
+    async fn async_list_cids(&self) -> Result<Vec<Cid>, Self::Error> {
+        self.slow.async_list_cids().await.map_err(CacheError::Slow)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oxide::compute_cid;
+    use crate::{MemoryStore, Store};
+
+    #[tokio::test]
+    async fn caches_blocks_read_from_the_slow_store() {
+        let store = CachedStore::new(MemoryStore::new(), MemoryStore::new());
+        let a = compute_cid(b"a");
+        let b = compute_cid(b"b");
+        let missing = compute_cid(b"missing");
+        store.slow().put(&a, b"a").unwrap();
+        store.slow().put(&b, b"b").unwrap();
+
+        assert_eq!(store.async_get(&a).await.unwrap(), Some(b"a".to_vec()));
+        assert!(store.fast().has(&a).unwrap());
+        let results = store.async_get_many(&[a, b, missing]).await.unwrap();
+        assert_eq!(results, [Some(b"a".to_vec()), Some(b"b".to_vec()), None]);
+
+        let stats = store.stats();
+        assert_eq!((stats.hits, stats.misses, stats.fills), (1, 3, 2));
+        assert!(store.fast().has(&b).unwrap());
+    }
+}
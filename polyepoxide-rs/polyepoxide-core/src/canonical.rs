@@ -0,0 +1,141 @@
+//! Canonical CBOR map ordering.
+//!
+//! Serde serializes maps in iteration order, which for `HashMap` differs
+//! between runs. Since identity is the hash of the encoding, `Oxide::to_bytes`
+//! re-encodes its output with every map's entries sorted by the bytewise order
+//! of their encoded keys (RFC 8949 §4.2.1). For string keys this equals the
+//! DAG-CBOR length-first ordering.
+
+/// Error from canonicalizing malformed or unsupported CBOR.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum CanonicalError {
+    #[error("unexpected end of CBOR input")]
+    Truncated,
+    #[error("unsupported CBOR initial byte {0:#04x}")]
+    Unsupported(u8),
+    #[error("trailing bytes after CBOR item")]
+    Trailing,
+}
+
+/// Re-encodes a single CBOR item with all map entries in canonical order.
+///
+/// Indefinite-length items are rejected, as DAG-CBOR forbids them.
+pub fn canonicalize(data: &[u8]) -> Result<Vec<u8>, CanonicalError> {
+    let mut out = Vec::with_capacity(data.len());
+    let end = write_item(data, 0, &mut out)?;
+    if end != data.len() {
+        return Err(CanonicalError::Trailing);
+    }
+    Ok(out)
+}
+
+/// Reads an item header at `pos`, returning (major type, argument, header end).
+pub(crate) fn read_header(data: &[u8], pos: usize) -> Result<(u8, u64, usize), CanonicalError> {
+    let initial = *data.get(pos).ok_or(CanonicalError::Truncated)?;
+    let major = initial >> 5;
+    let info = initial & 0x1f;
+    let len = match info {
+        0..=23 => return Ok((major, info as u64, pos + 1)),
+        24 => 1,
+        25 => 2,
+        26 => 4,
+        27 => 8,
+        _ => return Err(CanonicalError::Unsupported(initial)),
+    };
+    let bytes = data
+        .get(pos + 1..pos + 1 + len)
+        .ok_or(CanonicalError::Truncated)?;
+    let arg = bytes.iter().fold(0u64, |acc, &b| (acc << 8) | b as u64);
+    Ok((major, arg, pos + 1 + len))
+}
+
+/// Copies the item at `pos` to `out` in canonical form and returns its end.
+fn write_item(data: &[u8], pos: usize, out: &mut Vec<u8>) -> Result<usize, CanonicalError> {
+    let (major, arg, body) = read_header(data, pos)?;
+    out.extend_from_slice(&data[pos..body]);
+
+    match major {
+        // Integers and simple values/floats: the header is the whole item
+        0 | 1 | 7 => Ok(body),
+        // Byte and text strings
+        2 | 3 => {
+            let end = body
+                .checked_add(arg as usize)
+                .filter(|&end| end <= data.len())
+                .ok_or(CanonicalError::Truncated)?;
+            out.extend_from_slice(&data[body..end]);
+            Ok(end)
+        }
+        // Arrays
+        4 => {
+            let mut pos = body;
+            for _ in 0..arg {
+                pos = write_item(data, pos, out)?;
+            }
+            Ok(pos)
+        }
+        // Maps
+        5 => {
+            let mut entries = Vec::new();
+            let mut pos = body;
+            for _ in 0..arg {
+                let mut key = Vec::new();
+                pos = write_item(data, pos, &mut key)?;
+                let mut value = Vec::new();
+                pos = write_item(data, pos, &mut value)?;
+                entries.push((key, value));
+            }
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+            for (key, value) in entries {
+                out.extend_from_slice(&key);
+                out.extend_from_slice(&value);
+            }
+            Ok(pos)
+        }
+        // Tags (e.g. 42 for CID links) wrap a single item
+        6 => write_item(data, body, out),
+        _ => unreachable!("major type is three bits"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{BTreeMap, HashMap};
+
+    #[test]
+    fn sorts_map_entries() {
+        // {"bb": 1, "a": 2, "c": [{"z": 0, "y": 1}]}
+        let unsorted = [
+            0xa3, 0x62, b'b', b'b', 0x01, 0x61, b'a', 0x02, 0x61, b'c', 0x81, 0xa2, 0x61, b'z',
+            0x00, 0x61, b'y', 0x01,
+        ];
+        let sorted = [
+            0xa3, 0x61, b'a', 0x02, 0x61, b'c', 0x81, 0xa2, 0x61, b'y', 0x01, 0x61, b'z', 0x00,
+            0x62, b'b', b'b', 0x01,
+        ];
+        assert_eq!(canonicalize(&unsorted).unwrap(), sorted);
+        assert_eq!(canonicalize(&sorted).unwrap(), sorted);
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        assert_eq!(canonicalize(&[0x62, b'a']), Err(CanonicalError::Truncated));
+        assert_eq!(canonicalize(&[0x9f, 0xff]), Err(CanonicalError::Unsupported(0x9f)));
+        assert_eq!(canonicalize(&[0x01, 0x02]), Err(CanonicalError::Trailing));
+    }
+
+    #[test]
+    fn hash_map_encoding_is_deterministic() {
+        use crate::Oxide;
+
+        let entries: Vec<(String, u64)> = (0..64).map(|i| (format!("key{}", i), i)).collect();
+        let forward: HashMap<String, u64> = entries.iter().cloned().collect();
+        let backward: HashMap<String, u64> = entries.iter().rev().cloned().collect();
+        let ordered: BTreeMap<String, u64> = entries.into_iter().collect();
+
+        assert_eq!(forward.to_bytes(), backward.to_bytes());
+        assert_eq!(forward.compute_cid(), ordered.compute_cid());
+        assert_eq!(HashMap::<String, u64>::from_bytes(&forward.to_bytes()).unwrap(), forward);
+    }
+}
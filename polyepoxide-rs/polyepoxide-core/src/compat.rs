@@ -0,0 +1,277 @@
+//! Compatibility between schemas of different app versions.
+//!
+//! Data written under one schema can be read under another if every value the
+//! writer produces decodes into the reader's type. Decoding ignores unknown
+//! record fields and fills missing `#[oxide(default)]` ones, so records may
+//! gain such fields and lose any; unions and enums may gain variants; integers and floats
+//! may widen. Options are encoded as arrays and must be present, so a plain
+//! `Option` field can't be added without `#[oxide(default)]`.
+
+use crate::bond::Bond;
+use crate::schema::{FloatType, IntType, Structure};
+
+/// A place where data written under one schema fails to decode under another.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{path}: {reason}")]
+pub struct Incompatibility {
+    /// Location in the schema, e.g. `messages[].author`; empty at the root.
+    pub path: String,
+    pub reason: String,
+}
+
+/// Result of [`Structure::is_compatible_with`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompatibilityReport {
+    pub incompatibilities: Vec<Incompatibility>,
+}
+
+impl CompatibilityReport {
+    pub fn is_compatible(&self) -> bool {
+        self.incompatibilities.is_empty()
+    }
+}
+
+impl Structure {
+    /// Checks whether data written under `self` can be decoded under `reader`.
+    ///
+    /// Nested schemas are compared through their bonds; an unresolved bond is
+    /// only compatible with one of the same CID.
+    pub fn is_compatible_with(&self, reader: &Structure) -> CompatibilityReport {
+        let mut checker = Checker {
+            path: String::new(),
+            report: CompatibilityReport::default(),
+        };
+        checker.check(self, reader);
+        checker.report
+    }
+}
+
+struct Checker {
+    path: String,
+    report: CompatibilityReport,
+}
+
+impl Checker {
+    fn fail(&mut self, reason: String) {
+        self.report.incompatibilities.push(Incompatibility {
+            path: self.path.clone(),
+            reason,
+        });
+    }
+
+    /// Appends `segment` to the path, returning the length to restore.
+    fn enter(&mut self, segment: &str) -> usize {
+        let len = self.path.len();
+        if !self.path.is_empty() && !segment.starts_with('[') {
+            self.path.push('.');
+        }
+        self.path.push_str(segment);
+        len
+    }
+
+    fn nested(&mut self, segment: &str, writer: &Bond<Structure>, reader: &Bond<Structure>) {
+        let len = self.enter(segment);
+        self.check_bonds(writer, reader);
+        self.path.truncate(len);
+    }
+
+    fn check_bonds(&mut self, writer: &Bond<Structure>, reader: &Bond<Structure>) {
+        if writer.cid() == reader.cid() {
+            return;
+        }
+        match (writer.value(), reader.value()) {
+            (Some(w), Some(r)) => self.check(w, r),
+            _ => self.unresolved(),
+        }
+    }
+
+    fn unresolved(&mut self) {
+        self.fail("schemas differ and are not resolved".to_string());
+    }
+
+    fn check(&mut self, writer: &Structure, reader: &Structure) {
+        match (writer, reader) {
+            // Defaulted values are encoded as the inner type
+            (Structure::Defaulted(w), _) => match w.value() {
+                Some(w) => self.check(w, reader),
+                None => self.unresolved(),
+            },
+            (_, Structure::Defaulted(r)) => match r.value() {
+                Some(r) => self.check(writer, r),
+                None => self.unresolved(),
+            },
+            (Structure::Int(w), Structure::Int(r)) => {
+                if !int_widens(*w, *r) {
+                    self.fail(format!("{w:?} does not fit in {r:?}"));
+                }
+            }
+            (Structure::Float(w), Structure::Float(r)) => {
+                if matches!((w, r), (FloatType::F64, FloatType::F32)) {
+                    self.fail("F64 does not fit in F32".to_string());
+                }
+            }
+            (Structure::Sequence(w), Structure::Sequence(r)) => self.nested("[]", w, r),
+            (Structure::Bond(w), Structure::Bond(r)) => self.check_bonds(w, r),
+            (Structure::Tuple(w), Structure::Tuple(r)) => {
+                if w.len() != r.len() {
+                    self.fail(format!("tuple of {} read as tuple of {}", w.len(), r.len()));
+                    return;
+                }
+                for (i, (w, r)) in w.iter().zip(r).enumerate() {
+                    self.nested(&i.to_string(), w, r);
+                }
+            }
+            (Structure::Record(w), Structure::Record(r)) => {
+                for (name, r) in r {
+                    match w.get(name) {
+                        Some(w) => self.nested(name, w, r),
+                        // Only defaulted fields may be absent; options are arrays
+                        None if matches!(r.value(), Some(Structure::Defaulted(_))) => {}
+                        None => {
+                            let len = self.enter(name);
+                            self.fail("added field has no default".to_string());
+                            self.path.truncate(len);
+                        }
+                    }
+                }
+            }
+            (Structure::Tagged(w), Structure::Tagged(r)) => {
+                for (name, w) in w {
+                    match r.get(name) {
+                        Some(r) => self.nested(name, w, r),
+                        None => {
+                            let len = self.enter(name);
+                            self.fail("variant was removed".to_string());
+                            self.path.truncate(len);
+                        }
+                    }
+                }
+            }
+            (Structure::Enum(w), Structure::Enum(r)) => {
+                for name in w.iter().filter(|name| !r.contains(*name)) {
+                    self.fail(format!("variant {name} was removed"));
+                }
+            }
+            (
+                Structure::Map { key: wk, value: wv },
+                Structure::Map { key: rk, value: rv },
+            )
+            | (
+                Structure::OrderedMap { key: wk, value: wv },
+                Structure::OrderedMap { key: rk, value: rv },
+            ) => {
+                self.nested("key", wk, rk);
+                self.nested("value", wv, rv);
+            }
+            (w, r) if w == r => {}
+            (w, r) => self.fail(format!("{} cannot be read as {}", kind(w), kind(r))),
+        }
+    }
+}
+
+/// Whether every value of `writer` is a value of `reader`.
+///
+/// 128-bit integers are encoded as bytes rather than CBOR integers, so they
+/// only match themselves.
+fn int_widens(writer: IntType, reader: IntType) -> bool {
+    fn range(ty: IntType) -> Option<(bool, u32)> {
+        match ty {
+            IntType::U8 => Some((false, 8)),
+            IntType::U16 => Some((false, 16)),
+            IntType::U32 => Some((false, 32)),
+            IntType::U64 => Some((false, 64)),
+            IntType::I8 => Some((true, 8)),
+            IntType::I16 => Some((true, 16)),
+            IntType::I32 => Some((true, 32)),
+            IntType::I64 => Some((true, 64)),
+            IntType::U128 | IntType::I128 => None,
+        }
+    }
+    if writer == reader {
+        return true;
+    }
+    match (range(writer), range(reader)) {
+        (Some((false, w)), Some((false, r))) => r >= w,
+        (Some((false, w)), Some((true, r))) => r > w,
+        (Some((true, w)), Some((true, r))) => r >= w,
+        _ => false,
+    }
+}
+
+pub(crate) fn kind(structure: &Structure) -> &'static str {
+    match structure {
+        Structure::Bool => "Bool",
+        Structure::Char => "Char",
+        Structure::Unicode => "Unicode",
+        Structure::ByteString => "ByteString",
+        Structure::Int(_) => "Int",
+        Structure::Float(_) => "Float",
+        Structure::Unit => "Unit",
+        Structure::Sequence(_) => "Sequence",
+        Structure::Tuple(_) => "Tuple",
+        Structure::Record(_) => "Record",
+        Structure::Tagged(_) => "Tagged",
+        Structure::Enum(_) => "Enum",
+        Structure::Map { .. } => "Map",
+        Structure::OrderedMap { .. } => "OrderedMap",
+        Structure::Bond(_) => "Bond",
+        Structure::SelfRef(_) => "SelfRef",
+        Structure::Defaulted(_) => "Defaulted",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(names: &[&str]) -> Structure {
+        Structure::Enum(names.iter().map(|s| s.to_string()).collect())
+    }
+
+    fn v1() -> Structure {
+        Structure::record([
+            ("id", Structure::Int(IntType::U32)),
+            ("status", status(&["Open", "Done"])),
+            ("tags", Structure::sequence(Structure::Unicode)),
+        ])
+    }
+
+    fn v2() -> Structure {
+        Structure::record([
+            ("id", Structure::Int(IntType::U64)),
+            ("status", status(&["Open", "Done", "Archived"])),
+            ("tags", Structure::sequence(Structure::Unicode)),
+            ("note", Structure::defaulted(Structure::option(Structure::Unicode))),
+        ])
+    }
+
+    #[test]
+    fn old_data_reads_under_evolved_schema() {
+        assert!(v1().is_compatible_with(&v2()).is_compatible());
+        assert!(v2().is_compatible_with(&v2()).is_compatible());
+    }
+
+    #[test]
+    fn reports_each_incompatibility_with_path() {
+        let report = v2().is_compatible_with(&v1());
+        let paths: Vec<_> = report
+            .incompatibilities
+            .iter()
+            .map(|i| i.path.as_str())
+            .collect();
+        assert_eq!(paths, ["id", "status"]);
+
+        let list = |inner| Structure::record([("items", Structure::sequence(inner))]);
+        let required = list(Structure::record([("due", Structure::Int(IntType::I64))]));
+        let report = list(Structure::record([])).is_compatible_with(&required);
+        assert_eq!(report.incompatibilities[0].path, "items[].due");
+    }
+
+    #[test]
+    fn int_widening() {
+        assert!(int_widens(IntType::U32, IntType::I64));
+        assert!(!int_widens(IntType::U32, IntType::I32));
+        assert!(!int_widens(IntType::I8, IntType::U64));
+        assert!(!int_widens(IntType::U64, IntType::U128));
+    }
+}
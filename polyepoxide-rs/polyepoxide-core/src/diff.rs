@@ -0,0 +1,269 @@
+//! Structural differences between two versions of a value.
+//!
+//! Both versions are walked side by side using their schema. Bonds with equal
+//! CIDs are skipped without loading them, so the cost follows the size of the
+//! change rather than of the value.
+
+use std::collections::{BTreeSet, HashMap};
+
+use cid::Cid;
+use ipld_core::ipld::Ipld;
+
+use crate::store::Store;
+use crate::traverse::{parse_to_ipld, ParseError};
+use crate::Structure;
+
+/// Error computing a diff.
+#[derive(Debug, thiserror::Error)]
+pub enum DiffError<E> {
+    #[error("value not found: {0}")]
+    NotFound(Cid),
+    #[error("schema not found: {0}")]
+    SchemaNotFound(Cid),
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error("invalid schema {0}: {1}")]
+    InvalidSchema(Cid, String),
+    #[error("store error: {0}")]
+    Store(E),
+}
+
+/// One difference between two versions of a value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffEntry {
+    /// JSON-pointer-like location, e.g. `/items/3/name`; empty at the root.
+    /// Bonds are followed transparently, so paths continue into their targets.
+    pub path: String,
+    pub change: Change,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    Added(Ipld),
+    Removed(Ipld),
+    /// Replaced by a value that isn't compared further, such as a primitive,
+    /// a different union variant or a bond whose target is missing.
+    Changed { old: Ipld, new: Ipld },
+}
+
+/// Lists the differences between the values at `old` and `new`, both of the
+/// type whose schema is at `schema`.
+///
+/// Records, unions and maps are compared by key and sequences by index, so
+/// an element inserted in the middle of a list shows up as changes to every
+/// later element.
+pub fn diff<S: Store>(
+    store: &S,
+    old: Cid,
+    new: Cid,
+    schema: Cid,
+) -> Result<Vec<DiffEntry>, DiffError<S::Error>> {
+    let mut differ = Differ {
+        store,
+        schemas: HashMap::new(),
+        path: String::new(),
+        entries: Vec::new(),
+    };
+    let old = differ.load(old)?.ok_or(DiffError::NotFound(old))?;
+    let new = differ.load(new)?.ok_or(DiffError::NotFound(new))?;
+    let schema = differ.schema(schema)?;
+    differ.walk(&old, &new, Some(&schema))?;
+    Ok(differ.entries)
+}
+
+struct Differ<'a, S> {
+    store: &'a S,
+    /// Schemas loaded so far. Their bonds are unresolved and looked up here.
+    schemas: HashMap<Cid, Structure>,
+    path: String,
+    entries: Vec<DiffEntry>,
+}
+
+impl<S: Store> Differ<'_, S> {
+    fn load(&self, cid: Cid) -> Result<Option<Ipld>, DiffError<S::Error>> {
+        match self.store.get(&cid).map_err(DiffError::Store)? {
+            Some(bytes) => Ok(Some(parse_to_ipld(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn schema(&mut self, cid: Cid) -> Result<Structure, DiffError<S::Error>> {
+        if let Some(schema) = self.schemas.get(&cid) {
+            return Ok(schema.clone());
+        }
+        let bytes = self
+            .store
+            .get(&cid)
+            .map_err(DiffError::Store)?
+            .ok_or(DiffError::SchemaNotFound(cid))?;
+        let schema: Structure = serde_ipld_dagcbor::from_slice(&bytes)
+            .map_err(|e| DiffError::InvalidSchema(cid, e.to_string()))?;
+        self.schemas.insert(cid, schema.clone());
+        Ok(schema)
+    }
+
+    fn push(&mut self, change: Change) -> Result<(), DiffError<S::Error>> {
+        self.entries.push(DiffEntry {
+            path: self.path.clone(),
+            change,
+        });
+        Ok(())
+    }
+
+    fn changed(&mut self, old: &Ipld, new: &Ipld) -> Result<(), DiffError<S::Error>> {
+        self.push(Change::Changed {
+            old: old.clone(),
+            new: new.clone(),
+        })
+    }
+
+    /// Walks `old` and `new` with the path extended by `segment`.
+    fn nested(
+        &mut self,
+        segment: &str,
+        old: Option<&Ipld>,
+        new: Option<&Ipld>,
+        schema: Option<Cid>,
+    ) -> Result<(), DiffError<S::Error>> {
+        let len = self.path.len();
+        self.path.push('/');
+        self.path.push_str(&segment.replace('~', "~0").replace('/', "~1"));
+        let result = match (old, new) {
+            (Some(old), Some(new)) => match schema {
+                Some(cid) => {
+                    let schema = self.schema(cid)?;
+                    self.walk(old, new, Some(&schema))
+                }
+                None => self.walk(old, new, None),
+            },
+            (Some(old), None) => self.push(Change::Removed(old.clone())),
+            (None, Some(new)) => self.push(Change::Added(new.clone())),
+            (None, None) => Ok(()),
+        };
+        self.path.truncate(len);
+        result
+    }
+
+    /// Compares two values. Without a schema, as for recursive types
+    /// (`SelfRef`), their IPLD structure is compared and links are not
+    /// followed.
+    fn walk(
+        &mut self,
+        old: &Ipld,
+        new: &Ipld,
+        schema: Option<&Structure>,
+    ) -> Result<(), DiffError<S::Error>> {
+        if old == new {
+            return Ok(());
+        }
+        match (old, new, schema) {
+            (_, _, Some(Structure::Defaulted(inner))) => {
+                let inner = self.schema(inner.cid())?;
+                self.walk(old, new, Some(&inner))
+            }
+            (Ipld::Link(old_cid), Ipld::Link(new_cid), Some(Structure::Bond(target))) => {
+                match (self.load(*old_cid)?, self.load(*new_cid)?) {
+                    (Some(old), Some(new)) => {
+                        let target = self.schema(target.cid())?;
+                        self.walk(&old, &new, Some(&target))
+                    }
+                    _ => self.changed(old, new),
+                }
+            }
+            (Ipld::Map(o), Ipld::Map(n), Some(Structure::Tagged(variants))) => {
+                match (o.iter().next(), n.iter().next()) {
+                    (Some((o_name, o_value)), Some((n_name, n_value))) if o_name == n_name => {
+                        let schema = variants.get(o_name).map(|bond| bond.cid());
+                        self.nested(o_name, Some(o_value), Some(n_value), schema)
+                    }
+                    _ => self.changed(old, new),
+                }
+            }
+            (Ipld::Map(o), Ipld::Map(n), _) => {
+                let keys: BTreeSet<&String> = o.keys().chain(n.keys()).collect();
+                for key in keys {
+                    let schema = match schema {
+                        Some(Structure::Record(fields)) => fields.get(key).map(|b| b.cid()),
+                        Some(Structure::Map { value, .. })
+                        | Some(Structure::OrderedMap { value, .. }) => Some(value.cid()),
+                        _ => None,
+                    };
+                    self.nested(key, o.get(key), n.get(key), schema)?;
+                }
+                Ok(())
+            }
+            (Ipld::List(o), Ipld::List(n), _) => {
+                for i in 0..o.len().max(n.len()) {
+                    let schema = match schema {
+                        Some(Structure::Sequence(inner)) => Some(inner.cid()),
+                        Some(Structure::Tuple(elems)) => elems.get(i).map(|b| b.cid()),
+                        _ => None,
+                    };
+                    self.nested(&i.to_string(), o.get(i), n.get(i), schema)?;
+                }
+                Ok(())
+            }
+            _ => self.changed(old, new),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Bond, MemoryStore, Oxide, Solvent};
+
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, Oxide)]
+    #[oxide(crate = crate)]
+    struct Item {
+        name: String,
+        count: u32,
+    }
+
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, Oxide)]
+    #[oxide(crate = crate)]
+    struct Inventory {
+        owner: String,
+        items: Vec<Bond<Item>>,
+    }
+
+    fn snapshot(solvent: &mut Solvent, store: &MemoryStore, items: &[(&str, u32)]) -> Cid {
+        let items = items
+            .iter()
+            .map(|(name, count)| {
+                solvent.bond(Item {
+                    name: name.to_string(),
+                    count: *count,
+                })
+            })
+            .collect();
+        let cell = solvent.add(Inventory {
+            owner: "ada".to_string(),
+            items,
+        });
+        solvent.persist_cell(&cell, store).unwrap().0
+    }
+
+    #[test]
+    fn reports_changes_through_bonds() {
+        let mut solvent = Solvent::new();
+        let store = MemoryStore::new();
+        let old = snapshot(&mut solvent, &store, &[("lamp", 1), ("desk", 1)]);
+        let new = snapshot(&mut solvent, &store, &[("lamp", 2), ("desk", 1), ("chair", 4)]);
+
+        let entries = diff(&store, old, new, Inventory::schema().compute_cid()).unwrap();
+        let paths: Vec<_> = entries.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(paths, ["/items/0/count", "/items/2"]);
+        assert_eq!(
+            entries[0].change,
+            Change::Changed {
+                old: Ipld::Integer(1),
+                new: Ipld::Integer(2)
+            }
+        );
+        assert!(matches!(entries[1].change, Change::Added(Ipld::Link(_))));
+        assert!(diff(&store, old, old, Inventory::schema().compute_cid())
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,108 @@
+//! Bounding the cells a solvent keeps alive.
+//!
+//! A solvent holds a strong reference to every cell added to it, so a long
+//! session retains every value it ever touched. Evicting a cell drops that
+//! reference: the cell is freed once no value bonds to it, and loading it
+//! again fetches it from the store.
+//!
+//! Policies only evict cells nothing else holds, so every eviction frees its
+//! cell: a value goes before the cells it bonds to, which become evictable
+//! once it's freed, and cells callers still hold are kept.
+
+use std::collections::{BTreeMap, HashMap};
+
+use cid::Cid;
+
+/// Which cells a solvent drops as new ones are added.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// Keep every cell.
+    #[default]
+    Unbounded,
+    /// Keep at most this many cells, dropping the least recently used.
+    MaxCells(usize),
+    /// Keep at most this many encoded bytes of cells, dropping the least
+    /// recently used.
+    MaxBytes(usize),
+}
+
+impl EvictionPolicy {
+    pub(crate) fn exceeded_by(self, usage: &Usage) -> bool {
+        match self {
+            EvictionPolicy::Unbounded => false,
+            EvictionPolicy::MaxCells(max) => usage.entries.len() > max,
+            EvictionPolicy::MaxBytes(max) => usage.bytes > max,
+        }
+    }
+}
+
+/// Recency and encoded size of a solvent's own cells.
+#[derive(Debug, Default)]
+pub(crate) struct Usage {
+    clock: u64,
+    /// Last use and size of each cell.
+    entries: HashMap<Cid, (u64, usize)>,
+    /// Cells by last use, oldest first.
+    order: BTreeMap<u64, Cid>,
+    bytes: usize,
+}
+
+impl Usage {
+    pub fn insert(&mut self, cid: Cid, size: usize) {
+        self.remove(&cid);
+        self.clock += 1;
+        self.entries.insert(cid, (self.clock, size));
+        self.order.insert(self.clock, cid);
+        self.bytes += size;
+        #[cfg(feature = "metrics")]
+        crate::metered::solvent_cells_held(1, size);
+    }
+
+    /// Marks a cell as used now. Cells not tracked here are ignored.
+    pub fn touch(&mut self, cid: &Cid) {
+        if let Some((used, _)) = self.entries.get_mut(cid) {
+            self.order.remove(used);
+            self.clock += 1;
+            *used = self.clock;
+            self.order.insert(self.clock, *cid);
+        }
+    }
+
+    pub fn remove(&mut self, cid: &Cid) -> bool {
+        let Some((used, size)) = self.entries.remove(cid) else {
+            return false;
+        };
+        self.order.remove(&used);
+        self.bytes -= size;
+        #[cfg(feature = "metrics")]
+        crate::metered::solvent_cells_released(1, size);
+        true
+    }
+
+    /// Removes and returns the least recently used cell that is
+    /// `evictable`.
+    pub fn pop_oldest(&mut self, mut evictable: impl FnMut(&Cid) -> bool) -> Option<Cid> {
+        let (&used, &cid) = self.order.iter().find(|(_, cid)| evictable(cid))?;
+        self.order.remove(&used);
+        let (_, size) = self.entries.remove(&cid).expect("order lists tracked cells");
+        self.bytes -= size;
+        #[cfg(feature = "metrics")]
+        crate::metered::solvent_cells_released(1, size);
+        Some(cid)
+    }
+
+    pub fn size(&self, cid: &Cid) -> Option<usize> {
+        self.entries.get(cid).map(|&(_, size)| size)
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl Drop for Usage {
+    fn drop(&mut self) {
+        crate::metered::solvent_cells_released(self.entries.len(), self.bytes);
+    }
+}
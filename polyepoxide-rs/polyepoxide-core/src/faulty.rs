@@ -0,0 +1,285 @@
+//! Fault-injecting store wrapper for resilience testing.
+//!
+//! `FaultyStore` wraps another store and injects latency, errors, and
+//! corrupted payloads according to a seeded pseudo-random sequence, so tests
+//! exercising sync retry, verification, and resumption are reproducible.
+
+use cid::Cid;
+use std::io::{Cursor, Read};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::gc::GcStats;
+use crate::store::{Batch, Store};
+
+/// Error from a fault-injecting store.
+#[derive(Debug, thiserror::Error)]
+pub enum FaultyError<E> {
+    #[error("injected fault on {op} {cid}")]
+    Injected { op: &'static str, cid: Cid },
+    #[error(transparent)]
+    Inner(E),
+}
+
+/// Counters of faults injected so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FaultStats {
+    pub errors: u64,
+    pub corruptions: u64,
+}
+
+/// A store wrapper that injects configurable faults.
+///
+/// Faults can be switched off with `heal`, which lets a test fail an
+/// operation midway and then check that retrying it completes.
+pub struct FaultyStore<S> {
+    inner: S,
+    latency: Duration,
+    get_error_rate: f64,
+    put_error_rate: f64,
+    corruption_rate: f64,
+    /// Number of operations allowed to succeed before every operation fails.
+    fail_after: Option<u64>,
+    rng: Mutex<u64>,
+    operations: AtomicU64,
+    errors: AtomicU64,
+    corruptions: AtomicU64,
+    healed: AtomicBool,
+}
+
+impl<S: Store> FaultyStore<S> {
+    /// Wraps a store. Without further configuration no faults are injected.
+    pub fn new(inner: S, seed: u64) -> Self {
+        Self {
+            inner,
+            latency: Duration::ZERO,
+            get_error_rate: 0.0,
+            put_error_rate: 0.0,
+            corruption_rate: 0.0,
+            fail_after: None,
+            rng: Mutex::new(seed),
+            operations: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            corruptions: AtomicU64::new(0),
+            healed: AtomicBool::new(false),
+        }
+    }
+
+    /// Delays every operation by the given duration.
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Fails the given fraction of `get` and `has` calls.
+    pub fn with_get_error_rate(mut self, rate: f64) -> Self {
+        self.get_error_rate = rate;
+        self
+    }
+
+    /// Fails the given fraction of `put` calls.
+    pub fn with_put_error_rate(mut self, rate: f64) -> Self {
+        self.put_error_rate = rate;
+        self
+    }
+
+    /// Flips a byte in the given fraction of payloads returned by `get`.
+    pub fn with_corruption_rate(mut self, rate: f64) -> Self {
+        self.corruption_rate = rate;
+        self
+    }
+
+    /// Lets the first `n` operations succeed and fails every one after.
+    pub fn with_fail_after(mut self, n: u64) -> Self {
+        self.fail_after = Some(n);
+        self
+    }
+
+    /// Stops injecting faults; latency is kept.
+    pub fn heal(&self) {
+        self.healed.store(true, Ordering::SeqCst);
+    }
+
+    pub fn stats(&self) -> FaultStats {
+        FaultStats {
+            errors: self.errors.load(Ordering::SeqCst),
+            corruptions: self.corruptions.load(Ordering::SeqCst),
+        }
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Returns a value in [0, 1) from a splitmix64 sequence.
+    fn roll(&self) -> f64 {
+        let mut state = self.rng.lock().unwrap();
+        *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = *state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Applies latency and decides whether this operation fails.
+    fn inject(
+        &self,
+        op: &'static str,
+        cid: &Cid,
+        error_rate: f64,
+    ) -> Result<(), FaultyError<S::Error>> {
+        if !self.latency.is_zero() {
+            std::thread::sleep(self.latency);
+        }
+        if self.healed.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        let seen = self.operations.fetch_add(1, Ordering::SeqCst);
+        let exhausted = self.fail_after.is_some_and(|n| seen >= n);
+        if exhausted || (error_rate > 0.0 && self.roll() < error_rate) {
+            self.errors.fetch_add(1, Ordering::SeqCst);
+            return Err(FaultyError::Injected { op, cid: *cid });
+        }
+        Ok(())
+    }
+
+    /// Like `inject`, once for a whole batched call. The call fails as one,
+    /// as it would on a store with atomic batches.
+    fn inject_many<'a>(
+        &self,
+        op: &'static str,
+        mut cids: impl Iterator<Item = &'a Cid>,
+        error_rate: f64,
+    ) -> Result<(), FaultyError<S::Error>> {
+        match cids.next() {
+            Some(cid) => self.inject(op, cid, error_rate),
+            None => Ok(()),
+        }
+    }
+
+    /// Flips a byte of a payload read, at the corruption rate.
+    fn corrupt(&self, value: &mut Option<Vec<u8>>) {
+        if let Some(bytes) = value.as_mut().filter(|b| !b.is_empty())
+            && !self.healed.load(Ordering::SeqCst)
+            && self.corruption_rate > 0.0
+            && self.roll() < self.corruption_rate
+        {
+            let idx = (self.roll() * bytes.len() as f64) as usize;
+            bytes[idx] ^= 0xff;
+            self.corruptions.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+impl<S: Store> Store for FaultyStore<S> {
+    type Error = FaultyError<S::Error>;
+
+    fn get(&self, cid: &Cid) -> Result<Option<Vec<u8>>, Self::Error> {
+        self.inject("get", cid, self.get_error_rate)?;
+        let mut value = self.inner.get(cid).map_err(FaultyError::Inner)?;
+        self.corrupt(&mut value);
+        Ok(value)
+    }
+
+    fn get_reader(&self, cid: &Cid) -> Result<Option<Box<dyn Read + '_>>, Self::Error> {
+        self.inject("get", cid, self.get_error_rate)?;
+        if self.corruption_rate > 0.0 && !self.healed.load(Ordering::SeqCst) {
+            let mut value = self.inner.get(cid).map_err(FaultyError::Inner)?;
+            self.corrupt(&mut value);
+            return Ok(value.map(|bytes| Box::new(Cursor::new(bytes)) as Box<dyn Read>));
+        }
+        self.inner.get_reader(cid).map_err(FaultyError::Inner)
+    }
+
+    fn get_many(&self, cids: &[Cid]) -> Result<Vec<Option<Vec<u8>>>, Self::Error> {
+        self.inject_many("get_many", cids.iter(), self.get_error_rate)?;
+        let mut values = self.inner.get_many(cids).map_err(FaultyError::Inner)?;
+        for value in &mut values {
+            self.corrupt(value);
+        }
+        Ok(values)
+    }
+
+    fn put(&self, cid: &Cid, value: &[u8]) -> Result<(), Self::Error> {
+        self.inject("put", cid, self.put_error_rate)?;
+        self.inner.put(cid, value).map_err(FaultyError::Inner)
+    }
+
+    fn put_schema(&self, cid: &Cid, value: &[u8]) -> Result<(), Self::Error> {
+        self.inject("put", cid, self.put_error_rate)?;
+        self.inner.put_schema(cid, value).map_err(FaultyError::Inner)
+    }
+
+    fn has(&self, cid: &Cid) -> Result<bool, Self::Error> {
+        self.inject("has", cid, self.get_error_rate)?;
+        self.inner.has(cid).map_err(FaultyError::Inner)
+    }
+
+    fn put_many(&self, nodes: &[(&Cid, &[u8])]) -> Result<(), Self::Error> {
+        let cids = nodes.iter().map(|(cid, _)| *cid);
+        self.inject_many("put_many", cids, self.put_error_rate)?;
+        self.inner.put_many(nodes).map_err(FaultyError::Inner)
+    }
+
+    fn write_batch(&self, batch: &Batch) -> Result<(), Self::Error> {
+        self.inject_many("write_batch", batch.cids(), self.put_error_rate)?;
+        self.inner.write_batch(batch).map_err(FaultyError::Inner)
+    }
+
+    fn delete(&self, cid: &Cid) -> Result<(), Self::Error> {
+        self.inject("delete", cid, self.put_error_rate)?;
+        self.inner.delete(cid).map_err(FaultyError::Inner)
+    }
+
+    fn delete_many(&self, cids: &[Cid]) -> Result<(), Self::Error> {
+        self.inject_many("delete_many", cids.iter(), self.put_error_rate)?;
+        self.inner.delete_many(cids).map_err(FaultyError::Inner)
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = Result<Cid, Self::Error>> + '_> {
+        Box::new(self.inner.iter().map(|r| r.map_err(FaultyError::Inner)))
+    }
+
+    fn gc(&self, roots: &[Cid]) -> Result<GcStats, Self::Error> {
+        self.inner.gc(roots).map_err(FaultyError::Inner)
+    }
+
+    fn compact(&self) -> Result<(), Self::Error> {
+        self.inner.compact().map_err(FaultyError::Inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oxide::compute_cid;
+    use crate::MemoryStore;
+
+    fn batch(blocks: &[&str]) -> Batch {
+        let mut batch = Batch::new();
+        for block in blocks {
+            batch.put(compute_cid(block.as_bytes()), block.as_bytes().to_vec());
+        }
+        batch
+    }
+
+    #[test]
+    fn batches_fail_as_one() {
+        let store = FaultyStore::new(MemoryStore::new(), 1).with_fail_after(1);
+
+        store.write_batch(&batch(&["a", "b", "c"])).unwrap();
+        for block in ["a", "b", "c"] {
+            assert!(store.inner().has(&compute_cid(block.as_bytes())).unwrap());
+        }
+
+        // Fails before writing anything, rather than after the first block
+        assert!(store.write_batch(&batch(&["d", "e"])).is_err());
+        for block in ["d", "e"] {
+            assert!(!store.inner().has(&compute_cid(block.as_bytes())).unwrap());
+        }
+        assert_eq!(store.stats().errors, 1);
+    }
+}
@@ -0,0 +1,56 @@
+//! Reachability marking for garbage collection.
+//!
+//! Bonds are encoded as DAG-CBOR links (tag 42), and so are the bonds between
+//! schema nodes, so reachability can be computed from the raw bytes without
+//! resolving schemas. Values don't link to their schemas, though, so stores
+//! keep everything written as a schema when they collect.
+
+use cid::Cid;
+use ipld_core::ipld::Ipld;
+use std::collections::HashSet;
+
+use crate::store::Store;
+use crate::traverse::parse_to_ipld;
+
+/// Result of a garbage collection pass.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GcStats {
+    /// Nodes kept because they are reachable from a root.
+    pub retained: usize,
+    /// Nodes deleted.
+    pub freed: usize,
+    /// Total size of deleted values in bytes.
+    pub freed_bytes: u64,
+}
+
+/// Returns every CID reachable from `roots` that is present in the store.
+///
+/// Nodes that fail to parse are kept but not traversed.
+pub fn reachable<S: Store + ?Sized>(store: &S, roots: &[Cid]) -> Result<HashSet<Cid>, S::Error> {
+    let mut marked = HashSet::new();
+    let mut stack: Vec<Cid> = roots.to_vec();
+
+    while let Some(cid) = stack.pop() {
+        if marked.contains(&cid) {
+            continue;
+        }
+        let Some(bytes) = store.get(&cid)? else {
+            continue;
+        };
+        marked.insert(cid);
+        if let Ok(ipld) = parse_to_ipld(&bytes) {
+            push_links(&ipld, &mut stack);
+        }
+    }
+
+    Ok(marked)
+}
+
+pub(crate) fn push_links(ipld: &Ipld, stack: &mut Vec<Cid>) {
+    match ipld {
+        Ipld::Link(cid) => stack.push(*cid),
+        Ipld::List(items) => items.iter().for_each(|item| push_links(item, stack)),
+        Ipld::Map(map) => map.values().for_each(|v| push_links(v, stack)),
+        _ => {}
+    }
+}
@@ -0,0 +1,390 @@
+//! Secondary indexes over stored values.
+//!
+//! Finding an item by name would otherwise mean decoding every block. An
+//! index reads keys out of values of one type through a `Path`, such as
+//! `name` or `tags[*]`, and maps each key to the CIDs of the values holding
+//! it. Entries are named `<index>/<key>/<cid>` and kept by an `IndexStore`
+//! apart from refs, so they neither pin values nor show up among refs.
+
+use std::collections::{HashMap, HashSet};
+
+use cid::Cid;
+use ipld_core::ipld::Ipld;
+
+use crate::solvent::PersistError;
+use crate::store::Store;
+use crate::traverse::{loaded_values, LoadedValue, Path, PathError, SelectError};
+use crate::{Bond, Cell, Oxide, Solvent, Tombstones};
+
+/// A store that also keeps index entries, mapping names to the CIDs of
+/// indexed values.
+///
+/// Entries are kept apart from refs (e.g. in `Category::Indexes`): they are
+/// not gc roots, so values only they point to are collected, and
+/// `Indexes::prune` drops entries left behind.
+pub trait IndexStore: Store {
+    /// Records an entry, overwriting one of the same name.
+    fn set_index_entry(&self, name: &str, cid: &Cid) -> Result<(), Self::Error>;
+
+    /// Removes an entry. Removing a missing entry is not an error.
+    fn delete_index_entry(&self, name: &str) -> Result<(), Self::Error>;
+
+    /// Lists the entries whose names start with `prefix`, sorted by name.
+    fn list_index_entries(&self, prefix: &str) -> Result<Vec<(String, Cid)>, Self::Error>;
+}
+
+impl<S: IndexStore> IndexStore for &S {
+    fn set_index_entry(&self, name: &str, cid: &Cid) -> Result<(), Self::Error> {
+        (*self).set_index_entry(name, cid)
+    }
+
+    fn delete_index_entry(&self, name: &str) -> Result<(), Self::Error> {
+        (*self).delete_index_entry(name)
+    }
+
+    fn list_index_entries(&self, prefix: &str) -> Result<Vec<(String, Cid)>, Self::Error> {
+        (*self).list_index_entries(prefix)
+    }
+}
+
+/// Error from maintaining or querying indexes.
+#[derive(Debug, thiserror::Error)]
+pub enum IndexError<E> {
+    #[error("no index named {0:?}")]
+    Unknown(String),
+    #[error(transparent)]
+    Persist(#[from] PersistError<E>),
+    #[error(transparent)]
+    Select(#[from] SelectError<E>),
+    #[error("store error: {0}")]
+    Store(E),
+}
+
+struct Index {
+    name: String,
+    schema: Cid,
+    path: Path,
+    rekey: fn(&str) -> Option<String>,
+}
+
+/// Index definitions, applied to values as they are persisted.
+///
+/// Indexes are not unique: a key maps to every value holding it, including
+/// older versions of a value that has since been replaced. Entries outlive
+/// the values they point to: `find` skips values a `gc` has removed, and
+/// `prune` drops their entries.
+#[derive(Default)]
+pub struct Indexes {
+    indexes: Vec<Index>,
+    deleted: Tombstones,
+}
+
+impl Indexes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Defines an index over values of type `T`, keyed by what `path`
+    /// selects in them. Keys are strings, numbers, booleans, bytes or links;
+    /// other selected values are not indexed.
+    ///
+    /// Panics if `name` is already defined or contains `/`.
+    pub fn define<T: Oxide>(&mut self, name: &str, path: &str) -> Result<(), PathError> {
+        self.define_with::<T>(name, path, |key| Some(key.to_string()))
+    }
+
+    /// Defines an index like `define`, whose keys are rewritten by `rekey`,
+    /// such as to index timestamps by day. Keys it maps to None are not
+    /// indexed.
+    pub fn define_with<T: Oxide>(
+        &mut self,
+        name: &str,
+        path: &str,
+        rekey: fn(&str) -> Option<String>,
+    ) -> Result<(), PathError> {
+        assert!(!name.contains('/'), "index name {:?} contains '/'", name);
+        assert!(
+            self.indexes.iter().all(|index| index.name != name),
+            "index {:?} is already defined",
+            name
+        );
+        let schema = T::schema();
+        self.indexes.push(Index {
+            name: name.to_string(),
+            schema: schema.compute_cid(),
+            path: Path::compile(path, &Bond::new(schema))?,
+            rekey,
+        });
+        Ok(())
+    }
+
+    /// Leaves the values `tombstones` deleted out of lookups, and has
+    /// `prune` drop their entries. Replaces tombstones set before.
+    pub fn set_tombstones(&mut self, tombstones: Tombstones) {
+        self.deleted = tombstones;
+    }
+
+    /// Persists a cell like `Solvent::persist_cell`, then indexes it and the
+    /// values bonded from it that are loaded in `solvent`.
+    pub fn persist_cell<T: Oxide, S: IndexStore>(
+        &self,
+        solvent: &Solvent,
+        cell: &Cell<T>,
+        store: &S,
+    ) -> Result<(Cid, Cid), IndexError<S::Error>> {
+        let cids = solvent.persist_cell(cell, store)?;
+        self.index_cell(cell, store)?;
+        Ok(cids)
+    }
+
+    /// Indexes an already stored cell and the loaded values bonded from it,
+    /// such as after pulling them from a peer. Returns the number of entries
+    /// written.
+    pub fn index_cell<T: Oxide, S: IndexStore>(
+        &self,
+        cell: &Cell<T>,
+        store: &S,
+    ) -> Result<usize, IndexError<S::Error>> {
+        if self.indexes.is_empty() {
+            return Ok(0);
+        }
+        let mut written = 0;
+        for LoadedValue { cid, schema, .. } in loaded_values(cell) {
+            written += self.index_stored(&cid, &schema.cid(), store)?;
+        }
+        Ok(written)
+    }
+
+    /// Indexes one stored value of type `T` without the values bonded from
+    /// it, such as a new message appended to an indexed conversation.
+    /// Returns the number of entries written.
+    pub fn index_value<T: Oxide, S: IndexStore>(
+        &self,
+        cid: &Cid,
+        store: &S,
+    ) -> Result<usize, IndexError<S::Error>> {
+        if self.indexes.is_empty() {
+            return Ok(0);
+        }
+        self.index_stored(cid, &T::schema().compute_cid(), store)
+    }
+
+    /// Returns the stored values whose key in index `name` is `key`.
+    pub fn find<S: IndexStore>(
+        &self,
+        store: &S,
+        name: &str,
+        key: &str,
+    ) -> Result<Vec<Cid>, IndexError<S::Error>> {
+        self.scan(store, name, &format!("{}/", escape(key)))
+    }
+
+    /// Returns the stored values whose key in index `name` starts with
+    /// `prefix`, ordered by key; e.g. dates in one month with `2024-05-`.
+    pub fn find_prefix<S: IndexStore>(
+        &self,
+        store: &S,
+        name: &str,
+        prefix: &str,
+    ) -> Result<Vec<Cid>, IndexError<S::Error>> {
+        self.scan(store, name, &escape(prefix))
+    }
+
+    /// Removes the entries of values no longer in the store, such as after
+    /// a `gc`, or deleted by the tombstones set, and returns how many were
+    /// removed.
+    pub fn prune<S: IndexStore>(&self, store: &S) -> Result<usize, IndexError<S::Error>> {
+        let entries = store.list_index_entries("").map_err(IndexError::Store)?;
+        let mut present = HashMap::new();
+        let mut pruned = 0;
+        for (name, cid) in entries {
+            let kept = match present.get(&cid) {
+                Some(&kept) => kept,
+                None => {
+                    let kept = !self.deleted.is_deleted(&cid)
+                        && store.has(&cid).map_err(IndexError::Store)?;
+                    present.insert(cid, kept);
+                    kept
+                }
+            };
+            if !kept {
+                store.delete_index_entry(&name).map_err(IndexError::Store)?;
+                pruned += 1;
+            }
+        }
+        Ok(pruned)
+    }
+
+    fn index_stored<S: IndexStore>(
+        &self,
+        cid: &Cid,
+        schema: &Cid,
+        store: &S,
+    ) -> Result<usize, IndexError<S::Error>> {
+        let mut written = 0;
+        for index in self.indexes.iter().filter(|index| index.schema == *schema) {
+            for found in index.path.select(store, cid)? {
+                if let Some(key) = key(&found.value).and_then(|key| (index.rekey)(&key)) {
+                    let name = format!("{}/{}/{}", index.name, escape(&key), cid);
+                    store
+                        .set_index_entry(&name, cid)
+                        .map_err(IndexError::Store)?;
+                    written += 1;
+                }
+            }
+        }
+        Ok(written)
+    }
+
+    fn scan<S: IndexStore>(
+        &self,
+        store: &S,
+        name: &str,
+        prefix: &str,
+    ) -> Result<Vec<Cid>, IndexError<S::Error>> {
+        if !self.indexes.iter().any(|index| index.name == name) {
+            return Err(IndexError::Unknown(name.to_string()));
+        }
+        let entries = store
+            .list_index_entries(&format!("{}/{}", name, prefix))
+            .map_err(IndexError::Store)?;
+        let mut seen = HashSet::new();
+        let mut cids = Vec::new();
+        for (_, cid) in entries {
+            if seen.insert(cid)
+                && !self.deleted.is_deleted(&cid)
+                && store.has(&cid).map_err(IndexError::Store)?
+            {
+                cids.push(cid);
+            }
+        }
+        Ok(cids)
+    }
+}
+
+/// Renders an index key, or None for values that can't be keys.
+fn key(value: &Ipld) -> Option<String> {
+    match value {
+        Ipld::String(s) => Some(s.clone()),
+        Ipld::Integer(i) => Some(i.to_string()),
+        Ipld::Float(f) => Some(f.to_string()),
+        Ipld::Bool(b) => Some(b.to_string()),
+        Ipld::Bytes(bytes) => Some(bytes.iter().map(|b| format!("{:02x}", b)).collect()),
+        Ipld::Link(cid) => Some(cid.to_string()),
+        Ipld::Null | Ipld::List(_) | Ipld::Map(_) => None,
+    }
+}
+
+/// Escapes `/` in keys, which would otherwise let a prefix search for `a/`
+/// match entries of the key `a/b`.
+fn escape(key: &str) -> String {
+    key.replace('%', "%25").replace('/', "%2F")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MemoryStore, RefStore, Store};
+
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, Oxide)]
+    #[oxide(crate = crate)]
+    struct Item {
+        name: String,
+        tags: Vec<String>,
+    }
+
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, Oxide)]
+    #[oxide(crate = crate)]
+    struct Inventory {
+        items: Vec<Bond<Item>>,
+    }
+
+    #[test]
+    fn finds_bonded_values_by_key() {
+        let store = MemoryStore::new();
+        let mut solvent = Solvent::new();
+        let mut indexes = Indexes::new();
+        indexes.define::<Item>("item-name", "name").unwrap();
+        indexes.define::<Item>("item-tag", "tags[*]").unwrap();
+        assert!(indexes.define::<Item>("item-size", "size").is_err());
+
+        let drill = solvent.bond(Item {
+            name: "drill".into(),
+            tags: vec!["tools/power".into()],
+        });
+        let saw = solvent.bond(Item {
+            name: "saw".into(),
+            tags: vec!["tools".into()],
+        });
+        let inventory = solvent.add(Inventory {
+            items: vec![drill.clone(), saw.clone()],
+        });
+        indexes.persist_cell(&solvent, &inventory, &store).unwrap();
+
+        assert_eq!(
+            indexes.find(&store, "item-name", "saw").unwrap(),
+            [saw.cid()]
+        );
+        assert_eq!(
+            indexes.find(&store, "item-tag", "tools").unwrap(),
+            [saw.cid()]
+        );
+        let tools = indexes.find_prefix(&store, "item-tag", "tools").unwrap();
+        assert_eq!(tools.len(), 2);
+        assert!(matches!(
+            indexes.find(&store, "item-id", "1"),
+            Err(IndexError::Unknown(_))
+        ));
+
+        // Entries are neither refs nor gc roots, and are pruned with their values
+        assert!(store.list_refs("").unwrap().is_empty());
+        store.gc(&[saw.cid()]).unwrap();
+        assert!(!store.has(&drill.cid()).unwrap());
+        assert!(indexes
+            .find(&store, "item-name", "drill")
+            .unwrap()
+            .is_empty());
+        assert_eq!(indexes.prune(&store).unwrap(), 2);
+        assert_eq!(store.list_index_entries("").unwrap().len(), 2);
+        assert_eq!(
+            indexes.find(&store, "item-name", "saw").unwrap(),
+            [saw.cid()]
+        );
+
+        // Tombstoned values are left out of lookups and pruned
+        let mut tombstones = Tombstones::new();
+        tombstones.delete(saw.cid(), 100);
+        indexes.set_tombstones(tombstones);
+        assert!(indexes.find(&store, "item-name", "saw").unwrap().is_empty());
+        assert!(store.has(&saw.cid()).unwrap());
+        assert_eq!(indexes.prune(&store).unwrap(), 2);
+        assert!(store.list_index_entries("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn indexes_single_values_by_rekeyed_keys() {
+        let store = MemoryStore::new();
+        let mut solvent = Solvent::new();
+        let mut indexes = Indexes::new();
+        let initial = |name: &str| name.get(..1).map(str::to_uppercase);
+        indexes
+            .define_with::<Item>("item-initial", "name", initial)
+            .unwrap();
+
+        let mut cids = Vec::new();
+        for name in ["drill", "Dowel", ""] {
+            let item = solvent.add(Item {
+                name: name.into(),
+                tags: Vec::new(),
+            });
+            solvent.persist_cell(&item, &store).unwrap();
+            indexes.index_value::<Item>(&item.cid(), &store).unwrap();
+            cids.push(item.cid());
+        }
+
+        let found = indexes.find(&store, "item-initial", "D").unwrap();
+        assert_eq!(found.len(), 2);
+        assert!(found.contains(&cids[0]) && found.contains(&cids[1]));
+        assert_eq!(store.list_index_entries("").unwrap().len(), 2);
+    }
+}
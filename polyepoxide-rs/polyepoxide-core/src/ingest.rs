@@ -0,0 +1,235 @@
+//! Screening of external content before it becomes a blob.
+//!
+//! Once a file is stored as an oxide it is immutable and gets replicated to
+//! every peer, so size limits, type restrictions, and malware scanning have
+//! to happen before that point. Crates that turn files into blobs take an
+//! `IngestPolicy` and call `screen` first.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// Error from screening content.
+#[derive(Debug, thiserror::Error)]
+pub enum IngestError {
+    #[error("content rejected: {0}")]
+    Rejected(String),
+    #[error("content quarantined to {path}: {reason}")]
+    Quarantined { reason: String, path: PathBuf },
+    #[error("scanner failed: {0}")]
+    Scanner(#[from] std::io::Error),
+}
+
+/// Rules content must satisfy before it is stored.
+///
+/// The default policy accepts everything.
+#[derive(Debug, Clone, Default)]
+pub struct IngestPolicy {
+    max_size: Option<usize>,
+    /// MIME patterns such as `image/png` or `image/*`; empty allows all.
+    allowed_mime_types: Vec<String>,
+    /// Scanner command; content is piped to stdin and exit status 0 means clean.
+    scanner: Option<Vec<String>>,
+    /// Where content flagged by the scanner is kept for inspection.
+    quarantine_dir: Option<PathBuf>,
+}
+
+impl IngestPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_size(mut self, bytes: usize) -> Self {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    /// Restricts content to the given MIME patterns (`type/subtype` or `type/*`).
+    pub fn with_allowed_mime_types<I, S>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_mime_types = patterns.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Runs an external scanner, e.g. `["clamscan", "--no-summary", "-"]`.
+    pub fn with_scanner<I, S>(mut self, argv: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.scanner = Some(argv.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Keeps flagged content in a directory instead of discarding it.
+    pub fn with_quarantine_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.quarantine_dir = Some(dir.into());
+        self
+    }
+
+    /// Checks content against the policy.
+    ///
+    /// `name` is only used to label quarantined files.
+    pub fn screen(&self, name: &str, mime_type: &str, data: &[u8]) -> Result<(), IngestError> {
+        if let Some(max) = self.max_size
+            && data.len() > max
+        {
+            return Err(IngestError::Rejected(format!(
+                "{} is {} bytes, limit is {}",
+                name,
+                data.len(),
+                max
+            )));
+        }
+
+        if !self.allowed_mime_types.is_empty()
+            && !self
+                .allowed_mime_types
+                .iter()
+                .any(|pattern| mime_matches(pattern, mime_type))
+        {
+            return Err(IngestError::Rejected(format!(
+                "{} has disallowed type {}",
+                name, mime_type
+            )));
+        }
+
+        if let Some(argv) = &self.scanner
+            && let Some(reason) = run_scanner(argv, data)?
+        {
+            return Err(match &self.quarantine_dir {
+                Some(dir) => IngestError::Quarantined {
+                    path: quarantine(dir, name, data)?,
+                    reason,
+                },
+                None => IngestError::Rejected(reason),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+fn mime_matches(pattern: &str, mime_type: &str) -> bool {
+    let mime_type = mime_type.split(';').next().unwrap_or("").trim();
+    match pattern.strip_suffix("/*") {
+        Some(prefix) => mime_type
+            .split_once('/')
+            .is_some_and(|(kind, _)| kind.eq_ignore_ascii_case(prefix)),
+        None => pattern.eq_ignore_ascii_case(mime_type),
+    }
+}
+
+/// Returns the scanner's report if it flagged the content.
+fn run_scanner(argv: &[String], data: &[u8]) -> Result<Option<String>, std::io::Error> {
+    let (program, args) = argv
+        .split_first()
+        .ok_or_else(|| std::io::Error::other("empty scanner command"))?;
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    // Write from another thread while reading stdout: a scanner reporting
+    // before it has read everything would otherwise block on a full pipe
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let (written, output) = std::thread::scope(|scope| {
+        // Dropping stdin when done closes it
+        let writer = scope.spawn(move || stdin.write_all(data));
+        let output = child.wait_with_output();
+        let written = writer.join().expect("scanner input thread panicked");
+        (written, output)
+    });
+    // The scanner may exit early without reading everything
+    match written {
+        Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => return Err(e),
+        _ => {}
+    }
+
+    let output = output?;
+    if output.status.success() {
+        return Ok(None);
+    }
+    let report = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Ok(Some(if report.is_empty() {
+        format!("scanner exited with {}", output.status)
+    } else {
+        report
+    }))
+}
+
+fn quarantine(dir: &Path, name: &str, data: &[u8]) -> Result<PathBuf, std::io::Error> {
+    std::fs::create_dir_all(dir)?;
+    // Keep only the final path component so names can't escape the directory
+    let file_name = Path::new(name)
+        .file_name()
+        .map_or_else(|| "content".into(), |n| n.to_string_lossy().into_owned());
+    let path = dir.join(format!("{}.{}", crate::oxide::compute_cid(data), file_name));
+    std::fs::write(&path, data)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn size_and_mime_limits() {
+        let policy = IngestPolicy::new()
+            .with_max_size(4)
+            .with_allowed_mime_types(["image/*", "application/pdf"]);
+
+        assert!(policy.screen("a.png", "image/png", b"png").is_ok());
+        assert!(policy.screen("a.pdf", "application/pdf; q=1", b"pdf").is_ok());
+        assert!(matches!(
+            policy.screen("a.png", "image/png", b"too large"),
+            Err(IngestError::Rejected(_))
+        ));
+        assert!(matches!(
+            policy.screen("a.exe", "application/x-msdownload", b"MZ"),
+            Err(IngestError::Rejected(_))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn scanner_verdicts() {
+        let clean = IngestPolicy::new().with_scanner(["true"]);
+        assert!(clean.screen("a.txt", "text/plain", b"hello").is_ok());
+
+        let dir = std::env::temp_dir().join(format!("px-quarantine-{}", std::process::id()));
+        let flagged = IngestPolicy::new()
+            .with_scanner(["sh", "-c", "cat >/dev/null; echo FOUND; exit 1"])
+            .with_quarantine_dir(&dir);
+        match flagged.screen("../evil.txt", "text/plain", b"payload") {
+            Err(IngestError::Quarantined { reason, path }) => {
+                assert_eq!(reason, "FOUND");
+                assert!(path.starts_with(&dir));
+                assert_eq!(std::fs::read(&path).unwrap(), b"payload");
+            }
+            other => panic!("Expected Quarantined, got {:?}", other),
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn scanner_reporting_before_reading_doesnt_block() {
+        // Both the content and the report overflow a pipe buffer
+        let chatty = IngestPolicy::new().with_scanner([
+            "sh",
+            "-c",
+            "head -c 1000000 /dev/zero | tr '\\0' x; cat >/dev/null; exit 1",
+        ]);
+        let data = vec![0u8; 1_000_000];
+        assert!(matches!(
+            chatty.screen("a.bin", "application/octet-stream", &data),
+            Err(IngestError::Rejected(_))
+        ));
+    }
+}
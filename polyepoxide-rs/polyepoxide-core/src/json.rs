@@ -0,0 +1,878 @@
+//! JSON rendering of stored values, guided by their schemas.
+//!
+//! Bonds are rendered as `{"$ref": "<cid>"}` objects, expanded in place up to
+//! a given depth. Expanded objects keep their `$ref`.
+//!
+//! [`to_json_value`] and [`from_json_value`] convert an in-memory oxide
+//! to and from the same representation, with bonds always as `$ref`s.
+//! [`import_json`] stores a document of that form as a DAG of values.
+
+use std::collections::BTreeMap;
+
+use cid::Cid;
+use ipld_core::ipld::Ipld;
+use serde_json::{Map, Number, Value as JsonValue};
+
+use crate::canonical::canonicalize;
+use crate::compat::kind;
+use crate::sync::schema_bonds;
+use crate::traverse::{parse_to_ipld, ParseError, Path, SelectError};
+use crate::{compute_cid, Batch, Bond, IntType, MemoryStore, Oxide, Solvent, Store, Structure};
+
+/// Error rendering a value as JSON.
+#[derive(Debug, thiserror::Error)]
+pub enum JsonError<E> {
+    #[error("value not found: {0}")]
+    NotFound(Cid),
+    #[error("schema not found: {0}")]
+    SchemaNotFound(Cid),
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error("invalid schema {0}: {1}")]
+    InvalidSchema(Cid, String),
+    #[error("store error: {0}")]
+    Store(E),
+}
+
+/// Error converting JSON back into an oxide.
+#[derive(Debug, thiserror::Error)]
+pub enum FromJsonError {
+    #[error("invalid $ref: {0}")]
+    InvalidRef(String),
+    #[error("invalid base64: {0}")]
+    InvalidBase64(#[from] base64::DecodeError),
+    #[error("decode error: {0}")]
+    Decode(String),
+}
+
+/// Error importing JSON as stored values.
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError<E> {
+    #[error("schema not found: {0}")]
+    SchemaNotFound(Cid),
+    #[error(transparent)]
+    Invalid(#[from] InvalidJson),
+    #[error("store error: {0}")]
+    Store(E),
+}
+
+/// A part of a JSON document that doesn't match its schema.
+#[derive(Debug, thiserror::Error)]
+#[error("invalid value at {path:?}: {reason}")]
+pub struct InvalidJson {
+    /// JSON pointer to the part, e.g. `/items/3/name`; empty at the root.
+    pub path: String,
+    pub reason: String,
+}
+
+/// Renders the value at `cid`, expanding bonds up to `depth` levels deep
+/// (0 renders only `$ref`s). Its schema and all nested schemas must be in
+/// `schemas`; see [`load_schema`].
+pub fn to_json<S: Store>(
+    store: &S,
+    schemas: &Solvent,
+    cid: Cid,
+    schema_cid: Cid,
+    depth: usize,
+) -> Result<JsonValue, JsonError<S::Error>> {
+    let bytes = store
+        .get(&cid)
+        .map_err(JsonError::Store)?
+        .ok_or(JsonError::NotFound(cid))?;
+
+    let ipld = parse_to_ipld(&bytes)?;
+
+    let schema_cell = schemas
+        .get::<Structure>(&schema_cid)
+        .ok_or(JsonError::SchemaNotFound(schema_cid))?;
+
+    ipld_to_json(store, schemas, &ipld, schema_cell.value(), depth)
+}
+
+/// Renders the values `path` selects from the one at `cid` as an object
+/// keyed by where each was found, e.g. `items[2].name`, expanding bonds in
+/// them up to `depth` levels deep.
+pub fn select_to_json<S: Store>(
+    store: &S,
+    schemas: &Solvent,
+    cid: Cid,
+    path: &Path,
+    depth: usize,
+) -> Result<JsonValue, JsonError<S::Error>> {
+    let target = path.target();
+    let schema = target
+        .value()
+        .ok_or(JsonError::SchemaNotFound(target.cid()))?;
+    let matches = path.select(store, &cid).map_err(|e| match e {
+        SelectError::Store(e) => JsonError::Store(e),
+        SelectError::Missing(cid) => JsonError::NotFound(cid),
+        SelectError::Parse(e) => JsonError::Parse(e),
+    })?;
+    let mut selected = Map::new();
+    for found in matches {
+        let value = ipld_to_json(store, schemas, &found.value, schema, depth)?;
+        selected.insert(found.path, value);
+    }
+    Ok(JsonValue::Object(selected))
+}
+
+/// Renders the value at `cid` without a schema, expanding every link up to
+/// `depth` levels deep. Links that aren't bonds, such as schema references,
+/// are expanded too.
+pub fn to_json_untyped<S: Store>(
+    store: &S,
+    cid: Cid,
+    depth: usize,
+) -> Result<JsonValue, JsonError<S::Error>> {
+    let bytes = store
+        .get(&cid)
+        .map_err(JsonError::Store)?
+        .ok_or(JsonError::NotFound(cid))?;
+    untyped_to_json(store, &parse_to_ipld(&bytes)?, depth)
+}
+
+fn untyped_to_json<S: Store>(
+    store: &S,
+    ipld: &Ipld,
+    depth: usize,
+) -> Result<JsonValue, JsonError<S::Error>> {
+    match ipld {
+        Ipld::Link(target_cid) if depth > 0 => {
+            let Some(bytes) = store.get(target_cid).map_err(JsonError::Store)? else {
+                return Ok(raw_to_json(ipld));
+            };
+            let mut result = untyped_to_json(store, &parse_to_ipld(&bytes)?, depth - 1)?;
+            if let JsonValue::Object(ref mut obj) = result {
+                obj.insert("$ref".to_string(), JsonValue::String(target_cid.to_string()));
+            }
+            Ok(result)
+        }
+        Ipld::List(arr) => Ok(JsonValue::Array(
+            arr.iter()
+                .map(|elem| untyped_to_json(store, elem, depth))
+                .collect::<Result<_, _>>()?,
+        )),
+        Ipld::Map(map) => Ok(JsonValue::Object(
+            map.iter()
+                .map(|(k, v)| Ok((k.clone(), untyped_to_json(store, v, depth)?)))
+                .collect::<Result<_, _>>()?,
+        )),
+        _ => Ok(raw_to_json(ipld)),
+    }
+}
+
+/// Loads a schema and all schemas nested in it from `store` into `schemas`.
+pub fn load_schema<S: Store>(
+    store: &S,
+    schemas: &mut Solvent,
+    cid: Cid,
+) -> Result<(), JsonError<S::Error>> {
+    // Nested schemas are added first, so that their parents' bonds resolve
+    let mut stack = vec![(cid, None)];
+    while let Some((cid, schema)) = stack.pop() {
+        if schemas.contains(&cid) {
+            continue;
+        }
+        let schema: Structure = match schema {
+            Some(schema) => schema,
+            None => {
+                let bytes = store
+                    .get(&cid)
+                    .map_err(JsonError::Store)?
+                    .ok_or(JsonError::SchemaNotFound(cid))?;
+                serde_ipld_dagcbor::from_slice(&bytes)
+                    .map_err(|e| JsonError::InvalidSchema(cid, e.to_string()))?
+            }
+        };
+        let nested: Vec<Cid> = schema_bonds(&schema)
+            .into_iter()
+            .filter(|nested| !schemas.contains(nested))
+            .collect();
+        if nested.is_empty() {
+            schemas.add(schema);
+        } else {
+            stack.push((cid, Some(schema)));
+            stack.extend(nested.into_iter().map(|nested| (nested, None)));
+        }
+    }
+    Ok(())
+}
+
+fn ipld_to_json<S: Store>(
+    store: &S,
+    schemas: &Solvent,
+    ipld: &Ipld,
+    schema: &Structure,
+    depth: usize,
+) -> Result<JsonValue, JsonError<S::Error>> {
+    match (ipld, schema) {
+        (_, Structure::Defaulted(inner)) => match inner.value() {
+            Some(inner) => ipld_to_json(store, schemas, ipld, inner, depth),
+            None => Ok(raw_to_json(ipld)),
+        },
+        (Ipld::Link(target_cid), Structure::Bond(inner)) => {
+            if depth == 0 {
+                // Just output $ref
+                let mut obj = Map::new();
+                obj.insert("$ref".to_string(), JsonValue::String(target_cid.to_string()));
+                Ok(JsonValue::Object(obj))
+            } else {
+                // Expand the bond
+                if let Ok(Some(target_bytes)) = store.get(target_cid) {
+                    if let Ok(target_ipld) = parse_to_ipld(&target_bytes) {
+                        if let Some(inner_schema) = inner.value() {
+                            let mut result =
+                                ipld_to_json(store, schemas, &target_ipld, inner_schema, depth - 1)?;
+                            // Add $ref as metadata for expanded objects
+                            if let JsonValue::Object(ref mut obj) = result {
+                                obj.insert(
+                                    "$ref".to_string(),
+                                    JsonValue::String(target_cid.to_string()),
+                                );
+                            }
+                            return Ok(result);
+                        }
+                    }
+                }
+                // Fallback to just $ref
+                let mut obj = Map::new();
+                obj.insert("$ref".to_string(), JsonValue::String(target_cid.to_string()));
+                Ok(JsonValue::Object(obj))
+            }
+        }
+        (Ipld::Bytes(b), Structure::Int(int @ (IntType::U128 | IntType::I128))) => {
+            // Rendered as decimal strings, since JSON numbers lose precision
+            let Ok(bytes) = <[u8; 16]>::try_from(b.as_slice()) else {
+                return Ok(raw_to_json(ipld));
+            };
+            Ok(JsonValue::String(match int {
+                IntType::U128 => u128::from_be_bytes(bytes).to_string(),
+                _ => i128::from_be_bytes(bytes).to_string(),
+            }))
+        }
+        (Ipld::List(arr), Structure::ByteString) => {
+            // ByteString encodes as a list of integers
+            let bytes: Option<Vec<u8>> = arr
+                .iter()
+                .map(|b| match b {
+                    Ipld::Integer(n) => u8::try_from(*n).ok(),
+                    _ => None,
+                })
+                .collect();
+            match bytes {
+                Some(bytes) => {
+                    use base64::{engine::general_purpose::STANDARD, Engine};
+                    Ok(JsonValue::String(STANDARD.encode(bytes)))
+                }
+                None => Ok(raw_to_json(ipld)),
+            }
+        }
+        (Ipld::Map(map), Structure::Record(fields)) => {
+            let mut obj = Map::new();
+            for (name, field_schema_bond) in fields {
+                if let Some(fv) = map.get(name) {
+                    if let Some(field_schema) = field_schema_bond.value() {
+                        let json_val = ipld_to_json(store, schemas, fv, field_schema, depth)?;
+                        obj.insert(name.clone(), json_val);
+                    }
+                }
+            }
+            Ok(JsonValue::Object(obj))
+        }
+        (Ipld::List(arr), Structure::Sequence(inner)) => {
+            if let Some(inner_schema) = inner.value() {
+                let items: Result<Vec<_>, _> = arr
+                    .iter()
+                    .map(|elem| ipld_to_json(store, schemas, elem, inner_schema, depth))
+                    .collect();
+                Ok(JsonValue::Array(items?))
+            } else {
+                Ok(raw_to_json(ipld))
+            }
+        }
+        (Ipld::List(arr), Structure::Tuple(elems)) => {
+            let items: Result<Vec<_>, _> = arr
+                .iter()
+                .zip(elems.iter())
+                .map(|(v, s)| {
+                    if let Some(schema) = s.value() {
+                        ipld_to_json(store, schemas, v, schema, depth)
+                    } else {
+                        Ok(raw_to_json(v))
+                    }
+                })
+                .collect();
+            Ok(JsonValue::Array(items?))
+        }
+        (Ipld::Map(map), Structure::Tagged(variants)) => {
+            if map.len() == 1 {
+                if let Some((name, val)) = map.iter().next() {
+                    if let Some(variant_schema_bond) = variants.get(name) {
+                        if let Some(variant_schema) = variant_schema_bond.value() {
+                            let mut obj = Map::new();
+                            let json_val =
+                                ipld_to_json(store, schemas, val, variant_schema, depth)?;
+                            obj.insert(name.clone(), json_val);
+                            return Ok(JsonValue::Object(obj));
+                        }
+                    }
+                }
+            }
+            Ok(raw_to_json(ipld))
+        }
+        (Ipld::Map(map), Structure::Map { value: v, .. })
+        | (Ipld::Map(map), Structure::OrderedMap { value: v, .. }) => {
+            if let Some(vs) = v.value() {
+                let mut obj = Map::new();
+                for (mk, mv) in map {
+                    let json_val = ipld_to_json(store, schemas, mv, vs, depth)?;
+                    obj.insert(mk.clone(), json_val);
+                }
+                Ok(JsonValue::Object(obj))
+            } else {
+                Ok(raw_to_json(ipld))
+            }
+        }
+        _ => Ok(raw_to_json(ipld)),
+    }
+}
+
+/// Renders IPLD as JSON as is, with links as `$ref` objects.
+pub fn raw_to_json(ipld: &Ipld) -> JsonValue {
+    match ipld {
+        Ipld::Null => JsonValue::Null,
+        Ipld::Bool(b) => JsonValue::Bool(*b),
+        Ipld::Integer(n) => {
+            // i128 doesn't implement Into<Number>, so we need to convert via i64/u64
+            if *n >= 0 {
+                Number::from(*n as u64).into()
+            } else {
+                Number::from(*n as i64).into()
+            }
+        }
+        Ipld::Float(f) => Number::from_f64(*f)
+            .map(JsonValue::Number)
+            .unwrap_or(JsonValue::Null),
+        Ipld::String(s) => JsonValue::String(s.clone()),
+        Ipld::Bytes(b) => {
+            // Encode as base64
+            use base64::{engine::general_purpose::STANDARD, Engine};
+            JsonValue::String(STANDARD.encode(b))
+        }
+        Ipld::List(arr) => JsonValue::Array(arr.iter().map(raw_to_json).collect()),
+        Ipld::Map(map) => {
+            let obj: Map<String, JsonValue> = map
+                .iter()
+                .map(|(k, v)| (k.clone(), raw_to_json(v)))
+                .collect();
+            JsonValue::Object(obj)
+        }
+        Ipld::Link(cid) => {
+            let mut obj = Map::new();
+            obj.insert("$ref".to_string(), JsonValue::String(cid.to_string()));
+            JsonValue::Object(obj)
+        }
+    }
+}
+
+/// Renders an oxide as JSON, with bonds as `$ref`s and byte strings as base64.
+pub fn to_json_value<T: Oxide>(value: &T) -> JsonValue {
+    let ipld = parse_to_ipld(&value.to_bytes()).expect("encoder output should be valid IPLD");
+    ipld_to_json(&MemoryStore::new(), &Solvent::new(), &ipld, &T::schema(), 0)
+        .expect("rendering without expanding bonds doesn't touch the store")
+}
+
+/// Reads an oxide from JSON as produced by [`to_json_value`]. Bonds come
+/// back unresolved; expanded bonds are accepted, but only their `$ref` is read.
+pub fn from_json_value<T: Oxide>(value: JsonValue) -> Result<T, FromJsonError> {
+    let ipld = json_to_ipld(value, Some(&T::schema()))?;
+    let bytes =
+        serde_ipld_dagcbor::to_vec(&ipld).map_err(|e| FromJsonError::Decode(e.to_string()))?;
+    T::from_bytes(&bytes).map_err(|e| FromJsonError::Decode(e.to_string()))
+}
+
+/// Converts JSON to IPLD, using the schema where JSON is ambiguous: `$ref`
+/// objects, base64 strings and integral floats. Without a schema (below a
+/// `SelfRef`), every `$ref` object is read as a link.
+///
+/// [`import_json`] reads those leaves through here too, after checking them.
+fn json_to_ipld(value: JsonValue, schema: Option<&Structure>) -> Result<Ipld, FromJsonError> {
+    if let Some(Structure::Defaulted(inner)) = schema {
+        return json_to_ipld(value, inner.value());
+    }
+    let schema = schema.filter(|schema| !matches!(schema, Structure::SelfRef(_)));
+    Ok(match (value, schema) {
+        (JsonValue::Object(mut obj), Some(Structure::Bond(_)) | None)
+            if obj.contains_key("$ref") =>
+        {
+            let cid = match obj.remove("$ref") {
+                Some(JsonValue::String(cid)) => cid,
+                other => return Err(FromJsonError::InvalidRef(format!("{other:?}"))),
+            };
+            Ipld::Link(cid.parse().map_err(|_| FromJsonError::InvalidRef(cid))?)
+        }
+        (JsonValue::String(s), Some(Structure::ByteString)) => {
+            use base64::{engine::general_purpose::STANDARD, Engine};
+            let bytes = STANDARD.decode(s)?;
+            Ipld::List(bytes.into_iter().map(|b| Ipld::Integer(b.into())).collect())
+        }
+        (JsonValue::String(s), Some(Structure::Int(IntType::U128))) => {
+            let n: u128 = s
+                .parse()
+                .map_err(|_| FromJsonError::Decode(format!("invalid integer: {s}")))?;
+            Ipld::Bytes(n.to_be_bytes().to_vec())
+        }
+        (JsonValue::String(s), Some(Structure::Int(IntType::I128))) => {
+            let n: i128 = s
+                .parse()
+                .map_err(|_| FromJsonError::Decode(format!("invalid integer: {s}")))?;
+            Ipld::Bytes(n.to_be_bytes().to_vec())
+        }
+        (JsonValue::Number(n), Some(Structure::Float(_))) => {
+            Ipld::Float(n.as_f64().unwrap_or_default())
+        }
+        (
+            JsonValue::Object(obj),
+            Some(Structure::Record(fields) | Structure::Tagged(fields)),
+        ) => Ipld::Map(
+            obj.into_iter()
+                .map(|(k, v)| {
+                    let schema = fields.get(&k).and_then(Bond::value);
+                    Ok((k, json_to_ipld(v, schema)?))
+                })
+                .collect::<Result<_, FromJsonError>>()?,
+        ),
+        (
+            JsonValue::Object(obj),
+            Some(Structure::Map { value, .. } | Structure::OrderedMap { value, .. }),
+        ) => {
+            let schema = value.value();
+            Ipld::Map(
+                obj.into_iter()
+                    .map(|(k, v)| Ok((k, json_to_ipld(v, schema)?)))
+                    .collect::<Result<_, FromJsonError>>()?,
+            )
+        }
+        (JsonValue::Array(arr), Some(Structure::Sequence(inner))) => {
+            let schema = inner.value();
+            Ipld::List(
+                arr.into_iter()
+                    .map(|v| json_to_ipld(v, schema))
+                    .collect::<Result<_, _>>()?,
+            )
+        }
+        (JsonValue::Array(arr), Some(Structure::Tuple(elems))) => Ipld::List(
+            arr.into_iter()
+                .enumerate()
+                .map(|(i, v)| json_to_ipld(v, elems.get(i).and_then(Bond::value)))
+                .collect::<Result<_, _>>()?,
+        ),
+        (JsonValue::Array(arr), None) => Ipld::List(
+            arr.into_iter()
+                .map(|v| json_to_ipld(v, None))
+                .collect::<Result<_, _>>()?,
+        ),
+        (JsonValue::Object(obj), None) => Ipld::Map(
+            obj.into_iter()
+                .map(|(k, v)| Ok((k, json_to_ipld(v, None)?)))
+                .collect::<Result<_, FromJsonError>>()?,
+        ),
+        (value, _) => {
+            serde_json::from_value(value).map_err(|e| FromJsonError::Decode(e.to_string()))?
+        }
+    })
+}
+
+/// Stores a JSON document as a value of the type whose schema is at
+/// `schema_cid`, returning the CID of the root. Its schema and all nested
+/// schemas must be in `schemas`; see [`load_schema`].
+///
+/// The whole document is checked against the schema before anything is
+/// written. Bonds may be `$ref` objects or values in place, as rendered by
+/// [`to_json`]; values in place are stored as values of their own, ignoring
+/// any `$ref` they carry, so edited copies get new CIDs.
+///
+/// Options are arrays of at most one element, as [`to_json`] renders the
+/// fields of derived types. Their schema is the same as `Vec`'s, so an
+/// array too long for an option only fails when the value is decoded.
+pub fn import_json<S: Store>(
+    store: &S,
+    schemas: &Solvent,
+    value: JsonValue,
+    schema_cid: Cid,
+) -> Result<Cid, ImportError<S::Error>> {
+    let schema = schemas
+        .get::<Structure>(&schema_cid)
+        .ok_or(ImportError::SchemaNotFound(schema_cid))?;
+    let mut importer = Importer {
+        batch: Batch::new(),
+        path: String::new(),
+        frames: Vec::new(),
+    };
+    let cid = importer.block(value, schema.value())?;
+    store
+        .write_batch(&importer.batch)
+        .map_err(ImportError::Store)?;
+    Ok(cid)
+}
+
+struct Importer<'s> {
+    /// Converted values, each after the values it bonds to.
+    batch: Batch,
+    path: String,
+    /// Records and unions enclosing the current position, innermost last,
+    /// which stand in for the derived types `SelfRef`s count.
+    frames: Vec<&'s Structure>,
+}
+
+impl<'s> Importer<'s> {
+    fn invalid(&self, reason: impl Into<String>) -> InvalidJson {
+        InvalidJson {
+            path: self.path.clone(),
+            reason: reason.into(),
+        }
+    }
+
+    fn schema(&self, bond: &'s Bond<Structure>) -> Result<&'s Structure, InvalidJson> {
+        bond.value().ok_or_else(|| self.invalid(format!("schema {} not loaded", bond.cid())))
+    }
+
+    /// Converts `value` with the path extended by `segment`.
+    fn nested(
+        &mut self,
+        segment: &str,
+        value: JsonValue,
+        schema: &'s Structure,
+    ) -> Result<Ipld, InvalidJson> {
+        let len = self.path.len();
+        self.path.push('/');
+        self.path.push_str(&segment.replace('~', "~0").replace('/', "~1"));
+        let result = self.convert(value, schema);
+        self.path.truncate(len);
+        result
+    }
+
+    /// Converts `value` and adds it to the batch as a value of its own.
+    fn block(&mut self, value: JsonValue, schema: &'s Structure) -> Result<Cid, InvalidJson> {
+        let ipld = self.convert(value, schema)?;
+        let bytes = serde_ipld_dagcbor::to_vec(&ipld).map_err(|e| self.invalid(e.to_string()))?;
+        let bytes = canonicalize(&bytes).expect("encoder output should be well-formed CBOR");
+        let cid = compute_cid(&bytes);
+        self.batch.put(cid, bytes);
+        Ok(cid)
+    }
+
+    fn convert(&mut self, value: JsonValue, schema: &'s Structure) -> Result<Ipld, InvalidJson> {
+        match (value, schema) {
+            (value, Structure::Defaulted(inner)) => self.convert(value, self.schema(inner)?),
+            (value, Structure::SelfRef(n)) => {
+                let frame = self.frames.len().checked_sub(*n as usize + 1);
+                match frame.map(|i| self.frames[i]) {
+                    Some(frame) => self.convert(value, frame),
+                    None => Err(self.invalid(format!("unresolved SelfRef({n})"))),
+                }
+            }
+            (JsonValue::Object(mut obj), Structure::Bond(target)) => {
+                let target = self.schema(target)?;
+                match obj.remove("$ref") {
+                    Some(JsonValue::String(cid)) if obj.is_empty() => match cid.parse() {
+                        Ok(cid) => Ok(Ipld::Link(cid)),
+                        Err(_) => Err(self.invalid(format!("invalid $ref {cid:?}"))),
+                    },
+                    _ => Ok(Ipld::Link(self.block(JsonValue::Object(obj), target)?)),
+                }
+            }
+            (value, Structure::Bond(target)) => {
+                Ok(Ipld::Link(self.block(value, self.schema(target)?)?))
+            }
+            (JsonValue::Bool(b), Structure::Bool) => Ok(Ipld::Bool(b)),
+            (JsonValue::String(s), Structure::Char) if s.chars().count() == 1 => {
+                Ok(Ipld::String(s))
+            }
+            (JsonValue::String(s), Structure::Unicode) => Ok(Ipld::String(s)),
+            // Leaves JSON can't represent directly read the same as in
+            // `from_json_value`
+            (
+                value @ JsonValue::String(_),
+                Structure::ByteString | Structure::Int(IntType::U128 | IntType::I128),
+            )
+            | (value @ JsonValue::Number(_), Structure::Float(_)) => {
+                json_to_ipld(value, Some(schema)).map_err(|e| self.invalid(e.to_string()))
+            }
+            (JsonValue::Number(n), Structure::Int(int)) => match integer(&n, *int) {
+                Some(n) => Ok(Ipld::Integer(n)),
+                None => Err(self.invalid(format!("{n} is not a valid {int:?}"))),
+            },
+            (JsonValue::Null, Structure::Unit) => Ok(Ipld::Null),
+            (JsonValue::String(s), Structure::Enum(variants)) if variants.contains(&s) => {
+                Ok(Ipld::String(s))
+            }
+            // Options share the sequence schema and, as fields of derived
+            // types, encode as arrays of at most one element too
+            (JsonValue::Array(items), Structure::Sequence(inner)) => {
+                let inner = self.schema(inner)?;
+                let items = items
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, item)| self.nested(&i.to_string(), item, inner))
+                    .collect::<Result<_, _>>()?;
+                Ok(Ipld::List(items))
+            }
+            (JsonValue::Array(items), Structure::Tuple(elems)) if items.len() == elems.len() => {
+                let items = items
+                    .into_iter()
+                    .zip(elems)
+                    .enumerate()
+                    .map(|(i, (item, elem))| self.nested(&i.to_string(), item, self.schema(elem)?))
+                    .collect::<Result<_, _>>()?;
+                Ok(Ipld::List(items))
+            }
+            (JsonValue::Object(mut obj), Structure::Record(fields)) => {
+                if let Some(key) = obj.keys().find(|key| !fields.contains_key(*key)) {
+                    return Err(self.invalid(format!("unknown field {key:?}")));
+                }
+                self.frames.push(schema);
+                let mut map = BTreeMap::new();
+                for (name, field) in fields {
+                    let field = self.schema(field)?;
+                    match obj.remove(name) {
+                        Some(value) => {
+                            map.insert(name.clone(), self.nested(name, value, field)?);
+                        }
+                        None if matches!(field, Structure::Defaulted(_)) => {}
+                        None => return Err(self.invalid(format!("missing field {name:?}"))),
+                    }
+                }
+                self.frames.pop();
+                Ok(Ipld::Map(map))
+            }
+            (JsonValue::Object(obj), Structure::Tagged(variants)) if obj.len() == 1 => {
+                let (name, value) = obj.into_iter().next().expect("one entry");
+                let Some(variant) = variants.get(&name) else {
+                    return Err(self.invalid(format!("unknown variant {name:?}")));
+                };
+                let variant = self.schema(variant)?;
+                self.frames.push(schema);
+                let value = self.nested(&name, value, variant)?;
+                self.frames.pop();
+                Ok(Ipld::Map(BTreeMap::from([(name, value)])))
+            }
+            // Unit variants encode as their name
+            (JsonValue::String(name), Structure::Tagged(variants))
+                if matches!(variants.get(&name).and_then(Bond::value), Some(Structure::Unit)) =>
+            {
+                Ok(Ipld::String(name))
+            }
+            (
+                JsonValue::Object(obj),
+                Structure::Map { value, .. } | Structure::OrderedMap { value, .. },
+            ) => {
+                let value = self.schema(value)?;
+                let map = obj
+                    .into_iter()
+                    .map(|(k, v)| self.nested(&k, v, value).map(|v| (k, v)))
+                    .collect::<Result<_, _>>()?;
+                Ok(Ipld::Map(map))
+            }
+            (value, schema) => Err(self.invalid(format!(
+                "expected {}, found {}",
+                kind(schema),
+                json_kind(&value)
+            ))),
+        }
+    }
+}
+
+/// Reads a JSON number as an integer of type `int`, if it is one in range.
+fn integer(n: &Number, int: IntType) -> Option<i128> {
+    let n = n
+        .as_i64()
+        .map(i128::from)
+        .or_else(|| n.as_u64().map(i128::from))?;
+    let (min, max): (i128, i128) = match int {
+        IntType::U8 => (0, u8::MAX.into()),
+        IntType::U16 => (0, u16::MAX.into()),
+        IntType::U32 => (0, u32::MAX.into()),
+        IntType::U64 => (0, u64::MAX.into()),
+        IntType::I8 => (i8::MIN.into(), i8::MAX.into()),
+        IntType::I16 => (i16::MIN.into(), i16::MAX.into()),
+        IntType::I32 => (i32::MIN.into(), i32::MAX.into()),
+        IntType::I64 => (i64::MIN.into(), i64::MAX.into()),
+        // Rendered as strings, since JSON numbers lose precision
+        IntType::U128 | IntType::I128 => return None,
+    };
+    (min..=max).contains(&n).then_some(n)
+}
+
+fn json_kind(value: &JsonValue) -> &'static str {
+    match value {
+        JsonValue::Null => "null",
+        JsonValue::Bool(_) => "boolean",
+        JsonValue::Number(_) => "number",
+        JsonValue::String(_) => "string",
+        JsonValue::Array(_) => "array",
+        JsonValue::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ByteString;
+
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, Oxide)]
+    #[oxide(crate = crate)]
+    struct Author {
+        name: String,
+    }
+
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, Oxide)]
+    #[oxide(crate = crate)]
+    struct Chapter {
+        title: String,
+        author: Bond<Author>,
+    }
+
+    #[test]
+    fn renders_bonds_up_to_depth() {
+        let store = MemoryStore::new();
+        let mut solvent = Solvent::new();
+        let author = solvent.add(Author {
+            name: "Ada".into(),
+        });
+        let author_cid = author.cid();
+        let chapter = solvent.add(Chapter {
+            title: "Engines".into(),
+            author: Bond::from_cell(author),
+        });
+        let (cid, schema_cid) = solvent.persist_cell(&chapter, &store).unwrap();
+
+        let mut schemas = Solvent::new();
+        load_schema(&store, &mut schemas, schema_cid).unwrap();
+
+        let json = to_json(&store, &schemas, cid, schema_cid, 0).unwrap();
+        assert_eq!(json["title"], "Engines");
+        assert_eq!(json["author"]["$ref"], author_cid.to_string());
+        assert!(json["author"].get("name").is_none());
+
+        let json = to_json(&store, &schemas, cid, schema_cid, 1).unwrap();
+        assert_eq!(json["author"]["name"], "Ada");
+        assert_eq!(to_json_untyped(&store, cid, 1).unwrap(), json);
+    }
+
+    #[test]
+    fn renders_selected_values() {
+        let store = MemoryStore::new();
+        let mut solvent = Solvent::new();
+        let author = solvent.bond(Author { name: "Ada".into() });
+        let chapter = solvent.add(Chapter {
+            title: "Engines".into(),
+            author,
+        });
+        let (cid, schema_cid) = solvent.persist_cell(&chapter, &store).unwrap();
+        let mut schemas = Solvent::new();
+        load_schema(&store, &mut schemas, schema_cid).unwrap();
+        let schema = Bond::from_cell(schemas.get::<Structure>(&schema_cid).unwrap());
+
+        let path = Path::compile("author.name", &schema).unwrap();
+        let json = select_to_json(&store, &schemas, cid, &path, 0).unwrap();
+        assert_eq!(json, serde_json::json!({ "author.name": "Ada" }));
+
+        let path = Path::compile("author", &schema).unwrap();
+        let json = select_to_json(&store, &schemas, cid, &path, 1).unwrap();
+        assert_eq!(json["author"]["name"], "Ada");
+    }
+
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, Oxide)]
+    #[oxide(crate = crate)]
+    struct Attachment {
+        chapter: Bond<Chapter>,
+        data: ByteString,
+        ratio: f64,
+    }
+
+    #[test]
+    fn json_value_roundtrip() {
+        let chapter = Bond::new(Chapter {
+            title: "Engines".into(),
+            author: Bond::new(Author { name: "Ada".into() }),
+        });
+        let attachment = Attachment {
+            chapter: chapter.clone(),
+            data: ByteString::new(vec![0, 1, 255]),
+            ratio: 2.0,
+        };
+
+        let json = to_json_value(&attachment);
+        assert_eq!(json["chapter"]["$ref"], chapter.cid().to_string());
+        assert_eq!(json["data"], "AAH/");
+
+        let back: Attachment = from_json_value(json).unwrap();
+        assert_eq!(back.chapter.cid(), chapter.cid());
+        assert!(back.chapter.value().is_none());
+        assert_eq!(back.data.as_bytes(), &[0, 1, 255]);
+        assert_eq!(back.to_bytes(), attachment.to_bytes());
+    }
+
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, Oxide)]
+    #[oxide(crate = crate)]
+    struct Draft {
+        title: String,
+        #[serde(with = "crate::serde_helpers::option_as_array")]
+        subtitle: Option<String>,
+    }
+
+    #[test]
+    fn imports_options_as_arrays() {
+        let store = MemoryStore::new();
+        let mut solvent = Solvent::new();
+        let draft = solvent.add(Draft {
+            title: "Engines".into(),
+            subtitle: Some("Notes".into()),
+        });
+        let (cid, schema_cid) = solvent.persist_cell(&draft, &store).unwrap();
+        let mut schemas = Solvent::new();
+        load_schema(&store, &mut schemas, schema_cid).unwrap();
+
+        let mut json = to_json(&store, &schemas, cid, schema_cid, 0).unwrap();
+        assert_eq!(json["subtitle"], serde_json::json!(["Notes"]));
+        json["subtitle"] = serde_json::json!([]);
+        let imported = import_json(&store, &schemas, json.clone(), schema_cid).unwrap();
+        let back = Draft::from_bytes(&store.get(&imported).unwrap().unwrap()).unwrap();
+        assert_eq!(back.title, "Engines");
+        assert!(back.subtitle.is_none());
+
+        for subtitle in [JsonValue::Null, "Notes".into()] {
+            json["subtitle"] = subtitle;
+            let err = import_json(&store, &schemas, json.clone(), schema_cid).unwrap_err();
+            assert!(
+                matches!(err, ImportError::Invalid(InvalidJson { path, .. }) if path == "/subtitle")
+            );
+        }
+    }
+
+    #[test]
+    fn imports_expanded_bonds_as_values() {
+        let store = MemoryStore::new();
+        let mut solvent = Solvent::new();
+        let chapter = solvent.add(Chapter {
+            title: "Engines".into(),
+            author: solvent.bond(Author { name: "Ada".into() }),
+        });
+        let (cid, schema_cid) = solvent.persist_cell(&chapter, &store).unwrap();
+        let mut schemas = Solvent::new();
+        load_schema(&store, &mut schemas, schema_cid).unwrap();
+
+        let mut json = to_json(&store, &schemas, cid, schema_cid, 1).unwrap();
+        assert_eq!(import_json(&store, &schemas, json.clone(), schema_cid).unwrap(), cid);
+
+        json["author"]["name"] = "Grace".into();
+        let edited = import_json(&store, &schemas, json.clone(), schema_cid).unwrap();
+        let edited = to_json(&store, &schemas, edited, schema_cid, 1).unwrap();
+        assert_eq!(edited["author"]["name"], "Grace");
+
+        json["title"] = 7.into();
+        let err = import_json(&store, &schemas, json, schema_cid).unwrap_err();
+        assert!(matches!(err, ImportError::Invalid(InvalidJson { path, .. }) if path == "/title"));
+    }
+}
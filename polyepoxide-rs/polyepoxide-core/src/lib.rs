@@ -6,6 +6,21 @@
 //! - **Cell**: Wraps an oxide with cached CID computation
 //! - **Bond**: A typed reference to another oxide (resolved or unresolved)
 //! - **Solvent**: Manages oxides in memory and coordinates loading from stores
+//! - **SharedSolvent**: Solvent that can be added to concurrently from many tasks
+//! - **Blob**: Binary content split into content-defined chunks for deduplication
+//! - **CachedStore**: Reads through a fast store in front of a slow one, such as a remote peer
+//! - **BlockingStoreAdapter**: Runs a blocking store's calls off the async executor (`tokio` feature)
+//! - **MeteredStore**: Store wrapper recording call latencies (`metrics` feature)
+//! - **SlowLogStore**: Store wrapper logging calls over a latency threshold (`tracing` feature)
+//! - **VerifyingStore**: Store wrapper rejecting writes whose bytes don't match their CID
+//! - **Sealed**: An encrypted value, stored for fields marked `#[oxide(encrypt)]` (`encrypt` feature)
+//! - **Tombstones**: Deletion markers that sync like data, so removals propagate to peers
+//! - **RefStore**: Named, mutable references to CIDs, such as the head of a collection
+//! - **Indexes**: Secondary indexes finding values by a field without scanning the store
+//! - **CompatibilityReport**: Whether data written under one schema decodes under another
+//! - **diff**: Field-level differences between two versions of a value
+//! - **traverse::Path**: Schema-checked paths such as `items[*].photos` selecting parts of values
+//! - **TypeRegistry**: Maps schema CIDs to Rust types for decoding blocks at runtime
 //!
 //! # Example
 //!
@@ -32,25 +47,94 @@
 //! - Consistent content addressing across implementations
 
 mod async_store;
+mod blob;
+#[cfg(feature = "tokio")]
+mod blocking;
 mod bond;
+mod cache;
+mod canonical;
 mod cell;
+mod compat;
+mod diff;
+mod eviction;
+#[cfg(feature = "testing")]
+mod faulty;
+mod gc;
+mod index;
+mod ingest;
+#[cfg(feature = "json")]
+pub mod json;
+mod limits;
+mod lock;
+#[cfg(feature = "metrics")]
+mod metered;
 mod oxide;
+mod refs;
+mod registry;
 mod schema;
+#[cfg(feature = "encrypt")]
+mod sealed;
 pub mod serde_helpers;
+mod shared;
+#[cfg(feature = "tracing")]
+mod slowlog;
 mod solvent;
 mod store;
 mod sync;
+mod tiered;
+mod tombstone;
+mod verify;
 pub mod traverse;
 
 pub use async_store::AsyncStore;
+pub use blob::{
+    cdc_chunks, Blob, BlobError, BlobReader, DedupReport, AVG_CHUNK_SIZE, MAX_CHUNK_SIZE,
+    MIN_CHUNK_SIZE,
+};
+#[cfg(feature = "tokio")]
+pub use blocking::BlockingStoreAdapter;
 pub use bond::Bond;
+pub use cache::{CacheError, CacheStats, CachedStore};
+pub use canonical::{canonicalize, CanonicalError};
 pub use cell::Cell;
+pub use compat::{CompatibilityReport, Incompatibility};
+pub use diff::{diff, Change, DiffEntry, DiffError};
 pub use cid::Cid;
-pub use oxide::{compute_cid, BondMapper, BondVisitor, ByteString, Oxide};
+pub use eviction::EvictionPolicy;
+#[cfg(feature = "testing")]
+pub use faulty::{FaultStats, FaultyError, FaultyStore};
+pub use gc::{reachable, GcStats};
+pub use index::{IndexError, IndexStore, Indexes};
+pub use ingest::{IngestError, IngestPolicy};
+pub use limits::{DecodeLimits, LimitError};
+pub use lock::{LockError, StoreLock, LOCK_FILE};
+#[cfg(feature = "metrics")]
+pub use metered::MeteredStore;
+pub use oxide::{compute_cid, BondMapper, BondVisitor, ByteString, Oxide, I128, U128};
+pub use refs::RefStore;
+pub use registry::{DecodeError, DecodeFn, Decoded, Registration, TypeRegistry};
 pub use schema::{FloatType, IntType, Structure};
-pub use solvent::{Solvent, SolventError};
-pub use store::{MemoryStore, Store};
-pub use sync::{pull, push, SyncError};
+#[cfg(feature = "encrypt")]
+pub use sealed::{EncryptionKey, SealError, Sealed};
+pub use shared::SharedSolvent;
+#[cfg(feature = "tracing")]
+pub use slowlog::{SlowLogStore, SlowOp};
+pub use solvent::{
+    LoadError, PersistError, Persisted, Solvent, SolventError, Validator, Violation,
+};
+pub use store::{Batch, Category, CategoryStats, MemoryStore, Store, Transaction};
+pub use sync::{
+    pull, pull_partial, pull_with_options, push, resume_pull, walk_subgraph, PullOptions, Selector,
+    Subgraph, SyncError, SyncReport,
+};
+pub use tiered::{MigrationStats, TieredError, TieredStore};
+pub use tombstone::{Deleted, Tombstones};
+pub use verify::{VerifyError, VerifyingStore};
 
 #[cfg(feature = "derive")]
 pub use polyepoxide_derive::{oxide, Oxide};
+
+#[doc(hidden)]
+pub use inventory as __inventory;
+#[doc(hidden)]
+pub use schema::schema_frame as __schema_frame;
@@ -0,0 +1,170 @@
+//! Limits for decoding CBOR from untrusted sources.
+//!
+//! `serde_ipld_dagcbor` decodes recursively, so a peer sending a block of
+//! deeply nested arrays can overflow the stack of whoever decodes it. Blocks
+//! from untrusted sources are scanned against `DecodeLimits` first; the scan
+//! keeps its own stack of open collections and never recurses.
+
+use crate::canonical::{read_header, CanonicalError};
+
+/// Bounds a CBOR block must stay within before it is decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeLimits {
+    /// Maximum nesting of arrays, maps, and tags.
+    pub max_depth: usize,
+    /// Maximum number of items in an array, or entries in a map.
+    pub max_collection_len: usize,
+    /// Maximum size of the whole block in bytes.
+    pub max_size: usize,
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: 128,
+            max_collection_len: 1 << 24,
+            max_size: 16 * 1024 * 1024,
+        }
+    }
+}
+
+/// A block violating `DecodeLimits`.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum LimitError {
+    #[error("block is {size} bytes, over the limit of {limit}")]
+    TooLarge { size: usize, limit: usize },
+    #[error("nesting is deeper than the limit of {limit}")]
+    TooDeep { limit: usize },
+    #[error("collection of {len} items is over the limit of {limit}")]
+    TooLong { len: u64, limit: usize },
+    #[error(transparent)]
+    Malformed(#[from] CanonicalError),
+}
+
+impl DecodeLimits {
+    /// Checks that `data` is a single well-formed CBOR item within the limits.
+    pub fn check(&self, data: &[u8]) -> Result<(), LimitError> {
+        if data.len() > self.max_size {
+            return Err(LimitError::TooLarge {
+                size: data.len(),
+                limit: self.max_size,
+            });
+        }
+
+        // Items left to read in each open array, map, or tag; innermost last
+        let mut open: Vec<u64> = Vec::new();
+        let mut pos = 0;
+        loop {
+            let (major, arg, body) = read_header(data, pos)?;
+            pos = body;
+            let children = match major {
+                2 | 3 => {
+                    pos = body
+                        .checked_add(arg as usize)
+                        .filter(|&end| end <= data.len())
+                        .ok_or(CanonicalError::Truncated)?;
+                    0
+                }
+                4 | 5 => {
+                    if arg > self.max_collection_len as u64 {
+                        return Err(LimitError::TooLong {
+                            len: arg,
+                            limit: self.max_collection_len,
+                        });
+                    }
+                    // Every item takes at least a byte
+                    if arg > (data.len() - pos) as u64 {
+                        return Err(CanonicalError::Truncated.into());
+                    }
+                    if major == 5 { arg.saturating_mul(2) } else { arg }
+                }
+                6 => 1,
+                _ => 0,
+            };
+
+            if children > 0 {
+                if open.len() == self.max_depth {
+                    return Err(LimitError::TooDeep {
+                        limit: self.max_depth,
+                    });
+                }
+                open.push(children);
+                continue;
+            }
+
+            // The item is complete, and so is every collection it was last in
+            loop {
+                let Some(remaining) = open.last_mut() else {
+                    if pos != data.len() {
+                        return Err(CanonicalError::Trailing.into());
+                    }
+                    return Ok(());
+                };
+                *remaining -= 1;
+                if *remaining > 0 {
+                    break;
+                }
+                open.pop();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Oxide;
+    use std::collections::BTreeMap;
+
+    fn nested_arrays(depth: usize) -> Vec<u8> {
+        let mut data = vec![0x81; depth];
+        data.push(0x00);
+        data
+    }
+
+    #[test]
+    fn accepts_encoded_oxides() {
+        let limits = DecodeLimits::default();
+        let map: BTreeMap<String, Vec<Option<u64>>> =
+            [("a".to_string(), vec![Some(1), None]), ("b".to_string(), vec![])].into();
+        limits.check(&map.to_bytes()).unwrap();
+        limits.check(&crate::Structure::option(crate::Structure::Unicode).to_bytes()).unwrap();
+        limits.check(&nested_arrays(128)).unwrap();
+    }
+
+    #[test]
+    fn rejects_blocks_over_limits() {
+        let limits = DecodeLimits {
+            max_depth: 4,
+            max_collection_len: 3,
+            max_size: 16,
+        };
+        assert_eq!(limits.check(&nested_arrays(4)), Ok(()));
+        assert_eq!(
+            limits.check(&nested_arrays(5)),
+            Err(LimitError::TooDeep { limit: 4 })
+        );
+        assert_eq!(
+            limits.check(&[0x84, 0, 0, 0, 0]),
+            Err(LimitError::TooLong { len: 4, limit: 3 })
+        );
+        assert!(matches!(
+            limits.check(&[0; 17]),
+            Err(LimitError::TooLarge { size: 17, .. })
+        ));
+        // A header claiming more items than there are bytes
+        assert_eq!(
+            DecodeLimits::default().check(&[0x98, 0x10]),
+            Err(LimitError::Malformed(CanonicalError::Truncated))
+        );
+    }
+
+    #[test]
+    fn scans_deep_nesting_without_recursion() {
+        let limits = DecodeLimits {
+            max_depth: usize::MAX,
+            ..Default::default()
+        };
+        limits.check(&nested_arrays(1_000_000)).unwrap();
+    }
+}
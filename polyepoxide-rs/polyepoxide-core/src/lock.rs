@@ -0,0 +1,167 @@
+//! Advisory lock files for on-disk stores.
+//!
+//! Embedded databases don't support several processes writing the same
+//! directory; depending on the backend a second opener panics, fails with an
+//! opaque I/O error, or corrupts data. Stores take a `StoreLock` before
+//! opening the backend so a second process gets a clear error naming the
+//! holder instead. The lock is held by the operating system on the open lock
+//! file, so it goes away with its holder and a crash never leaves a store
+//! locked.
+
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Name of the lock file inside a store directory.
+pub const LOCK_FILE: &str = "polyepoxide.lock";
+
+/// Error acquiring a store lock.
+#[derive(Debug, thiserror::Error)]
+pub enum LockError {
+    #[error("store is locked by {holder}, pid {pid}")]
+    Locked { holder: String, pid: u32 },
+    #[error("lock file error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// An exclusive advisory lock on a store directory, released on drop.
+#[derive(Debug)]
+pub struct StoreLock {
+    path: PathBuf,
+    /// Holds the lock while open
+    file: File,
+}
+
+impl StoreLock {
+    /// Locks `dir` on behalf of the current process.
+    ///
+    /// The holder name defaults to the current executable's name.
+    pub fn acquire(dir: impl AsRef<Path>) -> Result<Self, LockError> {
+        let holder = std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.file_stem().map(|s| s.to_string_lossy().into_owned()))
+            .unwrap_or_else(|| "unknown".to_string());
+        Self::acquire_as(dir, &holder)
+    }
+
+    /// Locks `dir`, recording `holder` and the current PID in the lock file.
+    ///
+    /// Whatever the file records, only a live lock on it counts: a file left
+    /// by a crashed process is taken over even if its PID has been reused,
+    /// and a second lock on a directory fails within one process too.
+    pub fn acquire_as(dir: impl AsRef<Path>, holder: &str) -> Result<Self, LockError> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(LOCK_FILE);
+
+        // Created if missing but never removed: a process that opened the
+        // file just before its removal would lock a file no one else sees
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let contents = std::fs::read_to_string(&path).unwrap_or_default();
+                let mut lines = contents.lines();
+                let pid = lines.next().and_then(|l| l.trim().parse::<u32>().ok());
+                return Err(LockError::Locked {
+                    holder: lines.next().unwrap_or("unknown").to_string(),
+                    pid: pid.unwrap_or_default(),
+                });
+            }
+            Err(TryLockError::Error(e)) => return Err(e.into()),
+        }
+        file.set_len(0)?;
+        writeln!(file, "{}\n{}", std::process::id(), holder)?;
+        file.sync_all()?;
+        Ok(Self { path, file })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for StoreLock {
+    fn drop(&mut self) {
+        // Closing the file right after releases the lock
+        let _ = self.file.set_len(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("px-lock-{}-{}", name, std::process::id()))
+    }
+
+    /// Locks the lock file of `dir` as another process would.
+    fn lock_elsewhere(dir: &Path) -> Result<File, TryLockError> {
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(dir.join(LOCK_FILE))
+            .unwrap();
+        file.try_lock().map(|()| file)
+    }
+
+    #[test]
+    fn held_lock_reports_holder() {
+        let dir = temp_dir("held");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(LOCK_FILE), "4242\nsih\n").unwrap();
+        let other = lock_elsewhere(&dir).unwrap();
+
+        match StoreLock::acquire_as(&dir, "px") {
+            Err(LockError::Locked { holder, pid }) => {
+                assert_eq!(holder, "sih");
+                assert_eq!(pid, 4242);
+            }
+            other => panic!("Expected Locked, got {:?}", other),
+        }
+
+        drop(other);
+        assert!(StoreLock::acquire_as(&dir, "px").is_ok());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn second_acquire_reports_holder() {
+        let dir = temp_dir("again");
+        let lock = StoreLock::acquire_as(&dir, "sih").unwrap();
+
+        match StoreLock::acquire_as(&dir, "px") {
+            Err(LockError::Locked { holder, pid }) => {
+                assert_eq!(holder, "sih");
+                assert_eq!(pid, std::process::id());
+            }
+            other => panic!("Expected Locked, got {:?}", other),
+        }
+
+        drop(lock);
+        assert!(lock_elsewhere(&dir).is_ok());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn lock_left_by_crashed_process_is_taken() {
+        let dir = temp_dir("stale");
+        std::fs::create_dir_all(&dir).unwrap();
+        // Recording our own PID, as after a restart in a container
+        for pid in [u32::MAX, std::process::id()] {
+            std::fs::write(dir.join(LOCK_FILE), format!("{}\ncrashed\n", pid)).unwrap();
+
+            let lock = StoreLock::acquire_as(&dir, "px").unwrap();
+            let contents = std::fs::read_to_string(lock.path()).unwrap();
+            assert_eq!(contents, format!("{}\npx\n", std::process::id()));
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
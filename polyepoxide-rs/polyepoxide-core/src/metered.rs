@@ -0,0 +1,231 @@
+//! `metrics` counters and histograms, enabled by the `metrics` feature.
+//!
+//! Recorded through the `metrics` facade, so nothing is collected until the
+//! application installs a recorder such as a Prometheus exporter:
+//!
+//! - `polyepoxide_store_op_seconds{op}`: latency of `MeteredStore` calls
+//! - `polyepoxide_store_read_bytes_total`, `polyepoxide_store_written_bytes_total`
+//! - `polyepoxide_sync_pulls_total{outcome}`: finished pulls, `ok` or `error`
+//! - `polyepoxide_sync_nodes_total`, `polyepoxide_sync_bytes_total`: blocks and
+//!   bytes written to the destination by pulls
+//! - `polyepoxide_sync_pull_seconds`: duration of successful pulls
+//! - `polyepoxide_solvent_cells_added_total`, `polyepoxide_solvent_cells_evicted_total`
+//! - `polyepoxide_solvent_cells`, `polyepoxide_solvent_bytes`: cells held by all
+//!   solvents and their encoded size
+
+use cid::Cid;
+use metrics::{counter, gauge, histogram};
+use std::time::{Duration, Instant};
+
+use crate::gc::GcStats;
+use crate::store::{Batch, Store};
+
+pub(crate) fn pull_finished(nodes: usize, bytes: u64, duration: Duration) {
+    counter!("polyepoxide_sync_pulls_total", "outcome" => "ok").increment(1);
+    counter!("polyepoxide_sync_nodes_total").increment(nodes as u64);
+    counter!("polyepoxide_sync_bytes_total").increment(bytes);
+    histogram!("polyepoxide_sync_pull_seconds").record(duration.as_secs_f64());
+}
+
+pub(crate) fn pull_failed() {
+    counter!("polyepoxide_sync_pulls_total", "outcome" => "error").increment(1);
+}
+
+pub(crate) fn cell_added() {
+    counter!("polyepoxide_solvent_cells_added_total").increment(1);
+}
+
+pub(crate) fn cell_evicted() {
+    counter!("polyepoxide_solvent_cells_evicted_total").increment(1);
+}
+
+pub(crate) fn solvent_cells_held(cells: usize, bytes: usize) {
+    gauge!("polyepoxide_solvent_cells").increment(cells as f64);
+    gauge!("polyepoxide_solvent_bytes").increment(bytes as f64);
+}
+
+pub(crate) fn solvent_cells_released(cells: usize, bytes: usize) {
+    gauge!("polyepoxide_solvent_cells").decrement(cells as f64);
+    gauge!("polyepoxide_solvent_bytes").decrement(bytes as f64);
+}
+
+/// A store wrapper recording the latency and traffic of every call.
+pub struct MeteredStore<S> {
+    inner: S,
+}
+
+impl<S: Store> MeteredStore<S> {
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    fn timed<T>(&self, op: &'static str, call: impl FnOnce(&S) -> T) -> T {
+        let start = Instant::now();
+        let result = call(&self.inner);
+        histogram!("polyepoxide_store_op_seconds", "op" => op)
+            .record(start.elapsed().as_secs_f64());
+        result
+    }
+}
+
+fn read(bytes: usize) {
+    counter!("polyepoxide_store_read_bytes_total").increment(bytes as u64);
+}
+
+fn written(bytes: usize) {
+    counter!("polyepoxide_store_written_bytes_total").increment(bytes as u64);
+}
+
+impl<S: Store> Store for MeteredStore<S> {
+    type Error = S::Error;
+
+    fn get(&self, cid: &Cid) -> Result<Option<Vec<u8>>, Self::Error> {
+        let result = self.timed("get", |store| store.get(cid));
+        if let Ok(Some(bytes)) = &result {
+            read(bytes.len());
+        }
+        result
+    }
+
+    fn put(&self, cid: &Cid, value: &[u8]) -> Result<(), Self::Error> {
+        self.timed("put", |store| store.put(cid, value))?;
+        written(value.len());
+        Ok(())
+    }
+
+    fn put_schema(&self, cid: &Cid, value: &[u8]) -> Result<(), Self::Error> {
+        self.timed("put", |store| store.put_schema(cid, value))?;
+        written(value.len());
+        Ok(())
+    }
+
+    fn has(&self, cid: &Cid) -> Result<bool, Self::Error> {
+        self.timed("has", |store| store.has(cid))
+    }
+
+    fn get_many(&self, cids: &[Cid]) -> Result<Vec<Option<Vec<u8>>>, Self::Error> {
+        let result = self.timed("get_many", |store| store.get_many(cids));
+        if let Ok(blocks) = &result {
+            read(blocks.iter().flatten().map(Vec::len).sum());
+        }
+        result
+    }
+
+    fn put_many(&self, nodes: &[(&Cid, &[u8])]) -> Result<(), Self::Error> {
+        self.timed("put_many", |store| store.put_many(nodes))?;
+        written(nodes.iter().map(|(_, value)| value.len()).sum());
+        Ok(())
+    }
+
+    fn write_batch(&self, batch: &Batch) -> Result<(), Self::Error> {
+        self.timed("write_batch", |store| store.write_batch(batch))?;
+        let blocks = batch.schemas().chain(batch.values());
+        written(blocks.map(|(_, value)| value.len()).sum());
+        Ok(())
+    }
+
+    fn delete(&self, cid: &Cid) -> Result<(), Self::Error> {
+        self.timed("delete", |store| store.delete(cid))
+    }
+
+    fn delete_many(&self, cids: &[Cid]) -> Result<(), Self::Error> {
+        self.timed("delete_many", |store| store.delete_many(cids))
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = Result<Cid, Self::Error>> + '_> {
+        self.inner.iter()
+    }
+
+    fn gc(&self, roots: &[Cid]) -> Result<GcStats, Self::Error> {
+        self.timed("gc", |store| store.gc(roots))
+    }
+
+    fn compact(&self) -> Result<(), Self::Error> {
+        self.timed("compact", |store| store.compact())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oxide::compute_cid;
+    use crate::{EvictionPolicy, MemoryStore, Solvent};
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder, Snapshotter};
+
+    fn value(snapshotter: &Snapshotter, name: &str) -> Option<DebugValue> {
+        snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .filter(|(key, ..)| key.key().name() == name)
+            .map(|(.., value)| value)
+            .next()
+    }
+
+    fn counter(snapshotter: &Snapshotter, name: &str) -> u64 {
+        match value(snapshotter, name) {
+            Some(DebugValue::Counter(count)) => count,
+            other => panic!("{name} is {other:?}"),
+        }
+    }
+
+    fn gauge(snapshotter: &Snapshotter, name: &str) -> f64 {
+        match value(snapshotter, name) {
+            Some(DebugValue::Gauge(value)) => value.into_inner(),
+            other => panic!("{name} is {other:?}"),
+        }
+    }
+
+    #[test]
+    fn records_store_traffic() {
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        metrics::with_local_recorder(&recorder, || {
+            let store = MeteredStore::new(MemoryStore::new());
+            let cid = compute_cid(b"block");
+            store.put(&cid, b"block").unwrap();
+            store.get(&cid).unwrap();
+            store.get(&compute_cid(b"missing")).unwrap();
+        });
+
+        let latencies = snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .filter(|(key, ..)| key.key().name() == "polyepoxide_store_op_seconds")
+            .count();
+        assert_eq!(latencies, 2, "one histogram per op");
+        let written = counter(&snapshotter, "polyepoxide_store_written_bytes_total");
+        assert_eq!(written, 5);
+        let read = counter(&snapshotter, "polyepoxide_store_read_bytes_total");
+        assert_eq!(read, 5);
+    }
+
+    #[test]
+    fn counts_cells_held_by_solvents() {
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        metrics::with_local_recorder(&recorder, || {
+            let mut solvent = Solvent::new();
+            solvent.set_eviction_policy(EvictionPolicy::MaxCells(2));
+            for n in 0..3u64 {
+                solvent.add(n);
+            }
+            assert_eq!(gauge(&snapshotter, "polyepoxide_solvent_cells"), 2.0);
+            let bytes = solvent.retained_bytes() as f64;
+            assert_eq!(gauge(&snapshotter, "polyepoxide_solvent_bytes"), bytes);
+            drop(solvent);
+        });
+
+        let added = counter(&snapshotter, "polyepoxide_solvent_cells_added_total");
+        assert_eq!(added, 3);
+        let evicted = counter(&snapshotter, "polyepoxide_solvent_cells_evicted_total");
+        assert_eq!(evicted, 1);
+        assert_eq!(gauge(&snapshotter, "polyepoxide_solvent_cells"), 0.0);
+        assert_eq!(gauge(&snapshotter, "polyepoxide_solvent_bytes"), 0.0);
+    }
+}
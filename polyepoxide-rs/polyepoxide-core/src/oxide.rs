@@ -1,9 +1,15 @@
 use cid::Cid;
 use multihash_codetable::{Code, MultihashDigest};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Debug;
+use std::hash::Hash;
+use std::num::{
+    NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8, NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8,
+};
 
 use crate::bond::Bond;
+use crate::canonical::canonicalize;
 use crate::schema::Structure;
 
 /// DAG-CBOR codec code (0x71).
@@ -37,6 +43,11 @@ pub trait BondMapper {
 /// - Serializable to a canonical byte representation (CBOR)
 /// - Content-addressable (identity is the hash of serialized form)
 /// - Schema-aware (can describe its own structure)
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` cannot be stored as an oxide",
+    label = "`{Self}` does not implement `Oxide`",
+    note = "derive it with `#[oxide]`, or wrap it in a supported type such as `Vec`, `Option`, `BTreeMap` or `Bond`"
+)]
 pub trait Oxide: Debug + Serialize + DeserializeOwned + Clone + Send + Sync + 'static {
     /// Returns the structure describing this oxide's type.
     fn schema() -> Structure;
@@ -54,9 +65,10 @@ pub trait Oxide: Debug + Serialize + DeserializeOwned + Clone + Send + Sync + 's
         compute_cid(&data)
     }
 
-    /// Serializes this oxide to DAG-CBOR bytes.
+    /// Serializes this oxide to DAG-CBOR bytes, with map keys in canonical order.
     fn to_bytes(&self) -> Vec<u8> {
-        serde_ipld_dagcbor::to_vec(self).expect("serialization should not fail")
+        let bytes = serde_ipld_dagcbor::to_vec(self).expect("serialization should not fail");
+        canonicalize(&bytes).expect("encoder output should be well-formed CBOR")
     }
 
     /// Deserializes an oxide from DAG-CBOR bytes.
@@ -133,6 +145,21 @@ impl Oxide for ByteString {
     }
 }
 
+/// A `u128` that implements Oxide, encoded as 16 big-endian bytes.
+///
+/// Bare `u128` fields can be used in `#[oxide]` types, which encode them the
+/// same way.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct U128(#[serde(with = "crate::serde_helpers::u128_as_bytes")] pub u128);
+
+/// An `i128` that implements Oxide, encoded as 16 big-endian bytes.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct I128(#[serde(with = "crate::serde_helpers::i128_as_bytes")] pub i128);
+
 macro_rules! impl_oxide_int {
     ($t:ty, $variant:ident) => {
         impl Oxide for $t {
@@ -157,6 +184,18 @@ impl_oxide_int!(i8, I8);
 impl_oxide_int!(i16, I16);
 impl_oxide_int!(i32, I32);
 impl_oxide_int!(i64, I64);
+impl_oxide_int!(U128, U128);
+impl_oxide_int!(I128, I128);
+
+// Zero is rejected when decoding; the schema doesn't record the restriction
+impl_oxide_int!(NonZeroU8, U8);
+impl_oxide_int!(NonZeroU16, U16);
+impl_oxide_int!(NonZeroU32, U32);
+impl_oxide_int!(NonZeroU64, U64);
+impl_oxide_int!(NonZeroI8, I8);
+impl_oxide_int!(NonZeroI16, I16);
+impl_oxide_int!(NonZeroI32, I32);
+impl_oxide_int!(NonZeroI64, I64);
 
 macro_rules! impl_oxide_float {
     ($t:ty, $variant:ident) => {
@@ -219,6 +258,44 @@ impl<T: Oxide> Oxide for Option<T> {
     }
 }
 
+impl<K: Oxide + Eq + Hash, V: Oxide> Oxide for HashMap<K, V> {
+    fn schema() -> Structure {
+        Structure::map(K::schema(), V::schema())
+    }
+
+    fn visit_bonds(&self, visitor: &mut dyn BondVisitor) {
+        for (key, value) in self {
+            key.visit_bonds(visitor);
+            value.visit_bonds(visitor);
+        }
+    }
+
+    fn map_bonds(&self, mapper: &mut impl BondMapper) -> Self {
+        self.iter()
+            .map(|(key, value)| (key.map_bonds(mapper), value.map_bonds(mapper)))
+            .collect()
+    }
+}
+
+impl<K: Oxide + Ord, V: Oxide> Oxide for BTreeMap<K, V> {
+    fn schema() -> Structure {
+        Structure::map(K::schema(), V::schema())
+    }
+
+    fn visit_bonds(&self, visitor: &mut dyn BondVisitor) {
+        for (key, value) in self {
+            key.visit_bonds(visitor);
+            value.visit_bonds(visitor);
+        }
+    }
+
+    fn map_bonds(&self, mapper: &mut impl BondMapper) -> Self {
+        self.iter()
+            .map(|(key, value)| (key.map_bonds(mapper), value.map_bonds(mapper)))
+            .collect()
+    }
+}
+
 impl<T: Oxide, E: Oxide> Oxide for Result<T, E> {
     fn schema() -> Structure {
         Structure::result(T::schema(), E::schema())
@@ -271,4 +348,16 @@ mod tests {
         let recovered: ByteString = Oxide::from_bytes(&bytes).unwrap();
         assert_eq!(bs, recovered);
     }
+
+    #[test]
+    fn wide_and_nonzero_ints() {
+        let wide = U128(u128::MAX);
+        assert_eq!(U128::from_bytes(&wide.to_bytes()).unwrap(), wide);
+        let signed = I128(-1);
+        assert_eq!(I128::from_bytes(&signed.to_bytes()).unwrap(), signed);
+
+        let n = NonZeroU32::new(7).unwrap();
+        assert_eq!(n.to_bytes(), 7u32.to_bytes());
+        assert!(NonZeroU32::from_bytes(&0u32.to_bytes()).is_err());
+    }
 }
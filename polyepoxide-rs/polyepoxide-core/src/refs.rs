@@ -0,0 +1,67 @@
+//! Named references to CIDs.
+//!
+//! Blocks never change, so the "current" version of anything — the head of
+//! an inventory, the latest message of a conversation — has to be recorded
+//! outside the DAG. A ref maps a name such as `inventory/head` to a CID and is
+//! the only mutable state a store holds. Names are free-form; `/` separates
+//! namespaces by convention, so `list_refs("chat/")` finds every named chat.
+
+use cid::Cid;
+
+use crate::store::Store;
+
+/// A store that also keeps named references.
+///
+/// Refs don't keep their targets alive on their own: pass the targets of
+/// `list_refs("")` to `Store::gc` along with any other roots.
+pub trait RefStore: Store {
+    /// Returns the CID a ref points to, or None if it isn't set.
+    fn get_ref(&self, name: &str) -> Result<Option<Cid>, Self::Error>;
+
+    /// Points a ref at a CID, creating or overwriting it.
+    fn set_ref(&self, name: &str, cid: &Cid) -> Result<(), Self::Error>;
+
+    /// Removes a ref. Removing a missing ref is not an error.
+    fn delete_ref(&self, name: &str) -> Result<(), Self::Error>;
+
+    /// Lists the refs whose names start with `prefix`, sorted by name.
+    fn list_refs(&self, prefix: &str) -> Result<Vec<(String, Cid)>, Self::Error>;
+
+    /// Points a ref at `new` only if it currently points at `expected`, or is
+    /// unset when `expected` is None. Returns whether the ref was updated.
+    ///
+    /// Lets concurrent writers advance a head without losing updates.
+    fn compare_and_set_ref(
+        &self,
+        name: &str,
+        expected: Option<&Cid>,
+        new: &Cid,
+    ) -> Result<bool, Self::Error>;
+}
+
+impl<S: RefStore> RefStore for &S {
+    fn get_ref(&self, name: &str) -> Result<Option<Cid>, Self::Error> {
+        (*self).get_ref(name)
+    }
+
+    fn set_ref(&self, name: &str, cid: &Cid) -> Result<(), Self::Error> {
+        (*self).set_ref(name, cid)
+    }
+
+    fn delete_ref(&self, name: &str) -> Result<(), Self::Error> {
+        (*self).delete_ref(name)
+    }
+
+    fn list_refs(&self, prefix: &str) -> Result<Vec<(String, Cid)>, Self::Error> {
+        (*self).list_refs(prefix)
+    }
+
+    fn compare_and_set_ref(
+        &self,
+        name: &str,
+        expected: Option<&Cid>,
+        new: &Cid,
+    ) -> Result<bool, Self::Error> {
+        (*self).compare_and_set_ref(name, expected, new)
+    }
+}
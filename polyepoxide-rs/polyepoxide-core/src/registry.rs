@@ -0,0 +1,235 @@
+//! Runtime registry mapping schema CIDs to Rust types.
+//!
+//! Generic tools (viewers, daemons, consistency checks) only know a block's
+//! schema CID. The registry lets them decode blocks into the concrete Rust
+//! type when the binary links one in, and fall back to raw IPLD otherwise.
+//!
+//! Types are registered from anywhere in the dependency graph with
+//! `register_oxide!`, and collected into `TypeRegistry::global()`:
+//!
+//! ```ignore
+//! #[oxide]
+//! struct Note { text: String }
+//!
+//! polyepoxide_core::register_oxide!(Note);
+//! ```
+
+use cid::Cid;
+use ipld_core::ipld::Ipld;
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::traverse::{parse_to_ipld, ParseError};
+use crate::{Oxide, Structure};
+
+/// Decodes bytes into a type-erased value.
+pub type DecodeFn = fn(&[u8]) -> Result<Box<dyn Any + Send + Sync>, String>;
+
+/// A type submitted with `register_oxide!`.
+pub struct Registration {
+    type_name: fn() -> &'static str,
+    schema: fn() -> Structure,
+    decode: DecodeFn,
+}
+
+impl Registration {
+    /// Describes an oxide type; used by `register_oxide!`.
+    pub const fn of<T: Oxide>() -> Self {
+        Self {
+            type_name: std::any::type_name::<T>,
+            schema: T::schema,
+            decode: decode_as::<T>,
+        }
+    }
+}
+
+inventory::collect!(Registration);
+
+fn decode_as<T: Oxide>(bytes: &[u8]) -> Result<Box<dyn Any + Send + Sync>, String> {
+    T::from_bytes(bytes)
+        .map(|value| Box::new(value) as Box<dyn Any + Send + Sync>)
+        .map_err(|e| e.to_string())
+}
+
+/// Registers an oxide type with the global `TypeRegistry`.
+#[macro_export]
+macro_rules! register_oxide {
+    ($ty:ty) => {
+        $crate::__inventory::submit! {
+            $crate::Registration::of::<$ty>()
+        }
+    };
+}
+
+/// A block decoded through the registry.
+#[derive(Debug)]
+pub enum Decoded {
+    /// The schema is registered; `value` downcasts to the named type.
+    Typed {
+        type_name: &'static str,
+        value: Box<dyn Any + Send + Sync>,
+    },
+    /// No type is registered for the schema.
+    Ipld(Ipld),
+}
+
+impl Decoded {
+    /// Returns the value as `T` if it was decoded as that type.
+    pub fn downcast_ref<T: Oxide>(&self) -> Option<&T> {
+        match self {
+            Decoded::Typed { value, .. } => value.downcast_ref(),
+            Decoded::Ipld(_) => None,
+        }
+    }
+}
+
+/// Error decoding a block through the registry.
+#[derive(Debug, thiserror::Error)]
+pub enum DecodeError {
+    #[error("failed to decode as {type_name}: {message}")]
+    Typed {
+        type_name: &'static str,
+        message: String,
+    },
+    #[error(transparent)]
+    Ipld(#[from] ParseError),
+}
+
+struct Entry {
+    type_name: &'static str,
+    decode: DecodeFn,
+}
+
+/// Maps schema CIDs to decoders for the Rust types they describe.
+#[derive(Default)]
+pub struct TypeRegistry {
+    entries: HashMap<Cid, Entry>,
+}
+
+impl TypeRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a registry holding every type submitted with `register_oxide!`.
+    pub fn from_inventory() -> Self {
+        let mut registry = Self::new();
+        for registration in inventory::iter::<Registration> {
+            registry.insert(
+                (registration.schema)().compute_cid(),
+                (registration.type_name)(),
+                registration.decode,
+            );
+        }
+        registry
+    }
+
+    /// Returns the process-wide registry, built from the inventory on first use.
+    pub fn global() -> &'static TypeRegistry {
+        static GLOBAL: OnceLock<TypeRegistry> = OnceLock::new();
+        GLOBAL.get_or_init(Self::from_inventory)
+    }
+
+    /// Registers `T` and returns its schema CID.
+    ///
+    /// Types with identical schemas share a CID; the last one registered wins.
+    pub fn register<T: Oxide>(&mut self) -> Cid {
+        let cid = T::schema().compute_cid();
+        self.insert(cid, std::any::type_name::<T>(), decode_as::<T>);
+        cid
+    }
+
+    fn insert(&mut self, schema: Cid, type_name: &'static str, decode: DecodeFn) {
+        self.entries.insert(schema, Entry { type_name, decode });
+    }
+
+    /// Returns the name of the type registered for a schema.
+    pub fn type_name(&self, schema: &Cid) -> Option<&'static str> {
+        self.entries.get(schema).map(|entry| entry.type_name)
+    }
+
+    pub fn contains(&self, schema: &Cid) -> bool {
+        self.entries.contains_key(schema)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Decodes a block with the type registered for its schema, or as IPLD
+    /// if there is none.
+    pub fn decode(&self, schema: &Cid, bytes: &[u8]) -> Result<Decoded, DecodeError> {
+        match self.entries.get(schema) {
+            Some(entry) => (entry.decode)(bytes)
+                .map(|value| Decoded::Typed {
+                    type_name: entry.type_name,
+                    value,
+                })
+                .map_err(|message| DecodeError::Typed {
+                    type_name: entry.type_name,
+                    message,
+                }),
+            None => Ok(Decoded::Ipld(parse_to_ipld(bytes)?)),
+        }
+    }
+}
+
+// Schema nodes are blocks too
+register_oxide!(Structure);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Bond;
+
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, crate::Oxide)]
+    #[oxide(crate = crate)]
+    struct Note {
+        text: String,
+        next: Option<Bond<Note>>,
+    }
+
+    register_oxide!(Note);
+
+    #[test]
+    fn decodes_registered_types() {
+        let registry = TypeRegistry::global();
+        let note = Note {
+            text: "hello".into(),
+            next: None,
+        };
+        let schema = Note::schema().compute_cid();
+
+        assert!(registry.type_name(&schema).unwrap().ends_with("Note"));
+        let decoded = registry.decode(&schema, &note.to_bytes()).unwrap();
+        assert_eq!(decoded.downcast_ref::<Note>().unwrap().text, "hello");
+
+        let decoded = registry
+            .decode(&Structure::schema().compute_cid(), &Note::schema().to_bytes())
+            .unwrap();
+        assert!(decoded.downcast_ref::<Structure>().is_some());
+    }
+
+    #[test]
+    fn falls_back_to_ipld() {
+        let registry = TypeRegistry::new();
+        let decoded = registry
+            .decode(&u64::schema().compute_cid(), &42u64.to_bytes())
+            .unwrap();
+        assert!(matches!(decoded, Decoded::Ipld(Ipld::Integer(42))));
+    }
+
+    #[test]
+    fn reports_typed_decode_failures() {
+        let mut registry = TypeRegistry::new();
+        let schema = registry.register::<Note>();
+        let err = registry.decode(&schema, &42u64.to_bytes()).unwrap_err();
+        assert!(matches!(err, DecodeError::Typed { .. }));
+    }
+}
@@ -1,3 +1,6 @@
+use std::any::{type_name, TypeId};
+use std::cell::RefCell;
+
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 
@@ -5,6 +8,9 @@ use crate::bond::Bond;
 use crate::oxide::{BondMapper, BondVisitor, Oxide};
 
 /// Integer type variants for the schema.
+///
+/// 128-bit integers exceed DAG-CBOR's integer range and are encoded as
+/// 16-byte big-endian byte strings.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum IntType {
     U8,
@@ -15,12 +21,14 @@ pub enum IntType {
     I16,
     I32,
     I64,
+    U128,
+    I128,
 }
 
 impl IntType {
     /// Returns all variant names in order.
     pub fn variant_names() -> &'static [&'static str] {
-        &["U8", "U16", "U32", "U64", "I8", "I16", "I32", "I64"]
+        &["U8", "U16", "U32", "U64", "I8", "I16", "I32", "I64", "U128", "I128"]
     }
 }
 
@@ -70,8 +78,8 @@ impl Oxide for FloatType {
 /// Nested structures are referenced via `Bond<Structure>`, enabling deduplication
 /// and lazy loading when stored in a Solvent.
 ///
-/// NOTE: Canonical CBOR serialization is not yet implemented. Map key ordering
-/// must be handled explicitly when deterministic hashing is required.
+/// `Map` values are encoded with keys in canonical order regardless of the
+/// Rust map type, so `HashMap` and `BTreeMap` with equal contents share a CID.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Structure {
     // Primitives
@@ -120,6 +128,9 @@ pub enum Structure {
     /// Reference to n-th ancestor in schema tree (for recursive types).
     /// 0 = immediate parent, 1 = grandparent, etc.
     SelfRef(u32),
+    /// Record field that older data may lack, decoded as the type's default.
+    /// Encodes as the inner type.
+    Defaulted(Bond<Structure>),
 }
 
 impl Structure {
@@ -146,6 +157,11 @@ impl Structure {
         Structure::Bond(Bond::new(inner))
     }
 
+    /// Creates a defaulted field type.
+    pub fn defaulted(inner: Structure) -> Self {
+        Structure::Defaulted(Bond::new(inner))
+    }
+
     /// Creates an unordered map type.
     pub fn map(key: Structure, value: Structure) -> Self {
         Structure::Map {
@@ -188,6 +204,129 @@ impl Structure {
     }
 }
 
+thread_local! {
+    /// Types whose schema is being built on this thread, outermost first.
+    static SCHEMA_STACK: RefCell<Vec<TypeId>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Stands for a reference to the type at `SCHEMA_STACK` index `i` as
+/// `SelfRef(PENDING - i)` until that type's schema is built, when the
+/// records and unions in between can be counted.
+const PENDING: u32 = u32::MAX;
+
+/// Builds the schema of `T`, replacing references to types whose schema is
+/// already being built, as with recursive types, by `SelfRef`s.
+///
+/// A `SelfRef(n)` counts the records and unions enclosing it, innermost
+/// first, as resolvers do, so `T` must itself be a record or union if its
+/// schema refers back to it.
+///
+/// Called by derived `schema()` implementations.
+#[doc(hidden)]
+pub fn schema_frame<T: 'static>(build: impl FnOnce() -> Structure) -> Structure {
+    struct Pop;
+    impl Drop for Pop {
+        fn drop(&mut self) {
+            SCHEMA_STACK.with_borrow_mut(|stack| stack.pop());
+        }
+    }
+
+    let id = TypeId::of::<T>();
+    let index = SCHEMA_STACK.with_borrow(|stack| stack.iter().position(|t| *t == id));
+    if let Some(index) = index {
+        return Structure::SelfRef(PENDING - index as u32);
+    }
+    let index = SCHEMA_STACK.with_borrow_mut(|stack| {
+        stack.push(id);
+        stack.len() - 1
+    });
+    // Popped even if `build` panics
+    let _pop = Pop;
+    let schema = build();
+
+    let pending = PendingRef {
+        target: PENDING - index as u32,
+        type_name: type_name::<T>(),
+    };
+    match schema {
+        // The frame the references resolve to
+        Structure::Record(_) | Structure::Tagged(_) => {
+            map_children(&schema, |child| pending.resolve_bond(child, Some(0)))
+        }
+        _ => pending.resolve(&schema, None),
+    }
+}
+
+/// References to a type being built, to be replaced by `SelfRef`s.
+struct PendingRef {
+    target: u32,
+    type_name: &'static str,
+}
+
+impl PendingRef {
+    /// Resolves the references in `schema`, where `depth` is the number of
+    /// records and unions between it and the referenced type's frame, or
+    /// `None` if that type isn't a record or union.
+    fn resolve(&self, schema: &Structure, depth: Option<u32>) -> Structure {
+        match schema {
+            Structure::SelfRef(n) if *n == self.target => match depth {
+                Some(depth) => Structure::SelfRef(depth),
+                None => panic!(
+                    "{} refers to itself but isn't a record or tagged union",
+                    self.type_name
+                ),
+            },
+            Structure::Record(_) | Structure::Tagged(_) => map_children(schema, |child| {
+                self.resolve_bond(child, depth.map(|d| d + 1))
+            }),
+            _ => map_children(schema, |child| self.resolve_bond(child, depth)),
+        }
+    }
+
+    fn resolve_bond(&self, bond: &Bond<Structure>, depth: Option<u32>) -> Bond<Structure> {
+        match bond.value() {
+            Some(schema) => Bond::new(self.resolve(schema, depth)),
+            None => bond.clone(),
+        }
+    }
+}
+
+/// Rebuilds `schema` with `f` applied to each of its nested structures.
+fn map_children(
+    schema: &Structure,
+    mut f: impl FnMut(&Bond<Structure>) -> Bond<Structure>,
+) -> Structure {
+    match schema {
+        Structure::Sequence(inner) => Structure::Sequence(f(inner)),
+        Structure::Tuple(elements) => Structure::Tuple(elements.iter().map(f).collect()),
+        Structure::Record(fields) => {
+            Structure::Record(fields.iter().map(|(k, v)| (k.clone(), f(v))).collect())
+        }
+        Structure::Tagged(variants) => {
+            Structure::Tagged(variants.iter().map(|(k, v)| (k.clone(), f(v))).collect())
+        }
+        Structure::Map { key, value } => Structure::Map {
+            key: f(key),
+            value: f(value),
+        },
+        Structure::OrderedMap { key, value } => Structure::OrderedMap {
+            key: f(key),
+            value: f(value),
+        },
+        Structure::Bond(inner) => Structure::Bond(f(inner)),
+        Structure::Defaulted(inner) => Structure::Defaulted(f(inner)),
+        Structure::Bool
+        | Structure::Char
+        | Structure::Unicode
+        | Structure::ByteString
+        | Structure::Int(_)
+        | Structure::Float(_)
+        | Structure::Unit
+        | Structure::Enum(_)
+        | Structure::SelfRef(_) => schema.clone(),
+    }
+}
+
 impl Oxide for Structure {
     /// Returns the schema of Structure itself.
     ///
@@ -197,8 +336,12 @@ impl Oxide for Structure {
         // SelfRef(0) refers to the Structure type itself (breaks recursion)
         let self_ref = Structure::SelfRef(0);
 
-        // Schema for Map/OrderedMap payload: Record { key: Structure, value: Structure }
-        let map_payload = Structure::record([("key", self_ref.clone()), ("value", self_ref.clone())]);
+        // Schema for Map/OrderedMap payload: Record { key: Structure, value: Structure }.
+        // The record is a frame of its own, so Structure is one frame up.
+        let map_payload = Structure::record([
+            ("key", Structure::SelfRef(1)),
+            ("value", Structure::SelfRef(1)),
+        ]);
 
         Structure::tagged([
             // Primitives (unit payloads)
@@ -219,8 +362,9 @@ impl Oxide for Structure {
             ("Map", map_payload.clone()),
             ("OrderedMap", map_payload),
             // Polyepoxide-specific
-            ("Bond", self_ref),
+            ("Bond", self_ref.clone()),
             ("SelfRef", Structure::Int(IntType::U32)),
+            ("Defaulted", self_ref),
         ])
     }
 
@@ -241,7 +385,7 @@ impl Oxide for Structure {
                 key.visit_bonds(visitor);
                 value.visit_bonds(visitor);
             }
-            Structure::Bond(inner) => inner.visit_bonds(visitor),
+            Structure::Bond(inner) | Structure::Defaulted(inner) => inner.visit_bonds(visitor),
             // Primitives and SelfRef have no bonds
             Structure::Bool
             | Structure::Char
@@ -282,6 +426,7 @@ impl Oxide for Structure {
                 value: value.map_bonds(mapper),
             },
             Structure::Bond(inner) => Structure::Bond(inner.map_bonds(mapper)),
+            Structure::Defaulted(inner) => Structure::Defaulted(inner.map_bonds(mapper)),
             // Primitives and SelfRef are copied as-is
             other => other.clone(),
         }
@@ -337,6 +482,7 @@ impl PartialEq for Structure {
                 },
             ) => k1.cid() == k2.cid() && v1.cid() == v2.cid(),
             (Structure::Bond(a), Structure::Bond(b)) => a.cid() == b.cid(),
+            (Structure::Defaulted(a), Structure::Defaulted(b)) => a.cid() == b.cid(),
             (Structure::SelfRef(a), Structure::SelfRef(b)) => a == b,
             _ => false,
         }
@@ -388,9 +534,10 @@ mod tests {
     fn int_type_schema() {
         let schema = IntType::schema();
         if let Structure::Enum(variants) = schema {
-            assert_eq!(variants.len(), 8);
+            assert_eq!(variants.len(), 10);
             assert_eq!(variants[0], "U8");
             assert_eq!(variants[7], "I64");
+            assert_eq!(variants[9], "I128");
         } else {
             panic!("Expected Enum");
         }
@@ -412,8 +559,8 @@ mod tests {
     fn structure_schema_is_tagged() {
         let schema = Structure::schema();
         if let Structure::Tagged(variants) = &schema {
-            // Should have all 16 variants
-            assert_eq!(variants.len(), 16);
+            // Should have all 17 variants
+            assert_eq!(variants.len(), 17);
             assert!(variants.contains_key("Bool"));
             assert!(variants.contains_key("Record"));
             assert!(variants.contains_key("SelfRef"));
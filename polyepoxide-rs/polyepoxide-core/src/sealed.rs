@@ -0,0 +1,237 @@
+//! Field-level encryption.
+//!
+//! A [`Sealed`] value holds another oxide encrypted under an
+//! [`EncryptionKey`]. Fields marked `#[oxide(encrypt)]` in `#[oxide]` types
+//! are stored as `Sealed`, so sensitive parts of a value (a purchase price,
+//! a message's content) are ciphertext in every store and on the wire, while
+//! the other fields stay plaintext for indexes, traversal and sync.
+//!
+//! Encryption is deterministic: the nonce is derived from the key and the
+//! plaintext, so sealing equal values under one key gives equal bytes. CIDs
+//! stay stable and unchanged values deduplicate, at the cost of revealing
+//! which sealed fields are equal to anyone holding the ciphertext.
+//!
+//! Bonds inside a sealed value are hidden from gc, sync and indexes, so the
+//! value should not bond to anything that is stored separately.
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::oxide::{BondMapper, BondVisitor, ByteString, Oxide};
+use crate::schema::Structure;
+
+/// First byte of sealed bytes, identifying the cipher and layout.
+const VERSION: u8 = 1;
+const NONCE_LEN: usize = 24;
+const TAG_LEN: usize = 16;
+
+/// Contexts separating the subkeys derived from a key.
+const CIPHER_CONTEXT: &str = "polyepoxide 2026 sealed cipher key";
+const NONCE_CONTEXT: &str = "polyepoxide 2026 sealed nonce key";
+
+/// Error opening a sealed value.
+#[derive(Debug, thiserror::Error)]
+pub enum SealError {
+    #[error("sealed value is malformed")]
+    Malformed,
+    #[error("unsupported sealed value version {0}")]
+    UnsupportedVersion(u8),
+    #[error("decryption failed: wrong key or tampered value")]
+    Decrypt,
+    #[error("decode error: {0}")]
+    Decode(String),
+}
+
+/// Key sealing and opening values.
+///
+/// Keys come from whatever holds secrets for a device or identity: either
+/// 32 random bytes, or derived from a longer-lived secret per purpose.
+#[derive(Clone)]
+pub struct EncryptionKey {
+    cipher: [u8; 32],
+    nonce: [u8; 32],
+}
+
+impl EncryptionKey {
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self {
+            cipher: blake3::derive_key(CIPHER_CONTEXT, &bytes),
+            nonce: blake3::derive_key(NONCE_CONTEXT, &bytes),
+        }
+    }
+
+    /// Derives the key for `context`, such as `"aldehyde inventory prices"`,
+    /// from `secret`. Different contexts give unrelated keys.
+    pub fn derive(context: &str, secret: &[u8]) -> Self {
+        Self::from_bytes(blake3::derive_key(context, secret))
+    }
+
+    fn cipher(&self) -> XChaCha20Poly1305 {
+        XChaCha20Poly1305::new(Key::from_slice(&self.cipher))
+    }
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EncryptionKey(..)")
+    }
+}
+
+/// An oxide of type `T`, encrypted.
+///
+/// Stored as a byte string: a version byte, the nonce, then the ciphertext
+/// of `T`'s bytes with its authentication tag.
+pub struct Sealed<T> {
+    bytes: ByteString,
+    _type: PhantomData<fn() -> T>,
+}
+
+impl<T: Oxide> Sealed<T> {
+    pub fn seal(value: &T, key: &EncryptionKey) -> Self {
+        let plaintext = value.to_bytes();
+        let hash = blake3::keyed_hash(&key.nonce, &plaintext);
+        let nonce = XNonce::from_slice(&hash.as_bytes()[..NONCE_LEN]);
+        let ciphertext = key
+            .cipher()
+            .encrypt(nonce, plaintext.as_slice())
+            .expect("encryption only fails for inputs of many gigabytes");
+
+        let mut bytes = Vec::with_capacity(1 + NONCE_LEN + ciphertext.len());
+        bytes.push(VERSION);
+        bytes.extend_from_slice(nonce);
+        bytes.extend_from_slice(&ciphertext);
+        Self::from_sealed_bytes(bytes)
+    }
+
+    /// Decrypts and decodes the value.
+    pub fn open(&self, key: &EncryptionKey) -> Result<T, SealError> {
+        let bytes = self.bytes.as_bytes();
+        let (&version, rest) = bytes.split_first().ok_or(SealError::Malformed)?;
+        if version != VERSION {
+            return Err(SealError::UnsupportedVersion(version));
+        }
+        if rest.len() < NONCE_LEN + TAG_LEN {
+            return Err(SealError::Malformed);
+        }
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        let plaintext = key
+            .cipher()
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| SealError::Decrypt)?;
+        T::from_bytes(&plaintext).map_err(|e| SealError::Decode(e.to_string()))
+    }
+}
+
+impl<T> Sealed<T> {
+    /// Wraps bytes produced by [`Sealed::seal`], such as ones read from a
+    /// store without a key. They are only checked when opened.
+    pub fn from_sealed_bytes(bytes: Vec<u8>) -> Self {
+        Self {
+            bytes: ByteString(bytes),
+            _type: PhantomData,
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        self.bytes.as_bytes()
+    }
+}
+
+impl<T> Clone for Sealed<T> {
+    fn clone(&self) -> Self {
+        Self::from_sealed_bytes(self.bytes.0.clone())
+    }
+}
+
+impl<T> PartialEq for Sealed<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.bytes == other.bytes
+    }
+}
+
+impl<T> Eq for Sealed<T> {}
+
+impl<T> fmt::Debug for Sealed<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Sealed({} bytes)", self.bytes.0.len())
+    }
+}
+
+impl<T> Serialize for Sealed<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.bytes.serialize(serializer)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Sealed<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        ByteString::deserialize(deserializer).map(|bytes| Self::from_sealed_bytes(bytes.0))
+    }
+}
+
+impl<T: Oxide> Oxide for Sealed<T> {
+    fn schema() -> Structure {
+        Structure::ByteString
+    }
+
+    fn visit_bonds(&self, _visitor: &mut dyn BondVisitor) {}
+
+    fn map_bonds(&self, _mapper: &mut impl BondMapper) -> Self {
+        self.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seal_and_open() {
+        let key = EncryptionKey::from_bytes([7; 32]);
+        let sealed = Sealed::seal(&"secret".to_string(), &key);
+
+        assert_eq!(sealed.open(&key).unwrap(), "secret");
+        assert!(!sealed.as_bytes().windows(6).any(|w| w == b"secret"));
+
+        // Same value and key, same bytes; another key, other bytes
+        assert_eq!(Sealed::seal(&"secret".to_string(), &key), sealed);
+        let other = EncryptionKey::from_bytes([8; 32]);
+        assert_ne!(Sealed::seal(&"secret".to_string(), &other), sealed);
+        assert!(matches!(sealed.open(&other), Err(SealError::Decrypt)));
+    }
+
+    #[test]
+    fn rejects_tampered_and_malformed_bytes() {
+        let key = EncryptionKey::derive("polyepoxide tests", b"device secret");
+        let sealed = Sealed::seal(&42u64, &key);
+
+        let mut bytes = sealed.as_bytes().to_vec();
+        *bytes.last_mut().unwrap() ^= 1;
+        let tampered = Sealed::<u64>::from_sealed_bytes(bytes);
+        assert!(matches!(tampered.open(&key), Err(SealError::Decrypt)));
+
+        let short = Sealed::<u64>::from_sealed_bytes(vec![VERSION, 0, 0]);
+        assert!(matches!(short.open(&key), Err(SealError::Malformed)));
+        let future = Sealed::<u64>::from_sealed_bytes(vec![9; 64]);
+        assert!(matches!(
+            future.open(&key),
+            Err(SealError::UnsupportedVersion(9))
+        ));
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let key = EncryptionKey::from_bytes([1; 32]);
+        let sealed = Sealed::seal(&vec![1u32, 2, 3], &key);
+        let decoded = Sealed::<Vec<u32>>::from_bytes(&sealed.to_bytes()).unwrap();
+        assert_eq!(decoded.open(&key).unwrap(), vec![1, 2, 3]);
+        assert!(matches!(
+            Sealed::<Vec<u32>>::schema(),
+            Structure::ByteString
+        ));
+    }
+}
@@ -99,6 +99,59 @@ pub mod result_lowercase {
     }
 }
 
+/// Serialize `u128` as a 16-byte big-endian byte string.
+///
+/// DAG-CBOR integers are limited to 64 bits.
+pub mod u128_as_bytes {
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &u128, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&value.to_be_bytes())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u128, D::Error> {
+        super::int128::deserialize_bytes(deserializer).map(u128::from_be_bytes)
+    }
+}
+
+/// Serialize `i128` as a 16-byte big-endian two's complement byte string.
+pub mod i128_as_bytes {
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &i128, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&value.to_be_bytes())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i128, D::Error> {
+        super::int128::deserialize_bytes(deserializer).map(i128::from_be_bytes)
+    }
+}
+
+mod int128 {
+    use serde::de::{Error, Visitor};
+    use serde::Deserializer;
+
+    pub fn deserialize_bytes<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<[u8; 16], D::Error> {
+        struct BytesVisitor;
+
+        impl Visitor<'_> for BytesVisitor {
+            type Value = [u8; 16];
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("16 bytes")
+            }
+
+            fn visit_bytes<E: Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+                v.try_into().map_err(|_| E::invalid_length(v.len(), &self))
+            }
+        }
+
+        deserializer.deserialize_bytes(BytesVisitor)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -152,4 +205,23 @@ mod tests {
         let recovered: WithResult = serde_ipld_dagcbor::from_slice(&bytes).unwrap();
         assert_eq!(recovered, v);
     }
+
+    #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+    struct WithWide {
+        #[serde(with = "u128_as_bytes")]
+        unsigned: u128,
+        #[serde(with = "i128_as_bytes")]
+        signed: i128,
+    }
+
+    #[test]
+    fn int128_as_bytes_roundtrip() {
+        let v = WithWide {
+            unsigned: u128::MAX - 1,
+            signed: i128::MIN + 1,
+        };
+        let bytes = to_dagcbor(&v);
+        let recovered: WithWide = serde_ipld_dagcbor::from_slice(&bytes).unwrap();
+        assert_eq!(recovered, v);
+    }
 }
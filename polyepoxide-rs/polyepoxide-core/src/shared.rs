@@ -0,0 +1,171 @@
+//! Solvent that can be added to from many tasks at once.
+
+use std::any::Any;
+use std::sync::{Arc, RwLock};
+
+use cid::Cid;
+
+use crate::bond::Bond;
+use crate::cell::Cell;
+use crate::oxide::{BondMapper, Oxide};
+use crate::solvent::{persist_closure, CellLookup, Cells};
+use crate::store::Store;
+
+const SHARDS: usize = 16;
+
+/// A [`Solvent`](crate::Solvent) whose methods take `&self`, for sharing
+/// between tasks behind an `Arc`.
+///
+/// Cells are split over shards by CID, each behind its own lock, so tasks
+/// adding unrelated values rarely wait for each other. Forks, eviction and
+/// validators are only available on `Solvent`.
+pub struct SharedSolvent {
+    shards: Box<[RwLock<Cells>]>,
+}
+
+impl SharedSolvent {
+    pub fn new() -> Self {
+        SharedSolvent {
+            shards: (0..SHARDS).map(|_| RwLock::default()).collect(),
+        }
+    }
+
+    fn shard(&self, cid: &Cid) -> &RwLock<Cells> {
+        // CIDs are hashes, so any digest byte spreads them evenly
+        let byte = cid.hash().digest().first().copied().unwrap_or_default();
+        &self.shards[byte as usize % self.shards.len()]
+    }
+
+    fn lookup(&self, cid: &Cid) -> Option<Arc<dyn Any + Send + Sync>> {
+        self.shard(cid).read().unwrap().get(cid).cloned()
+    }
+
+    /// Adds an oxide and its nested bond targets, returning its cell.
+    ///
+    /// If an oxide with the same CID already exists, returns the existing
+    /// cell, including when another task added it concurrently.
+    pub fn add<T: Oxide>(&self, value: T) -> Arc<Cell<T>> {
+        let cid = value.compute_cid();
+        if let Some(cell) = self.get::<T>(&cid) {
+            return cell;
+        }
+
+        // No lock is held while adding the targets, as they may share a shard
+        let value = value.map_bonds(&mut SharedBondMapper { solvent: self });
+        let cell = Arc::new(Cell::with_cid(value, cid));
+
+        let mut shard = self.shard(&cid).write().unwrap();
+        if let Some(existing) = shard.get(&cid).cloned()
+            && let Ok(existing) = existing.downcast::<Cell<T>>()
+        {
+            return existing;
+        }
+        shard.insert(cid, cell.clone());
+        cell
+    }
+
+    /// Adds a value and returns a resolved bond to it.
+    pub fn bond<T: Oxide>(&self, value: T) -> Bond<T> {
+        Bond::from_cell(self.add(value))
+    }
+
+    /// Gets an oxide by CID, if it exists and has the correct type.
+    pub fn get<T: Oxide>(&self, cid: &Cid) -> Option<Arc<Cell<T>>> {
+        self.lookup(cid)?.downcast::<Cell<T>>().ok()
+    }
+
+    pub fn contains(&self, cid: &Cid) -> bool {
+        self.shard(cid).read().unwrap().contains_key(cid)
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards.iter().map(|shard| shard.read().unwrap().len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Resolves a bond to a cell of this solvent, if it has the target.
+    pub fn resolve<T: Oxide>(&self, bond: &Bond<T>) -> Bond<T> {
+        match bond {
+            Bond::Unresolved(cid) => match self.get::<T>(cid) {
+                Some(cell) => Bond::Resolved(cell),
+                None => bond.clone(),
+            },
+            Bond::Resolved(_) => bond.clone(),
+        }
+    }
+
+    /// Persists a cell, its transitive bond dependencies and the schema tree
+    /// of its type. Returns the value CID and schema CID.
+    pub fn persist_cell<T: Oxide, S: Store>(
+        &self,
+        cell: &Cell<T>,
+        store: &S,
+    ) -> Result<(Cid, Cid), S::Error> {
+        persist_closure(self, cell, store)
+    }
+}
+
+impl Default for SharedSolvent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CellLookup for SharedSolvent {
+    fn lookup_cell(&self, cid: &Cid) -> Option<Arc<dyn Any + Send + Sync>> {
+        self.lookup(cid)
+    }
+}
+
+/// Adds bond targets to the solvent, as `Solvent::add` does.
+struct SharedBondMapper<'a> {
+    solvent: &'a SharedSolvent,
+}
+
+impl BondMapper for SharedBondMapper<'_> {
+    fn map_bond<T: Oxide>(&mut self, bond: Bond<T>) -> Bond<T> {
+        match bond {
+            Bond::Unresolved(cid) => match self.solvent.get::<T>(&cid) {
+                Some(cell) => Bond::from_cell(cell),
+                None => Bond::Unresolved(cid),
+            },
+            Bond::Resolved(cell) => self.solvent.bond(cell.value().clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryStore;
+
+    #[test]
+    fn concurrent_adds_share_cells() {
+        let solvent = SharedSolvent::new();
+        let cells: Vec<_> = std::thread::scope(|scope| {
+            let add_all = || (0..100u64).map(|i| solvent.add(i)).collect::<Vec<_>>();
+            let tasks: Vec<_> = (0..4).map(|_| scope.spawn(add_all)).collect();
+            tasks.into_iter().map(|task| task.join().unwrap()).collect()
+        });
+        assert_eq!(solvent.len(), 100);
+        for other in &cells[1..] {
+            assert!(cells[0].iter().zip(other).all(|(a, b)| Arc::ptr_eq(a, b)));
+        }
+    }
+
+    #[test]
+    fn persists_nested_bonds() {
+        let solvent = SharedSolvent::new();
+        let inner = solvent.bond("inner".to_string());
+        let outer = solvent.add(vec![inner.clone()]);
+        assert_eq!(solvent.len(), 2);
+
+        let store = MemoryStore::new();
+        let (cid, _) = solvent.persist_cell(&outer, &store).unwrap();
+        assert_eq!(cid, outer.cid());
+        assert!(store.has(&inner.cid()).unwrap());
+    }
+}
@@ -0,0 +1,345 @@
+//! Slow-operation log for diagnosing store performance.
+//!
+//! `SlowLogStore` wraps a store and records every call that takes longer than
+//! a threshold. Records are emitted as `tracing` warnings, kept in a bounded
+//! in-memory buffer, and optionally appended to a file as tab-separated lines
+//! that `px slowlog` can summarize.
+
+use cid::Cid;
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::gc::GcStats;
+use crate::refs::RefStore;
+use crate::store::{Batch, Store};
+
+/// Number of records kept in memory.
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// A single store call that exceeded the threshold.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlowOp {
+    /// Wall-clock time the call finished, in milliseconds since epoch.
+    pub timestamp_ms: u64,
+    pub op: String,
+    /// The CID of the call, or the first of a batched call.
+    pub cid: Cid,
+    /// Payload size in bytes (read or written), 0 for `has`, `delete` and misses.
+    pub size: usize,
+    pub duration: Duration,
+    /// Name of the tracing span active when the call was made.
+    pub caller: Option<String>,
+}
+
+impl SlowOp {
+    /// Formats the record as a tab-separated log line (without newline).
+    pub fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            self.timestamp_ms,
+            self.op,
+            self.cid,
+            self.size,
+            self.duration.as_micros(),
+            self.caller.as_deref().unwrap_or("-"),
+        )
+    }
+
+    /// Parses a line produced by `to_line`.
+    pub fn parse_line(line: &str) -> Option<Self> {
+        let mut parts = line.split('\t');
+        let timestamp_ms = parts.next()?.parse().ok()?;
+        let op = parts.next()?.to_string();
+        let cid = parts.next()?.parse().ok()?;
+        let size = parts.next()?.parse().ok()?;
+        let duration = Duration::from_micros(parts.next()?.parse().ok()?);
+        let caller = match parts.next()? {
+            "-" => None,
+            name => Some(name.to_string()),
+        };
+        Some(Self {
+            timestamp_ms,
+            op,
+            cid,
+            size,
+            duration,
+            caller,
+        })
+    }
+}
+
+/// A store wrapper that logs calls slower than a threshold.
+pub struct SlowLogStore<S> {
+    inner: S,
+    threshold: Duration,
+    capacity: usize,
+    records: Mutex<VecDeque<SlowOp>>,
+    file: Option<Mutex<File>>,
+}
+
+impl<S: Store> SlowLogStore<S> {
+    pub fn new(inner: S, threshold: Duration) -> Self {
+        Self {
+            inner,
+            threshold,
+            capacity: DEFAULT_CAPACITY,
+            records: Mutex::new(VecDeque::new()),
+            file: None,
+        }
+    }
+
+    /// Sets how many recent records to keep in memory.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Appends records to the given file in addition to keeping them in memory.
+    pub fn with_log_file(mut self, path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        self.file = Some(Mutex::new(file));
+        Ok(self)
+    }
+
+    /// Returns the recorded slow calls, oldest first.
+    pub fn records(&self) -> Vec<SlowOp> {
+        self.records.lock().unwrap().iter().cloned().collect()
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    fn record(&self, op: &str, cid: &Cid, size: usize, duration: Duration) {
+        if duration < self.threshold {
+            return;
+        }
+
+        let caller = tracing::Span::current()
+            .metadata()
+            .map(|m| m.name().to_string());
+        tracing::warn!(
+            op,
+            cid = %cid,
+            size,
+            duration_ms = duration.as_millis() as u64,
+            caller = caller.as_deref().unwrap_or("-"),
+            "slow store operation"
+        );
+
+        let record = SlowOp {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_millis() as u64),
+            op: op.to_string(),
+            cid: *cid,
+            size,
+            duration,
+            caller,
+        };
+
+        // The log is best-effort: a failed write must not fail the store call
+        if let Some(file) = &self.file {
+            let _ = writeln!(file.lock().unwrap(), "{}", record.to_line());
+        }
+
+        let mut records = self.records.lock().unwrap();
+        if records.len() >= self.capacity {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+
+    /// Records a batched call under its first CID; empty calls aren't logged.
+    fn record_many<'a>(
+        &self,
+        op: &str,
+        mut cids: impl Iterator<Item = &'a Cid>,
+        size: usize,
+        duration: Duration,
+    ) {
+        if let Some(cid) = cids.next() {
+            self.record(op, cid, size, duration);
+        }
+    }
+}
+
+impl<S: Store> Store for SlowLogStore<S> {
+    type Error = S::Error;
+
+    fn get(&self, cid: &Cid) -> Result<Option<Vec<u8>>, Self::Error> {
+        let start = Instant::now();
+        let result = self.inner.get(cid);
+        let size = match &result {
+            Ok(Some(bytes)) => bytes.len(),
+            _ => 0,
+        };
+        self.record("get", cid, size, start.elapsed());
+        result
+    }
+
+    fn get_reader(&self, cid: &Cid) -> Result<Option<Box<dyn Read + '_>>, Self::Error> {
+        let start = Instant::now();
+        let result = self.inner.get_reader(cid);
+        self.record("get_reader", cid, 0, start.elapsed());
+        result
+    }
+
+    fn get_many(&self, cids: &[Cid]) -> Result<Vec<Option<Vec<u8>>>, Self::Error> {
+        let start = Instant::now();
+        let result = self.inner.get_many(cids);
+        let size = match &result {
+            Ok(values) => values.iter().flatten().map(Vec::len).sum(),
+            Err(_) => 0,
+        };
+        self.record_many("get_many", cids.iter(), size, start.elapsed());
+        result
+    }
+
+    fn put(&self, cid: &Cid, value: &[u8]) -> Result<(), Self::Error> {
+        let start = Instant::now();
+        let result = self.inner.put(cid, value);
+        self.record("put", cid, value.len(), start.elapsed());
+        result
+    }
+
+    fn put_schema(&self, cid: &Cid, value: &[u8]) -> Result<(), Self::Error> {
+        let start = Instant::now();
+        let result = self.inner.put_schema(cid, value);
+        self.record("put", cid, value.len(), start.elapsed());
+        result
+    }
+
+    fn put_many(&self, nodes: &[(&Cid, &[u8])]) -> Result<(), Self::Error> {
+        let start = Instant::now();
+        let result = self.inner.put_many(nodes);
+        let size = nodes.iter().map(|(_, value)| value.len()).sum();
+        let cids = nodes.iter().map(|(cid, _)| *cid);
+        self.record_many("put_many", cids, size, start.elapsed());
+        result
+    }
+
+    fn write_batch(&self, batch: &Batch) -> Result<(), Self::Error> {
+        let start = Instant::now();
+        let result = self.inner.write_batch(batch);
+        let blocks = batch.schemas().chain(batch.values());
+        let size = blocks.map(|(_, value)| value.len()).sum();
+        self.record_many("write_batch", batch.cids(), size, start.elapsed());
+        result
+    }
+
+    fn has(&self, cid: &Cid) -> Result<bool, Self::Error> {
+        let start = Instant::now();
+        let result = self.inner.has(cid);
+        self.record("has", cid, 0, start.elapsed());
+        result
+    }
+
+    fn delete(&self, cid: &Cid) -> Result<(), Self::Error> {
+        let start = Instant::now();
+        let result = self.inner.delete(cid);
+        self.record("delete", cid, 0, start.elapsed());
+        result
+    }
+
+    fn delete_many(&self, cids: &[Cid]) -> Result<(), Self::Error> {
+        let start = Instant::now();
+        let result = self.inner.delete_many(cids);
+        self.record_many("delete_many", cids.iter(), 0, start.elapsed());
+        result
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = Result<Cid, Self::Error>> + '_> {
+        self.inner.iter()
+    }
+
+    fn gc(&self, roots: &[Cid]) -> Result<GcStats, Self::Error> {
+        self.inner.gc(roots)
+    }
+
+    fn compact(&self) -> Result<(), Self::Error> {
+        self.inner.compact()
+    }
+}
+
+/// Refs are forwarded untimed: they are a name lookup, not block I/O.
+impl<S: RefStore> RefStore for SlowLogStore<S> {
+    fn get_ref(&self, name: &str) -> Result<Option<Cid>, Self::Error> {
+        self.inner.get_ref(name)
+    }
+
+    fn set_ref(&self, name: &str, cid: &Cid) -> Result<(), Self::Error> {
+        self.inner.set_ref(name, cid)
+    }
+
+    fn delete_ref(&self, name: &str) -> Result<(), Self::Error> {
+        self.inner.delete_ref(name)
+    }
+
+    fn list_refs(&self, prefix: &str) -> Result<Vec<(String, Cid)>, Self::Error> {
+        self.inner.list_refs(prefix)
+    }
+
+    fn compare_and_set_ref(
+        &self,
+        name: &str,
+        expected: Option<&Cid>,
+        new: &Cid,
+    ) -> Result<bool, Self::Error> {
+        self.inner.compare_and_set_ref(name, expected, new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oxide::compute_cid;
+    use crate::{FaultyStore, MemoryStore};
+
+    #[test]
+    fn records_calls_over_threshold() {
+        let cid = compute_cid(b"data");
+
+        let quiet = SlowLogStore::new(MemoryStore::new(), Duration::from_secs(60));
+        quiet.put(&cid, b"data").unwrap();
+        assert!(quiet.records().is_empty());
+
+        let logged = SlowLogStore::new(MemoryStore::new(), Duration::ZERO).with_capacity(2);
+        logged.put(&cid, b"data").unwrap();
+        logged.get(&cid).unwrap();
+        logged.has(&cid).unwrap();
+
+        let records = logged.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].op, "get");
+        assert_eq!(records[0].size, 4);
+        assert_eq!(records[1].op, "has");
+        let line = records[0].to_line();
+        assert_eq!(SlowOp::parse_line(&line).unwrap().to_line(), line);
+    }
+
+    #[test]
+    fn forwards_batches_whole() {
+        // The inner store fails every call after the first, so a batch
+        // split into single puts would be left half written
+        let inner = FaultyStore::new(MemoryStore::new(), 1).with_fail_after(1);
+        let store = SlowLogStore::new(inner, Duration::ZERO);
+        let mut batch = Batch::new();
+        for block in ["a", "b", "c"] {
+            batch.put(compute_cid(block.as_bytes()), block.as_bytes().to_vec());
+        }
+
+        store.write_batch(&batch).unwrap();
+        for cid in batch.cids() {
+            assert!(store.inner().inner().has(cid).unwrap());
+        }
+        let records = store.records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].op, "write_batch");
+        assert_eq!(records[0].size, 3);
+    }
+}
@@ -1,14 +1,19 @@
 use cid::Cid;
 use log::debug;
-use std::any::Any;
+use std::any::{Any, TypeId};
 use std::collections::{HashMap, HashSet};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
+use crate::async_store::AsyncStore;
 use crate::bond::Bond;
 use crate::cell::Cell;
-use crate::oxide::{BondMapper, Oxide};
+use crate::eviction::{EvictionPolicy, Usage};
+use crate::gc::push_links;
+use crate::limits::{DecodeLimits, LimitError};
+use crate::oxide::{compute_cid, BondMapper, Oxide};
 use crate::schema::Structure;
-use crate::store::Store;
+use crate::store::{Batch, MemoryStore, Store};
+use crate::traverse::parse_to_ipld;
 
 /// Error type for solvent operations.
 #[derive(Debug, thiserror::Error)]
@@ -17,6 +22,78 @@ pub enum SolventError {
     NotFound(Cid),
     #[error("type mismatch for CID {0}")]
     TypeMismatch(Cid),
+    #[error("block rejected: {0}")]
+    Rejected(#[from] LimitError),
+    #[error("decode error: {0}")]
+    Decode(String),
+}
+
+/// An application invariant violated by a value.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{cid}: {message}")]
+pub struct Violation {
+    pub cid: Cid,
+    pub message: String,
+}
+
+/// Error from persisting a cell.
+#[derive(Debug, thiserror::Error)]
+pub enum PersistError<E> {
+    #[error("store error: {0}")]
+    Store(E),
+    #[error("validation failed: {}", format_violations(.0))]
+    Invalid(Vec<Violation>),
+}
+
+/// Error from loading a value from a store.
+#[derive(Debug, thiserror::Error)]
+pub enum LoadError<E> {
+    #[error(transparent)]
+    Solvent(#[from] SolventError),
+    #[error("store error: {0}")]
+    Store(E),
+}
+
+fn format_violations(violations: &[Violation]) -> String {
+    violations
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Checks an application invariant on values of type `T`.
+///
+/// Validators are registered on a Solvent and run by `persist_cell` over the
+/// whole closure being persisted, so invalid data never gets written (and
+/// thus never gets synced).
+pub trait Validator<T: Oxide>: Send + Sync + 'static {
+    /// Returns a description of the violation if the value is invalid.
+    fn validate(&self, value: &T) -> Result<(), String>;
+}
+
+impl<T: Oxide, F> Validator<T> for F
+where
+    F: Fn(&T) -> Result<(), String> + Send + Sync + 'static,
+{
+    fn validate(&self, value: &T) -> Result<(), String> {
+        self(value)
+    }
+}
+
+type ErasedValidator = Arc<dyn Fn(&dyn Any) -> Result<(), String> + Send + Sync>;
+
+pub(crate) type Cells = HashMap<Cid, Arc<dyn Any + Send + Sync>>;
+
+/// Cells by CID, as followed when persisting.
+pub(crate) trait CellLookup {
+    fn lookup_cell(&self, cid: &Cid) -> Option<Arc<dyn Any + Send + Sync>>;
+}
+
+impl CellLookup for Solvent {
+    fn lookup_cell(&self, cid: &Cid) -> Option<Arc<dyn Any + Send + Sync>> {
+        self.lookup(cid).cloned()
+    }
 }
 
 /// Solvent manages oxides in memory and coordinates with backing stores.
@@ -28,16 +105,202 @@ pub enum SolventError {
 /// When adding an oxide, all nested bond targets are also added to the solvent,
 /// achieving deduplication of shared sub-structures.
 ///
+/// A solvent can be forked into a copy-on-write view for speculative edits;
+/// see [`Solvent::fork`].
+///
+/// Cells are kept until evicted, explicitly or by an [`EvictionPolicy`].
+///
 /// Future: will coordinate with disk/remote stores for loading.
 pub struct Solvent {
-    cells: HashMap<Cid, Arc<dyn Any + Send + Sync>>,
+    /// Cells added to this solvent itself.
+    cells: Arc<Cells>,
+    /// Cells of the solvents this one was forked from, oldest first. Shared
+    /// with those solvents and never mutated: a forked-from solvent adding
+    /// more cells copies its own layer first.
+    layers: Vec<Arc<Cells>>,
+    /// Recency and size of `cells`. Behind a mutex as lookups update it.
+    usage: Mutex<Usage>,
+    eviction: EvictionPolicy,
+    validators: HashMap<TypeId, Vec<ErasedValidator>>,
+    decode_limits: DecodeLimits,
 }
 
 impl Solvent {
     /// Creates a new empty solvent.
     pub fn new() -> Self {
         Solvent {
-            cells: HashMap::new(),
+            cells: Arc::new(HashMap::new()),
+            layers: Vec::new(),
+            usage: Mutex::default(),
+            eviction: EvictionPolicy::default(),
+            validators: HashMap::new(),
+            decode_limits: DecodeLimits::default(),
+        }
+    }
+
+    /// Sets the limits checked by `decode`.
+    pub fn set_decode_limits(&mut self, limits: DecodeLimits) {
+        self.decode_limits = limits;
+    }
+
+    /// Returns the limits checked by `decode`.
+    pub fn decode_limits(&self) -> DecodeLimits {
+        self.decode_limits
+    }
+
+    /// Decodes an oxide from untrusted bytes and adds it to the solvent.
+    ///
+    /// The bytes are checked against the decode limits before decoding.
+    pub fn decode<T: Oxide>(&mut self, bytes: &[u8]) -> Result<Arc<Cell<T>>, SolventError> {
+        self.decode_limits.check(bytes)?;
+        let value = T::from_bytes(bytes).map_err(|e| SolventError::Decode(e.to_string()))?;
+        Ok(self.add(value))
+    }
+
+    /// Creates a copy-on-write view of this solvent.
+    ///
+    /// The fork sees every cell of this solvent, but cells added to it stay
+    /// out of this solvent until [`merge_into`](Self::merge_into) is called,
+    /// so it can be dropped to discard a speculative edit. Forking doesn't
+    /// copy any cells; adding to this solvent while a fork is alive copies
+    /// the map of its own cells once.
+    pub fn fork(&self) -> Solvent {
+        let mut layers = self.layers.clone();
+        layers.push(Arc::clone(&self.cells));
+        Solvent {
+            cells: Arc::new(HashMap::new()),
+            layers,
+            usage: Mutex::default(),
+            eviction: self.eviction,
+            validators: self.validators.clone(),
+            decode_limits: self.decode_limits,
+        }
+    }
+
+    /// Adds the cells added to this fork to `parent`.
+    ///
+    /// Only cells added since the fork are moved, so `parent` should be the
+    /// solvent this one was forked from (or one holding the same cells).
+    pub fn merge_into(self, parent: &mut Solvent) {
+        let usage = self.usage.into_inner().unwrap();
+        let cells = Arc::make_mut(&mut parent.cells);
+        let parent_usage = parent.usage.get_mut().unwrap();
+        for (cid, cell) in Arc::unwrap_or_clone(self.cells) {
+            if !parent.layers.iter().any(|layer| layer.contains_key(&cid)) {
+                cells.entry(cid).or_insert(cell);
+                parent_usage.insert(cid, usage.size(&cid).unwrap_or_default());
+            }
+        }
+        parent.evict_excess();
+    }
+
+    /// Sets which cells are evicted as new ones are added, evicting any
+    /// excess right away.
+    pub fn set_eviction_policy(&mut self, policy: EvictionPolicy) {
+        self.eviction = policy;
+        self.evict_excess();
+    }
+
+    /// Drops this solvent's reference to a cell, returning whether it held
+    /// one. Cells of the solvents this one was forked from are kept.
+    ///
+    /// Values bonding to the cell keep it alive; it is freed once they are.
+    pub fn evict(&mut self, cid: &Cid) -> bool {
+        if !self.cells.contains_key(cid) {
+            return false;
+        }
+        Arc::make_mut(&mut self.cells).remove(cid);
+        self.usage.get_mut().unwrap().remove(cid);
+        #[cfg(feature = "metrics")]
+        crate::metered::cell_evicted();
+        true
+    }
+
+    /// Evicts the least recently used cells until their encoded size is at
+    /// most `bytes`.
+    pub fn shrink_to(&mut self, bytes: usize) {
+        self.evict_while(|usage| usage.bytes() > bytes);
+    }
+
+    /// Returns the encoded size of the cells held by this solvent itself.
+    pub fn retained_bytes(&self) -> usize {
+        self.usage.lock().unwrap().bytes()
+    }
+
+    fn evict_excess(&mut self) {
+        let policy = self.eviction;
+        self.evict_while(|usage| policy.exceeded_by(usage));
+    }
+
+    /// Evicts the least recently used cells that nothing but this solvent
+    /// holds while `over` the limit; evicting others would free nothing.
+    fn evict_while(&mut self, over: impl Fn(&Usage) -> bool) {
+        // Forks hold every cell this solvent has so far
+        if Arc::strong_count(&self.cells) > 1 {
+            return;
+        }
+        let usage = self.usage.get_mut().unwrap();
+        while over(usage) {
+            let cells = &self.cells;
+            let unheld = |cid: &Cid| {
+                cells.get(cid).is_some_and(|cell| Arc::strong_count(cell) == 1)
+            };
+            let Some(cid) = usage.pop_oldest(unheld) else { break };
+            Arc::make_mut(&mut self.cells).remove(&cid);
+            #[cfg(feature = "metrics")]
+            crate::metered::cell_evicted();
+        }
+    }
+
+    fn lookup(&self, cid: &Cid) -> Option<&Arc<dyn Any + Send + Sync>> {
+        self.cells
+            .get(cid)
+            .or_else(|| self.layers.iter().rev().find_map(|layer| layer.get(cid)))
+    }
+
+    /// Registers a validator run on every value of type `T` before persisting.
+    pub fn add_validator<T: Oxide>(&mut self, validator: impl Validator<T>) {
+        let erased: ErasedValidator = Arc::new(move |value: &dyn Any| {
+            validator.validate(value.downcast_ref::<T>().expect("validator keyed by TypeId"))
+        });
+        self.validators
+            .entry(TypeId::of::<T>())
+            .or_default()
+            .push(erased);
+    }
+
+    /// Runs registered validators over a value and all bond targets reachable
+    /// through the solvent.
+    ///
+    /// Walks with an explicit stack, so long chains of bonds don't overflow the
+    /// call stack.
+    pub fn validate<T: Oxide>(&self, value: &T) -> Vec<Violation> {
+        if self.validators.is_empty() {
+            return Vec::new();
+        }
+        let mut violations = Vec::new();
+        let mut visited = HashSet::new();
+        let mut stack: Vec<Box<dyn PendingCheck + '_>> = vec![Box::new(value)];
+        while let Some(pending) = stack.pop() {
+            if !visited.insert(pending.cid()) {
+                continue;
+            }
+            let children = pending.check(self, &mut violations);
+            // Reversed, so children are checked in the order they're bonded
+            stack.extend(children.into_iter().rev());
+        }
+        violations
+    }
+
+    /// Runs the validators registered for `T` over a value.
+    fn run_validators<T: Oxide>(&self, cid: Cid, value: &T, violations: &mut Vec<Violation>) {
+        let Some(validators) = self.validators.get(&TypeId::of::<T>()) else {
+            return;
+        };
+        for validator in validators {
+            if let Err(message) = validator(value) {
+                violations.push(Violation { cid, message });
+            }
         }
     }
 
@@ -49,12 +312,14 @@ impl Solvent {
     pub fn add<T: Oxide>(&mut self, value: T) -> Arc<Cell<T>> {
         // Compute CID first - this is the same whether bonds are resolved or not,
         // since bonds serialize to just their CID
-        let cid = value.compute_cid();
+        let bytes = value.to_bytes();
+        let cid = compute_cid(&bytes);
         debug!("Adding {:?}", cid);
 
         // Check if already exists - return existing cell
-        if let Some(existing) = self.cells.get(&cid) {
+        if let Some(existing) = self.lookup(&cid) {
             if let Some(cell) = existing.clone().downcast::<Cell<T>>().ok() {
+                self.usage.get_mut().unwrap().touch(&cid);
                 return cell;
             }
             // Type mismatch - this shouldn't happen with correct usage
@@ -66,7 +331,11 @@ impl Solvent {
 
         // Create and store the cell
         let cell = Arc::new(Cell::with_cid(value, cid));
-        self.cells.insert(cid, cell.clone());
+        Arc::make_mut(&mut self.cells).insert(cid, cell.clone());
+        self.usage.get_mut().unwrap().insert(cid, bytes.len());
+        #[cfg(feature = "metrics")]
+        crate::metered::cell_added();
+        self.evict_excess();
         cell
     }
 
@@ -78,24 +347,25 @@ impl Solvent {
 
     /// Gets an oxide by CID, if it exists and has the correct type.
     pub fn get<T: Oxide>(&self, cid: &Cid) -> Option<Arc<Cell<T>>> {
-        self.cells
-            .get(cid)
-            .and_then(|any| any.clone().downcast::<Cell<T>>().ok())
+        let cell = self.lookup(cid)?.clone().downcast::<Cell<T>>().ok()?;
+        self.usage.lock().unwrap().touch(cid);
+        Some(cell)
     }
 
     /// Checks if an oxide with the given CID exists.
     pub fn contains(&self, cid: &Cid) -> bool {
-        self.cells.contains_key(cid)
+        self.lookup(cid).is_some()
     }
 
     /// Returns the number of oxides in the solvent.
     pub fn len(&self) -> usize {
-        self.cells.len()
+        // Layers are disjoint: a cell is only added if no layer has it yet
+        self.cells.len() + self.layers.iter().map(|layer| layer.len()).sum::<usize>()
     }
 
     /// Returns true if the solvent is empty.
     pub fn is_empty(&self) -> bool {
-        self.cells.is_empty()
+        self.len() == 0
     }
 
     /// Creates a resolved bond for the given value.
@@ -123,80 +393,297 @@ impl Solvent {
         }
     }
 
+    /// Loads a value from `store`, resolving its bonds to targets loaded up to
+    /// `depth` levels deep (0 loads only the value itself).
+    ///
+    /// Values already in the solvent are not fetched again, and their bonds
+    /// are left as they are.
+    pub fn load<T: Oxide, S: Store>(
+        &mut self,
+        cid: &Cid,
+        store: &S,
+        depth: usize,
+    ) -> Result<Arc<Cell<T>>, LoadError<S::Error>> {
+        if let Some(cell) = self.get::<T>(cid) {
+            return Ok(cell);
+        }
+        let bytes = self.fetch(cid, store)?;
+        let value = T::from_bytes(&bytes).map_err(|e| SolventError::Decode(e.to_string()))?;
+
+        // Targets are added first, so that `add` resolves the bonds to them
+        if depth > 0 {
+            self.load_targets(&value, store, depth - 1)?;
+        }
+        Ok(self.add(value))
+    }
+
+    /// Loads the bond targets of `value` up to `depth` levels below them.
+    ///
+    /// Walks with an explicit stack, so long chains of bonds don't overflow the
+    /// call stack.
+    fn load_targets<T: Oxide, S: Store>(
+        &mut self,
+        value: &T,
+        store: &S,
+        depth: usize,
+    ) -> Result<(), LoadError<S::Error>> {
+        // Each target is pushed again as decoded once its own targets are
+        // pushed above it, and added when popped a second time
+        let mut stack: Vec<(Box<dyn PendingLoad>, usize, bool)> = bond_targets(value)
+            .into_iter()
+            .rev()
+            .map(|target| (target, depth, false))
+            .collect();
+        while let Some((mut target, depth, decoded)) = stack.pop() {
+            if decoded {
+                target.add(self);
+                continue;
+            }
+            if target.is_loaded(self) {
+                continue;
+            }
+            let bytes = self.fetch(&target.cid(), store)?;
+            let targets = target.decode(&bytes)?;
+            stack.push((target, depth, true));
+            if depth > 0 {
+                stack.extend(targets.into_iter().rev().map(|t| (t, depth - 1, false)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a block to decode from `store`, checking it against the decode
+    /// limits.
+    fn fetch<S: Store>(&self, cid: &Cid, store: &S) -> Result<Vec<u8>, LoadError<S::Error>> {
+        let bytes = store
+            .get(cid)
+            .map_err(LoadError::Store)?
+            .ok_or(SolventError::NotFound(*cid))?;
+        self.decode_limits.check(&bytes).map_err(SolventError::from)?;
+        Ok(bytes)
+    }
+
+    /// Async variant of [`load`](Self::load).
+    ///
+    /// Blocks are fetched level by level, with one batched request per level,
+    /// before being decoded.
+    pub async fn load_async<T: Oxide, S: AsyncStore>(
+        &mut self,
+        cid: &Cid,
+        store: &S,
+        depth: usize,
+    ) -> Result<Arc<Cell<T>>, LoadError<S::Error>> {
+        // Bond types are only known while decoding, so blocks are fetched by
+        // following their raw links; in values, links are always bonds
+        let fetched = MemoryStore::new();
+        let mut seen = HashSet::new();
+        let mut level = vec![*cid];
+        for remaining in (0..=depth).rev() {
+            let wanted: Vec<Cid> = level
+                .into_iter()
+                .filter(|cid| !self.contains(cid) && seen.insert(*cid))
+                .collect();
+            if wanted.is_empty() {
+                break;
+            }
+            let blocks = store.async_get_many(&wanted).await.map_err(LoadError::Store)?;
+            level = Vec::new();
+            for (cid, bytes) in wanted.iter().zip(blocks) {
+                // Missing blocks are reported by `load`
+                let Some(bytes) = bytes else { continue };
+                if remaining > 0
+                    && let Ok(ipld) = parse_to_ipld(&bytes)
+                {
+                    push_links(&ipld, &mut level);
+                }
+                fetched.put(cid, &bytes).unwrap_or_else(|e| match e {});
+            }
+        }
+
+        self.load(cid, &fetched, depth).map_err(|e| match e {
+            LoadError::Solvent(e) => LoadError::Solvent(e),
+            LoadError::Store(e) => match e {},
+        })
+    }
+
     /// Persists a cell and all its transitive bond dependencies to a store.
     ///
     /// Also persists the schema tree for the value's type.
+    /// Registered validators run first; nothing is written if any fails.
     /// Returns the value CID and schema CID.
     pub fn persist_cell<T: Oxide, S: Store>(
         &self,
         cell: &Cell<T>,
         store: &S,
-    ) -> Result<(Cid, Cid), S::Error> {
-        let mut visited = HashSet::new();
-        debug!("Persisting cell {:?}", cell.cid());
-
-        // Persist the schema tree first
-        // Use a temporary solvent to resolve schema bonds
-        debug!("aaa {:?}", cell.value());
-        let mut schema_solvent = Solvent::new();
-        debug!("bbb");
-        let schema = T::schema();
-        debug!("ccc");
-        let schema_cell = schema_solvent.add(schema);
-        debug!("ddd");
-        let schema_cid = schema_cell.cid();
-        debug!("eee");
-
-        // Persist all schemas from the solvent
-        for (cid, any_cell) in &schema_solvent.cells {
-            if let Some(structure_cell) = any_cell.clone().downcast::<Cell<Structure>>().ok() {
-                debug!("Serializing {:?}", cid);
-                let bytes = structure_cell.value().to_bytes();
-                debug!("Putting {:?}", cid);
-                store.put(cid, &bytes)?;
-                visited.insert(*cid);
+    ) -> Result<(Cid, Cid), PersistError<S::Error>> {
+        self.check_valid(cell.value())?;
+        persist_closure(self, cell, store).map_err(PersistError::Store)
+    }
+
+    /// Like [`persist_cell`](Self::persist_cell), but writes in chunks and
+    /// calls `progress` with each CID written, the number written so far and
+    /// the total. Returns every CID of the closure.
+    ///
+    /// Chunks are written dependency-first, so an interrupted save never
+    /// leaves a value without its dependencies.
+    pub fn persist_cell_with<T: Oxide, S: Store>(
+        &self,
+        cell: &Cell<T>,
+        store: &S,
+        mut progress: impl FnMut(Cid, usize, usize),
+    ) -> Result<Persisted, PersistError<S::Error>> {
+        self.check_valid(cell.value())?;
+        let (batch, schema) = collect_closure(self, cell);
+        let total = batch.len();
+        let mut cids = Vec::with_capacity(total);
+        for chunk in batch.split(PERSIST_CHUNK_SIZE) {
+            store.write_batch(&chunk).map_err(PersistError::Store)?;
+            for cid in chunk.cids() {
+                cids.push(*cid);
+                progress(*cid, cids.len(), total);
             }
         }
+        Ok(Persisted {
+            value: cell.cid(),
+            schema,
+            cids,
+        })
+    }
 
-        // Persist the value and all bond dependencies
-        self.persist_value(cell.value(), store, &mut visited)?;
+    fn check_valid<T: Oxide, E>(&self, value: &T) -> Result<(), PersistError<E>> {
+        let violations = self.validate(value);
+        if !violations.is_empty() {
+            return Err(PersistError::Invalid(violations));
+        }
+        Ok(())
+    }
+}
+
+/// Writes per chunk in [`Solvent::persist_cell_with`].
+const PERSIST_CHUNK_SIZE: usize = 64;
+
+/// Result of [`Solvent::persist_cell_with`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Persisted {
+    pub value: Cid,
+    pub schema: Cid,
+    /// Schemas, then values dependency-first, ending with `value`.
+    pub cids: Vec<Cid>,
+}
+
+/// Persists a cell, its transitive bond dependencies found in `cells` and
+/// the schema tree of its type, returning the value CID and schema CID.
+///
+/// Everything is written in one batch, so on stores with atomic batches the
+/// cell never becomes visible without its dependencies.
+pub(crate) fn persist_closure<T: Oxide, S: Store>(
+    cells: &dyn CellLookup,
+    cell: &Cell<T>,
+    store: &S,
+) -> Result<(Cid, Cid), S::Error> {
+    let (batch, schema_cid) = collect_closure(cells, cell);
+    store.write_batch(&batch)?;
+    Ok((cell.cid(), schema_cid))
+}
 
-        Ok((cell.cid(), schema_cid))
+/// Encodes a cell, its dependencies found in `cells` and the schema tree of
+/// its type into a batch. Returns the batch and the schema CID.
+fn collect_closure<T: Oxide>(cells: &dyn CellLookup, cell: &Cell<T>) -> (Batch, Cid) {
+    let mut batch = Batch::new();
+    let mut visited = HashSet::new();
+    debug!("Persisting cell {:?}", cell.cid());
+
+    // Persist the schema tree first
+    // Use a temporary solvent to resolve schema bonds
+    let mut schema_solvent = Solvent::new();
+    let schema_cell = schema_solvent.add(T::schema());
+    let schema_cid = schema_cell.cid();
+
+    // Persist all schemas from the solvent
+    for (cid, any_cell) in schema_solvent.cells.iter() {
+        if let Some(structure_cell) = any_cell.clone().downcast::<Cell<Structure>>().ok() {
+            debug!("Putting {:?}", cid);
+            let bytes = structure_cell.value().to_bytes();
+            batch.put_schema(*cid, bytes);
+            visited.insert(*cid);
+        }
     }
 
-    /// Persists a value and all its bond dependencies.
-    /// Uses dependency-first order: children are stored before parents.
-    fn persist_value<T: Oxide, S: Store>(
-        &self,
-        value: &T,
-        store: &S,
-        visited: &mut HashSet<Cid>,
-    ) -> Result<(), S::Error> {
-        let cid = value.compute_cid();
+    // Persist the value and all bond dependencies
+    persist_value(cells, cell.value(), &mut batch, &mut visited);
+
+    (batch, schema_cid)
+}
+
+/// Persists a value and all its bond dependencies.
+/// Uses dependency-first order: children are stored before parents.
+///
+/// Walks with an explicit stack, so long chains of bonds don't overflow the
+/// call stack.
+fn persist_value<T: Oxide>(
+    cells: &dyn CellLookup,
+    value: &T,
+    batch: &mut Batch,
+    visited: &mut HashSet<Cid>,
+) {
+    // Each value is pushed again as expanded once its children are pushed
+    // above it, and written when popped a second time
+    let mut stack: Vec<(Box<dyn PendingValue + '_>, bool)> = vec![(Box::new(value), false)];
+    while let Some((pending, expanded)) = stack.pop() {
+        let cid = pending.cid();
+        if expanded {
+            batch.put(cid, pending.bytes());
+            continue;
+        }
         debug!("Persisting value {:?}", cid);
-        if visited.contains(&cid) {
-            return Ok(());
+        if !visited.insert(cid) {
+            continue;
         }
-        visited.insert(cid);
+        let children = pending.children(cells);
+        stack.push((pending, true));
+        // Reversed, so children are written in the order they're bonded
+        stack.extend(children.into_iter().rev().map(|child| (child, false)));
+    }
+}
+
+/// A value of any oxide type waiting to be persisted.
+trait PendingValue {
+    fn cid(&self) -> Cid;
+    fn bytes(&self) -> Vec<u8>;
+    /// The values this one bonds to that are found in `cells`.
+    fn children(&self, cells: &dyn CellLookup) -> Vec<Box<dyn PendingValue>>;
+}
+
+impl<T: Oxide> PendingValue for &T {
+    fn cid(&self) -> Cid {
+        self.compute_cid()
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        self.to_bytes()
+    }
 
-        // First persist all bond dependencies (children before parent)
+    fn children(&self, cells: &dyn CellLookup) -> Vec<Box<dyn PendingValue>> {
         let mut mapper = PersistingMapper {
-            solvent: self,
-            store,
-            visited,
-            error: None,
+            cells,
+            children: Vec::new(),
         };
-        value.map_bonds(&mut mapper);
+        self.map_bonds(&mut mapper);
+        mapper.children
+    }
+}
 
-        if let Some(e) = mapper.error {
-            return Err(e);
-        }
+impl<T: Oxide> PendingValue for Arc<Cell<T>> {
+    fn cid(&self) -> Cid {
+        Cell::cid(self)
+    }
 
-        // Then persist this value
-        let bytes = value.to_bytes();
-        store.put(&cid, &bytes)?;
+    fn bytes(&self) -> Vec<u8> {
+        self.value().to_bytes()
+    }
 
-        Ok(())
+    fn children(&self, cells: &dyn CellLookup) -> Vec<Box<dyn PendingValue>> {
+        self.value().children(cells)
     }
 }
 
@@ -233,38 +720,147 @@ impl BondMapper for SolventBondMapper<'_> {
     }
 }
 
-/// Bond mapper that persists bond targets to a store.
-struct PersistingMapper<'a, S: Store> {
+/// A value of any oxide type waiting to be validated.
+trait PendingCheck {
+    fn cid(&self) -> Cid;
+    /// Runs the validators of the value's type, returning the bond targets
+    /// found through the solvent.
+    fn check(
+        &self,
+        solvent: &Solvent,
+        violations: &mut Vec<Violation>,
+    ) -> Vec<Box<dyn PendingCheck>>;
+}
+
+impl<T: Oxide> PendingCheck for &T {
+    fn cid(&self) -> Cid {
+        self.compute_cid()
+    }
+
+    fn check(
+        &self,
+        solvent: &Solvent,
+        violations: &mut Vec<Violation>,
+    ) -> Vec<Box<dyn PendingCheck>> {
+        solvent.run_validators(self.compute_cid(), *self, violations);
+        let mut mapper = ValidatingMapper {
+            solvent,
+            children: Vec::new(),
+        };
+        self.map_bonds(&mut mapper);
+        mapper.children
+    }
+}
+
+impl<T: Oxide> PendingCheck for Arc<Cell<T>> {
+    fn cid(&self) -> Cid {
+        Cell::cid(self)
+    }
+
+    fn check(
+        &self,
+        solvent: &Solvent,
+        violations: &mut Vec<Violation>,
+    ) -> Vec<Box<dyn PendingCheck>> {
+        solvent.run_validators(Cell::cid(self), self.value(), violations);
+        let mut mapper = ValidatingMapper {
+            solvent,
+            children: Vec::new(),
+        };
+        self.value().map_bonds(&mut mapper);
+        mapper.children
+    }
+}
+
+/// Bond mapper collecting the bond targets found through the solvent, for
+/// [`Solvent::validate`] to check.
+struct ValidatingMapper<'a> {
     solvent: &'a Solvent,
-    store: &'a S,
-    visited: &'a mut HashSet<Cid>,
-    error: Option<S::Error>,
+    children: Vec<Box<dyn PendingCheck>>,
 }
 
-impl<S: Store> BondMapper for PersistingMapper<'_, S> {
+impl BondMapper for ValidatingMapper<'_> {
     fn map_bond<T: Oxide>(&mut self, bond: Bond<T>) -> Bond<T> {
-        if self.error.is_some() {
-            return bond;
+        if let Some(cell) = self.solvent.resolve(&bond).cell() {
+            self.children.push(Box::new(cell.clone()));
         }
+        bond
+    }
+}
 
-        let cid = bond.cid();
-        if self.visited.contains(&cid) {
-            return bond;
-        }
-        self.visited.insert(cid);
-
-        // Get the cell from solvent and persist it
-        if let Some(cell) = self.solvent.get::<T>(&cid) {
-            let bytes = cell.value().to_bytes();
-            if let Err(e) = self.store.put(&cid, &bytes) {
-                self.error = Some(e);
-                return bond;
-            }
+/// A bond target of any oxide type waiting to be loaded.
+trait PendingLoad {
+    fn cid(&self) -> Cid;
+    fn is_loaded(&self, solvent: &Solvent) -> bool;
+    /// Decodes the value, returning the targets of its bonds.
+    fn decode(&mut self, bytes: &[u8]) -> Result<Vec<Box<dyn PendingLoad>>, SolventError>;
+    /// Adds the decoded value to the solvent.
+    fn add(self: Box<Self>, solvent: &mut Solvent);
+}
 
-            // Recursively persist nested bonds
-            cell.value().map_bonds(self);
-        }
+struct LoadTarget<T> {
+    cid: Cid,
+    value: Option<T>,
+}
+
+impl<T: Oxide> PendingLoad for LoadTarget<T> {
+    fn cid(&self) -> Cid {
+        self.cid
+    }
+
+    fn is_loaded(&self, solvent: &Solvent) -> bool {
+        solvent.get::<T>(&self.cid).is_some()
+    }
+
+    fn decode(&mut self, bytes: &[u8]) -> Result<Vec<Box<dyn PendingLoad>>, SolventError> {
+        let value = T::from_bytes(bytes).map_err(|e| SolventError::Decode(e.to_string()))?;
+        let targets = bond_targets(&value);
+        self.value = Some(value);
+        Ok(targets)
+    }
 
+    fn add(self: Box<Self>, solvent: &mut Solvent) {
+        solvent.add(self.value.expect("decoded before added"));
+    }
+}
+
+/// The targets of the bonds of `value`, in order.
+fn bond_targets<T: Oxide>(value: &T) -> Vec<Box<dyn PendingLoad>> {
+    let mut mapper = LoadingMapper {
+        targets: Vec::new(),
+    };
+    value.map_bonds(&mut mapper);
+    mapper.targets
+}
+
+/// Bond mapper collecting bond targets, for [`Solvent::load`] to load.
+struct LoadingMapper {
+    targets: Vec<Box<dyn PendingLoad>>,
+}
+
+impl BondMapper for LoadingMapper {
+    fn map_bond<T: Oxide>(&mut self, bond: Bond<T>) -> Bond<T> {
+        self.targets.push(Box::new(LoadTarget::<T> {
+            cid: bond.cid(),
+            value: None,
+        }));
+        bond
+    }
+}
+
+/// Bond mapper collecting the bond targets found in the solvent, for
+/// [`persist_value`] to persist.
+struct PersistingMapper<'a> {
+    cells: &'a dyn CellLookup,
+    children: Vec<Box<dyn PendingValue>>,
+}
+
+impl BondMapper for PersistingMapper<'_> {
+    fn map_bond<T: Oxide>(&mut self, bond: Bond<T>) -> Bond<T> {
+        let cell = self.cells.lookup_cell(&bond.cid());
+        if let Some(cell) = cell.and_then(|cell| cell.downcast::<Cell<T>>().ok()) {
+            self.children.push(Box::new(cell));
+        }
         bond
     }
 }
@@ -385,6 +981,206 @@ mod tests {
         assert_eq!(solvent.len(), 2);
     }
 
+    #[test]
+    fn persist_rejects_invalid_values() {
+        let mut solvent = Solvent::new();
+        solvent.add_validator(|s: &String| {
+            if s.is_empty() {
+                Err("must not be empty".to_string())
+            } else {
+                Ok(())
+            }
+        });
+        let store = crate::MemoryStore::new();
+
+        let valid = solvent.add(vec![solvent.bond("ok".to_string())]);
+        assert!(solvent.persist_cell(&valid, &store).is_ok());
+
+        let empty = solvent.bond(String::new());
+        let invalid = solvent.add(vec![empty.clone()]);
+        match solvent.persist_cell(&invalid, &store) {
+            Err(PersistError::Invalid(violations)) => {
+                assert_eq!(violations.len(), 1);
+                assert_eq!(violations[0].cid, empty.cid());
+            }
+            other => panic!("Expected Invalid, got {:?}", other),
+        }
+        assert!(!store.has(&invalid.cid()).unwrap());
+    }
+
+    #[test]
+    fn persists_long_chains() {
+        // Deep enough to overflow the stack if persisting recursed per bond
+        let mut solvent = Solvent::new();
+        let mut cells = vec![solvent.add(Structure::Enum(vec!["leaf".to_string()]))];
+        for _ in 0..20_000 {
+            let previous = Bond::Unresolved(cells.last().unwrap().cid());
+            cells.push(solvent.add(Structure::Sequence(previous)));
+        }
+        let root = cells.last().unwrap();
+        let (batch, _) = collect_closure(&solvent, root);
+        let values: Vec<Cid> = batch.values().map(|(cid, _)| *cid).collect();
+        assert_eq!(values.len(), cells.len());
+        assert_eq!(values.first(), Some(&cells[0].cid()));
+        assert_eq!(values.last(), Some(&root.cid()));
+
+        // Freed from the root down, so dropping doesn't recurse along the chain
+        drop(solvent);
+        while cells.pop().is_some() {}
+    }
+
+    #[test]
+    fn validates_and_loads_long_chains() {
+        // Deep enough to overflow the stack if either recursed per bond
+        let mut solvent = Solvent::new();
+        solvent.add_validator(|s: &Structure| match s {
+            Structure::Enum(_) => Err("leaf".to_string()),
+            _ => Ok(()),
+        });
+        let store = MemoryStore::new();
+        let mut cells = vec![solvent.add(Structure::Enum(vec!["leaf".to_string()]))];
+        for _ in 0..20_000 {
+            let previous = Bond::Unresolved(cells.last().unwrap().cid());
+            cells.push(solvent.add(Structure::Sequence(previous)));
+        }
+        let cids: Vec<Cid> = cells.iter().map(|cell| cell.cid()).collect();
+        let root = *cids.last().unwrap();
+
+        let violations = solvent.validate(cells.last().unwrap().value());
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].cid, cids[0]);
+
+        solvent
+            .persist_cell(cells.last().unwrap(), &store)
+            .unwrap_err();
+        let (batch, _) = collect_closure(&solvent, cells.last().unwrap());
+        store.write_batch(&batch).unwrap();
+        drop(solvent);
+        while cells.pop().is_some() {}
+
+        let mut loaded = Solvent::new();
+        loaded
+            .load::<Structure, _>(&root, &store, usize::MAX)
+            .unwrap();
+        assert_eq!(loaded.len(), cids.len());
+        let mut cells: Vec<_> = cids
+            .iter()
+            .map(|cid| loaded.get::<Structure>(cid).unwrap())
+            .collect();
+        assert!(matches!(cells[1].value(), Structure::Sequence(leaf) if leaf.is_resolved()));
+        drop(loaded);
+        while cells.pop().is_some() {}
+    }
+
+    #[test]
+    fn persist_with_reports_progress() {
+        let mut solvent = Solvent::new();
+        let items: Vec<_> = (0..100u64).map(|i| solvent.bond(i)).collect();
+        let list = solvent.add(items);
+        let store = crate::MemoryStore::new();
+
+        let mut calls = Vec::new();
+        let persisted = solvent
+            .persist_cell_with(&list, &store, |cid, done, total| calls.push((cid, done, total)))
+            .unwrap();
+        assert_eq!(persisted.cids.last(), Some(&list.cid()));
+        assert!(persisted.cids.contains(&persisted.schema));
+        assert!(persisted.cids.iter().all(|cid| store.has(cid).unwrap()));
+        let total = persisted.cids.len();
+        assert_eq!(calls.len(), total);
+        assert_eq!(calls.last(), Some(&(list.cid(), total, total)));
+    }
+
+    #[test]
+    fn fork_isolates_and_merges() {
+        let mut solvent = Solvent::new();
+        let shared = solvent.add(Structure::sequence(Structure::Unicode));
+
+        let mut fork = solvent.fork();
+        assert!(Arc::ptr_eq(&fork.get::<Structure>(&shared.cid()).unwrap(), &shared));
+        let draft = fork.add(Structure::sequence(Structure::sequence(Structure::Unicode)));
+        assert_eq!(fork.len(), 3);
+
+        // The parent keeps evolving independently of the fork
+        let other = solvent.add("parent only".to_string());
+        assert!(!solvent.contains(&draft.cid()));
+        assert!(!fork.contains(&other.cid()));
+
+        // A discarded fork leaves no trace, a merged one adds its cells
+        solvent.fork().add(42u64);
+        assert_eq!(solvent.len(), 3);
+        fork.merge_into(&mut solvent);
+        assert_eq!(solvent.len(), 4);
+        assert!(Arc::ptr_eq(&solvent.get::<Structure>(&draft.cid()).unwrap(), &draft));
+    }
+
+    #[test]
+    fn eviction_drops_least_recently_used() {
+        let mut solvent = Solvent::new();
+        solvent.set_eviction_policy(EvictionPolicy::MaxCells(2));
+        let a = solvent.add(1u64).cid();
+        let b = solvent.add(2u64).cid();
+        // Using `a` leaves `b` as the least recently used
+        solvent.get::<u64>(&a).unwrap();
+        let c = solvent.add(3u64).cid();
+        assert!(!solvent.contains(&b));
+        assert!(solvent.contains(&a) && solvent.contains(&c));
+
+        assert!(solvent.evict(&a));
+        assert!(!solvent.evict(&a));
+        solvent.shrink_to(0);
+        assert!(solvent.is_empty());
+        assert_eq!(solvent.retained_bytes(), 0);
+    }
+
+    #[test]
+    fn eviction_frees_chains_from_the_top() {
+        let mut solvent = Solvent::new();
+        let leaf = solvent.bond(1u64);
+        let middle = solvent.bond(vec![leaf.clone()]);
+        let top = solvent.add(vec![middle.clone()]);
+        let weak = (
+            Arc::downgrade(leaf.cell().unwrap()),
+            Arc::downgrade(middle.cell().unwrap()),
+            Arc::downgrade(&top),
+        );
+        drop((leaf, middle, top));
+        let (leaf, middle, top) = weak;
+
+        // The leaf and middle are older but bonded to, so the top goes first
+        solvent.set_eviction_policy(EvictionPolicy::MaxCells(2));
+        assert!(top.upgrade().is_none());
+        assert!(middle.upgrade().is_some() && leaf.upgrade().is_some());
+        solvent.set_eviction_policy(EvictionPolicy::MaxCells(1));
+        assert!(middle.upgrade().is_none());
+
+        // Cells held outside the solvent are kept
+        let held = leaf.upgrade().unwrap();
+        solvent.shrink_to(0);
+        assert_eq!(solvent.len(), 1);
+        drop(held);
+        solvent.shrink_to(0);
+        assert!(solvent.is_empty());
+        assert!(leaf.upgrade().is_none());
+    }
+
+    #[test]
+    fn decode_checks_limits() {
+        let mut solvent = Solvent::new();
+        let bytes = vec![vec![1u64, 2]].to_bytes();
+        let cell = solvent.decode::<Vec<Vec<u64>>>(&bytes).unwrap();
+        assert!(solvent.contains(&cell.cid()));
+
+        solvent.set_decode_limits(DecodeLimits {
+            max_depth: 1,
+            ..Default::default()
+        });
+        assert!(matches!(
+            solvent.decode::<Vec<Vec<u64>>>(&bytes),
+            Err(SolventError::Rejected(LimitError::TooDeep { limit: 1 }))
+        ));
+    }
+
     #[test]
     fn solvent_deep_nesting() {
         let mut solvent = Solvent::new();
@@ -397,4 +1193,41 @@ mod tests {
         // Should have 4 entries: 3 Sequences and 1 Bool
         assert_eq!(solvent.len(), 4);
     }
+
+    fn inner(schema: &Structure) -> &Bond<Structure> {
+        match schema {
+            Structure::Sequence(inner) => inner,
+            other => panic!("Expected Sequence, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn load_resolves_bonds_up_to_depth() {
+        let deep = Structure::sequence(Structure::sequence(Structure::Bool));
+        let mut source = Solvent::new();
+        let cell = source.add(deep);
+        let store = crate::MemoryStore::new();
+        let (cid, _) = source.persist_cell(&cell, &store).unwrap();
+
+        let mut solvent = Solvent::new();
+        let loaded = solvent.load::<Structure, _>(&cid, &store, 1).unwrap();
+        let middle = inner(loaded.value());
+        assert!(middle.is_resolved());
+        assert!(!inner(middle.value().unwrap()).is_resolved());
+
+        let mut solvent = Solvent::new();
+        let loaded = solvent
+            .load_async::<Structure, _>(&cid, &store, usize::MAX)
+            .await
+            .unwrap();
+        let middle = inner(loaded.value());
+        assert!(inner(middle.value().unwrap()).is_resolved());
+        assert_eq!(solvent.len(), 3);
+
+        let missing = compute_cid(b"missing");
+        assert!(matches!(
+            Solvent::new().load::<Structure, _>(&missing, &store, 0),
+            Err(LoadError::Solvent(SolventError::NotFound(cid))) if cid == missing
+        ));
+    }
 }
@@ -1,8 +1,156 @@
 use cid::Cid;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::convert::Infallible;
+use std::io::{Cursor, Read};
 use std::sync::RwLock;
 
+use crate::gc::{reachable, GcStats};
+use crate::index::IndexStore;
+use crate::refs::RefStore;
+
+/// Kinds of data a store holds.
+///
+/// Backends may keep categories physically apart (separate keyspaces or
+/// column families); the `Store` trait itself only reads and writes values
+/// and schemas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
+    Values,
+    Schemas,
+    Refs,
+    Indexes,
+    Pins,
+}
+
+impl Category {
+    pub const ALL: [Category; 5] = [
+        Category::Values,
+        Category::Schemas,
+        Category::Refs,
+        Category::Indexes,
+        Category::Pins,
+    ];
+
+    /// Name used for the backend keyspace or column family.
+    pub fn name(self) -> &'static str {
+        match self {
+            Category::Values => "values",
+            Category::Schemas => "schemas",
+            Category::Refs => "refs",
+            Category::Indexes => "indexes",
+            Category::Pins => "pins",
+        }
+    }
+
+    /// Position in `Category::ALL`.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Size of one category in a store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CategoryStats {
+    pub category: Category,
+    pub entries: usize,
+    pub bytes: u64,
+}
+
+impl CategoryStats {
+    pub fn new(category: Category) -> Self {
+        Self {
+            category,
+            entries: 0,
+            bytes: 0,
+        }
+    }
+}
+
+/// Values and schemas written together by [`Store::write_batch`].
+#[derive(Debug, Clone, Default)]
+pub struct Batch {
+    schemas: Vec<(Cid, Vec<u8>)>,
+    values: Vec<(Cid, Vec<u8>)>,
+}
+
+impl Batch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn put(&mut self, cid: Cid, value: Vec<u8>) {
+        self.values.push((cid, value));
+    }
+
+    pub fn put_schema(&mut self, cid: Cid, value: Vec<u8>) {
+        self.schemas.push((cid, value));
+    }
+
+    /// Values in the order they were added.
+    pub fn values(&self) -> impl Iterator<Item = (&Cid, &[u8])> {
+        self.values.iter().map(|(cid, value)| (cid, value.as_slice()))
+    }
+
+    pub fn schemas(&self) -> impl Iterator<Item = (&Cid, &[u8])> {
+        self.schemas.iter().map(|(cid, value)| (cid, value.as_slice()))
+    }
+
+    /// CIDs of the schemas, then the values.
+    pub fn cids(&self) -> impl Iterator<Item = &Cid> {
+        self.schemas.iter().chain(&self.values).map(|(cid, _)| cid)
+    }
+
+    pub fn len(&self) -> usize {
+        self.schemas.len() + self.values.len()
+    }
+
+    /// Splits into batches of at most `size` writes, keeping the schemas
+    /// first and the values in order.
+    pub fn split(self, size: usize) -> Vec<Batch> {
+        let size = size.max(1);
+        let schemas = self.schemas.into_iter().map(|write| (true, write));
+        let values = self.values.into_iter().map(|write| (false, write));
+        let mut batches: Vec<Batch> = Vec::new();
+        for (i, (schema, (cid, value))) in schemas.chain(values).enumerate() {
+            if i % size == 0 {
+                batches.push(Batch::new());
+            }
+            let batch = batches.last_mut().expect("pushed above");
+            if schema {
+                batch.put_schema(cid, value);
+            } else {
+                batch.put(cid, value);
+            }
+        }
+        batches
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Writes buffered until [`commit`](Self::commit), then applied as one
+/// batch. Dropping a transaction discards its writes.
+pub struct Transaction<'a, S: Store> {
+    store: &'a S,
+    batch: Batch,
+}
+
+impl<S: Store> Transaction<'_, S> {
+    pub fn put(&mut self, cid: &Cid, value: &[u8]) {
+        self.batch.put(*cid, value.to_vec());
+    }
+
+    pub fn put_schema(&mut self, cid: &Cid, value: &[u8]) {
+        self.batch.put_schema(*cid, value.to_vec());
+    }
+
+    pub fn commit(self) -> Result<(), S::Error> {
+        self.store.write_batch(&self.batch)
+    }
+}
+
 /// A simple CID-keyed store for oxide bytes.
 ///
 /// Stores operate on raw bytes — serialization/deserialization is handled
@@ -16,11 +164,108 @@ pub trait Store {
     /// Retrieves the bytes associated with a CID, or None if not present.
     fn get(&self, cid: &Cid) -> Result<Option<Vec<u8>>, Self::Error>;
 
+    /// Opens the bytes of a CID for reading.
+    ///
+    /// The default copies the value out with `get`; backends that can read
+    /// from their own buffers override it.
+    fn get_reader(&self, cid: &Cid) -> Result<Option<Box<dyn Read + '_>>, Self::Error> {
+        let Some(bytes) = self.get(cid)? else {
+            return Ok(None);
+        };
+        Ok(Some(Box::new(Cursor::new(bytes))))
+    }
+
     /// Stores bytes at the given CID.
     fn put(&self, cid: &Cid, value: &[u8]) -> Result<(), Self::Error>;
 
+    /// Stores a schema node.
+    ///
+    /// Backends that keep schemas apart from values override this; `get` and
+    /// `has` must find schemas either way.
+    fn put_schema(&self, cid: &Cid, value: &[u8]) -> Result<(), Self::Error> {
+        self.put(cid, value)
+    }
+
     /// Checks whether a CID exists in the store.
     fn has(&self, cid: &Cid) -> Result<bool, Self::Error>;
+
+    /// Retrieves several values, in the order of `cids`.
+    ///
+    /// The default calls `get` for each CID; backends with batched reads
+    /// override it.
+    fn get_many(&self, cids: &[Cid]) -> Result<Vec<Option<Vec<u8>>>, Self::Error> {
+        cids.iter().map(|cid| self.get(cid)).collect()
+    }
+
+    /// Stores several values, in order.
+    ///
+    /// The default calls `put` for each node; backends with write batches
+    /// override it.
+    fn put_many(&self, nodes: &[(&Cid, &[u8])]) -> Result<(), Self::Error> {
+        nodes.iter().try_for_each(|(cid, value)| self.put(cid, value))
+    }
+
+    /// Writes a batch of schemas and values.
+    ///
+    /// Backends with atomic write batches override it, so that after a crash
+    /// either every write of the batch is visible or none is. The default
+    /// writes the schemas, then the values in order.
+    fn write_batch(&self, batch: &Batch) -> Result<(), Self::Error> {
+        for (cid, value) in batch.schemas() {
+            self.put_schema(cid, value)?;
+        }
+        self.put_many(&batch.values().collect::<Vec<_>>())
+    }
+
+    /// Starts buffering writes to apply together with
+    /// [`write_batch`](Self::write_batch).
+    fn transaction(&self) -> Transaction<'_, Self>
+    where
+        Self: Sized,
+    {
+        Transaction {
+            store: self,
+            batch: Batch::new(),
+        }
+    }
+
+    /// Removes a value or schema. Deleting a missing CID is not an error.
+    ///
+    /// Nothing checks that other values no longer bond to it; use `gc` to
+    /// remove only unreachable data.
+    fn delete(&self, cid: &Cid) -> Result<(), Self::Error>;
+
+    /// Removes several values. The default calls `delete` for each CID.
+    fn delete_many(&self, cids: &[Cid]) -> Result<(), Self::Error> {
+        cids.iter().try_for_each(|cid| self.delete(cid))
+    }
+
+    /// Iterates over the CIDs of every value and schema in the store.
+    ///
+    /// The order is backend-specific. Writes made during iteration may or
+    /// may not be observed.
+    fn iter(&self) -> Box<dyn Iterator<Item = Result<Cid, Self::Error>> + '_>;
+
+    /// Collects the CIDs of everything in the store.
+    fn list_cids(&self) -> Result<Vec<Cid>, Self::Error> {
+        self.iter().collect()
+    }
+
+    /// Deletes every value not reachable from `roots`.
+    ///
+    /// Schemas written with [`put_schema`](Self::put_schema) are never
+    /// deleted: values don't link to their schemas, so they can't be found
+    /// from the roots. Values written during collection that aren't reachable
+    /// from the roots may be deleted.
+    fn gc(&self, roots: &[Cid]) -> Result<GcStats, Self::Error>;
+
+    /// Asks the backend to reclaim the space of deleted data.
+    ///
+    /// On-disk backends may keep deleted entries around until compaction;
+    /// stores without such a step do nothing.
+    fn compact(&self) -> Result<(), Self::Error> {
+        Ok(())
+    }
 }
 
 impl<S: Store> Store for &S {
@@ -30,13 +275,53 @@ impl<S: Store> Store for &S {
         (*self).get(cid)
     }
 
+    fn get_reader(&self, cid: &Cid) -> Result<Option<Box<dyn Read + '_>>, Self::Error> {
+        (*self).get_reader(cid)
+    }
+
     fn put(&self, cid: &Cid, value: &[u8]) -> Result<(), Self::Error> {
         (*self).put(cid, value)
     }
 
+    fn put_schema(&self, cid: &Cid, value: &[u8]) -> Result<(), Self::Error> {
+        (*self).put_schema(cid, value)
+    }
+
     fn has(&self, cid: &Cid) -> Result<bool, Self::Error> {
         (*self).has(cid)
     }
+
+    fn get_many(&self, cids: &[Cid]) -> Result<Vec<Option<Vec<u8>>>, Self::Error> {
+        (*self).get_many(cids)
+    }
+
+    fn put_many(&self, nodes: &[(&Cid, &[u8])]) -> Result<(), Self::Error> {
+        (*self).put_many(nodes)
+    }
+
+    fn write_batch(&self, batch: &Batch) -> Result<(), Self::Error> {
+        (*self).write_batch(batch)
+    }
+
+    fn delete(&self, cid: &Cid) -> Result<(), Self::Error> {
+        (*self).delete(cid)
+    }
+
+    fn delete_many(&self, cids: &[Cid]) -> Result<(), Self::Error> {
+        (*self).delete_many(cids)
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = Result<Cid, Self::Error>> + '_> {
+        (*self).iter()
+    }
+
+    fn gc(&self, roots: &[Cid]) -> Result<GcStats, Self::Error> {
+        (*self).gc(roots)
+    }
+
+    fn compact(&self) -> Result<(), Self::Error> {
+        (*self).compact()
+    }
 }
 
 /// An in-memory store backed by a HashMap.
@@ -45,6 +330,10 @@ impl<S: Store> Store for &S {
 #[derive(Debug, Default)]
 pub struct MemoryStore {
     data: RwLock<HashMap<Cid, Vec<u8>>>,
+    /// Entries of `data` written as schemas, which `gc` keeps.
+    schemas: RwLock<HashSet<Cid>>,
+    refs: RwLock<BTreeMap<String, Cid>>,
+    index_entries: RwLock<BTreeMap<String, Cid>>,
 }
 
 impl MemoryStore {
@@ -65,15 +354,149 @@ impl Store for MemoryStore {
         Ok(())
     }
 
+    fn put_schema(&self, cid: &Cid, value: &[u8]) -> Result<(), Self::Error> {
+        self.data.write().unwrap().insert(*cid, value.to_vec());
+        self.schemas.write().unwrap().insert(*cid);
+        Ok(())
+    }
+
     fn has(&self, cid: &Cid) -> Result<bool, Self::Error> {
         Ok(self.data.read().unwrap().contains_key(cid))
     }
+
+    fn get_many(&self, cids: &[Cid]) -> Result<Vec<Option<Vec<u8>>>, Self::Error> {
+        let data = self.data.read().unwrap();
+        Ok(cids.iter().map(|cid| data.get(cid).cloned()).collect())
+    }
+
+    fn put_many(&self, nodes: &[(&Cid, &[u8])]) -> Result<(), Self::Error> {
+        let mut data = self.data.write().unwrap();
+        for (cid, value) in nodes {
+            data.insert(**cid, value.to_vec());
+        }
+        Ok(())
+    }
+
+    fn write_batch(&self, batch: &Batch) -> Result<(), Self::Error> {
+        let mut data = self.data.write().unwrap();
+        let mut schemas = self.schemas.write().unwrap();
+        for (cid, value) in batch.schemas() {
+            data.insert(*cid, value.to_vec());
+            schemas.insert(*cid);
+        }
+        for (cid, value) in batch.values() {
+            data.insert(*cid, value.to_vec());
+        }
+        Ok(())
+    }
+
+    fn delete(&self, cid: &Cid) -> Result<(), Self::Error> {
+        self.delete_many(std::slice::from_ref(cid))
+    }
+
+    fn delete_many(&self, cids: &[Cid]) -> Result<(), Self::Error> {
+        let mut data = self.data.write().unwrap();
+        let mut schemas = self.schemas.write().unwrap();
+        for cid in cids {
+            data.remove(cid);
+            schemas.remove(cid);
+        }
+        Ok(())
+    }
+
+    /// Iterates over a snapshot of the keys taken when called.
+    fn iter(&self) -> Box<dyn Iterator<Item = Result<Cid, Self::Error>> + '_> {
+        let cids: Vec<Cid> = self.data.read().unwrap().keys().copied().collect();
+        Box::new(cids.into_iter().map(Ok))
+    }
+
+    fn gc(&self, roots: &[Cid]) -> Result<GcStats, Self::Error> {
+        let keep = reachable(self, roots)?;
+        let mut stats = GcStats {
+            retained: keep.len(),
+            ..Default::default()
+        };
+        let schemas = self.schemas.read().unwrap();
+        self.data.write().unwrap().retain(|cid, value| {
+            if keep.contains(cid) || schemas.contains(cid) {
+                return true;
+            }
+            stats.freed += 1;
+            stats.freed_bytes += value.len() as u64;
+            false
+        });
+        Ok(stats)
+    }
+}
+
+impl RefStore for MemoryStore {
+    fn get_ref(&self, name: &str) -> Result<Option<Cid>, Self::Error> {
+        Ok(self.refs.read().unwrap().get(name).copied())
+    }
+
+    fn set_ref(&self, name: &str, cid: &Cid) -> Result<(), Self::Error> {
+        self.refs.write().unwrap().insert(name.to_string(), *cid);
+        Ok(())
+    }
+
+    fn delete_ref(&self, name: &str) -> Result<(), Self::Error> {
+        self.refs.write().unwrap().remove(name);
+        Ok(())
+    }
+
+    fn list_refs(&self, prefix: &str) -> Result<Vec<(String, Cid)>, Self::Error> {
+        let refs = self.refs.read().unwrap();
+        Ok(refs
+            .range(prefix.to_string()..)
+            .take_while(|(name, _)| name.starts_with(prefix))
+            .map(|(name, cid)| (name.clone(), *cid))
+            .collect())
+    }
+
+    fn compare_and_set_ref(
+        &self,
+        name: &str,
+        expected: Option<&Cid>,
+        new: &Cid,
+    ) -> Result<bool, Self::Error> {
+        let mut refs = self.refs.write().unwrap();
+        if refs.get(name) != expected {
+            return Ok(false);
+        }
+        refs.insert(name.to_string(), *new);
+        Ok(true)
+    }
+}
+
+impl IndexStore for MemoryStore {
+    fn set_index_entry(&self, name: &str, cid: &Cid) -> Result<(), Self::Error> {
+        self.index_entries
+            .write()
+            .unwrap()
+            .insert(name.to_string(), *cid);
+        Ok(())
+    }
+
+    fn delete_index_entry(&self, name: &str) -> Result<(), Self::Error> {
+        self.index_entries.write().unwrap().remove(name);
+        Ok(())
+    }
+
+    fn list_index_entries(&self, prefix: &str) -> Result<Vec<(String, Cid)>, Self::Error> {
+        let entries = self.index_entries.read().unwrap();
+        Ok(entries
+            .range(prefix.to_string()..)
+            .take_while(|(name, _)| name.starts_with(prefix))
+            .map(|(name, cid)| (name.clone(), *cid))
+            .collect())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::oxide::compute_cid;
+    use crate::solvent::Solvent;
 
     #[test]
     fn memory_store_put_get() {
@@ -97,6 +520,18 @@ mod tests {
         assert_eq!(retrieved, None);
     }
 
+    #[test]
+    fn memory_store_get_reader() {
+        let store = MemoryStore::new();
+        let cid = compute_cid(b"test");
+        store.put(&cid, b"hello").unwrap();
+
+        let mut read = Vec::new();
+        store.get_reader(&cid).unwrap().unwrap().read_to_end(&mut read).unwrap();
+        assert_eq!(read, b"hello");
+        assert!(store.get_reader(&compute_cid(b"missing")).unwrap().is_none());
+    }
+
     #[test]
     fn memory_store_has() {
         let store = MemoryStore::new();
@@ -109,6 +544,33 @@ mod tests {
         assert!(store.has(&cid).unwrap());
     }
 
+    #[test]
+    fn memory_store_delete() {
+        let store = MemoryStore::new();
+        let cid = compute_cid(b"test");
+        store.put(&cid, b"value").unwrap();
+
+        store.delete(&cid).unwrap();
+        store.delete(&cid).unwrap();
+
+        assert!(!store.has(&cid).unwrap());
+    }
+
+    #[test]
+    fn memory_store_list_cids() {
+        let store = MemoryStore::new();
+        let cids: Vec<Cid> = (0..3u8).map(|i| compute_cid(&[i])).collect();
+        for cid in &cids {
+            store.put(cid, b"value").unwrap();
+        }
+
+        let mut listed = store.list_cids().unwrap();
+        listed.sort();
+        let mut expected = cids.clone();
+        expected.sort();
+        assert_eq!(listed, expected);
+    }
+
     #[test]
     fn memory_store_overwrite() {
         let store = MemoryStore::new();
@@ -120,4 +582,56 @@ mod tests {
         let retrieved = store.get(&cid).unwrap();
         assert_eq!(retrieved, Some(b"second".to_vec()));
     }
+
+    #[test]
+    fn memory_store_refs() {
+        let store = MemoryStore::new();
+        let first = compute_cid(b"first");
+        let second = compute_cid(b"second");
+
+        assert_eq!(store.get_ref("inventory/head").unwrap(), None);
+        store.set_ref("inventory/head", &first).unwrap();
+        store.set_ref("chat/work", &second).unwrap();
+        store.set_ref("inventory/draft", &second).unwrap();
+        assert_eq!(store.get_ref("inventory/head").unwrap(), Some(first));
+
+        let names: Vec<String> = store
+            .list_refs("inventory/")
+            .unwrap()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, ["inventory/draft", "inventory/head"]);
+
+        assert!(!store
+            .compare_and_set_ref("inventory/head", Some(&second), &second)
+            .unwrap());
+        assert!(store
+            .compare_and_set_ref("inventory/head", Some(&first), &second)
+            .unwrap());
+        assert!(store.compare_and_set_ref("new", None, &first).unwrap());
+        assert!(!store.compare_and_set_ref("new", None, &second).unwrap());
+
+        store.delete_ref("inventory/head").unwrap();
+        assert_eq!(store.get_ref("inventory/head").unwrap(), None);
+        assert_eq!(store.list_refs("").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn memory_store_gc_keeps_schemas() {
+        let store = MemoryStore::new();
+        let mut solvent = Solvent::new();
+        let kept = solvent.add(vec![solvent.bond("kept".to_string())]);
+        let dropped = solvent.add("dropped".to_string());
+        let (cid, schema_cid) = solvent.persist_cell(&kept, &store).unwrap();
+        let (dropped_cid, dropped_schema) = solvent.persist_cell(&dropped, &store).unwrap();
+
+        let stats = store.gc(&[cid]).unwrap();
+
+        assert_eq!(stats.freed, 1);
+        assert!(store.has(&kept.value()[0].cid()).unwrap());
+        assert!(!store.has(&dropped_cid).unwrap());
+        assert!(store.has(&schema_cid).unwrap());
+        assert!(store.has(&dropped_schema).unwrap());
+    }
 }
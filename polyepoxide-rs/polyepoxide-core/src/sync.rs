@@ -6,10 +6,18 @@
 //! source, checked against dest, stored if missing, then traversed for bonds.
 
 use cid::Cid;
+use futures::future::try_join_all;
+use indexmap::IndexSet;
+use ipld_core::ipld::Ipld;
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use crate::traverse::collect_bonds;
-use crate::{AsyncStore, Cell, Solvent, Structure};
+use crate::limits::{DecodeLimits, LimitError};
+use crate::oxide::compute_cid;
+use crate::traverse::{collect_bonds, Path};
+use crate::{AsyncStore, Batch, Cell, MemoryStore, Oxide, Solvent, Store, Structure, Tombstones};
 
 /// Error during sync operations.
 #[derive(Debug, thiserror::Error)]
@@ -18,12 +26,186 @@ pub enum SyncError<S, D> {
     NotFound(Cid),
     #[error("invalid format: {0}")]
     Format(String),
+    #[error("content does not match CID {0}")]
+    Corrupted(Cid),
+    #[error("block {0} rejected: {1}")]
+    Rejected(Cid, LimitError),
     #[error("source store error: {0}")]
     Source(S),
     #[error("destination store error: {0}")]
     Dest(D),
 }
 
+/// Number of fetched nodes buffered before they are written to dest.
+const WRITE_BATCH_SIZE: usize = 256;
+
+/// Options for [`pull_with_options`].
+#[derive(Debug, Clone, Copy)]
+pub struct PullOptions {
+    /// Number of sibling nodes whose bonds are fetched concurrently.
+    pub max_in_flight: usize,
+    /// Limits every fetched block must satisfy before it is decoded.
+    pub limits: DecodeLimits,
+    /// Stops the pull once this many values have been written, leaving the
+    /// rest in the report's frontier for [`resume_pull`].
+    pub max_nodes: Option<usize>,
+}
+
+impl Default for PullOptions {
+    fn default() -> Self {
+        Self {
+            max_in_flight: 1,
+            limits: DecodeLimits::default(),
+            max_nodes: None,
+        }
+    }
+}
+
+/// What a pull transferred.
+#[derive(Debug, Clone, Default)]
+pub struct SyncReport {
+    /// CIDs written to dest, schemas included, in the order written.
+    pub transferred: Vec<Cid>,
+    /// Values found in dest already, whose subgraphs weren't walked.
+    pub skipped: usize,
+    /// Bytes written to dest.
+    pub bytes: u64,
+    pub duration: Duration,
+    /// `(value, schema)` of the values still missing for the graph to be
+    /// complete: first those fetched but not yet written when the pull
+    /// stopped at `max_nodes`, deepest first, then those a `Selector` left
+    /// out. Empty if it completed.
+    pub frontier: Vec<(Cid, Cid)>,
+}
+
+impl SyncReport {
+    pub fn is_complete(&self) -> bool {
+        self.frontier.is_empty()
+    }
+}
+
+/// Predicate over the `(value, schema)` of a bond, for [`Selector::stop_at`].
+type BondPredicate = Arc<dyn Fn(&Cid, &Structure) -> bool + Send + Sync>;
+
+/// Which bonds [`pull_partial`] follows. The default follows all of them.
+#[derive(Clone, Default)]
+pub struct Selector {
+    max_depth: Option<usize>,
+    skipped_schemas: HashSet<Cid>,
+    stop_at: Option<BondPredicate>,
+    path: Option<Path>,
+    deleted: Tombstones,
+}
+
+impl Selector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Follows bonds at most `depth` levels from the root; 0 pulls the root
+    /// alone.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Leaves out values of type `T`, such as the `ByteString` chunks of a
+    /// `Blob`.
+    pub fn skip_type<T: Oxide>(self) -> Self {
+        self.skip_schema(T::schema().compute_cid())
+    }
+
+    /// Leaves out values whose schema has this CID.
+    pub fn skip_schema(mut self, schema_cid: Cid) -> Self {
+        self.skipped_schemas.insert(schema_cid);
+        self
+    }
+
+    /// Leaves out the values for which `predicate`, given their CID and
+    /// schema, returns true.
+    pub fn stop_at(
+        mut self,
+        predicate: impl Fn(&Cid, &Structure) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.stop_at = Some(Arc::new(predicate));
+        self
+    }
+
+    /// Leaves out the values `tombstones` deleted, so that a pull from a
+    /// peer that still bonds them doesn't bring them back.
+    pub fn skip_deleted(mut self, tombstones: Tombstones) -> Self {
+        self.deleted = tombstones;
+        self
+    }
+
+    /// Follows only the bonds `path` crosses, and every bond below the
+    /// values it selects. The path must be compiled against the schema of
+    /// the root.
+    pub fn along(mut self, path: Path) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    /// Removes and returns the bonds of `value` that the path doesn't
+    /// cross from `bonds`. `step` is how far along the path `value` was
+    /// reached, or `None` if it's below a selected value; the steps to the
+    /// bonds kept are recorded in `steps`.
+    fn off_path(
+        &self,
+        bonds: &mut Vec<(Cid, Cid)>,
+        value: &Ipld,
+        step: Option<usize>,
+        steps: &mut HashMap<Cid, usize>,
+    ) -> Vec<(Cid, Cid)> {
+        let (Some(path), Some(step)) = (&self.path, step) else {
+            return Vec::new();
+        };
+        let mut on_path = HashSet::new();
+        for (cid, next) in path.links_from(value, step) {
+            on_path.insert(cid);
+            match next {
+                Some(next) => steps.insert(cid, next),
+                None => steps.remove(&cid),
+            };
+        }
+        let (kept, left_out): (Vec<_>, Vec<_>) =
+            bonds.drain(..).partition(|(cid, _)| on_path.contains(cid));
+        *bonds = kept;
+        left_out
+    }
+
+    /// Removes and returns the bonds not followed from `bonds`, the bonds
+    /// of a value `depth - 1` levels from the root.
+    fn leave_out(
+        &self,
+        bonds: &mut Vec<(Cid, Cid)>,
+        depth: usize,
+        schemas: &Solvent,
+    ) -> Vec<(Cid, Cid)> {
+        let (kept, left_out): (Vec<_>, Vec<_>) = bonds
+            .drain(..)
+            .partition(|(value, schema)| self.follows(value, schema, depth, schemas));
+        *bonds = kept;
+        left_out
+    }
+
+    fn follows(&self, value: &Cid, schema: &Cid, depth: usize, schemas: &Solvent) -> bool {
+        if self.max_depth.is_some_and(|max| depth > max)
+            || self.skipped_schemas.contains(schema)
+            || self.deleted.is_deleted(value)
+        {
+            return false;
+        }
+        let Some(stop_at) = &self.stop_at else {
+            return true;
+        };
+        // Bond schemas are loaded along with the schema bonding to them
+        schemas
+            .get::<Structure>(schema)
+            .is_none_or(|structure| !stop_at(value, structure.value()))
+    }
+}
+
 /// Pull a value and all its dependencies from source to destination.
 ///
 /// Uses dependency-first order: children are stored before parents.
@@ -31,6 +213,14 @@ pub enum SyncError<S, D> {
 /// dependencies are already present. This allows using `dest.has()` to
 /// skip already-synced subgraphs without separate visited tracking.
 ///
+/// The graph is walked depth-first with an explicit stack rather than
+/// recursion, so long bond chains (e.g. conversations of many messages)
+/// cost one heap frame per level instead of a nested future on the call
+/// stack. The bonds of each node are checked and fetched in one batch, and
+/// writes are buffered and flushed with `async_write_batch` in
+/// dependency-first order, so the invariant holds at every flush, even if
+/// the pull is interrupted.
+///
 /// # Arguments
 /// * `source` - The store to pull from
 /// * `dest` - The store to pull into
@@ -38,97 +228,422 @@ pub enum SyncError<S, D> {
 /// * `schema_cid` - CID of the root value's schema
 ///
 /// # Returns
-/// The set of CIDs that were transferred
+/// A report of what was transferred
 pub async fn pull<S, D>(
     source: &S,
     dest: &D,
     value_cid: Cid,
     schema_cid: Cid,
-) -> Result<Vec<Cid>, SyncError<S::Error, D::Error>>
+) -> Result<SyncReport, SyncError<S::Error, D::Error>>
 where
     S: AsyncStore,
     D: AsyncStore,
 {
-    let mut transferred = Vec::new();
-    let mut schemas = Solvent::new();
+    pull_with_options(source, dest, value_cid, schema_cid, &PullOptions::default()).await
+}
 
-    pull_recursive(
-        source,
-        dest,
-        value_cid,
-        schema_cid,
-        &mut schemas,
-        &mut transferred,
-    )
-    .await?;
-
-    Ok(transferred)
+/// Like [`pull`], but fetches the bonds of up to `options.max_in_flight`
+/// sibling nodes concurrently, which matters for high-latency sources.
+///
+/// Siblings are still written in order, each after its own subgraph, so the
+/// dependency-first invariant holds. Subgraphs shared between siblings
+/// fetched together may be fetched twice, but are written once.
+///
+/// With `options.max_nodes` set, the pull may stop early with a frontier in
+/// its report, to be continued by [`resume_pull`].
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(root = %value_cid))
+)]
+pub async fn pull_with_options<S, D>(
+    source: &S,
+    dest: &D,
+    value_cid: Cid,
+    schema_cid: Cid,
+    options: &PullOptions,
+) -> Result<SyncReport, SyncError<S::Error, D::Error>>
+where
+    S: AsyncStore,
+    D: AsyncStore,
+{
+    let roots = [(value_cid, schema_cid)];
+    pull_from(source, dest, &roots, options, &Selector::default()).await
 }
 
-/// Recursive helper for pull - processes dependencies before storing current value.
-async fn pull_recursive<S, D>(
+/// Like [`pull`], but only follows the bonds `selector` selects, such as
+/// conversation metadata without the file blobs it embeds.
+///
+/// Values are written without the subgraphs left out, so the invariant
+/// [`pull`] relies on doesn't hold for them: a later `pull` of the same
+/// root stops at them. To complete the graph, pass the report's frontier
+/// to [`resume_pull`].
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(root = %value_cid))
+)]
+pub async fn pull_partial<S, D>(
     source: &S,
     dest: &D,
     value_cid: Cid,
     schema_cid: Cid,
-    schemas: &mut Solvent,
-    transferred: &mut Vec<Cid>,
+    selector: &Selector,
+) -> Result<SyncReport, SyncError<S::Error, D::Error>>
+where
+    S: AsyncStore,
+    D: AsyncStore,
+{
+    let roots = [(value_cid, schema_cid)];
+    pull_from(source, dest, &roots, &PullOptions::default(), selector).await
+}
+
+/// Continues a pull stopped at `max_nodes` from the frontier of its report.
+///
+/// The values written before aren't fetched or checked again, beyond the
+/// frontier's own bonds. The returned report covers this call only.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(frontier = frontier.len()))
+)]
+pub async fn resume_pull<S, D>(
+    source: &S,
+    dest: &D,
+    frontier: &[(Cid, Cid)],
+    options: &PullOptions,
+) -> Result<SyncReport, SyncError<S::Error, D::Error>>
+where
+    S: AsyncStore,
+    D: AsyncStore,
+{
+    pull_from(source, dest, frontier, options, &Selector::default()).await
+}
+
+async fn pull_from<S, D>(
+    source: &S,
+    dest: &D,
+    roots: &[(Cid, Cid)],
+    options: &PullOptions,
+    selector: &Selector,
+) -> Result<SyncReport, SyncError<S::Error, D::Error>>
+where
+    S: AsyncStore,
+    D: AsyncStore,
+{
+    let start = Instant::now();
+    let mut report = SyncReport::default();
+    match pull_nodes(source, dest, roots, options, selector, &mut report).await {
+        Ok(()) => {
+            report.duration = start.elapsed();
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                nodes = report.transferred.len(),
+                skipped = report.skipped,
+                bytes = report.bytes,
+                duration = ?report.duration,
+                complete = report.is_complete(),
+                "pull finished"
+            );
+            #[cfg(feature = "metrics")]
+            crate::metered::pull_finished(report.transferred.len(), report.bytes, report.duration);
+            Ok(report)
+        }
+        Err(e) => {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(error = %e, "pull failed");
+            #[cfg(feature = "metrics")]
+            crate::metered::pull_failed();
+            Err(e)
+        }
+    }
+}
+
+/// Pulls each of `roots` in turn, sharing the schemas and pending writes.
+async fn pull_nodes<S, D>(
+    source: &S,
+    dest: &D,
+    roots: &[(Cid, Cid)],
+    options: &PullOptions,
+    selector: &Selector,
+    report: &mut SyncReport,
 ) -> Result<(), SyncError<S::Error, D::Error>>
 where
     S: AsyncStore,
     D: AsyncStore,
 {
-    // If dest already has this CID, all dependencies are present (invariant)
-    if dest.async_has(&value_cid).await.map_err(SyncError::Dest)? {
-        return Ok(());
+    let max_in_flight = options.max_in_flight.max(1);
+    let limits = options.limits;
+    let mut schemas = Solvent::new();
+    schemas.set_decode_limits(limits);
+    let mut pending = PendingWrites::default();
+    let mut left_out = IndexSet::new();
+    // How far along the selector's path each value on it was reached
+    let mut steps = HashMap::new();
+
+    for (i, &(value_cid, schema_cid)) in roots.iter().enumerate() {
+        if pending.queued.contains(&value_cid) {
+            continue;
+        }
+        // If dest already has this CID, all dependencies are present (invariant)
+        if dest.async_has(&value_cid).await.map_err(SyncError::Dest)? {
+            report.skipped += 1;
+            continue;
+        }
+
+        let value_bytes = source
+            .async_get(&value_cid)
+            .await
+            .map_err(SyncError::Source)?
+            .ok_or(SyncError::NotFound(value_cid))?;
+        verify(value_cid, &value_bytes, &limits)?;
+        if selector.path.is_some() {
+            steps.insert(value_cid, 0);
+        }
+        let (value, mut bonds) = bonds_to_pull(
+            source,
+            dest,
+            &value_bytes,
+            schema_cid,
+            &mut schemas,
+            &pending,
+            report,
+        )
+        .await?;
+        let step = steps.get(&value_cid).copied();
+        left_out.extend(selector.off_path(&mut bonds, &value, step, &mut steps));
+        left_out.extend(selector.leave_out(&mut bonds, 1, &schemas));
+        let children = fetch_children(source, dest, &bonds, &limits).await?;
+        report.skipped += bonds.len() - children.len();
+        let mut stack = vec![Frame {
+            cid: value_cid,
+            schema: schema_cid,
+            depth: 0,
+            bytes: value_bytes,
+            children,
+        }];
+        loop {
+            if options
+                .max_nodes
+                .is_some_and(|max| pending.queued.len() >= max)
+            {
+                // Frames left on the stack are fetched but not yet queued
+                pending.flush(dest, report).await?;
+                report.frontier = stack.iter().rev().map(|f| (f.cid, f.schema)).collect();
+                report.frontier.extend_from_slice(&roots[i + 1..]);
+                report.frontier.extend(left_out);
+                return Ok(());
+            }
+            let Some(frame) = stack.last_mut() else {
+                break;
+            };
+            let depth = frame.depth + 1;
+            let mut batch = Vec::new();
+            while batch.len() < max_in_flight {
+                match frame.children.pop() {
+                    // An earlier sibling's subgraph may have queued it since
+                    Some((cid, _, _)) if pending.queued.contains(&cid) => {}
+                    Some(child) => batch.push(child),
+                    None => break,
+                }
+            }
+
+            if batch.is_empty() {
+                // All dependencies are queued, so the node itself can be too,
+                // unless a sibling fetched in the same batch already queued it
+                let frame = stack.pop().expect("stack is non-empty");
+                if !pending.queued.contains(&frame.cid) {
+                    pending.push(dest, frame.cid, frame.bytes, report).await?;
+                }
+                continue;
+            }
+
+            // Schemas are resolved one node at a time, as they share the solvent
+            let mut expanded = Vec::with_capacity(batch.len());
+            for (cid, schema_cid, bytes) in batch {
+                let (value, mut bonds) = bonds_to_pull(
+                    source,
+                    dest,
+                    &bytes,
+                    schema_cid,
+                    &mut schemas,
+                    &pending,
+                    report,
+                )
+                .await?;
+                let step = steps.get(&cid).copied();
+                left_out.extend(selector.off_path(&mut bonds, &value, step, &mut steps));
+                left_out.extend(selector.leave_out(&mut bonds, depth + 1, &schemas));
+                expanded.push((cid, schema_cid, bytes, bonds));
+            }
+            let fetches = expanded
+                .iter()
+                .map(|(_, _, _, bonds)| fetch_children(source, dest, bonds, &limits));
+            let children = try_join_all(fetches).await?;
+
+            // Pushed in reverse, so the first sibling's subgraph is pulled first
+            for ((cid, schema, bytes, bonds), children) in expanded.into_iter().zip(children).rev()
+            {
+                report.skipped += bonds.len() - children.len();
+                stack.push(Frame {
+                    cid,
+                    schema,
+                    depth,
+                    bytes,
+                    children,
+                });
+            }
+        }
     }
+    pending.flush(dest, report).await?;
+    report.frontier.extend(left_out);
+    Ok(())
+}
 
-    // Ensure schema is available
-    let schema_cell = ensure_schema(source, dest, schema_cid, schemas, transferred).await?;
+/// A node on the traversal stack, queued for writing once all of its
+/// children have been.
+struct Frame {
+    cid: Cid,
+    schema: Cid,
+    /// Bonds followed from the root to reach this node.
+    depth: usize,
+    bytes: Vec<u8>,
+    /// Fetched and verified `(value, schema, bytes)` of bonds still to be
+    /// descended into, in reverse order so that `pop` yields them in order.
+    children: Vec<(Cid, Cid, Vec<u8>)>,
+}
 
-    // Fetch value from source
-    let value_bytes = source
-        .async_get(&value_cid)
-        .await
-        .map_err(SyncError::Source)?
-        .ok_or(SyncError::NotFound(value_cid))?;
+/// Fetched nodes waiting to be written, in dependency-first order.
+#[derive(Default)]
+struct PendingWrites {
+    batch: Batch,
+    /// Every node queued during this pull, flushed or not. Unflushed nodes
+    /// aren't visible to `dest.has()`, so this keeps shared subgraphs from
+    /// being fetched twice.
+    queued: HashSet<Cid>,
+}
+
+impl PendingWrites {
+    async fn push<S, D: AsyncStore>(
+        &mut self,
+        dest: &D,
+        cid: Cid,
+        bytes: Vec<u8>,
+        report: &mut SyncReport,
+    ) -> Result<(), SyncError<S, D::Error>> {
+        self.queued.insert(cid);
+        self.batch.put(cid, bytes);
+        if self.batch.len() >= WRITE_BATCH_SIZE {
+            self.flush(dest, report).await?;
+        }
+        Ok(())
+    }
+
+    async fn flush<S, D: AsyncStore>(
+        &mut self,
+        dest: &D,
+        report: &mut SyncReport,
+    ) -> Result<(), SyncError<S, D::Error>> {
+        if self.batch.is_empty() {
+            return Ok(());
+        }
+        dest.async_write_batch(&self.batch)
+            .await
+            .map_err(SyncError::Dest)?;
+        let bytes: usize = self.batch.values().map(|(_, value)| value.len()).sum();
+        report.bytes += bytes as u64;
+        report
+            .transferred
+            .extend(std::mem::take(&mut self.batch).cids());
+        Ok(())
+    }
+}
+
+/// Parses a fetched and verified value and returns it with the
+/// `(value, schema)` bonds that are not already queued.
+async fn bonds_to_pull<S, D>(
+    source: &S,
+    dest: &D,
+    value_bytes: &[u8],
+    schema_cid: Cid,
+    schemas: &mut Solvent,
+    pending: &PendingWrites,
+    report: &mut SyncReport,
+) -> Result<(Ipld, Vec<(Cid, Cid)>), SyncError<S::Error, D::Error>>
+where
+    S: AsyncStore,
+    D: AsyncStore,
+{
+    // Ensure schema is available
+    let schema_cell = ensure_schema(source, dest, schema_cid, schemas, report).await?;
 
     // Parse to discover bonds (use serde_ipld_dagcbor for DAG-CBOR)
-    let value: ipld_core::ipld::Ipld = serde_ipld_dagcbor::from_slice(&value_bytes)
+    let value: Ipld = serde_ipld_dagcbor::from_slice(value_bytes)
         .map_err(|e| SyncError::Format(format!("value parse error: {}", e)))?;
 
-    // First, recursively pull all bond dependencies (children before parent)
     let mut bonds = Vec::new();
     collect_bonds(&value, schema_cell.value(), schemas, &mut bonds);
-    for (bond_cid, bond_schema_cid) in bonds {
-        Box::pin(pull_recursive(
-            source,
-            dest,
-            bond_cid,
-            bond_schema_cid,
-            schemas,
-            transferred,
-        ))
-        .await?;
-    }
 
-    // Now store this value (all dependencies are already in dest)
-    dest.async_put(&value_cid, &value_bytes)
+    let mut seen = HashSet::new();
+    bonds.retain(|(cid, _)| !pending.queued.contains(cid) && seen.insert(*cid));
+    Ok((value, bonds))
+}
+
+/// Fetches and verifies the bonds missing from dest, in reverse order for
+/// [`Frame::children`]. Bonds present in dest have their dependencies too.
+async fn fetch_children<S, D>(
+    source: &S,
+    dest: &D,
+    bonds: &[(Cid, Cid)],
+    limits: &DecodeLimits,
+) -> Result<Vec<(Cid, Cid, Vec<u8>)>, SyncError<S::Error, D::Error>>
+where
+    S: AsyncStore,
+    D: AsyncStore,
+{
+    let bond_cids: Vec<Cid> = bonds.iter().map(|(cid, _)| *cid).collect();
+    let present = dest
+        .async_has_many(&bond_cids)
         .await
         .map_err(SyncError::Dest)?;
-    transferred.push(value_cid);
+    let missing: Vec<(Cid, Cid)> = bonds
+        .iter()
+        .zip(present)
+        .filter(|(_, present)| !present)
+        .map(|(bond, _)| *bond)
+        .collect();
 
-    Ok(())
+    // Fetch all missing children at once
+    let missing_cids: Vec<Cid> = missing.iter().map(|(cid, _)| *cid).collect();
+    let fetched = source
+        .async_get_many(&missing_cids)
+        .await
+        .map_err(SyncError::Source)?;
+    let mut children = Vec::with_capacity(missing.len());
+    for ((bond_cid, bond_schema_cid), bytes) in missing.into_iter().zip(fetched) {
+        let bytes = bytes.ok_or(SyncError::NotFound(bond_cid))?;
+        verify(bond_cid, &bytes, limits)?;
+        children.push((bond_cid, bond_schema_cid, bytes));
+    }
+    children.reverse();
+    Ok(children)
 }
 
-/// Ensure a schema is available at dest, fetching from source if needed.
+/// Rejects fetched bytes that don't hash to the requested CID, or that
+/// would be unsafe to decode.
+fn verify<S, D>(cid: Cid, bytes: &[u8], limits: &DecodeLimits) -> Result<(), SyncError<S, D>> {
+    if compute_cid(bytes) != cid {
+        return Err(SyncError::Corrupted(cid));
+    }
+    limits.check(bytes).map_err(|e| SyncError::Rejected(cid, e))
+}
+
+/// Ensure a schema and all its nested schemas are available at dest,
+/// fetching from source if needed.
 /// Returns a Cell containing the schema for traversal.
 async fn ensure_schema<S, D>(
     source: &S,
     dest: &D,
     cid: Cid,
     schemas: &mut Solvent,
-    transferred: &mut Vec<Cid>,
+    report: &mut SyncReport,
 ) -> Result<Arc<Cell<Structure>>, SyncError<S::Error, D::Error>>
 where
     S: AsyncStore,
@@ -139,6 +654,38 @@ where
         return Ok(cell);
     }
 
+    // Nested schemas must be in the solvent before their parent is added,
+    // so that `Solvent::add` can resolve the parent's bonds.
+    let limits = schemas.decode_limits();
+    let mut stack = vec![fetch_schema(source, dest, cid, &limits, report).await?];
+    while let Some(schema) = stack.last() {
+        let unresolved = schema_bonds(schema)
+            .into_iter()
+            .find(|nested| schemas.get::<Structure>(nested).is_none());
+        match unresolved {
+            Some(nested) => stack.push(fetch_schema(source, dest, nested, &limits, report).await?),
+            None => {
+                let schema = stack.pop().expect("stack is non-empty");
+                schemas.add(schema);
+            }
+        }
+    }
+
+    Ok(schemas.get::<Structure>(&cid).expect("schema was just added"))
+}
+
+/// Fetch a single schema from source, storing it in dest if missing.
+async fn fetch_schema<S, D>(
+    source: &S,
+    dest: &D,
+    cid: Cid,
+    limits: &DecodeLimits,
+    report: &mut SyncReport,
+) -> Result<Structure, SyncError<S::Error, D::Error>>
+where
+    S: AsyncStore,
+    D: AsyncStore,
+{
     // Check if dest has it
     let dest_has = dest.async_has(&cid).await.map_err(SyncError::Dest)?;
 
@@ -148,71 +695,36 @@ where
         .await
         .map_err(SyncError::Source)?
         .ok_or(SyncError::NotFound(cid))?;
+    verify(cid, &bytes, limits)?;
 
     // Store in dest if missing
     if !dest_has {
-        dest.async_put(&cid, &bytes).await.map_err(SyncError::Dest)?;
-        transferred.push(cid);
+        dest.async_put_schema(&cid, &bytes)
+            .await
+            .map_err(SyncError::Dest)?;
+        report.transferred.push(cid);
+        report.bytes += bytes.len() as u64;
     }
 
-    let schema: Structure = serde_ipld_dagcbor::from_slice(&bytes)
-        .map_err(|e| SyncError::Format(format!("schema parse error: {}", e)))?;
-
-    // Recursively ensure nested schema bonds are transferred
-    ensure_nested_schemas(source, dest, &schema, schemas, transferred).await?;
-
-    // Add to solvent (this also resolves internal bonds)
-    Ok(schemas.add(schema))
+    serde_ipld_dagcbor::from_slice(&bytes)
+        .map_err(|e| SyncError::Format(format!("schema parse error: {}", e)))
 }
 
-/// Recursively ensure all schema bonds are transferred.
-async fn ensure_nested_schemas<S, D>(
-    source: &S,
-    dest: &D,
-    schema: &Structure,
-    schemas: &mut Solvent,
-    transferred: &mut Vec<Cid>,
-) -> Result<(), SyncError<S::Error, D::Error>>
-where
-    S: AsyncStore,
-    D: AsyncStore,
-{
+/// CIDs of the schemas directly bonded from `schema`.
+pub(crate) fn schema_bonds(schema: &Structure) -> Vec<Cid> {
     match schema {
-        Structure::Sequence(inner) | Structure::Bond(inner) => {
-            let cid = inner.cid();
-            if schemas.get::<Structure>(&cid).is_none() {
-                Box::pin(ensure_schema(source, dest, cid, schemas, transferred)).await?;
-            }
-        }
-        Structure::Tuple(elems) => {
-            for elem in elems {
-                let cid = elem.cid();
-                if schemas.get::<Structure>(&cid).is_none() {
-                    Box::pin(ensure_schema(source, dest, cid, schemas, transferred)).await?;
-                }
-            }
+        Structure::Sequence(inner) | Structure::Bond(inner) | Structure::Defaulted(inner) => {
+            vec![inner.cid()]
         }
+        Structure::Tuple(elems) => elems.iter().map(|elem| elem.cid()).collect(),
         Structure::Record(fields) | Structure::Tagged(fields) => {
-            for (_, field) in fields {
-                let cid = field.cid();
-                if schemas.get::<Structure>(&cid).is_none() {
-                    Box::pin(ensure_schema(source, dest, cid, schemas, transferred)).await?;
-                }
-            }
+            fields.values().map(|field| field.cid()).collect()
         }
         Structure::Map { key: k, value: v } | Structure::OrderedMap { key: k, value: v } => {
-            let kk = k.cid();
-            let vk = v.cid();
-            if schemas.get::<Structure>(&kk).is_none() {
-                Box::pin(ensure_schema(source, dest, kk, schemas, transferred)).await?;
-            }
-            if schemas.get::<Structure>(&vk).is_none() {
-                Box::pin(ensure_schema(source, dest, vk, schemas, transferred)).await?;
-            }
+            vec![k.cid(), v.cid()]
         }
-        _ => {}
+        _ => Vec::new(),
     }
-    Ok(())
 }
 
 /// Push a value and all its dependencies from source to destination.
@@ -224,7 +736,7 @@ pub async fn push<S, D>(
     dest: &D,
     value_cid: Cid,
     schema_cid: Cid,
-) -> Result<Vec<Cid>, SyncError<S::Error, D::Error>>
+) -> Result<SyncReport, SyncError<S::Error, D::Error>>
 where
     S: AsyncStore,
     D: AsyncStore,
@@ -232,6 +744,113 @@ where
     pull(source, dest, value_cid, schema_cid).await
 }
 
+/// Blocks of a subgraph gathered by [`walk_subgraph`].
+#[derive(Debug, Clone, Default)]
+pub struct Subgraph {
+    /// Every schema needed to traverse `nodes`.
+    pub schemas: Vec<(Cid, Vec<u8>)>,
+    /// Values in dependency-first order: each node comes after those of its
+    /// bonds that were included.
+    pub nodes: Vec<(Cid, Vec<u8>)>,
+    /// `(value, schema)` bonds left out once the node limit was reached.
+    pub frontier: Vec<(Cid, Cid)>,
+}
+
+/// Walks the graph under a value in `store`, collecting up to `max_nodes`
+/// values totalling at most `max_bytes` for a peer to pull in one round trip.
+///
+/// Nodes are chosen depth-first from the root, so the included ones are
+/// connected to it, and the bonds beyond them are returned as the frontier
+/// to walk from next. The root is included even if it exceeds `max_bytes`.
+/// Values missing from `store` are left out.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "debug", skip_all, fields(root = %value_cid))
+)]
+pub async fn walk_subgraph<S: AsyncStore>(
+    store: &S,
+    value_cid: Cid,
+    schema_cid: Cid,
+    max_nodes: usize,
+    max_bytes: usize,
+) -> Result<Subgraph, SyncError<S::Error, Infallible>> {
+    // Schemas are copied into scratch as they are loaded
+    let scratch = MemoryStore::new();
+    let mut schemas = Solvent::new();
+    let mut loaded = SyncReport::default();
+
+    let mut included: HashMap<Cid, (Vec<u8>, Vec<Cid>)> = HashMap::new();
+    let mut seen = HashSet::from([value_cid]);
+    let mut stack = vec![(value_cid, schema_cid)];
+    let mut total_bytes = 0;
+    while included.len() < max_nodes {
+        let Some((cid, schema_cid)) = stack.pop() else {
+            break;
+        };
+        let Some(bytes) = store.async_get(&cid).await.map_err(SyncError::Source)? else {
+            continue;
+        };
+        if !included.is_empty() && total_bytes + bytes.len() > max_bytes {
+            stack.push((cid, schema_cid));
+            break;
+        }
+        total_bytes += bytes.len();
+        let schema_cell =
+            ensure_schema(store, &scratch, schema_cid, &mut schemas, &mut loaded).await?;
+        let value: ipld_core::ipld::Ipld = serde_ipld_dagcbor::from_slice(&bytes)
+            .map_err(|e| SyncError::Format(format!("value parse error: {}", e)))?;
+        let mut bonds = Vec::new();
+        collect_bonds(&value, schema_cell.value(), &schemas, &mut bonds);
+
+        let children = bonds.iter().map(|(cid, _)| *cid).collect();
+        // Pushed in reverse, so the first bond is walked first
+        for bond in bonds.into_iter().rev() {
+            if seen.insert(bond.0) {
+                stack.push(bond);
+            }
+        }
+        included.insert(cid, (bytes, children));
+    }
+
+    // Post-order over the included nodes
+    let mut nodes = Vec::with_capacity(included.len());
+    let mut emitted = HashSet::new();
+    let mut order = vec![(value_cid, 0)];
+    while let Some((cid, next)) = order.last_mut() {
+        let Some((_, children)) = included.get(cid) else {
+            order.pop();
+            continue;
+        };
+        match children.get(*next) {
+            Some(child) => {
+                *next += 1;
+                if !emitted.contains(child) {
+                    order.push((*child, 0));
+                }
+            }
+            None => {
+                let cid = *cid;
+                order.pop();
+                if emitted.insert(cid) {
+                    let (bytes, _) = included.remove(&cid).expect("node is included");
+                    nodes.push((cid, bytes));
+                }
+            }
+        }
+    }
+
+    let schemas = loaded
+        .transferred
+        .into_iter()
+        .filter_map(|cid| Some((cid, scratch.get(&cid).ok()??)))
+        .collect();
+    Ok(Subgraph {
+        schemas,
+        nodes,
+        frontier: stack,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -264,6 +883,13 @@ mod tests {
         chapters: Vec<Bond<Chapter>>,
     }
 
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, Oxide)]
+    #[oxide(crate = crate)]
+    struct Link {
+        index: u32,
+        previous: Option<Bond<Link>>,
+    }
+
     #[tokio::test]
     async fn pull_simple_record() {
         let source = MemoryStore::new();
@@ -279,7 +905,8 @@ mod tests {
 
         let transferred = pull(&source, &dest, value_cid, schema_cid)
             .await
-            .unwrap();
+            .unwrap()
+            .transferred;
 
         assert!(!transferred.is_empty());
         assert!(dest.has(&value_cid).unwrap());
@@ -311,7 +938,8 @@ mod tests {
 
         let transferred = pull(&source, &dest, chapter_cid, schema_cid)
             .await
-            .unwrap();
+            .unwrap()
+            .transferred;
 
         // Should have transferred chapter and author
         assert!(transferred.contains(&chapter_cid));
@@ -368,7 +996,8 @@ mod tests {
 
         let transferred = pull(&source, &dest, book_cid, schema_cid)
             .await
-            .unwrap();
+            .unwrap()
+            .transferred;
 
         // Should have transferred everything: book, 2 chapters, 2 authors
         assert!(transferred.contains(&book_cid));
@@ -426,7 +1055,8 @@ mod tests {
 
         let transferred = pull(&source, &dest, book_cid, schema_cid)
             .await
-            .unwrap();
+            .unwrap()
+            .transferred;
 
         // Shared author should only be transferred once
         let author_count = transferred
@@ -442,6 +1072,71 @@ mod tests {
         assert!(dest.has(&author_cell.cid()).unwrap());
     }
 
+    #[tokio::test]
+    async fn pull_siblings_concurrently() {
+        let source = MemoryStore::new();
+        let dest = MemoryStore::new();
+        let mut solvent = Solvent::new();
+
+        let author_cell = solvent.add(Author {
+            name: "Shared".into(),
+            bio: "In every chapter".into(),
+        });
+        let chapters: Vec<_> = (0..10)
+            .map(|i| {
+                solvent.add(Chapter {
+                    title: format!("Chapter {}", i),
+                    page_count: i,
+                    author: Bond::from_cell(Arc::clone(&author_cell)),
+                })
+            })
+            .collect();
+        let book_cell = solvent.add(Book {
+            title: "Anthology".into(),
+            year: 2025,
+            chapters: chapters
+                .iter()
+                .map(|c| Bond::from_cell(Arc::clone(c)))
+                .collect(),
+        });
+        let (book_cid, schema_cid) = solvent.persist_cell(&book_cell, &source).unwrap();
+
+        let options = PullOptions {
+            max_in_flight: 4,
+            ..Default::default()
+        };
+        let transferred = pull_with_options(&source, &dest, book_cid, schema_cid, &options)
+            .await
+            .unwrap()
+            .transferred;
+
+        let position = |cid: Cid| transferred.iter().position(|c| *c == cid).unwrap();
+        assert_eq!(
+            transferred.iter().filter(|c| **c == author_cell.cid()).count(),
+            1
+        );
+        for chapter in &chapters {
+            assert!(position(author_cell.cid()) < position(chapter.cid()));
+            assert!(position(chapter.cid()) < position(book_cid));
+        }
+    }
+
+    #[tokio::test]
+    async fn pull_rejects_deeply_nested_blocks() {
+        let source = MemoryStore::new();
+        let dest = MemoryStore::new();
+
+        let mut bytes = vec![0x81; 100_000];
+        bytes.push(0x00);
+        let cid = compute_cid(&bytes);
+        source.put(&cid, &bytes).unwrap();
+        let schema_cid = Structure::Unit.compute_cid();
+
+        let err = pull(&source, &dest, cid, schema_cid).await.unwrap_err();
+        assert!(matches!(err, SyncError::Rejected(c, LimitError::TooDeep { .. }) if c == cid));
+        assert!(!dest.has(&cid).unwrap());
+    }
+
     #[tokio::test]
     async fn pull_incremental() {
         let source = MemoryStore::new();
@@ -460,12 +1155,298 @@ mod tests {
 
         let transferred = pull(&source, &dest, value_cid, schema_cid)
             .await
-            .unwrap();
+            .unwrap()
+            .transferred;
 
         // Nothing should be transferred since dest already has everything
         assert!(transferred.is_empty());
     }
 
+    #[tokio::test]
+    async fn pull_flushes_large_graphs_in_batches() {
+        let source = MemoryStore::new();
+        let dest = MemoryStore::new();
+        let mut solvent = Solvent::new();
+
+        let author_cell = solvent.add(Author {
+            name: "Prolific".into(),
+            bio: "Writes a lot".into(),
+        });
+        let chapters: Vec<_> = (0..WRITE_BATCH_SIZE as u32 + 10)
+            .map(|i| {
+                solvent.add(Chapter {
+                    title: format!("Chapter {}", i),
+                    page_count: i,
+                    author: Bond::from_cell(Arc::clone(&author_cell)),
+                })
+            })
+            .collect();
+        let book = Book {
+            title: "Long Book".into(),
+            year: 2025,
+            chapters: chapters
+                .iter()
+                .map(|c| Bond::from_cell(Arc::clone(c)))
+                .collect(),
+        };
+        let book_cell = solvent.add(book);
+        let (book_cid, schema_cid) = solvent.persist_cell(&book_cell, &source).unwrap();
+
+        let transferred = pull(&source, &dest, book_cid, schema_cid)
+            .await
+            .unwrap()
+            .transferred;
+
+        assert!(transferred.contains(&book_cid));
+        assert_eq!(
+            transferred.iter().filter(|k| **k == author_cell.cid()).count(),
+            1
+        );
+        for chapter in &chapters {
+            assert!(dest.has(&chapter.cid()).unwrap());
+        }
+        assert!(dest.has(&book_cid).unwrap());
+    }
+
+    #[tokio::test]
+    async fn pull_deep_chain() {
+        let source = MemoryStore::new();
+        let dest = MemoryStore::new();
+
+        // Written node by node: building the chain through a Solvent would
+        // recurse over it just like the old pull did
+        let mut previous = None;
+        for index in 0..50_000 {
+            let link = Link { index, previous };
+            let bytes = link.to_bytes();
+            let cid = compute_cid(&bytes);
+            source.put(&cid, &bytes).unwrap();
+            previous = Some(Bond::from_cid(cid));
+        }
+        let head_cid = previous.unwrap().cid();
+        let mut solvent = Solvent::new();
+        let first = solvent.add(Link {
+            index: 0,
+            previous: None,
+        });
+        let (first_cid, schema_cid) = solvent.persist_cell(&first, &source).unwrap();
+
+        let transferred = pull(&source, &dest, head_cid, schema_cid)
+            .await
+            .unwrap()
+            .transferred;
+
+        assert_eq!(transferred.last(), Some(&head_cid));
+        assert!(dest.has(&first_cid).unwrap());
+        assert!(dest.has(&head_cid).unwrap());
+    }
+
+    #[tokio::test]
+    async fn pull_resumes_from_frontier() {
+        let source = MemoryStore::new();
+        let dest = MemoryStore::new();
+        let mut solvent = Solvent::new();
+        let mut cids = Vec::new();
+        let mut previous = None;
+        for index in 0..10 {
+            let cell = solvent.add(Link { index, previous });
+            cids.push(cell.cid());
+            previous = Some(Bond::from_cell(cell));
+        }
+        let head = solvent.get::<Link>(&cids[9]).unwrap();
+        let (head_cid, schema_cid) = solvent.persist_cell(&head, &source).unwrap();
+
+        let options = PullOptions {
+            max_nodes: Some(4),
+            ..Default::default()
+        };
+        let report = pull_with_options(&source, &dest, head_cid, schema_cid, &options)
+            .await
+            .unwrap();
+        assert!(!report.is_complete());
+        assert!(dest.has(&cids[3]).unwrap());
+        assert!(!dest.has(&cids[4]).unwrap());
+        let expected: Vec<_> = cids[4..].iter().map(|cid| (*cid, schema_cid)).collect();
+        assert_eq!(report.frontier, expected);
+
+        let resumed = resume_pull(&source, &dest, &report.frontier, &PullOptions::default())
+            .await
+            .unwrap();
+        assert!(resumed.is_complete());
+        assert_eq!(resumed.transferred, &cids[4..]);
+        // Only the frontier's bond into the finished part is checked again
+        assert_eq!(resumed.skipped, 1);
+        assert!(dest.has(&head_cid).unwrap());
+    }
+
+    #[tokio::test]
+    async fn pull_partial_leaves_out_unselected_bonds() {
+        let source = MemoryStore::new();
+        let dest = MemoryStore::new();
+        let mut solvent = Solvent::new();
+        let author = solvent.add(Author {
+            name: "Author".into(),
+            bio: "Writes".into(),
+        });
+        let chapter = solvent.add(Chapter {
+            title: "One".into(),
+            page_count: 10,
+            author: Bond::from_cell(Arc::clone(&author)),
+        });
+        let book = solvent.add(Book {
+            title: "Book".into(),
+            year: 2025,
+            chapters: vec![Bond::from_cell(Arc::clone(&chapter))],
+        });
+        let (book_cid, schema_cid) = solvent.persist_cell(&book, &source).unwrap();
+
+        let shallow = Selector::new().max_depth(0);
+        let report = pull_partial(&source, &dest, book_cid, schema_cid, &shallow)
+            .await
+            .unwrap();
+        let chapter_schema = Chapter::schema().compute_cid();
+        assert_eq!(report.frontier, [(chapter.cid(), chapter_schema)]);
+
+        let report = resume_pull(&source, &dest, &report.frontier, &PullOptions::default())
+            .await
+            .unwrap();
+        assert!(report.is_complete());
+        assert!(dest.has(&author.cid()).unwrap());
+
+        let dest = MemoryStore::new();
+        let selector = Selector::new().skip_type::<Author>();
+        let report = pull_partial(&source, &dest, book_cid, schema_cid, &selector)
+            .await
+            .unwrap();
+        assert!(dest.has(&chapter.cid()).unwrap());
+        assert!(!dest.has(&author.cid()).unwrap());
+        let author_schema = Author::schema().compute_cid();
+        assert_eq!(report.frontier, [(author.cid(), author_schema)]);
+    }
+
+    #[tokio::test]
+    async fn pull_partial_skips_deleted_values() {
+        let source = MemoryStore::new();
+        let dest = MemoryStore::new();
+        let mut solvent = Solvent::new();
+        let author = solvent.bond(Author {
+            name: "Author".into(),
+            bio: "Writes".into(),
+        });
+        let chapters: Vec<Bond<Chapter>> = ["One", "Two"]
+            .into_iter()
+            .map(|title| {
+                solvent.bond(Chapter {
+                    title: title.into(),
+                    page_count: 10,
+                    author: author.clone(),
+                })
+            })
+            .collect();
+        let book = solvent.add(Book {
+            title: "Book".into(),
+            year: 2025,
+            chapters: chapters.clone(),
+        });
+        let (book_cid, schema_cid) = solvent.persist_cell(&book, &source).unwrap();
+
+        let mut tombstones = Tombstones::new();
+        tombstones.delete(chapters[0].cid(), 100);
+        let selector = Selector::new().skip_deleted(tombstones);
+        let report = pull_partial(&source, &dest, book_cid, schema_cid, &selector)
+            .await
+            .unwrap();
+        assert!(!dest.has(&chapters[0].cid()).unwrap());
+        assert!(dest.has(&chapters[1].cid()).unwrap());
+        assert!(dest.has(&author.cid()).unwrap());
+        let chapter_schema = Chapter::schema().compute_cid();
+        assert_eq!(report.frontier, [(chapters[0].cid(), chapter_schema)]);
+    }
+
+    #[tokio::test]
+    async fn pull_partial_follows_a_path() {
+        let source = MemoryStore::new();
+        let mut solvent = Solvent::new();
+        let author = solvent.bond(Author {
+            name: "Author".into(),
+            bio: "Writes".into(),
+        });
+        let chapters: Vec<Bond<Chapter>> = ["One", "Two"]
+            .into_iter()
+            .map(|title| {
+                solvent.bond(Chapter {
+                    title: title.into(),
+                    page_count: 10,
+                    author: author.clone(),
+                })
+            })
+            .collect();
+        let book = solvent.add(Book {
+            title: "Book".into(),
+            year: 2025,
+            chapters: chapters.clone(),
+        });
+        let (book_cid, schema_cid) = solvent.persist_cell(&book, &source).unwrap();
+        let schema = Bond::new(Book::schema());
+        let author_schema = Author::schema().compute_cid();
+        let chapter_schema = Chapter::schema().compute_cid();
+
+        let dest = MemoryStore::new();
+        let path = Path::compile("chapters[0].title", &schema).unwrap();
+        let selector = Selector::new().along(path);
+        let report = pull_partial(&source, &dest, book_cid, schema_cid, &selector)
+            .await
+            .unwrap();
+        assert!(dest.has(&chapters[0].cid()).unwrap());
+        assert!(!dest.has(&author.cid()).unwrap());
+        assert_eq!(
+            report.frontier,
+            [
+                (chapters[1].cid(), chapter_schema),
+                (author.cid(), author_schema)
+            ]
+        );
+
+        // Everything below a selected value is pulled
+        let dest = MemoryStore::new();
+        let path = Path::compile("chapters[1]", &schema).unwrap();
+        let selector = Selector::new().along(path);
+        let report = pull_partial(&source, &dest, book_cid, schema_cid, &selector)
+            .await
+            .unwrap();
+        assert!(dest.has(&author.cid()).unwrap());
+        assert_eq!(report.frontier, [(chapters[0].cid(), chapter_schema)]);
+    }
+
+    #[tokio::test]
+    async fn walk_subgraph_stops_at_frontier() {
+        let store = MemoryStore::new();
+        let mut solvent = Solvent::new();
+        let mut head = solvent.add(Link {
+            index: 0,
+            previous: None,
+        });
+        let mut cids = vec![head.cid()];
+        for index in 1..10 {
+            let previous = Some(Bond::from_cell(head));
+            head = solvent.add(Link { index, previous });
+            cids.push(head.cid());
+        }
+        let (_, schema_cid) = solvent.persist_cell(&head, &store).unwrap();
+
+        let subgraph = walk_subgraph(&store, cids[9], schema_cid, 4, usize::MAX)
+            .await
+            .unwrap();
+
+        let walked: Vec<Cid> = subgraph.nodes.iter().map(|(cid, _)| *cid).collect();
+        assert_eq!(walked, cids[6..].to_vec());
+        assert_eq!(subgraph.frontier, vec![(cids[5], schema_cid)]);
+        assert!(subgraph.schemas.iter().any(|(cid, _)| *cid == schema_cid));
+
+        let subgraph = walk_subgraph(&store, cids[9], schema_cid, 100, 1).await.unwrap();
+        assert_eq!(subgraph.nodes.len(), 1);
+    }
+
     #[tokio::test]
     async fn push_with_bonds() {
         let source = MemoryStore::new();
@@ -488,7 +1469,8 @@ mod tests {
 
         let transferred = push(&source, &dest, chapter_cid, schema_cid)
             .await
-            .unwrap();
+            .unwrap()
+            .transferred;
 
         assert!(!transferred.is_empty());
         assert!(dest.has(&chapter_cid).unwrap());
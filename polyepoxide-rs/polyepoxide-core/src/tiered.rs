@@ -0,0 +1,477 @@
+//! Hot/cold storage tiering.
+//!
+//! `TieredStore` keeps a small primary (hot) store and moves large, rarely
+//! read blocks to a secondary (cold) store such as slow disk or object
+//! storage. A migrated block leaves a stub in the hot store, so `has` stays
+//! cheap and `get` fetches from the cold store transparently.
+//!
+//! Access times are kept in memory. A store opened with `TieredStore::open`
+//! reads them back from a record in the hot store, which
+//! `save_access_times` writes, so blocks stay idle across restarts. The
+//! record is only reachable from a ref, so the store keeps it through `gc`
+//! itself and deletes the previous one on each save.
+
+use cid::Cid;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::gc::{reachable, GcStats};
+use crate::index::IndexStore;
+use crate::oxide::compute_cid;
+use crate::refs::RefStore;
+use crate::store::{Batch, Store};
+
+/// Prefix marking a stub. 0xff is a CBOR "break" byte, which can't start a
+/// well-formed DAG-CBOR item, so stubs never collide with real blocks.
+const STUB_MAGIC: &[u8] = b"\xffpx-cold";
+
+/// Accesses closer together than this update the recorded time only once.
+const DEFAULT_ACCESS_RESOLUTION: Duration = Duration::from_secs(60);
+
+/// Ref in the hot store to the saved access times.
+const ACCESS_REF: &str = "tiered/access";
+
+/// Access times as saved in the hot store, in milliseconds since the Unix
+/// epoch. CIDs are kept as strings rather than links, so the record doesn't
+/// keep the blocks it lists from being collected.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct AccessRecord {
+    opened: u64,
+    accessed: BTreeMap<String, u64>,
+}
+
+/// Error from a tiered store.
+#[derive(Debug, thiserror::Error)]
+pub enum TieredError<H, C> {
+    #[error("hot store error: {0}")]
+    Hot(H),
+    #[error("cold store error: {0}")]
+    Cold(C),
+    #[error("block {0} is stubbed but missing from the cold store")]
+    MissingCold(Cid),
+}
+
+/// Outcome of a migration pass.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MigrationStats {
+    pub migrated: usize,
+    pub migrated_bytes: u64,
+}
+
+/// A store that serves blocks from a hot store and spills cold ones to a
+/// secondary store.
+pub struct TieredStore<H, C> {
+    hot: H,
+    cold: C,
+    /// Approximate last access per block; blocks not listed count as
+    /// accessed when the store was first opened.
+    accessed: Mutex<HashMap<Cid, SystemTime>>,
+    opened: SystemTime,
+    resolution: Duration,
+    /// The access record last read or saved, kept through `gc`.
+    access_record: Mutex<Option<Cid>>,
+}
+
+impl<H: Store, C: Store> TieredStore<H, C> {
+    /// Creates a store tracking access times from now on, without reading
+    /// saved ones.
+    pub fn new(hot: H, cold: C) -> Self {
+        Self {
+            hot,
+            cold,
+            accessed: Mutex::new(HashMap::new()),
+            opened: SystemTime::now(),
+            resolution: DEFAULT_ACCESS_RESOLUTION,
+            access_record: Mutex::new(None),
+        }
+    }
+
+    /// Sets how coarsely access times are tracked.
+    pub fn with_access_resolution(mut self, resolution: Duration) -> Self {
+        self.resolution = resolution;
+        self
+    }
+
+    pub fn hot(&self) -> &H {
+        &self.hot
+    }
+
+    pub fn cold(&self) -> &C {
+        &self.cold
+    }
+
+    /// Returns how long ago the block was last read, approximately.
+    pub fn idle_time(&self, cid: &Cid) -> Duration {
+        let accessed = self.accessed.lock().unwrap();
+        let last = accessed.get(cid).unwrap_or(&self.opened);
+        last.elapsed().unwrap_or_default()
+    }
+
+    /// Returns true if the block has been moved to the cold store.
+    pub fn is_cold(&self, cid: &Cid) -> Result<bool, TieredError<H::Error, C::Error>> {
+        Ok(self
+            .hot
+            .get(cid)
+            .map_err(TieredError::Hot)?
+            .is_some_and(|bytes| is_stub(&bytes)))
+    }
+
+    /// Moves candidate blocks of at least `min_size` bytes that haven't been
+    /// read for `idle_for` to the cold store.
+    ///
+    /// Candidates typically come from `reachable` over the application's roots.
+    /// Each block is written to the cold store before its stub replaces it.
+    pub fn migrate_cold(
+        &self,
+        candidates: impl IntoIterator<Item = Cid>,
+        idle_for: Duration,
+        min_size: usize,
+    ) -> Result<MigrationStats, TieredError<H::Error, C::Error>> {
+        let mut stats = MigrationStats::default();
+        for cid in candidates {
+            if self.idle_time(&cid) < idle_for {
+                continue;
+            }
+            let Some(bytes) = self.hot.get(&cid).map_err(TieredError::Hot)? else {
+                continue;
+            };
+            if is_stub(&bytes) || bytes.len() < min_size {
+                continue;
+            }
+
+            self.cold.put(&cid, &bytes).map_err(TieredError::Cold)?;
+            self.hot
+                .put(&cid, &stub(bytes.len()))
+                .map_err(TieredError::Hot)?;
+            stats.migrated += 1;
+            stats.migrated_bytes += bytes.len() as u64;
+        }
+        Ok(stats)
+    }
+
+    fn touch(&self, cid: &Cid) {
+        let now = SystemTime::now();
+        let mut accessed = self.accessed.lock().unwrap();
+        let last = accessed.entry(*cid).or_insert(now);
+        if now.duration_since(*last).unwrap_or_default() >= self.resolution {
+            *last = now;
+        }
+    }
+}
+
+impl<H: RefStore, C: Store> TieredStore<H, C> {
+    /// Creates a store with the access times last saved in `hot`. A missing
+    /// or unreadable record counts every block as accessed now.
+    pub fn open(hot: H, cold: C) -> Result<Self, TieredError<H::Error, C::Error>> {
+        let store = Self::new(hot, cold);
+        let Some(cid) = store.hot.get_ref(ACCESS_REF).map_err(TieredError::Hot)? else {
+            return Ok(store);
+        };
+        let record = store.hot.get(&cid).map_err(TieredError::Hot)?;
+        let Some(record) =
+            record.and_then(|bytes| serde_ipld_dagcbor::from_slice::<AccessRecord>(&bytes).ok())
+        else {
+            return Ok(store);
+        };
+        let accessed = record
+            .accessed
+            .iter()
+            .filter_map(|(cid, ms)| Some((cid.parse().ok()?, from_unix_ms(*ms))))
+            .collect();
+        Ok(Self {
+            accessed: Mutex::new(accessed),
+            opened: from_unix_ms(record.opened),
+            access_record: Mutex::new(Some(cid)),
+            ..store
+        })
+    }
+
+    /// Saves the access times to the hot store, for `open` to read back,
+    /// replacing the previous record. Blocks no longer in the store are
+    /// left out and forgotten.
+    pub fn save_access_times(&self) -> Result<(), TieredError<H::Error, C::Error>> {
+        let mut accessed = self.accessed.lock().unwrap();
+        let mut present = HashMap::with_capacity(accessed.len());
+        for (cid, time) in accessed.iter() {
+            if self.hot.has(cid).map_err(TieredError::Hot)? {
+                present.insert(*cid, *time);
+            }
+        }
+        *accessed = present;
+        let record = AccessRecord {
+            opened: unix_ms(self.opened),
+            accessed: accessed
+                .iter()
+                .map(|(cid, time)| (cid.to_string(), unix_ms(*time)))
+                .collect(),
+        };
+        drop(accessed);
+
+        let bytes = serde_ipld_dagcbor::to_vec(&record).expect("access record encodes");
+        let cid = compute_cid(&bytes);
+        let previous = self.hot.get_ref(ACCESS_REF).map_err(TieredError::Hot)?;
+        self.hot.put(&cid, &bytes).map_err(TieredError::Hot)?;
+        self.hot
+            .set_ref(ACCESS_REF, &cid)
+            .map_err(TieredError::Hot)?;
+        *self.access_record.lock().unwrap() = Some(cid);
+        match previous {
+            Some(previous) if previous != cid => {
+                self.hot.delete(&previous).map_err(TieredError::Hot)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn unix_ms(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+fn from_unix_ms(ms: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(ms)
+}
+
+fn stub(size: usize) -> Vec<u8> {
+    let mut bytes = STUB_MAGIC.to_vec();
+    bytes.extend_from_slice(&(size as u64).to_be_bytes());
+    bytes
+}
+
+fn is_stub(bytes: &[u8]) -> bool {
+    bytes.len() == STUB_MAGIC.len() + 8 && bytes.starts_with(STUB_MAGIC)
+}
+
+impl<H: Store, C: Store> Store for TieredStore<H, C> {
+    type Error = TieredError<H::Error, C::Error>;
+
+    fn get(&self, cid: &Cid) -> Result<Option<Vec<u8>>, Self::Error> {
+        let Some(bytes) = self.hot.get(cid).map_err(TieredError::Hot)? else {
+            return Ok(None);
+        };
+        self.touch(cid);
+        if !is_stub(&bytes) {
+            return Ok(Some(bytes));
+        }
+        self.cold
+            .get(cid)
+            .map_err(TieredError::Cold)?
+            .ok_or(TieredError::MissingCold(*cid))
+            .map(Some)
+    }
+
+    fn put(&self, cid: &Cid, value: &[u8]) -> Result<(), Self::Error> {
+        self.hot.put(cid, value).map_err(TieredError::Hot)
+    }
+
+    fn put_schema(&self, cid: &Cid, value: &[u8]) -> Result<(), Self::Error> {
+        self.hot.put_schema(cid, value).map_err(TieredError::Hot)
+    }
+
+    fn put_many(&self, nodes: &[(&Cid, &[u8])]) -> Result<(), Self::Error> {
+        self.hot.put_many(nodes).map_err(TieredError::Hot)
+    }
+
+    fn write_batch(&self, batch: &Batch) -> Result<(), Self::Error> {
+        self.hot.write_batch(batch).map_err(TieredError::Hot)
+    }
+
+    fn has(&self, cid: &Cid) -> Result<bool, Self::Error> {
+        self.hot.has(cid).map_err(TieredError::Hot)
+    }
+
+    /// Removes the block from both tiers, hot first so a failure never
+    /// leaves a stub without its data.
+    fn delete(&self, cid: &Cid) -> Result<(), Self::Error> {
+        self.hot.delete(cid).map_err(TieredError::Hot)?;
+        self.cold.delete(cid).map_err(TieredError::Cold)?;
+        self.accessed.lock().unwrap().remove(cid);
+        Ok(())
+    }
+
+    /// Every block has an entry in the hot store, data or stub.
+    fn iter(&self) -> Box<dyn Iterator<Item = Result<Cid, Self::Error>> + '_> {
+        Box::new(self.hot.iter().map(|r| r.map_err(TieredError::Hot)))
+    }
+
+    /// Marks through stubs, then collects both tiers.
+    ///
+    /// Every reachable block is passed to the tiers as a root, since neither
+    /// can traverse links hidden behind stubs on its own. The access record
+    /// is kept as well, and access times of collected blocks are dropped.
+    fn gc(&self, roots: &[Cid]) -> Result<GcStats, Self::Error> {
+        let mut roots = roots.to_vec();
+        roots.extend(*self.access_record.lock().unwrap());
+        let keep = reachable(self, &roots)?;
+        self.accessed
+            .lock()
+            .unwrap()
+            .retain(|cid, _| keep.contains(cid));
+        let keep: Vec<Cid> = keep.into_iter().collect();
+        let hot = self.hot.gc(&keep).map_err(TieredError::Hot)?;
+        let cold = self.cold.gc(&keep).map_err(TieredError::Cold)?;
+        Ok(GcStats {
+            retained: hot.retained,
+            freed: hot.freed + cold.freed,
+            freed_bytes: hot.freed_bytes + cold.freed_bytes,
+        })
+    }
+
+    fn compact(&self) -> Result<(), Self::Error> {
+        self.hot.compact().map_err(TieredError::Hot)?;
+        self.cold.compact().map_err(TieredError::Cold)
+    }
+}
+
+/// Refs live in the hot store; the cold store only holds migrated blocks.
+impl<H: RefStore, C: Store> RefStore for TieredStore<H, C> {
+    fn get_ref(&self, name: &str) -> Result<Option<Cid>, Self::Error> {
+        self.hot.get_ref(name).map_err(TieredError::Hot)
+    }
+
+    fn set_ref(&self, name: &str, cid: &Cid) -> Result<(), Self::Error> {
+        self.hot.set_ref(name, cid).map_err(TieredError::Hot)
+    }
+
+    fn delete_ref(&self, name: &str) -> Result<(), Self::Error> {
+        self.hot.delete_ref(name).map_err(TieredError::Hot)
+    }
+
+    fn list_refs(&self, prefix: &str) -> Result<Vec<(String, Cid)>, Self::Error> {
+        self.hot.list_refs(prefix).map_err(TieredError::Hot)
+    }
+
+    fn compare_and_set_ref(
+        &self,
+        name: &str,
+        expected: Option<&Cid>,
+        new: &Cid,
+    ) -> Result<bool, Self::Error> {
+        self.hot
+            .compare_and_set_ref(name, expected, new)
+            .map_err(TieredError::Hot)
+    }
+}
+
+/// Index entries live in the hot store, like refs.
+impl<H: IndexStore, C: Store> IndexStore for TieredStore<H, C> {
+    fn set_index_entry(&self, name: &str, cid: &Cid) -> Result<(), Self::Error> {
+        self.hot
+            .set_index_entry(name, cid)
+            .map_err(TieredError::Hot)
+    }
+
+    fn delete_index_entry(&self, name: &str) -> Result<(), Self::Error> {
+        self.hot.delete_index_entry(name).map_err(TieredError::Hot)
+    }
+
+    fn list_index_entries(&self, prefix: &str) -> Result<Vec<(String, Cid)>, Self::Error> {
+        self.hot
+            .list_index_entries(prefix)
+            .map_err(TieredError::Hot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oxide::compute_cid;
+    use crate::MemoryStore;
+
+    #[test]
+    fn migrates_large_idle_blocks() {
+        let store = TieredStore::new(MemoryStore::new(), MemoryStore::new());
+        let large = vec![7u8; 1024];
+        let large_cid = compute_cid(&large);
+        let small_cid = compute_cid(b"small");
+        store.put(&large_cid, &large).unwrap();
+        store.put(&small_cid, b"small").unwrap();
+
+        let stats = store
+            .migrate_cold([large_cid, small_cid], Duration::ZERO, 512)
+            .unwrap();
+
+        assert_eq!(stats.migrated, 1);
+        assert!(store.is_cold(&large_cid).unwrap());
+        assert!(!store.is_cold(&small_cid).unwrap());
+        assert!(store.hot().get(&large_cid).unwrap().unwrap().len() < 32);
+        assert_eq!(store.get(&large_cid).unwrap(), Some(large));
+        assert!(store.has(&large_cid).unwrap());
+    }
+
+    #[test]
+    fn recently_read_blocks_stay_hot() {
+        let store = TieredStore::new(MemoryStore::new(), MemoryStore::new());
+        let data = vec![1u8; 1024];
+        let cid = compute_cid(&data);
+        store.put(&cid, &data).unwrap();
+        store.get(&cid).unwrap();
+
+        let stats = store
+            .migrate_cold([cid], Duration::from_secs(3600), 0)
+            .unwrap();
+        assert_eq!(stats.migrated, 0);
+        assert!(!store.is_cold(&cid).unwrap());
+    }
+
+    #[test]
+    fn access_times_survive_reopening() {
+        let (hot, cold) = (MemoryStore::new(), MemoryStore::new());
+        let idle = vec![2u8; 1024];
+        let read = vec![3u8; 1024];
+        let (idle_cid, read_cid) = (compute_cid(&idle), compute_cid(&read));
+
+        let store = TieredStore::open(&hot, &cold).unwrap();
+        store.put(&idle_cid, &idle).unwrap();
+        store.put(&read_cid, &read).unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+        store.get(&read_cid).unwrap();
+        store.save_access_times().unwrap();
+        drop(store);
+
+        let store = TieredStore::open(&hot, &cold).unwrap();
+        let stats = store
+            .migrate_cold([idle_cid, read_cid], Duration::from_millis(100), 0)
+            .unwrap();
+        assert_eq!(stats.migrated, 1);
+        assert!(store.is_cold(&idle_cid).unwrap());
+        assert!(!store.is_cold(&read_cid).unwrap());
+    }
+
+    #[test]
+    fn access_record_is_kept_by_gc_and_replaced() {
+        let (hot, cold) = (MemoryStore::new(), MemoryStore::new());
+        let kept = vec![4u8; 1024];
+        let dropped = vec![5u8; 1024];
+        let (kept_cid, dropped_cid) = (compute_cid(&kept), compute_cid(&dropped));
+
+        let store = TieredStore::open(&hot, &cold).unwrap();
+        store.put(&kept_cid, &kept).unwrap();
+        store.put(&dropped_cid, &dropped).unwrap();
+        store.get(&kept_cid).unwrap();
+        store.get(&dropped_cid).unwrap();
+        store.save_access_times().unwrap();
+        let first = hot.get_ref(ACCESS_REF).unwrap().unwrap();
+
+        store.gc(&[kept_cid]).unwrap();
+        assert!(!store.has(&dropped_cid).unwrap());
+        assert!(hot.has(&first).unwrap());
+
+        store.save_access_times().unwrap();
+        let second = hot.get_ref(ACCESS_REF).unwrap().unwrap();
+        assert_ne!(first, second);
+        assert!(!hot.has(&first).unwrap());
+        let bytes = hot.get(&second).unwrap().unwrap();
+        let record: AccessRecord = serde_ipld_dagcbor::from_slice(&bytes).unwrap();
+        assert!(record.accessed.contains_key(&kept_cid.to_string()));
+        assert!(!record.accessed.contains_key(&dropped_cid.to_string()));
+        drop(store);
+
+        let store = TieredStore::open(&hot, &cold).unwrap();
+        assert!(store.accessed.lock().unwrap().contains_key(&kept_cid));
+    }
+}
@@ -0,0 +1,339 @@
+//! Tombstones for soft-deleting synced data.
+//!
+//! Content-addressed values can't be "removed" from peers that already hold
+//! them — the next sync would bring them back. Deletions are therefore
+//! recorded as data: a `Deleted` marker travels through sync like any other
+//! oxide, and readers drop entities whose CID is tombstoned.
+//!
+//! The marker stores the target CID as plain bytes rather than a `Bond`, so a
+//! tombstone neither keeps the deleted subgraph reachable (allowing GC to prune
+//! it) nor causes `pull` to transfer it.
+//!
+//! Tombstones are honoured by:
+//! - `Tombstones::gc`, which frees deleted values even where a root still
+//!   bonds them
+//! - `Selector::skip_deleted`, so a pull doesn't bring deleted values back
+//! - `Indexes::set_tombstones`, so lookups leave deleted values out
+
+use cid::Cid;
+use serde::{Deserialize, Serialize};
+
+use crate::bond::Bond;
+use crate::gc::GcStats;
+use crate::oxide::{BondMapper, BondVisitor, ByteString, Oxide};
+use crate::schema::{IntType, Structure};
+use crate::store::Store;
+
+/// Marker recording that the entity with the given CID was deleted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Deleted {
+    target: ByteString,
+    /// Deletion time in milliseconds since epoch.
+    pub deleted_at_ms: u64,
+}
+
+impl Deleted {
+    pub fn new(target: Cid, deleted_at_ms: u64) -> Self {
+        Self {
+            target: ByteString(target.to_bytes()),
+            deleted_at_ms,
+        }
+    }
+
+    /// Returns the CID of the deleted entity, or `None` if the marker was
+    /// decoded from bytes that aren't one.
+    pub fn target(&self) -> Option<Cid> {
+        Cid::try_from(self.target.as_bytes()).ok()
+    }
+}
+
+impl Oxide for Deleted {
+    fn schema() -> Structure {
+        Structure::record([
+            ("target", Structure::ByteString),
+            ("deleted_at_ms", Structure::Int(IntType::U64)),
+        ])
+    }
+
+    fn visit_bonds(&self, _visitor: &mut dyn BondVisitor) {}
+
+    fn map_bonds(&self, _mapper: &mut impl BondMapper) -> Self {
+        self.clone()
+    }
+}
+
+/// A set of tombstones, kept sorted by target for a canonical encoding.
+///
+/// Two replicas that saw the same deletions produce the same CID regardless
+/// of the order the deletions happened in. Decoded entries are sorted and
+/// deduplicated the same way, whatever order the encoding had.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "RawTombstones")]
+pub struct Tombstones {
+    entries: Vec<Deleted>,
+}
+
+/// Tombstones as encoded, before being put in order.
+#[derive(Deserialize)]
+struct RawTombstones {
+    entries: Vec<Deleted>,
+}
+
+impl From<RawTombstones> for Tombstones {
+    fn from(raw: RawTombstones) -> Self {
+        let mut tombstones = Tombstones::new();
+        for entry in raw.entries {
+            tombstones.insert(entry);
+        }
+        tombstones
+    }
+}
+
+impl Tombstones {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a deletion. Re-deleting keeps the earliest timestamp.
+    pub fn delete(&mut self, target: Cid, deleted_at_ms: u64) {
+        self.insert(Deleted::new(target, deleted_at_ms));
+    }
+
+    fn insert(&mut self, marker: Deleted) {
+        match self
+            .entries
+            .binary_search_by(|e| e.target.as_bytes().cmp(marker.target.as_bytes()))
+        {
+            Ok(i) => {
+                let existing = &mut self.entries[i];
+                existing.deleted_at_ms = existing.deleted_at_ms.min(marker.deleted_at_ms);
+            }
+            Err(i) => self.entries.insert(i, marker),
+        }
+    }
+
+    /// Returns true if the given CID has been deleted.
+    pub fn is_deleted(&self, cid: &Cid) -> bool {
+        let bytes = cid.to_bytes();
+        self.entries
+            .binary_search_by(|e| e.target.as_bytes().cmp(&bytes))
+            .is_ok()
+    }
+
+    /// Merges tombstones from another replica.
+    ///
+    /// Deletion wins over presence: once either side tombstoned an entity,
+    /// the merged set does too.
+    pub fn merge(&self, other: &Tombstones) -> Tombstones {
+        let mut merged = self.clone();
+        for entry in &other.entries {
+            merged.insert(entry.clone());
+        }
+        merged
+    }
+
+    /// Filters out bonds pointing at deleted entities.
+    pub fn retain_live<'a, T: Oxide>(
+        &self,
+        bonds: impl IntoIterator<Item = &'a Bond<T>>,
+    ) -> Vec<&'a Bond<T>> {
+        bonds
+            .into_iter()
+            .filter(|b| !self.is_deleted(&b.cid()))
+            .collect()
+    }
+
+    /// Collects garbage like `Store::gc`, also freeing the values deleted
+    /// before `before_ms` and whatever only they kept alive, even where a
+    /// root still bonds them. Later deletions are left for a later pass, so
+    /// that peers have time to receive the tombstones first.
+    ///
+    /// Bonds to freed values dangle afterwards: readers drop them with
+    /// `retain_live` before loading.
+    pub fn gc<S: Store>(
+        &self,
+        store: &S,
+        roots: &[Cid],
+        before_ms: u64,
+    ) -> Result<GcStats, S::Error> {
+        let expired: Vec<Cid> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.deleted_at_ms < before_ms)
+            .filter_map(Deleted::target)
+            .collect();
+        let mut freed = 0;
+        let mut freed_bytes = 0;
+        for value in store.get_many(&expired)?.into_iter().flatten() {
+            freed += 1;
+            freed_bytes += value.len() as u64;
+        }
+        // Marking doesn't cross missing values, so what only the deleted
+        // values link to is freed by the regular pass
+        store.delete_many(&expired)?;
+        let mut stats = store.gc(roots)?;
+        stats.freed += freed;
+        stats.freed_bytes += freed_bytes;
+        Ok(stats)
+    }
+
+    /// Iterates over all tombstoned CIDs, skipping targets that aren't one.
+    pub fn targets(&self) -> impl Iterator<Item = Cid> + '_ {
+        self.entries.iter().filter_map(Deleted::target)
+    }
+
+    pub fn entries(&self) -> &[Deleted] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl Oxide for Tombstones {
+    fn schema() -> Structure {
+        Structure::record([("entries", Structure::sequence(Deleted::schema()))])
+    }
+
+    fn visit_bonds(&self, _visitor: &mut dyn BondVisitor) {}
+
+    fn map_bonds(&self, _mapper: &mut impl BondMapper) -> Self {
+        self.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tombstones_filter_and_merge() {
+        let a: Bond<String> = Bond::new("a".to_string());
+        let b: Bond<String> = Bond::new("b".to_string());
+        let c: Bond<String> = Bond::new("c".to_string());
+
+        let mut left = Tombstones::new();
+        left.delete(a.cid(), 200);
+        let mut right = Tombstones::new();
+        right.delete(c.cid(), 300);
+        right.delete(a.cid(), 100);
+
+        let merged = left.merge(&right);
+        assert_eq!(merged, right.merge(&left));
+        assert_eq!(merged.compute_cid(), right.merge(&left).compute_cid());
+        assert_eq!(merged.len(), 2);
+        assert_eq!(
+            merged
+                .entries()
+                .iter()
+                .find(|e| e.target() == Some(a.cid()))
+                .unwrap()
+                .deleted_at_ms,
+            100
+        );
+
+        let items = [a, b.clone(), c];
+        let live = merged.retain_live(&items);
+        assert_eq!(live.len(), 1);
+        assert_eq!(live[0].cid(), b.cid());
+    }
+
+    #[test]
+    fn tombstones_roundtrip() {
+        let mut tombstones = Tombstones::new();
+        tombstones.delete(42u64.compute_cid(), 1700000000000);
+
+        let recovered = Tombstones::from_bytes(&tombstones.to_bytes()).unwrap();
+        assert_eq!(recovered, tombstones);
+        assert!(recovered.is_deleted(&42u64.compute_cid()));
+        assert_eq!(recovered.targets().collect::<Vec<_>>(), vec![42u64.compute_cid()]);
+    }
+
+    #[test]
+    fn decoding_sorts_entries_and_tolerates_bad_targets() {
+        let (a, b) = (1u64.compute_cid(), 2u64.compute_cid());
+        let mut expected = Tombstones::new();
+        expected.delete(a, 100);
+        expected.delete(b, 200);
+
+        #[derive(Serialize)]
+        struct Unsorted {
+            entries: Vec<Deleted>,
+        }
+        let mut entries = expected.entries().to_vec();
+        entries.reverse();
+        entries.push(Deleted::new(a, 300));
+        let bytes = serde_ipld_dagcbor::to_vec(&Unsorted { entries }).unwrap();
+        let decoded = Tombstones::from_bytes(&bytes).unwrap();
+        assert_eq!(decoded, expected);
+        assert!(decoded.is_deleted(&a) && decoded.is_deleted(&b));
+
+        let bad = Deleted {
+            target: ByteString(vec![0xff, 0x00]),
+            deleted_at_ms: 0,
+        };
+        assert_eq!(bad.target(), None);
+        let mut tombstones = Tombstones::new();
+        tombstones.insert(bad);
+        assert_eq!(tombstones.targets().count(), 0);
+    }
+
+    #[test]
+    fn gc_frees_expired_deletions_and_what_only_they_hold() {
+        use crate::{MemoryStore, Solvent};
+
+        #[derive(Debug, Clone, Serialize, Deserialize, crate::Oxide)]
+        #[oxide(crate = crate)]
+        struct Entry {
+            name: String,
+            attachment: Bond<String>,
+        }
+
+        #[derive(Debug, Clone, Serialize, Deserialize, crate::Oxide)]
+        #[oxide(crate = crate)]
+        struct List {
+            entries: Vec<Bond<Entry>>,
+        }
+
+        let store = MemoryStore::new();
+        let mut solvent = Solvent::new();
+        let shared = solvent.bond("shared".to_string());
+        let own = solvent.bond("own".to_string());
+        let kept = solvent.bond(Entry {
+            name: "kept".into(),
+            attachment: shared.clone(),
+        });
+        let deleted = solvent.bond(Entry {
+            name: "deleted".into(),
+            attachment: own.clone(),
+        });
+        let list = solvent.add(List {
+            entries: vec![kept.clone(), deleted.clone()],
+        });
+        let (root, _) = solvent.persist_cell(&list, &store).unwrap();
+
+        let mut tombstones = Tombstones::new();
+        tombstones.delete(deleted.cid(), 1000);
+
+        // Too recent: the list still bonds the entry, so nothing is freed
+        let stats = tombstones.gc(&store, &[root], 1000).unwrap();
+        assert_eq!(stats.freed, 0);
+        assert!(store.has(&deleted.cid()).unwrap());
+
+        let stats = tombstones.gc(&store, &[root], 2000).unwrap();
+        assert_eq!(stats.freed, 2);
+        assert!(!store.has(&deleted.cid()).unwrap());
+        assert!(!store.has(&own.cid()).unwrap());
+        for cid in [root, kept.cid(), shared.cid()] {
+            assert!(store.has(&cid).unwrap());
+        }
+        let live = tombstones.retain_live(&list.value().entries);
+        assert_eq!(live.len(), 1);
+        assert_eq!(live[0].cid(), kept.cid());
+    }
+}
@@ -1,12 +1,20 @@
 //! Traversal utilities for schema-aware IPLD exploration.
 //!
 //! Provides low-level functions for parsing DAG-CBOR data and extracting
-//! bond references using schema information.
+//! bond references using schema information, and `Path`s selecting parts
+//! of values by field names and indices.
+
+use std::any::TypeId;
+use std::collections::{HashMap, HashSet};
 
 use cid::Cid;
 use ipld_core::ipld::Ipld;
 
-use crate::{Solvent, Structure};
+use crate::{Bond, BondMapper, Cell, Oxide, Solvent, Structure};
+
+mod path;
+
+pub use path::{Match, Path, PathError, SelectError};
 
 /// Error during IPLD parsing.
 #[derive(Debug, thiserror::Error)]
@@ -18,6 +26,76 @@ pub fn parse_to_ipld(bytes: &[u8]) -> Result<Ipld, ParseError> {
     serde_ipld_dagcbor::from_slice(bytes).map_err(|e| ParseError(e.to_string()))
 }
 
+/// Resolves a `SelfRef` against `frames`, the enclosing records and unions
+/// innermost last, which stand in for the derived types schemas are built
+/// from. Skips `Defaulted` wrappers. Returns `None` if a schema on the way
+/// isn't loaded or a `SelfRef` reaches past the outermost frame.
+pub fn resolve_schema(
+    schema: &Bond<Structure>,
+    frames: &[Bond<Structure>],
+) -> Option<Bond<Structure>> {
+    match schema.value()? {
+        Structure::SelfRef(n) => frames
+            .len()
+            .checked_sub(*n as usize + 1)
+            .map(|i| frames[i].clone()),
+        Structure::Defaulted(inner) => resolve_schema(inner, frames),
+        _ => Some(schema.clone()),
+    }
+}
+
+/// A value found by [`loaded_values`].
+#[derive(Debug, Clone)]
+pub struct LoadedValue {
+    pub cid: Cid,
+    pub schema: Bond<Structure>,
+    /// The value encoded as DAG-CBOR.
+    pub bytes: Vec<u8>,
+}
+
+/// Returns a cell and the values bonded from it that are loaded, each once,
+/// with their schemas.
+pub fn loaded_values<T: Oxide>(cell: &Cell<T>) -> Vec<LoadedValue> {
+    let mut values = LoadedValues::default();
+    values.visit(cell);
+    values.found
+}
+
+#[derive(Default)]
+struct LoadedValues {
+    found: Vec<LoadedValue>,
+    seen: HashSet<Cid>,
+    schemas: HashMap<TypeId, Bond<Structure>>,
+}
+
+impl LoadedValues {
+    fn visit<T: Oxide>(&mut self, cell: &Cell<T>) {
+        if !self.seen.insert(cell.cid()) {
+            return;
+        }
+        let schema = self
+            .schemas
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Bond::new(T::schema()))
+            .clone();
+        self.found.push(LoadedValue {
+            cid: cell.cid(),
+            schema,
+            bytes: cell.value().to_bytes(),
+        });
+        cell.value().map_bonds(self);
+    }
+}
+
+impl BondMapper for LoadedValues {
+    fn map_bond<T: Oxide>(&mut self, bond: Bond<T>) -> Bond<T> {
+        if let Some(cell) = bond.cell() {
+            self.visit(cell);
+        }
+        bond
+    }
+}
+
 /// Extract bond targets from an IPLD value given its schema.
 ///
 /// Appends (value_cid, schema_cid) pairs to `bonds`.
@@ -89,6 +167,11 @@ pub fn collect_bonds(
                 }
             }
         }
+        Structure::Defaulted(inner) => {
+            if let Some(inner_schema) = inner.value() {
+                collect_bonds(value, inner_schema, schemas, bonds);
+            }
+        }
         _ => {}
     }
 }
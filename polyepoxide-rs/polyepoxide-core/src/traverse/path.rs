@@ -0,0 +1,431 @@
+//! Paths selecting parts of a value, such as `items[*].photos[0].content`.
+//!
+//! A path is a chain of segments separated by dots:
+//! - `name` picks a record field, a union variant or a map key
+//! - `[n]` picks the n-th element of a sequence or tuple
+//! - `*` or `[*]` picks every element of a sequence or every map value
+//!
+//! Bonds are followed wherever a segment applies to their target, so
+//! `chapters[*].title` reads the title of each bonded chapter. A path is
+//! checked against a schema when compiled, so it fails before touching any
+//! data if it names a field the type doesn't have.
+
+use std::fmt;
+
+use cid::Cid;
+use ipld_core::ipld::Ipld;
+
+use crate::store::Store;
+use crate::compat::kind;
+use crate::traverse::{parse_to_ipld, resolve_schema, ParseError};
+use crate::{Bond, Structure};
+
+/// Error parsing a path or checking it against a schema.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PathError {
+    #[error("invalid path at byte {position}: {message}")]
+    Syntax { position: usize, message: String },
+    #[error("`{segment}` doesn't apply to {found} at `{at}`")]
+    Mismatch {
+        at: String,
+        segment: String,
+        found: &'static str,
+    },
+    #[error("schema at `{at}` is not loaded")]
+    Unresolved { at: String },
+}
+
+/// Error selecting from stored values.
+#[derive(Debug, thiserror::Error)]
+pub enum SelectError<E> {
+    #[error("store error: {0}")]
+    Store(E),
+    #[error("bonded value {0} is missing")]
+    Missing(Cid),
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Field(String),
+    Index(usize),
+    All,
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Segment::Field(name) => f.write_str(name),
+            Segment::Index(i) => write!(f, "[{}]", i),
+            Segment::All => f.write_str("[*]"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Step {
+    Select(Segment),
+    /// Loads the bonded value the current one links to.
+    Follow,
+}
+
+/// A value found by `Path::select`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    /// Where the value was found, with wildcards replaced by indices and
+    /// keys, e.g. `items[2].photos[0].content`.
+    pub path: String,
+    pub value: Ipld,
+}
+
+/// A path checked against the schema of the values it selects from.
+#[derive(Debug, Clone)]
+pub struct Path {
+    text: String,
+    steps: Vec<Step>,
+    target: Bond<Structure>,
+}
+
+impl Path {
+    /// Parses `text` and checks it against `schema`, whose nested schemas
+    /// must be loaded. The empty path selects the value itself.
+    pub fn compile(text: &str, schema: &Bond<Structure>) -> Result<Self, PathError> {
+        let mut steps = Vec::new();
+        let mut frames = Vec::new();
+        let mut current = schema.clone();
+        let mut at = String::new();
+        for segment in parse(text)? {
+            // Bonds are only followed when a segment reaches into them, so
+            // a path ending on a bond selects the link itself
+            let structure = loop {
+                current = resolve(&current, &frames, &at)?;
+                let structure = current.value().expect("resolved");
+                match structure {
+                    Structure::Bond(target) => {
+                        steps.push(Step::Follow);
+                        current = target.clone();
+                    }
+                    _ => break structure,
+                }
+            };
+            if matches!(structure, Structure::Record(_) | Structure::Tagged(_)) {
+                frames.push(current.clone());
+            }
+            let next = match (&segment, structure) {
+                (Segment::Field(name), Structure::Record(fields) | Structure::Tagged(fields)) => {
+                    fields.get(name)
+                }
+                (
+                    Segment::Field(_) | Segment::All,
+                    Structure::Map { value, .. } | Structure::OrderedMap { value, .. },
+                ) => Some(value),
+                (Segment::Index(_) | Segment::All, Structure::Sequence(inner)) => Some(inner),
+                (Segment::Index(i), Structure::Tuple(elements)) => elements.get(*i),
+                _ => None,
+            };
+            let Some(next) = next else {
+                return Err(PathError::Mismatch {
+                    at,
+                    segment: segment.to_string(),
+                    found: kind(structure),
+                });
+            };
+            current = next.clone();
+            at = join(&at, &segment);
+            steps.push(Step::Select(segment));
+        }
+        let target = resolve(&current, &frames, &at)?;
+        Ok(Self {
+            text: text.to_string(),
+            steps,
+            target,
+        })
+    }
+
+    /// Schema of the selected values.
+    pub fn target(&self) -> &Bond<Structure> {
+        &self.target
+    }
+
+    /// Whether the value reached by `keys` from the root is one this path
+    /// selects. Keys are record fields, union variants and map keys, and
+    /// `[n]` for the n-th element; bonds are crossed without a key.
+    pub fn matches<K: AsRef<str>>(&self, keys: &[K]) -> bool {
+        let mut segments = self.steps.iter().filter_map(|step| match step {
+            Step::Select(segment) => Some(segment),
+            Step::Follow => None,
+        });
+        let mut keys = keys.iter().map(AsRef::as_ref);
+        loop {
+            match (segments.next(), keys.next()) {
+                (None, None) => return true,
+                (Some(Segment::All), Some(_)) => {}
+                (Some(segment), Some(key)) if segment.to_string() == key => {}
+                _ => return false,
+            }
+        }
+    }
+
+    /// Links a selection continuing from `value`, reached after `step` of
+    /// the path's steps, loads: each with the steps taken once it's loaded,
+    /// or `None` for the links below the selected values.
+    pub(crate) fn links_from(&self, value: &Ipld, step: usize) -> Vec<(Cid, Option<usize>)> {
+        let mut links = Vec::new();
+        collect_links(&self.steps, step, value, &mut links);
+        links
+    }
+
+    /// Selects from the value stored under `root`, loading bonded values
+    /// from `store`. Values lacking an optional field, a union variant or an
+    /// index yield no match rather than an error.
+    pub fn select<S: Store>(
+        &self,
+        store: &S,
+        root: &Cid,
+    ) -> Result<Vec<Match>, SelectError<S::Error>> {
+        let mut matches = Vec::new();
+        let value = load(store, root)?;
+        walk(store, &self.steps, &value, String::new(), &mut matches)?;
+        Ok(matches)
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+fn parse(text: &str) -> Result<Vec<Segment>, PathError> {
+    let syntax = |position: usize, message: String| PathError::Syntax { position, message };
+    let mut segments = Vec::new();
+    if text.is_empty() {
+        return Ok(segments);
+    }
+    let mut position = 0;
+    for (i, part) in text.split('.').enumerate() {
+        let (name, mut rest) = part.split_at(part.find('[').unwrap_or(part.len()));
+        match name {
+            "" if i == 0 && !rest.is_empty() => {}
+            "" => return Err(syntax(position, "empty segment".to_string())),
+            "*" => segments.push(Segment::All),
+            _ => segments.push(Segment::Field(name.to_string())),
+        }
+        let mut offset = position + name.len();
+        while !rest.is_empty() {
+            let close = match rest.find(']') {
+                Some(close) if rest.starts_with('[') => close,
+                _ => {
+                    return Err(syntax(
+                        offset,
+                        format!("expected `[index]`, found {:?}", rest),
+                    ))
+                }
+            };
+            segments.push(match &rest[1..close] {
+                "*" => Segment::All,
+                index => Segment::Index(
+                    index
+                        .parse()
+                        .map_err(|_| syntax(offset + 1, format!("invalid index {:?}", index)))?,
+                ),
+            });
+            offset += close + 1;
+            rest = &rest[close + 1..];
+        }
+        position += part.len() + 1;
+    }
+    Ok(segments)
+}
+
+fn resolve(
+    schema: &Bond<Structure>,
+    frames: &[Bond<Structure>],
+    at: &str,
+) -> Result<Bond<Structure>, PathError> {
+    resolve_schema(schema, frames).ok_or_else(|| PathError::Unresolved { at: at.to_string() })
+}
+
+fn join(path: &str, segment: &impl fmt::Display) -> String {
+    let segment = segment.to_string();
+    if path.is_empty() || segment.starts_with('[') {
+        format!("{}{}", path, segment)
+    } else {
+        format!("{}.{}", path, segment)
+    }
+}
+
+/// Pushes the links `steps[step..]` follow from `value`, with the step
+/// after each, and every link below the values the steps end at, with
+/// `None`.
+fn collect_links(steps: &[Step], step: usize, value: &Ipld, links: &mut Vec<(Cid, Option<usize>)>) {
+    let mut stack = vec![(step, value)];
+    while let Some((step, value)) = stack.pop() {
+        let Some(current) = steps.get(step) else {
+            let mut below = vec![value];
+            while let Some(value) = below.pop() {
+                match value {
+                    Ipld::Link(cid) => links.push((*cid, None)),
+                    Ipld::List(items) => below.extend(items.iter().rev()),
+                    Ipld::Map(map) => below.extend(map.values().rev()),
+                    _ => {}
+                }
+            }
+            continue;
+        };
+        match (current, value) {
+            (Step::Follow, Ipld::Link(cid)) => links.push((*cid, Some(step + 1))),
+            (Step::Select(Segment::Field(name)), Ipld::Map(map)) => {
+                stack.extend(map.get(name).map(|field| (step + 1, field)));
+            }
+            (Step::Select(Segment::Index(i)), Ipld::List(items)) => {
+                stack.extend(items.get(*i).map(|item| (step + 1, item)));
+            }
+            (Step::Select(Segment::All), Ipld::List(items)) => {
+                stack.extend(items.iter().rev().map(|item| (step + 1, item)));
+            }
+            (Step::Select(Segment::All), Ipld::Map(map)) => {
+                stack.extend(map.values().rev().map(|item| (step + 1, item)));
+            }
+            _ => {}
+        }
+    }
+}
+
+fn load<S: Store>(store: &S, cid: &Cid) -> Result<Ipld, SelectError<S::Error>> {
+    let bytes = store
+        .get(cid)
+        .map_err(SelectError::Store)?
+        .ok_or(SelectError::Missing(*cid))?;
+    Ok(parse_to_ipld(&bytes)?)
+}
+
+fn walk<S: Store>(
+    store: &S,
+    steps: &[Step],
+    value: &Ipld,
+    path: String,
+    matches: &mut Vec<Match>,
+) -> Result<(), SelectError<S::Error>> {
+    let Some((step, rest)) = steps.split_first() else {
+        matches.push(Match {
+            path,
+            value: value.clone(),
+        });
+        return Ok(());
+    };
+    match (step, value) {
+        (Step::Follow, Ipld::Link(cid)) => walk(store, rest, &load(store, cid)?, path, matches),
+        (Step::Select(Segment::Field(name)), Ipld::Map(map)) => match map.get(name) {
+            Some(field) => walk(store, rest, field, join(&path, name), matches),
+            None => Ok(()),
+        },
+        (Step::Select(Segment::Index(i)), Ipld::List(items)) => match items.get(*i) {
+            Some(item) => walk(store, rest, item, join(&path, &Segment::Index(*i)), matches),
+            None => Ok(()),
+        },
+        (Step::Select(Segment::All), Ipld::List(items)) => {
+            for (i, item) in items.iter().enumerate() {
+                walk(store, rest, item, join(&path, &Segment::Index(i)), matches)?;
+            }
+            Ok(())
+        }
+        (Step::Select(Segment::All), Ipld::Map(map)) => {
+            for (key, item) in map {
+                walk(store, rest, item, join(&path, key), matches)?;
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MemoryStore, Oxide, Solvent};
+
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, Oxide)]
+    #[oxide(crate = crate)]
+    struct Photo {
+        caption: String,
+        previous: Option<Bond<Photo>>,
+    }
+
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, Oxide)]
+    #[oxide(crate = crate)]
+    struct Album {
+        title: String,
+        photos: Vec<Bond<Photo>>,
+    }
+
+    #[test]
+    fn compiles_against_the_schema() {
+        let schema = Bond::new(Album::schema());
+        let path = Path::compile("photos[*].previous[0].caption", &schema).unwrap();
+        assert!(matches!(path.target().value(), Some(Structure::Unicode)));
+
+        assert!(matches!(
+            Path::compile("photos[*].title", &schema),
+            Err(PathError::Mismatch { at, .. }) if at == "photos[*]"
+        ));
+        assert!(matches!(
+            Path::compile("title[0]", &schema),
+            Err(PathError::Mismatch {
+                found: "Unicode",
+                ..
+            })
+        ));
+        assert!(matches!(
+            Path::compile("photos[x]", &schema),
+            Err(PathError::Syntax { position: 7, .. })
+        ));
+        assert!(matches!(
+            Path::compile("photos..caption", &schema),
+            Err(PathError::Syntax { position: 7, .. })
+        ));
+    }
+
+    #[test]
+    fn selects_through_bonds() {
+        let store = MemoryStore::new();
+        let mut solvent = Solvent::new();
+        let first = solvent.bond(Photo {
+            caption: "first".into(),
+            previous: None,
+        });
+        let second = solvent.bond(Photo {
+            caption: "second".into(),
+            previous: Some(first.clone()),
+        });
+        let album = solvent.add(Album {
+            title: "Holiday".into(),
+            photos: vec![first, second],
+        });
+        let (root, _) = solvent.persist_cell(&album, &store).unwrap();
+        let schema = Bond::new(Album::schema());
+
+        let path = Path::compile("photos[*].caption", &schema).unwrap();
+        let matches = path.select(&store, &root).unwrap();
+        let found: Vec<_> = matches
+            .iter()
+            .map(|m| (m.path.as_str(), m.value.clone()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("photos[0].caption", Ipld::String("first".into())),
+                ("photos[1].caption", Ipld::String("second".into())),
+            ]
+        );
+
+        let path = Path::compile("photos[*].previous[*].caption", &schema).unwrap();
+        let matches = path.select(&store, &root).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].path, "photos[1].previous[0].caption");
+        assert!(path.matches(&["photos", "[1]", "previous", "[0]", "caption"]));
+        assert!(!path.matches(&["photos", "[1]", "previous", "[0]"]));
+        assert!(!path.matches(&["photos", "[1]", "caption"]));
+    }
+}
@@ -0,0 +1,126 @@
+//! Store wrapper that checks written bytes against their CIDs.
+//!
+//! Bytes received from peers come with a claimed CID. Storing them unchecked
+//! lets a peer place arbitrary content under a CID others trust.
+
+use cid::Cid;
+
+use crate::async_store::AsyncStore;
+use crate::oxide::compute_cid;
+use crate::store::Batch;
+
+/// Error from a verifying store.
+#[derive(Debug, thiserror::Error)]
+pub enum VerifyError<E> {
+    #[error("block {cid} does not match its content (hashes to {actual})")]
+    Mismatch { cid: Cid, actual: Cid },
+    #[error(transparent)]
+    Inner(E),
+}
+
+/// Recomputes the CID of every value written through it and rejects
+/// mismatches. Reads are passed through.
+///
+/// Borrows the store, so it can wrap a store shared with other users for
+/// the duration of a request.
+pub struct VerifyingStore<'a, S: ?Sized> {
+    inner: &'a S,
+}
+
+impl<'a, S: AsyncStore + ?Sized> VerifyingStore<'a, S> {
+    pub fn new(inner: &'a S) -> Self {
+        Self { inner }
+    }
+
+    pub fn inner(&self) -> &'a S {
+        self.inner
+    }
+}
+
+fn verify<E>(cid: &Cid, value: &[u8]) -> Result<(), VerifyError<E>> {
+    let actual = compute_cid(value);
+    if actual != *cid {
+        return Err(VerifyError::Mismatch { cid: *cid, actual });
+    }
+    Ok(())
+}
+
+impl<S: AsyncStore + ?Sized> AsyncStore for VerifyingStore<'_, S> {
+    type Error = VerifyError<S::Error>;
+
+    async fn async_get(&self, cid: &Cid) -> Result<Option<Vec<u8>>, Self::Error> {
+        self.inner.async_get(cid).await.map_err(VerifyError::Inner)
+    }
+
+    async fn async_put(&self, cid: &Cid, value: &[u8]) -> Result<(), Self::Error> {
+        verify(cid, value)?;
+        self.inner.async_put(cid, value).await.map_err(VerifyError::Inner)
+    }
+
+    async fn async_put_schema(&self, cid: &Cid, value: &[u8]) -> Result<(), Self::Error> {
+        verify(cid, value)?;
+        self.inner.async_put_schema(cid, value).await.map_err(VerifyError::Inner)
+    }
+
+    async fn async_has(&self, cid: &Cid) -> Result<bool, Self::Error> {
+        self.inner.async_has(cid).await.map_err(VerifyError::Inner)
+    }
+
+    async fn async_get_many(&self, cids: &[Cid]) -> Result<Vec<Option<Vec<u8>>>, Self::Error> {
+        self.inner.async_get_many(cids).await.map_err(VerifyError::Inner)
+    }
+
+    async fn async_put_many(&self, nodes: &[(&Cid, &[u8])]) -> Result<(), Self::Error> {
+        // Check the whole batch first, so a forged block stores nothing
+        for (cid, value) in nodes {
+            verify(cid, value)?;
+        }
+        self.inner.async_put_many(nodes).await.map_err(VerifyError::Inner)
+    }
+
+    async fn async_write_batch(&self, batch: &Batch) -> Result<(), Self::Error> {
+        for (cid, value) in batch.schemas().chain(batch.values()) {
+            verify(cid, value)?;
+        }
+        self.inner.async_write_batch(batch).await.map_err(VerifyError::Inner)
+    }
+
+    async fn async_delete(&self, cid: &Cid) -> Result<(), Self::Error> {
+        self.inner.async_delete(cid).await.map_err(VerifyError::Inner)
+    }
+
+    async fn async_delete_many(&self, cids: &[Cid]) -> Result<(), Self::Error> {
+        self.inner.async_delete_many(cids).await.map_err(VerifyError::Inner)
+    }
+
+    async fn async_has_many(&self, cids: &[Cid]) -> Result<Vec<bool>, Self::Error> {
+        self.inner.async_has_many(cids).await.map_err(VerifyError::Inner)
+    }
+
+    async fn async_list_cids(&self) -> Result<Vec<Cid>, Self::Error> {
+        self.inner.async_list_cids().await.map_err(VerifyError::Inner)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MemoryStore, Store};
+
+    #[tokio::test]
+    async fn rejects_mismatched_batch() {
+        let store = MemoryStore::new();
+        let verifying = VerifyingStore::new(&store);
+        let good = compute_cid(b"good");
+        let forged = compute_cid(b"original");
+
+        let result = verifying
+            .async_put_many(&[(&good, &b"good"[..]), (&forged, &b"forged"[..])])
+            .await;
+        assert!(matches!(result, Err(VerifyError::Mismatch { cid, .. }) if cid == forged));
+        assert!(!store.has(&good).unwrap());
+
+        verifying.async_put(&good, b"good").await.unwrap();
+        assert!(store.has(&good).unwrap());
+    }
+}
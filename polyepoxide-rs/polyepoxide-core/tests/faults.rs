@@ -0,0 +1,80 @@
+//! Sync under injected store faults.
+
+use polyepoxide_core::{
+    oxide, pull, Bond, Cid, FaultyStore, MemoryStore, Oxide, Solvent, Store, SyncError,
+};
+
+#[oxide]
+struct Entry {
+    text: String,
+    previous: Option<Bond<Entry>>,
+}
+
+/// Persists a chain of entries and returns the head's value and schema CIDs.
+fn build_chain(store: &MemoryStore, len: usize) -> (Cid, Cid) {
+    let mut solvent = Solvent::new();
+    let mut previous = None;
+    for i in 0..len {
+        previous = Some(solvent.bond(Entry {
+            text: format!("entry {}", i),
+            previous,
+        }));
+    }
+    let head = solvent.add(Entry {
+        text: "head".to_string(),
+        previous,
+    });
+    solvent.persist_cell(&head, store).unwrap()
+}
+
+#[tokio::test]
+async fn pull_resumes_after_source_failure() {
+    let origin = MemoryStore::new();
+    let (value_cid, schema_cid) = build_chain(&origin, 20);
+
+    let source = FaultyStore::new(&origin, 1).with_fail_after(15);
+    let dest = MemoryStore::new();
+
+    let err = pull(&source, &dest, value_cid, schema_cid).await.unwrap_err();
+    assert!(matches!(err, SyncError::Source(_)));
+    assert!(!dest.has(&value_cid).unwrap());
+
+    // Everything stored before the failure is kept; the retry fetches the rest
+    source.heal();
+    let report = pull(&source, &dest, value_cid, schema_cid).await.unwrap();
+    assert!(!report.transferred.is_empty());
+    assert!(dest.has(&value_cid).unwrap());
+    let report = pull(&source, &dest, value_cid, schema_cid).await.unwrap();
+    assert!(report.transferred.is_empty());
+}
+
+#[tokio::test]
+async fn pull_retries_through_random_errors() {
+    let origin = MemoryStore::new();
+    let (value_cid, schema_cid) = build_chain(&origin, 10);
+
+    let source = FaultyStore::new(&origin, 7).with_get_error_rate(0.2);
+    let dest = FaultyStore::new(MemoryStore::new(), 8).with_put_error_rate(0.2);
+
+    let mut attempts = 0;
+    while pull(&source, &dest, value_cid, schema_cid).await.is_err() {
+        attempts += 1;
+        assert!(attempts < 100, "pull never completed");
+    }
+    assert!(dest.inner().has(&value_cid).unwrap());
+    assert!(source.stats().errors + dest.stats().errors > 0);
+}
+
+#[tokio::test]
+async fn pull_rejects_corrupted_payloads() {
+    let origin = MemoryStore::new();
+    let (value_cid, schema_cid) = build_chain(&origin, 5);
+
+    let source = FaultyStore::new(&origin, 3).with_corruption_rate(1.0);
+    let dest = MemoryStore::new();
+
+    let err = pull(&source, &dest, value_cid, schema_cid).await.unwrap_err();
+    assert!(matches!(err, SyncError::Corrupted(_)));
+    assert!(source.stats().corruptions > 0);
+    assert!(!dest.has(&value_cid).unwrap());
+}
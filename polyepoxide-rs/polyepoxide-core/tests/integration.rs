@@ -1,5 +1,6 @@
 //! Integration tests demonstrating nested structures with bonds.
 
+use polyepoxide_core::traverse::resolve_schema;
 use polyepoxide_core::{oxide, Bond, BondVisitor, Cid, Oxide, Solvent, Structure};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
@@ -303,6 +304,127 @@ fn oxide_result_lowercase_encoding() {
     assert_eq!(recovered2, v2);
 }
 
+/// Test that #[oxide] encodes 128-bit integer fields as bytes
+#[oxide]
+#[derive(PartialEq)]
+struct WithWideFields {
+    balance: u128,
+    delta: i128,
+}
+
+#[test]
+fn oxide_wide_int_encoding() {
+    let v = WithWideFields {
+        balance: u128::MAX,
+        delta: -1,
+    };
+    let recovered: WithWideFields = Oxide::from_bytes(&v.to_bytes()).unwrap();
+    assert_eq!(recovered, v);
+
+    let Structure::Record(fields) = WithWideFields::schema() else {
+        panic!("Expected Record");
+    };
+    assert!(matches!(
+        fields["balance"].value(),
+        Some(Structure::Int(polyepoxide_core::IntType::U128))
+    ));
+}
+
+/// Test that #[oxide(default)] fields can be added without breaking old data
+#[oxide]
+#[derive(PartialEq)]
+struct WithAddedFields {
+    name: String,
+    #[oxide(default)]
+    nickname: Option<String>,
+    #[oxide(default)]
+    visits: u32,
+}
+
+#[test]
+fn oxide_default_reads_older_data() {
+    let old = WithOptionalField {
+        name: "Ada".to_string(),
+        count: Some(3),
+    };
+    let recovered: WithAddedFields = Oxide::from_bytes(&old.to_bytes()).unwrap();
+    assert_eq!(recovered.name, "Ada");
+    assert_eq!(recovered.nickname, None);
+    assert_eq!(recovered.visits, 0);
+
+    let Structure::Record(fields) = WithAddedFields::schema() else {
+        panic!("Expected Record");
+    };
+    assert!(matches!(fields["visits"].value(), Some(Structure::Defaulted(_))));
+    let old_schema = Structure::record([("name", Structure::Unicode)]);
+    assert!(old_schema.is_compatible_with(&WithAddedFields::schema()).is_compatible());
+}
+
+/// Test that plain Option fields can't be added: older data lacks them
+#[test]
+fn oxide_added_option_rejects_older_data() {
+    let old_schema = Structure::record([("name", Structure::Unicode)]);
+    let report = old_schema.is_compatible_with(&WithOptionalField::schema());
+    assert_eq!(report.incompatibilities.len(), 1);
+    assert_eq!(report.incompatibilities[0].path, "count");
+
+    let old = WithAddedFields {
+        name: "Ada".to_string(),
+        nickname: None,
+        visits: 0,
+    };
+    assert!(WithOptionalField::from_bytes(&old.to_bytes()).is_err());
+}
+
+/// Test that #[oxide(encrypt)] fields are stored sealed
+#[oxide]
+#[derive(PartialEq)]
+struct WithEncryptedFields {
+    name: String,
+    #[oxide(encrypt)]
+    price: u64,
+    #[oxide(encrypt)]
+    note: Option<String>,
+}
+
+#[test]
+fn oxide_encrypted_fields() {
+    use polyepoxide_core::{EncryptionKey, MemoryStore, Sealed, Store};
+
+    let key = EncryptionKey::derive("polyepoxide tests", b"device secret");
+    let v = WithEncryptedFields {
+        name: "lamp".to_string(),
+        price: Sealed::seal(&4200, &key),
+        note: Sealed::seal(&Some("gift from Ada".to_string()), &key),
+    };
+
+    // Structural fields stay readable, sealed ones are byte strings
+    let Structure::Record(fields) = WithEncryptedFields::schema() else {
+        panic!("Expected Record");
+    };
+    assert!(matches!(fields["name"].value(), Some(Structure::Unicode)));
+    assert!(matches!(fields["price"].value(), Some(Structure::ByteString)));
+    assert!(matches!(fields["note"].value(), Some(Structure::ByteString)));
+
+    let mut solvent = Solvent::new();
+    let store = MemoryStore::new();
+    let cell = solvent.add(v.clone());
+    solvent.persist_cell(&cell, &store).unwrap();
+    let bytes = store.get(&cell.cid()).unwrap().unwrap();
+    assert!(bytes.windows(4).any(|w| w == b"lamp"));
+    assert!(!bytes.windows(13).any(|w| w == b"gift from Ada"));
+
+    let loaded = Solvent::new()
+        .load::<WithEncryptedFields, _>(&cell.cid(), &store, usize::MAX)
+        .unwrap();
+    assert_eq!(*loaded.value(), v);
+    assert_eq!(loaded.value().price.open(&key).unwrap(), 4200);
+    assert_eq!(
+        loaded.value().note.open(&key).unwrap().as_deref(),
+        Some("gift from Ada")
+    );
+}
+
 /// Test C-style enum (all unit variants)
 #[derive(Debug, Clone, Serialize, Deserialize, Oxide, PartialEq)]
 enum Color {
@@ -456,3 +578,145 @@ fn generic_struct() {
     let recovered: Wrapper<String> = Oxide::from_bytes(&bytes).unwrap();
     assert_eq!(recovered.inner, "hello");
 }
+
+/// Test generic struct with several parameters and an explicit bound
+#[derive(Debug, Clone, Serialize, Deserialize, Oxide)]
+#[serde(bound = "K: Oxide, V: Oxide")]
+#[oxide(bound = "K: Oxide, V: Oxide")]
+struct Index<K, V, Tag> {
+    key: K,
+    target: Bond<V>,
+    #[oxide(skip)]
+    #[serde(skip)]
+    tag: std::marker::PhantomData<Tag>,
+}
+
+#[test]
+fn generic_struct_with_bound() {
+    // Tag is not an oxide; the explicit bound doesn't require it
+    type Entry = Index<String, u64, std::time::Instant>;
+
+    let Structure::Record(fields) = Entry::schema() else {
+        panic!("Expected Record schema");
+    };
+    assert_eq!(fields.keys().collect::<Vec<_>>(), ["key", "target"]);
+
+    let entry = Entry {
+        key: "answer".to_string(),
+        target: Bond::new(42),
+        tag: std::marker::PhantomData,
+    };
+    let recovered: Entry = Oxide::from_bytes(&entry.to_bytes()).unwrap();
+    assert_eq!(recovered.key, "answer");
+    assert_eq!(recovered.target.cid(), entry.target.cid());
+}
+
+/// Mutually recursive types: Folder -> Entry -> Folder
+#[derive(Debug, Clone, Serialize, Deserialize, Oxide)]
+struct Folder {
+    name: String,
+    entries: Vec<Entry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Oxide)]
+enum Entry {
+    File(String),
+    Folder(Bond<Folder>),
+}
+
+#[test]
+fn mutually_recursive_schema() {
+    let Structure::Record(fields) = Folder::schema() else {
+        panic!("Expected Record schema");
+    };
+    let Some(Structure::Sequence(entry)) = fields["entries"].value() else {
+        panic!("Expected Sequence");
+    };
+    let Some(Structure::Tagged(variants)) = entry.value() else {
+        panic!("Expected Tagged");
+    };
+    let Some(Structure::Bond(folder)) = variants["Folder"].value() else {
+        panic!("Expected Bond");
+    };
+    // Folder is one type above Entry
+    assert!(matches!(folder.value(), Some(Structure::SelfRef(1))));
+
+    let mut solvent = Solvent::new();
+    let inner = solvent.bond(Folder {
+        name: "docs".to_string(),
+        entries: vec![Entry::File("readme".to_string())],
+    });
+    let root = Folder {
+        name: "root".to_string(),
+        entries: vec![Entry::Folder(inner)],
+    };
+    let recovered: Folder = Oxide::from_bytes(&root.to_bytes()).unwrap();
+    assert_eq!(recovered.entries.len(), 1);
+}
+
+/// Recursion through a tuple struct, which adds no record or union
+#[derive(Debug, Clone, Serialize, Deserialize, Oxide)]
+struct Tree {
+    label: String,
+    children: Children,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Oxide)]
+struct Children(Vec<Bond<Tree>>);
+
+#[test]
+fn recursion_through_tuple_struct() {
+    let schema = Bond::new(Tree::schema());
+    let Some(Structure::Record(fields)) = schema.value() else {
+        panic!("Expected Record schema");
+    };
+    let Some(Structure::Tuple(elements)) = fields["children"].value() else {
+        panic!("Expected Tuple");
+    };
+    let Some(Structure::Sequence(child)) = elements[0].value() else {
+        panic!("Expected Sequence");
+    };
+    let Some(Structure::Bond(tree)) = child.value() else {
+        panic!("Expected Bond");
+    };
+    // Tree's record is the only frame in between
+    assert!(matches!(tree.value(), Some(Structure::SelfRef(0))));
+    let resolved = resolve_schema(tree, std::slice::from_ref(&schema)).unwrap();
+    assert_eq!(resolved.cid(), schema.cid());
+}
+
+/// Recursion through a struct-like variant, whose record is a frame of its own
+#[derive(Debug, Clone, Serialize, Deserialize, Oxide)]
+enum Expr {
+    Literal(i64),
+    Add { left: Bond<Expr>, right: Bond<Expr> },
+}
+
+#[test]
+fn recursion_through_struct_variant() {
+    let schema = Bond::new(Expr::schema());
+    let Some(Structure::Tagged(variants)) = schema.value() else {
+        panic!("Expected Tagged schema");
+    };
+    let add = variants["Add"].clone();
+    let Some(Structure::Record(fields)) = add.value() else {
+        panic!("Expected Record");
+    };
+    let Some(Structure::Bond(left)) = fields["left"].value() else {
+        panic!("Expected Bond");
+    };
+    // Expr is one frame above the variant's record
+    assert!(matches!(left.value(), Some(Structure::SelfRef(1))));
+    let resolved = resolve_schema(left, &[schema.clone(), add]).unwrap();
+    assert_eq!(resolved.cid(), schema.cid());
+}
+
+/// A recursive type that isn't a record or union has no frame to refer to
+#[derive(Debug, Clone, Serialize, Deserialize, Oxide)]
+struct Chain(Option<Bond<Chain>>);
+
+#[test]
+#[should_panic(expected = "isn't a record or tagged union")]
+fn recursive_tuple_struct_has_no_schema() {
+    Chain::schema();
+}
@@ -1,32 +1,51 @@
 use proc_macro::TokenStream;
-use quote::quote;
+use quote::{quote, quote_spanned};
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
 use syn::{parse_macro_input, DeriveInput};
 
 mod schema;
 
-/// Parse the crate path from #[oxide(crate = path)] attribute.
-fn parse_crate_path(input: &DeriveInput) -> proc_macro2::TokenStream {
+/// Container-level `#[oxide(...)]` attributes.
+struct ContainerAttrs {
+    crate_path: proc_macro2::TokenStream,
+    /// Replaces the inferred `T: Oxide` bounds when set.
+    bound: Option<Vec<syn::WherePredicate>>,
+}
+
+/// Parse `#[oxide(crate = path)]` and `#[oxide(bound = "...")]`.
+fn parse_container_attrs(input: &DeriveInput) -> syn::Result<ContainerAttrs> {
+    let mut crate_path: Option<syn::Path> = None;
+    let mut bound = None;
+
     for attr in &input.attrs {
         if !attr.path().is_ident("oxide") {
             continue;
         }
 
-        let mut crate_path: Option<syn::Path> = None;
-        let _ = attr.parse_nested_meta(|meta| {
+        attr.parse_nested_meta(|meta| {
             if meta.path.is_ident("crate") {
-                let value = meta.value()?;
-                crate_path = Some(value.parse()?);
+                crate_path = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("bound") {
+                let value: syn::LitStr = meta.value()?.parse()?;
+                let predicates = value
+                    .parse_with(Punctuated::<syn::WherePredicate, syn::Token![,]>::parse_terminated)?;
+                bound = Some(predicates.into_iter().collect());
+            } else {
+                return Err(meta.error("unknown oxide attribute"));
             }
             Ok(())
-        });
-
-        if let Some(path) = crate_path {
-            return quote! { #path };
-        }
+        })?;
     }
 
-    // Default to ::polyepoxide_core
-    quote! { ::polyepoxide_core }
+    Ok(ContainerAttrs {
+        // Default to ::polyepoxide_core
+        crate_path: match crate_path {
+            Some(path) => quote! { #path },
+            None => quote! { ::polyepoxide_core },
+        },
+        bound,
+    })
 }
 
 /// Attribute macro that derives all required traits for Oxide types.
@@ -39,6 +58,12 @@ fn parse_crate_path(input: &DeriveInput) -> proc_macro2::TokenStream {
 /// Additionally, it adds serde attributes to ensure correct CBOR encoding:
 /// - `Option<T>` fields get `#[serde(with = "polyepoxide_core::serde_helpers::option_as_array")]`
 /// - `Result<T, E>` fields get `#[serde(with = "polyepoxide_core::serde_helpers::result_lowercase")]`
+/// - `u128` and `i128` fields get `#[serde(with = "polyepoxide_core::serde_helpers::u128_as_bytes")]`
+///   or `i128_as_bytes`
+/// - `#[oxide(default)]` fields get `#[serde(default)]`
+///
+/// `#[oxide(encrypt)]` fields of type `T` become `polyepoxide_core::Sealed<T>`,
+/// stored encrypted (`encrypt` feature of polyepoxide-core).
 ///
 /// # Example
 ///
@@ -104,28 +129,62 @@ fn add_serde_attrs_to_fields(fields: &mut syn::Fields) {
 }
 
 fn add_serde_attr_to_field(field: &mut syn::Field) {
+    if parse_field_attrs(&field.attrs).encrypt && !is_sealed(&field.ty) {
+        let ty = &field.ty;
+        field.ty = syn::parse_quote! { ::polyepoxide_core::Sealed<#ty> };
+    }
     if let Some(serde_with) = get_serde_with_for_type(&field.ty) {
-        // Check if field already has a serde(with) attribute
-        let has_serde_with = field.attrs.iter().any(|attr| {
-            if !attr.path().is_ident("serde") {
-                return false;
-            }
-            let mut has_with = false;
-            let _ = attr.parse_nested_meta(|meta| {
-                if meta.path.is_ident("with") {
-                    has_with = true;
-                }
-                Ok(())
-            });
-            has_with
-        });
-
-        if !has_serde_with {
+        if !has_serde_attr(field, "with") {
             field.attrs.push(syn::parse_quote! {
                 #[serde(with = #serde_with)]
             });
         }
     }
+    if parse_field_attrs(&field.attrs).default && !has_serde_attr(field, "default") {
+        field.attrs.push(syn::parse_quote! { #[serde(default)] });
+    }
+}
+
+/// Whether the field already has a `#[serde(name ...)]` attribute.
+fn has_serde_attr(field: &syn::Field, name: &str) -> bool {
+    field.attrs.iter().any(|attr| {
+        if !attr.path().is_ident("serde") {
+            return false;
+        }
+        let mut found = false;
+        let _ = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident(name) {
+                found = true;
+            }
+            Ok(())
+        });
+        found
+    })
+}
+
+/// Whether `ty` is a `Sealed<T>`, as `#[oxide(encrypt)]` fields must be.
+fn is_sealed(ty: &syn::Type) -> bool {
+    matches!(ty, syn::Type::Path(type_path)
+        if type_path.path.segments.last().is_some_and(|s| s.ident == "Sealed"))
+}
+
+/// Fails on `#[oxide(encrypt)]` fields the `#[oxide]` attribute didn't wrap,
+/// which would otherwise be stored in plaintext.
+fn check_encrypted_fields(input: &DeriveInput) -> syn::Result<()> {
+    let fields: Vec<&syn::Field> = match &input.data {
+        syn::Data::Struct(data) => data.fields.iter().collect(),
+        syn::Data::Enum(data) => data.variants.iter().flat_map(|v| &v.fields).collect(),
+        syn::Data::Union(_) => Vec::new(),
+    };
+    for field in fields {
+        if parse_field_attrs(&field.attrs).encrypt && !is_sealed(&field.ty) {
+            return Err(syn::Error::new_spanned(
+                &field.ty,
+                "`#[oxide(encrypt)]` fields must be `Sealed<T>`; use the `#[oxide]` attribute to wrap them",
+            ));
+        }
+    }
+    Ok(())
 }
 
 /// Returns the serde "with" module path for types needing special encoding.
@@ -135,6 +194,8 @@ fn get_serde_with_for_type(ty: &syn::Type) -> Option<&'static str> {
             match segment.ident.to_string().as_str() {
                 "Option" => return Some("::polyepoxide_core::serde_helpers::option_as_array"),
                 "Result" => return Some("::polyepoxide_core::serde_helpers::result_lowercase"),
+                "u128" => return Some("::polyepoxide_core::serde_helpers::u128_as_bytes"),
+                "i128" => return Some("::polyepoxide_core::serde_helpers::i128_as_bytes"),
                 _ => {}
             }
         }
@@ -163,6 +224,14 @@ fn get_serde_with_for_type(ty: &syn::Type) -> Option<&'static str> {
 ///
 /// - `#[oxide(skip)]` - Skip this field in schema/visit/map (field must impl Default)
 /// - `#[oxide(rename = "name")]` - Use custom name in schema
+/// - `#[oxide(crate = path)]` - Path to polyepoxide_core (on the type)
+/// - `#[oxide(bound = "T: Oxide")]` - Where-predicates replacing the inferred
+///   `T: Oxide` bound on every type parameter (on the type)
+/// - `#[oxide(default)]` - Field may be missing from older data; its schema is
+///   wrapped in `Structure::Defaulted`. Needs `#[serde(default)]`, which the
+///   `#[oxide]` attribute adds
+/// - `#[oxide(encrypt)]` - Field is stored encrypted. Its type must be
+///   `Sealed<T>`, which the `#[oxide]` attribute substitutes for `T`
 #[proc_macro_derive(Oxide, attributes(oxide))]
 pub fn derive_oxide(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
@@ -176,19 +245,25 @@ pub fn derive_oxide(input: TokenStream) -> TokenStream {
 fn derive_oxide_impl(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
     let name = &input.ident;
     let generics = &input.generics;
-    let crate_path = parse_crate_path(input);
+    let ContainerAttrs { crate_path, bound } = parse_container_attrs(input)?;
+    check_encrypted_fields(input)?;
 
     let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
 
     // Build where clause with Oxide bounds for type parameters
-    let where_clause = build_where_clause(generics, where_clause, &crate_path);
+    let where_clause =
+        build_where_clause(name, generics, where_clause, bound.as_deref(), &crate_path);
 
     let schema_impl = schema::generate_schema(input, &crate_path)?;
     let visit_bonds_impl = generate_visit_bonds(input, &crate_path)?;
     let map_bonds_impl = generate_map_bonds(input, &crate_path)?;
 
+    // Spanned at the type name so unmet supertraits (missing serde derives)
+    // are reported there rather than at the derive attribute
+    let oxide_trait = quote_spanned! {name.span()=> #crate_path::Oxide };
+
     Ok(quote! {
-        impl #impl_generics #crate_path::Oxide for #name #ty_generics #where_clause {
+        impl #impl_generics #oxide_trait for #name #ty_generics #where_clause {
             #schema_impl
             #visit_bonds_impl
             #map_bonds_impl
@@ -197,28 +272,48 @@ fn derive_oxide_impl(input: &DeriveInput) -> syn::Result<proc_macro2::TokenStrea
 }
 
 fn build_where_clause(
+    name: &syn::Ident,
     generics: &syn::Generics,
     existing: Option<&syn::WhereClause>,
+    bound: Option<&[syn::WherePredicate]>,
     crate_path: &proc_macro2::TokenStream,
 ) -> proc_macro2::TokenStream {
     let type_params: Vec<_> = generics.type_params().map(|p| &p.ident).collect();
 
-    if type_params.is_empty() && existing.is_none() {
+    if type_params.is_empty() && existing.is_none() && bound.is_none() {
         return quote! {};
     }
 
-    let oxide_bounds = type_params.iter().map(|p| {
-        quote! { #p: #crate_path::Oxide }
-    });
+    let oxide_bounds: Vec<_> = match bound {
+        Some(predicates) => predicates.iter().map(|p| quote! { #p }).collect(),
+        None => type_params
+            .iter()
+            .map(|p| quote_spanned! {p.span()=> #p: #crate_path::Oxide })
+            .collect(),
+    };
 
     let existing_predicates = existing.map(|w| {
         let predicates = &w.predicates;
         quote! { #predicates, }
     }).unwrap_or_default();
 
+    // The supertraits, so parameters the bound leaves out (e.g. phantom ones)
+    // only need what the type's own derives require. This also satisfies the
+    // supertraits of a generic type missing a serde derive, which is then
+    // only reported where the type is used as an oxide
+    let serde_bound = quote_spanned! {name.span()=>
+        Self: ::std::fmt::Debug
+            + ::std::clone::Clone
+            + ::serde::Serialize
+            + ::serde::de::DeserializeOwned
+            + ::std::marker::Send
+            + ::std::marker::Sync
+            + 'static
+    };
+
     quote! {
         where
-            Self: ::serde::Serialize + ::serde::de::DeserializeOwned,
+            #serde_bound,
             #existing_predicates
             #(#oxide_bounds),*
     }
@@ -228,12 +323,12 @@ fn generate_visit_bonds(input: &DeriveInput, crate_path: &proc_macro2::TokenStre
     match &input.data {
         syn::Data::Struct(data) => generate_visit_bonds_struct(data, crate_path),
         syn::Data::Enum(data) => generate_visit_bonds_enum(data, crate_path),
-        syn::Data::Union(_) => Err(syn::Error::new_spanned(input, "Oxide cannot be derived for unions")),
+        syn::Data::Union(data) => Err(syn::Error::new_spanned(data.union_token, "Oxide cannot be derived for unions")),
     }
 }
 
 fn generate_visit_bonds_struct(data: &syn::DataStruct, crate_path: &proc_macro2::TokenStream) -> syn::Result<proc_macro2::TokenStream> {
-    let visits = generate_field_visits(&data.fields, quote! { self })?;
+    let visits = generate_field_visits(&data.fields, quote! { self }, crate_path)?;
 
     Ok(quote! {
         fn visit_bonds(&self, visitor: &mut dyn #crate_path::BondVisitor) {
@@ -259,7 +354,7 @@ fn generate_visit_bonds_enum(data: &syn::DataEnum, crate_path: &proc_macro2::Tok
                         let attrs = parse_field_attrs(&f.attrs);
                         if attrs.skip { return None; }
                         let ident = f.ident.as_ref()?;
-                        Some(quote! { #ident.visit_bonds(visitor); })
+                        Some(visit_field(&f.ty, quote! { #ident }, crate_path))
                     })
                     .collect();
                 quote! {
@@ -277,7 +372,7 @@ fn generate_visit_bonds_enum(data: &syn::DataEnum, crate_path: &proc_macro2::Tok
                         let attrs = parse_field_attrs(&f.attrs);
                         if attrs.skip { return None; }
                         let binding = quote::format_ident!("f{}", i);
-                        Some(quote! { #binding.visit_bonds(visitor); })
+                        Some(visit_field(&f.ty, quote! { #binding }, crate_path))
                     })
                     .collect();
                 quote! {
@@ -301,6 +396,7 @@ fn generate_visit_bonds_enum(data: &syn::DataEnum, crate_path: &proc_macro2::Tok
 fn generate_field_visits(
     fields: &syn::Fields,
     prefix: proc_macro2::TokenStream,
+    crate_path: &proc_macro2::TokenStream,
 ) -> syn::Result<proc_macro2::TokenStream> {
     match fields {
         syn::Fields::Named(named) => {
@@ -309,7 +405,7 @@ fn generate_field_visits(
                     let attrs = parse_field_attrs(&f.attrs);
                     if attrs.skip { return None; }
                     let ident = f.ident.as_ref()?;
-                    Some(quote! { #prefix.#ident.visit_bonds(visitor); })
+                    Some(visit_field(&f.ty, quote! { &#prefix.#ident }, crate_path))
                 })
                 .collect();
             Ok(quote! { #(#visits)* })
@@ -320,7 +416,7 @@ fn generate_field_visits(
                     let attrs = parse_field_attrs(&f.attrs);
                     if attrs.skip { return None; }
                     let idx = syn::Index::from(i);
-                    Some(quote! { #prefix.#idx.visit_bonds(visitor); })
+                    Some(visit_field(&f.ty, quote! { &#prefix.#idx }, crate_path))
                 })
                 .collect();
             Ok(quote! { #(#visits)* })
@@ -333,7 +429,7 @@ fn generate_map_bonds(input: &DeriveInput, crate_path: &proc_macro2::TokenStream
     match &input.data {
         syn::Data::Struct(data) => generate_map_bonds_struct(&input.ident, data, crate_path),
         syn::Data::Enum(data) => generate_map_bonds_enum(data, crate_path),
-        syn::Data::Union(_) => Err(syn::Error::new_spanned(input, "Oxide cannot be derived for unions")),
+        syn::Data::Union(data) => Err(syn::Error::new_spanned(data.union_token, "Oxide cannot be derived for unions")),
     }
 }
 
@@ -342,7 +438,7 @@ fn generate_map_bonds_struct(
     data: &syn::DataStruct,
     crate_path: &proc_macro2::TokenStream,
 ) -> syn::Result<proc_macro2::TokenStream> {
-    let construction = generate_field_mappings(name, &data.fields)?;
+    let construction = generate_field_mappings(name, &data.fields, crate_path)?;
 
     Ok(quote! {
         fn map_bonds(&self, mapper: &mut impl #crate_path::BondMapper) -> Self {
@@ -370,7 +466,7 @@ fn generate_map_bonds_enum(data: &syn::DataEnum, crate_path: &proc_macro2::Token
                         let mapping = if attrs.skip {
                             quote! { ::std::default::Default::default() }
                         } else {
-                            quote! { #ident.map_bonds(mapper) }
+                            map_field(&f.ty, quote! { #ident }, crate_path)
                         };
                         Some(quote! { #ident: #mapping })
                     })
@@ -390,7 +486,7 @@ fn generate_map_bonds_enum(data: &syn::DataEnum, crate_path: &proc_macro2::Token
                         if attrs.skip {
                             quote! { ::std::default::Default::default() }
                         } else {
-                            quote! { #binding.map_bonds(mapper) }
+                            map_field(&f.ty, quote! { #binding }, crate_path)
                         }
                     })
                     .collect();
@@ -413,6 +509,7 @@ fn generate_map_bonds_enum(data: &syn::DataEnum, crate_path: &proc_macro2::Token
 fn generate_field_mappings(
     name: &syn::Ident,
     fields: &syn::Fields,
+    crate_path: &proc_macro2::TokenStream,
 ) -> syn::Result<proc_macro2::TokenStream> {
     match fields {
         syn::Fields::Named(named) => {
@@ -423,7 +520,7 @@ fn generate_field_mappings(
                     let mapping = if attrs.skip {
                         quote! { ::std::default::Default::default() }
                     } else {
-                        quote! { self.#ident.map_bonds(mapper) }
+                        map_field(&f.ty, quote! { &self.#ident }, crate_path)
                     };
                     Some(quote! { #ident: #mapping })
                 })
@@ -438,7 +535,7 @@ fn generate_field_mappings(
                     if attrs.skip {
                         quote! { ::std::default::Default::default() }
                     } else {
-                        quote! { self.#idx.map_bonds(mapper) }
+                        map_field(&f.ty, quote! { &self.#idx }, crate_path)
                     }
                 })
                 .collect();
@@ -448,9 +545,52 @@ fn generate_field_mappings(
     }
 }
 
+/// Statement visiting the bonds of `value`, a reference to a field of type `ty`.
+///
+/// Calls go through the fully qualified trait path, spanned to the field type,
+/// so a type that doesn't implement Oxide is reported at the field.
+fn visit_field(
+    ty: &syn::Type,
+    value: proc_macro2::TokenStream,
+    crate_path: &proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    if schema::is_wide_int(ty) {
+        return quote! {};
+    }
+    match schema::boxed_inner(ty) {
+        Some(inner) => quote_spanned! {inner.span()=>
+            <#inner as #crate_path::Oxide>::visit_bonds(&**#value, visitor);
+        },
+        None => quote_spanned! {ty.span()=>
+            <#ty as #crate_path::Oxide>::visit_bonds(#value, visitor);
+        },
+    }
+}
+
+/// Expression mapping the bonds of `value`, a reference to a field of type `ty`.
+fn map_field(
+    ty: &syn::Type,
+    value: proc_macro2::TokenStream,
+    crate_path: &proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    if schema::is_wide_int(ty) {
+        return quote! { *#value };
+    }
+    match schema::boxed_inner(ty) {
+        Some(inner) => quote_spanned! {inner.span()=>
+            ::std::boxed::Box::new(<#inner as #crate_path::Oxide>::map_bonds(&**#value, mapper))
+        },
+        None => quote_spanned! {ty.span()=>
+            <#ty as #crate_path::Oxide>::map_bonds(#value, mapper)
+        },
+    }
+}
+
 #[derive(Default)]
 pub(crate) struct FieldAttrs {
     pub skip: bool,
+    pub default: bool,
+    pub encrypt: bool,
     pub rename: Option<String>,
 }
 
@@ -465,6 +605,10 @@ pub(crate) fn parse_field_attrs(attrs: &[syn::Attribute]) -> FieldAttrs {
         let _ = attr.parse_nested_meta(|meta| {
             if meta.path.is_ident("skip") {
                 result.skip = true;
+            } else if meta.path.is_ident("default") {
+                result.default = true;
+            } else if meta.path.is_ident("encrypt") {
+                result.encrypt = true;
             } else if meta.path.is_ident("rename") {
                 let value: syn::LitStr = meta.value()?.parse()?;
                 result.rename = Some(value.value());
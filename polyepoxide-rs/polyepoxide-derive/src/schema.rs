@@ -1,5 +1,6 @@
 use proc_macro2::TokenStream;
-use quote::quote;
+use quote::{quote, quote_spanned};
+use syn::spanned::Spanned;
 use syn::{DeriveInput, Type};
 
 use crate::{parse_field_attrs, FieldAttrs};
@@ -11,8 +12,8 @@ pub fn generate_schema(input: &DeriveInput, crate_path: &TokenStream) -> syn::Re
     match &input.data {
         syn::Data::Struct(data) => generate_schema_struct(self_type, data, crate_path),
         syn::Data::Enum(data) => generate_schema_enum(self_type, data, crate_path),
-        syn::Data::Union(_) => Err(syn::Error::new_spanned(
-            input,
+        syn::Data::Union(data) => Err(syn::Error::new_spanned(
+            data.union_token,
             "Oxide cannot be derived for unions",
         )),
     }
@@ -34,8 +35,7 @@ fn generate_schema_struct(
                         return None;
                     }
                     let name = get_field_name(f, &attrs);
-                    let ty = &f.ty;
-                    let schema = type_to_schema(ty, self_type, crate_path);
+                    let schema = named_field_schema(f, &attrs, self_type, crate_path);
                     Some(quote! { (#name, #schema) })
                 })
                 .collect();
@@ -63,7 +63,7 @@ fn generate_schema_struct(
 
     Ok(quote! {
         fn schema() -> #crate_path::Structure {
-            #schema_expr
+            #crate_path::__schema_frame::<Self>(|| #schema_expr)
         }
     })
 }
@@ -108,7 +108,7 @@ fn generate_schema_enum(self_type: &syn::Ident, data: &syn::DataEnum, crate_path
 
     Ok(quote! {
         fn schema() -> #crate_path::Structure {
-            #schema_expr
+            #crate_path::__schema_frame::<Self>(|| #schema_expr)
         }
     })
 }
@@ -128,7 +128,7 @@ fn variant_payload_schema(fields: &syn::Fields, self_type: &syn::Ident, crate_pa
                         return None;
                     }
                     let name = get_field_name(f, &attrs);
-                    let schema = type_to_schema(&f.ty, self_type, crate_path);
+                    let schema = named_field_schema(f, &attrs, self_type, crate_path);
                     Some(quote! { (#name, #schema) })
                 })
                 .collect();
@@ -169,14 +169,30 @@ fn get_field_name(field: &syn::Field, attrs: &FieldAttrs) -> String {
         .unwrap_or_else(|| field.ident.as_ref().unwrap().to_string())
 }
 
+fn named_field_schema(
+    field: &syn::Field,
+    attrs: &FieldAttrs,
+    self_type: &syn::Ident,
+    crate_path: &TokenStream,
+) -> TokenStream {
+    let schema = type_to_schema(&field.ty, self_type, crate_path);
+    if attrs.default {
+        quote! { #crate_path::Structure::defaulted(#schema) }
+    } else {
+        schema
+    }
+}
+
 /// Convert a Rust type to its Schema representation.
-/// Detects self-references and replaces them with SelfRef(0).
+/// Self-references, like references through other types, are replaced by
+/// `SelfRef`s at runtime by `__schema_frame`, which counts the records and
+/// unions in between.
 fn type_to_schema(ty: &Type, self_type: &syn::Ident, crate_path: &TokenStream) -> TokenStream {
     match ty {
         Type::Path(type_path) => {
             // Check if this is a self-reference
             if is_self_reference(type_path, self_type) {
-                return quote! { #crate_path::Structure::SelfRef(0) };
+                return quote! { <Self as #crate_path::Oxide>::schema() };
             }
 
             let last_segment = type_path.path.segments.last();
@@ -203,8 +219,16 @@ fn type_to_schema(ty: &Type, self_type: &syn::Ident, crate_path: &TokenStream) -
                             return quote! { #crate_path::Structure::bond(#inner_schema) };
                         }
                     }
+                    // No Oxide impl: their serde encoding doesn't fit DAG-CBOR,
+                    // so they're only usable as fields encoded via serde_helpers
+                    "u128" if type_path.path.is_ident("u128") => {
+                        return quote! { #crate_path::Structure::Int(#crate_path::IntType::U128) };
+                    }
+                    "i128" if type_path.path.is_ident("i128") => {
+                        return quote! { #crate_path::Structure::Int(#crate_path::IntType::I128) };
+                    }
                     "Box" => {
-                        if let Some(inner) = extract_single_generic_arg(&segment.arguments) {
+                        if let Some(inner) = boxed_inner(ty) {
                             // Box<T> has same schema as T
                             return type_to_schema(&inner, self_type, crate_path);
                         }
@@ -213,8 +237,9 @@ fn type_to_schema(ty: &Type, self_type: &syn::Ident, crate_path: &TokenStream) -
                 }
             }
 
-            // Delegate to the type's Oxide implementation
-            quote! { <#type_path as #crate_path::Oxide>::schema() }
+            // Delegate to the type's Oxide implementation, spanned to the
+            // field type so a missing impl is reported there
+            quote_spanned! {type_path.span()=> <#type_path as #crate_path::Oxide>::schema() }
         }
         Type::Tuple(tuple) if tuple.elems.is_empty() => {
             quote! { #crate_path::Structure::Unit }
@@ -233,7 +258,7 @@ fn type_to_schema(ty: &Type, self_type: &syn::Ident, crate_path: &TokenStream) -
         }
         _ => {
             // Fallback: delegate to Oxide implementation
-            quote! { <#ty as #crate_path::Oxide>::schema() }
+            quote_spanned! {ty.span()=> <#ty as #crate_path::Oxide>::schema() }
         }
     }
 }
@@ -247,6 +272,30 @@ fn is_self_reference(type_path: &syn::TypePath, self_type: &syn::Ident) -> bool
     false
 }
 
+/// Whether the type is `u128` or `i128`, which have no Oxide impl.
+pub(crate) fn is_wide_int(ty: &Type) -> bool {
+    let Type::Path(type_path) = ty else {
+        return false;
+    };
+    type_path.path.is_ident("u128") || type_path.path.is_ident("i128")
+}
+
+/// Returns `T` if the type is `Box<T>`.
+///
+/// Trait objects aren't unwrapped: `dyn Trait` can't implement Oxide, but a
+/// local `Box<dyn Trait>` can.
+pub(crate) fn boxed_inner(ty: &Type) -> Option<Type> {
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != "Box" {
+        return None;
+    }
+    extract_single_generic_arg(&segment.arguments)
+        .filter(|inner| !matches!(inner, Type::TraitObject(_)))
+}
+
 /// Extract the single generic argument from angle brackets, e.g., T from Vec<T>.
 fn extract_single_generic_arg(args: &syn::PathArguments) -> Option<Type> {
     match args {
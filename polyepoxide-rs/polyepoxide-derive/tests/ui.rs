@@ -0,0 +1,7 @@
+//! Compile-fail tests checking that derive errors point at the offending code.
+
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,11 @@
+use polyepoxide_core::Oxide;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize, Oxide)]
+struct Item {
+    name: String,
+    #[oxide(encrypt)]
+    price: u64,
+}
+
+fn main() {}
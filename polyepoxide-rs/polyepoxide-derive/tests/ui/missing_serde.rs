@@ -0,0 +1,9 @@
+use polyepoxide_core::{Bond, Oxide};
+
+#[derive(Debug, Clone, Oxide)]
+struct Node {
+    value: u32,
+    next: Option<Bond<Node>>,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use polyepoxide_core::Oxide;
+
+#[derive(Oxide)]
+union Bits {
+    int: u32,
+    float: f32,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use std::collections::HashSet;
+
+use polyepoxide_core::oxide;
+
+#[oxide]
+struct Tags {
+    name: String,
+    tags: HashSet<String>,
+}
+
+fn main() {}
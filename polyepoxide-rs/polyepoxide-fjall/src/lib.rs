@@ -1,22 +1,48 @@
 //! Fjall-backed store for Polyepoxide.
+//!
+//! Each data category lives in its own keyspace, so compaction, statistics,
+//! and scans over refs or indexes don't touch value blocks.
 
+use std::io::{Cursor, Read};
 use std::path::Path;
+use std::sync::Mutex;
 
 use cid::Cid;
 use fjall::{Database, Keyspace, KeyspaceCreateOptions};
-use polyepoxide_core::Store;
+use polyepoxide_core::{
+    reachable, Batch, Category, CategoryStats, GcStats, IndexStore, LockError, RefStore, Store,
+    StoreLock,
+};
 use thiserror::Error;
 
+#[cfg(feature = "tokio")]
+use polyepoxide_core::BlockingStoreAdapter;
+
+/// Keyspace `open` uses. Its categories are kept in keyspaces named after
+/// the category alone.
 pub const DEFAULT_KEYSPACE: &str = "data";
 
 #[derive(Debug, Error)]
-#[error("Fjall error: {0}")]
-pub struct FjallError(#[from] fjall::Error);
+pub enum FjallError {
+    #[error("Fjall error: {0}")]
+    Fjall(#[from] fjall::Error),
+    #[error(transparent)]
+    Lock(#[from] LockError),
+}
+
+/// A Fjall store for async code such as `pull`. Fjall has no async API, so
+/// its calls run on tokio's blocking thread pool.
+#[cfg(feature = "tokio")]
+pub type AsyncFjallStore = BlockingStoreAdapter<FjallStore>;
 
 /// A persistent store backed by Fjall.
 pub struct FjallStore {
-    keyspace: Keyspace,
-    _database: Database, // Keep keyspace alive
+    /// One keyspace per category, indexed by `Category::index`.
+    keyspaces: Vec<Keyspace>,
+    /// Serializes ref compare-and-set; the store lock keeps other processes out.
+    refs_lock: Mutex<()>,
+    _database: Database, // Keep keyspaces alive
+    _lock: StoreLock,    // Released after the database is closed
 }
 
 impl FjallStore {
@@ -29,14 +55,90 @@ impl FjallStore {
 
     /// Opens a Fjall store at the given path with a specific keyspace name.
     ///
-    /// Creates the database and keyspace if they don't exist.
+    /// Each category is kept in a keyspace named `<keyspace>.<category>`.
+    /// Creates the database and keyspaces if they don't exist, and moves data
+    /// out of a single keyspace named `keyspace`, where stores kept it before
+    /// categories were separated. Fails with `FjallError::Lock` if another
+    /// process has the store open.
     pub fn open_keyspace(path: impl AsRef<Path>, keyspace: &str) -> Result<Self, FjallError> {
+        let lock = StoreLock::acquire(path.as_ref())?;
         let database = Database::builder(path).open()?;
-        let keyspace = database.keyspace(keyspace, || KeyspaceCreateOptions::default())?;
-        Ok(Self {
-            keyspace,
+        let keyspaces = Category::ALL
+            .iter()
+            .map(|&c| {
+                let name = category_keyspace(keyspace, c);
+                database.keyspace(&name, KeyspaceCreateOptions::default)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let store = Self {
+            keyspaces,
+            refs_lock: Mutex::new(()),
             _database: database,
-        })
+            _lock: lock,
+        };
+        store.migrate_legacy(keyspace)?;
+        Ok(store)
+    }
+
+    /// Opens a Fjall store at the given path for async use, as `open` does.
+    #[cfg(feature = "tokio")]
+    pub fn open_async(path: impl AsRef<Path>) -> Result<AsyncFjallStore, FjallError> {
+        Ok(BlockingStoreAdapter::new(Self::open(path)?))
+    }
+
+    /// Returns the keyspace holding the given category.
+    pub fn keyspace(&self, category: Category) -> &Keyspace {
+        &self.keyspaces[category.index()]
+    }
+
+    /// Counts entries and bytes in each category.
+    pub fn stats(&self) -> Result<Vec<CategoryStats>, FjallError> {
+        Category::ALL
+            .iter()
+            .map(|&category| {
+                let mut stats = CategoryStats::new(category);
+                for entry in self.keyspace(category).iter() {
+                    let (_, value) = entry.into_inner()?;
+                    stats.entries += 1;
+                    stats.bytes += value.len() as u64;
+                }
+                Ok(stats)
+            })
+            .collect()
+    }
+
+    /// Moves entries of the legacy keyspace named `keyspace` into the values
+    /// keyspace.
+    ///
+    /// Schemas aren't told apart from values here; lookups check both, so
+    /// leaving them among values is harmless. Each entry is copied before it
+    /// is removed, so an interrupted migration resumes on the next open.
+    fn migrate_legacy(&self, keyspace: &str) -> Result<(), FjallError> {
+        // Opening a keyspace creates it, so only open one that exists, and
+        // never one holding a category of the default keyspace
+        let is_category = Category::ALL.iter().any(|c| c.name() == keyspace);
+        if is_category || !self._database.keyspace_exists(keyspace) {
+            return Ok(());
+        }
+        let legacy = self
+            ._database
+            .keyspace(keyspace, KeyspaceCreateOptions::default)?;
+        let values = self.keyspace(Category::Values);
+        for entry in legacy.iter() {
+            let (key, value) = entry.into_inner()?;
+            values.insert(key.clone(), value)?;
+            legacy.remove(key)?;
+        }
+        Ok(())
+    }
+}
+
+/// Name of the keyspace holding `category` of the store `keyspace`. The
+/// default keyspace's categories keep the names they were created with.
+fn category_keyspace(keyspace: &str, category: Category) -> String {
+    match keyspace {
+        DEFAULT_KEYSPACE => category.name().to_string(),
+        _ => format!("{}.{}", keyspace, category.name()),
     }
 }
 
@@ -44,25 +146,220 @@ impl Store for FjallStore {
     type Error = FjallError;
 
     fn get(&self, cid: &Cid) -> Result<Option<Vec<u8>>, Self::Error> {
-        Ok(self.keyspace.get(cid.to_bytes())?.map(|v| v.to_vec()))
+        let key = cid.to_bytes();
+        for category in [Category::Values, Category::Schemas] {
+            if let Some(value) = self.keyspace(category).get(&key)? {
+                return Ok(Some(value.to_vec()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Reads from the shared slice instead of copying the value.
+    fn get_reader(&self, cid: &Cid) -> Result<Option<Box<dyn Read + '_>>, Self::Error> {
+        let key = cid.to_bytes();
+        for category in [Category::Values, Category::Schemas] {
+            if let Some(value) = self.keyspace(category).get(&key)? {
+                return Ok(Some(Box::new(Cursor::new(value))));
+            }
+        }
+        Ok(None)
     }
 
     fn put(&self, cid: &Cid, value: &[u8]) -> Result<(), Self::Error> {
-        self.keyspace.insert(cid.to_bytes(), value)?;
+        self.keyspace(Category::Values)
+            .insert(cid.to_bytes(), value)?;
+        Ok(())
+    }
+
+    fn put_schema(&self, cid: &Cid, value: &[u8]) -> Result<(), Self::Error> {
+        self.keyspace(Category::Schemas)
+            .insert(cid.to_bytes(), value)?;
         Ok(())
     }
 
     fn has(&self, cid: &Cid) -> Result<bool, Self::Error> {
-        self.keyspace
-            .contains_key(cid.to_bytes())
-            .map_err(Into::into)
+        let key = cid.to_bytes();
+        Ok(self.keyspace(Category::Values).contains_key(&key)?
+            || self.keyspace(Category::Schemas).contains_key(&key)?)
+    }
+
+    /// Writes all nodes in a single atomic batch.
+    fn put_many(&self, nodes: &[(&Cid, &[u8])]) -> Result<(), Self::Error> {
+        let values = self.keyspace(Category::Values);
+        let mut batch = self._database.batch();
+        for (cid, value) in nodes {
+            batch.insert(values, cid.to_bytes(), *value);
+        }
+        batch.commit()?;
+        Ok(())
+    }
+
+    /// Writes schemas and values in a single atomic batch.
+    fn write_batch(&self, batch: &Batch) -> Result<(), Self::Error> {
+        let mut writes = self._database.batch();
+        for (cid, value) in batch.schemas() {
+            writes.insert(self.keyspace(Category::Schemas), cid.to_bytes(), value);
+        }
+        for (cid, value) in batch.values() {
+            writes.insert(self.keyspace(Category::Values), cid.to_bytes(), value);
+        }
+        writes.commit()?;
+        Ok(())
+    }
+
+    fn delete(&self, cid: &Cid) -> Result<(), Self::Error> {
+        self.delete_many(std::slice::from_ref(cid))
+    }
+
+    /// Removes the CIDs from values and schemas in a single atomic batch.
+    fn delete_many(&self, cids: &[Cid]) -> Result<(), Self::Error> {
+        let mut batch = self._database.batch();
+        for cid in cids {
+            let key = cid.to_bytes();
+            for category in [Category::Values, Category::Schemas] {
+                batch.remove(self.keyspace(category), key.clone());
+            }
+        }
+        batch.commit()?;
+        Ok(())
+    }
+
+    /// Iterates over values, then schemas. Keys that aren't CIDs are skipped.
+    fn iter(&self) -> Box<dyn Iterator<Item = Result<Cid, Self::Error>> + '_> {
+        Box::new(
+            [Category::Values, Category::Schemas]
+                .into_iter()
+                .flat_map(|category| self.keyspace(category).iter())
+                .filter_map(|entry| match entry.into_inner() {
+                    Ok((key, _)) => Cid::try_from(&key[..]).ok().map(Ok),
+                    Err(e) => Some(Err(e.into())),
+                }),
+        )
+    }
+
+    fn gc(&self, roots: &[Cid]) -> Result<GcStats, Self::Error> {
+        let keep = reachable(self, roots)?;
+        let mut stats = GcStats {
+            retained: keep.len(),
+            ..Default::default()
+        };
+
+        // Schemas are never swept, as values don't link to them
+        let keyspace = self.keyspace(Category::Values);
+        // Collect first: deleting while iterating would invalidate the snapshot
+        let mut garbage = Vec::new();
+        for entry in keyspace.iter() {
+            let (key, value) = entry.into_inner()?;
+            // Keys that aren't CIDs weren't written through this API; leave them
+            let Ok(cid) = Cid::try_from(&key[..]) else {
+                continue;
+            };
+            if !keep.contains(&cid) {
+                stats.freed += 1;
+                stats.freed_bytes += value.len() as u64;
+                garbage.push(key);
+            }
+        }
+        for key in garbage {
+            keyspace.remove(key)?;
+        }
+        Ok(stats)
+    }
+
+    /// Runs a major compaction of every keyspace, dropping tombstones.
+    fn compact(&self) -> Result<(), Self::Error> {
+        for keyspace in &self.keyspaces {
+            keyspace.major_compact()?;
+        }
+        Ok(())
+    }
+}
+
+/// Refs are kept in the refs keyspace, keyed by name, with the CID bytes as
+/// value.
+impl RefStore for FjallStore {
+    fn get_ref(&self, name: &str) -> Result<Option<Cid>, Self::Error> {
+        let value = self.keyspace(Category::Refs).get(name.as_bytes())?;
+        Ok(value.and_then(|bytes| Cid::try_from(&bytes[..]).ok()))
+    }
+
+    fn set_ref(&self, name: &str, cid: &Cid) -> Result<(), Self::Error> {
+        let _guard = self.refs_lock.lock().unwrap();
+        self.keyspace(Category::Refs)
+            .insert(name.as_bytes(), cid.to_bytes())?;
+        Ok(())
+    }
+
+    fn delete_ref(&self, name: &str) -> Result<(), Self::Error> {
+        let _guard = self.refs_lock.lock().unwrap();
+        self.keyspace(Category::Refs).remove(name.as_bytes())?;
+        Ok(())
+    }
+
+    /// Entries whose name isn't UTF-8 or whose value isn't a CID are skipped.
+    fn list_refs(&self, prefix: &str) -> Result<Vec<(String, Cid)>, Self::Error> {
+        let mut refs = Vec::new();
+        for entry in self.keyspace(Category::Refs).prefix(prefix.as_bytes()) {
+            let (key, value) = entry.into_inner()?;
+            let (Ok(name), Ok(cid)) = (std::str::from_utf8(&key), Cid::try_from(&value[..])) else {
+                continue;
+            };
+            refs.push((name.to_string(), cid));
+        }
+        Ok(refs)
+    }
+
+    fn compare_and_set_ref(
+        &self,
+        name: &str,
+        expected: Option<&Cid>,
+        new: &Cid,
+    ) -> Result<bool, Self::Error> {
+        let _guard = self.refs_lock.lock().unwrap();
+        let refs = self.keyspace(Category::Refs);
+        let current = refs
+            .get(name.as_bytes())?
+            .and_then(|bytes| Cid::try_from(&bytes[..]).ok());
+        if current.as_ref() != expected {
+            return Ok(false);
+        }
+        refs.insert(name.as_bytes(), new.to_bytes())?;
+        Ok(true)
+    }
+}
+
+/// Index entries are kept in the indexes keyspace, laid out like refs.
+impl IndexStore for FjallStore {
+    fn set_index_entry(&self, name: &str, cid: &Cid) -> Result<(), Self::Error> {
+        self.keyspace(Category::Indexes)
+            .insert(name.as_bytes(), cid.to_bytes())?;
+        Ok(())
+    }
+
+    fn delete_index_entry(&self, name: &str) -> Result<(), Self::Error> {
+        self.keyspace(Category::Indexes).remove(name.as_bytes())?;
+        Ok(())
+    }
+
+    /// Entries whose name isn't UTF-8 or whose value isn't a CID are skipped.
+    fn list_index_entries(&self, prefix: &str) -> Result<Vec<(String, Cid)>, Self::Error> {
+        let mut entries = Vec::new();
+        for entry in self.keyspace(Category::Indexes).prefix(prefix.as_bytes()) {
+            let (key, value) = entry.into_inner()?;
+            let (Ok(name), Ok(cid)) = (std::str::from_utf8(&key), Cid::try_from(&value[..])) else {
+                continue;
+            };
+            entries.push((name.to_string(), cid));
+        }
+        Ok(entries)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use polyepoxide_core::compute_cid;
+    use polyepoxide_core::{compute_cid, Solvent};
     use tempfile::TempDir;
 
     fn temp_store() -> (FjallStore, TempDir) {
@@ -83,6 +380,17 @@ mod tests {
         assert_eq!(retrieved, Some(value.to_vec()));
     }
 
+    #[test]
+    fn get_reader() {
+        let (store, _dir) = temp_store();
+        let cid = compute_cid(b"test");
+        store.put(&cid, b"hello world").unwrap();
+
+        let mut read = Vec::new();
+        store.get_reader(&cid).unwrap().unwrap().read_to_end(&mut read).unwrap();
+        assert_eq!(read, b"hello world");
+    }
+
     #[test]
     fn get_missing() {
         let (store, _dir) = temp_store();
@@ -105,6 +413,208 @@ mod tests {
         assert!(store.has(&cid).unwrap());
     }
 
+    #[test]
+    fn batch_put_get() {
+        let (store, _dir) = temp_store();
+        let cids: Vec<Cid> = (0..3u8).map(|i| compute_cid(&[i])).collect();
+        let missing = compute_cid(b"missing");
+        store.put_schema(&cids[2], b"schema").unwrap();
+
+        store
+            .put_many(&[(&cids[0], b"a".as_slice()), (&cids[1], b"b".as_slice())])
+            .unwrap();
+        let results = store.get_many(&[cids[0], missing, cids[1], cids[2]]).unwrap();
+
+        assert_eq!(
+            results,
+            vec![
+                Some(b"a".to_vec()),
+                None,
+                Some(b"b".to_vec()),
+                Some(b"schema".to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn delete() {
+        let (store, _dir) = temp_store();
+        let value = compute_cid(b"value");
+        let schema = compute_cid(b"schema");
+        let kept = compute_cid(b"kept");
+        store.put(&value, b"value").unwrap();
+        store.put_schema(&schema, b"schema").unwrap();
+        store.put(&kept, b"kept").unwrap();
+
+        store.delete_many(&[value, schema]).unwrap();
+        store.delete(&compute_cid(b"never stored")).unwrap();
+
+        assert!(!store.has(&value).unwrap());
+        assert!(!store.has(&schema).unwrap());
+        assert!(store.has(&kept).unwrap());
+    }
+
+    #[test]
+    fn list_cids() {
+        let (store, _dir) = temp_store();
+        let value = compute_cid(b"value");
+        let schema = compute_cid(b"schema");
+        store.put(&value, b"value").unwrap();
+        store.put_schema(&schema, b"schema").unwrap();
+
+        let mut cids = store.list_cids().unwrap();
+        cids.sort();
+        let mut expected = vec![value, schema];
+        expected.sort();
+        assert_eq!(cids, expected);
+    }
+
+    #[test]
+    fn gc_removes_unreachable() {
+        let (store, _dir) = temp_store();
+        let mut solvent = Solvent::new();
+        let kept = solvent.add(vec![solvent.bond("kept".to_string())]);
+        let dropped = solvent.add(vec![solvent.bond("dropped".to_string())]);
+        let (kept_cid, schema_cid) = solvent.persist_cell(&kept, &store).unwrap();
+        let (dropped_cid, _) = solvent.persist_cell(&dropped, &store).unwrap();
+
+        let stats = store.gc(&[kept_cid, schema_cid]).unwrap();
+
+        assert_eq!(stats.freed, 2);
+        assert!(stats.freed_bytes > 0);
+        assert!(store.has(&kept_cid).unwrap());
+        assert!(store.has(&kept.value()[0].cid()).unwrap());
+        assert!(!store.has(&dropped_cid).unwrap());
+        assert!(!store.has(&dropped.value()[0].cid()).unwrap());
+        assert_eq!(store.gc(&[kept_cid, schema_cid]).unwrap().freed, 0);
+    }
+
+    #[test]
+    fn gc_keeps_schemas() {
+        let (store, _dir) = temp_store();
+        let mut solvent = Solvent::new();
+        let cell = solvent.add(vec![solvent.bond("kept".to_string())]);
+        let (cid, schema_cid) = solvent.persist_cell(&cell, &store).unwrap();
+
+        store.gc(&[cid]).unwrap();
+
+        assert!(store.has(&cid).unwrap());
+        assert!(store.has(&schema_cid).unwrap());
+    }
+
+    #[test]
+    fn second_open_is_locked() {
+        let (_store, dir) = temp_store();
+
+        let err = FjallStore::open(dir.path()).err().unwrap();
+        assert!(matches!(err, FjallError::Lock(LockError::Locked { .. })));
+    }
+
+    #[test]
+    fn schemas_kept_separately() {
+        let (store, _dir) = temp_store();
+        let schema = compute_cid(b"schema");
+        let value = compute_cid(b"value");
+
+        store.put_schema(&schema, b"schema").unwrap();
+        store.put(&value, b"value").unwrap();
+
+        assert!(store.has(&schema).unwrap());
+        assert_eq!(store.get(&schema).unwrap(), Some(b"schema".to_vec()));
+        let stats = store.stats().unwrap();
+        let schemas = stats.iter().find(|s| s.category == Category::Schemas).unwrap();
+        let values = stats.iter().find(|s| s.category == Category::Values).unwrap();
+        assert_eq!((schemas.entries, values.entries), (1, 1));
+    }
+
+    #[test]
+    fn transaction_writes_on_commit() {
+        let (store, _dir) = temp_store();
+        let schema = compute_cid(b"schema");
+        let value = compute_cid(b"value");
+
+        let mut transaction = store.transaction();
+        transaction.put_schema(&schema, b"schema");
+        transaction.put(&value, b"value");
+        assert!(!store.has(&value).unwrap());
+        transaction.commit().unwrap();
+
+        assert_eq!(store.get(&value).unwrap(), Some(b"value".to_vec()));
+        let stats = store.stats().unwrap();
+        let schemas = stats.iter().find(|s| s.category == Category::Schemas).unwrap();
+        assert_eq!(schemas.entries, 1);
+    }
+
+    #[test]
+    fn migrates_legacy_keyspace() {
+        let dir = TempDir::new().unwrap();
+        let cid = compute_cid(b"old");
+        {
+            let database = Database::builder(dir.path()).open().unwrap();
+            let legacy = database
+                .keyspace(DEFAULT_KEYSPACE, KeyspaceCreateOptions::default)
+                .unwrap();
+            legacy.insert(cid.to_bytes(), b"old").unwrap();
+        }
+
+        let store = FjallStore::open(dir.path()).unwrap();
+        assert_eq!(store.get(&cid).unwrap(), Some(b"old".to_vec()));
+        let stats = store.stats().unwrap();
+        assert_eq!(stats[Category::Values.index()].entries, 1);
+    }
+
+    #[test]
+    fn migrates_named_legacy_keyspace() {
+        let dir = TempDir::new().unwrap();
+        let cid = compute_cid(b"old");
+        {
+            let database = Database::builder(dir.path()).open().unwrap();
+            let legacy = database
+                .keyspace("inventory", KeyspaceCreateOptions::default)
+                .unwrap();
+            legacy.insert(cid.to_bytes(), b"old").unwrap();
+        }
+
+        let store = FjallStore::open_keyspace(dir.path(), "inventory").unwrap();
+        assert_eq!(store.get(&cid).unwrap(), Some(b"old".to_vec()));
+        assert!(!store._database.keyspace_exists(DEFAULT_KEYSPACE));
+        drop(store);
+
+        let store = FjallStore::open(dir.path()).unwrap();
+        assert_eq!(store.get(&cid).unwrap(), None);
+        assert!(!store._database.keyspace_exists(DEFAULT_KEYSPACE));
+    }
+
+    #[test]
+    fn refs() {
+        let dir = TempDir::new().unwrap();
+        let first = compute_cid(b"first");
+        let second = compute_cid(b"second");
+
+        {
+            let store = FjallStore::open(dir.path()).unwrap();
+            store.set_ref("inventory/head", &first).unwrap();
+            store.set_ref("chat/work", &second).unwrap();
+            assert!(store
+                .compare_and_set_ref("inventory/head", Some(&first), &second)
+                .unwrap());
+            assert!(!store
+                .compare_and_set_ref("inventory/head", Some(&first), &first)
+                .unwrap());
+            // Refs aren't blocks
+            assert!(store.list_cids().unwrap().is_empty());
+        }
+
+        let store = FjallStore::open(dir.path()).unwrap();
+        assert_eq!(store.get_ref("inventory/head").unwrap(), Some(second));
+        assert_eq!(
+            store.list_refs("chat/").unwrap(),
+            vec![("chat/work".to_string(), second)]
+        );
+        store.delete_ref("chat/work").unwrap();
+        assert_eq!(store.get_ref("chat/work").unwrap(), None);
+    }
+
     #[test]
     fn persistence() {
         let dir = TempDir::new().unwrap();
@@ -0,0 +1,192 @@
+//! Versions of a value recorded as a DAG of commits.
+//!
+//! A commit bonds to a snapshot of a root value and to the commits it was
+//! made on top of, so histories sync and deduplicate like any other data.
+//! The current head is usually kept in a ref (see `RefStore`).
+
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use polyepoxide_core::{Bond, Cell, Cid, LoadError, Oxide, Solvent, Store};
+use serde::{Deserialize, Serialize};
+
+/// A recorded version of a value of type `T`.
+///
+/// The root is a typed bond rather than a bare CID, so pulling a commit or
+/// collecting garbage from it follows the snapshot too.
+#[derive(Debug, Clone, Serialize, Deserialize, Oxide)]
+#[serde(bound = "T: Oxide")]
+pub struct Commit<T> {
+    /// Commits this one was made on top of: none for the first commit, two
+    /// or more for a merge.
+    pub parents: Vec<Bond<Commit<T>>>,
+    pub root: Bond<T>,
+    /// Milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+    pub author: String,
+    pub message: String,
+}
+
+/// Records a commit of `root` on top of `parents`, timestamped now.
+pub fn commit<T: Oxide>(
+    solvent: &mut Solvent,
+    parents: Vec<Bond<Commit<T>>>,
+    root: Bond<T>,
+    author: &str,
+    message: &str,
+) -> Bond<Commit<T>> {
+    let timestamp_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64);
+    solvent.bond(Commit {
+        parents,
+        root,
+        timestamp_ms,
+        author: author.to_string(),
+        message: message.to_string(),
+    })
+}
+
+/// Returns `head` and all its ancestors, newest first.
+///
+/// Commits not in `solvent` are loaded from `store`. Equal timestamps are
+/// ordered by CID, so every replica lists the same order.
+pub fn log<T: Oxide, S: Store>(
+    solvent: &mut Solvent,
+    store: &S,
+    head: &Cid,
+) -> Result<Vec<Arc<Cell<Commit<T>>>>, LoadError<S::Error>> {
+    let mut commits = Vec::new();
+    walk(solvent, store, head, |commit| {
+        commits.push(commit);
+        true
+    })?;
+    Ok(commits)
+}
+
+/// Returns the CIDs of `head` and all its ancestors.
+pub fn ancestry<T: Oxide, S: Store>(
+    solvent: &mut Solvent,
+    store: &S,
+    head: &Cid,
+) -> Result<HashSet<Cid>, LoadError<S::Error>> {
+    let mut cids = HashSet::new();
+    walk::<T, S>(solvent, store, head, |commit| {
+        cids.insert(commit.cid());
+        true
+    })?;
+    Ok(cids)
+}
+
+/// Returns the newest commit that both `a` and `b` descend from, each
+/// counting as its own descendant, or `None` if their histories are
+/// unrelated.
+pub fn common_ancestor<T: Oxide, S: Store>(
+    solvent: &mut Solvent,
+    store: &S,
+    a: &Cid,
+    b: &Cid,
+) -> Result<Option<Cid>, LoadError<S::Error>> {
+    let ours = ancestry::<T, S>(solvent, store, a)?;
+    let mut found = None;
+    walk::<T, S>(solvent, store, b, |commit| {
+        if ours.contains(&commit.cid()) {
+            found = Some(commit.cid());
+        }
+        found.is_none()
+    })?;
+    Ok(found)
+}
+
+/// Visits `head` and its ancestors newest first, until `visit` returns false.
+fn walk<T: Oxide, S: Store>(
+    solvent: &mut Solvent,
+    store: &S,
+    head: &Cid,
+    mut visit: impl FnMut(Arc<Cell<Commit<T>>>) -> bool,
+) -> Result<(), LoadError<S::Error>> {
+    let mut seen = HashSet::from([*head]);
+    let mut loaded = HashMap::new();
+    let mut queue = BinaryHeap::new();
+
+    let head = solvent.load::<Commit<T>, S>(head, store, 0)?;
+    queue.push((head.value().timestamp_ms, head.cid()));
+    loaded.insert(head.cid(), head);
+    while let Some((_, cid)) = queue.pop() {
+        let commit = loaded.remove(&cid).expect("queued commits are loaded");
+        for parent in &commit.value().parents {
+            if !seen.insert(parent.cid()) {
+                continue;
+            }
+            let parent = match parent.cell() {
+                Some(cell) => cell.clone(),
+                None => solvent.load::<Commit<T>, S>(&parent.cid(), store, 0)?,
+            };
+            queue.push((parent.value().timestamp_ms, parent.cid()));
+            loaded.insert(parent.cid(), parent);
+        }
+        if !visit(commit) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use polyepoxide_core::MemoryStore;
+
+    fn at(
+        solvent: &mut Solvent,
+        parents: &[&Bond<Commit<String>>],
+        timestamp_ms: u64,
+    ) -> Bond<Commit<String>> {
+        let root = solvent.bond(format!("version {timestamp_ms}"));
+        solvent.bond(Commit {
+            parents: parents.iter().map(|&parent| parent.clone()).collect(),
+            root,
+            timestamp_ms,
+            author: "ada".to_string(),
+            message: String::new(),
+        })
+    }
+
+    #[test]
+    fn walks_merged_branches() {
+        // first - main ----- merge
+        //       \- feature -/
+        let mut solvent = Solvent::new();
+        let first = at(&mut solvent, &[], 1);
+        let main = at(&mut solvent, &[&first], 2);
+        let feature = at(&mut solvent, &[&first], 3);
+        let merge = at(&mut solvent, &[&main, &feature], 4);
+
+        let store = MemoryStore::new();
+        solvent.persist_cell(merge.cell().unwrap(), &store).unwrap();
+
+        // A fresh solvent loads the history from the store
+        let mut solvent = Solvent::new();
+        let log = log::<String, _>(&mut solvent, &store, &merge.cid()).unwrap();
+        let times: Vec<_> = log.iter().map(|c| c.value().timestamp_ms).collect();
+        assert_eq!(times, [4, 3, 2, 1]);
+
+        let ancestor = common_ancestor::<String, _>(
+            &mut solvent,
+            &store,
+            &main.cid(),
+            &feature.cid(),
+        );
+        assert_eq!(ancestor.unwrap(), Some(first.cid()));
+        let ancestor = common_ancestor::<String, _>(
+            &mut solvent,
+            &store,
+            &merge.cid(),
+            &main.cid(),
+        );
+        assert_eq!(ancestor.unwrap(), Some(main.cid()));
+        let ancestry = ancestry::<String, _>(&mut solvent, &store, &feature.cid()).unwrap();
+        assert_eq!(ancestry, HashSet::from([feature.cid(), first.cid()]));
+    }
+}
@@ -0,0 +1,116 @@
+//! A store served over HTTP, used as an `AsyncStore`.
+
+use cid::Cid;
+use polyepoxide_core::{AsyncStore, Subgraph};
+use reqwest::StatusCode;
+
+use crate::server::PullResponse;
+
+/// Error from HTTP store operations.
+#[derive(Debug, thiserror::Error)]
+pub enum HttpStoreError {
+    #[error("request failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("server responded {status}: {message}")]
+    Status { status: StatusCode, message: String },
+    #[error("invalid response: {0}")]
+    Decode(String),
+    #[error("server does not support {0}")]
+    Unsupported(String),
+}
+
+/// A store served by `router` on another machine.
+pub struct HttpStore {
+    base: String,
+    client: reqwest::Client,
+}
+
+impl HttpStore {
+    /// Connects to the server at `base`, such as `http://10.0.0.2:8080`.
+    pub fn new(base: impl Into<String>) -> Self {
+        Self::with_client(base, reqwest::Client::new())
+    }
+
+    /// Connects with a preconfigured client, e.g. with timeouts or auth
+    /// headers.
+    pub fn with_client(base: impl Into<String>, client: reqwest::Client) -> Self {
+        let base = base.into().trim_end_matches('/').to_string();
+        Self { base, client }
+    }
+
+    fn block_url(&self, cid: &Cid) -> String {
+        format!("{}/blocks/{}", self.base, cid)
+    }
+
+    /// Fetches a value along with as much of its subgraph as the server
+    /// returns in one response, up to `max_nodes` values.
+    pub async fn pull_subgraph(
+        &self,
+        root: Cid,
+        schema: Cid,
+        max_nodes: usize,
+    ) -> Result<Subgraph, HttpStoreError> {
+        let response = self
+            .client
+            .get(format!("{}/sync/pull", self.base))
+            .query(&[
+                ("root", root.to_string()),
+                ("schema", schema.to_string()),
+                ("max_nodes", max_nodes.to_string()),
+            ])
+            .send()
+            .await?;
+        let body = success(response).await?.bytes().await?;
+        let response: PullResponse = serde_ipld_dagcbor::from_slice(&body)
+            .map_err(|e| HttpStoreError::Decode(e.to_string()))?;
+        Ok(response.into())
+    }
+}
+
+/// Turns responses other than 2xx into errors carrying the body's text.
+async fn success(response: reqwest::Response) -> Result<reqwest::Response, HttpStoreError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let message = response.text().await.unwrap_or_default();
+    Err(HttpStoreError::Status { status, message })
+}
+
+impl AsyncStore for HttpStore {
+    type Error = HttpStoreError;
+
+    async fn async_get(&self, cid: &Cid) -> Result<Option<Vec<u8>>, Self::Error> {
+        let response = self.client.get(self.block_url(cid)).send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let body = success(response).await?.bytes().await?;
+        Ok(Some(body.to_vec()))
+    }
+
+    async fn async_put(&self, cid: &Cid, value: &[u8]) -> Result<(), Self::Error> {
+        let request = self.client.put(self.block_url(cid)).body(value.to_vec());
+        success(request.send().await?).await?;
+        Ok(())
+    }
+
+    async fn async_has(&self, cid: &Cid) -> Result<bool, Self::Error> {
+        let response = self.client.head(self.block_url(cid)).send().await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(false);
+        }
+        success(response).await?;
+        Ok(true)
+    }
+
+    /// The server doesn't accept deletions, so peers can't remove data.
+    async fn async_delete(&self, _cid: &Cid) -> Result<(), Self::Error> {
+        Err(HttpStoreError::Unsupported("delete".to_string()))
+    }
+
+    /// The server only serves CIDs asked for by name.
+    async fn async_list_cids(&self) -> Result<Vec<Cid>, Self::Error> {
+        Err(HttpStoreError::Unsupported("list".to_string()))
+    }
+}
@@ -0,0 +1,25 @@
+//! Polyepoxide block store over plain HTTP.
+//!
+//! A lighter alternative to polyepoxide-libp2p for machines that can already
+//! reach each other, such as over a VPN:
+//! - `router`/`serve` expose an `AsyncStore` over HTTP
+//! - `HttpStore` implements `AsyncStore` against such a server, so `pull`
+//!   and `push` work with it like with any other store
+//!
+//! Every endpoint can be tried with curl:
+//!
+//! ```text
+//! curl http://host:8080/blocks/bafyr4i... > block.cbor
+//! curl -I http://host:8080/blocks/bafyr4i...
+//! curl -T block.cbor http://host:8080/blocks/bafyr4i...
+//! curl 'http://host:8080/sync/pull?root=bafyr4i...&schema=bafyr4i...'
+//! ```
+
+mod client;
+mod server;
+
+pub use client::{HttpStore, HttpStoreError};
+pub use server::{router, serve, PullResponse, MAX_SUBGRAPH_NODES};
+
+/// Media type of blocks and `/sync/pull` responses.
+pub const DAG_CBOR: &str = "application/vnd.ipld.dag-cbor";
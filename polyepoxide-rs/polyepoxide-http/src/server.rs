@@ -0,0 +1,173 @@
+//! Serving a store over HTTP.
+
+use std::sync::Arc;
+
+use axum::body::Bytes;
+use axum::extract::{DefaultBodyLimit, Path, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use cid::Cid;
+use polyepoxide_core::{
+    walk_subgraph, AsyncStore, DecodeLimits, Subgraph, VerifyError, VerifyingStore,
+};
+use serde::{Deserialize, Serialize};
+use tokio::net::TcpListener;
+
+use crate::DAG_CBOR;
+
+/// Most values served for one `/sync/pull` request.
+pub const MAX_SUBGRAPH_NODES: usize = 4096;
+
+/// Most value bytes served for one `/sync/pull` request.
+const MAX_SUBGRAPH_BYTES: usize = 8 * 1024 * 1024;
+
+/// Body of a `/sync/pull` response: a `Subgraph` encoded as DAG-CBOR.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PullResponse {
+    pub schemas: Vec<(Cid, Vec<u8>)>,
+    pub nodes: Vec<(Cid, Vec<u8>)>,
+    pub frontier: Vec<(Cid, Cid)>,
+}
+
+impl From<PullResponse> for Subgraph {
+    fn from(response: PullResponse) -> Self {
+        Subgraph {
+            schemas: response.schemas,
+            nodes: response.nodes,
+            frontier: response.frontier,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PullParams {
+    root: String,
+    schema: String,
+    max_nodes: Option<usize>,
+}
+
+/// Routes serving `store`:
+///
+/// - `GET /blocks/{cid}`: the block's bytes, or 404
+/// - `HEAD /blocks/{cid}`: 200 if the block is stored, 404 if not
+/// - `PUT /blocks/{cid}`: stores the request body, which must hash to `cid`
+///   and stay within the default `DecodeLimits`
+/// - `GET /sync/pull?root=&schema=&max_nodes=`: a value with as much of its
+///   subgraph as fits in one response, as a `PullResponse`
+///
+/// Errors are plain-text bodies: 400 for invalid CIDs or blocks, 500 for
+/// store failures.
+pub fn router<S: AsyncStore + 'static>(store: Arc<S>) -> Router {
+    Router::new()
+        .route(
+            "/blocks/{cid}",
+            get(get_block::<S>)
+                .head(head_block::<S>)
+                .put(put_block::<S>),
+        )
+        .route("/sync/pull", get(pull::<S>))
+        .layer(DefaultBodyLimit::max(DecodeLimits::default().max_size))
+        .with_state(store)
+}
+
+/// Serves `store` on `listener` until the task is dropped or fails.
+pub async fn serve<S: AsyncStore + 'static>(
+    listener: TcpListener,
+    store: Arc<S>,
+) -> std::io::Result<()> {
+    axum::serve(listener, router(store)).await
+}
+
+/// A refused request, answered with a plain-text body.
+struct Failure(StatusCode, String);
+
+impl IntoResponse for Failure {
+    fn into_response(self) -> Response {
+        (self.0, self.1).into_response()
+    }
+}
+
+fn parse_cid(input: &str) -> Result<Cid, Failure> {
+    Cid::try_from(input).map_err(|e| {
+        Failure(
+            StatusCode::BAD_REQUEST,
+            format!("invalid CID {:?}: {}", input, e),
+        )
+    })
+}
+
+fn internal(error: impl std::fmt::Display) -> Failure {
+    Failure(StatusCode::INTERNAL_SERVER_ERROR, error.to_string())
+}
+
+async fn get_block<S: AsyncStore>(
+    State(store): State<Arc<S>>,
+    Path(cid): Path<String>,
+) -> Result<Response, Failure> {
+    let cid = parse_cid(&cid)?;
+    match store.async_get(&cid).await.map_err(internal)? {
+        Some(bytes) => Ok(([(header::CONTENT_TYPE, DAG_CBOR)], bytes).into_response()),
+        None => Ok(StatusCode::NOT_FOUND.into_response()),
+    }
+}
+
+async fn head_block<S: AsyncStore>(
+    State(store): State<Arc<S>>,
+    Path(cid): Path<String>,
+) -> Result<StatusCode, Failure> {
+    let cid = parse_cid(&cid)?;
+    if store.async_has(&cid).await.map_err(internal)? {
+        Ok(StatusCode::OK)
+    } else {
+        Ok(StatusCode::NOT_FOUND)
+    }
+}
+
+async fn put_block<S: AsyncStore>(
+    State(store): State<Arc<S>>,
+    Path(cid): Path<String>,
+    body: Bytes,
+) -> Result<StatusCode, Failure> {
+    let cid = parse_cid(&cid)?;
+    let rejected = |e: &dyn std::fmt::Display| {
+        Failure(
+            StatusCode::BAD_REQUEST,
+            format!("block {} rejected: {}", cid, e),
+        )
+    };
+    DecodeLimits::default()
+        .check(&body)
+        .map_err(|e| rejected(&e))?;
+    match VerifyingStore::new(store.as_ref())
+        .async_put(&cid, &body)
+        .await
+    {
+        Ok(()) => Ok(StatusCode::NO_CONTENT),
+        Err(e @ VerifyError::Mismatch { .. }) => Err(rejected(&e)),
+        Err(e) => Err(internal(e)),
+    }
+}
+
+async fn pull<S: AsyncStore>(
+    State(store): State<Arc<S>>,
+    Query(params): Query<PullParams>,
+) -> Result<Response, Failure> {
+    let root = parse_cid(&params.root)?;
+    let schema = parse_cid(&params.schema)?;
+    let max_nodes = params
+        .max_nodes
+        .unwrap_or(MAX_SUBGRAPH_NODES)
+        .min(MAX_SUBGRAPH_NODES);
+    let subgraph = walk_subgraph(store.as_ref(), root, schema, max_nodes, MAX_SUBGRAPH_BYTES)
+        .await
+        .map_err(internal)?;
+    let body = serde_ipld_dagcbor::to_vec(&PullResponse {
+        schemas: subgraph.schemas,
+        nodes: subgraph.nodes,
+        frontier: subgraph.frontier,
+    })
+    .map_err(internal)?;
+    Ok(([(header::CONTENT_TYPE, DAG_CBOR)], body).into_response())
+}
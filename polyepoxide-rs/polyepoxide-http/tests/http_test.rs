@@ -0,0 +1,72 @@
+//! Integration tests syncing through a server on a local port.
+
+use std::sync::Arc;
+
+use polyepoxide_core::{compute_cid, pull, AsyncStore, Bond, MemoryStore, Oxide, Solvent, Store};
+use polyepoxide_http::{serve, HttpStore, HttpStoreError};
+use tokio::net::TcpListener;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, Oxide)]
+struct Node {
+    label: String,
+    next: Option<Bond<Node>>,
+}
+
+/// Serves `store` on a free port and returns a client for it.
+async fn start(store: Arc<MemoryStore>) -> HttpStore {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(serve(listener, store));
+    HttpStore::new(format!("http://{}", address))
+}
+
+#[tokio::test]
+async fn blocks_round_trip() {
+    let store = Arc::new(MemoryStore::new());
+    let client = start(store.clone()).await;
+    let cid = compute_cid(b"\x65hello");
+
+    assert_eq!(client.async_get(&cid).await.unwrap(), None);
+    assert!(!client.async_has(&cid).await.unwrap());
+    client.async_put(&cid, b"\x65hello").await.unwrap();
+    assert!(client.async_has(&cid).await.unwrap());
+    assert_eq!(
+        client.async_get(&cid).await.unwrap(),
+        Some(b"\x65hello".to_vec())
+    );
+    assert!(store.has(&cid).unwrap());
+
+    // Blocks that don't hash to their CID are refused
+    let forged = client
+        .async_put(&compute_cid(b"\x65other"), b"\x65hello")
+        .await;
+    assert!(matches!(forged, Err(HttpStoreError::Status { status, .. }) if status == 400));
+}
+
+#[tokio::test]
+async fn pulls_a_value_through_the_server() {
+    let source = Arc::new(MemoryStore::new());
+    let mut solvent = Solvent::new();
+    let leaf = solvent.bond(Node {
+        label: "leaf".into(),
+        next: None,
+    });
+    let root = solvent.add(Node {
+        label: "root".into(),
+        next: Some(leaf.clone()),
+    });
+    let (root_cid, schema_cid) = solvent.persist_cell(&root, source.as_ref()).unwrap();
+    let client = start(source).await;
+
+    let subgraph = client
+        .pull_subgraph(root_cid, schema_cid, 10)
+        .await
+        .unwrap();
+    let cids: Vec<_> = subgraph.nodes.iter().map(|(cid, _)| *cid).collect();
+    assert_eq!(cids, [leaf.cid(), root_cid]);
+
+    let dest = MemoryStore::new();
+    pull(&client, &dest, root_cid, schema_cid).await.unwrap();
+    assert!(dest.has(&root_cid).unwrap());
+    assert!(dest.has(&leaf.cid()).unwrap());
+}
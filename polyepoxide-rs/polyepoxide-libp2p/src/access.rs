@@ -0,0 +1,67 @@
+//! Per-peer access control for served requests.
+
+use std::collections::HashSet;
+
+use cid::Cid;
+use libp2p::PeerId;
+
+/// Decides which blocks a connected peer may read and write.
+///
+/// `cid` is the block a request names: each CID of a `Get`, `Has`, `Put`
+/// or `Delete`, and the root of a `PullSubgraph`, whose whole subgraph is
+/// served if the root may be read.
+pub trait AccessPolicy: Send + Sync {
+    fn can_read(&self, peer: &PeerId, cid: &Cid) -> bool;
+
+    /// Also consulted for deletes.
+    fn can_write(&self, peer: &PeerId, cid: &Cid) -> bool;
+}
+
+/// Lets every peer read and write everything.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AllowAll;
+
+impl AccessPolicy for AllowAll {
+    fn can_read(&self, _peer: &PeerId, _cid: &Cid) -> bool {
+        true
+    }
+
+    fn can_write(&self, _peer: &PeerId, _cid: &Cid) -> bool {
+        true
+    }
+}
+
+/// Lets listed peers read or write any block, and no one else.
+///
+/// Write access doesn't imply read access.
+#[derive(Debug, Clone, Default)]
+pub struct Allowlist {
+    readers: HashSet<PeerId>,
+    writers: HashSet<PeerId>,
+}
+
+impl Allowlist {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn allow_read(mut self, peer: PeerId) -> Self {
+        self.readers.insert(peer);
+        self
+    }
+
+    pub fn allow_write(mut self, peer: PeerId) -> Self {
+        self.writers.insert(peer);
+        self
+    }
+}
+
+impl AccessPolicy for Allowlist {
+    fn can_read(&self, peer: &PeerId, _cid: &Cid) -> bool {
+        self.readers.contains(peer)
+    }
+
+    fn can_write(&self, peer: &PeerId, _cid: &Cid) -> bool {
+        self.writers.contains(peer)
+    }
+}
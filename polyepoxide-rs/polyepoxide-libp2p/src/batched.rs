@@ -0,0 +1,114 @@
+//! Pulling deep graphs from a peer in a handful of round trips.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use cid::Cid;
+use futures::future::try_join_all;
+use polyepoxide_core::{pull, AsyncStore, SyncError, SyncReport};
+
+use crate::remote_store::{RemoteStore, RemoteStoreError};
+
+/// Pull a value and all its dependencies from a peer, like `pull`, but
+/// fetch them with `PullSubgraph` requests of up to `max_nodes` values
+/// instead of one `Get` per level of the graph.
+///
+/// The peer doesn't know what dest already has and serves the subgraph
+/// regardless, so this suits graphs mostly missing locally, such as the
+/// first sync of a long conversation.
+pub async fn pull_batched<D: AsyncStore>(
+    remote: &RemoteStore,
+    dest: &D,
+    value_cid: Cid,
+    schema_cid: Cid,
+    max_nodes: usize,
+) -> Result<SyncReport, SyncError<RemoteStoreError, D::Error>> {
+    let source = Prefetch {
+        remote,
+        max_nodes,
+        blocks: Mutex::default(),
+        frontier: Mutex::new(HashMap::from([(value_cid, schema_cid)])),
+    };
+    pull(&source, dest, value_cid, schema_cid).await
+}
+
+/// Serves reads from prefetched subgraphs. Reading a frontier value requests
+/// the subgraph under it; other misses fall back to `Get`.
+struct Prefetch<'a> {
+    remote: &'a RemoteStore,
+    max_nodes: usize,
+    /// Received blocks not read yet. `pull` reads each block once.
+    blocks: Mutex<HashMap<Cid, Vec<u8>>>,
+    /// Values known to be left out of the received subgraphs, with their
+    /// schemas.
+    frontier: Mutex<HashMap<Cid, Cid>>,
+}
+
+impl Prefetch<'_> {
+    async fn prefetch(&self, root: Cid, schema: Cid) -> Result<(), RemoteStoreError> {
+        let subgraph = self
+            .remote
+            .pull_subgraph(root, schema, self.max_nodes)
+            .await?;
+        let mut blocks = self.blocks.lock().unwrap();
+        blocks.extend(subgraph.schemas);
+        blocks.extend(subgraph.nodes);
+        self.frontier.lock().unwrap().extend(subgraph.frontier);
+        Ok(())
+    }
+}
+
+impl AsyncStore for Prefetch<'_> {
+    type Error = RemoteStoreError;
+
+    async fn async_get(&self, cid: &Cid) -> Result<Option<Vec<u8>>, Self::Error> {
+        let results = self.async_get_many(&[*cid]).await?;
+        Ok(results.into_iter().next().flatten())
+    }
+
+    async fn async_get_many(&self, cids: &[Cid]) -> Result<Vec<Option<Vec<u8>>>, Self::Error> {
+        let roots: Vec<(Cid, Cid)> = {
+            let blocks = self.blocks.lock().unwrap();
+            let mut frontier = self.frontier.lock().unwrap();
+            cids.iter()
+                .filter(|cid| !blocks.contains_key(*cid))
+                .filter_map(|cid| frontier.remove_entry(cid))
+                .collect()
+        };
+        try_join_all(roots.into_iter().map(|(root, schema)| self.prefetch(root, schema))).await?;
+
+        let mut results: Vec<Option<Vec<u8>>> = {
+            let mut blocks = self.blocks.lock().unwrap();
+            cids.iter().map(|cid| blocks.remove(cid)).collect()
+        };
+        let missing: Vec<Cid> = cids
+            .iter()
+            .zip(&results)
+            .filter(|(_, result)| result.is_none())
+            .map(|(cid, _)| *cid)
+            .collect();
+        if !missing.is_empty() {
+            let mut fetched = self.remote.async_get_many(&missing).await?.into_iter();
+            for result in results.iter_mut().filter(|result| result.is_none()) {
+                *result = fetched.next().flatten();
+            }
+        }
+        Ok(results)
+    }
+
+    async fn async_put(&self, cid: &Cid, value: &[u8]) -> Result<(), Self::Error> {
+        self.remote.async_put(cid, value).await
+    }
+
+    async fn async_has(&self, cid: &Cid) -> Result<bool, Self::Error> {
+        self.remote.async_has(cid).await
+    }
+
+    async fn async_delete(&self, cid: &Cid) -> Result<(), Self::Error> {
+        self.remote.async_delete(cid).await
+    }
+
+    async fn async_list_cids(&self) -> Result<Vec<Cid>, Self::Error> {
+        self.remote.async_list_cids().await
+    }
+}
@@ -6,15 +6,52 @@ use async_trait::async_trait;
 use futures::prelude::*;
 use libp2p::request_response;
 use libp2p::StreamProtocol;
+use polyepoxide_core::DecodeLimits;
 
 use crate::protocol::{Request, Response, PROTOCOL_NAME};
 
-/// Maximum message size (16 MB).
-const MAX_MESSAGE_SIZE: u64 = 16 * 1024 * 1024;
+/// Default maximum message size (16 MB).
+pub(crate) const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
+
+/// Start of the error message sent in place of a response over the limit.
+pub(crate) const RESPONSE_TOO_LARGE: &str = "response too large";
 
 /// CBOR codec for Polyepoxide protocol.
-#[derive(Debug, Clone, Default)]
-pub struct PolyepoxideCodec;
+///
+/// Messages larger than `max_message_size` are rejected when read. A
+/// response that would be larger is replaced with `Response::Error`, so the
+/// requester learns why rather than seeing the stream fail.
+#[derive(Debug, Clone)]
+pub struct PolyepoxideCodec {
+    max_message_size: usize,
+}
+
+impl Default for PolyepoxideCodec {
+    fn default() -> Self {
+        Self {
+            max_message_size: MAX_MESSAGE_SIZE,
+        }
+    }
+}
+
+impl PolyepoxideCodec {
+    /// Sets the largest message sent or accepted, at most 4 GB.
+    pub fn with_max_message_size(mut self, size: usize) -> Self {
+        self.max_message_size = size.min(u32::MAX as usize);
+        self
+    }
+
+    /// Blocks travel inside messages as byte arrays, so collections may be
+    /// as long as the message itself, but the nesting of a valid message is
+    /// shallow.
+    fn limits(&self) -> DecodeLimits {
+        DecodeLimits {
+            max_depth: 16,
+            max_collection_len: self.max_message_size,
+            max_size: self.max_message_size,
+        }
+    }
+}
 
 #[async_trait]
 impl request_response::Codec for PolyepoxideCodec {
@@ -30,7 +67,7 @@ impl request_response::Codec for PolyepoxideCodec {
     where
         T: AsyncRead + Unpin + Send,
     {
-        read_cbor_message(io).await
+        read_cbor_message(io, &self.limits()).await
     }
 
     async fn read_response<T>(
@@ -41,7 +78,7 @@ impl request_response::Codec for PolyepoxideCodec {
     where
         T: AsyncRead + Unpin + Send,
     {
-        read_cbor_message(io).await
+        read_cbor_message(io, &self.limits()).await
     }
 
     async fn write_request<T>(
@@ -53,7 +90,7 @@ impl request_response::Codec for PolyepoxideCodec {
     where
         T: AsyncWrite + Unpin + Send,
     {
-        write_cbor_message(io, &req).await
+        write_cbor_message(io, &encode(&req)?, self.max_message_size).await
     }
 
     async fn write_response<T>(
@@ -65,12 +102,19 @@ impl request_response::Codec for PolyepoxideCodec {
     where
         T: AsyncWrite + Unpin + Send,
     {
-        write_cbor_message(io, &res).await
+        let mut buf = encode(&res)?;
+        if buf.len() > self.max_message_size {
+            let error = Response::Error {
+                message: format!("{}: {} bytes", RESPONSE_TOO_LARGE, buf.len()),
+            };
+            buf = encode(&error)?;
+        }
+        write_cbor_message(io, &buf, self.max_message_size).await
     }
 }
 
 /// Read a length-prefixed DAG-CBOR message.
-async fn read_cbor_message<T, M>(io: &mut T) -> io::Result<M>
+async fn read_cbor_message<T, M>(io: &mut T, limits: &DecodeLimits) -> io::Result<M>
 where
     T: AsyncRead + Unpin + Send,
     M: serde::de::DeserializeOwned,
@@ -78,9 +122,9 @@ where
     // Read 4-byte length prefix (big-endian)
     let mut len_buf = [0u8; 4];
     io.read_exact(&mut len_buf).await?;
-    let len = u32::from_be_bytes(len_buf) as u64;
+    let len = u32::from_be_bytes(len_buf) as usize;
 
-    if len > MAX_MESSAGE_SIZE {
+    if len > limits.max_size {
         return Err(io::Error::new(
             io::ErrorKind::InvalidData,
             format!("message too large: {} bytes", len),
@@ -88,25 +132,28 @@ where
     }
 
     // Read message body
-    let mut buf = vec![0u8; len as usize];
+    let mut buf = vec![0u8; len];
     io.read_exact(&mut buf).await?;
 
-    // Deserialize using DAG-CBOR
+    // Deserialize using DAG-CBOR, once it is known not to overflow the decoder
+    limits
+        .check(&buf)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
     serde_ipld_dagcbor::from_slice(&buf)
         .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
 }
 
+/// Serialize a message using DAG-CBOR.
+fn encode<M: serde::Serialize>(msg: &M) -> io::Result<Vec<u8>> {
+    serde_ipld_dagcbor::to_vec(msg).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
 /// Write a length-prefixed DAG-CBOR message.
-async fn write_cbor_message<T, M>(io: &mut T, msg: &M) -> io::Result<()>
+async fn write_cbor_message<T>(io: &mut T, buf: &[u8], max_size: usize) -> io::Result<()>
 where
     T: AsyncWrite + Unpin + Send,
-    M: serde::Serialize,
 {
-    // Serialize to buffer using DAG-CBOR
-    let buf = serde_ipld_dagcbor::to_vec(msg)
-        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-
-    if buf.len() as u64 > MAX_MESSAGE_SIZE {
+    if buf.len() > max_size {
         return Err(io::Error::new(
             io::ErrorKind::InvalidData,
             format!("message too large: {} bytes", buf.len()),
@@ -118,7 +165,7 @@ where
     io.write_all(&len_buf).await?;
 
     // Write message body
-    io.write_all(&buf).await?;
+    io.write_all(buf).await?;
 
     Ok(())
 }
@@ -127,3 +174,50 @@ where
 pub fn protocol() -> StreamProtocol {
     StreamProtocol::new(PROTOCOL_NAME)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::io::Cursor;
+    use polyepoxide_core::compute_cid;
+    use request_response::Codec;
+
+    #[tokio::test]
+    async fn oversized_response_becomes_error() {
+        let mut codec = PolyepoxideCodec::default().with_max_message_size(256);
+        let response = Response::Nodes {
+            found: vec![(compute_cid(b"big"), vec![0; 1024])],
+            missing: Vec::new(),
+        };
+        let mut buf = Cursor::new(Vec::new());
+        codec
+            .write_response(&protocol(), &mut buf, response)
+            .await
+            .unwrap();
+
+        buf.set_position(0);
+        let read = codec.read_response(&protocol(), &mut buf).await.unwrap();
+        match read {
+            Response::Error { message } => assert!(message.contains("too large")),
+            other => panic!("expected Error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn oversized_message_is_rejected() {
+        let mut codec = PolyepoxideCodec::default().with_max_message_size(256);
+        let request = Request::Put {
+            nodes: vec![(compute_cid(b"big"), vec![0; 1024])],
+        };
+        let mut buf = Cursor::new(Vec::new());
+        let written = codec.write_request(&protocol(), &mut buf, request).await;
+        assert!(written.is_err());
+
+        let mut frame = 1024u32.to_be_bytes().to_vec();
+        frame.resize(4 + 1024, 0);
+        let read = codec
+            .read_request(&protocol(), &mut Cursor::new(frame))
+            .await;
+        assert_eq!(read.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,112 @@
+//! Driving connectivity of a running swarm from application code.
+//!
+//! The swarm runner owns the swarm, so dialing, listening and listing
+//! connected peers go through its command channel with `dial`, `listen`
+//! and `connected_peers`. Connections opened and closed either way are
+//! reported as `ConnectionEvent`s.
+
+use libp2p::swarm::SwarmEvent;
+use libp2p::{Multiaddr, PeerId, Swarm};
+use tokio::sync::mpsc;
+
+use crate::remote_store::{request, Command, RemoteStoreError};
+use crate::PolyepoxideBehaviour;
+
+/// A change in the swarm's connections or listening addresses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionEvent {
+    /// The first connection to a peer was established.
+    Connected { peer: PeerId, address: Multiaddr },
+    /// The last connection to a peer was closed.
+    Disconnected { peer: PeerId },
+    /// Dialing failed; `peer` is set when dialing a known peer.
+    DialFailed { peer: Option<PeerId>, error: String },
+    /// The swarm accepts connections on a new address.
+    Listening { address: Multiaddr },
+}
+
+/// Starts dialing `address` through the swarm runner behind `command_tx`.
+/// Fails if the address can't be dialed at all; whether the connection
+/// succeeds is reported as a `ConnectionEvent`.
+pub async fn dial(
+    command_tx: &mpsc::Sender<Command>,
+    address: Multiaddr,
+) -> Result<(), RemoteStoreError> {
+    request(command_tx, |response_tx| Command::Dial {
+        address,
+        response_tx,
+    })
+    .await?
+}
+
+/// Starts listening on `address`, such as `/ip4/0.0.0.0/tcp/0`. The
+/// addresses actually listened on are reported as `ConnectionEvent`s.
+pub async fn listen(
+    command_tx: &mpsc::Sender<Command>,
+    address: Multiaddr,
+) -> Result<(), RemoteStoreError> {
+    request(command_tx, |response_tx| Command::Listen {
+        address,
+        response_tx,
+    })
+    .await?
+}
+
+/// Lists the peers the swarm is connected to.
+pub async fn connected_peers(
+    command_tx: &mpsc::Sender<Command>,
+) -> Result<Vec<PeerId>, RemoteStoreError> {
+    request(command_tx, |response_tx| Command::ConnectedPeers {
+        response_tx,
+    })
+    .await
+}
+
+pub(crate) fn dial_address(
+    swarm: &mut Swarm<PolyepoxideBehaviour>,
+    address: Multiaddr,
+) -> Result<(), RemoteStoreError> {
+    swarm
+        .dial(address)
+        .map_err(|e| RemoteStoreError::RequestFailed(e.to_string()))
+}
+
+pub(crate) fn listen_on(
+    swarm: &mut Swarm<PolyepoxideBehaviour>,
+    address: Multiaddr,
+) -> Result<(), RemoteStoreError> {
+    swarm
+        .listen_on(address)
+        .map(|_| ())
+        .map_err(|e| RemoteStoreError::RequestFailed(e.to_string()))
+}
+
+/// Reads the connection change a swarm event reports, if any.
+pub(crate) fn connection_event<E>(event: &SwarmEvent<E>) -> Option<ConnectionEvent> {
+    match event {
+        SwarmEvent::ConnectionEstablished {
+            peer_id,
+            endpoint,
+            num_established,
+            ..
+        } if num_established.get() == 1 => Some(ConnectionEvent::Connected {
+            peer: *peer_id,
+            address: endpoint.get_remote_address().clone(),
+        }),
+        SwarmEvent::ConnectionClosed {
+            peer_id,
+            num_established: 0,
+            ..
+        } => Some(ConnectionEvent::Disconnected { peer: *peer_id }),
+        SwarmEvent::OutgoingConnectionError { peer_id, error, .. } => {
+            Some(ConnectionEvent::DialFailed {
+                peer: *peer_id,
+                error: error.to_string(),
+            })
+        }
+        SwarmEvent::NewListenAddr { address, .. } => Some(ConnectionEvent::Listening {
+            address: address.clone(),
+        }),
+        _ => None,
+    }
+}
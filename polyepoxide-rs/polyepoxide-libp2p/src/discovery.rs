@@ -0,0 +1,209 @@
+//! Finding peers to sync with without configuring their addresses.
+//!
+//! With the `discovery` feature, `PolyepoxideBehaviour::with_discovery` adds
+//! mDNS, which finds peers on the local network, and Kademlia, which finds
+//! them through known bootstrap nodes. The swarm runner reports what they
+//! find as `DiscoveryEvent`s and lists it through `discover_peers`.
+
+use std::collections::HashMap;
+
+use libp2p::{Multiaddr, PeerId};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::remote_store::{Command, RemoteStoreError};
+
+/// A change in the peers known to the swarm runner.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiscoveryEvent {
+    /// A peer was found, or found at new addresses.
+    Discovered {
+        peer: PeerId,
+        addresses: Vec<Multiaddr>,
+    },
+    /// A peer is no longer announced at any known address.
+    Expired { peer: PeerId },
+}
+
+/// Discovery mechanisms to run alongside the sync protocol.
+#[cfg(feature = "discovery")]
+#[derive(Debug, Clone, Default)]
+pub struct DiscoveryConfig {
+    /// Find peers on the local network with mDNS.
+    pub mdns: bool,
+    /// Find peers through the Kademlia DHT.
+    pub kademlia: bool,
+    /// Kademlia nodes to join the DHT through.
+    pub bootstrap: Vec<(PeerId, Multiaddr)>,
+}
+
+#[cfg(feature = "discovery")]
+impl DiscoveryConfig {
+    pub fn with_mdns(mut self, enabled: bool) -> Self {
+        self.mdns = enabled;
+        self
+    }
+
+    pub fn with_kademlia(mut self, enabled: bool) -> Self {
+        self.kademlia = enabled;
+        self
+    }
+
+    pub fn with_bootstrap(mut self, peer: PeerId, address: Multiaddr) -> Self {
+        self.bootstrap.push((peer, address));
+        self
+    }
+}
+
+/// List the peers the swarm runner behind `command_tx` has discovered so
+/// far, with their addresses. Also starts a Kademlia lookup for more peers,
+/// reported later as `DiscoveryEvent`s.
+pub async fn discover_peers(
+    command_tx: &mpsc::Sender<Command>,
+) -> Result<Vec<(PeerId, Vec<Multiaddr>)>, RemoteStoreError> {
+    let (tx, rx) = oneshot::channel();
+    command_tx
+        .send(Command::DiscoverPeers { response_tx: tx })
+        .await
+        .map_err(|_| RemoteStoreError::ConnectionClosed)?;
+    rx.await.map_err(|_| RemoteStoreError::ConnectionClosed)
+}
+
+/// Addresses of the peers discovered so far.
+#[derive(Debug, Default)]
+pub(crate) struct KnownPeers {
+    peers: HashMap<PeerId, Vec<Multiaddr>>,
+}
+
+// Only the discovery behaviours find peers
+#[cfg_attr(not(feature = "discovery"), allow(dead_code))]
+impl KnownPeers {
+    /// Records addresses of a peer, returning an event if any are new.
+    pub(crate) fn discovered(
+        &mut self,
+        peer: PeerId,
+        addresses: Vec<Multiaddr>,
+    ) -> Option<DiscoveryEvent> {
+        let known = self.peers.entry(peer).or_default();
+        let new: Vec<Multiaddr> = addresses
+            .into_iter()
+            .filter(|address| !known.contains(address))
+            .collect();
+        if new.is_empty() {
+            return None;
+        }
+        known.extend(new.iter().cloned());
+        Some(DiscoveryEvent::Discovered {
+            peer,
+            addresses: new,
+        })
+    }
+
+    /// Forgets an address of a peer, returning an event if it was the last.
+    pub(crate) fn expired(&mut self, peer: PeerId, address: &Multiaddr) -> Option<DiscoveryEvent> {
+        let known = self.peers.get_mut(&peer)?;
+        known.retain(|known| known != address);
+        if !known.is_empty() {
+            return None;
+        }
+        self.peers.remove(&peer);
+        Some(DiscoveryEvent::Expired { peer })
+    }
+
+    pub(crate) fn list(&self) -> Vec<(PeerId, Vec<Multiaddr>)> {
+        self.peers
+            .iter()
+            .map(|(peer, addresses)| (*peer, addresses.clone()))
+            .collect()
+    }
+}
+
+#[cfg(feature = "discovery")]
+pub(crate) use enabled::{bootstrap, handle_event};
+
+#[cfg(not(feature = "discovery"))]
+pub(crate) fn bootstrap(_swarm: &mut libp2p::Swarm<crate::PolyepoxideBehaviour>) {}
+
+#[cfg(feature = "discovery")]
+mod enabled {
+    use libp2p::{kad, mdns, Swarm};
+    use tokio::sync::mpsc;
+
+    use super::{DiscoveryEvent, KnownPeers};
+    use crate::{PolyepoxideBehaviour, PolyepoxideBehaviourEvent};
+
+    /// Starts a Kademlia lookup of random peers, if Kademlia is enabled.
+    pub(crate) fn bootstrap(swarm: &mut Swarm<PolyepoxideBehaviour>) {
+        if let Some(kademlia) = swarm.behaviour_mut().kademlia.as_mut() {
+            // Fails only when no node is known yet; mDNS may add some later
+            let _ = kademlia.bootstrap();
+        }
+    }
+
+    /// Records peers found by mDNS or Kademlia and reports them on
+    /// `events_tx`, dropping events if nobody keeps up with them.
+    pub(crate) fn handle_event(
+        swarm: &mut Swarm<PolyepoxideBehaviour>,
+        event: PolyepoxideBehaviourEvent,
+        known: &mut KnownPeers,
+        events_tx: Option<&mpsc::Sender<DiscoveryEvent>>,
+    ) {
+        let mut events = Vec::new();
+        match event {
+            PolyepoxideBehaviourEvent::Mdns(mdns::Event::Discovered(found)) => {
+                for (peer, address) in found {
+                    // Peers on the LAN also serve as Kademlia bootstrap nodes
+                    if let Some(kademlia) = swarm.behaviour_mut().kademlia.as_mut() {
+                        kademlia.add_address(&peer, address.clone());
+                    }
+                    events.extend(known.discovered(peer, vec![address]));
+                }
+            }
+            PolyepoxideBehaviourEvent::Mdns(mdns::Event::Expired(lost)) => {
+                for (peer, address) in lost {
+                    events.extend(known.expired(peer, &address));
+                }
+            }
+            PolyepoxideBehaviourEvent::Kademlia(kad::Event::RoutingUpdated {
+                peer,
+                addresses,
+                ..
+            }) => {
+                events.extend(known.discovered(peer, addresses.into_vec()));
+            }
+            _ => {}
+        }
+        if let Some(events_tx) = events_tx {
+            for event in events {
+                let _ = events_tx.try_send(event);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_peers_report_changes_only() {
+        let mut known = KnownPeers::default();
+        let peer = PeerId::random();
+        let lan: Multiaddr = "/ip4/192.168.1.2/tcp/4001".parse().unwrap();
+        let wan: Multiaddr = "/ip4/203.0.113.7/tcp/4001".parse().unwrap();
+
+        assert!(known.discovered(peer, vec![lan.clone()]).is_some());
+        assert_eq!(known.discovered(peer, vec![lan.clone()]), None);
+        assert_eq!(
+            known.discovered(peer, vec![lan.clone(), wan.clone()]),
+            Some(DiscoveryEvent::Discovered {
+                peer,
+                addresses: vec![wan.clone()],
+            })
+        );
+        assert_eq!(known.list(), vec![(peer, vec![lan.clone(), wan.clone()])]);
+
+        assert_eq!(known.expired(peer, &lan), None);
+        assert_eq!(known.expired(peer, &wan), Some(DiscoveryEvent::Expired { peer }));
+        assert!(known.list().is_empty());
+    }
+}
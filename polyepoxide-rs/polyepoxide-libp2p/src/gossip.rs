@@ -0,0 +1,142 @@
+//! Announcing new roots to every peer following an application.
+//!
+//! `PolyepoxideBehaviour::with_gossip` adds gossipsub, with one topic per
+//! application such as `aldehyde/inventory`. Peers follow a topic with
+//! `subscribe_roots` and announce roots they wrote with `publish_root`. The
+//! swarm runner delivers roots published by others as `Announcement`s, the
+//! same as direct `AnnounceRoot` requests, and `auto_pull` pulls each one.
+
+use cid::Cid;
+use libp2p::gossipsub::{self, IdentTopic};
+use libp2p::{identity::Keypair, Swarm};
+use polyepoxide_core::{pull, AsyncStore, SyncReport};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use crate::access::AccessPolicy;
+use crate::remote_store::{request, Command, RemoteStore, RemoteStoreError};
+use crate::{Announcement, PolyepoxideBehaviour};
+
+/// Body of a message on an application topic.
+#[derive(Serialize, Deserialize)]
+struct RootMessage {
+    root: Cid,
+    schema: Cid,
+}
+
+/// Starts following the roots published on `topic` through the swarm runner
+/// behind `command_tx`.
+pub async fn subscribe_roots(
+    command_tx: &mpsc::Sender<Command>,
+    topic: &str,
+) -> Result<(), RemoteStoreError> {
+    request(command_tx, |response_tx| Command::Subscribe {
+        topic: topic.to_string(),
+        response_tx,
+    })
+    .await?
+}
+
+/// Tells every peer following `topic` that `root` can be pulled from here.
+///
+/// Fails if no peer following the topic is connected yet.
+pub async fn publish_root(
+    command_tx: &mpsc::Sender<Command>,
+    topic: &str,
+    root: Cid,
+    schema: Cid,
+) -> Result<(), RemoteStoreError> {
+    request(command_tx, |response_tx| Command::PublishRoot {
+        topic: topic.to_string(),
+        root,
+        schema,
+        response_tx,
+    })
+    .await?
+}
+
+/// Pulls every announced root from the peer that published it into `store`,
+/// calling `pulled` after each complete pull. Runs until `announcements`
+/// closes; failed pulls are logged and skipped.
+pub async fn auto_pull<D: AsyncStore>(
+    mut announcements: mpsc::Receiver<Announcement>,
+    command_tx: mpsc::Sender<Command>,
+    store: D,
+    mut pulled: impl FnMut(Announcement, SyncReport),
+) {
+    while let Some(announcement) = announcements.recv().await {
+        let remote = RemoteStore::new(announcement.peer, command_tx.clone());
+        match pull(&remote, &store, announcement.root, announcement.schema).await {
+            Ok(report) => pulled(announcement, report),
+            Err(error) => {
+                let (peer, root) = (announcement.peer, announcement.root);
+                tracing::warn!(%peer, %root, %error, "pulling announced root failed");
+            }
+        }
+    }
+}
+
+/// Builds the gossipsub behaviour, signing messages with `keypair` so
+/// receivers know which peer to pull from.
+pub(crate) fn behaviour(keypair: &Keypair) -> std::io::Result<gossipsub::Behaviour> {
+    let config = gossipsub::ConfigBuilder::default()
+        .validation_mode(gossipsub::ValidationMode::Strict)
+        .build()
+        .map_err(std::io::Error::other)?;
+    gossipsub::Behaviour::new(
+        gossipsub::MessageAuthenticity::Signed(keypair.clone()),
+        config,
+    )
+    .map_err(std::io::Error::other)
+}
+
+fn enabled(
+    swarm: &mut Swarm<PolyepoxideBehaviour>,
+) -> Result<&mut gossipsub::Behaviour, RemoteStoreError> {
+    swarm
+        .behaviour_mut()
+        .gossip
+        .as_mut()
+        .ok_or_else(|| RemoteStoreError::Unsupported("gossip".to_string()))
+}
+
+pub(crate) fn subscribe(
+    swarm: &mut Swarm<PolyepoxideBehaviour>,
+    topic: &str,
+) -> Result<(), RemoteStoreError> {
+    enabled(swarm)?
+        .subscribe(&IdentTopic::new(topic))
+        .map(|_| ())
+        .map_err(|e| RemoteStoreError::RequestFailed(e.to_string()))
+}
+
+pub(crate) fn publish(
+    swarm: &mut Swarm<PolyepoxideBehaviour>,
+    topic: &str,
+    root: Cid,
+    schema: Cid,
+) -> Result<(), RemoteStoreError> {
+    let data = serde_ipld_dagcbor::to_vec(&RootMessage { root, schema })
+        .map_err(|e| RemoteStoreError::RequestFailed(e.to_string()))?;
+    enabled(swarm)?
+        .publish(IdentTopic::new(topic), data)
+        .map(|_| ())
+        .map_err(|e| RemoteStoreError::RequestFailed(e.to_string()))
+}
+
+/// Reads the root announced by a gossip message. Other events, malformed
+/// messages and roots the publisher may not write yield nothing.
+pub(crate) fn announcement(
+    event: gossipsub::Event,
+    policy: &dyn AccessPolicy,
+) -> Option<Announcement> {
+    let gossipsub::Event::Message { message, .. } = event else {
+        return None;
+    };
+    let RootMessage { root, schema } = serde_ipld_dagcbor::from_slice(&message.data).ok()?;
+    // Strict validation rejects unsigned messages, so the source is set
+    let peer = message.source?;
+    policy
+        .can_write(&peer, &root)
+        .then_some(Announcement { peer, root, schema })
+}
@@ -1,41 +1,115 @@
 //! Request handler for serving local store data to peers.
 
-use polyepoxide_core::AsyncStore;
+use cid::Cid;
+use libp2p::PeerId;
+use polyepoxide_core::{walk_subgraph, AsyncStore, VerifyingStore};
 
-use crate::protocol::{Request, Response};
+use crate::access::AccessPolicy;
+use crate::protocol::{Capabilities, Request, Response};
+
+/// Most values served for one `PullSubgraph` request.
+pub const MAX_SUBGRAPH_NODES: usize = 4096;
 
 /// Handle an incoming request against a local store.
+///
+/// Optional requests such as `Delete` are refused; use
+/// `handle_request_with` to serve them.
 pub async fn handle_request<S: AsyncStore>(store: &S, request: Request) -> Response {
+    handle_request_with(store, request, Capabilities::default()).await
+}
+
+/// Handle an incoming request, serving the optional requests enabled in
+/// `capabilities`.
+pub async fn handle_request_with<S: AsyncStore>(
+    store: &S,
+    request: Request,
+    capabilities: Capabilities,
+) -> Response {
+    serve(store, request, capabilities, |_, _| true).await
+}
+
+/// Handle a request from `peer`, serving only the blocks `policy` lets it
+/// access. Blocks it may not read are reported missing; writes and deletes
+/// are refused whole if any block is denied.
+pub async fn handle_request_from<S, P>(
+    store: &S,
+    peer: &PeerId,
+    request: Request,
+    capabilities: Capabilities,
+    policy: &P,
+) -> Response
+where
+    S: AsyncStore,
+    P: AccessPolicy + ?Sized,
+{
+    serve(store, request, capabilities, |access, cid| match access {
+        Access::Read => policy.can_read(peer, cid),
+        Access::Write => policy.can_write(peer, cid),
+    })
+    .await
+}
+
+enum Access {
+    Read,
+    Write,
+}
+
+async fn serve<S: AsyncStore>(
+    store: &S,
+    request: Request,
+    capabilities: Capabilities,
+    allowed: impl Fn(Access, &Cid) -> bool,
+) -> Response {
     match request {
-        Request::Get { cids } => match store.async_get_many(&cids).await {
-            Ok(results) => {
-                let mut found = Vec::new();
-                let mut missing = Vec::new();
-
-                for (cid, result) in cids.into_iter().zip(results) {
-                    match result {
-                        Some(data) => found.push((cid, data)),
-                        None => missing.push(cid),
+        Request::Get { cids } => {
+            let (cids, mut missing): (Vec<Cid>, Vec<Cid>) =
+                cids.into_iter().partition(|cid| allowed(Access::Read, cid));
+            match store.async_get_many(&cids).await {
+                Ok(results) => {
+                    let mut found = Vec::new();
+
+                    for (cid, result) in cids.into_iter().zip(results) {
+                        match result {
+                            Some(data) => found.push((cid, data)),
+                            None => missing.push(cid),
+                        }
                     }
-                }
 
-                Response::Nodes { found, missing }
+                    Response::Nodes { found, missing }
+                }
+                Err(e) => Response::Error {
+                    message: e.to_string(),
+                },
             }
-            Err(e) => Response::Error {
-                message: e.to_string(),
-            },
-        },
+        }
 
         Request::Has { cids } => match store.async_has_many(&cids).await {
-            Ok(present) => Response::Has { present },
+            Ok(present) => Response::Has {
+                present: cids
+                    .iter()
+                    .zip(present)
+                    .map(|(cid, present)| present && allowed(Access::Read, cid))
+                    .collect(),
+            },
             Err(e) => Response::Error {
                 message: e.to_string(),
             },
         },
 
         Request::Put { nodes } => {
+            if let Some((cid, _)) = nodes.iter().find(|(cid, _)| !allowed(Access::Write, cid)) {
+                return Response::Denied { cid: *cid };
+            }
+            // Reject unsafe blocks before storing any
+            for (cid, data) in &nodes {
+                if let Err(e) = capabilities.limits.check(data) {
+                    return Response::Error {
+                        message: format!("block {} rejected: {}", cid, e),
+                    };
+                }
+            }
             let refs: Vec<_> = nodes.iter().map(|(k, v)| (k, v.as_slice())).collect();
-            match store.async_put_many(&refs).await {
+            match VerifyingStore::new(store).async_put_many(&refs).await {
                 Ok(()) => Response::Stored {
                     cids: nodes.into_iter().map(|(k, _)| k).collect(),
                 },
@@ -44,13 +118,66 @@ pub async fn handle_request<S: AsyncStore>(store: &S, request: Request) -> Respo
                 },
             }
         }
+
+        Request::Delete { .. } if !capabilities.delete => Response::Unsupported {
+            capability: "delete".to_string(),
+        },
+
+        Request::Delete { cids } => {
+            if let Some(cid) = cids.iter().find(|cid| !allowed(Access::Write, cid)) {
+                return Response::Denied { cid: *cid };
+            }
+            match store.async_delete_many(&cids).await {
+                Ok(()) => Response::Deleted { cids },
+                Err(e) => Response::Error {
+                    message: e.to_string(),
+                },
+            }
+        }
+
+        Request::AnnounceRoot { root, .. } if !allowed(Access::Write, &root) => {
+            Response::Denied { cid: root }
+        }
+
+        Request::AnnounceRoot { .. } => Response::Acknowledged,
+
+        Request::PullSubgraph { root, .. } if !allowed(Access::Read, &root) => {
+            Response::Denied { cid: root }
+        }
+
+        Request::PullSubgraph {
+            root,
+            schema,
+            max_nodes,
+        } => {
+            let max_nodes = max_nodes.min(MAX_SUBGRAPH_NODES);
+            let max_bytes = capabilities.max_subgraph_bytes();
+            match walk_subgraph(store, root, schema, max_nodes, max_bytes).await {
+                Ok(subgraph) => Response::Subgraph {
+                    schemas: subgraph.schemas,
+                    nodes: subgraph.nodes,
+                    frontier: subgraph.frontier,
+                },
+                Err(e) => Response::Error {
+                    message: e.to_string(),
+                },
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use polyepoxide_core::{compute_cid, MemoryStore, Store};
+    use crate::access::Allowlist;
+    use polyepoxide_core::{compute_cid, Bond, MemoryStore, Oxide, Solvent, Store};
+    use std::sync::Arc;
+
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, Oxide)]
+    struct Node {
+        label: String,
+        next: Option<Bond<Node>>,
+    }
 
     #[tokio::test]
     async fn handle_get_found() {
@@ -111,12 +238,13 @@ mod tests {
     #[tokio::test]
     async fn handle_put() {
         let store = MemoryStore::new();
-        let cid = compute_cid(b"new");
+        // DAG-CBOR integer 0, so the block passes the decode limits
+        let cid = compute_cid(&[0x00]);
 
         let response = handle_request(
             &store,
             Request::Put {
-                nodes: vec![(cid, b"value".to_vec())],
+                nodes: vec![(cid, vec![0x00])],
             },
         )
         .await;
@@ -128,6 +256,159 @@ mod tests {
         }
 
         // Verify it was actually stored
-        assert_eq!(store.get(&cid).unwrap(), Some(b"value".to_vec()));
+        assert_eq!(store.get(&cid).unwrap(), Some(vec![0x00]));
+
+        let announce = Request::AnnounceRoot {
+            root: cid,
+            schema: cid,
+        };
+        let response = handle_request(&store, announce).await;
+        assert!(matches!(response, Response::Acknowledged));
+    }
+
+    #[tokio::test]
+    async fn handle_put_rejects_nested_blocks() {
+        let store = MemoryStore::new();
+        let mut deep = vec![0x81; 1000];
+        deep.push(0x00);
+        let nodes = vec![(compute_cid(&[0x00]), vec![0x00]), (compute_cid(&deep), deep)];
+
+        let response = handle_request(&store, Request::Put { nodes }).await;
+
+        assert!(matches!(response, Response::Error { .. }));
+        assert!(!store.has(&compute_cid(&[0x00])).unwrap());
+    }
+
+    #[tokio::test]
+    async fn handle_put_rejects_forged_blocks() {
+        let store = MemoryStore::new();
+        let cid = compute_cid(b"original");
+
+        let put = Request::Put {
+            nodes: vec![(cid, b"forged".to_vec())],
+        };
+        let response = handle_request(&store, put).await;
+
+        assert!(matches!(response, Response::Error { .. }));
+        assert!(!store.has(&cid).unwrap());
+    }
+
+    #[tokio::test]
+    async fn handle_pull_subgraph() {
+        let store = MemoryStore::new();
+        let mut solvent = Solvent::new();
+        let leaf = solvent.add(Node {
+            label: "leaf".into(),
+            next: None,
+        });
+        let root = solvent.add(Node {
+            label: "root".into(),
+            next: Some(Bond::from_cell(Arc::clone(&leaf))),
+        });
+        let (root_cid, schema_cid) = solvent.persist_cell(&root, &store).unwrap();
+
+        let request = Request::PullSubgraph {
+            root: root_cid,
+            schema: schema_cid,
+            max_nodes: 10,
+        };
+        let response = handle_request(&store, request).await;
+
+        if let Response::Subgraph {
+            schemas,
+            nodes,
+            frontier,
+        } = response
+        {
+            assert!(!schemas.is_empty());
+            let cids: Vec<_> = nodes.iter().map(|(cid, _)| *cid).collect();
+            assert_eq!(cids, vec![leaf.cid(), root_cid]);
+            assert!(frontier.is_empty());
+        } else {
+            panic!("Expected Subgraph response");
+        }
+    }
+
+    #[tokio::test]
+    async fn pull_subgraph_fits_max_message_size() {
+        let store = MemoryStore::new();
+        let mut solvent = Solvent::new();
+        let leaf = solvent.add(Node {
+            label: "leaf".repeat(256),
+            next: None,
+        });
+        let root = solvent.add(Node {
+            label: "root".into(),
+            next: Some(Bond::from_cell(Arc::clone(&leaf))),
+        });
+        let (root_cid, schema_cid) = solvent.persist_cell(&root, &store).unwrap();
+
+        let request = Request::PullSubgraph {
+            root: root_cid,
+            schema: schema_cid,
+            max_nodes: 10,
+        };
+        let capabilities = Capabilities::default().with_max_message_size(1024);
+        let response = handle_request_with(&store, request, capabilities).await;
+
+        if let Response::Subgraph {
+            nodes, frontier, ..
+        } = response
+        {
+            let cids: Vec<_> = nodes.iter().map(|(cid, _)| *cid).collect();
+            assert_eq!(cids, vec![root_cid]);
+            assert_eq!(frontier, vec![(leaf.cid(), schema_cid)]);
+        } else {
+            panic!("Expected Subgraph response");
+        }
+    }
+
+    #[tokio::test]
+    async fn handle_request_from_applies_policy() {
+        let store = MemoryStore::new();
+        let cid = compute_cid(b"private");
+        store.put(&cid, b"private").unwrap();
+        let reader = PeerId::random();
+        let stranger = PeerId::random();
+        let policy = Allowlist::new().allow_read(reader);
+        let capabilities = Capabilities::default();
+
+        let get = Request::Get { cids: vec![cid] };
+        let response = handle_request_from(&store, &reader, get.clone(), capabilities, &policy);
+        assert!(matches!(response.await, Response::Nodes { found, .. } if found.len() == 1));
+        let response = handle_request_from(&store, &stranger, get, capabilities, &policy);
+        assert!(matches!(response.await, Response::Nodes { missing, .. } if missing == [cid]));
+
+        let has = Request::Has { cids: vec![cid] };
+        let response = handle_request_from(&store, &stranger, has, capabilities, &policy);
+        assert!(matches!(response.await, Response::Has { present } if present == [false]));
+
+        let put = Request::Put {
+            nodes: vec![(compute_cid(b"new"), b"new".to_vec())],
+        };
+        let response = handle_request_from(&store, &reader, put, capabilities, &policy);
+        assert!(matches!(response.await, Response::Denied { .. }));
+        assert!(!store.has(&compute_cid(b"new")).unwrap());
+    }
+
+    #[tokio::test]
+    async fn handle_delete_requires_capability() {
+        let store = MemoryStore::new();
+        let cid = compute_cid(b"doomed");
+        store.put(&cid, b"value").unwrap();
+        let request = Request::Delete { cids: vec![cid] };
+
+        let response = handle_request(&store, request.clone()).await;
+        assert!(matches!(response, Response::Unsupported { .. }));
+        assert!(store.has(&cid).unwrap());
+
+        let capabilities = Capabilities::default().with_delete(true);
+        let response = handle_request_with(&store, request, capabilities).await;
+        if let Response::Deleted { cids } = response {
+            assert_eq!(cids, vec![cid]);
+        } else {
+            panic!("Expected Deleted response");
+        }
+        assert!(!store.has(&cid).unwrap());
     }
 }
@@ -6,8 +6,28 @@
 //! # Architecture
 //!
 //! - `RemoteStore` implements `AsyncStore` for a remote peer
-//! - `PolyepoxideCodec` handles CBOR serialization over libp2p streams
-//! - `handle_request` processes incoming requests against a local store
+//! - `MultiSourceStore` spreads reads over several peers holding the same data,
+//!   or each part of it, falling back to other peers for blocks one lacks
+//! - `RemoteSolvent` loads typed values from a peer as bonds are followed
+//! - `pull_batched` pulls deep graphs with `PullSubgraph` requests, each
+//!   answered with many values in dependency-first order
+//! - `PolyepoxideCodec` handles CBOR serialization over libp2p streams,
+//!   rejecting messages over a configurable size
+//! - `handle_request` processes incoming requests against a local store;
+//!   optional requests like deletes are only served if enabled in `Capabilities`,
+//!   and `handle_request_from` checks each peer against an `AccessPolicy`
+//! - `SwarmOptions::with_rate_limit` answers peers sending requests too fast
+//!   with `Response::RateLimited`, which `RemoteStore` waits out and retries
+//! - Failed requests are logged with `tracing`; with the `metrics` feature
+//!   they are also counted in `polyepoxide_libp2p_failures_total{direction}`,
+//!   and core's sync and store metrics are enabled
+//! - `dial`, `listen` and `connected_peers` drive a running swarm's
+//!   connectivity, reported back as `ConnectionEvent`s
+//! - With the `discovery` feature, mDNS and Kademlia find peers without
+//!   configured addresses; see `discover_peers` and `DiscoveryEvent`
+//! - `PolyepoxideBehaviour::with_gossip` announces roots over a gossipsub
+//!   topic per application; see `subscribe_roots`, `publish_root` and
+//!   `auto_pull`
 //!
 //! # Example
 //!
@@ -22,43 +42,141 @@
 //! pull(&remote, &local_store, value_cid, schema_cid).await?;
 //! ```
 
+mod access;
+mod batched;
 mod codec;
+mod connections;
+mod discovery;
+mod gossip;
 mod handler;
+mod multi_source;
 mod protocol;
+mod rate_limit;
+mod remote_solvent;
 mod remote_store;
 
+pub use access::{AccessPolicy, AllowAll, Allowlist};
+pub use batched::pull_batched;
 pub use codec::{protocol, PolyepoxideCodec};
-pub use handler::handle_request;
-pub use protocol::{Request, Response, PROTOCOL_NAME};
+pub use connections::{connected_peers, dial, listen, ConnectionEvent};
+#[cfg(feature = "discovery")]
+pub use discovery::DiscoveryConfig;
+pub use discovery::{discover_peers, DiscoveryEvent};
+pub use gossip::{auto_pull, publish_root, subscribe_roots};
+pub use handler::{handle_request, handle_request_from, handle_request_with};
+pub use multi_source::{MultiSourceError, MultiSourceStore, SourceHealth};
+pub use protocol::{Capabilities, Request, Response, PROTOCOL_NAME};
+pub use rate_limit::RateLimit;
+pub use remote_solvent::{RemoteSolvent, RemoteSolventError};
 pub use remote_store::{Command, RemoteStore, RemoteStoreError};
 
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
 
+use cid::Cid;
 use futures::StreamExt;
+use libp2p::identity::Keypair;
 use libp2p::request_response::{self, OutboundRequestId};
+use libp2p::swarm::behaviour::toggle::Toggle;
 use libp2p::swarm::{NetworkBehaviour, SwarmEvent};
-use libp2p::Swarm;
+use libp2p::{gossipsub, PeerId, Swarm};
 use polyepoxide_core::AsyncStore;
 use tokio::sync::{mpsc, oneshot};
 
-/// Behaviour combining request_response for sync protocol.
+use discovery::KnownPeers;
+use rate_limit::RateLimiter;
+
+/// Behaviour combining request_response for sync protocol with optional
+/// root announcements.
+#[cfg(not(feature = "discovery"))]
 #[derive(NetworkBehaviour)]
 pub struct PolyepoxideBehaviour {
     pub sync: request_response::Behaviour<PolyepoxideCodec>,
+    pub gossip: Toggle<gossipsub::Behaviour>,
+}
+
+/// Behaviour combining request_response for sync protocol with optional
+/// root announcements and peer discovery.
+#[cfg(feature = "discovery")]
+#[derive(NetworkBehaviour)]
+pub struct PolyepoxideBehaviour {
+    pub sync: request_response::Behaviour<PolyepoxideCodec>,
+    pub gossip: Toggle<gossipsub::Behaviour>,
+    pub mdns: Toggle<libp2p::mdns::tokio::Behaviour>,
+    pub kademlia: Toggle<libp2p::kad::Behaviour<libp2p::kad::store::MemoryStore>>,
 }
 
 impl PolyepoxideBehaviour {
     /// Create a new behaviour with the sync protocol.
     pub fn new() -> Self {
-        let config = request_response::Config::default();
-        let sync = request_response::Behaviour::new(
-            [(protocol(), request_response::ProtocolSupport::Full)],
-            config,
+        let sync = sync_behaviour(
+            PolyepoxideCodec::default(),
+            request_response::Config::default(),
         );
-        Self { sync }
+        Self {
+            sync,
+            gossip: None.into(),
+            #[cfg(feature = "discovery")]
+            mdns: None.into(),
+            #[cfg(feature = "discovery")]
+            kademlia: None.into(),
+        }
+    }
+
+    /// Replaces the sync protocol with one using `codec` and `config`, e.g.
+    /// for a smaller message size or a longer request timeout. Peers should
+    /// agree on the message size, and `Capabilities::with_max_message_size`
+    /// should be given the same one, so responses fit in it.
+    pub fn with_sync(mut self, codec: PolyepoxideCodec, config: request_response::Config) -> Self {
+        self.sync = sync_behaviour(codec, config);
+        self
+    }
+
+    /// Enables root announcements over gossipsub, signed with the swarm's
+    /// `keypair`.
+    pub fn with_gossip(mut self, keypair: &Keypair) -> std::io::Result<Self> {
+        self.gossip = Some(gossip::behaviour(keypair)?).into();
+        Ok(self)
+    }
+
+    /// Create a new behaviour with the sync protocol and the discovery
+    /// mechanisms enabled in `config`.
+    #[cfg(feature = "discovery")]
+    pub fn with_discovery(peer_id: PeerId, config: &DiscoveryConfig) -> std::io::Result<Self> {
+        use libp2p::{kad, mdns};
+
+        let mut behaviour = Self::new();
+        if config.mdns {
+            let mdns = mdns::tokio::Behaviour::new(mdns::Config::default(), peer_id)?;
+            behaviour.mdns = Some(mdns).into();
+        }
+        if config.kademlia {
+            let store = kad::store::MemoryStore::new(peer_id);
+            let mut kademlia = kad::Behaviour::new(peer_id, store);
+            // Answer queries even without a confirmed public address, so
+            // peers on a LAN can find each other
+            kademlia.set_mode(Some(kad::Mode::Server));
+            for (peer, address) in &config.bootstrap {
+                kademlia.add_address(peer, address.clone());
+            }
+            behaviour.kademlia = Some(kademlia).into();
+        }
+        Ok(behaviour)
     }
 }
 
+fn sync_behaviour(
+    codec: PolyepoxideCodec,
+    config: request_response::Config,
+) -> request_response::Behaviour<PolyepoxideCodec> {
+    request_response::Behaviour::with_codec(
+        codec,
+        [(protocol(), request_response::ProtocolSupport::Full)],
+        config,
+    )
+}
+
 impl Default for PolyepoxideBehaviour {
     fn default() -> Self {
         Self::new()
@@ -72,15 +190,109 @@ impl Default for PolyepoxideBehaviour {
 /// - Inbound requests by calling the handler with the local store
 /// - Response matching for pending requests
 pub async fn run_swarm<S, T>(
+    swarm: Swarm<PolyepoxideBehaviour>,
+    local_store: S,
+    command_rx: mpsc::Receiver<Command>,
+) where
+    S: AsyncStore + 'static,
+    T: Send,
+{
+    run_swarm_with(swarm, local_store, command_rx, SwarmOptions::default()).await
+}
+
+/// Settings for `run_swarm_with`.
+#[derive(Clone)]
+pub struct SwarmOptions {
+    /// Optional requests served to peers.
+    pub capabilities: Capabilities,
+    /// Which blocks each peer may read and write.
+    pub policy: Arc<dyn AccessPolicy>,
+    /// Receives discovered peers. Events are dropped while it is full.
+    pub events: Option<mpsc::Sender<DiscoveryEvent>>,
+    /// Receives opened and closed connections and new listening addresses.
+    /// Events are dropped while it is full.
+    pub connections: Option<mpsc::Sender<ConnectionEvent>>,
+    /// Receives roots announced by peers, directly or on a followed gossip
+    /// topic, which can then be pulled from them. Announcements are dropped
+    /// while it is full or the peer may not write the root.
+    pub announcements: Option<mpsc::Sender<Announcement>>,
+    /// How many requests each peer may send; unlimited if unset.
+    pub rate_limit: Option<RateLimit>,
+}
+
+/// A root a peer announced with `Request::AnnounceRoot` or `publish_root`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Announcement {
+    pub peer: PeerId,
+    pub root: Cid,
+    pub schema: Cid,
+}
+
+impl Default for SwarmOptions {
+    fn default() -> Self {
+        Self {
+            capabilities: Capabilities::default(),
+            policy: Arc::new(AllowAll),
+            events: None,
+            connections: None,
+            announcements: None,
+            rate_limit: None,
+        }
+    }
+}
+
+impl SwarmOptions {
+    pub fn with_capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    pub fn with_policy(mut self, policy: impl AccessPolicy + 'static) -> Self {
+        self.policy = Arc::new(policy);
+        self
+    }
+
+    pub fn with_events(mut self, events: mpsc::Sender<DiscoveryEvent>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    pub fn with_connections(mut self, connections: mpsc::Sender<ConnectionEvent>) -> Self {
+        self.connections = Some(connections);
+        self
+    }
+
+    pub fn with_announcements(mut self, announcements: mpsc::Sender<Announcement>) -> Self {
+        self.announcements = Some(announcements);
+        self
+    }
+
+    pub fn with_rate_limit(mut self, rate_limit: RateLimit) -> Self {
+        self.rate_limit = Some(rate_limit);
+        self
+    }
+}
+
+/// Like `run_swarm`, but serving requests as configured in `options`.
+///
+/// Each inbound request is handled on its own task, so a slow one, such as
+/// a deep `PullSubgraph`, doesn't hold up the swarm.
+#[cfg_attr(not(feature = "discovery"), allow(unused_mut))]
+pub async fn run_swarm_with<S>(
     mut swarm: Swarm<PolyepoxideBehaviour>,
     local_store: S,
     mut command_rx: mpsc::Receiver<Command>,
+    options: SwarmOptions,
 ) where
-    S: AsyncStore,
-    T: Send,
+    S: AsyncStore + 'static,
 {
     let mut pending_requests: HashMap<OutboundRequestId, oneshot::Sender<Result<Response, RemoteStoreError>>> =
         HashMap::new();
+    let mut known_peers = KnownPeers::default();
+    let mut rate_limiter = options.rate_limit.map(RateLimiter::new);
+    let local_store = Arc::new(local_store);
+    // Responses of the handler tasks, sent back to the peers by the swarm
+    let (response_tx, mut response_rx) = mpsc::unbounded_channel();
 
     loop {
         tokio::select! {
@@ -94,19 +306,78 @@ pub async fn run_swarm<S, T>(
                     Command::SendResponse { channel, response } => {
                         let _ = swarm.behaviour_mut().sync.send_response(channel, response);
                     }
+                    Command::DiscoverPeers { response_tx } => {
+                        discovery::bootstrap(&mut swarm);
+                        let _ = response_tx.send(known_peers.list());
+                    }
+                    Command::Subscribe { topic, response_tx } => {
+                        let _ = response_tx.send(gossip::subscribe(&mut swarm, &topic));
+                    }
+                    Command::PublishRoot { topic, root, schema, response_tx } => {
+                        let _ = response_tx.send(gossip::publish(&mut swarm, &topic, root, schema));
+                    }
+                    Command::Dial { address, response_tx } => {
+                        let _ = response_tx.send(connections::dial_address(&mut swarm, address));
+                    }
+                    Command::Listen { address, response_tx } => {
+                        let _ = response_tx.send(connections::listen_on(&mut swarm, address));
+                    }
+                    Command::ConnectedPeers { response_tx } => {
+                        let _ = response_tx.send(swarm.connected_peers().copied().collect());
+                    }
                 }
             }
 
+            // Send the responses of handled requests
+            Some((channel, response)) = response_rx.recv() => {
+                let _ = swarm.behaviour_mut().sync.send_response(channel, response);
+            }
+
             // Handle swarm events
             event = swarm.select_next_some() => {
+                if let (Some(change), Some(tx)) =
+                    (connections::connection_event(&event), &options.connections)
+                {
+                    let _ = tx.try_send(change);
+                }
                 match event {
                     SwarmEvent::Behaviour(PolyepoxideBehaviourEvent::Sync(req_res_event)) => {
                         match req_res_event {
-                            request_response::Event::Message { peer: _, message } => {
+                            request_response::Event::Message { peer, message } => {
                                 match message {
                                     request_response::Message::Request { request, channel, .. } => {
-                                        let response = handle_request(&local_store, request).await;
-                                        let _ = swarm.behaviour_mut().sync.send_response(channel, response);
+                                        let limited = rate_limiter.as_mut().map(|l| l.check(peer, Instant::now()));
+                                        if let Some(Err(retry_after)) = limited {
+                                            let retry_after_ms = u64::try_from(retry_after.as_millis()).unwrap_or(u64::MAX);
+                                            let response = Response::RateLimited { retry_after_ms };
+                                            let _ = swarm.behaviour_mut().sync.send_response(channel, response);
+                                            continue;
+                                        }
+                                        let announced = match request {
+                                            Request::AnnounceRoot { root, schema } => Some((root, schema)),
+                                            _ => None,
+                                        };
+                                        let store = local_store.clone();
+                                        let capabilities = options.capabilities;
+                                        let policy = options.policy.clone();
+                                        let announcements = options.announcements.clone();
+                                        let response_tx = response_tx.clone();
+                                        tokio::spawn(async move {
+                                            let response = handle_request_from(
+                                                store.as_ref(),
+                                                &peer,
+                                                request,
+                                                capabilities,
+                                                policy.as_ref(),
+                                            )
+                                            .await;
+                                            if let (Some((root, schema)), Response::Acknowledged, Some(tx)) =
+                                                (announced, &response, &announcements)
+                                            {
+                                                let _ = tx.try_send(Announcement { peer, root, schema });
+                                            }
+                                            let _ = response_tx.send((channel, response));
+                                        });
                                     }
                                     request_response::Message::Response { request_id, response } => {
                                         if let Some(tx) = pending_requests.remove(&request_id) {
@@ -115,20 +386,49 @@ pub async fn run_swarm<S, T>(
                                     }
                                 }
                             }
-                            request_response::Event::OutboundFailure { request_id, error, .. } => {
+                            request_response::Event::OutboundFailure { peer, request_id, error, .. } => {
+                                tracing::warn!(%peer, %error, "request to peer failed");
+                                #[cfg(feature = "metrics")]
+                                count_failure("outbound");
                                 if let Some(tx) = pending_requests.remove(&request_id) {
-                                    let _ = tx.send(Err(RemoteStoreError::RequestFailed(error.to_string())));
+                                    let error = match error {
+                                        request_response::OutboundFailure::UnsupportedProtocols => {
+                                            RemoteStoreError::Unsupported(PROTOCOL_NAME.to_string())
+                                        }
+                                        error => RemoteStoreError::RequestFailed(error.to_string()),
+                                    };
+                                    let _ = tx.send(Err(error));
                                 }
                             }
-                            request_response::Event::InboundFailure { .. } => {
-                                // Log but don't crash
+                            request_response::Event::InboundFailure { peer, error, .. } => {
+                                tracing::warn!(%peer, %error, "request from peer failed");
+                                #[cfg(feature = "metrics")]
+                                count_failure("inbound");
                             }
                             request_response::Event::ResponseSent { .. } => {}
                         }
                     }
+                    SwarmEvent::Behaviour(PolyepoxideBehaviourEvent::Gossip(event)) => {
+                        let policy = options.policy.as_ref();
+                        if let (Some(announcement), Some(tx)) =
+                            (gossip::announcement(event, policy), &options.announcements)
+                        {
+                            let _ = tx.try_send(announcement);
+                        }
+                    }
+                    #[cfg(feature = "discovery")]
+                    SwarmEvent::Behaviour(event) => {
+                        let events = options.events.as_ref();
+                        discovery::handle_event(&mut swarm, event, &mut known_peers, events);
+                    }
                     _ => {}
                 }
             }
         }
     }
 }
+
+#[cfg(feature = "metrics")]
+fn count_failure(direction: &'static str) {
+    metrics::counter!("polyepoxide_libp2p_failures_total", "direction" => direction).increment(1);
+}
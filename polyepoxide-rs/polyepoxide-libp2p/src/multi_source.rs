@@ -0,0 +1,477 @@
+//! MultiSourceStore - fetches blocks from several peers holding the same
+//! data or parts of it.
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use cid::Cid;
+use futures::future::join_all;
+use libp2p::PeerId;
+use polyepoxide_core::AsyncStore;
+use tokio::sync::mpsc;
+
+use crate::remote_store::{Command, RemoteStore};
+
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Weight of the newest sample in the latency moving average.
+const LATENCY_SMOOTHING: f64 = 0.3;
+
+/// Error from multi-source operations.
+#[derive(Debug, thiserror::Error)]
+pub enum MultiSourceError<E> {
+    #[error("no sources configured")]
+    NoSources,
+    #[error("all sources failed: {0}")]
+    AllFailed(E),
+    #[error("all sources timed out")]
+    TimedOut,
+    #[error("source failed: {0}")]
+    SourceFailed(E),
+}
+
+/// Observed health of a single source.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SourceHealth {
+    /// Smoothed request latency, `None` until the first successful request.
+    pub latency: Option<Duration>,
+    /// Failures (errors or timeouts) since the last success.
+    pub consecutive_failures: u32,
+}
+
+/// Several stores holding the same data, or each part of it, exposed as a
+/// single AsyncStore.
+///
+/// Batch reads are split across sources so different blocks come from
+/// different peers in parallel. Sources are ranked by health — fewest recent
+/// failures first, then lowest latency. A batch is first split across the
+/// sources that haven't failed since their last success; blocks a source
+/// failed to deliver or doesn't have are then asked of the next one, which is
+/// how a failed source gets the chance to recover.
+pub struct MultiSourceStore<S = RemoteStore> {
+    sources: Vec<S>,
+    health: Vec<Mutex<SourceHealth>>,
+    request_timeout: Duration,
+}
+
+impl<S: AsyncStore> MultiSourceStore<S> {
+    pub fn new(sources: Vec<S>) -> Self {
+        let health = sources.iter().map(|_| Mutex::default()).collect();
+        Self {
+            sources,
+            health,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+        }
+    }
+
+    /// Sets how long to wait for a single source before treating it as slow
+    /// and retrying its blocks elsewhere.
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    pub fn sources(&self) -> &[S] {
+        &self.sources
+    }
+
+    /// Returns the current health of each source, in construction order.
+    pub fn health(&self) -> Vec<SourceHealth> {
+        self.health.iter().map(|h| *h.lock().unwrap()).collect()
+    }
+
+    /// Source indices ordered best-first.
+    fn ranked(&self) -> Vec<usize> {
+        let health = self.health();
+        let mut order: Vec<usize> = (0..self.sources.len()).collect();
+        order.sort_by_key(|&i| {
+            (
+                health[i].consecutive_failures,
+                health[i].latency.unwrap_or(Duration::ZERO),
+            )
+        });
+        order
+    }
+
+    /// Number of sources that haven't failed since their last success.
+    fn healthy_count(&self) -> usize {
+        self.health
+            .iter()
+            .filter(|h| h.lock().unwrap().consecutive_failures == 0)
+            .count()
+    }
+
+    fn record_success(&self, idx: usize, elapsed: Duration) {
+        let mut health = self.health[idx].lock().unwrap();
+        health.consecutive_failures = 0;
+        health.latency = Some(match health.latency {
+            Some(avg) => avg.mul_f64(1.0 - LATENCY_SMOOTHING) + elapsed.mul_f64(LATENCY_SMOOTHING),
+            None => elapsed,
+        });
+    }
+
+    fn record_failure(&self, idx: usize) {
+        self.health[idx].lock().unwrap().consecutive_failures += 1;
+    }
+
+    /// Fetches from a single source. `Err(None)` means the source timed out.
+    async fn fetch_from(
+        &self,
+        idx: usize,
+        cids: &[Cid],
+    ) -> Result<Vec<Option<Vec<u8>>>, Option<S::Error>> {
+        let start = Instant::now();
+        let result =
+            tokio::time::timeout(self.request_timeout, self.sources[idx].async_get_many(cids))
+                .await;
+        match result {
+            Ok(Ok(values)) => {
+                self.record_success(idx, start.elapsed());
+                Ok(values)
+            }
+            Ok(Err(e)) => {
+                self.record_failure(idx);
+                Err(Some(e))
+            }
+            Err(_) => {
+                self.record_failure(idx);
+                Err(None)
+            }
+        }
+    }
+}
+
+impl MultiSourceStore {
+    /// Reads from `peers` through the swarm runner behind `command_tx`, such
+    /// as several devices that each hold part of a DAG.
+    pub fn for_peers(
+        peers: impl IntoIterator<Item = PeerId>,
+        command_tx: &mpsc::Sender<Command>,
+    ) -> Self {
+        let sources = peers
+            .into_iter()
+            .map(|peer| RemoteStore::new(peer, command_tx.clone()))
+            .collect();
+        Self::new(sources)
+    }
+}
+
+impl<S: AsyncStore> AsyncStore for MultiSourceStore<S> {
+    type Error = MultiSourceError<S::Error>;
+
+    async fn async_get(&self, cid: &Cid) -> Result<Option<Vec<u8>>, Self::Error> {
+        let results = self.async_get_many(&[*cid]).await?;
+        Ok(results.into_iter().next().flatten())
+    }
+
+    async fn async_get_many(&self, cids: &[Cid]) -> Result<Vec<Option<Vec<u8>>>, Self::Error> {
+        let ranked = self.ranked();
+        let n = ranked.len();
+        if n == 0 {
+            return Err(MultiSourceError::NoSources);
+        }
+
+        // The first round only goes to sources without recent failures,
+        // which rank first; if every source failed lately, it goes to all.
+        let healthy = self.healthy_count();
+        let spread = if healthy == 0 { n } else { healthy };
+
+        let mut results = vec![None; cids.len()];
+        let mut answered = vec![false; cids.len()];
+        let mut pending: Vec<usize> = (0..cids.len()).collect();
+        let mut last_error = None;
+
+        // In round r, block i goes to ranked[(i % spread + r) % n], so every
+        // block visits each source at most once and the first round spreads
+        // the batch across the healthy sources.
+        for round in 0..n {
+            if pending.is_empty() {
+                break;
+            }
+
+            let mut batches: Vec<Vec<usize>> = vec![Vec::new(); n];
+            for &i in &pending {
+                batches[(i % spread + round) % n].push(i);
+            }
+
+            let attempts = join_all(
+                batches
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, batch)| !batch.is_empty())
+                    .map(|(slot, batch)| {
+                        let idx = ranked[slot];
+                        let batch_cids: Vec<Cid> = batch.iter().map(|&i| cids[i]).collect();
+                        async move { (batch, self.fetch_from(idx, &batch_cids).await) }
+                    }),
+            )
+            .await;
+
+            let mut next = Vec::new();
+            for (batch, attempt) in attempts {
+                match attempt {
+                    Ok(values) => {
+                        for (&i, value) in batch.iter().zip(values) {
+                            answered[i] = true;
+                            match value {
+                                Some(data) => results[i] = Some(data),
+                                None => next.push(i),
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        last_error = e.or(last_error);
+                        next.extend(batch);
+                    }
+                }
+            }
+            pending = next;
+        }
+
+        // A block no source could answer for is an error, not a miss
+        if pending.iter().any(|&i| !answered[i]) {
+            return Err(match last_error {
+                Some(e) => MultiSourceError::AllFailed(e),
+                None => MultiSourceError::TimedOut,
+            });
+        }
+
+        Ok(results)
+    }
+
+    async fn async_put(&self, cid: &Cid, value: &[u8]) -> Result<(), Self::Error> {
+        self.async_put_many(&[(cid, value)]).await
+    }
+
+    /// Writes to all sources; succeeds if at least one accepted the write.
+    async fn async_put_many(&self, nodes: &[(&Cid, &[u8])]) -> Result<(), Self::Error> {
+        let results = join_all(self.sources.iter().map(|s| s.async_put_many(nodes))).await;
+        let mut last_error = None;
+        for result in results {
+            match result {
+                Ok(()) => return Ok(()),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.map_or(MultiSourceError::NoSources, MultiSourceError::AllFailed))
+    }
+
+    async fn async_delete(&self, cid: &Cid) -> Result<(), Self::Error> {
+        self.async_delete_many(&[*cid]).await
+    }
+
+    /// Deletes from all sources; fails if any source failed, since the data
+    /// would otherwise remain readable.
+    async fn async_delete_many(&self, cids: &[Cid]) -> Result<(), Self::Error> {
+        if self.sources.is_empty() {
+            return Err(MultiSourceError::NoSources);
+        }
+        let results = join_all(self.sources.iter().map(|s| s.async_delete_many(cids))).await;
+        for result in results {
+            result.map_err(MultiSourceError::SourceFailed)?;
+        }
+        Ok(())
+    }
+
+    /// Lists the union of all sources' CIDs; fails if any source can't list.
+    async fn async_list_cids(&self) -> Result<Vec<Cid>, Self::Error> {
+        if self.sources.is_empty() {
+            return Err(MultiSourceError::NoSources);
+        }
+        let results = join_all(self.sources.iter().map(|s| s.async_list_cids())).await;
+        let mut seen = HashSet::new();
+        let mut cids = Vec::new();
+        for result in results {
+            for cid in result.map_err(MultiSourceError::SourceFailed)? {
+                if seen.insert(cid) {
+                    cids.push(cid);
+                }
+            }
+        }
+        Ok(cids)
+    }
+
+    async fn async_has(&self, cid: &Cid) -> Result<bool, Self::Error> {
+        let results = self.async_has_many(&[*cid]).await?;
+        Ok(results.into_iter().next().unwrap_or(false))
+    }
+
+    /// A CID is present if any source has it.
+    async fn async_has_many(&self, cids: &[Cid]) -> Result<Vec<bool>, Self::Error> {
+        let mut present = vec![false; cids.len()];
+        let mut any_answered = false;
+        let mut last_error = None;
+
+        for idx in self.ranked() {
+            let missing: Vec<Cid> = cids
+                .iter()
+                .zip(&present)
+                .filter(|(_, p)| !**p)
+                .map(|(c, _)| *c)
+                .collect();
+            if missing.is_empty() {
+                break;
+            }
+
+            let start = Instant::now();
+            match self.sources[idx].async_has_many(&missing).await {
+                Ok(flags) => {
+                    self.record_success(idx, start.elapsed());
+                    any_answered = true;
+                    let mut flags = flags.into_iter();
+                    for p in present.iter_mut().filter(|p| !**p) {
+                        *p = flags.next().unwrap_or(false);
+                    }
+                }
+                Err(e) => {
+                    self.record_failure(idx);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        if !any_answered {
+            return Err(last_error.map_or(MultiSourceError::NoSources, MultiSourceError::AllFailed));
+        }
+        Ok(present)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use polyepoxide_core::{compute_cid, MemoryStore, Store};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    /// A source that can be taken down, counting the reads it is asked for.
+    #[derive(Default)]
+    struct Flaky {
+        store: MemoryStore,
+        down: AtomicBool,
+        reads: AtomicUsize,
+    }
+
+    impl Flaky {
+        fn check(&self) -> Result<(), std::io::Error> {
+            if self.down.load(Ordering::SeqCst) {
+                return Err(std::io::Error::other("source down"));
+            }
+            Ok(())
+        }
+    }
+
+    impl AsyncStore for &Flaky {
+        type Error = std::io::Error;
+
+        async fn async_get(&self, cid: &Cid) -> Result<Option<Vec<u8>>, Self::Error> {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            self.check()?;
+            Ok(self.store.get(cid).unwrap())
+        }
+
+        async fn async_put(&self, cid: &Cid, value: &[u8]) -> Result<(), Self::Error> {
+            self.check()?;
+            self.store.put(cid, value).unwrap();
+            Ok(())
+        }
+
+        async fn async_has(&self, cid: &Cid) -> Result<bool, Self::Error> {
+            self.check()?;
+            Ok(self.store.has(cid).unwrap())
+        }
+
+        async fn async_delete(&self, cid: &Cid) -> Result<(), Self::Error> {
+            self.check()?;
+            self.store.delete(cid).unwrap();
+            Ok(())
+        }
+
+        async fn async_list_cids(&self) -> Result<Vec<Cid>, Self::Error> {
+            self.check()?;
+            Ok(self.store.list_cids().unwrap())
+        }
+    }
+
+    #[tokio::test]
+    async fn get_many_across_sources() {
+        let a = MemoryStore::new();
+        let b = MemoryStore::new();
+        let cids: Vec<Cid> = (0..4u8).map(|i| compute_cid(&[i])).collect();
+
+        // Each source holds only half of the blocks
+        for (i, cid) in cids.iter().enumerate() {
+            let store = if i % 2 == 0 { &a } else { &b };
+            store.put(cid, &[i as u8]).unwrap();
+        }
+        let missing = compute_cid(b"nowhere");
+
+        let multi = MultiSourceStore::new(vec![a, b]);
+        let mut query = cids.clone();
+        query.push(missing);
+        let results = multi.async_get_many(&query).await.unwrap();
+
+        for (i, result) in results.iter().take(4).enumerate() {
+            assert_eq!(result.as_deref(), Some(&[i as u8][..]));
+        }
+        assert_eq!(results[4], None);
+        assert!(multi.async_has(&cids[1]).await.unwrap());
+        assert!(!multi.async_has(&missing).await.unwrap());
+        assert!(multi.health().iter().all(|h| h.latency.is_some()));
+    }
+
+    #[tokio::test]
+    async fn fails_over_and_skips_unhealthy_sources() {
+        let down = Flaky::default();
+        let up = Flaky::default();
+        let cids: Vec<Cid> = (0..4u8).map(|i| compute_cid(&[i])).collect();
+        for (i, cid) in cids.iter().enumerate() {
+            down.store.put(cid, &[i as u8]).unwrap();
+            up.store.put(cid, &[i as u8]).unwrap();
+        }
+        down.down.store(true, Ordering::SeqCst);
+
+        let multi = MultiSourceStore::new(vec![&down, &up]);
+        let results = multi.async_get_many(&cids).await.unwrap();
+        assert!(results.iter().all(Option::is_some));
+        let health = multi.health();
+        assert_eq!(health[0].consecutive_failures, 1);
+        assert_eq!(health[1].consecutive_failures, 0);
+
+        // The failed source is left out of the first round from now on
+        let before = down.reads.load(Ordering::SeqCst);
+        multi.async_get_many(&cids).await.unwrap();
+        assert_eq!(down.reads.load(Ordering::SeqCst), before);
+    }
+
+    #[tokio::test]
+    async fn unhealthy_source_recovers() {
+        let flaky = Flaky::default();
+        let steady = Flaky::default();
+        let shared = compute_cid(b"shared");
+        let only_flaky = compute_cid(b"only flaky");
+        flaky.store.put(&shared, b"shared").unwrap();
+        flaky.store.put(&only_flaky, b"only flaky").unwrap();
+        steady.store.put(&shared, b"shared").unwrap();
+
+        flaky.down.store(true, Ordering::SeqCst);
+        let multi = MultiSourceStore::new(vec![&flaky, &steady]);
+        multi.async_get_many(&[shared, shared]).await.unwrap();
+        assert_eq!(multi.health()[0].consecutive_failures, 1);
+
+        // A block the healthy source lacks is still asked of the other one,
+        // and its answer clears the failures
+        flaky.down.store(false, Ordering::SeqCst);
+        let value = multi.async_get(&only_flaky).await.unwrap();
+        assert_eq!(value.as_deref(), Some(&b"only flaky"[..]));
+        assert_eq!(multi.health()[0].consecutive_failures, 0);
+
+        // Answering existence checks counts as success too
+        flaky.down.store(true, Ordering::SeqCst);
+        multi.async_get_many(&[shared, shared]).await.unwrap();
+        assert_eq!(multi.health()[0].consecutive_failures, 1);
+        flaky.down.store(false, Ordering::SeqCst);
+        assert!(multi.async_has(&only_flaky).await.unwrap());
+        assert_eq!(multi.health()[0].consecutive_failures, 0);
+    }
+}
@@ -1,8 +1,11 @@
 //! Protocol messages for Polyepoxide sync over libp2p.
 
 use cid::Cid;
+use polyepoxide_core::DecodeLimits;
 use serde::{Deserialize, Serialize};
 
+use crate::codec::MAX_MESSAGE_SIZE;
+
 pub const PROTOCOL_NAME: &str = "/polyepoxide/sync/0.1.0";
 
 /// Request types for the sync protocol.
@@ -12,8 +15,21 @@ pub enum Request {
     Get { cids: Vec<Cid> },
     /// Check which CIDs exist.
     Has { cids: Vec<Cid> },
-    /// Store values at the given CIDs.
+    /// Store values at the given CIDs. Values that don't hash to their CID
+    /// are rejected.
     Put { nodes: Vec<(Cid, Vec<u8>)> },
+    /// Remove the given CIDs. Only served by peers that enable deletion.
+    Delete { cids: Vec<Cid> },
+    /// Tell the peer that a new root is available here, such as the head
+    /// of a conversation just pushed with `Put`.
+    AnnounceRoot { root: Cid, schema: Cid },
+    /// Get a value together with as much of its subgraph as fits in one
+    /// response, up to `max_nodes` values.
+    PullSubgraph {
+        root: Cid,
+        schema: Cid,
+        max_nodes: usize,
+    },
 }
 
 /// Response types for the sync protocol.
@@ -28,10 +44,79 @@ pub enum Response {
     Has { present: Vec<bool> },
     /// Response to Put: CIDs that were stored.
     Stored { cids: Vec<Cid> },
+    /// Response to Delete: CIDs that were removed.
+    Deleted { cids: Vec<Cid> },
+    /// Response to AnnounceRoot.
+    Acknowledged,
+    /// Response to PullSubgraph: the schemas needed to traverse `nodes`,
+    /// the values in dependency-first order, and the `(value, schema)`
+    /// bonds that didn't fit.
+    Subgraph {
+        schemas: Vec<(Cid, Vec<u8>)>,
+        nodes: Vec<(Cid, Vec<u8>)>,
+        frontier: Vec<(Cid, Cid)>,
+    },
+    /// The peer doesn't serve this kind of request.
+    Unsupported { capability: String },
+    /// The requesting peer may not access this block.
+    Denied { cid: Cid },
+    /// The requesting peer sent too many requests; it may retry after
+    /// `retry_after_ms` milliseconds.
+    RateLimited { retry_after_ms: u64 },
     /// Error response.
     Error { message: String },
 }
 
+/// Optional requests a peer is willing to serve, and what it accepts.
+///
+/// Reads and writes are always served. Optional requests are off by default,
+/// since they let any connected peer alter the local store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Serve `Request::Delete`.
+    pub delete: bool,
+    /// Limits every block written by `Request::Put` must satisfy.
+    pub limits: DecodeLimits,
+    /// Largest message the codec sends; `PullSubgraph` responses are sized
+    /// to fit in it.
+    pub max_message_size: usize,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Self {
+            delete: false,
+            limits: DecodeLimits::default(),
+            max_message_size: MAX_MESSAGE_SIZE,
+        }
+    }
+}
+
+impl Capabilities {
+    pub fn with_delete(mut self, enabled: bool) -> Self {
+        self.delete = enabled;
+        self
+    }
+
+    pub fn with_limits(mut self, limits: DecodeLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Sizes responses for a codec built with the same
+    /// `PolyepoxideCodec::with_max_message_size`.
+    pub fn with_max_message_size(mut self, size: usize) -> Self {
+        self.max_message_size = size;
+        self
+    }
+
+    /// Most value bytes served for one `PullSubgraph` request, leaving half
+    /// the message for schemas and framing.
+    pub(crate) fn max_subgraph_bytes(&self) -> usize {
+        self.max_message_size / 2
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
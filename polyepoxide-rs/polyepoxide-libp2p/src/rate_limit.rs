@@ -0,0 +1,80 @@
+//! Per-peer limits on the rate of served requests.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use libp2p::PeerId;
+
+/// Peers tracked before those with full allowances are forgotten.
+const MAX_TRACKED_PEERS: usize = 1024;
+
+/// How many requests each peer may send: `burst` at once, refilled at
+/// `per_second`. Requests over the limit are answered with
+/// `Response::RateLimited` instead of being served.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub per_second: u32,
+    pub burst: u32,
+}
+
+/// Token buckets of the peers that sent requests recently.
+pub(crate) struct RateLimiter {
+    limit: RateLimit,
+    buckets: HashMap<PeerId, (f64, Instant)>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            buckets: HashMap::new(),
+        }
+    }
+
+    /// Takes one request from the peer's allowance, or returns how long
+    /// until it has one.
+    pub(crate) fn check(&mut self, peer: PeerId, now: Instant) -> Result<(), Duration> {
+        let burst = self.limit.burst as f64;
+        let rate = self.limit.per_second as f64;
+        if self.buckets.len() >= MAX_TRACKED_PEERS {
+            self.buckets
+                .retain(|_, (tokens, updated)| *tokens + refill(*updated, now, rate) < burst);
+        }
+        let (tokens, updated) = self.buckets.entry(peer).or_insert((burst, now));
+        *tokens = (*tokens + refill(*updated, now, rate)).min(burst);
+        *updated = now;
+        if *tokens >= 1.0 {
+            *tokens -= 1.0;
+            Ok(())
+        } else if rate > 0.0 {
+            Err(Duration::from_secs_f64((1.0 - *tokens) / rate))
+        } else {
+            Err(Duration::MAX)
+        }
+    }
+}
+
+fn refill(updated: Instant, now: Instant, rate: f64) -> f64 {
+    now.saturating_duration_since(updated).as_secs_f64() * rate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limits_each_peer_separately() {
+        let mut limiter = RateLimiter::new(RateLimit {
+            per_second: 2,
+            burst: 2,
+        });
+        let (a, b) = (PeerId::random(), PeerId::random());
+        let start = Instant::now();
+
+        assert!(limiter.check(a, start).is_ok());
+        assert!(limiter.check(a, start).is_ok());
+        assert_eq!(limiter.check(a, start), Err(Duration::from_millis(500)));
+        assert!(limiter.check(b, start).is_ok());
+        assert!(limiter.check(a, start + Duration::from_millis(500)).is_ok());
+    }
+}
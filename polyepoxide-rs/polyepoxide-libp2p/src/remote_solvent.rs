@@ -0,0 +1,136 @@
+//! RemoteSolvent - typed values loaded from a peer as they are accessed.
+
+use std::sync::Arc;
+
+use cid::Cid;
+use polyepoxide_core::{compute_cid, AsyncStore, Bond, Cell, Oxide, Solvent, SolventError};
+
+use crate::remote_store::RemoteStore;
+
+/// Error from loading a value through a `RemoteSolvent`.
+#[derive(Debug, thiserror::Error)]
+pub enum RemoteSolventError<E> {
+    #[error("value not found: {0}")]
+    NotFound(Cid),
+    #[error("content does not match CID {0}")]
+    Corrupted(Cid),
+    #[error("invalid value {0}: {1}")]
+    Invalid(Cid, SolventError),
+    #[error("source error: {0}")]
+    Source(E),
+}
+
+/// A solvent filled on demand from a remote store.
+///
+/// Loading a bond fetches just its block, so a peer's data can be browsed
+/// without pulling it all first. Loaded values stay in the solvent, and
+/// later loads of them, or of values bonding to them, are served locally.
+/// Bonds of a loaded value stay unresolved until they are loaded in turn.
+pub struct RemoteSolvent<S = RemoteStore> {
+    source: S,
+    solvent: Solvent,
+}
+
+impl<S: AsyncStore> RemoteSolvent<S> {
+    pub fn new(source: S) -> Self {
+        Self::with_solvent(source, Solvent::new())
+    }
+
+    /// Serves loads from `solvent` before asking `source`.
+    pub fn with_solvent(source: S, solvent: Solvent) -> Self {
+        Self { source, solvent }
+    }
+
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    /// The values loaded so far.
+    pub fn solvent(&self) -> &Solvent {
+        &self.solvent
+    }
+
+    pub fn into_solvent(self) -> Solvent {
+        self.solvent
+    }
+
+    /// Returns the value a bond points to, fetching it if it isn't resolved
+    /// or loaded yet.
+    ///
+    /// The fetched block is checked against the bond's CID and the solvent's
+    /// decode limits before it is decoded as `T`.
+    pub async fn load<T: Oxide>(
+        &mut self,
+        bond: &Bond<T>,
+    ) -> Result<Arc<Cell<T>>, RemoteSolventError<S::Error>> {
+        if let Some(cell) = bond.cell() {
+            return Ok(Arc::clone(cell));
+        }
+        let cid = bond.cid();
+        if let Some(cell) = self.solvent.get::<T>(&cid) {
+            return Ok(cell);
+        }
+
+        let bytes = self
+            .source
+            .async_get(&cid)
+            .await
+            .map_err(RemoteSolventError::Source)?
+            .ok_or(RemoteSolventError::NotFound(cid))?;
+        if compute_cid(&bytes) != cid {
+            return Err(RemoteSolventError::Corrupted(cid));
+        }
+        self.solvent
+            .decode(&bytes)
+            .map_err(|e| RemoteSolventError::Invalid(cid, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use polyepoxide_core::{MemoryStore, Store};
+
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, Oxide)]
+    struct Author {
+        name: String,
+    }
+
+    #[derive(Debug, Clone, serde::Serialize, serde::Deserialize, Oxide)]
+    struct Chapter {
+        title: String,
+        author: Bond<Author>,
+    }
+
+    #[tokio::test]
+    async fn load_fetches_on_demand() {
+        let source = MemoryStore::new();
+        let mut solvent = Solvent::new();
+        let author = solvent.add(Author {
+            name: "Ada".into(),
+        });
+        let chapter = solvent.add(Chapter {
+            title: "Engines".into(),
+            author: Bond::from_cell(author),
+        });
+        let (chapter_cid, _) = solvent.persist_cell(&chapter, &source).unwrap();
+
+        let mut remote = RemoteSolvent::new(source);
+        let loaded = remote.load(&Bond::<Chapter>::from_cid(chapter_cid)).await.unwrap();
+        assert_eq!(loaded.value().title, "Engines");
+        assert!(!loaded.value().author.is_resolved());
+        assert_eq!(remote.solvent().len(), 1);
+
+        let author = remote.load(&loaded.value().author).await.unwrap();
+        assert_eq!(author.value().name, "Ada");
+
+        // Loaded values no longer need the source
+        remote.source().delete(&author.cid()).unwrap();
+        assert!(remote.load(&loaded.value().author).await.is_ok());
+
+        let forged = compute_cid(b"expected");
+        remote.source().put(&forged, b"served").unwrap();
+        let result = remote.load(&Bond::<Author>::from_cid(forged)).await;
+        assert!(matches!(result, Err(RemoteSolventError::Corrupted(_))));
+    }
+}
@@ -1,13 +1,15 @@
 //! RemoteStore - wraps a libp2p peer as an AsyncStore.
 
 use std::collections::HashMap;
+use std::time::Duration;
 
 use cid::Cid;
 use libp2p::request_response::ResponseChannel;
-use libp2p::PeerId;
-use polyepoxide_core::AsyncStore;
+use libp2p::{Multiaddr, PeerId};
+use polyepoxide_core::{AsyncStore, Subgraph};
 use tokio::sync::{mpsc, oneshot};
 
+use crate::codec::RESPONSE_TOO_LARGE;
 use crate::protocol::{Request, Response};
 
 /// Error from remote store operations.
@@ -21,8 +23,40 @@ pub enum RemoteStoreError {
     UnexpectedResponse,
     #[error("remote error: {0}")]
     Remote(String),
+    #[error("peer does not support {0}")]
+    Unsupported(String),
+    #[error("access to {0} denied")]
+    Denied(Cid),
+    #[error("request timed out")]
+    Timeout,
+    #[error("rate limited by peer, retry after {0:?}")]
+    RateLimited(Duration),
 }
 
+impl RemoteStoreError {
+    /// Whether sending the request again may succeed, as after a dropped
+    /// connection, a timeout or a rate limit. Refusals and errors reported
+    /// by the peer would only repeat.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Self::RequestFailed(_) | Self::Timeout | Self::RateLimited(_)
+        )
+    }
+}
+
+const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_RETRIES: u32 = 3;
+const DEFAULT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Longest wait between two attempts of a request.
+const MAX_BACKOFF: Duration = Duration::from_secs(10);
+
+/// CIDs asked for in one `Get` request. Blob chunks are at most 64KB, so a
+/// full batch of them fits in a message; batches of larger values whose
+/// response is too large are split further.
+const MAX_GET_BATCH: usize = 64;
+
 /// Command sent to the swarm driver.
 pub enum Command {
     /// Send a request to a peer.
@@ -36,14 +70,65 @@ pub enum Command {
         channel: ResponseChannel<Response>,
         response: Response,
     },
+    /// List discovered peers and look for more.
+    DiscoverPeers {
+        response_tx: oneshot::Sender<Vec<(PeerId, Vec<Multiaddr>)>>,
+    },
+    /// Follow the roots published on a gossip topic.
+    Subscribe {
+        topic: String,
+        response_tx: oneshot::Sender<Result<(), RemoteStoreError>>,
+    },
+    /// Announce a root on a gossip topic.
+    PublishRoot {
+        topic: String,
+        root: Cid,
+        schema: Cid,
+        response_tx: oneshot::Sender<Result<(), RemoteStoreError>>,
+    },
+    /// Start dialing an address.
+    Dial {
+        address: Multiaddr,
+        response_tx: oneshot::Sender<Result<(), RemoteStoreError>>,
+    },
+    /// Start listening on an address.
+    Listen {
+        address: Multiaddr,
+        response_tx: oneshot::Sender<Result<(), RemoteStoreError>>,
+    },
+    /// List connected peers.
+    ConnectedPeers {
+        response_tx: oneshot::Sender<Vec<PeerId>>,
+    },
+}
+
+/// Sends the command built around a response channel to the swarm runner
+/// and waits for its response.
+pub(crate) async fn request<T>(
+    command_tx: &mpsc::Sender<Command>,
+    command: impl FnOnce(oneshot::Sender<T>) -> Command,
+) -> Result<T, RemoteStoreError> {
+    let (tx, rx) = oneshot::channel();
+    command_tx
+        .send(command(tx))
+        .await
+        .map_err(|_| RemoteStoreError::ConnectionClosed)?;
+    rx.await.map_err(|_| RemoteStoreError::ConnectionClosed)
 }
 
 /// A remote peer exposed as an AsyncStore.
 ///
-/// Sends requests via the command channel and waits for responses.
+/// Sends requests via the command channel and waits for responses. Requests
+/// that time out or fail in transit are retried with exponential backoff;
+/// every request is idempotent, as blocks are addressed by content. Requests
+/// the peer rate limits are retried once it allows, unless that is longer
+/// than the largest backoff.
 pub struct RemoteStore {
     peer_id: PeerId,
     command_tx: mpsc::Sender<Command>,
+    request_timeout: Duration,
+    retries: u32,
+    backoff: Duration,
 }
 
 impl RemoteStore {
@@ -52,15 +137,123 @@ impl RemoteStore {
         Self {
             peer_id,
             command_tx,
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+            retries: DEFAULT_RETRIES,
+            backoff: DEFAULT_BACKOFF,
         }
     }
 
+    /// Sets how long to wait for each attempt of a request.
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+
+    /// Sets how many times a failed request is sent again, and the wait
+    /// before the first retry, which doubles for each further one.
+    pub fn with_retries(mut self, retries: u32, backoff: Duration) -> Self {
+        self.retries = retries;
+        self.backoff = backoff;
+        self
+    }
+
     /// Returns the peer ID this store connects to.
     pub fn peer_id(&self) -> PeerId {
         self.peer_id
     }
 
+    /// Tells the peer that `root` can be pulled from here, typically after
+    /// pushing it.
+    pub async fn announce_root(&self, root: Cid, schema: Cid) -> Result<(), RemoteStoreError> {
+        let response = self
+            .send_request(Request::AnnounceRoot { root, schema })
+            .await?;
+
+        match response {
+            Response::Acknowledged => Ok(()),
+            Response::Denied { cid } => Err(RemoteStoreError::Denied(cid)),
+            Response::Error { message } => Err(RemoteStoreError::Remote(message)),
+            _ => Err(RemoteStoreError::UnexpectedResponse),
+        }
+    }
+
+    /// Fetches a value along with as much of its subgraph as the peer
+    /// serves in one response, up to `max_nodes` values.
+    pub async fn pull_subgraph(
+        &self,
+        root: Cid,
+        schema: Cid,
+        max_nodes: usize,
+    ) -> Result<Subgraph, RemoteStoreError> {
+        let response = self
+            .send_request(Request::PullSubgraph {
+                root,
+                schema,
+                max_nodes,
+            })
+            .await?;
+
+        match response {
+            Response::Subgraph {
+                schemas,
+                nodes,
+                frontier,
+            } => Ok(Subgraph {
+                schemas,
+                nodes,
+                frontier,
+            }),
+            Response::Unsupported { capability } => Err(RemoteStoreError::Unsupported(capability)),
+            Response::Denied { cid } => Err(RemoteStoreError::Denied(cid)),
+            Response::Error { message } => Err(RemoteStoreError::Remote(message)),
+            _ => Err(RemoteStoreError::UnexpectedResponse),
+        }
+    }
+
     async fn send_request(&self, request: Request) -> Result<Response, RemoteStoreError> {
+        let mut backoff = self.backoff;
+        let mut attempt = 0;
+        loop {
+            let sent = tokio::time::timeout(self.request_timeout, self.send_once(request.clone()));
+            let result = match sent.await.unwrap_or(Err(RemoteStoreError::Timeout)) {
+                Ok(Response::RateLimited { retry_after_ms }) => Err(RemoteStoreError::RateLimited(
+                    Duration::from_millis(retry_after_ms),
+                )),
+                result => result,
+            };
+            let wait = match &result {
+                Err(RemoteStoreError::RateLimited(after)) => backoff.max(*after),
+                _ => backoff,
+            };
+            match result {
+                Err(e) if e.is_retryable() && attempt < self.retries && wait <= MAX_BACKOFF => {
+                    tracing::debug!(peer = %self.peer_id, error = %e, attempt, "retrying request");
+                    tokio::time::sleep(wait).await;
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Asks for one batch of CIDs, returning the ones found.
+    async fn get_batch(&self, cids: &[Cid]) -> Result<Vec<(Cid, Vec<u8>)>, RemoteStoreError> {
+        let response = self
+            .send_request(Request::Get {
+                cids: cids.to_vec(),
+            })
+            .await?;
+
+        match response {
+            Response::Nodes { found, missing: _ } => Ok(found),
+            Response::Denied { cid } => Err(RemoteStoreError::Denied(cid)),
+            Response::Error { message } => Err(RemoteStoreError::Remote(message)),
+            _ => Err(RemoteStoreError::UnexpectedResponse),
+        }
+    }
+
+    async fn send_once(&self, request: Request) -> Result<Response, RemoteStoreError> {
         let (tx, rx) = oneshot::channel();
 
         self.command_tx
@@ -84,21 +277,24 @@ impl AsyncStore for RemoteStore {
         Ok(results.into_iter().next().flatten())
     }
 
+    /// Sends the CIDs in batches, so that no response exceeds the message
+    /// limit.
     async fn async_get_many(&self, cids: &[Cid]) -> Result<Vec<Option<Vec<u8>>>, Self::Error> {
-        let response = self
-            .send_request(Request::Get {
-                cids: cids.to_vec(),
-            })
-            .await?;
-
-        match response {
-            Response::Nodes { found, missing: _ } => {
-                let found_map: HashMap<Cid, Vec<u8>> = found.into_iter().collect();
-                Ok(cids.iter().map(|c| found_map.get(c).cloned()).collect())
+        let mut found_map: HashMap<Cid, Vec<u8>> = HashMap::new();
+        let mut batches: Vec<&[Cid]> = cids.chunks(MAX_GET_BATCH).rev().collect();
+        while let Some(batch) = batches.pop() {
+            match self.get_batch(batch).await {
+                Err(RemoteStoreError::Remote(message))
+                    if batch.len() > 1 && message.starts_with(RESPONSE_TOO_LARGE) =>
+                {
+                    let (first, second) = batch.split_at(batch.len() / 2);
+                    batches.push(second);
+                    batches.push(first);
+                }
+                result => found_map.extend(result?),
             }
-            Response::Error { message } => Err(RemoteStoreError::Remote(message)),
-            _ => Err(RemoteStoreError::UnexpectedResponse),
         }
+        Ok(cids.iter().map(|c| found_map.get(c).cloned()).collect())
     }
 
     async fn async_put(&self, cid: &Cid, value: &[u8]) -> Result<(), Self::Error> {
@@ -113,11 +309,38 @@ impl AsyncStore for RemoteStore {
 
         match response {
             Response::Stored { cids: _ } => Ok(()),
+            Response::Denied { cid } => Err(RemoteStoreError::Denied(cid)),
             Response::Error { message } => Err(RemoteStoreError::Remote(message)),
             _ => Err(RemoteStoreError::UnexpectedResponse),
         }
     }
 
+    async fn async_delete(&self, cid: &Cid) -> Result<(), Self::Error> {
+        self.async_delete_many(&[*cid]).await
+    }
+
+    async fn async_delete_many(&self, cids: &[Cid]) -> Result<(), Self::Error> {
+        let response = self
+            .send_request(Request::Delete {
+                cids: cids.to_vec(),
+            })
+            .await?;
+
+        match response {
+            Response::Deleted { cids: _ } => Ok(()),
+            Response::Unsupported { capability } => Err(RemoteStoreError::Unsupported(capability)),
+            Response::Denied { cid } => Err(RemoteStoreError::Denied(cid)),
+            Response::Error { message } => Err(RemoteStoreError::Remote(message)),
+            _ => Err(RemoteStoreError::UnexpectedResponse),
+        }
+    }
+
+    /// The sync protocol has no listing request; peers only serve CIDs
+    /// asked for by name.
+    async fn async_list_cids(&self) -> Result<Vec<Cid>, Self::Error> {
+        Err(RemoteStoreError::Unsupported("list".to_string()))
+    }
+
     async fn async_has(&self, cid: &Cid) -> Result<bool, Self::Error> {
         let results = self.async_has_many(&[*cid]).await?;
         Ok(results.into_iter().next().unwrap_or(false))
@@ -132,8 +355,47 @@ impl AsyncStore for RemoteStore {
 
         match response {
             Response::Has { present } => Ok(present),
+            Response::Denied { cid } => Err(RemoteStoreError::Denied(cid)),
             Response::Error { message } => Err(RemoteStoreError::Remote(message)),
             _ => Err(RemoteStoreError::UnexpectedResponse),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use polyepoxide_core::compute_cid;
+
+    #[tokio::test(start_paused = true)]
+    async fn retries_failed_and_unanswered_requests() {
+        let cid = compute_cid(b"x");
+        let (command_tx, mut command_rx) = mpsc::channel(8);
+        let peer = tokio::spawn(async move {
+            let mut attempts = 0;
+            let mut unanswered = Vec::new();
+            while let Some(Command::SendRequest { response_tx, .. }) = command_rx.recv().await {
+                attempts += 1;
+                match attempts {
+                    1 => {
+                        let failure = RemoteStoreError::RequestFailed("dial failed".into());
+                        let _ = response_tx.send(Err(failure));
+                    }
+                    2 => unanswered.push(response_tx),
+                    _ => {
+                        let _ = response_tx.send(Ok(Response::Denied { cid }));
+                    }
+                }
+            }
+            attempts
+        });
+
+        let store = RemoteStore::new(PeerId::random(), command_tx)
+            .with_request_timeout(Duration::from_secs(1));
+        let result = store.async_has(&cid).await;
+        assert!(matches!(result, Err(RemoteStoreError::Denied(_))));
+        // Refusals are final
+        drop(store);
+        assert_eq!(peer.await.unwrap(), 3);
+    }
+}
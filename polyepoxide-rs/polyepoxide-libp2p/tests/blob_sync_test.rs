@@ -0,0 +1,97 @@
+//! Pulling a blob whose chunks together exceed the message size limit.
+//!
+//! A single `Get` for every chunk would be answered with an error, so the
+//! remote store has to ask for them in batches.
+
+use libp2p::core::transport::MemoryTransport;
+use libp2p::identity::Keypair;
+use libp2p::Transport;
+use libp2p::{Multiaddr, PeerId, Swarm};
+use polyepoxide_core::{pull, Blob, Solvent, Store};
+use polyepoxide_fjall::FjallStore;
+use polyepoxide_libp2p::{run_swarm_with, PolyepoxideBehaviour, RemoteStore, SwarmOptions};
+use tokio::sync::mpsc;
+
+/// Larger than the 16MB message limit.
+const BLOB_SIZE: usize = 20 * 1024 * 1024;
+
+fn create_swarm(listen: &Multiaddr) -> Swarm<PolyepoxideBehaviour> {
+    let keypair = Keypair::generate_ed25519();
+    let peer_id = PeerId::from(keypair.public());
+
+    let transport = MemoryTransport::default()
+        .upgrade(libp2p::core::upgrade::Version::V1)
+        .authenticate(libp2p::noise::Config::new(&keypair).unwrap())
+        .multiplex(libp2p::yamux::Config::default())
+        .boxed();
+
+    let mut swarm = Swarm::new(
+        transport,
+        PolyepoxideBehaviour::new(),
+        peer_id,
+        libp2p::swarm::Config::with_tokio_executor(),
+    );
+    swarm.listen_on(listen.clone()).unwrap();
+    swarm
+}
+
+/// Opens a store that outlives the test, as the swarm runners need `'static`.
+fn open_store(dir: &tempfile::TempDir) -> &'static FjallStore {
+    Box::leak(Box::new(FjallStore::open(dir.path()).unwrap()))
+}
+
+/// Pseudo-random bytes, so that no two chunks deduplicate.
+fn noise(len: usize) -> Vec<u8> {
+    let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+    (0..len)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn pulls_blob_larger_than_message_limit() {
+    let dir_a = tempfile::tempdir().unwrap();
+    let dir_b = tempfile::tempdir().unwrap();
+    let store_a = open_store(&dir_a);
+    let store_b = open_store(&dir_b);
+
+    let data = noise(BLOB_SIZE);
+    let mut solvent = Solvent::new();
+    let blob = solvent.bond(Blob::new(&data));
+    let (root, schema) = solvent
+        .persist_cell(blob.cell().unwrap(), store_a)
+        .unwrap();
+
+    let addr_a: Multiaddr = "/memory/7401".parse().unwrap();
+    let addr_b: Multiaddr = "/memory/7402".parse().unwrap();
+    let mut swarm_a = create_swarm(&addr_a);
+    let mut swarm_b = create_swarm(&addr_b);
+    let peer_a = *swarm_a.local_peer_id();
+    let peer_b = *swarm_b.local_peer_id();
+    swarm_a.add_peer_address(peer_b, addr_b);
+    swarm_b.add_peer_address(peer_a, addr_a);
+
+    let (_cmd_a, cmd_rx_a) = mpsc::channel(32);
+    let (cmd_b, cmd_rx_b) = mpsc::channel(32);
+    tokio::spawn(run_swarm_with(swarm_a, store_a, cmd_rx_a, SwarmOptions::default()));
+    tokio::spawn(run_swarm_with(swarm_b, store_b, cmd_rx_b, SwarmOptions::default()));
+
+    let remote_a = RemoteStore::new(peer_a, cmd_b);
+    pull(&remote_a, &store_b, root, schema).await.unwrap();
+
+    let chunks = &blob.value().unwrap().chunks;
+    assert!(chunks.len() > 1);
+    for chunk in chunks {
+        assert!(store_b.has(&chunk.cid()).unwrap());
+    }
+
+    let pulled = Solvent::new()
+        .load::<Blob, _>(&root, store_b, usize::MAX)
+        .unwrap();
+    assert_eq!(pulled.value().to_vec().unwrap(), data);
+}
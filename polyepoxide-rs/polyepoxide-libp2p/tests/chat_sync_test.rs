@@ -0,0 +1,194 @@
+//! End-to-end replication of a chat conversation between two devices.
+//!
+//! Each device has its own Fjall store and swarm runner. Device A records a
+//! conversation and announces its head; device B pulls it, resumes the
+//! conversation, and A pulls the continuation back. This is the path silane
+//! takes when a conversation is synced between devices.
+
+use std::time::Duration;
+
+use libp2p::core::transport::MemoryTransport;
+use libp2p::identity::Keypair;
+use libp2p::Transport;
+use libp2p::{Multiaddr, PeerId, Swarm};
+use polyepoxide_core::{pull, Bond, Cell, RefStore, Solvent};
+use polyepoxide_fjall::FjallStore;
+use polyepoxide_libp2p::{run_swarm_with, PolyepoxideBehaviour, RemoteStore, SwarmOptions};
+use polyepoxide_llm::{ContentBlock, Message, MessageContent};
+use tokio::sync::mpsc;
+
+const CONVERSATION: &str = "chat/demo";
+
+fn create_swarm(listen: &Multiaddr) -> Swarm<PolyepoxideBehaviour> {
+    let keypair = Keypair::generate_ed25519();
+    let peer_id = PeerId::from(keypair.public());
+
+    let transport = MemoryTransport::default()
+        .upgrade(libp2p::core::upgrade::Version::V1)
+        .authenticate(libp2p::noise::Config::new(&keypair).unwrap())
+        .multiplex(libp2p::yamux::Config::default())
+        .boxed();
+
+    let mut swarm = Swarm::new(
+        transport,
+        PolyepoxideBehaviour::new(),
+        peer_id,
+        libp2p::swarm::Config::with_tokio_executor(),
+    );
+    swarm.listen_on(listen.clone()).unwrap();
+    swarm
+}
+
+/// Opens a store that outlives the test, as the swarm runners need `'static`.
+fn open_store(dir: &tempfile::TempDir) -> &'static FjallStore {
+    Box::leak(Box::new(FjallStore::open(dir.path()).unwrap()))
+}
+
+fn text(message: &Message) -> &str {
+    let blocks = match &message.content {
+        MessageContent::User(blocks) => blocks,
+        MessageContent::Assistant { blocks, .. } => blocks,
+        other => panic!("unexpected content {:?}", other),
+    };
+    match blocks.as_slice() {
+        [ContentBlock::Text(text)] => text,
+        other => panic!("unexpected blocks {:?}", other),
+    }
+}
+
+/// Texts of the conversation ending at `head`, oldest first.
+fn transcript(head: &Cell<Message>) -> Vec<String> {
+    let mut texts = vec![text(head.value()).to_string()];
+    let mut previous = head.value().previous.clone();
+    while let Some(bond) = previous {
+        let message = bond.value().expect("history should be loaded");
+        texts.push(text(message).to_string());
+        previous = message.previous.clone();
+    }
+    texts.reverse();
+    texts
+}
+
+fn append(
+    solvent: &mut Solvent,
+    previous: Option<Bond<Message>>,
+    user: bool,
+    text: &str,
+) -> Bond<Message> {
+    let blocks = vec![ContentBlock::Text(text.to_string())];
+    let content = if user {
+        MessageContent::User(blocks)
+    } else {
+        MessageContent::Assistant {
+            blocks,
+            tool_calls: vec![],
+        }
+    };
+    solvent.bond(Message {
+        content,
+        metadata: None,
+        previous,
+    })
+}
+
+#[tokio::test]
+async fn second_device_resumes_synced_conversation() {
+    let dir_a = tempfile::tempdir().unwrap();
+    let dir_b = tempfile::tempdir().unwrap();
+    let store_a = open_store(&dir_a);
+    let store_b = open_store(&dir_b);
+
+    // Device A runs a scripted conversation
+    let mut solvent_a = Solvent::new();
+    let script = [
+        "What is a Merkle DAG?",
+        "A graph whose nodes are addressed by the hash of their content.",
+        "Why does that help sync?",
+        "Equal subgraphs have equal hashes, so peers only exchange what differs.",
+    ];
+    let mut head = None;
+    for (i, line) in script.iter().enumerate() {
+        head = Some(append(&mut solvent_a, head, i % 2 == 0, line));
+    }
+    let head = head.unwrap();
+    let (root, schema) = solvent_a
+        .persist_cell(head.cell().unwrap(), store_a)
+        .unwrap();
+    store_a.set_ref(CONVERSATION, &root).unwrap();
+
+    // Both devices run a swarm and know each other's address
+    let addr_a: Multiaddr = "/memory/7101".parse().unwrap();
+    let addr_b: Multiaddr = "/memory/7102".parse().unwrap();
+    let mut swarm_a = create_swarm(&addr_a);
+    let mut swarm_b = create_swarm(&addr_b);
+    let peer_a = *swarm_a.local_peer_id();
+    let peer_b = *swarm_b.local_peer_id();
+    swarm_a.add_peer_address(peer_b, addr_b);
+    swarm_b.add_peer_address(peer_a, addr_a);
+
+    let (cmd_a, cmd_rx_a) = mpsc::channel(32);
+    let (cmd_b, cmd_rx_b) = mpsc::channel(32);
+    let (announce_tx, mut announce_rx) = mpsc::channel(8);
+    tokio::spawn(run_swarm_with(swarm_a, store_a, cmd_rx_a, SwarmOptions::default()));
+    tokio::spawn(run_swarm_with(
+        swarm_b,
+        store_b,
+        cmd_rx_b,
+        SwarmOptions::default().with_announcements(announce_tx),
+    ));
+
+    // A announces its head; B pulls it from the announcing peer
+    RemoteStore::new(peer_b, cmd_a.clone())
+        .announce_root(root, schema)
+        .await
+        .unwrap();
+    let announcement = tokio::time::timeout(Duration::from_secs(5), announce_rx.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(announcement.peer, peer_a);
+    assert_eq!(announcement.root, root);
+
+    let remote_a = RemoteStore::new(announcement.peer, cmd_b.clone());
+    pull(&remote_a, &store_b, announcement.root, announcement.schema)
+        .await
+        .unwrap();
+    assert!(store_b
+        .compare_and_set_ref(CONVERSATION, None, &announcement.root)
+        .unwrap());
+
+    // B resumes the conversation from its own store
+    let mut solvent_b = Solvent::new();
+    let synced = store_b.get_ref(CONVERSATION).unwrap().unwrap();
+    let loaded = solvent_b
+        .load::<Message, _>(&synced, store_b, usize::MAX)
+        .unwrap();
+    assert_eq!(transcript(&loaded), script);
+
+    let previous = Some(Bond::from_cell(loaded));
+    let question = append(&mut solvent_b, previous, true, "And conflicts?");
+    let (resumed, _) = solvent_b
+        .persist_cell(question.cell().unwrap(), store_b)
+        .unwrap();
+    assert!(store_b
+        .compare_and_set_ref(CONVERSATION, Some(&synced), &resumed)
+        .unwrap());
+
+    // A pulls the continuation and converges on B's head
+    let remote_b = RemoteStore::new(peer_b, cmd_a);
+    let report = pull(&remote_b, &store_a, resumed, schema).await.unwrap();
+    assert_eq!(report.transferred.len(), 1, "only the new message should be transferred");
+    assert!(store_a
+        .compare_and_set_ref(CONVERSATION, Some(&root), &resumed)
+        .unwrap());
+
+    let mut solvent_a = Solvent::new();
+    let converged = solvent_a
+        .load::<Message, _>(&resumed, store_a, usize::MAX)
+        .unwrap();
+    assert_eq!(transcript(&converged).len(), script.len() + 1);
+    assert_eq!(
+        store_a.get_ref(CONVERSATION).unwrap(),
+        store_b.get_ref(CONVERSATION).unwrap()
+    );
+}
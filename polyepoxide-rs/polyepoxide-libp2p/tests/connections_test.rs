@@ -0,0 +1,86 @@
+//! Dialing and listening through the swarm runner's command channel.
+
+use std::time::Duration;
+
+use libp2p::core::transport::MemoryTransport;
+use libp2p::identity::Keypair;
+use libp2p::Transport;
+use libp2p::{Multiaddr, PeerId, Swarm};
+use polyepoxide_core::MemoryStore;
+use polyepoxide_libp2p::{
+    connected_peers, dial, listen, run_swarm_with, ConnectionEvent, PolyepoxideBehaviour,
+    SwarmOptions,
+};
+use tokio::sync::mpsc;
+
+fn create_swarm() -> Swarm<PolyepoxideBehaviour> {
+    let keypair = Keypair::generate_ed25519();
+    let peer_id = PeerId::from(keypair.public());
+
+    let transport = MemoryTransport::default()
+        .upgrade(libp2p::core::upgrade::Version::V1)
+        .authenticate(libp2p::noise::Config::new(&keypair).unwrap())
+        .multiplex(libp2p::yamux::Config::default())
+        .boxed();
+
+    Swarm::new(
+        transport,
+        PolyepoxideBehaviour::new(),
+        peer_id,
+        libp2p::swarm::Config::with_tokio_executor(),
+    )
+}
+
+async fn next_event(events: &mut mpsc::Receiver<ConnectionEvent>) -> ConnectionEvent {
+    tokio::time::timeout(Duration::from_secs(5), events.recv())
+        .await
+        .expect("event should arrive in time")
+        .unwrap()
+}
+
+#[tokio::test]
+async fn application_dials_and_lists_peers() {
+    let swarm_a = create_swarm();
+    let swarm_b = create_swarm();
+    let peer_b = *swarm_b.local_peer_id();
+
+    let (cmd_a, cmd_rx_a) = mpsc::channel(32);
+    let (cmd_b, cmd_rx_b) = mpsc::channel(32);
+    let (events_a, mut events_rx_a) = mpsc::channel(8);
+    let (events_b, mut events_rx_b) = mpsc::channel(8);
+    let store_a: &'static MemoryStore = Box::leak(Box::new(MemoryStore::new()));
+    let store_b: &'static MemoryStore = Box::leak(Box::new(MemoryStore::new()));
+    let options = |events| SwarmOptions::default().with_connections(events);
+    tokio::spawn(run_swarm_with(
+        swarm_a,
+        store_a,
+        cmd_rx_a,
+        options(events_a),
+    ));
+    tokio::spawn(run_swarm_with(
+        swarm_b,
+        store_b,
+        cmd_rx_b,
+        options(events_b),
+    ));
+
+    let address: Multiaddr = "/memory/7301".parse().unwrap();
+    listen(&cmd_b, address.clone()).await.unwrap();
+    assert_eq!(
+        next_event(&mut events_rx_b).await,
+        ConnectionEvent::Listening {
+            address: address.clone()
+        }
+    );
+
+    assert!(connected_peers(&cmd_a).await.unwrap().is_empty());
+    dial(&cmd_a, address.clone()).await.unwrap();
+    assert_eq!(
+        next_event(&mut events_rx_a).await,
+        ConnectionEvent::Connected {
+            peer: peer_b,
+            address
+        }
+    );
+    assert_eq!(connected_peers(&cmd_a).await.unwrap(), [peer_b]);
+}
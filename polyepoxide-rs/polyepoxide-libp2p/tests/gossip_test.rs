@@ -0,0 +1,95 @@
+//! Root announcements over gossipsub, pulled automatically by a follower.
+
+use std::time::Duration;
+
+use libp2p::core::transport::MemoryTransport;
+use libp2p::identity::Keypair;
+use libp2p::Transport;
+use libp2p::{Multiaddr, PeerId, Swarm};
+use polyepoxide_core::{MemoryStore, Solvent, Store};
+use polyepoxide_libp2p::{
+    auto_pull, publish_root, run_swarm_with, subscribe_roots, PolyepoxideBehaviour, SwarmOptions,
+};
+use tokio::sync::mpsc;
+
+const TOPIC: &str = "aldehyde/inventory";
+
+fn create_swarm(listen: &Multiaddr) -> Swarm<PolyepoxideBehaviour> {
+    let keypair = Keypair::generate_ed25519();
+    let peer_id = PeerId::from(keypair.public());
+
+    let transport = MemoryTransport::default()
+        .upgrade(libp2p::core::upgrade::Version::V1)
+        .authenticate(libp2p::noise::Config::new(&keypair).unwrap())
+        .multiplex(libp2p::yamux::Config::default())
+        .boxed();
+
+    let mut swarm = Swarm::new(
+        transport,
+        PolyepoxideBehaviour::new().with_gossip(&keypair).unwrap(),
+        peer_id,
+        libp2p::swarm::Config::with_tokio_executor(),
+    );
+    swarm.listen_on(listen.clone()).unwrap();
+    swarm
+}
+
+#[tokio::test]
+async fn followers_pull_published_roots() {
+    let store_a: &'static MemoryStore = Box::leak(Box::new(MemoryStore::new()));
+    let store_b: &'static MemoryStore = Box::leak(Box::new(MemoryStore::new()));
+    let mut solvent = Solvent::new();
+    let items = solvent.add(vec!["drill".to_string(), "saw".to_string()]);
+    let (root, schema) = solvent.persist_cell(&items, store_a).unwrap();
+
+    let addr_a: Multiaddr = "/memory/7201".parse().unwrap();
+    let addr_b: Multiaddr = "/memory/7202".parse().unwrap();
+    let swarm_a = create_swarm(&addr_a);
+    let mut swarm_b = create_swarm(&addr_b);
+    let peer_a = *swarm_a.local_peer_id();
+    swarm_b.add_peer_address(peer_a, addr_a);
+    swarm_b.dial(peer_a).unwrap();
+
+    let (cmd_a, cmd_rx_a) = mpsc::channel(32);
+    let (cmd_b, cmd_rx_b) = mpsc::channel(32);
+    let (announce_tx, announce_rx) = mpsc::channel(8);
+    tokio::spawn(run_swarm_with(
+        swarm_a,
+        store_a,
+        cmd_rx_a,
+        SwarmOptions::default(),
+    ));
+    tokio::spawn(run_swarm_with(
+        swarm_b,
+        store_b,
+        cmd_rx_b,
+        SwarmOptions::default().with_announcements(announce_tx),
+    ));
+    let (pulled_tx, mut pulled_rx) = mpsc::unbounded_channel();
+    tokio::spawn(auto_pull(
+        announce_rx,
+        cmd_b.clone(),
+        store_b,
+        move |announcement, _| {
+            let _ = pulled_tx.send(announcement);
+        },
+    ));
+
+    subscribe_roots(&cmd_a, TOPIC).await.unwrap();
+    subscribe_roots(&cmd_b, TOPIC).await.unwrap();
+    // Publishing fails until A learns that B follows the topic
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while publish_root(&cmd_a, TOPIC, root, schema).await.is_err() {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .expect("B should subscribe in time");
+
+    let announcement = tokio::time::timeout(Duration::from_secs(5), pulled_rx.recv())
+        .await
+        .expect("root should be pulled in time")
+        .unwrap();
+    assert_eq!((announcement.peer, announcement.root), (peer_a, root));
+    assert!(store_b.has(&root).unwrap());
+}
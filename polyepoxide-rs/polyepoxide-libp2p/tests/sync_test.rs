@@ -83,6 +83,21 @@ async fn run_swarm_driver(
                     Command::SendResponse { channel, response } => {
                         let _ = swarm.behaviour_mut().sync.send_response(channel, response);
                     }
+                    Command::DiscoverPeers { response_tx } => {
+                        let _ = response_tx.send(Vec::new());
+                    }
+                    Command::Subscribe { response_tx, .. } | Command::PublishRoot { response_tx, .. } => {
+                        let _ = response_tx.send(Err(RemoteStoreError::Unsupported("gossip".into())));
+                    }
+                    Command::Dial { address, response_tx } => {
+                        let _ = response_tx.send(swarm.dial(address).map_err(|e| RemoteStoreError::RequestFailed(e.to_string())));
+                    }
+                    Command::Listen { response_tx, .. } => {
+                        let _ = response_tx.send(Err(RemoteStoreError::Unsupported("listen".into())));
+                    }
+                    Command::ConnectedPeers { response_tx } => {
+                        let _ = response_tx.send(swarm.connected_peers().copied().collect());
+                    }
                 }
             }
 
@@ -188,8 +203,8 @@ async fn sync_simple_value_over_memory_transport() {
     // This is a simplified demonstration of the API usage.
 
     match transferred {
-        Ok(keys) => {
-            println!("Transferred {} keys", keys.len());
+        Ok(report) => {
+            println!("Transferred {} keys", report.transferred.len());
             assert!(store2.has(&value_key).unwrap());
         }
         Err(e) => {
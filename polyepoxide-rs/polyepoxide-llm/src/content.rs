@@ -1,4 +1,4 @@
-use polyepoxide_core::{oxide, ByteString};
+use polyepoxide_core::{oxide, Blob, Bond, ByteString, IngestError, IngestPolicy, MAX_CHUNK_SIZE};
 
 use crate::tool::ToolCall;
 
@@ -31,6 +31,70 @@ pub enum ContentBlock {
     },
     /// Model's internal reasoning/thinking output.
     Thinking(String),
+    /// A file too large for one block, stored in chunks. Images are told
+    /// apart by their MIME type.
+    ChunkedFile {
+        name: String,
+        mime_type: String,
+        data: Bond<Blob>,
+    },
+}
+
+impl ContentBlock {
+    /// Creates a file attachment after screening it against `policy`.
+    pub fn file(
+        policy: &IngestPolicy,
+        name: impl Into<String>,
+        mime_type: impl Into<String>,
+        data: Vec<u8>,
+    ) -> Result<Self, IngestError> {
+        let name = name.into();
+        let mime_type = mime_type.into();
+        policy.screen(&name, &mime_type, &data)?;
+        Ok(ContentBlock::File {
+            name,
+            mime_type: Some(mime_type),
+            data: ByteString(data),
+        })
+    }
+
+    /// Creates an attachment after screening it against `policy`: an
+    /// embedded image or a file, or a chunked file if larger than a chunk.
+    pub fn attachment(
+        policy: &IngestPolicy,
+        name: impl Into<String>,
+        mime_type: impl Into<String>,
+        data: Vec<u8>,
+    ) -> Result<Self, IngestError> {
+        let name = name.into();
+        let mime_type = mime_type.into();
+        if data.len() > MAX_CHUNK_SIZE {
+            policy.screen(&name, &mime_type, &data)?;
+            Ok(ContentBlock::ChunkedFile {
+                name,
+                mime_type,
+                data: Bond::new(Blob::new(&data)),
+            })
+        } else if mime_type.starts_with("image/") {
+            Self::embedded_image(policy, mime_type, data)
+        } else {
+            Self::file(policy, name, mime_type, data)
+        }
+    }
+
+    /// Creates an embedded image after screening it against `policy`.
+    pub fn embedded_image(
+        policy: &IngestPolicy,
+        media_type: impl Into<String>,
+        data: Vec<u8>,
+    ) -> Result<Self, IngestError> {
+        let media_type = media_type.into();
+        policy.screen("image", &media_type, &data)?;
+        Ok(ContentBlock::Image(ImageData::Embedded {
+            media_type,
+            data: ByteString(data),
+        }))
+    }
 }
 
 /// The content of a message, categorized by role.
@@ -0,0 +1,378 @@
+//! Token usage and cost aggregation over conversation branches.
+//!
+//! Branches of a conversation share their common prefix. When summing usage
+//! over several branches, every message is counted once for the conversation
+//! total, and each branch additionally reports the part that only it owns.
+//!
+//! A `CostLedger` keeps running totals of a branch in the store, so that
+//! they can be extended with new messages without walking the whole branch.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use polyepoxide_core::{oxide, Bond, Cid};
+use serde::{Deserialize, Serialize};
+
+use crate::message::Message;
+use crate::metadata::TokenUsage;
+
+/// Summed token counts. Missing counts in `TokenUsage` are treated as zero.
+#[oxide]
+#[derive(Copy, Default, PartialEq, Eq)]
+pub struct UsageTotals {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub cache_creation_tokens: u64,
+}
+
+impl UsageTotals {
+    pub fn add(&mut self, usage: &TokenUsage) {
+        self.input_tokens += usage.input_tokens.unwrap_or(0);
+        self.output_tokens += usage.output_tokens.unwrap_or(0);
+        self.cache_read_tokens += usage.cache_read_tokens.unwrap_or(0);
+        self.cache_creation_tokens += usage.cache_creation_tokens.unwrap_or(0);
+    }
+
+    pub fn merge(&mut self, other: &UsageTotals) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_read_tokens += other.cache_read_tokens;
+        self.cache_creation_tokens += other.cache_creation_tokens;
+    }
+
+    pub fn total_tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens + self.cache_read_tokens + self.cache_creation_tokens
+    }
+}
+
+/// Prices for a model, in currency units per million tokens.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelPricing {
+    pub input: f64,
+    pub output: f64,
+    pub cache_read: f64,
+    pub cache_creation: f64,
+}
+
+impl ModelPricing {
+    pub fn cost(&self, usage: &UsageTotals) -> f64 {
+        (usage.input_tokens as f64 * self.input
+            + usage.output_tokens as f64 * self.output
+            + usage.cache_read_tokens as f64 * self.cache_read
+            + usage.cache_creation_tokens as f64 * self.cache_creation)
+            / 1_000_000.0
+    }
+}
+
+/// Usage broken down by the model that generated each message.
+///
+/// Messages without a model (user input, imports) are keyed by `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UsageBreakdown {
+    pub by_model: BTreeMap<Option<String>, UsageTotals>,
+    /// Number of messages counted.
+    pub messages: usize,
+}
+
+impl UsageBreakdown {
+    fn add_message(&mut self, message: &Message) {
+        self.messages += 1;
+        let Some(metadata) = &message.metadata else {
+            return;
+        };
+        if let Some(usage) = &metadata.usage {
+            self.by_model
+                .entry(metadata.model.clone())
+                .or_default()
+                .add(usage);
+        }
+    }
+
+    /// Usage summed over all models.
+    pub fn totals(&self) -> UsageTotals {
+        let mut totals = UsageTotals::default();
+        for usage in self.by_model.values() {
+            totals.merge(usage);
+        }
+        totals
+    }
+
+    /// Cost per model. Models missing from `pricing` are omitted.
+    pub fn cost_by_model(&self, pricing: &HashMap<String, ModelPricing>) -> BTreeMap<String, f64> {
+        self.by_model
+            .iter()
+            .filter_map(|(model, usage)| {
+                let model = model.as_ref()?;
+                Some((model.clone(), pricing.get(model)?.cost(usage)))
+            })
+            .collect()
+    }
+
+    /// Total cost over all priced models.
+    pub fn cost(&self, pricing: &HashMap<String, ModelPricing>) -> f64 {
+        self.cost_by_model(pricing).values().sum()
+    }
+}
+
+/// Usage of a single branch, identified by its head message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BranchUsage {
+    pub head: Cid,
+    /// Usage of the whole path from the root to the head.
+    pub path: UsageBreakdown,
+    /// Usage of messages not shared with any other given branch.
+    pub exclusive: UsageBreakdown,
+    /// True if the walk stopped at an unresolved bond before reaching the root.
+    pub truncated: bool,
+}
+
+/// Usage of a set of branches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConversationUsage {
+    /// Every message reachable from any head, counted once.
+    pub total: UsageBreakdown,
+    pub branches: Vec<BranchUsage>,
+}
+
+/// Walks a branch from head to root, stopping at the first unresolved bond.
+pub(crate) fn walk(head: &Bond<Message>) -> (Vec<(Cid, &Message)>, bool) {
+    let mut path = Vec::new();
+    let mut current = head;
+    loop {
+        let Some(message) = current.value() else {
+            return (path, true);
+        };
+        path.push((current.cid(), message));
+        match &message.previous {
+            Some(previous) => current = previous,
+            None => return (path, false),
+        }
+    }
+}
+
+/// Aggregates usage along a single branch.
+pub fn branch_usage(head: &Bond<Message>) -> UsageBreakdown {
+    let mut breakdown = UsageBreakdown::default();
+    for (_, message) in walk(head).0 {
+        breakdown.add_message(message);
+    }
+    breakdown
+}
+
+/// Aggregates usage over several branches of a conversation.
+///
+/// Messages on a shared prefix contribute to the total once and to each
+/// branch's `path`, but to no branch's `exclusive` usage. A head given more
+/// than once is reported as one branch.
+pub fn conversation_usage(heads: &[Bond<Message>]) -> ConversationUsage {
+    let mut seen = HashSet::new();
+    let heads: Vec<&Bond<Message>> = heads.iter().filter(|h| seen.insert(h.cid())).collect();
+    let walks: Vec<_> = heads.iter().copied().map(walk).collect();
+
+    let mut owners: HashMap<Cid, usize> = HashMap::new();
+    for (path, _) in &walks {
+        for (cid, _) in path {
+            *owners.entry(*cid).or_default() += 1;
+        }
+    }
+
+    let mut total = UsageBreakdown::default();
+    let mut counted = HashSet::new();
+    let mut branches = Vec::with_capacity(heads.len());
+    for (head, (path, truncated)) in heads.iter().zip(&walks) {
+        let mut branch = BranchUsage {
+            head: head.cid(),
+            path: UsageBreakdown::default(),
+            exclusive: UsageBreakdown::default(),
+            truncated: *truncated,
+        };
+        for (cid, message) in path {
+            branch.path.add_message(message);
+            if owners[cid] == 1 {
+                branch.exclusive.add_message(message);
+            }
+            if counted.insert(*cid) {
+                total.add_message(message);
+            }
+        }
+        branches.push(branch);
+    }
+
+    ConversationUsage { total, branches }
+}
+
+/// Usage and cost of one model's messages in a `CostLedger`.
+#[oxide]
+#[derive(Default, PartialEq)]
+pub struct LedgerEntry {
+    pub usage: UsageTotals,
+    /// Cost at the prices when the messages were counted; zero if unpriced.
+    pub cost: f64,
+}
+
+impl LedgerEntry {
+    fn add(&mut self, usage: &UsageTotals, cost: f64) {
+        self.usage.merge(usage);
+        self.cost += cost;
+    }
+}
+
+/// A reply generated for a prompt on a ledger's branch but left off it,
+/// such as another compared model's.
+#[oxide]
+pub struct SiblingReply {
+    pub reply: Bond<Message>,
+    /// Model of the reply, or "" if it has none.
+    pub model: String,
+    pub entry: LedgerEntry,
+}
+
+/// Running usage and cost of a branch, up to and including `head`.
+///
+/// Costs are fixed when messages are counted, so price changes only apply
+/// to messages counted afterwards.
+#[oxide]
+pub struct CostLedger {
+    pub head: Bond<Message>,
+    /// Usage by model; messages without a model are keyed by "".
+    pub by_model: BTreeMap<String, LedgerEntry>,
+    pub messages: u64,
+    /// Replies paid for but not on the branch. A sibling that becomes part
+    /// of the branch is counted there instead.
+    pub siblings: Vec<SiblingReply>,
+}
+
+impl CostLedger {
+    /// Counts the branch ending at `head`.
+    pub fn new(head: Bond<Message>, pricing: &HashMap<String, ModelPricing>) -> Self {
+        let mut ledger = Self {
+            head: head.clone(),
+            by_model: BTreeMap::new(),
+            messages: 0,
+            siblings: Vec::new(),
+        };
+        for (_, message) in walk(&head).0 {
+            ledger.add_message(message, pricing);
+        }
+        ledger
+    }
+
+    /// The ledger of the branch ending at `head`, counting only messages
+    /// after this ledger's head. If `head` doesn't descend from it, the
+    /// branch is counted from the start. Siblings are kept.
+    pub fn extend(&self, head: Bond<Message>, pricing: &HashMap<String, ModelPricing>) -> Self {
+        let mut added = Vec::new();
+        let mut current = &head;
+        let mut ledger = loop {
+            if current.cid() == self.head.cid() {
+                let mut ledger = Self {
+                    head: head.clone(),
+                    by_model: self.by_model.clone(),
+                    messages: self.messages,
+                    siblings: Vec::new(),
+                };
+                for message in added {
+                    ledger.add_message(message, pricing);
+                }
+                break ledger;
+            }
+            let Some(message) = current.value() else {
+                break Self::new(head.clone(), pricing);
+            };
+            added.push(message);
+            match &message.previous {
+                Some(previous) => current = previous,
+                None => break Self::new(head.clone(), pricing),
+            }
+        };
+        ledger.siblings = self.siblings.clone();
+        ledger.drop_siblings_on_branch();
+        ledger
+    }
+
+    /// Counts `replies` as siblings of the branch. Replies on the branch or
+    /// already counted are skipped.
+    pub fn add_siblings(
+        &mut self,
+        replies: &[Bond<Message>],
+        pricing: &HashMap<String, ModelPricing>,
+    ) {
+        for reply in replies {
+            let Some(message) = reply.value() else {
+                continue;
+            };
+            if self.siblings.iter().any(|s| s.reply.cid() == reply.cid()) {
+                continue;
+            }
+            let (model, usage) = usage_of(message);
+            let cost = pricing.get(&model).map_or(0.0, |p| p.cost(&usage));
+            let mut entry = LedgerEntry::default();
+            entry.add(&usage, cost);
+            self.siblings.push(SiblingReply {
+                reply: reply.clone(),
+                model,
+                entry,
+            });
+        }
+        self.drop_siblings_on_branch();
+    }
+
+    fn drop_siblings_on_branch(&mut self) {
+        if self.siblings.is_empty() {
+            return;
+        }
+        let branch: HashSet<Cid> = walk(&self.head).0.into_iter().map(|(cid, _)| cid).collect();
+        self.siblings.retain(|s| !branch.contains(&s.reply.cid()));
+    }
+
+    fn add_message(&mut self, message: &Message, pricing: &HashMap<String, ModelPricing>) {
+        self.messages += 1;
+        if message.metadata.as_ref().is_none_or(|m| m.usage.is_none()) {
+            return;
+        }
+        let (model, usage) = usage_of(message);
+        let cost = pricing.get(&model).map_or(0.0, |p| p.cost(&usage));
+        self.by_model.entry(model).or_default().add(&usage, cost);
+    }
+
+    /// Usage of the branch summed over all models.
+    pub fn totals(&self) -> UsageTotals {
+        let mut totals = UsageTotals::default();
+        for entry in self.by_model.values() {
+            totals.merge(&entry.usage);
+        }
+        totals
+    }
+
+    /// Cost of the branch.
+    pub fn cost(&self) -> f64 {
+        self.by_model.values().map(|entry| entry.cost).sum()
+    }
+
+    /// Usage of the branch and its siblings.
+    pub fn spent_totals(&self) -> UsageTotals {
+        let mut totals = self.totals();
+        for sibling in &self.siblings {
+            totals.merge(&sibling.entry.usage);
+        }
+        totals
+    }
+
+    /// Cost of the branch and its siblings.
+    pub fn spent(&self) -> f64 {
+        self.cost() + self.siblings.iter().map(|s| s.entry.cost).sum::<f64>()
+    }
+}
+
+/// The model of a message, or "" if it has none, and its usage.
+fn usage_of(message: &Message) -> (String, UsageTotals) {
+    let mut usage = UsageTotals::default();
+    let Some(metadata) = &message.metadata else {
+        return (String::new(), usage);
+    };
+    if let Some(tokens) = &metadata.usage {
+        usage.add(tokens);
+    }
+    (metadata.model.clone().unwrap_or_default(), usage)
+}
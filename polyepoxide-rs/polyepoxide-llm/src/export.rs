@@ -0,0 +1,294 @@
+//! Export of conversation branches as chat-format JSONL.
+//!
+//! Each branch becomes one line of the form used by OpenAI fine-tuning and
+//! most eval harnesses: `{"messages": [{"role": ..., "content": ...}, ...]}`.
+//! Images and files have no text form and are left out.
+
+use polyepoxide_core::{Bond, Cid};
+use serde_json::{json, Value};
+
+use crate::content::{ContentBlock, MessageContent};
+use crate::cost::walk;
+use crate::message::Message;
+
+/// Error exporting conversations.
+#[derive(Debug, thiserror::Error)]
+pub enum ExportError {
+    #[error("branch {head} is not fully loaded: message {missing} is unresolved")]
+    Unresolved { head: Cid, missing: Cid },
+}
+
+/// Controls what goes into exported conversations.
+///
+/// By default, system prompts are kept while thinking blocks, tool calls,
+/// and tool results are dropped.
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    include_system: bool,
+    include_thinking: bool,
+    include_tools: bool,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            include_system: true,
+            include_thinking: false,
+            include_tools: false,
+        }
+    }
+}
+
+impl ExportOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_system(mut self, include: bool) -> Self {
+        self.include_system = include;
+        self
+    }
+
+    /// Keeps thinking blocks, wrapped in `<thinking>` tags ahead of the reply.
+    pub fn with_thinking(mut self, include: bool) -> Self {
+        self.include_thinking = include;
+        self
+    }
+
+    /// Keeps assistant tool calls and `tool` role results.
+    pub fn with_tools(mut self, include: bool) -> Self {
+        self.include_tools = include;
+        self
+    }
+}
+
+/// Exports each branch, from its root to the given head, as one JSONL line.
+///
+/// Branches left without messages after filtering produce no line. Every
+/// branch must be loaded back to its root.
+pub fn export_jsonl(heads: &[Bond<Message>], options: &ExportOptions) -> Result<String, ExportError> {
+    let mut out = String::new();
+    for head in heads {
+        let messages: Vec<Value> = loaded_branch(head)?
+            .iter()
+            .filter_map(|(_, message)| export_message(message, options))
+            .collect();
+        if messages.is_empty() {
+            continue;
+        }
+        out.push_str(&json!({ "messages": messages }).to_string());
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// The messages of a branch, oldest first, or an error if it isn't loaded
+/// back to its root.
+pub(crate) fn loaded_branch(head: &Bond<Message>) -> Result<Vec<(Cid, &Message)>, ExportError> {
+    let (mut path, truncated) = walk(head);
+    if truncated {
+        let missing = path
+            .last()
+            .and_then(|(_, message)| message.previous.as_ref())
+            .map_or(head.cid(), |previous| previous.cid());
+        return Err(ExportError::Unresolved {
+            head: head.cid(),
+            missing,
+        });
+    }
+    path.reverse();
+    Ok(path)
+}
+
+fn export_message(message: &Message, options: &ExportOptions) -> Option<Value> {
+    match &message.content {
+        MessageContent::System(blocks) => {
+            let text = blocks_text(blocks, options)?;
+            options
+                .include_system
+                .then(|| json!({ "role": "system", "content": text }))
+        }
+        MessageContent::User(blocks) => {
+            let text = blocks_text(blocks, options)?;
+            Some(json!({ "role": "user", "content": text }))
+        }
+        MessageContent::Assistant { blocks, tool_calls } => {
+            let text = blocks_text(blocks, options);
+            let tool_calls: Vec<Value> = if options.include_tools {
+                tool_calls
+                    .iter()
+                    .map(|call| {
+                        json!({
+                            "id": call.id,
+                            "type": "function",
+                            "function": { "name": call.name, "arguments": call.arguments },
+                        })
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            if text.is_none() && tool_calls.is_empty() {
+                return None;
+            }
+            let mut value = json!({ "role": "assistant", "content": text });
+            if !tool_calls.is_empty() {
+                value["tool_calls"] = Value::Array(tool_calls);
+            }
+            Some(value)
+        }
+        MessageContent::ToolResult {
+            tool_call_id,
+            result,
+            ..
+        } => options.include_tools.then(|| {
+            json!({ "role": "tool", "tool_call_id": tool_call_id, "content": result })
+        }),
+    }
+}
+
+/// Joins the textual blocks of a message, or None if nothing remains.
+fn blocks_text(blocks: &[ContentBlock], options: &ExportOptions) -> Option<String> {
+    let parts: Vec<String> = blocks
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text(text) => Some(text.clone()),
+            ContentBlock::Code { language, code } => Some(format!(
+                "```{}\n{}\n```",
+                language.as_deref().unwrap_or(""),
+                code
+            )),
+            ContentBlock::Thinking(thinking) if options.include_thinking => {
+                Some(format!("<thinking>\n{}\n</thinking>", thinking))
+            }
+            ContentBlock::Thinking(_)
+            | ContentBlock::Image(_)
+            | ContentBlock::File { .. }
+            | ContentBlock::ChunkedFile { .. } => None,
+        })
+        .collect();
+    if parts.is_empty() {
+        return None;
+    }
+    Some(parts.join("\n\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tool::ToolCall;
+    use polyepoxide_core::Solvent;
+    use std::sync::Arc;
+
+    fn chain(solvent: &mut Solvent, contents: Vec<MessageContent>) -> Bond<Message> {
+        let mut previous = None;
+        for content in contents {
+            let cell = solvent.add(Message {
+                content,
+                metadata: None,
+                previous,
+            });
+            previous = Some(Bond::from_cell(Arc::clone(&cell)));
+        }
+        previous.unwrap()
+    }
+
+    fn conversation(solvent: &mut Solvent) -> Bond<Message> {
+        chain(
+            solvent,
+            vec![
+                MessageContent::System(vec![ContentBlock::Text("Be brief.".into())]),
+                MessageContent::User(vec![ContentBlock::Text("Weather?".into())]),
+                MessageContent::Assistant {
+                    blocks: vec![ContentBlock::Thinking("Need the tool.".into())],
+                    tool_calls: vec![ToolCall {
+                        id: "call_1".into(),
+                        name: "weather".into(),
+                        arguments: "{}".into(),
+                    }],
+                },
+                MessageContent::ToolResult {
+                    tool_call_id: "call_1".into(),
+                    result: "Sunny".into(),
+                    is_error: false,
+                },
+                MessageContent::Assistant {
+                    blocks: vec![ContentBlock::Text("Sunny.".into())],
+                    tool_calls: vec![],
+                },
+            ],
+        )
+    }
+
+    fn lines(jsonl: &str) -> Vec<Value> {
+        jsonl
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn drops_thinking_and_tool_noise_by_default() {
+        let mut solvent = Solvent::new();
+        let head = conversation(&mut solvent);
+
+        let jsonl = export_jsonl(&[head], &ExportOptions::default()).unwrap();
+        let lines = lines(&jsonl);
+
+        assert_eq!(lines.len(), 1);
+        assert_eq!(
+            lines[0],
+            json!({ "messages": [
+                { "role": "system", "content": "Be brief." },
+                { "role": "user", "content": "Weather?" },
+                { "role": "assistant", "content": "Sunny." },
+            ]})
+        );
+    }
+
+    #[test]
+    fn keeps_tools_and_thinking_when_asked() {
+        let mut solvent = Solvent::new();
+        let head = conversation(&mut solvent);
+        let options = ExportOptions::new()
+            .with_system(false)
+            .with_thinking(true)
+            .with_tools(true);
+
+        let jsonl = export_jsonl(&[head], &options).unwrap();
+        let messages = lines(&jsonl)[0]["messages"].as_array().unwrap().clone();
+
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[1]["content"], "<thinking>\nNeed the tool.\n</thinking>");
+        assert_eq!(messages[1]["tool_calls"][0]["function"]["name"], "weather");
+        assert_eq!(messages[2]["role"], "tool");
+        assert_eq!(messages[2]["tool_call_id"], "call_1");
+    }
+
+    #[test]
+    fn one_line_per_branch() {
+        let mut solvent = Solvent::new();
+        let first = conversation(&mut solvent);
+        let second = chain(
+            &mut solvent,
+            vec![MessageContent::User(vec![ContentBlock::Text("Hi".into())])],
+        );
+
+        let jsonl = export_jsonl(&[first, second], &ExportOptions::default()).unwrap();
+        assert_eq!(lines(&jsonl).len(), 2);
+    }
+
+    #[test]
+    fn rejects_unloaded_branches() {
+        let mut solvent = Solvent::new();
+        let root = solvent.add(Message {
+            content: MessageContent::User(vec![ContentBlock::Text("Hi".into())]),
+            metadata: None,
+            previous: None,
+        });
+        let head = Bond::<Message>::from_cid(root.cid());
+
+        let err = export_jsonl(&[head], &ExportOptions::default()).unwrap_err();
+        assert!(matches!(err, ExportError::Unresolved { .. }));
+    }
+}
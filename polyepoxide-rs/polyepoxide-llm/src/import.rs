@@ -0,0 +1,475 @@
+//! Import of conversations exported from ChatGPT and Claude.
+//!
+//! Both exports are a `conversations.json` array. Messages are chained
+//! parent-first, so edited and regenerated messages become branches sharing
+//! their history. Images are left out, as the exports only refer to them.
+
+use std::collections::{HashMap, HashSet};
+
+use polyepoxide_core::{Bond, ByteString};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::content::{ContentBlock, MessageContent};
+use crate::message::Message;
+use crate::metadata::MessageMetadata;
+use crate::time::parse_timestamp;
+use crate::tool::ToolCall;
+
+/// Error importing conversations.
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError {
+    #[error("invalid export: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// A conversation read from an export.
+#[derive(Debug, Clone)]
+pub struct ImportedConversation {
+    pub title: Option<String>,
+    /// The last message of each branch, the one last viewed first.
+    pub heads: Vec<Bond<Message>>,
+}
+
+/// Reads ChatGPT's `conversations.json`.
+pub fn import_chatgpt(json: &str) -> Result<Vec<ImportedConversation>, ImportError> {
+    let conversations: Vec<ChatGptConversation> = serde_json::from_str(json)?;
+    Ok(conversations
+        .into_iter()
+        .map(|conversation| {
+            let ids: HashMap<&str, usize> = conversation
+                .mapping
+                .keys()
+                .enumerate()
+                .map(|(i, id)| (id.as_str(), i))
+                .collect();
+            let nodes = conversation
+                .mapping
+                .values()
+                .map(|node| Node {
+                    parent: node.parent.as_deref().and_then(|id| ids.get(id).copied()),
+                    messages: node.message.iter().filter_map(chatgpt_message).collect(),
+                })
+                .collect();
+            let current = conversation
+                .current_node
+                .as_deref()
+                .and_then(|id| ids.get(id).copied());
+            ImportedConversation {
+                title: conversation.title,
+                heads: chain_tree(nodes, current),
+            }
+        })
+        .collect())
+}
+
+/// Reads the `conversations.json` of a Claude data export.
+pub fn import_anthropic(json: &str) -> Result<Vec<ImportedConversation>, ImportError> {
+    let conversations: Vec<ClaudeConversation> = serde_json::from_str(json)?;
+    Ok(conversations
+        .into_iter()
+        .map(|conversation| {
+            let messages = conversation.chat_messages;
+            let ids: HashMap<&str, usize> = messages
+                .iter()
+                .enumerate()
+                .map(|(i, message)| (message.uuid.as_str(), i))
+                .collect();
+            let nodes = messages
+                .iter()
+                .enumerate()
+                .map(|(i, message)| Node {
+                    // Older exports have no parents and no branches
+                    parent: match &message.parent_message_uuid {
+                        Some(parent) => ids.get(parent.as_str()).copied(),
+                        None => i.checked_sub(1),
+                    },
+                    messages: claude_messages(message),
+                })
+                .collect();
+            ImportedConversation {
+                title: conversation.name.filter(|name| !name.is_empty()),
+                heads: chain_tree(nodes, messages.len().checked_sub(1)),
+            }
+        })
+        .collect())
+}
+
+/// An exported message, before it is chained to its parent. One exported
+/// message may become several, or none.
+struct Node {
+    parent: Option<usize>,
+    messages: Vec<(MessageContent, Option<MessageMetadata>)>,
+}
+
+/// Chains the nodes from their roots, returning the heads of all branches,
+/// with the branch through `current` first.
+fn chain_tree(mut nodes: Vec<Node>, current: Option<usize>) -> Vec<Bond<Message>> {
+    let mut children = vec![Vec::new(); nodes.len()];
+    let mut stack = Vec::new();
+    for (i, node) in nodes.iter().enumerate().rev() {
+        match node.parent {
+            Some(parent) => children[parent].push(i),
+            None => stack.push((i, None)),
+        }
+    }
+
+    let mut tails: Vec<Option<Bond<Message>>> = vec![None; nodes.len()];
+    let mut leaves = Vec::new();
+    let mut continued = HashSet::new();
+    while let Some((i, mut previous)) = stack.pop() {
+        for (content, metadata) in std::mem::take(&mut nodes[i].messages) {
+            if let Some(bond) = &previous {
+                continued.insert(bond.cid());
+            }
+            previous = Some(Bond::new(Message {
+                content,
+                metadata,
+                previous,
+            }));
+        }
+        tails[i] = previous.clone();
+        if children[i].is_empty() {
+            leaves.extend(previous);
+        }
+        for &child in &children[i] {
+            stack.push((child, tails[i].clone()));
+        }
+    }
+
+    // A leaf left without messages of its own ends where another branch
+    // goes on
+    let mut seen = HashSet::new();
+    let mut heads: Vec<Bond<Message>> = leaves
+        .into_iter()
+        .filter(|head| !continued.contains(&head.cid()) && seen.insert(head.cid()))
+        .collect();
+    let current = current
+        .and_then(|i| tails[i].as_ref())
+        .map(|bond| bond.cid());
+    if let Some(i) = heads.iter().position(|head| Some(head.cid()) == current) {
+        let head = heads.remove(i);
+        heads.insert(0, head);
+    }
+    heads
+}
+
+fn metadata(model: Option<String>, timestamp_ms: Option<u64>) -> Option<MessageMetadata> {
+    (model.is_some() || timestamp_ms.is_some()).then_some(MessageMetadata {
+        model,
+        timestamp_ms,
+        generation_params: None,
+        stop_reason: None,
+        usage: None,
+    })
+}
+
+#[derive(Deserialize)]
+struct ChatGptConversation {
+    title: Option<String>,
+    mapping: HashMap<String, ChatGptNode>,
+    current_node: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChatGptNode {
+    message: Option<ChatGptMessage>,
+    parent: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChatGptMessage {
+    author: ChatGptAuthor,
+    create_time: Option<f64>,
+    content: Value,
+    #[serde(default)]
+    metadata: Value,
+}
+
+#[derive(Deserialize)]
+struct ChatGptAuthor {
+    role: String,
+    name: Option<String>,
+}
+
+fn chatgpt_message(message: &ChatGptMessage) -> Option<(MessageContent, Option<MessageMetadata>)> {
+    if message.metadata["is_visually_hidden_from_conversation"] == true {
+        return None;
+    }
+    let content = &message.content;
+    let text = |value: &Value| value.as_str().filter(|s| !s.is_empty()).map(String::from);
+    let blocks: Vec<ContentBlock> = match content["content_type"].as_str() {
+        Some("text" | "multimodal_text") => content["parts"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(text)
+            .map(ContentBlock::Text)
+            .collect(),
+        Some("code") => text(&content["text"])
+            .map(|code| ContentBlock::Code {
+                language: text(&content["language"]).filter(|l| l != "unknown"),
+                code,
+            })
+            .into_iter()
+            .collect(),
+        Some("thoughts") => content["thoughts"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|thought| text(&thought["content"]))
+            .map(ContentBlock::Thinking)
+            .collect(),
+        // A note of how long the model thought, not the thinking itself
+        Some("reasoning_recap") => Vec::new(),
+        _ => text(&content["text"])
+            .map(ContentBlock::Text)
+            .into_iter()
+            .collect(),
+    };
+    if blocks.is_empty() {
+        return None;
+    }
+
+    let content = match message.author.role.as_str() {
+        "system" => MessageContent::System(blocks),
+        "user" => MessageContent::User(blocks),
+        // Tool outputs aren't tied to a call id, only to the tool's name
+        "tool" => MessageContent::ToolResult {
+            tool_call_id: message.author.name.clone().unwrap_or_default(),
+            result: blocks_text(&blocks),
+            is_error: false,
+        },
+        _ => MessageContent::Assistant {
+            blocks,
+            tool_calls: vec![],
+        },
+    };
+    let model = text(&message.metadata["model_slug"]);
+    let timestamp_ms = message.create_time.map(|secs| (secs * 1000.0) as u64);
+    Some((content, metadata(model, timestamp_ms)))
+}
+
+fn blocks_text(blocks: &[ContentBlock]) -> String {
+    blocks
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text(text) | ContentBlock::Thinking(text) => Some(text.as_str()),
+            ContentBlock::Code { code, .. } => Some(code.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+#[derive(Deserialize)]
+struct ClaudeConversation {
+    name: Option<String>,
+    #[serde(default)]
+    chat_messages: Vec<ClaudeMessage>,
+}
+
+#[derive(Deserialize)]
+struct ClaudeMessage {
+    uuid: String,
+    sender: String,
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    content: Vec<Value>,
+    created_at: Option<String>,
+    #[serde(default)]
+    attachments: Vec<ClaudeAttachment>,
+    parent_message_uuid: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ClaudeAttachment {
+    file_name: String,
+    file_type: Option<String>,
+    #[serde(default)]
+    extracted_content: String,
+}
+
+/// Converts a Claude message, splitting it where it holds tool results,
+/// which are messages of their own here.
+fn claude_messages(message: &ClaudeMessage) -> Vec<(MessageContent, Option<MessageMetadata>)> {
+    let timestamp_ms = message.created_at.as_deref().and_then(parse_timestamp);
+    let mut blocks: Vec<ContentBlock> = message
+        .attachments
+        .iter()
+        .map(|attachment| ContentBlock::File {
+            name: attachment.file_name.clone(),
+            mime_type: attachment.file_type.clone().filter(|t| !t.is_empty()),
+            data: ByteString(attachment.extracted_content.clone().into_bytes()),
+        })
+        .collect();
+    let mut tool_calls = Vec::new();
+    let mut messages = Vec::new();
+    let turn = |blocks, tool_calls| match message.sender.as_str() {
+        "assistant" => MessageContent::Assistant { blocks, tool_calls },
+        _ => MessageContent::User(blocks),
+    };
+
+    if message.content.is_empty() && !message.text.is_empty() {
+        blocks.push(ContentBlock::Text(message.text.clone()));
+    }
+    for item in &message.content {
+        let string = |key: &str| item[key].as_str().unwrap_or_default().to_string();
+        match item["type"].as_str() {
+            Some("text") => blocks.push(ContentBlock::Text(string("text"))),
+            Some("thinking") => blocks.push(ContentBlock::Thinking(string("thinking"))),
+            Some("tool_use") => tool_calls.push(ToolCall {
+                id: string("id"),
+                name: string("name"),
+                arguments: item["input"].to_string(),
+            }),
+            Some("tool_result") => {
+                if !blocks.is_empty() || !tool_calls.is_empty() {
+                    let content =
+                        turn(std::mem::take(&mut blocks), std::mem::take(&mut tool_calls));
+                    messages.push(content);
+                }
+                let result = match &item["content"] {
+                    Value::String(text) => text.clone(),
+                    Value::Array(parts) => parts
+                        .iter()
+                        .filter_map(|part| part["text"].as_str())
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                    _ => String::new(),
+                };
+                messages.push(MessageContent::ToolResult {
+                    tool_call_id: string("tool_use_id"),
+                    result,
+                    is_error: item["is_error"] == true,
+                });
+            }
+            _ => {}
+        }
+    }
+    if !blocks.is_empty() || !tool_calls.is_empty() {
+        messages.push(turn(blocks, tool_calls));
+    }
+
+    messages
+        .into_iter()
+        .map(|content| (content, metadata(None, timestamp_ms)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn texts(head: &Bond<Message>) -> Vec<String> {
+        let mut texts = Vec::new();
+        let mut current = Some(head);
+        while let Some(bond) = current {
+            let message = bond.value().unwrap();
+            texts.push(match &message.content {
+                MessageContent::System(blocks)
+                | MessageContent::User(blocks)
+                | MessageContent::Assistant { blocks, .. } => blocks_text(blocks),
+                MessageContent::ToolResult { result, .. } => result.clone(),
+            });
+            current = message.previous.as_ref();
+        }
+        texts.reverse();
+        texts
+    }
+
+    fn chatgpt_node(parent: Option<&str>, role: &str, text: &str) -> Value {
+        json!({
+            "parent": parent,
+            "message": {
+                "author": { "role": role },
+                "create_time": 1_700_000_000.5,
+                "content": { "content_type": "text", "parts": [text] },
+                "metadata": { "model_slug": "gpt-4o" },
+            },
+        })
+    }
+
+    #[test]
+    fn chatgpt_regenerations_become_branches() {
+        let export = json!([{
+            "title": "Greeting",
+            "current_node": "b",
+            "mapping": {
+                "root": { "parent": null, "message": null },
+                "system": {
+                    "parent": "root",
+                    "message": {
+                        "author": { "role": "system" },
+                        "content": { "content_type": "text", "parts": [""] },
+                        "metadata": { "is_visually_hidden_from_conversation": true },
+                    },
+                },
+                "q": chatgpt_node(Some("system"), "user", "Hi"),
+                "a": chatgpt_node(Some("q"), "assistant", "Hello"),
+                "b": chatgpt_node(Some("q"), "assistant", "Hey"),
+            },
+        }]);
+
+        let conversations = import_chatgpt(&export.to_string()).unwrap();
+        assert_eq!(conversations[0].title.as_deref(), Some("Greeting"));
+        let heads = &conversations[0].heads;
+        assert_eq!(heads.len(), 2);
+        assert_eq!(texts(&heads[0]), ["Hi", "Hey"]);
+        assert_eq!(texts(&heads[1]), ["Hi", "Hello"]);
+
+        let metadata = heads[0].value().unwrap().metadata.as_ref().unwrap();
+        assert_eq!(metadata.model.as_deref(), Some("gpt-4o"));
+        assert_eq!(metadata.timestamp_ms, Some(1_700_000_000_500));
+    }
+
+    #[test]
+    fn claude_tool_results_are_split_out() {
+        let export = json!([{
+            "name": "Weather",
+            "chat_messages": [
+                {
+                    "uuid": "1",
+                    "sender": "human",
+                    "text": "Weather?",
+                    "created_at": "2023-11-14T23:13:20.000000+01:00",
+                    "attachments": [{ "file_name": "notes.txt", "extracted_content": "rain" }],
+                },
+                {
+                    "uuid": "2",
+                    "sender": "assistant",
+                    "content": [
+                        { "type": "tool_use", "id": "t1", "name": "weather", "input": {} },
+                        { "type": "tool_result", "tool_use_id": "t1", "content": "Sunny" },
+                        { "type": "text", "text": "Sunny." },
+                    ],
+                },
+            ],
+        }]);
+
+        let conversations = import_anthropic(&export.to_string()).unwrap();
+        let head = &conversations[0].heads[0];
+        assert_eq!(texts(head), ["Weather?", "", "Sunny", "Sunny."]);
+
+        let mut messages = Vec::new();
+        let mut current = Some(head);
+        while let Some(bond) = current {
+            messages.push(bond.value().unwrap());
+            current = bond.value().unwrap().previous.as_ref();
+        }
+        match &messages[2].content {
+            MessageContent::Assistant { tool_calls, .. } => assert_eq!(tool_calls[0].id, "t1"),
+            _ => panic!("Expected Assistant"),
+        }
+        match &messages[3].content {
+            MessageContent::User(blocks) => {
+                assert!(matches!(&blocks[0], ContentBlock::File { .. }))
+            }
+            _ => panic!("Expected User"),
+        }
+        let metadata = messages[3].metadata.as_ref().unwrap();
+        assert_eq!(metadata.timestamp_ms, Some(1_700_000_000_000));
+    }
+}
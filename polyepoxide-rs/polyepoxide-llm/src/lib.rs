@@ -10,14 +10,26 @@
 //! - Content-addressable message references
 
 mod content;
+mod cost;
+mod export;
+mod import;
 mod message;
 mod metadata;
+mod time;
 mod tool;
+mod transcript;
 
 pub use content::{ContentBlock, ImageData, MessageContent};
+pub use cost::{
+    branch_usage, conversation_usage, BranchUsage, ConversationUsage, CostLedger, LedgerEntry,
+    ModelPricing, SiblingReply, UsageBreakdown, UsageTotals,
+};
+pub use export::{export_jsonl, ExportError, ExportOptions};
+pub use import::{import_anthropic, import_chatgpt, ImportError, ImportedConversation};
 pub use message::Message;
 pub use metadata::{GenerationParams, MessageMetadata, TokenUsage};
 pub use tool::ToolCall;
+pub use transcript::{export_html, export_json, export_markdown};
 
 #[cfg(test)]
 mod tests {
@@ -134,6 +146,130 @@ mod tests {
         assert_eq!(solvent.len(), 3);
     }
 
+    fn reply(previous: Bond<Message>, model: &str, input: u64, output: u64) -> Message {
+        Message {
+            content: MessageContent::Assistant {
+                blocks: vec![ContentBlock::Text("...".to_string())],
+                tool_calls: vec![],
+            },
+            metadata: Some(MessageMetadata {
+                model: Some(model.to_string()),
+                timestamp_ms: None,
+                generation_params: None,
+                stop_reason: None,
+                usage: Some(TokenUsage {
+                    input_tokens: Some(input),
+                    output_tokens: Some(output),
+                    cache_read_tokens: None,
+                    cache_creation_tokens: None,
+                }),
+            }),
+            previous: Some(previous),
+        }
+    }
+
+    #[test]
+    fn usage_across_branches() {
+        let mut solvent = Solvent::new();
+        let question = solvent.bond(Message {
+            content: MessageContent::User(vec![ContentBlock::Text("Question?".to_string())]),
+            metadata: None,
+            previous: None,
+        });
+        let shared = solvent.bond(reply(question, "model-a", 10, 100));
+        let head_a = solvent.bond(reply(shared.clone(), "model-a", 200, 20));
+        let head_b = solvent.bond(reply(shared, "model-b", 300, 30));
+
+        let path = branch_usage(&head_a);
+        assert_eq!(path.messages, 3);
+        assert_eq!(path.totals().output_tokens, 120);
+
+        let usage = conversation_usage(&[head_a.clone(), head_b, head_a]);
+        assert_eq!(usage.branches.len(), 2, "repeated heads are one branch");
+        assert_eq!(usage.total.messages, 4);
+        assert_eq!(usage.total.totals().input_tokens, 510);
+        assert_eq!(usage.branches[0].exclusive.totals().input_tokens, 200);
+        assert_eq!(usage.branches[1].path.totals().input_tokens, 310);
+        assert!(!usage.branches[1].truncated);
+
+        let pricing = std::collections::HashMap::from([
+            ("model-a".to_string(), ModelPricing { input: 1.0, output: 2.0, ..Default::default() }),
+        ]);
+        let costs = usage.total.cost_by_model(&pricing);
+        assert_eq!(costs.len(), 1);
+        assert!((costs["model-a"] - 450.0 / 1_000_000.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn cost_ledger_extends_along_branch() {
+        let mut solvent = Solvent::new();
+        let question = solvent.bond(Message {
+            content: MessageContent::User(vec![ContentBlock::Text("Question?".to_string())]),
+            metadata: None,
+            previous: None,
+        });
+        let pricing = std::collections::HashMap::from([(
+            "model-a".to_string(),
+            ModelPricing {
+                input: 1.0,
+                output: 2.0,
+                ..Default::default()
+            },
+        )]);
+        let answer = solvent.bond(reply(question, "model-a", 10, 100));
+        let ledger = CostLedger::new(answer.clone(), &pricing);
+        assert_eq!(ledger.messages, 2);
+
+        let follow_up = solvent.bond(reply(answer, "model-b", 200, 20));
+        let extended = ledger.extend(follow_up.clone(), &pricing);
+        let recounted = CostLedger::new(follow_up, &pricing);
+        assert_eq!(extended.by_model, recounted.by_model);
+        assert_eq!(extended.messages, 3);
+        assert_eq!(extended.totals().input_tokens, 210);
+        assert!((extended.cost() - 210.0 / 1_000_000.0).abs() < 1e-12);
+
+        let recovered = CostLedger::from_bytes(&extended.to_bytes()).unwrap();
+        assert_eq!(recovered.by_model, extended.by_model);
+    }
+
+    #[test]
+    fn cost_ledger_counts_siblings_off_the_branch() {
+        let mut solvent = Solvent::new();
+        let question = solvent.bond(Message {
+            content: MessageContent::User(vec![ContentBlock::Text("Question?".to_string())]),
+            metadata: None,
+            previous: None,
+        });
+        let pricing = std::collections::HashMap::from([(
+            "model-a".to_string(),
+            ModelPricing {
+                input: 1.0,
+                ..Default::default()
+            },
+        )]);
+        let first = solvent.bond(reply(question.clone(), "model-a", 10, 0));
+        let second = solvent.bond(reply(question, "model-b", 20, 0));
+
+        let mut ledger = CostLedger::new(first.clone(), &pricing);
+        ledger.add_siblings(&[first.clone(), second.clone()], &pricing);
+        ledger.add_siblings(&[second.clone()], &pricing);
+        // The branch's own reply isn't a sibling
+        assert_eq!(ledger.siblings.len(), 1);
+        assert_eq!(ledger.totals().input_tokens, 10);
+        assert_eq!(ledger.spent_totals().input_tokens, 30);
+
+        // Flipping to the other reply counts it on the branch instead
+        let mut flipped = ledger.extend(second, &pricing);
+        flipped.add_siblings(&[first], &pricing);
+        assert_eq!(flipped.totals().input_tokens, 20);
+        assert_eq!(flipped.spent_totals().input_tokens, 30);
+        assert!((flipped.spent() - 10.0 / 1_000_000.0).abs() < 1e-12);
+
+        let recovered = CostLedger::from_bytes(&flipped.to_bytes()).unwrap();
+        assert_eq!(recovered.siblings.len(), 1);
+        assert_eq!(recovered.siblings[0].model, "model-a");
+    }
+
     #[test]
     fn tool_call_roundtrip() {
         let msg = Message {
@@ -263,6 +399,31 @@ mod tests {
         }
     }
 
+    #[test]
+    fn large_attachments_are_chunked() {
+        use polyepoxide_core::{IngestPolicy, MAX_CHUNK_SIZE};
+
+        let policy = IngestPolicy::new();
+        let small = ContentBlock::attachment(&policy, "a.png", "image/png", vec![1; 16]).unwrap();
+        assert!(matches!(
+            small,
+            ContentBlock::Image(ImageData::Embedded { .. })
+        ));
+
+        let data: Vec<u8> = (0..4 * MAX_CHUNK_SIZE)
+            .map(|i| (i * 7 % 251) as u8)
+            .collect();
+        let large = ContentBlock::attachment(&policy, "b.bin", "application/zip", data.clone());
+        match large.unwrap() {
+            ContentBlock::ChunkedFile { data: blob, .. } => {
+                let blob = blob.value().unwrap();
+                assert!(blob.chunks.len() > 1);
+                assert_eq!(blob.to_vec().unwrap(), data);
+            }
+            _ => panic!("Expected ChunkedFile"),
+        }
+    }
+
     #[test]
     fn metadata_roundtrip() {
         let msg = Message {
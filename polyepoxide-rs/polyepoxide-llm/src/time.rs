@@ -0,0 +1,89 @@
+//! Converting between RFC 3339 timestamps and milliseconds since the Unix
+//! epoch, as imports read and transcripts show them.
+
+const MS_PER_DAY: i64 = 86_400_000;
+
+/// Days since 1970-01-01 for a proleptic Gregorian date, after Howard
+/// Hinnant's `days_from_civil`.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Inverse of `days_from_civil`: year, month and day.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    (year_of_era + era * 400 + i64::from(month <= 2), month, day)
+}
+
+/// Parses an RFC 3339 timestamp, e.g. `2024-03-01T12:00:00.5+01:00`, to
+/// milliseconds since the Unix epoch.
+pub(crate) fn parse_timestamp(timestamp: &str) -> Option<u64> {
+    let (date, time) = timestamp.split_once('T')?;
+    let mut date = date.splitn(3, '-').map(|part| part.parse::<i64>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+    let (time, offset) = time.split_at(time.find(['Z', '+', '-']).unwrap_or(time.len()));
+    let mut time = time.splitn(3, ':');
+    let hours: i64 = time.next()?.parse().ok()?;
+    let minutes: i64 = time.next()?.parse().ok()?;
+    let seconds: f64 = time.next()?.parse().ok()?;
+    let offset_minutes = match offset.split_once(':') {
+        Some((offset_hours, offset_minutes)) => {
+            let magnitude =
+                offset_hours[1..].parse::<i64>().ok()? * 60 + offset_minutes.parse::<i64>().ok()?;
+            match offset_hours.starts_with('-') {
+                true => -magnitude,
+                false => magnitude,
+            }
+        }
+        None => 0,
+    };
+
+    let minutes = (days_from_civil(year, month, day) * 24 + hours) * 60 + minutes - offset_minutes;
+    let ms = minutes * 60_000 + (seconds * 1000.0).round() as i64;
+    u64::try_from(ms).ok()
+}
+
+/// Formats milliseconds since the Unix epoch as a UTC date and time.
+pub(crate) fn format_timestamp(ms: u64) -> String {
+    let ms = ms as i64;
+    let (year, month, day) = civil_from_days(ms.div_euclid(MS_PER_DAY));
+    let secs = ms.rem_euclid(MS_PER_DAY) / 1000;
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formatting_inverts_parsing() {
+        let ms = parse_timestamp("2024-02-29T23:59:58.5+01:00").unwrap();
+        assert_eq!(format_timestamp(ms), "2024-02-29 22:59:58 UTC");
+        assert_eq!(format_timestamp(0), "1970-01-01 00:00:00 UTC");
+    }
+}
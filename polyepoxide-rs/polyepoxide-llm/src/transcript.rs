@@ -0,0 +1,514 @@
+//! Readable transcripts of conversation branches, as Markdown, HTML or JSON.
+//!
+//! Unlike [`export_jsonl`](crate::export_jsonl), transcripts keep everything:
+//! thinking, tool calls and results, and the metadata of each message.
+//! Attachments are described rather than embedded.
+
+use polyepoxide_core::Bond;
+use serde_json::{json, Value};
+
+use crate::content::{ContentBlock, ImageData, MessageContent};
+use crate::export::{loaded_branch, ExportError};
+use crate::message::Message;
+use crate::metadata::{GenerationParams, MessageMetadata, TokenUsage};
+use crate::time::format_timestamp;
+use crate::tool::ToolCall;
+
+const HTML_STYLE: &str = "body { font-family: sans-serif; max-width: 50em; margin: auto; }
+.message { border-top: 1px solid #ccc; padding: 0.5em 0; }
+.meta { color: #666; font-size: 0.9em; }
+.text { white-space: pre-wrap; }
+pre { background: #f4f4f4; padding: 0.5em; overflow-x: auto; }
+.error { color: #a00; }";
+
+/// A piece of a message as shown in a transcript.
+enum Part<'a> {
+    Text(&'a str),
+    Code {
+        language: Option<&'a str>,
+        code: &'a str,
+    },
+    Thinking(&'a str),
+    Image(&'a str),
+    Attachment {
+        name: &'a str,
+        mime_type: Option<&'a str>,
+        /// Unknown for chunked files that aren't loaded.
+        size: Option<u64>,
+    },
+    ToolCall(&'a ToolCall),
+    ToolResult {
+        tool_call_id: &'a str,
+        result: &'a str,
+        is_error: bool,
+    },
+}
+
+/// Renders each branch, from its root to the given head, as Markdown.
+pub fn export_markdown(heads: &[Bond<Message>]) -> Result<String, ExportError> {
+    let mut out = String::new();
+    for head in heads {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&format!("# Conversation {}\n", head.cid()));
+        for (_, message) in loaded_branch(head)? {
+            out.push_str(&format!("\n## {}\n", title(role(message))));
+            if let Some(line) = message.metadata.as_ref().and_then(metadata_line) {
+                out.push_str(&format!("\n*{}*\n", line));
+            }
+            for part in parts(message) {
+                out.push('\n');
+                out.push_str(&markdown_part(&part));
+                out.push('\n');
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Renders the branches as one standalone HTML page.
+pub fn export_html(heads: &[Bond<Message>]) -> Result<String, ExportError> {
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Conversations</title>\n<style>\n{}\n</style>\n</head>\n<body>\n",
+        HTML_STYLE
+    );
+    for head in heads {
+        out.push_str(&format!(
+            "<section class=\"conversation\">\n<h1>Conversation {}</h1>\n",
+            head.cid()
+        ));
+        for (cid, message) in loaded_branch(head)? {
+            let role = role(message);
+            out.push_str(&format!(
+                "<article class=\"message {}\" id=\"{}\">\n<h2>{}</h2>\n",
+                role,
+                cid,
+                title(role)
+            ));
+            if let Some(line) = message.metadata.as_ref().and_then(metadata_line) {
+                out.push_str(&format!("<p class=\"meta\">{}</p>\n", escape(&line)));
+            }
+            for part in parts(message) {
+                out.push_str(&html_part(&part));
+                out.push('\n');
+            }
+            out.push_str("</article>\n");
+        }
+        out.push_str("</section>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    Ok(out)
+}
+
+/// Renders the branches as a JSON array of `{"head", "messages"}` objects,
+/// each message with its CID, role, typed content parts and metadata.
+pub fn export_json(heads: &[Bond<Message>]) -> Result<String, ExportError> {
+    let mut branches = Vec::new();
+    for head in heads {
+        let messages: Vec<Value> = loaded_branch(head)?
+            .into_iter()
+            .map(|(cid, message)| {
+                json!({
+                    "cid": cid.to_string(),
+                    "role": role(message),
+                    "content": parts(message).iter().map(json_part).collect::<Vec<_>>(),
+                    "metadata": message.metadata.as_ref().map(json_metadata),
+                })
+            })
+            .collect();
+        branches.push(json!({ "head": head.cid().to_string(), "messages": messages }));
+    }
+    Ok(format!("{:#}\n", Value::Array(branches)))
+}
+
+fn role(message: &Message) -> &'static str {
+    match message.content {
+        MessageContent::System(_) => "system",
+        MessageContent::User(_) => "user",
+        MessageContent::Assistant { .. } => "assistant",
+        MessageContent::ToolResult { .. } => "tool",
+    }
+}
+
+fn title(role: &str) -> String {
+    role[..1].to_uppercase() + &role[1..]
+}
+
+fn parts(message: &Message) -> Vec<Part<'_>> {
+    let (blocks, tool_calls) = match &message.content {
+        MessageContent::System(blocks) | MessageContent::User(blocks) => (blocks, &[][..]),
+        MessageContent::Assistant { blocks, tool_calls } => (blocks, &tool_calls[..]),
+        MessageContent::ToolResult {
+            tool_call_id,
+            result,
+            is_error,
+        } => {
+            return vec![Part::ToolResult {
+                tool_call_id,
+                result,
+                is_error: *is_error,
+            }];
+        }
+    };
+    blocks
+        .iter()
+        .map(|block| match block {
+            ContentBlock::Text(text) => Part::Text(text),
+            ContentBlock::Code { language, code } => Part::Code {
+                language: language.as_deref(),
+                code,
+            },
+            ContentBlock::Thinking(thinking) => Part::Thinking(thinking),
+            ContentBlock::Image(ImageData::Url { url, .. }) => Part::Image(url),
+            ContentBlock::Image(ImageData::Embedded { media_type, data }) => Part::Attachment {
+                name: "image",
+                mime_type: Some(media_type.as_str()),
+                size: Some(data.0.len() as u64),
+            },
+            ContentBlock::File {
+                name,
+                mime_type,
+                data,
+            } => Part::Attachment {
+                name,
+                mime_type: mime_type.as_deref(),
+                size: Some(data.0.len() as u64),
+            },
+            ContentBlock::ChunkedFile {
+                name,
+                mime_type,
+                data,
+            } => Part::Attachment {
+                name,
+                mime_type: Some(mime_type.as_str()),
+                size: data.value().map(|blob| blob.size),
+            },
+        })
+        .chain(tool_calls.iter().map(Part::ToolCall))
+        .collect()
+}
+
+/// Model, time, tokens and stop reason, whichever are known.
+fn metadata_line(metadata: &MessageMetadata) -> Option<String> {
+    let mut fields = Vec::new();
+    if let Some(model) = &metadata.model {
+        fields.push(model.clone());
+    }
+    if let Some(ms) = metadata.timestamp_ms {
+        fields.push(format_timestamp(ms));
+    }
+    let tokens = metadata
+        .usage
+        .as_ref()
+        .and_then(|usage| Some((usage.input_tokens?, usage.output_tokens?)));
+    if let Some((input, output)) = tokens {
+        fields.push(format!("{} in / {} out tokens", input, output));
+    }
+    if let Some(reason) = &metadata.stop_reason {
+        fields.push(format!("stop: {}", reason));
+    }
+    (!fields.is_empty()).then(|| fields.join(" · "))
+}
+
+/// Metadata as a JSON object, leaving out the fields that aren't known.
+fn json_metadata(metadata: &MessageMetadata) -> Value {
+    json_object([
+        ("model", metadata.model.as_deref().map(Value::from)),
+        ("timestamp_ms", metadata.timestamp_ms.map(Value::from)),
+        (
+            "generation_params",
+            metadata.generation_params.as_ref().map(json_params),
+        ),
+        (
+            "stop_reason",
+            metadata.stop_reason.as_deref().map(Value::from),
+        ),
+        ("usage", metadata.usage.as_ref().map(json_usage)),
+    ])
+}
+
+fn json_params(params: &GenerationParams) -> Value {
+    json_object([
+        ("temperature", params.temperature.map(Value::from)),
+        ("top_p", params.top_p.map(Value::from)),
+        ("top_k", params.top_k.map(Value::from)),
+        ("max_tokens", params.max_tokens.map(Value::from)),
+        (
+            "frequency_penalty",
+            params.frequency_penalty.map(Value::from),
+        ),
+        ("presence_penalty", params.presence_penalty.map(Value::from)),
+        ("stop", params.stop.clone().map(Value::from)),
+        ("min_p", params.min_p.map(Value::from)),
+        ("top_a", params.top_a.map(Value::from)),
+        (
+            "repetition_penalty",
+            params.repetition_penalty.map(Value::from),
+        ),
+        ("seed", params.seed.map(Value::from)),
+        (
+            "reasoning_effort",
+            params.reasoning_effort.as_deref().map(Value::from),
+        ),
+        (
+            "reasoning_max_tokens",
+            params.reasoning_max_tokens.map(Value::from),
+        ),
+    ])
+}
+
+fn json_usage(usage: &TokenUsage) -> Value {
+    json_object([
+        ("input_tokens", usage.input_tokens.map(Value::from)),
+        ("output_tokens", usage.output_tokens.map(Value::from)),
+        (
+            "cache_read_tokens",
+            usage.cache_read_tokens.map(Value::from),
+        ),
+        (
+            "cache_creation_tokens",
+            usage.cache_creation_tokens.map(Value::from),
+        ),
+    ])
+}
+
+fn json_object<const N: usize>(fields: [(&str, Option<Value>); N]) -> Value {
+    Value::Object(
+        fields
+            .into_iter()
+            .filter_map(|(key, value)| Some((key.to_string(), value?)))
+            .collect(),
+    )
+}
+
+fn attachment_description(name: &str, mime_type: Option<&str>, size: Option<u64>) -> String {
+    let details: Vec<String> = mime_type
+        .map(String::from)
+        .into_iter()
+        .chain(size.map(|size| format!("{} bytes", size)))
+        .collect();
+    match details.is_empty() {
+        true => format!("attachment: {}", name),
+        false => format!("attachment: {} ({})", name, details.join(", ")),
+    }
+}
+
+/// Tool call arguments, pretty-printed if they are valid JSON.
+fn arguments(call: &ToolCall) -> String {
+    serde_json::from_str::<Value>(&call.arguments)
+        .map(|value| format!("{:#}", value))
+        .unwrap_or_else(|_| call.arguments.clone())
+}
+
+/// A fenced code block, with a fence longer than any backtick run inside.
+fn fenced(language: &str, content: &str) -> String {
+    let longest = content.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{}{}\n{}\n{}", fence, language, content, fence)
+}
+
+fn markdown_part(part: &Part) -> String {
+    match part {
+        Part::Text(text) => text.to_string(),
+        Part::Code { language, code } => fenced(language.unwrap_or(""), code),
+        Part::Thinking(thinking) => format!(
+            "<details>\n<summary>Thinking</summary>\n\n{}\n\n</details>",
+            thinking
+        ),
+        Part::Image(url) => format!("![image]({})", url),
+        Part::Attachment {
+            name,
+            mime_type,
+            size,
+        } => format!("*[{}]*", attachment_description(name, *mime_type, *size)),
+        Part::ToolCall(call) => format!(
+            "**Tool call** `{}` ({})\n\n{}",
+            call.name,
+            call.id,
+            fenced("json", &arguments(call))
+        ),
+        Part::ToolResult {
+            tool_call_id,
+            result,
+            is_error,
+        } => format!(
+            "**Tool result{}** ({})\n\n{}",
+            if *is_error { ", error" } else { "" },
+            tool_call_id,
+            fenced("", result)
+        ),
+    }
+}
+
+fn html_part(part: &Part) -> String {
+    match part {
+        Part::Text(text) => format!("<div class=\"text\">{}</div>", escape(text)),
+        Part::Code { language, code } => code_block(*language, code),
+        Part::Thinking(thinking) => format!(
+            "<details><summary>Thinking</summary><div class=\"text\">{}</div></details>",
+            escape(thinking)
+        ),
+        Part::Image(url) => format!("<img src=\"{}\" alt=\"image\">", escape(url)),
+        Part::Attachment {
+            name,
+            mime_type,
+            size,
+        } => format!(
+            "<p class=\"attachment\">[{}]</p>",
+            escape(&attachment_description(name, *mime_type, *size))
+        ),
+        Part::ToolCall(call) => format!(
+            "<p>Tool call <code>{}</code> ({})</p>\n{}",
+            escape(&call.name),
+            escape(&call.id),
+            code_block(Some("json"), &arguments(call))
+        ),
+        Part::ToolResult {
+            tool_call_id,
+            result,
+            is_error,
+        } => format!(
+            "<p{}>Tool result{} ({})</p>\n{}",
+            if *is_error { " class=\"error\"" } else { "" },
+            if *is_error { ", error" } else { "" },
+            escape(tool_call_id),
+            code_block(None, result)
+        ),
+    }
+}
+
+fn code_block(language: Option<&str>, code: &str) -> String {
+    match language {
+        Some(language) => format!(
+            "<pre><code class=\"language-{}\">{}</code></pre>",
+            escape(language),
+            escape(code)
+        ),
+        None => format!("<pre><code>{}</code></pre>", escape(code)),
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn json_part(part: &Part) -> Value {
+    match part {
+        Part::Text(text) => json!({ "type": "text", "text": text }),
+        Part::Code { language, code } => {
+            json!({ "type": "code", "language": language, "code": code })
+        }
+        Part::Thinking(thinking) => json!({ "type": "thinking", "text": thinking }),
+        Part::Image(url) => json!({ "type": "image", "url": url }),
+        Part::Attachment {
+            name,
+            mime_type,
+            size,
+        } => json!({ "type": "attachment", "name": name, "mime_type": mime_type, "size": size }),
+        Part::ToolCall(call) => json!({
+            "type": "tool_call",
+            "id": call.id,
+            "name": call.name,
+            "arguments": serde_json::from_str::<Value>(&call.arguments)
+                .unwrap_or_else(|_| Value::String(call.arguments.clone())),
+        }),
+        Part::ToolResult {
+            tool_call_id,
+            result,
+            is_error,
+        } => json!({
+            "type": "tool_result",
+            "tool_call_id": tool_call_id,
+            "result": result,
+            "is_error": is_error,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use polyepoxide_core::Solvent;
+    use std::sync::Arc;
+
+    fn conversation(solvent: &mut Solvent) -> Bond<Message> {
+        let question = solvent.add(Message {
+            content: MessageContent::User(vec![
+                ContentBlock::Text("Why <b>?".into()),
+                ContentBlock::Code {
+                    language: Some("md".into()),
+                    code: "```\nfenced\n```".into(),
+                },
+            ]),
+            metadata: None,
+            previous: None,
+        });
+        let answer = solvent.add(Message {
+            content: MessageContent::Assistant {
+                blocks: vec![ContentBlock::Thinking("Hmm.".into())],
+                tool_calls: vec![ToolCall {
+                    id: "call_1".into(),
+                    name: "search".into(),
+                    arguments: r#"{"q":"b"}"#.into(),
+                }],
+            },
+            metadata: Some(MessageMetadata {
+                model: Some("model-a".into()),
+                timestamp_ms: Some(1_700_000_000_000),
+                generation_params: None,
+                stop_reason: Some("tool_calls".into()),
+                usage: None,
+            }),
+            previous: Some(Bond::from_cell(Arc::clone(&question))),
+        });
+        Bond::from_cell(answer)
+    }
+
+    #[test]
+    fn markdown_keeps_everything() {
+        let mut solvent = Solvent::new();
+        let head = conversation(&mut solvent);
+
+        let markdown = export_markdown(&[head]).unwrap();
+        assert!(markdown.contains("## User\n\nWhy <b>?\n\n````md\n```\nfenced\n```\n````\n"));
+        assert!(markdown.contains("*model-a · 2023-11-14 22:13:20 UTC · stop: tool_calls*"));
+        assert!(markdown.contains("<summary>Thinking</summary>\n\nHmm."));
+        assert!(
+            markdown.contains("**Tool call** `search` (call_1)\n\n```json\n{\n  \"q\": \"b\"\n}")
+        );
+    }
+
+    #[test]
+    fn html_and_json_carry_the_same_parts() {
+        let mut solvent = Solvent::new();
+        let head = conversation(&mut solvent);
+
+        let html = export_html(&[head.clone()]).unwrap();
+        assert!(html.contains("<div class=\"text\">Why &lt;b&gt;?</div>"));
+        assert!(html.contains("<pre><code class=\"language-md\">```"));
+
+        let json: Value = serde_json::from_str(&export_json(&[head]).unwrap()).unwrap();
+        let messages = json[0]["messages"].as_array().unwrap();
+        assert_eq!(messages[0]["role"], "user");
+        assert_eq!(messages[1]["content"][1]["arguments"]["q"], "b");
+        assert_eq!(messages[1]["metadata"]["model"], "model-a");
+        assert_eq!(
+            messages[1]["metadata"]["timestamp_ms"],
+            1_700_000_000_000u64
+        );
+        assert!(messages[1]["metadata"].get("usage").is_none());
+        assert!(messages[0]["metadata"].is_null());
+    }
+}
@@ -1,30 +1,102 @@
 //! RocksDB-backed store for Polyepoxide.
+//!
+//! Each data category lives in its own column family, so compaction,
+//! statistics, and scans over refs or indexes don't touch value blocks.
 
+use std::io::{Cursor, Read};
 use std::path::Path;
+use std::sync::Mutex;
 
 use cid::Cid;
-use polyepoxide_core::Store;
-use rocksdb::{DB, Options};
+use polyepoxide_core::{
+    reachable, Batch, Category, CategoryStats, GcStats, IndexStore, LockError, RefStore, Store,
+    StoreLock,
+};
+use rocksdb::{
+    ColumnFamily, DB, DEFAULT_COLUMN_FAMILY_NAME, Direction, IteratorMode, Options, WriteBatch,
+};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
-#[error("RocksDB error: {0}")]
-pub struct RocksError(#[from] rocksdb::Error);
+pub enum RocksError {
+    #[error("RocksDB error: {0}")]
+    Rocks(#[from] rocksdb::Error),
+    #[error(transparent)]
+    Lock(#[from] LockError),
+}
 
 /// A persistent store backed by RocksDB.
 pub struct RocksStore {
     db: DB,
+    /// Serializes ref compare-and-set; the store lock keeps other processes out.
+    refs_lock: Mutex<()>,
+    _lock: StoreLock, // Released after the database is closed
 }
 
 impl RocksStore {
     /// Opens a RocksDB store at the given path.
     ///
-    /// Creates the database if it doesn't exist.
+    /// Creates the database if it doesn't exist, and moves data out of the
+    /// default column family used by older stores. Fails with
+    /// `RocksError::Lock` if another process has the store open.
     pub fn open(path: impl AsRef<Path>) -> Result<Self, RocksError> {
+        let lock = StoreLock::acquire(path.as_ref())?;
         let mut opts = Options::default();
         opts.create_if_missing(true);
-        let db = DB::open(&opts, path)?;
-        Ok(Self { db })
+        opts.create_missing_column_families(true);
+        let db = DB::open_cf(&opts, path, Category::ALL.iter().map(|c| c.name()))?;
+        let store = Self {
+            db,
+            refs_lock: Mutex::new(()),
+            _lock: lock,
+        };
+        store.migrate_legacy()?;
+        Ok(store)
+    }
+
+    /// Returns the column family holding the given category.
+    pub fn column_family(&self, category: Category) -> &ColumnFamily {
+        self.db
+            .cf_handle(category.name())
+            .expect("column families are created on open")
+    }
+
+    /// Counts entries and bytes in each category.
+    pub fn stats(&self) -> Result<Vec<CategoryStats>, RocksError> {
+        Category::ALL
+            .iter()
+            .map(|&category| {
+                let mut stats = CategoryStats::new(category);
+                for entry in self
+                    .db
+                    .iterator_cf(self.column_family(category), IteratorMode::Start)
+                {
+                    let (_, value) = entry?;
+                    stats.entries += 1;
+                    stats.bytes += value.len() as u64;
+                }
+                Ok(stats)
+            })
+            .collect()
+    }
+
+    /// Moves entries of the default column family into the values family.
+    ///
+    /// Schemas aren't told apart from values here; lookups check both, so
+    /// leaving them among values is harmless. Each entry is copied before it
+    /// is removed, so an interrupted migration resumes on the next open.
+    fn migrate_legacy(&self) -> Result<(), RocksError> {
+        let legacy = self
+            .db
+            .cf_handle(DEFAULT_COLUMN_FAMILY_NAME)
+            .expect("default column family always exists");
+        let values = self.column_family(Category::Values);
+        for entry in self.db.iterator_cf(legacy, IteratorMode::Start) {
+            let (key, value) = entry?;
+            self.db.put_cf(values, &key, &value)?;
+            self.db.delete_cf(legacy, &key)?;
+        }
+        Ok(())
     }
 }
 
@@ -32,23 +104,256 @@ impl Store for RocksStore {
     type Error = RocksError;
 
     fn get(&self, cid: &Cid) -> Result<Option<Vec<u8>>, Self::Error> {
-        Ok(self.db.get(cid.to_bytes())?)
+        let key = cid.to_bytes();
+        for category in [Category::Values, Category::Schemas] {
+            if let Some(value) = self.db.get_cf(self.column_family(category), &key)? {
+                return Ok(Some(value));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Reads from the pinned block instead of copying the value.
+    fn get_reader(&self, cid: &Cid) -> Result<Option<Box<dyn Read + '_>>, Self::Error> {
+        let key = cid.to_bytes();
+        for category in [Category::Values, Category::Schemas] {
+            if let Some(value) = self.db.get_pinned_cf(self.column_family(category), &key)? {
+                return Ok(Some(Box::new(Cursor::new(value))));
+            }
+        }
+        Ok(None)
     }
 
     fn put(&self, cid: &Cid, value: &[u8]) -> Result<(), Self::Error> {
-        self.db.put(cid.to_bytes(), value)?;
+        self.db
+            .put_cf(self.column_family(Category::Values), cid.to_bytes(), value)?;
+        Ok(())
+    }
+
+    fn put_schema(&self, cid: &Cid, value: &[u8]) -> Result<(), Self::Error> {
+        self.db
+            .put_cf(self.column_family(Category::Schemas), cid.to_bytes(), value)?;
         Ok(())
     }
 
     fn has(&self, cid: &Cid) -> Result<bool, Self::Error> {
-        Ok(self.db.get_pinned(cid.to_bytes())?.is_some())
+        let key = cid.to_bytes();
+        for category in [Category::Values, Category::Schemas] {
+            if self
+                .db
+                .get_pinned_cf(self.column_family(category), &key)?
+                .is_some()
+            {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Looks up all CIDs with one `multi_get` per column family.
+    fn get_many(&self, cids: &[Cid]) -> Result<Vec<Option<Vec<u8>>>, Self::Error> {
+        let keys: Vec<Vec<u8>> = cids.iter().map(|cid| cid.to_bytes()).collect();
+        let mut results = vec![None; cids.len()];
+        for category in [Category::Values, Category::Schemas] {
+            let missing: Vec<usize> = (0..cids.len()).filter(|&i| results[i].is_none()).collect();
+            if missing.is_empty() {
+                break;
+            }
+            let cf = self.column_family(category);
+            let found = self
+                .db
+                .multi_get_cf(missing.iter().map(|&i| (cf, &keys[i])));
+            for (i, value) in missing.into_iter().zip(found) {
+                results[i] = value?;
+            }
+        }
+        Ok(results)
+    }
+
+    /// Writes all nodes in a single atomic `WriteBatch`.
+    fn put_many(&self, nodes: &[(&Cid, &[u8])]) -> Result<(), Self::Error> {
+        let cf = self.column_family(Category::Values);
+        let mut batch = WriteBatch::default();
+        for (cid, value) in nodes {
+            batch.put_cf(cf, cid.to_bytes(), value);
+        }
+        self.db.write(batch)?;
+        Ok(())
+    }
+
+    /// Writes schemas and values in a single `WriteBatch`.
+    fn write_batch(&self, batch: &Batch) -> Result<(), Self::Error> {
+        let mut writes = WriteBatch::default();
+        for (cid, value) in batch.schemas() {
+            writes.put_cf(self.column_family(Category::Schemas), cid.to_bytes(), value);
+        }
+        for (cid, value) in batch.values() {
+            writes.put_cf(self.column_family(Category::Values), cid.to_bytes(), value);
+        }
+        self.db.write(writes)?;
+        Ok(())
+    }
+
+    fn delete(&self, cid: &Cid) -> Result<(), Self::Error> {
+        self.delete_many(std::slice::from_ref(cid))
+    }
+
+    /// Removes the CIDs from values and schemas in a single `WriteBatch`.
+    fn delete_many(&self, cids: &[Cid]) -> Result<(), Self::Error> {
+        let mut batch = WriteBatch::default();
+        for cid in cids {
+            let key = cid.to_bytes();
+            for category in [Category::Values, Category::Schemas] {
+                batch.delete_cf(self.column_family(category), &key);
+            }
+        }
+        self.db.write(batch)?;
+        Ok(())
+    }
+
+    /// Iterates over values, then schemas. Keys that aren't CIDs are skipped.
+    fn iter(&self) -> Box<dyn Iterator<Item = Result<Cid, Self::Error>> + '_> {
+        Box::new(
+            [Category::Values, Category::Schemas]
+                .into_iter()
+                .flat_map(|category| {
+                    self.db
+                        .iterator_cf(self.column_family(category), IteratorMode::Start)
+                })
+                .filter_map(|entry| match entry {
+                    Ok((key, _)) => Cid::try_from(&key[..]).ok().map(Ok),
+                    Err(e) => Some(Err(e.into())),
+                }),
+        )
+    }
+
+    fn gc(&self, roots: &[Cid]) -> Result<GcStats, Self::Error> {
+        let keep = reachable(self, roots)?;
+        let mut stats = GcStats {
+            retained: keep.len(),
+            ..Default::default()
+        };
+
+        // Schemas are never swept, as values don't link to them
+        let cf = self.column_family(Category::Values);
+        for entry in self.db.iterator_cf(cf, IteratorMode::Start) {
+            let (key, value) = entry?;
+            // Keys that aren't CIDs weren't written through this API; leave them
+            let Ok(cid) = Cid::try_from(&key[..]) else {
+                continue;
+            };
+            if !keep.contains(&cid) {
+                self.db.delete_cf(cf, &key)?;
+                stats.freed += 1;
+                stats.freed_bytes += value.len() as u64;
+            }
+        }
+        Ok(stats)
+    }
+
+    /// Compacts the full key range of every column family.
+    fn compact(&self) -> Result<(), Self::Error> {
+        for category in Category::ALL {
+            self.db.compact_range_cf(self.column_family(category), None::<&[u8]>, None::<&[u8]>);
+        }
+        Ok(())
+    }
+}
+
+/// Refs are kept in the refs column family, keyed by name, with the CID
+/// bytes as value.
+impl RefStore for RocksStore {
+    fn get_ref(&self, name: &str) -> Result<Option<Cid>, Self::Error> {
+        let value = self.db.get_cf(self.column_family(Category::Refs), name)?;
+        Ok(value.and_then(|bytes| Cid::try_from(&bytes[..]).ok()))
+    }
+
+    fn set_ref(&self, name: &str, cid: &Cid) -> Result<(), Self::Error> {
+        let _guard = self.refs_lock.lock().unwrap();
+        self.db
+            .put_cf(self.column_family(Category::Refs), name, cid.to_bytes())?;
+        Ok(())
+    }
+
+    fn delete_ref(&self, name: &str) -> Result<(), Self::Error> {
+        let _guard = self.refs_lock.lock().unwrap();
+        self.db.delete_cf(self.column_family(Category::Refs), name)?;
+        Ok(())
+    }
+
+    /// Entries whose name isn't UTF-8 or whose value isn't a CID are skipped.
+    fn list_refs(&self, prefix: &str) -> Result<Vec<(String, Cid)>, Self::Error> {
+        let mode = IteratorMode::From(prefix.as_bytes(), Direction::Forward);
+        let mut refs = Vec::new();
+        for entry in self.db.iterator_cf(self.column_family(Category::Refs), mode) {
+            let (key, value) = entry?;
+            if !key.starts_with(prefix.as_bytes()) {
+                break;
+            }
+            let (Ok(name), Ok(cid)) = (std::str::from_utf8(&key), Cid::try_from(&value[..])) else {
+                continue;
+            };
+            refs.push((name.to_string(), cid));
+        }
+        Ok(refs)
+    }
+
+    fn compare_and_set_ref(
+        &self,
+        name: &str,
+        expected: Option<&Cid>,
+        new: &Cid,
+    ) -> Result<bool, Self::Error> {
+        let _guard = self.refs_lock.lock().unwrap();
+        let refs = self.column_family(Category::Refs);
+        let current = self
+            .db
+            .get_cf(refs, name)?
+            .and_then(|bytes| Cid::try_from(&bytes[..]).ok());
+        if current.as_ref() != expected {
+            return Ok(false);
+        }
+        self.db.put_cf(refs, name, new.to_bytes())?;
+        Ok(true)
+    }
+}
+
+/// Index entries are kept in the indexes column family, laid out like
+/// refs.
+impl IndexStore for RocksStore {
+    fn set_index_entry(&self, name: &str, cid: &Cid) -> Result<(), Self::Error> {
+        self.db
+            .put_cf(self.column_family(Category::Indexes), name, cid.to_bytes())?;
+        Ok(())
+    }
+
+    fn delete_index_entry(&self, name: &str) -> Result<(), Self::Error> {
+        self.db.delete_cf(self.column_family(Category::Indexes), name)?;
+        Ok(())
+    }
+
+    /// Entries whose name isn't UTF-8 or whose value isn't a CID are skipped.
+    fn list_index_entries(&self, prefix: &str) -> Result<Vec<(String, Cid)>, Self::Error> {
+        let mode = IteratorMode::From(prefix.as_bytes(), Direction::Forward);
+        let mut entries = Vec::new();
+        for entry in self.db.iterator_cf(self.column_family(Category::Indexes), mode) {
+            let (key, value) = entry?;
+            if !key.starts_with(prefix.as_bytes()) {
+                break;
+            }
+            let (Ok(name), Ok(cid)) = (std::str::from_utf8(&key), Cid::try_from(&value[..])) else {
+                continue;
+            };
+            entries.push((name.to_string(), cid));
+        }
+        Ok(entries)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use polyepoxide_core::compute_cid;
+    use polyepoxide_core::{compute_cid, Solvent};
     use tempfile::TempDir;
 
     fn temp_store() -> (RocksStore, TempDir) {
@@ -69,6 +374,17 @@ mod tests {
         assert_eq!(retrieved, Some(value.to_vec()));
     }
 
+    #[test]
+    fn get_reader() {
+        let (store, _dir) = temp_store();
+        let cid = compute_cid(b"test");
+        store.put(&cid, b"hello world").unwrap();
+
+        let mut read = Vec::new();
+        store.get_reader(&cid).unwrap().unwrap().read_to_end(&mut read).unwrap();
+        assert_eq!(read, b"hello world");
+    }
+
     #[test]
     fn get_missing() {
         let (store, _dir) = temp_store();
@@ -91,6 +407,139 @@ mod tests {
         assert!(store.has(&cid).unwrap());
     }
 
+    #[test]
+    fn batch_put_get() {
+        let (store, _dir) = temp_store();
+        let cids: Vec<Cid> = (0..3u8).map(|i| compute_cid(&[i])).collect();
+        let missing = compute_cid(b"missing");
+        store.put_schema(&cids[2], b"schema").unwrap();
+
+        store
+            .put_many(&[(&cids[0], b"a".as_slice()), (&cids[1], b"b".as_slice())])
+            .unwrap();
+        let results = store.get_many(&[cids[0], missing, cids[1], cids[2]]).unwrap();
+
+        assert_eq!(
+            results,
+            vec![
+                Some(b"a".to_vec()),
+                None,
+                Some(b"b".to_vec()),
+                Some(b"schema".to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn delete() {
+        let (store, _dir) = temp_store();
+        let value = compute_cid(b"value");
+        let schema = compute_cid(b"schema");
+        let kept = compute_cid(b"kept");
+        store.put(&value, b"value").unwrap();
+        store.put_schema(&schema, b"schema").unwrap();
+        store.put(&kept, b"kept").unwrap();
+
+        store.delete_many(&[value, schema]).unwrap();
+        store.delete(&compute_cid(b"never stored")).unwrap();
+
+        assert!(!store.has(&value).unwrap());
+        assert!(!store.has(&schema).unwrap());
+        assert!(store.has(&kept).unwrap());
+    }
+
+    #[test]
+    fn list_cids() {
+        let (store, _dir) = temp_store();
+        let value = compute_cid(b"value");
+        let schema = compute_cid(b"schema");
+        store.put(&value, b"value").unwrap();
+        store.put_schema(&schema, b"schema").unwrap();
+
+        let mut cids = store.list_cids().unwrap();
+        cids.sort();
+        let mut expected = vec![value, schema];
+        expected.sort();
+        assert_eq!(cids, expected);
+    }
+
+    #[test]
+    fn gc_removes_unreachable() {
+        let (store, _dir) = temp_store();
+        let mut solvent = Solvent::new();
+        let kept = solvent.add(vec![solvent.bond("kept".to_string())]);
+        let dropped = solvent.add(vec![solvent.bond("dropped".to_string())]);
+        let (kept_cid, schema_cid) = solvent.persist_cell(&kept, &store).unwrap();
+        let (dropped_cid, _) = solvent.persist_cell(&dropped, &store).unwrap();
+
+        let stats = store.gc(&[kept_cid, schema_cid]).unwrap();
+
+        assert_eq!(stats.freed, 2);
+        assert!(store.has(&kept.value()[0].cid()).unwrap());
+        assert!(!store.has(&dropped_cid).unwrap());
+    }
+
+    #[test]
+    fn gc_keeps_schemas() {
+        let (store, _dir) = temp_store();
+        let mut solvent = Solvent::new();
+        let cell = solvent.add(vec![solvent.bond("kept".to_string())]);
+        let (cid, schema_cid) = solvent.persist_cell(&cell, &store).unwrap();
+
+        store.gc(&[cid]).unwrap();
+
+        assert!(store.has(&cid).unwrap());
+        assert!(store.has(&schema_cid).unwrap());
+    }
+
+    #[test]
+    fn migrates_default_column_family() {
+        let dir = TempDir::new().unwrap();
+        let cid = compute_cid(b"old");
+        {
+            let mut opts = Options::default();
+            opts.create_if_missing(true);
+            let db = DB::open(&opts, dir.path()).unwrap();
+            db.put(cid.to_bytes(), b"old").unwrap();
+        }
+
+        let store = RocksStore::open(dir.path()).unwrap();
+        assert_eq!(store.get(&cid).unwrap(), Some(b"old".to_vec()));
+        let stats = store.stats().unwrap();
+        assert_eq!(stats[Category::Values.index()].entries, 1);
+    }
+
+    #[test]
+    fn refs() {
+        let dir = TempDir::new().unwrap();
+        let first = compute_cid(b"first");
+        let second = compute_cid(b"second");
+
+        {
+            let store = RocksStore::open(dir.path()).unwrap();
+            store.set_ref("inventory/head", &first).unwrap();
+            store.set_ref("chat/work", &second).unwrap();
+            store.set_ref("chatter", &first).unwrap();
+            assert!(store
+                .compare_and_set_ref("inventory/head", Some(&first), &second)
+                .unwrap());
+            assert!(!store
+                .compare_and_set_ref("inventory/head", Some(&first), &first)
+                .unwrap());
+            // Refs aren't blocks
+            assert!(store.list_cids().unwrap().is_empty());
+        }
+
+        let store = RocksStore::open(dir.path()).unwrap();
+        assert_eq!(store.get_ref("inventory/head").unwrap(), Some(second));
+        assert_eq!(
+            store.list_refs("chat/").unwrap(),
+            vec![("chat/work".to_string(), second)]
+        );
+        store.delete_ref("chat/work").unwrap();
+        assert_eq!(store.get_ref("chat/work").unwrap(), None);
+    }
+
     #[test]
     fn persistence() {
         let dir = TempDir::new().unwrap();
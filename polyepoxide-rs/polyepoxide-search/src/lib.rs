@@ -0,0 +1,205 @@
+//! Full-text search over the text fields of stored values.
+//!
+//! `SearchIndex` keeps a tantivy index beside a store. Values are added with
+//! their schema, which says which fields are text; every string in a value,
+//! however deeply nested, is indexed under the value's CID. Bonded values
+//! are separate documents, so a match names the value holding the text
+//! rather than every value linking to it.
+//!
+//! ```ignore
+//! let search = SearchIndex::open(Path::new("search"))?;
+//! search.add_cell(&conversation)?;
+//! search.commit()?;
+//! for (cid, score) in search.search("tantivy AND rust", 10)? { ... }
+//! ```
+
+use std::path::Path;
+use std::sync::Mutex;
+
+use cid::Cid;
+use ipld_core::ipld::Ipld;
+use polyepoxide_core::traverse::{loaded_values, parse_to_ipld, resolve_schema, ParseError};
+use polyepoxide_core::{Bond, Cell, Oxide, Structure};
+use tantivy::collector::TopDocs;
+use tantivy::directory::MmapDirectory;
+use tantivy::query::QueryParser;
+use tantivy::schema::{Field, Schema, Value, STORED, STRING, TEXT};
+use tantivy::{Index, IndexReader, IndexWriter, ReloadPolicy, TantivyDocument, Term};
+
+/// Memory the index writer may use before flushing segments.
+const WRITER_MEMORY: usize = 50_000_000;
+
+/// Error from indexing or searching.
+#[derive(Debug, thiserror::Error)]
+pub enum SearchError {
+    #[error("index error: {0}")]
+    Index(#[from] tantivy::TantivyError),
+    #[error("invalid query: {0}")]
+    Query(#[from] tantivy::query::QueryParserError),
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error("index holds an invalid CID {0:?}")]
+    Corrupt(String),
+}
+
+/// A full-text index of values, keyed by CID.
+///
+/// Added and removed values take effect for searches after `commit`.
+pub struct SearchIndex {
+    index: Index,
+    writer: Mutex<IndexWriter>,
+    reader: IndexReader,
+    cid: Field,
+    text: Field,
+}
+
+impl SearchIndex {
+    /// Opens the index in `dir`, creating it if the directory is empty.
+    pub fn open(dir: &Path) -> Result<Self, SearchError> {
+        let directory = MmapDirectory::open(dir).map_err(tantivy::TantivyError::from)?;
+        Self::with_index(Index::open_or_create(directory, schema())?)
+    }
+
+    /// Creates an index kept only in memory.
+    pub fn in_memory() -> Result<Self, SearchError> {
+        Self::with_index(Index::create_in_ram(schema()))
+    }
+
+    fn with_index(index: Index) -> Result<Self, SearchError> {
+        let schema = index.schema();
+        Ok(Self {
+            writer: Mutex::new(index.writer(WRITER_MEMORY)?),
+            reader: index
+                .reader_builder()
+                .reload_policy(ReloadPolicy::Manual)
+                .try_into()?,
+            cid: schema.get_field("cid")?,
+            text: schema.get_field("text")?,
+            index,
+        })
+    }
+
+    /// Indexes the strings in `value`, replacing any earlier entry for
+    /// `cid`. Strings in bonded values are not included. Returns whether the
+    /// value held any text; values without text are not indexed.
+    pub fn add(
+        &self,
+        cid: &Cid,
+        value: &Ipld,
+        schema: &Bond<Structure>,
+    ) -> Result<bool, SearchError> {
+        let mut texts = Vec::new();
+        collect_texts(value, schema, &mut Vec::new(), &mut texts);
+        let writer = self.writer.lock().unwrap();
+        writer.delete_term(Term::from_field_text(self.cid, &cid.to_string()));
+        if texts.is_empty() {
+            return Ok(false);
+        }
+        let mut document = TantivyDocument::default();
+        document.add_text(self.cid, cid.to_string());
+        for text in texts {
+            document.add_text(self.text, text);
+        }
+        writer.add_document(document)?;
+        Ok(true)
+    }
+
+    /// Indexes a cell and the values bonded from it that are loaded.
+    /// Returns the number of values holding text.
+    pub fn add_cell<T: Oxide>(&self, cell: &Cell<T>) -> Result<usize, SearchError> {
+        let mut added = 0;
+        for value in loaded_values(cell) {
+            if self.add(&value.cid, &parse_to_ipld(&value.bytes)?, &value.schema)? {
+                added += 1;
+            }
+        }
+        Ok(added)
+    }
+
+    /// Drops a value from the index, such as after deleting it from the
+    /// store.
+    pub fn remove(&self, cid: &Cid) {
+        let writer = self.writer.lock().unwrap();
+        writer.delete_term(Term::from_field_text(self.cid, &cid.to_string()));
+    }
+
+    /// Makes added and removed values visible to searches and durable.
+    pub fn commit(&self) -> Result<(), SearchError> {
+        self.writer.lock().unwrap().commit()?;
+        self.reader.reload()?;
+        Ok(())
+    }
+
+    /// Returns up to `limit` values matching `query`, best first, with
+    /// their scores.
+    ///
+    /// Queries use tantivy's syntax: terms, `"phrases"`, `AND`/`OR`, `-term`
+    /// and `term*` prefixes.
+    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<(Cid, f32)>, SearchError> {
+        let query = QueryParser::for_index(&self.index, vec![self.text]).parse_query(query)?;
+        let searcher = self.reader.searcher();
+        let mut results = Vec::new();
+        for (score, address) in searcher.search(&query, &TopDocs::with_limit(limit))? {
+            let document: TantivyDocument = searcher.doc(address)?;
+            let cid = document.get_first(self.cid).and_then(|v| v.as_str());
+            let cid = cid.ok_or_else(|| SearchError::Corrupt(String::new()))?;
+            let cid = Cid::try_from(cid).map_err(|_| SearchError::Corrupt(cid.to_string()))?;
+            results.push((cid, score));
+        }
+        Ok(results)
+    }
+}
+
+fn schema() -> Schema {
+    let mut builder = Schema::builder();
+    builder.add_text_field("cid", STRING | STORED);
+    builder.add_text_field("text", TEXT);
+    builder.build()
+}
+
+/// Appends the strings in `value` to `texts`, reading nested schemas
+/// through `frames`, the enclosing records and unions innermost last.
+fn collect_texts(
+    value: &Ipld,
+    schema: &Bond<Structure>,
+    frames: &mut Vec<Bond<Structure>>,
+    texts: &mut Vec<String>,
+) {
+    let Some(schema) = resolve_schema(schema, frames) else {
+        return;
+    };
+    let Some(structure) = schema.value() else {
+        return;
+    };
+    match (structure, value) {
+        (Structure::Unicode, Ipld::String(text)) => texts.push(text.clone()),
+        (Structure::Record(fields) | Structure::Tagged(fields), Ipld::Map(map)) => {
+            frames.push(schema.clone());
+            for (name, field) in map {
+                if let Some(field_schema) = fields.get(name) {
+                    collect_texts(field, field_schema, frames, texts);
+                }
+            }
+            frames.pop();
+        }
+        (Structure::Sequence(inner), Ipld::List(items)) => {
+            for item in items {
+                collect_texts(item, inner, frames, texts);
+            }
+        }
+        (Structure::Tuple(elements), Ipld::List(items)) => {
+            for (item, element) in items.iter().zip(elements) {
+                collect_texts(item, element, frames, texts);
+            }
+        }
+        (
+            Structure::Map { value: inner, .. } | Structure::OrderedMap { value: inner, .. },
+            Ipld::Map(map),
+        ) => {
+            for item in map.values() {
+                collect_texts(item, inner, frames, texts);
+            }
+        }
+        _ => {}
+    }
+}
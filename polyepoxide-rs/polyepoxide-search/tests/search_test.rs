@@ -0,0 +1,40 @@
+use polyepoxide_core::{Bond, Oxide, Solvent};
+use polyepoxide_search::SearchIndex;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, Oxide)]
+struct Message {
+    author: String,
+    text: String,
+    reply_to: Option<Bond<Message>>,
+}
+
+#[test]
+fn finds_messages_by_their_text() {
+    let mut solvent = Solvent::new();
+    let question = solvent.bond(Message {
+        author: "ada".into(),
+        text: "Which drill bits fit the cordless drill?".into(),
+        reply_to: None,
+    });
+    let answer = solvent.add(Message {
+        author: "grace".into(),
+        text: "The hex shank ones in the blue case".into(),
+        reply_to: Some(question.clone()),
+    });
+
+    let search = SearchIndex::in_memory().unwrap();
+    assert_eq!(search.add_cell(&answer).unwrap(), 2);
+    search.commit().unwrap();
+
+    let cids = |query: &str| -> Vec<_> {
+        let results = search.search(query, 10).unwrap();
+        results.into_iter().map(|(cid, _)| cid).collect()
+    };
+    assert_eq!(cids("drill"), [question.cid()]);
+    assert_eq!(cids("grace"), [answer.cid()]);
+    assert!(cids("\"blue case\" AND hex").contains(&answer.cid()));
+
+    search.remove(&question.cid());
+    search.commit().unwrap();
+    assert!(cids("drill").is_empty());
+}
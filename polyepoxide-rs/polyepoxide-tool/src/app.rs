@@ -2,6 +2,7 @@
 
 use std::io::{self, stdout};
 use std::path::PathBuf;
+use std::time::Duration;
 
 use cid::Cid;
 use crossterm::{
@@ -9,13 +10,26 @@ use crossterm::{
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     ExecutableCommand,
 };
+use ipld_core::ipld::Ipld;
 use ratatui::{backend::CrosstermBackend, Terminal};
 use tui_tree_widget::TreeState;
 
 use crate::export::{export, ExportFormat, ExportOptions};
-use crate::store::AnyStore;
+use crate::inspect::inspect;
 use crate::tree::{NodeId, TreeModel};
 use crate::ui;
+use crate::PxStore;
+
+/// What the line being typed at the bottom is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Prompt {
+    /// A query, after `/`.
+    Search,
+    /// A path such as `items[*].name`, after `:`.
+    Select,
+    /// A new value for the selected leaf.
+    Edit,
+}
 
 /// Application state.
 pub struct App {
@@ -24,12 +38,24 @@ pub struct App {
     pub should_quit: bool,
     pub last_error: Option<String>,
     pub export_path: Option<PathBuf>,
+    /// Line being typed, if any.
+    pub input: Option<(Prompt, String)>,
+    /// Paths of the nodes matching the last search.
+    pub search_matches: Vec<Vec<NodeId>>,
+    /// Index of the match last jumped to.
+    pub search_index: usize,
+    /// Whether the raw bytes pane is shown.
+    pub show_raw: bool,
+    /// Contents of the raw bytes pane and the node they describe.
+    pub raw: Option<(NodeId, Vec<String>)>,
+    /// Lines scrolled down in the raw bytes pane.
+    pub raw_scroll: u16,
 }
 
 impl App {
     /// Create a new application.
     pub fn new(
-        store: AnyStore,
+        store: PxStore,
         root_cid: Cid,
         schema_cid: Cid,
     ) -> Result<Self, Box<dyn std::error::Error>> {
@@ -48,6 +74,12 @@ impl App {
             should_quit: false,
             last_error: None,
             export_path: None,
+            input: None,
+            search_matches: Vec::new(),
+            search_index: 0,
+            show_raw: false,
+            raw: None,
+            raw_scroll: 0,
         })
     }
 
@@ -71,15 +103,22 @@ impl App {
         terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
     ) -> Result<(), Box<dyn std::error::Error>> {
         loop {
+            self.tree.poll();
             terminal.draw(|frame| ui::render(frame, self))?;
 
             if self.should_quit {
                 break;
             }
 
+            // Wake up regularly to show loaded bonds and animate the spinner
+            if !event::poll(Duration::from_millis(100))? {
+                continue;
+            }
             if let Event::Key(key) = event::read()? {
                 if key.kind == KeyEventKind::Press {
                     self.handle_key(key.code);
+                    self.load_opened();
+                    self.update_raw();
                 }
             }
         }
@@ -90,6 +129,20 @@ impl App {
     fn handle_key(&mut self, code: KeyCode) {
         self.last_error = None;
 
+        if let Some((prompt, line)) = &mut self.input {
+            match code {
+                KeyCode::Char(c) => line.push(c),
+                KeyCode::Backspace => {
+                    line.pop();
+                }
+                KeyCode::Enter if *prompt == Prompt::Edit => self.edit_selected(),
+                KeyCode::Enter => self.run_search(),
+                KeyCode::Esc => self.input = None,
+                _ => {}
+            }
+            return;
+        }
+
         match code {
             KeyCode::Char('q') | KeyCode::Esc => {
                 self.should_quit = true;
@@ -124,10 +177,132 @@ impl App {
             KeyCode::Char('y') => {
                 self.export_current(ExportFormat::Yaml);
             }
+            KeyCode::Char('/') => {
+                self.input = Some((Prompt::Search, String::new()));
+            }
+            KeyCode::Char(':') => {
+                self.input = Some((Prompt::Select, String::new()));
+            }
+            KeyCode::Char('i') => {
+                self.start_edit();
+            }
+            KeyCode::Char('r') => {
+                self.show_raw = !self.show_raw;
+            }
+            KeyCode::PageDown => {
+                self.raw_scroll = self.raw_scroll.saturating_add(10);
+            }
+            KeyCode::PageUp => {
+                self.raw_scroll = self.raw_scroll.saturating_sub(10);
+            }
+            KeyCode::Char('n') => {
+                self.jump_to_match(1);
+            }
+            KeyCode::Char('N') => {
+                self.jump_to_match(self.search_matches.len().saturating_sub(1));
+            }
             _ => {}
         }
     }
 
+    /// Starts loading the bonds opened since the last key.
+    fn load_opened(&mut self) {
+        for path in self.tree_state.opened() {
+            if let Some(node_id) = path.last() {
+                self.tree.load_children(node_id);
+            }
+        }
+    }
+
+    /// Describes the selected node's bytes if the raw pane is shown and
+    /// the selection has changed.
+    fn update_raw(&mut self) {
+        let selected = self.tree_state.selected().last().filter(|_| self.show_raw);
+        let Some(node_id) = selected else {
+            self.raw = None;
+            return;
+        };
+        if matches!(&self.raw, Some((id, _)) if id == node_id) {
+            return;
+        }
+        let lines = match self.tree.raw_bytes(node_id) {
+            Ok((cid, bytes)) => inspect(cid, &bytes),
+            Err(e) => vec![format!("Error: {}", e)],
+        };
+        self.raw = Some((node_id.clone(), lines));
+        self.raw_scroll = 0;
+    }
+
+    fn run_search(&mut self) {
+        let Some((prompt, query)) = self.input.take() else {
+            return;
+        };
+        self.search_matches = match prompt {
+            Prompt::Select => match self.tree.select(&query) {
+                Ok(matches) => matches,
+                Err(e) => {
+                    self.search_matches.clear();
+                    self.last_error = Some(format!("Invalid path: {}", e));
+                    return;
+                }
+            },
+            _ if query.is_empty() => Vec::new(),
+            _ => self.tree.search(&query),
+        };
+        if self.search_matches.is_empty() {
+            self.last_error = Some(format!("No matches for {:?}", query));
+            return;
+        }
+        self.search_index = self.search_matches.len() - 1;
+        self.jump_to_match(1);
+    }
+
+    /// Selects the match `offset` places after the current one, wrapping
+    /// around, and opens its ancestors.
+    fn jump_to_match(&mut self, offset: usize) {
+        if self.search_matches.is_empty() {
+            return;
+        }
+        self.search_index = (self.search_index + offset) % self.search_matches.len();
+        let path = &self.search_matches[self.search_index];
+        for depth in 1..path.len() {
+            self.tree_state.open(path[..depth].to_vec());
+        }
+        self.tree_state.select(path.clone());
+    }
+
+    /// Opens the edit prompt with the selected leaf's current value.
+    fn start_edit(&mut self) {
+        let node = match self.tree_state.selected().last() {
+            Some(id) => self.tree.get_node(id),
+            None => return,
+        };
+        let current = match node.and_then(|n| n.ipld.as_ref()) {
+            Some(Ipld::String(s)) => s.clone(),
+            Some(Ipld::Integer(n)) => n.to_string(),
+            Some(Ipld::Bool(b)) => b.to_string(),
+            _ => {
+                self.last_error = Some("Only strings, integers and booleans can be edited".into());
+                return;
+            }
+        };
+        self.input = Some((Prompt::Edit, current));
+    }
+
+    fn edit_selected(&mut self) {
+        let Some((_, line)) = self.input.take() else {
+            return;
+        };
+        let node_id = match self.tree_state.selected().last() {
+            Some(id) => id.clone(),
+            None => return,
+        };
+        match self.tree.edit_leaf(&node_id, &line) {
+            Ok(_) => self.reset_tree_state(),
+            Err(e) => self.last_error = Some(format!("Edit error: {}", e)),
+        }
+    }
+
     fn zoom_in_selected(&mut self) {
         let node_id = match self.tree_state.selected().last() {
             Some(id) => id.clone(),
@@ -161,6 +336,8 @@ impl App {
     }
 
     fn reset_tree_state(&mut self) {
+        // Matches refer to nodes of the previous tree
+        self.search_matches.clear();
         self.tree_state = TreeState::default();
         if let Some(root_id) = self.tree.roots.first() {
             self.tree_state.select(vec![root_id.clone()]);
@@ -183,6 +360,8 @@ impl App {
         let ext = match format {
             ExportFormat::Json => "json",
             ExportFormat::Yaml => "yaml",
+            ExportFormat::Dot => "dot",
+            ExportFormat::Mermaid => "mmd",
         };
 
         // Determine what to export: for bonds use the linked CID, otherwise use root
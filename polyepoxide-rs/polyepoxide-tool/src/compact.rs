@@ -0,0 +1,45 @@
+//! `px compact`: reclaiming the space of deleted and collected data.
+
+use std::path::Path;
+
+use polyepoxide_core::Store;
+
+use crate::error::PxError;
+use crate::{open_store, SlowLogArgs};
+
+/// Compacts the store and reports its on-disk size before and after.
+pub fn run(store_type: &str, path: &Path, slow_log: &SlowLogArgs) -> Result<String, PxError> {
+    let before = disk_size(path)?;
+    // Closed before measuring again, so the backend has removed obsolete files
+    {
+        let store = open_store(store_type, path, slow_log)?;
+        store.compact()?;
+    }
+    let after = disk_size(path)?;
+
+    Ok(format!(
+        "before: {:>12} bytes\nafter:  {:>12} bytes\nfreed:  {:>12} bytes\n",
+        before,
+        after,
+        before.saturating_sub(after)
+    ))
+}
+
+/// Total size of the files under `path`.
+fn disk_size(path: &Path) -> Result<u64, PxError> {
+    let read_error = |source: std::io::Error| PxError::Read {
+        path: path.to_path_buf(),
+        source,
+    };
+    let mut total = 0;
+    for entry in std::fs::read_dir(path).map_err(read_error)? {
+        let entry = entry.map_err(read_error)?;
+        let metadata = entry.metadata().map_err(read_error)?;
+        total += if metadata.is_dir() {
+            disk_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(total)
+}
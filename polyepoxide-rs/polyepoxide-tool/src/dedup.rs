@@ -0,0 +1,61 @@
+//! `px dedup`: how much fixed-size vs content-defined chunking would store.
+
+use polyepoxide_core::{Blob, ByteString, DedupReport, Store, MAX_CHUNK_SIZE};
+
+use crate::error::PxError;
+use crate::PxStore;
+
+/// Measures chunking over the binary content of a store: every `Blob`, and
+/// every unchunked `ByteString` too large to be a blob chunk.
+pub fn report(store: &PxStore) -> Result<String, PxError> {
+    let mut contents = Vec::new();
+    for cid in store.list_cids()? {
+        let Some(bytes) = store.get(&cid)? else {
+            continue;
+        };
+        if let Ok(blob) = serde_ipld_dagcbor::from_slice::<Blob>(&bytes) {
+            let chunk_cids: Vec<_> = blob.chunks.iter().map(|chunk| chunk.cid()).collect();
+            let chunks: Option<Vec<_>> = store.get_many(&chunk_cids)?.into_iter().collect();
+            let Some(chunks) = chunks else {
+                continue;
+            };
+            let mut data = Vec::with_capacity(blob.size as usize);
+            for chunk in chunks {
+                if let Ok(chunk) = serde_ipld_dagcbor::from_slice::<ByteString>(&chunk) {
+                    data.extend(chunk.into_vec());
+                }
+            }
+            contents.push(data);
+        } else if let Ok(data) = serde_ipld_dagcbor::from_slice::<ByteString>(&bytes) {
+            if data.as_bytes().len() > MAX_CHUNK_SIZE {
+                contents.push(data.into_vec());
+            }
+        }
+    }
+
+    let report = DedupReport::measure(contents.iter().map(Vec::as_slice));
+    Ok(render(&report))
+}
+
+fn render(report: &DedupReport) -> String {
+    let ratio = |bytes: u64| bytes as f64 / report.total_bytes.max(1) as f64 * 100.0;
+    let mut out = format!(
+        "{} blobs, {} bytes\n{:<8} {:>8} {:>12} {:>8}\n",
+        report.blobs, report.total_bytes, "chunking", "chunks", "bytes", "stored"
+    );
+    out.push_str(&format!(
+        "{:<8} {:>8} {:>12} {:>7.1}%\n",
+        "fixed",
+        report.fixed_chunks,
+        report.fixed_bytes,
+        ratio(report.fixed_bytes)
+    ));
+    out.push_str(&format!(
+        "{:<8} {:>8} {:>12} {:>7.1}%\n",
+        "cdc",
+        report.cdc_chunks,
+        report.cdc_bytes,
+        ratio(report.cdc_bytes)
+    ));
+    out
+}
@@ -0,0 +1,22 @@
+//! Text rendering of the differences between two versions of a value.
+
+use polyepoxide_core::json::raw_to_json;
+use polyepoxide_core::{Change, DiffEntry};
+
+/// Renders one line per entry: `+` added, `-` removed, `~` changed.
+pub fn render(entries: &[DiffEntry]) -> String {
+    let mut out = String::new();
+    for entry in entries {
+        let path = if entry.path.is_empty() { "/" } else { &entry.path };
+        let line = match &entry.change {
+            Change::Added(value) => format!("+ {} {}", path, raw_to_json(value)),
+            Change::Removed(value) => format!("- {} {}", path, raw_to_json(value)),
+            Change::Changed { old, new } => {
+                format!("~ {} {} -> {}", path, raw_to_json(old), raw_to_json(new))
+            }
+        };
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
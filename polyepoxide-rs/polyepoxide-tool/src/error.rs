@@ -0,0 +1,120 @@
+//! Errors reported by the command line, with hints on how to fix them.
+
+use std::path::PathBuf;
+
+use cid::Cid;
+use miette::{Diagnostic, GraphicalReportHandler};
+use polyepoxide_any::AnyStoreError;
+use thiserror::Error;
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum PxError {
+    #[error("invalid CID for {arg}: {input:?}")]
+    #[diagnostic(
+        code(px::invalid_cid),
+        help("CIDs are multibase strings such as `bafyrei...`; `px schemas` lists the schema CIDs in a store")
+    )]
+    InvalidCid {
+        arg: &'static str,
+        input: String,
+        #[source]
+        source: cid::Error,
+    },
+
+    #[error("unknown store type: {0}")]
+    #[diagnostic(
+        code(px::unknown_store_type),
+        help("use `--store fjall` or `--store rocks`")
+    )]
+    UnknownStoreType(String),
+
+    #[error("failed to open {store} store at {}", .path.display())]
+    #[diagnostic(
+        code(px::open_store),
+        help("check that --path points to an existing store of the type given by --store, and that no other process holds it open")
+    )]
+    OpenStore {
+        store: String,
+        path: PathBuf,
+        #[source]
+        source: AnyStoreError,
+    },
+
+    #[error(transparent)]
+    #[diagnostic(code(px::store))]
+    Store(#[from] AnyStoreError),
+
+    #[error("schema {cid} not found in {}", .path.display())]
+    #[diagnostic(
+        code(px::schema_not_found),
+        help("run `px schemas --path {}` to list the schemas in this store", .path.display())
+    )]
+    SchemaNotFound { cid: Cid, path: PathBuf },
+
+    #[error("ref {name:?} not found in {}", .path.display())]
+    #[diagnostic(
+        code(px::ref_not_found),
+        help("run `px refs list --path {}` to list the refs in this store", .path.display())
+    )]
+    RefNotFound { name: String, path: PathBuf },
+
+    #[error("ref {name:?} in {} was moved by another writer", .path.display())]
+    #[diagnostic(
+        code(px::ref_moved),
+        help("run the command again to record on top of the new head")
+    )]
+    RefMoved { name: String, path: PathBuf },
+
+    #[error("block {cid} is not a valid schema")]
+    #[diagnostic(
+        code(px::invalid_schema),
+        help("--schema must name the schema of the value, not the value itself")
+    )]
+    InvalidSchema {
+        cid: Cid,
+        #[source]
+        source: serde_ipld_dagcbor::DecodeError<std::convert::Infallible>,
+    },
+
+    #[error("unknown export format: {0}")]
+    #[diagnostic(
+        code(px::unknown_format),
+        help("use `--format json`, `yaml`, `dot` or `mermaid`")
+    )]
+    UnknownFormat(String),
+
+    #[error("failed to read {}", .path.display())]
+    #[diagnostic(code(px::read))]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("failed to write {}", .path.display())]
+    #[diagnostic(code(px::write))]
+    Write {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("found {problems} problems in the store at {}", .path.display())]
+    #[diagnostic(
+        code(px::corrupt),
+        help("values that don't match their CID or are missing need to be copied again from another replica or a backup")
+    )]
+    Corrupt { problems: usize, path: PathBuf },
+
+    #[error(transparent)]
+    Other(#[from] Box<dyn std::error::Error>),
+}
+
+/// Prints an error with its causes and help text to stderr.
+pub fn report(error: &PxError) {
+    let mut out = String::new();
+    match GraphicalReportHandler::new().render_report(&mut out, error) {
+        Ok(()) => eprint!("{}", out),
+        Err(_) => eprintln!("Error: {}", error),
+    }
+}
@@ -0,0 +1,202 @@
+//! DOT and Mermaid rendering of the bonds between values.
+
+use std::collections::{HashMap, VecDeque};
+
+use cid::Cid;
+use ipld_core::ipld::Ipld;
+use polyepoxide_core::traverse::{parse_to_ipld, resolve_schema};
+use polyepoxide_core::{Bond, Solvent, Store, Structure};
+
+use crate::tree::{short_cid, type_hint};
+use crate::PxStore;
+
+/// Values reachable from a root, with an edge for each bond between them.
+pub struct Graph {
+    /// CID and type hint of each value, the root first.
+    nodes: Vec<(Cid, String)>,
+    /// Source and target node and the path of the bond in the source.
+    edges: Vec<(usize, usize, String)>,
+}
+
+/// A bond found in a value.
+pub struct Link {
+    pub path: String,
+    pub cid: Cid,
+    pub schema: Option<Bond<Structure>>,
+    /// Records and unions enclosing the bond, which its target's `SelfRef`s
+    /// may refer to.
+    pub frames: Vec<Bond<Structure>>,
+}
+
+impl Graph {
+    /// Collects the values bonded from `root`, following bonds up to `depth`
+    /// levels deep (0 shows only the root's direct bonds). Its schema and
+    /// all nested schemas must be in `schemas`.
+    pub fn collect(
+        store: &PxStore,
+        schemas: &Solvent,
+        root: Cid,
+        schema: Cid,
+        depth: usize,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let schema = schemas.get::<Structure>(&schema).map(Bond::from_cell);
+        let mut graph = Graph {
+            nodes: vec![(root, hint(schema.as_ref()))],
+            edges: Vec::new(),
+        };
+        let mut index = HashMap::from([(root, 0)]);
+        let mut queue = VecDeque::from([(0, schema, Vec::new(), 0)]);
+
+        while let Some((from, schema, mut frames, level)) = queue.pop_front() {
+            let Some(bytes) = store.get(&graph.nodes[from].0)? else {
+                continue;
+            };
+            let mut links = Vec::new();
+            find_links(
+                &parse_to_ipld(&bytes)?,
+                schema.as_ref(),
+                &mut frames,
+                "",
+                &mut links,
+            );
+            for link in links {
+                let to = match index.get(&link.cid) {
+                    Some(&to) => to,
+                    None => {
+                        let to = graph.nodes.len();
+                        graph.nodes.push((link.cid, hint(link.schema.as_ref())));
+                        index.insert(link.cid, to);
+                        if level < depth {
+                            queue.push_back((to, link.schema, link.frames, level + 1));
+                        }
+                        to
+                    }
+                };
+                graph.edges.push((from, to, link.path));
+            }
+        }
+        Ok(graph)
+    }
+
+    pub fn to_dot(&self) -> String {
+        let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+        let mut out = String::from("digraph {\n    node [shape=box];\n");
+        for (cid, hint) in &self.nodes {
+            out.push_str(&format!(
+                "    \"{}\" [label=\"{}\\n{}\"];\n",
+                cid,
+                escape(hint),
+                short_cid(cid)
+            ));
+        }
+        for (from, to, path) in &self.edges {
+            out.push_str(&format!(
+                "    \"{}\" -> \"{}\" [label=\"{}\"];\n",
+                self.nodes[*from].0,
+                self.nodes[*to].0,
+                escape(path)
+            ));
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    pub fn to_mermaid(&self) -> String {
+        // Labels are HTML, so type hints like `Seq<String>` need entities
+        let escape = |s: &str| {
+            s.replace('"', "#quot;")
+                .replace('<', "#lt;")
+                .replace('>', "#gt;")
+        };
+        let mut out = String::from("flowchart TD\n");
+        for (i, (cid, hint)) in self.nodes.iter().enumerate() {
+            out.push_str(&format!(
+                "    n{}[\"{}<br/>{}\"]\n",
+                i,
+                escape(hint),
+                short_cid(cid)
+            ));
+        }
+        for (from, to, path) in &self.edges {
+            out.push_str(&format!(
+                "    n{} -->|\"{}\"| n{}\n",
+                from,
+                escape(path),
+                to
+            ));
+        }
+        out
+    }
+}
+
+fn hint(schema: Option<&Bond<Structure>>) -> String {
+    schema
+        .and_then(|schema| schema.value())
+        .map_or_else(|| "?".to_string(), type_hint)
+}
+
+/// Collects the links in `ipld` with their path and, where the schema gives
+/// it, their target's schema. Links are bonds, as values link nothing else.
+pub fn find_links(
+    ipld: &Ipld,
+    schema: Option<&Bond<Structure>>,
+    frames: &mut Vec<Bond<Structure>>,
+    path: &str,
+    links: &mut Vec<Link>,
+) {
+    let schema = schema.and_then(|schema| resolve_schema(schema, frames));
+    let structure = schema.as_ref().and_then(|schema| schema.value());
+    let framed = match &schema {
+        Some(schema) if matches!(structure, Some(Structure::Record(_) | Structure::Tagged(_))) => {
+            frames.push(schema.clone());
+            true
+        }
+        _ => false,
+    };
+
+    let child = |key: &str| match path {
+        "" => key.to_string(),
+        _ => format!("{}/{}", path, key),
+    };
+    match (ipld, structure) {
+        (Ipld::Link(cid), Some(Structure::Bond(target))) => links.push(Link {
+            path: path.to_string(),
+            cid: *cid,
+            schema: resolve_schema(target, frames),
+            frames: frames.clone(),
+        }),
+        (Ipld::Link(cid), _) => links.push(Link {
+            path: path.to_string(),
+            cid: *cid,
+            schema: None,
+            frames: frames.clone(),
+        }),
+        (Ipld::Map(map), _) => {
+            for (key, value) in map {
+                let schema = match structure {
+                    Some(Structure::Record(fields) | Structure::Tagged(fields)) => fields.get(key),
+                    Some(Structure::Map { value, .. } | Structure::OrderedMap { value, .. }) => {
+                        Some(value)
+                    }
+                    _ => None,
+                };
+                find_links(value, schema, frames, &child(key), links);
+            }
+        }
+        (Ipld::List(items), _) => {
+            for (i, item) in items.iter().enumerate() {
+                let schema = match structure {
+                    Some(Structure::Sequence(inner)) => Some(inner),
+                    Some(Structure::Tuple(elems)) => elems.get(i),
+                    _ => None,
+                };
+                find_links(item, schema, frames, &child(&i.to_string()), links);
+            }
+        }
+        _ => {}
+    }
+
+    if framed {
+        frames.pop();
+    }
+}
@@ -0,0 +1,70 @@
+//! `px snapshot` and `px log`: versions of a root recorded as commits.
+//!
+//! The tool doesn't know the type of the root, but a commit's encoding doesn't
+//! depend on it, so commits are handled as `Commit<()>`. Only the commit
+//! blocks are written: their schema names the root's type, which only the
+//! application knows.
+
+use std::path::Path;
+
+use cid::Cid;
+use polyepoxide_core::{Bond, Oxide, RefStore, Solvent, Store};
+use polyepoxide_history::Commit;
+
+use crate::error::PxError;
+use crate::PxStore;
+
+/// Records `root` as a commit on top of the one `ref_name` points to, if
+/// any, and moves the ref to it. Returns the commit's CID, or an error if
+/// another writer moved the ref in the meantime.
+pub fn snapshot(
+    store: &PxStore,
+    path: &Path,
+    ref_name: &str,
+    root: Cid,
+    author: &str,
+    message: &str,
+) -> Result<Cid, PxError> {
+    let head = store.get_ref(ref_name)?;
+    let parents = head.map(Bond::from_cid).into_iter().collect();
+    let mut solvent = Solvent::new();
+    let commit: Bond<Commit<()>> =
+        polyepoxide_history::commit(&mut solvent, parents, Bond::from_cid(root), author, message);
+    let cid = commit.cid();
+    let value = commit.value().expect("commits are added resolved");
+    store.put(&cid, &value.to_bytes())?;
+    if !store.compare_and_set_ref(ref_name, head.as_ref(), &cid)? {
+        return Err(PxError::RefMoved {
+            name: ref_name.to_string(),
+            path: path.to_path_buf(),
+        });
+    }
+    Ok(cid)
+}
+
+/// Lists the commits reachable from `ref_name`, newest first, one per line:
+/// commit CID, root CID, timestamp in milliseconds, author and message.
+pub fn log(store: &PxStore, path: &Path, ref_name: &str) -> Result<String, PxError> {
+    let head = store
+        .get_ref(ref_name)?
+        .ok_or_else(|| PxError::RefNotFound {
+            name: ref_name.to_string(),
+            path: path.to_path_buf(),
+        })?;
+    let commits = polyepoxide_history::log::<(), _>(&mut Solvent::new(), store, &head)
+        .map_err(|e| PxError::Other(Box::new(e)))?;
+
+    let mut out = String::new();
+    for commit in commits {
+        let value = commit.value();
+        out.push_str(&format!(
+            "{}\t{}\t{}\t{}\t{}\n",
+            commit.cid(),
+            value.root.cid(),
+            value.timestamp_ms,
+            value.author,
+            value.message
+        ));
+    }
+    Ok(out)
+}
@@ -0,0 +1,301 @@
+//! Raw DAG-CBOR of a node: hex dump, diagnostic notation and CID details.
+
+use cid::Cid;
+use polyepoxide_core::compute_cid;
+
+/// Describes `bytes` line by line. `cid` is the key the block is stored
+/// under, or `None` for a value encoded on its own rather than read from a
+/// block.
+pub fn inspect(cid: Option<Cid>, bytes: &[u8]) -> Vec<String> {
+    let computed = compute_cid(bytes);
+    let mut lines = Vec::new();
+    match cid {
+        Some(cid) if cid == computed => lines.push(format!("CID: {}", cid)),
+        Some(cid) => {
+            lines.push(format!("CID: {}", cid));
+            lines.push(format!("Computed CID: {} (MISMATCH)", computed));
+        }
+        None => {
+            lines.push("Inline value, encoded on its own".to_string());
+            lines.push(format!("Computed CID: {}", computed));
+        }
+    }
+    let cid = cid.unwrap_or(computed);
+    let hash = cid.hash();
+    lines.push(format!("Size: {} bytes", bytes.len()));
+    lines.push(format!(
+        "CID v{}, codec {} (0x{:x})",
+        u64::from(cid.version()),
+        codec_name(cid.codec()),
+        cid.codec()
+    ));
+    lines.push(format!(
+        "Multihash {} (0x{:x}), {}-byte digest",
+        hash_name(hash.code()),
+        hash.code(),
+        hash.size()
+    ));
+    lines.push(format!("Digest: {}", hex(hash.digest())));
+
+    lines.push(String::new());
+    lines.push("Hex:".to_string());
+    lines.extend(hex_dump(bytes));
+
+    lines.push(String::new());
+    lines.push("Diagnostic:".to_string());
+    lines.push(diagnostic(bytes).unwrap_or_else(|e| format!("invalid CBOR: {}", e)));
+    lines
+}
+
+fn codec_name(code: u64) -> &'static str {
+    match code {
+        0x55 => "raw",
+        0x70 => "dag-pb",
+        0x71 => "dag-cbor",
+        0x0129 => "dag-json",
+        _ => "unknown",
+    }
+}
+
+fn hash_name(code: u64) -> &'static str {
+    match code {
+        0x00 => "identity",
+        0x12 => "sha2-256",
+        0x13 => "sha2-512",
+        0x1e => "blake3",
+        0xb220 => "blake2b-256",
+        _ => "unknown",
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Eight bytes per line, narrow enough for a side pane.
+fn hex_dump(bytes: &[u8]) -> Vec<String> {
+    bytes
+        .chunks(8)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let hex: Vec<_> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+            let ascii: String = chunk
+                .iter()
+                .map(|&b| match b {
+                    b' '..=b'~' => b as char,
+                    _ => '.',
+                })
+                .collect();
+            format!("{:06x}  {:<23}  {}", i * 8, hex.join(" "), ascii)
+        })
+        .collect()
+}
+
+/// Renders CBOR in diagnostic notation (RFC 8949, section 8).
+///
+/// Arguments not encoded in the fewest bytes get an encoding indicator, such
+/// as `1_0` for 1 in a one-byte argument, and floats always do, as these are
+/// what makes two encodings of a value differ.
+fn diagnostic(bytes: &[u8]) -> Result<String, String> {
+    let mut reader = Reader { bytes, pos: 0 };
+    let mut out = String::new();
+    reader.item(&mut out)?;
+    if reader.pos != bytes.len() {
+        return Err(format!("trailing bytes at offset {}", reader.pos));
+    }
+    Ok(out)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let end = self.pos.saturating_add(n);
+        let taken = self
+            .bytes
+            .get(self.pos..end)
+            .ok_or_else(|| format!("unexpected end at offset {}", self.pos))?;
+        self.pos = end;
+        Ok(taken)
+    }
+
+    fn at_break(&self) -> bool {
+        self.bytes.get(self.pos) == Some(&0xff)
+    }
+
+    /// Reads an item's initial byte and argument. Returns the major type,
+    /// the argument (`None` for indefinite lengths) and its encoding
+    /// indicator.
+    fn head(&mut self) -> Result<(u8, Option<u64>, String), String> {
+        let initial = self.take(1)?[0];
+        let width = match initial & 0x1f {
+            info @ 0..=23 => return Ok((initial >> 5, Some(info.into()), String::new())),
+            24 => 1,
+            25 => 2,
+            26 => 4,
+            27 => 8,
+            31 => return Ok((initial >> 5, None, "_".to_string())),
+            info => return Err(format!("reserved additional info {}", info)),
+        };
+        let arg = self
+            .take(width)?
+            .iter()
+            .fold(0u64, |arg, &b| (arg << 8) | u64::from(b));
+        let shortest = match arg {
+            0..=23 => 0,
+            24..=0xff => 1,
+            0x100..=0xffff => 2,
+            0x1_0000..=0xffff_ffff => 4,
+            _ => 8,
+        };
+        let indicator = match width {
+            _ if width == shortest => String::new(),
+            1 => "_0".to_string(),
+            2 => "_1".to_string(),
+            4 => "_2".to_string(),
+            _ => "_3".to_string(),
+        };
+        Ok((initial >> 5, Some(arg), indicator))
+    }
+
+    fn item(&mut self, out: &mut String) -> Result<(), String> {
+        // Floats and simple values are told apart by the additional info
+        if let Some(&initial) = self.bytes.get(self.pos) {
+            if initial >> 5 == 7 {
+                return self.simple(out);
+            }
+        }
+        let (major, arg, indicator) = self.head()?;
+        match (major, arg) {
+            (0, Some(n)) => out.push_str(&format!("{}{}", n, indicator)),
+            (1, Some(n)) => out.push_str(&format!("{}{}", -1 - i128::from(n), indicator)),
+            (2 | 3, Some(len)) => {
+                let len = usize::try_from(len).map_err(|_| "length too large".to_string())?;
+                let content = self.take(len)?;
+                if major == 2 {
+                    out.push_str(&format!("h'{}'{}", hex(content), indicator));
+                } else {
+                    let text = std::str::from_utf8(content)
+                        .map_err(|_| format!("invalid UTF-8 before offset {}", self.pos))?;
+                    out.push_str(&format!("{:?}{}", text, indicator));
+                }
+            }
+            (2 | 3, None) => {
+                out.push_str("(_ ");
+                self.items(out, |reader, out| reader.item(out))?;
+                out.push(')');
+            }
+            (4, len) => {
+                out.push('[');
+                out.push_str(&indicator);
+                if !indicator.is_empty() {
+                    out.push(' ');
+                }
+                self.sequence(out, len, |reader, out| reader.item(out))?;
+                out.push(']');
+            }
+            (5, len) => {
+                out.push('{');
+                out.push_str(&indicator);
+                if !indicator.is_empty() {
+                    out.push(' ');
+                }
+                self.sequence(out, len, |reader, out| {
+                    reader.item(out)?;
+                    out.push_str(": ");
+                    reader.item(out)
+                })?;
+                out.push('}');
+            }
+            (6, Some(tag)) => {
+                out.push_str(&format!("{}{}(", tag, indicator));
+                self.item(out)?;
+                out.push(')');
+            }
+            _ => {
+                return Err(format!(
+                    "invalid indefinite length for major type {}",
+                    major
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads `len` entries, or entries up to a break if `len` is `None`.
+    fn sequence(
+        &mut self,
+        out: &mut String,
+        len: Option<u64>,
+        entry: impl Fn(&mut Self, &mut String) -> Result<(), String>,
+    ) -> Result<(), String> {
+        match len {
+            Some(len) => {
+                for i in 0..len {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    entry(self, out)?;
+                }
+                Ok(())
+            }
+            None => self.items(out, entry),
+        }
+    }
+
+    /// Reads entries up to and including a break.
+    fn items(
+        &mut self,
+        out: &mut String,
+        entry: impl Fn(&mut Self, &mut String) -> Result<(), String>,
+    ) -> Result<(), String> {
+        let mut first = true;
+        while !self.at_break() {
+            if !first {
+                out.push_str(", ");
+            }
+            first = false;
+            entry(self, out)?;
+        }
+        self.take(1)?;
+        Ok(())
+    }
+
+    fn simple(&mut self, out: &mut String) -> Result<(), String> {
+        let info = self.take(1)?[0] & 0x1f;
+        let text = match info {
+            20 => "false".to_string(),
+            21 => "true".to_string(),
+            22 => "null".to_string(),
+            23 => "undefined".to_string(),
+            0..=19 => format!("simple({})", info),
+            24 => format!("simple({})", self.take(1)?[0]),
+            25 => format!("{:?}_1", half(u16::from_be_bytes(self.array()?))),
+            26 => format!("{:?}_2", f32::from_be_bytes(self.array()?)),
+            27 => format!("{:?}_3", f64::from_be_bytes(self.array()?)),
+            _ => return Err(format!("unexpected break or reserved value {}", info)),
+        };
+        out.push_str(&text);
+        Ok(())
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        Ok(self.take(N)?.try_into().expect("took N bytes"))
+    }
+}
+
+/// Decodes an IEEE 754 half-precision float.
+fn half(bits: u16) -> f64 {
+    let sign = if bits >> 15 == 1 { -1.0 } else { 1.0 };
+    let exponent = i32::from((bits >> 10) & 0x1f);
+    let mantissa = f64::from(bits & 0x3ff);
+    sign * match exponent {
+        0 => mantissa * 2f64.powi(-24),
+        31 if mantissa == 0.0 => f64::INFINITY,
+        31 => f64::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2f64.powi(exponent - 15),
+    }
+}
@@ -1,20 +1,35 @@
 //! Polyepoxide TUI explorer tool.
 
 mod app;
+mod compact;
+mod dedup;
+mod diff;
+mod error;
 mod export;
-mod store;
+mod graph;
+mod history;
+mod inspect;
+mod publish;
+mod refs;
+mod slowlog;
+mod stat;
 mod tree;
 mod ui;
+mod verify;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 use std::str::FromStr;
+use std::time::Duration;
 
 use cid::Cid;
 use clap::{Parser, Subcommand};
+use polyepoxide_any::AnyStore;
+use polyepoxide_core::SlowLogStore;
 
 use app::App;
+use error::PxError;
 use export::{export, ExportFormat, ExportOptions};
-use store::AnyStore;
 
 #[derive(Parser)]
 #[command(name = "polyepoxide-tool")]
@@ -22,8 +37,27 @@ use store::AnyStore;
 struct Cli {
     #[command(subcommand)]
     command: Command,
+
+    #[command(flatten)]
+    slow_log: SlowLogArgs,
+}
+
+/// Logging of slow store calls, for every command that opens a store.
+#[derive(clap::Args)]
+struct SlowLogArgs {
+    /// Append store calls slower than the threshold to this file
+    #[arg(long, global = true)]
+    slow_log: Option<PathBuf>,
+
+    /// Latency in milliseconds above which a store call is logged
+    #[arg(long, global = true, default_value = "100")]
+    slow_threshold_ms: u64,
 }
 
+/// The store every command works on. Calls are only timed against a real
+/// threshold when `--slow-log` is given.
+type PxStore = SlowLogStore<AnyStore>;
+
 #[derive(Subcommand)]
 enum Command {
     /// Explore a graph in the TUI
@@ -45,7 +79,7 @@ enum Command {
         path: PathBuf,
     },
 
-    /// Export a value to JSON or YAML
+    /// Export a value to JSON or YAML, or the graph of its bonds to DOT or Mermaid
     Export {
         /// CID of the root value
         #[arg(long)]
@@ -63,7 +97,7 @@ enum Command {
         #[arg(long)]
         path: PathBuf,
 
-        /// Output format: json or yaml
+        /// Output format: json, yaml, dot or mermaid
         #[arg(long, default_value = "json")]
         format: String,
 
@@ -71,15 +105,241 @@ enum Command {
         #[arg(long, default_value = "2")]
         depth: usize,
 
+        /// Export only the parts a path selects, e.g. `items[*].name`
+        #[arg(long)]
+        select: Option<String>,
+
         /// Output file (default: stdout)
         #[arg(short, long)]
         output: Option<PathBuf>,
     },
+
+    /// Write the content of a blob to stdout or a file, one chunk at a time
+    Cat {
+        /// CID of the blob
+        #[arg(long)]
+        cid: String,
+
+        /// Store type: fjall or rocks
+        #[arg(long, default_value = "fjall")]
+        store: String,
+
+        /// Path to the store
+        #[arg(long)]
+        path: PathBuf,
+
+        /// Output file (default: stdout)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+
+    /// Store a JSON document as a value of the given schema and print its CID
+    Import {
+        /// CID of the document's schema
+        #[arg(long)]
+        schema: String,
+
+        /// Store type: fjall or rocks
+        #[arg(long, default_value = "fjall")]
+        store: String,
+
+        /// Path to the store
+        #[arg(long)]
+        path: PathBuf,
+
+        /// JSON file to import (default: stdin)
+        #[arg(short, long)]
+        input: Option<PathBuf>,
+    },
+
+    /// List the differences between two versions of a value
+    Diff {
+        /// CID of the old version
+        #[arg(long)]
+        old: String,
+
+        /// CID of the new version
+        #[arg(long)]
+        new: String,
+
+        /// CID of the schema of both versions
+        #[arg(long)]
+        schema: String,
+
+        /// Store type: fjall or rocks
+        #[arg(long, default_value = "fjall")]
+        store: String,
+
+        /// Path to the store
+        #[arg(long)]
+        path: PathBuf,
+    },
+
+    /// Publish a value and its dependencies to an IPFS node using SHA2-256 CIDs
+    PublishIpfs {
+        /// CID of the root value
+        #[arg(long)]
+        cid: String,
+
+        /// Store type: fjall or rocks
+        #[arg(long, default_value = "fjall")]
+        store: String,
+
+        /// Path to the store
+        #[arg(long)]
+        path: PathBuf,
+
+        /// IPFS HTTP RPC API address
+        #[arg(long, default_value = "http://127.0.0.1:5001")]
+        api: String,
+
+        /// Translation table file (default: ipfs-translation.json in the store directory)
+        #[arg(long)]
+        table: Option<PathBuf>,
+    },
+
+    /// List the CIDs of the schemas in a store
+    Schemas {
+        /// Store type: fjall or rocks
+        #[arg(long, default_value = "fjall")]
+        store: String,
+
+        /// Path to the store
+        #[arg(long)]
+        path: PathBuf,
+    },
+
+    /// Compact a store and report its on-disk size before and after
+    Compact {
+        /// Store type: fjall or rocks
+        #[arg(long, default_value = "fjall")]
+        store: String,
+
+        /// Path to the store
+        #[arg(long)]
+        path: PathBuf,
+    },
+
+    /// Compare fixed-size and content-defined chunking of the binary content in a store
+    Dedup {
+        /// Store type: fjall or rocks
+        #[arg(long, default_value = "fjall")]
+        store: String,
+
+        /// Path to the store
+        #[arg(long)]
+        path: PathBuf,
+    },
+
+    /// Manage named references to CIDs, such as the head of a collection
+    Refs {
+        #[command(subcommand)]
+        command: refs::RefsCommand,
+    },
+
+    /// Record a version of a root as a commit and point a ref at it
+    Snapshot {
+        /// CID of the root value
+        #[arg(long)]
+        root: String,
+
+        /// Description of the version
+        #[arg(long)]
+        message: String,
+
+        /// Ref holding the latest commit; its commit becomes the parent
+        #[arg(long = "ref", default_value = "head")]
+        ref_name: String,
+
+        /// Author recorded in the commit
+        #[arg(long, default_value = "")]
+        author: String,
+
+        /// Store type: fjall or rocks
+        #[arg(long, default_value = "fjall")]
+        store: String,
+
+        /// Path to the store
+        #[arg(long)]
+        path: PathBuf,
+    },
+
+    /// List the commits reachable from a ref, newest first
+    Log {
+        /// Ref holding the latest commit
+        #[arg(long = "ref", default_value = "head")]
+        ref_name: String,
+
+        /// Store type: fjall or rocks
+        #[arg(long, default_value = "fjall")]
+        store: String,
+
+        /// Path to the store
+        #[arg(long)]
+        path: PathBuf,
+    },
+
+    /// Report the number and size of the blocks reachable from a value, per type
+    Stat {
+        /// CID of the root value
+        #[arg(long)]
+        cid: String,
+
+        /// CID of the root value's schema
+        #[arg(long)]
+        schema: String,
+
+        /// Store type: fjall or rocks
+        #[arg(long, default_value = "fjall")]
+        store: String,
+
+        /// Path to the store
+        #[arg(long)]
+        path: PathBuf,
+
+        /// Number of largest blocks to list
+        #[arg(long, default_value = "10")]
+        top: usize,
+    },
+
+    /// Check that every value matches its CID and that no bond or ref points to a missing value
+    Verify {
+        /// Store type: fjall or rocks
+        #[arg(long, default_value = "fjall")]
+        store: String,
+
+        /// Path to the store
+        #[arg(long)]
+        path: PathBuf,
+    },
+
+    /// Summarize a slow-operation log written with --slow-log
+    Slowlog {
+        /// Path to the log file
+        file: PathBuf,
+
+        /// Only include one operation: get, put, or has
+        #[arg(long)]
+        op: Option<String>,
+
+        /// Number of slowest calls to list
+        #[arg(long, default_value = "20")]
+        top: usize,
+    },
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let cli = Cli::parse();
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            error::report(&err);
+            ExitCode::FAILURE
+        }
+    }
+}
 
+fn run(cli: Cli) -> Result<(), PxError> {
+    let slow_log = cli.slow_log;
     match cli.command {
         Command::Explore {
             cid,
@@ -87,9 +347,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             store,
             path,
         } => {
-            let root_cid = Cid::from_str(&cid)?;
-            let schema_cid = Cid::from_str(&schema)?;
-            let store = open_store(&store, &path)?;
+            let root_cid = parse_cid("--cid", &cid)?;
+            let schema_cid = parse_cid("--schema", &schema)?;
+            let store = open_store(&store, &path, &slow_log)?;
 
             let mut app = App::new(store, root_cid, schema_cid)?;
             app.run()?;
@@ -101,82 +361,276 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             path,
             format,
             depth,
+            select,
             output,
         } => {
-            let root_cid = Cid::from_str(&cid)?;
-            let schema_cid = Cid::from_str(&schema)?;
-            let store = open_store(&store, &path)?;
+            let root_cid = parse_cid("--cid", &cid)?;
+            let schema_cid = parse_cid("--schema", &schema)?;
+            let store = open_store(&store, &path, &slow_log)?;
 
             let format = match format.to_lowercase().as_str() {
                 "json" => ExportFormat::Json,
                 "yaml" | "yml" => ExportFormat::Yaml,
-                _ => return Err(format!("unknown format: {}", format).into()),
+                "dot" | "graphviz" => ExportFormat::Dot,
+                "mermaid" => ExportFormat::Mermaid,
+                _ => return Err(PxError::UnknownFormat(format)),
             };
 
             let options = ExportOptions {
                 depth,
                 pretty: true,
+                select,
             };
 
             // Build a solvent with the schema
             let mut schemas = polyepoxide_core::Solvent::new();
-            load_schema_recursive(&store, &mut schemas, schema_cid)?;
+            load_schema_recursive(&store, &path, &mut schemas, schema_cid)?;
 
             let content = export(&store, &schemas, root_cid, schema_cid, format, &options)?;
 
             match output {
-                Some(path) => std::fs::write(path, content)?,
+                Some(path) => std::fs::write(&path, content)
+                    .map_err(|source| PxError::Write { path, source })?,
                 None => print!("{}", content),
             }
         }
+        Command::Cat {
+            cid,
+            store,
+            path,
+            output,
+        } => {
+            use polyepoxide_core::{Blob, Oxide, Store};
+
+            let cid = parse_cid("--cid", &cid)?;
+            let store = open_store(&store, &path, &slow_log)?;
+            let bytes = store
+                .get(&cid)?
+                .ok_or_else(|| PxError::Other(format!("value not found: {}", cid).into()))?;
+            let blob = Blob::from_bytes(&bytes).map_err(|e| PxError::Other(Box::new(e)))?;
+
+            // Chunks are decoded straight from the store's buffers
+            let mut reader = blob.reader(&store);
+            match output {
+                Some(path) => std::fs::File::create(&path)
+                    .and_then(|mut file| std::io::copy(&mut reader, &mut file))
+                    .map_err(|source| PxError::Write { path, source })?,
+                None => std::io::copy(&mut reader, &mut std::io::stdout().lock())
+                    .map_err(|e| PxError::Other(Box::new(e)))?,
+            };
+        }
+        Command::Import {
+            schema,
+            store,
+            path,
+            input,
+        } => {
+            use polyepoxide_core::json::{import_json, ImportError};
+
+            let schema_cid = parse_cid("--schema", &schema)?;
+            let store = open_store(&store, &path, &slow_log)?;
+            let json: serde_json::Value = match input {
+                Some(input) => {
+                    let content = std::fs::read_to_string(&input)
+                        .map_err(|source| PxError::Read { path: input, source })?;
+                    serde_json::from_str(&content).map_err(|e| PxError::Other(Box::new(e)))?
+                }
+                None => serde_json::from_reader(std::io::stdin().lock())
+                    .map_err(|e| PxError::Other(Box::new(e)))?,
+            };
+
+            let mut schemas = polyepoxide_core::Solvent::new();
+            load_schema_recursive(&store, &path, &mut schemas, schema_cid)?;
+
+            let cid = import_json(&store, &schemas, json, schema_cid).map_err(|e| match e {
+                ImportError::SchemaNotFound(cid) => PxError::SchemaNotFound { cid, path },
+                ImportError::Store(e) => PxError::Store(e),
+                e => PxError::Other(Box::new(e)),
+            })?;
+            println!("{}", cid);
+        }
+        Command::Diff {
+            old,
+            new,
+            schema,
+            store,
+            path,
+        } => {
+            use polyepoxide_core::DiffError;
+
+            let old = parse_cid("--old", &old)?;
+            let new = parse_cid("--new", &new)?;
+            let schema_cid = parse_cid("--schema", &schema)?;
+            let store = open_store(&store, &path, &slow_log)?;
+
+            let entries = polyepoxide_core::diff(&store, old, new, schema_cid).map_err(|e| match e {
+                DiffError::SchemaNotFound(cid) => PxError::SchemaNotFound { cid, path },
+                e => PxError::Other(Box::new(e)),
+            })?;
+            print!("{}", diff::render(&entries));
+        }
+        Command::PublishIpfs {
+            cid,
+            store,
+            path,
+            api,
+            table,
+        } => {
+            let root_cid = parse_cid("--cid", &cid)?;
+            let table_path = table.unwrap_or_else(|| path.join("ipfs-translation.json"));
+            let store = open_store(&store, &path, &slow_log)?;
+
+            let mut table = publish::TranslationTable::load(&table_path)?;
+            let (published, blocks) = publish::reencode_closure(&store, root_cid, &mut table)?;
+            let client = reqwest::blocking::Client::new();
+            for block in &blocks {
+                publish::put_block(&client, &api, block)?;
+            }
+            table.save(&table_path)?;
+
+            eprintln!("Published {} new blocks", blocks.len());
+            println!("{}", published);
+        }
+        Command::Schemas { store, path } => {
+            use polyepoxide_core::{Store, Structure};
+
+            let store = open_store(&store, &path, &slow_log)?;
+            for cid in store.list_cids()? {
+                let Some(bytes) = store.get(&cid)? else {
+                    continue;
+                };
+                if serde_ipld_dagcbor::from_slice::<Structure>(&bytes).is_ok() {
+                    println!("{}", cid);
+                }
+            }
+        }
+        Command::Compact { store, path } => {
+            print!("{}", compact::run(&store, &path, &slow_log)?);
+        }
+        Command::Dedup { store, path } => {
+            let store = open_store(&store, &path, &slow_log)?;
+            print!("{}", dedup::report(&store)?);
+        }
+        Command::Refs { command } => refs::run(command, &slow_log)?,
+        Command::Snapshot {
+            root,
+            message,
+            ref_name,
+            author,
+            store,
+            path,
+        } => {
+            let root = parse_cid("--root", &root)?;
+            let store = open_store(&store, &path, &slow_log)?;
+            let commit = history::snapshot(&store, &path, &ref_name, root, &author, &message)?;
+            println!("{}", commit);
+        }
+        Command::Log {
+            ref_name,
+            store,
+            path,
+        } => {
+            let store = open_store(&store, &path, &slow_log)?;
+            print!("{}", history::log(&store, &path, &ref_name)?);
+        }
+        Command::Stat {
+            cid,
+            schema,
+            store,
+            path,
+            top,
+        } => {
+            let root_cid = parse_cid("--cid", &cid)?;
+            let schema_cid = parse_cid("--schema", &schema)?;
+            let store = open_store(&store, &path, &slow_log)?;
+
+            let mut schemas = polyepoxide_core::Solvent::new();
+            load_schema_recursive(&store, &path, &mut schemas, schema_cid)?;
+            let report = stat::report(&store, &schemas, root_cid, schema_cid, top)?;
+            print!("{}", report);
+        }
+        Command::Verify { store, path } => {
+            let store = open_store(&store, &path, &slow_log)?;
+            print!("{}", verify::run(&store, &path)?);
+        }
+        Command::Slowlog { file, op, top } => {
+            print!("{}", slowlog::summarize(&file, op.as_deref(), top)?);
+        }
     }
 
     Ok(())
 }
 
-fn open_store(store_type: &str, path: &PathBuf) -> Result<AnyStore, Box<dyn std::error::Error>> {
-    match store_type.to_lowercase().as_str() {
-        "fjall" => Ok(AnyStore::open_fjall(path)?),
-        "rocks" | "rocksdb" => Ok(AnyStore::open_rocks(path)?),
-        _ => Err(format!("unknown store type: {}", store_type).into()),
-    }
+fn parse_cid(arg: &'static str, input: &str) -> Result<Cid, PxError> {
+    Cid::from_str(input).map_err(|source| PxError::InvalidCid {
+        arg,
+        input: input.to_string(),
+        source,
+    })
+}
+
+fn open_store(store_type: &str, path: &Path, slow_log: &SlowLogArgs) -> Result<PxStore, PxError> {
+    let store = match store_type.to_lowercase().as_str() {
+        "fjall" => AnyStore::open_fjall(path),
+        "rocks" | "rocksdb" => AnyStore::open_rocks(path),
+        _ => return Err(PxError::UnknownStoreType(store_type.to_string())),
+    };
+    let store = store.map_err(|source| PxError::OpenStore {
+        store: store_type.to_string(),
+        path: path.to_path_buf(),
+        source,
+    })?;
+
+    let Some(file) = &slow_log.slow_log else {
+        return Ok(SlowLogStore::new(store, Duration::MAX));
+    };
+    let threshold = Duration::from_millis(slow_log.slow_threshold_ms);
+    SlowLogStore::new(store, threshold)
+        .with_log_file(file)
+        .map_err(|source| PxError::Write {
+            path: file.clone(),
+            source,
+        })
 }
 
 fn load_schema_recursive(
-    store: &AnyStore,
+    store: &PxStore,
+    path: &Path,
     schemas: &mut polyepoxide_core::Solvent,
     cid: Cid,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<(), PxError> {
     use polyepoxide_core::{Store, Structure};
 
     if schemas.get::<Structure>(&cid).is_some() {
         return Ok(());
     }
 
-    let bytes = store
-        .get(&cid)?
-        .ok_or_else(|| format!("schema not found: {}", cid))?;
+    let bytes = store.get(&cid)?.ok_or_else(|| PxError::SchemaNotFound {
+        cid,
+        path: path.to_path_buf(),
+    })?;
 
-    let schema: Structure = serde_ipld_dagcbor::from_slice(&bytes)?;
+    let schema: Structure = serde_ipld_dagcbor::from_slice(&bytes)
+        .map_err(|source| PxError::InvalidSchema { cid, source })?;
 
     // Recursively load nested schemas
     match &schema {
-        Structure::Sequence(inner) | Structure::Bond(inner) => {
-            load_schema_recursive(store, schemas, inner.cid())?;
+        Structure::Sequence(inner) | Structure::Bond(inner) | Structure::Defaulted(inner) => {
+            load_schema_recursive(store, path, schemas, inner.cid())?;
         }
         Structure::Tuple(elems) => {
             for elem in elems {
-                load_schema_recursive(store, schemas, elem.cid())?;
+                load_schema_recursive(store, path, schemas, elem.cid())?;
             }
         }
         Structure::Record(fields) | Structure::Tagged(fields) => {
             for (_, field) in fields {
-                load_schema_recursive(store, schemas, field.cid())?;
+                load_schema_recursive(store, path, schemas, field.cid())?;
             }
         }
         Structure::Map { key: k, value: v } | Structure::OrderedMap { key: k, value: v } => {
-            load_schema_recursive(store, schemas, k.cid())?;
-            load_schema_recursive(store, schemas, v.cid())?;
+            load_schema_recursive(store, path, schemas, k.cid())?;
+            load_schema_recursive(store, path, schemas, v.cid())?;
         }
         _ => {}
     }
@@ -0,0 +1,258 @@
+//! Publishing to IPFS with SHA2-256 CIDs.
+//!
+//! Internal CIDs use Blake3, which standard IPFS nodes and gateways don't
+//! verify. Publishing re-encodes the closure of a root bottom-up: every node
+//! gets its links rewritten to the already-published CIDs of its children and
+//! is then hashed with SHA2-256. The internal → published mapping is kept in
+//! a JSON translation table so repeated publishes skip known nodes.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use cid::Cid;
+use ipld_core::ipld::Ipld;
+use multihash_codetable::{Code, MultihashDigest};
+use polyepoxide_core::traverse::parse_to_ipld;
+use polyepoxide_core::{DecodeLimits, Store};
+
+const DAG_CBOR_CODEC: u64 = 0x71;
+
+/// Mapping from internal (Blake3) CIDs to published (SHA2-256) CIDs.
+#[derive(Debug, Default)]
+pub struct TranslationTable {
+    entries: BTreeMap<Cid, Cid>,
+}
+
+impl TranslationTable {
+    /// Loads the table from a JSON file, or returns an empty table if missing.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let raw: BTreeMap<String, String> = serde_json::from_slice(&std::fs::read(path)?)?;
+        let entries = raw
+            .into_iter()
+            .map(|(k, v)| Ok((k.parse()?, v.parse()?)))
+            .collect::<Result<_, cid::Error>>()?;
+        Ok(Self { entries })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let raw: BTreeMap<String, String> = self
+            .entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        std::fs::write(path, serde_json::to_vec_pretty(&raw)?)?;
+        Ok(())
+    }
+
+    pub fn get(&self, internal: &Cid) -> Option<Cid> {
+        self.entries.get(internal).copied()
+    }
+
+    pub fn insert(&mut self, internal: Cid, published: Cid) {
+        self.entries.insert(internal, published);
+    }
+}
+
+/// A re-encoded block ready to be sent to IPFS.
+pub struct PublishedBlock {
+    pub internal: Cid,
+    pub published: Cid,
+    pub bytes: Vec<u8>,
+}
+
+/// Computes a CIDv1 for DAG-CBOR data using SHA2-256.
+pub fn compute_sha256_cid(data: &[u8]) -> Cid {
+    Cid::new_v1(DAG_CBOR_CODEC, Code::Sha2_256.digest(data))
+}
+
+/// Re-encodes the closure of `root` with SHA2-256 CIDs.
+///
+/// Nodes already present in `table` are not re-encoded. Returns the new blocks
+/// in dependency-first order, so they can be uploaded as they come.
+pub fn reencode_closure<S: Store>(
+    store: &S,
+    root: Cid,
+    table: &mut TranslationTable,
+) -> Result<(Cid, Vec<PublishedBlock>), Box<dyn std::error::Error>> {
+    let mut blocks = Vec::new();
+    // Nodes are parsed when first reached and re-encoded once every node
+    // they link to is in the table
+    let mut stack: Vec<(Cid, Option<Ipld>)> = vec![(root, None)];
+    while let Some((cid, ipld)) = stack.last_mut() {
+        let cid = *cid;
+        if table.get(&cid).is_some() {
+            stack.pop();
+            continue;
+        }
+        match ipld.take() {
+            None => {
+                let bytes = store
+                    .get(&cid)?
+                    .ok_or_else(|| format!("value not found: {}", cid))?;
+                // Stores hold blocks pulled from peers, which may be hostile
+                DecodeLimits::default().check(&bytes)?;
+                let parsed = parse_to_ipld(&bytes)?;
+                let children: Vec<Cid> = links(&parsed)
+                    .into_iter()
+                    .filter(|child| table.get(child).is_none())
+                    .collect();
+                *ipld = Some(parsed);
+                stack.extend(children.into_iter().rev().map(|child| (child, None)));
+            }
+            Some(mut ipld) => {
+                stack.pop();
+                rewrite_links(&mut ipld, table)?;
+                let bytes = serde_ipld_dagcbor::to_vec(&ipld)?;
+                let published = compute_sha256_cid(&bytes);
+                table.insert(cid, published);
+                blocks.push(PublishedBlock {
+                    internal: cid,
+                    published,
+                    bytes,
+                });
+            }
+        }
+    }
+    let published = table.get(&root).expect("root was re-encoded");
+    Ok((published, blocks))
+}
+
+/// Returns the links in `ipld`, in order.
+fn links(ipld: &Ipld) -> Vec<Cid> {
+    let mut links = Vec::new();
+    let mut stack = vec![ipld];
+    while let Some(ipld) = stack.pop() {
+        match ipld {
+            Ipld::Link(target) => links.push(*target),
+            Ipld::List(items) => stack.extend(items.iter().rev()),
+            Ipld::Map(map) => stack.extend(map.values().rev()),
+            _ => {}
+        }
+    }
+    links
+}
+
+/// Replaces each link in `ipld` with its published CID from `table`.
+fn rewrite_links(ipld: &mut Ipld, table: &TranslationTable) -> Result<(), String> {
+    let mut stack = vec![ipld];
+    while let Some(ipld) = stack.pop() {
+        match ipld {
+            Ipld::Link(target) => {
+                *target = table
+                    .get(target)
+                    .ok_or_else(|| format!("{} was not published", target))?;
+            }
+            Ipld::List(items) => stack.extend(items.iter_mut()),
+            Ipld::Map(map) => stack.extend(map.values_mut()),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Uploads a block through the Kubo HTTP RPC API (`/api/v0/block/put`).
+///
+/// `client` is shared across the blocks of a run, so uploads reuse its
+/// connections.
+pub fn put_block(
+    client: &reqwest::blocking::Client,
+    api: &str,
+    block: &PublishedBlock,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let form = reqwest::blocking::multipart::Form::new().part(
+        "data",
+        reqwest::blocking::multipart::Part::bytes(block.bytes.clone()),
+    );
+    let response: serde_json::Value = client
+        .post(format!(
+            "{}/api/v0/block/put?cid-codec=dag-cbor&mhtype=sha2-256&pin=true",
+            api.trim_end_matches('/')
+        ))
+        .multipart(form)
+        .send()?
+        .error_for_status()?
+        .json()?;
+
+    let key = response
+        .get("Key")
+        .and_then(|k| k.as_str())
+        .ok_or("missing Key in block/put response")?;
+    if key.parse::<Cid>()? != block.published {
+        return Err(format!(
+            "IPFS node returned {} for block {}, expected {}",
+            key, block.internal, block.published
+        )
+        .into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use polyepoxide_core::{compute_cid, MemoryStore};
+
+    fn put(store: &MemoryStore, ipld: &Ipld) -> Cid {
+        let bytes = serde_ipld_dagcbor::to_vec(ipld).unwrap();
+        let cid = compute_cid(&bytes);
+        store.put(&cid, &bytes).unwrap();
+        cid
+    }
+
+    #[test]
+    fn rewrites_links_to_published_cids() {
+        let store = MemoryStore::new();
+        let leaf = put(&store, &Ipld::String("leaf".into()));
+        let root = put(
+            &store,
+            &Ipld::Map(BTreeMap::from([
+                ("first".to_string(), Ipld::Link(leaf)),
+                (
+                    "nested".to_string(),
+                    Ipld::List(vec![Ipld::Integer(1), Ipld::Link(leaf)]),
+                ),
+            ])),
+        );
+
+        let mut table = TranslationTable::default();
+        let (published, blocks) = reencode_closure(&store, root, &mut table).unwrap();
+        let internal: Vec<Cid> = blocks.iter().map(|block| block.internal).collect();
+        assert_eq!(internal, [leaf, root]);
+        for block in &blocks {
+            assert_eq!(block.published, compute_sha256_cid(&block.bytes));
+        }
+        assert_eq!(published, blocks[1].published);
+
+        let published_leaf = table.get(&leaf).unwrap();
+        let Ipld::Map(map) = parse_to_ipld(&blocks[1].bytes).unwrap() else {
+            panic!("root is not a map");
+        };
+        assert_eq!(map["first"], Ipld::Link(published_leaf));
+        assert_eq!(
+            map["nested"],
+            Ipld::List(vec![Ipld::Integer(1), Ipld::Link(published_leaf)])
+        );
+
+        // Known nodes are skipped on the next publish
+        let (again, blocks) = reencode_closure(&store, root, &mut table).unwrap();
+        assert_eq!(again, published);
+        assert!(blocks.is_empty());
+    }
+
+    #[test]
+    fn reencodes_long_chains() {
+        let store = MemoryStore::new();
+        let mut head = put(&store, &Ipld::Null);
+        for _ in 0..20_000 {
+            head = put(&store, &Ipld::List(vec![Ipld::Link(head)]));
+        }
+
+        let mut table = TranslationTable::default();
+        let (published, blocks) = reencode_closure(&store, head, &mut table).unwrap();
+        assert_eq!(blocks.len(), 20_001);
+        assert_eq!(blocks.last().unwrap().published, published);
+    }
+}
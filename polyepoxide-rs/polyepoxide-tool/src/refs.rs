@@ -0,0 +1,88 @@
+//! `px refs`: named references to CIDs.
+
+use std::path::PathBuf;
+
+use clap::Subcommand;
+use polyepoxide_core::RefStore;
+
+use crate::error::PxError;
+use crate::{open_store, parse_cid, SlowLogArgs};
+
+#[derive(Subcommand)]
+pub enum RefsCommand {
+    /// List refs, optionally only those whose name starts with a prefix
+    List {
+        /// Name prefix, e.g. "inventory/"
+        #[arg(long, default_value = "")]
+        prefix: String,
+
+        #[command(flatten)]
+        store: StoreArgs,
+    },
+
+    /// Print the CID a ref points to
+    Get {
+        name: String,
+
+        #[command(flatten)]
+        store: StoreArgs,
+    },
+
+    /// Point a ref at a CID
+    Set {
+        name: String,
+
+        cid: String,
+
+        #[command(flatten)]
+        store: StoreArgs,
+    },
+
+    /// Remove a ref
+    Delete {
+        name: String,
+
+        #[command(flatten)]
+        store: StoreArgs,
+    },
+}
+
+#[derive(clap::Args)]
+pub struct StoreArgs {
+    /// Store type: fjall or rocks
+    #[arg(long, default_value = "fjall")]
+    store: String,
+
+    /// Path to the store
+    #[arg(long)]
+    path: PathBuf,
+}
+
+pub fn run(command: RefsCommand, slow_log: &SlowLogArgs) -> Result<(), PxError> {
+    match command {
+        RefsCommand::List { prefix, store } => {
+            let db = open_store(&store.store, &store.path, slow_log)?;
+            for (name, cid) in db.list_refs(&prefix)? {
+                println!("{}\t{}", name, cid);
+            }
+        }
+        RefsCommand::Get { name, store } => {
+            let db = open_store(&store.store, &store.path, slow_log)?;
+            let cid = db.get_ref(&name)?.ok_or(PxError::RefNotFound {
+                name,
+                path: store.path,
+            })?;
+            println!("{}", cid);
+        }
+        RefsCommand::Set { name, cid, store } => {
+            let cid = parse_cid("<CID>", &cid)?;
+            let db = open_store(&store.store, &store.path, slow_log)?;
+            db.set_ref(&name, &cid)?;
+        }
+        RefsCommand::Delete { name, store } => {
+            let db = open_store(&store.store, &store.path, slow_log)?;
+            db.delete_ref(&name)?;
+        }
+    }
+    Ok(())
+}
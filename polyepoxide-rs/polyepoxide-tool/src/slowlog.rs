@@ -0,0 +1,129 @@
+//! Summarizing slow-operation logs written by `SlowLogStore`.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+use polyepoxide_core::SlowOp;
+
+/// Per-operation totals.
+#[derive(Default)]
+struct OpSummary {
+    count: usize,
+    bytes: usize,
+    total: Duration,
+    max: Duration,
+}
+
+/// Renders a summary of a slow log: totals per operation and the slowest calls.
+pub fn summarize(
+    path: &Path,
+    op: Option<&str>,
+    top: usize,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let content = std::fs::read_to_string(path)?;
+    Ok(render(&content, op, top))
+}
+
+/// Summarizes the lines of a log; lines that don't parse are skipped.
+fn render(content: &str, op: Option<&str>, top: usize) -> String {
+    let mut records: Vec<SlowOp> = content
+        .lines()
+        .filter_map(SlowOp::parse_line)
+        .filter(|r| op.is_none_or(|op| r.op == op))
+        .collect();
+
+    let mut by_op: BTreeMap<&str, OpSummary> = BTreeMap::new();
+    for record in &records {
+        let summary = by_op.entry(&record.op).or_default();
+        summary.count += 1;
+        summary.bytes += record.size;
+        summary.total += record.duration;
+        summary.max = summary.max.max(record.duration);
+    }
+
+    let mut out = String::new();
+    out.push_str(&format!(
+        "{:<6} {:>8} {:>12} {:>10} {:>10}\n",
+        "op", "count", "bytes", "avg ms", "max ms"
+    ));
+    for (name, summary) in &by_op {
+        out.push_str(&format!(
+            "{:<6} {:>8} {:>12} {:>10.1} {:>10.1}\n",
+            name,
+            summary.count,
+            summary.bytes,
+            millis(summary.total) / summary.count as f64,
+            millis(summary.max),
+        ));
+    }
+
+    records.sort_by(|a, b| b.duration.cmp(&a.duration));
+    out.push_str(&format!("\nSlowest {} calls:\n", top.min(records.len())));
+    for record in records.iter().take(top) {
+        out.push_str(&format!(
+            "{:>10.1} ms  {:<4} {:>10} B  {}  {}\n",
+            millis(record.duration),
+            record.op,
+            record.size,
+            record.cid,
+            record.caller.as_deref().unwrap_or("-"),
+        ));
+    }
+    out
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use polyepoxide_core::{compute_cid, MemoryStore, SlowLogStore, Store};
+
+    #[test]
+    fn summarizes_logged_calls() {
+        let store = SlowLogStore::new(MemoryStore::new(), Duration::ZERO);
+        let cid = compute_cid(b"data");
+        store.put(&cid, b"data").unwrap();
+        store.get(&cid).unwrap();
+        store.get(&cid).unwrap();
+
+        let mut content = String::from("not a record\n");
+        for record in store.records() {
+            content.push_str(&record.to_line());
+            content.push('\n');
+        }
+
+        let out = render(&content, None, 2);
+        let rows: Vec<Vec<&str>> = out
+            .lines()
+            .skip(1)
+            .take_while(|line| !line.is_empty())
+            .map(|line| line.split_whitespace().collect())
+            .collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0][..3], ["get", "2", "8"]);
+        assert_eq!(rows[1][..3], ["put", "1", "4"]);
+        assert!(out.contains("Slowest 2 calls:"));
+        assert_eq!(out.matches(&cid.to_string()).count(), 2);
+
+        let gets = render(&content, Some("get"), 10);
+        assert!(gets.contains("Slowest 2 calls:"));
+        assert!(!gets.contains("\nput"));
+    }
+
+    #[test]
+    fn parses_fields_in_order() {
+        let cid = compute_cid(b"data");
+        let line = format!("1700000000000\tget\t{}\t4\t1500\tload", cid);
+
+        let out = render(&line, None, 1);
+        assert!(out.contains("get           1            4        1.5        1.5"));
+        assert!(out.ends_with(&format!(
+            "       1.5 ms  get           4 B  {}  load\n",
+            cid
+        )));
+    }
+}
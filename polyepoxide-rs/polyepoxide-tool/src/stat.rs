@@ -0,0 +1,106 @@
+//! `px stat`: what the values reachable from a root take up in a store.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+
+use cid::Cid;
+use polyepoxide_core::traverse::parse_to_ipld;
+use polyepoxide_core::{Bond, Solvent, Store, Structure};
+
+use crate::graph::find_links;
+use crate::tree::{short_cid, type_hint};
+use crate::PxStore;
+
+/// Blocks and bytes of one schema.
+#[derive(Default)]
+struct Usage {
+    blocks: u64,
+    bytes: u64,
+}
+
+/// Walks the blocks reachable from `root`, each counted once however many
+/// bonds lead to it, and summarizes their number and size overall, per
+/// schema and for the `top` largest. Blocks below bonds without a schema are
+/// counted under `?`.
+pub fn report(
+    store: &PxStore,
+    schemas: &Solvent,
+    root: Cid,
+    schema: Cid,
+    top: usize,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let schema = schemas.get::<Structure>(&schema).map(Bond::from_cell);
+    let mut seen = HashSet::from([root]);
+    let mut queue = VecDeque::from([(root, schema, Vec::new(), 0)]);
+    let mut total = Usage::default();
+    let mut types: HashMap<Option<Cid>, (String, Usage)> = HashMap::new();
+    let mut largest = BinaryHeap::new();
+    let (mut depth, mut missing) = (0, 0);
+
+    while let Some((cid, schema, mut frames, level)) = queue.pop_front() {
+        let Some(bytes) = store.get(&cid)? else {
+            missing += 1;
+            continue;
+        };
+        let mut links = Vec::new();
+        find_links(
+            &parse_to_ipld(&bytes)?,
+            schema.as_ref(),
+            &mut frames,
+            "",
+            &mut links,
+        );
+        for link in links {
+            if seen.insert(link.cid) {
+                queue.push_back((link.cid, link.schema, link.frames, level + 1));
+            }
+        }
+
+        let size = bytes.len() as u64;
+        let hint = match &schema {
+            Some(schema) => schema.value().map_or_else(
+                || short_cid(&schema.cid()),
+                |value| format!("{} {}", type_hint(value), short_cid(&schema.cid())),
+            ),
+            None => "?".to_string(),
+        };
+        let (_, usage) = types
+            .entry(schema.map(|schema| schema.cid()))
+            .or_insert_with(|| (hint.clone(), Usage::default()));
+        usage.blocks += 1;
+        usage.bytes += size;
+        total.blocks += 1;
+        total.bytes += size;
+        depth = level;
+
+        // Keeps the `top` largest seen so far, smallest first out
+        largest.push(Reverse((size, cid, hint)));
+        if largest.len() > top {
+            largest.pop();
+        }
+    }
+
+    let mut out = format!(
+        "{} blocks, {} bytes, {} bonds deep\n",
+        total.blocks, total.bytes, depth
+    );
+    if missing > 0 {
+        out.push_str(&format!("{} blocks missing from the store\n", missing));
+    }
+
+    let mut types: Vec<_> = types.into_values().collect();
+    types.sort_by(|a, b| b.1.bytes.cmp(&a.1.bytes));
+    out.push_str(&format!("\n{:>10} {:>14}  type\n", "blocks", "bytes"));
+    for (hint, usage) in types {
+        out.push_str(&format!(
+            "{:>10} {:>14}  {}\n",
+            usage.blocks, usage.bytes, hint
+        ));
+    }
+
+    out.push_str(&format!("\n{:>14}  {:<59}  type\n", "bytes", "cid"));
+    for Reverse((size, cid, hint)) in largest.into_sorted_vec() {
+        out.push_str(&format!("{:>14}  {}  {}\n", size, cid, hint));
+    }
+    Ok(out)
+}
@@ -1,16 +1,23 @@
 //! Lazy tree model for polyepoxide graph exploration.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
 
 use cid::Cid;
 use ipld_core::ipld::Ipld;
-use polyepoxide_core::traverse::parse_to_ipld;
-use polyepoxide_core::{Cell, Oxide, Solvent, Store, Structure};
+use polyepoxide_core::traverse::{parse_to_ipld, Path};
+use polyepoxide_core::{
+    canonicalize, compute_cid, Batch, Bond, Cell, IntType, Oxide, Solvent, Store, Structure,
+};
+use ratatui::style::{Color, Style};
+use ratatui::text::Line;
 use tui_tree_widget::TreeItem;
 use unicode_segmentation::UnicodeSegmentation;
 
-use crate::store::AnyStore;
+use crate::PxStore;
 
 /// Check if a string has more than N grapheme clusters.
 /// This is more efficient than counting all graphemes for long strings.
@@ -62,6 +69,53 @@ pub struct NodeData {
     pub depth: usize,
     /// Child node IDs.
     pub children: Vec<NodeId>,
+    /// Keys leading from the root to this node, as in its ID. Bonds are
+    /// crossed without a key of their own.
+    pub path: Vec<String>,
+    /// For bonds, whether the target's contents have been loaded as this
+    /// node's children.
+    pub target: Option<Target>,
+}
+
+/// Loading state of the value a bond node points to.
+#[derive(Debug, Clone)]
+pub enum Target {
+    /// Requested when the node is first opened.
+    Unloaded(Bond<Structure>),
+    /// Being read by the loader thread.
+    Loading(Bond<Structure>),
+    Loaded,
+    Failed(String),
+}
+
+/// Frames of the spinner shown while a bond target loads.
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+
+/// Reads bond targets on a background thread, so a slow store doesn't block
+/// the UI. The thread exits when the loader is dropped.
+struct Loader {
+    requests: Sender<(NodeId, Cid)>,
+    results: Receiver<(NodeId, Cid, Result<Ipld, String>)>,
+}
+
+impl Loader {
+    fn spawn(store: Arc<PxStore>) -> Self {
+        let (requests, pending) = mpsc::channel::<(NodeId, Cid)>();
+        let (done, results) = mpsc::channel();
+        thread::spawn(move || {
+            for (node_id, cid) in pending {
+                let result = match store.get(&cid) {
+                    Ok(Some(bytes)) => parse_to_ipld(&bytes).map_err(|e| e.to_string()),
+                    Ok(None) => Err(format!("value not found: {}", cid)),
+                    Err(e) => Err(e.to_string()),
+                };
+                if done.send((node_id, cid, result)).is_err() {
+                    break;
+                }
+            }
+        });
+        Self { requests, results }
+    }
 }
 
 /// Breadcrumb entry for zoom navigation.
@@ -81,7 +135,10 @@ pub struct TreeModel {
     /// Breadcrumb trail for zoom navigation.
     pub breadcrumbs: Vec<Breadcrumb>,
     /// Store for loading data.
-    store: AnyStore,
+    store: Arc<PxStore>,
+    loader: Loader,
+    /// Start of the spinner animation.
+    created: Instant,
     /// Schema resolver.
     schemas: Solvent,
     /// Current root CID.
@@ -93,14 +150,17 @@ pub struct TreeModel {
 impl TreeModel {
     /// Create a new tree model from a root CID and schema CID.
     pub fn new(
-        store: AnyStore,
+        store: PxStore,
         root_cid: Cid,
         root_schema_cid: Cid,
     ) -> Result<Self, Box<dyn std::error::Error>> {
+        let store = Arc::new(store);
         let mut model = Self {
             nodes: HashMap::new(),
             roots: Vec::new(),
             breadcrumbs: Vec::new(),
+            loader: Loader::spawn(store.clone()),
+            created: Instant::now(),
             store,
             schemas: Solvent::new(),
             root_cid,
@@ -142,7 +202,7 @@ impl TreeModel {
         schema: &Structure,
     ) -> Result<(), Box<dyn std::error::Error>> {
         match schema {
-            Structure::Sequence(inner) | Structure::Bond(inner) => {
+            Structure::Sequence(inner) | Structure::Bond(inner) | Structure::Defaulted(inner) => {
                 let cid = inner.cid();
                 if self.schemas.get::<Structure>(&cid).is_none() {
                     self.load_schema(cid)?;
@@ -195,7 +255,7 @@ impl TreeModel {
         let node_id = NodeId::root(&self.root_cid);
         let label = short_cid(&self.root_cid);
 
-        self.build_node(&node_id, &label, &ipld, schema, 0)?;
+        self.build_node(&node_id, Vec::new(), &label, &ipld, schema, 0)?;
         self.roots.push(node_id);
 
         Ok(())
@@ -204,16 +264,33 @@ impl TreeModel {
     fn build_node(
         &mut self,
         node_id: &NodeId,
+        path: Vec<String>,
         label: &str,
         ipld: &Ipld,
         schema: &Structure,
         depth: usize,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        let type_hint = self.schema_to_type_hint(schema);
+        // Defaulted fields are encoded as their inner type
+        if let Structure::Defaulted(inner) = schema {
+            if let Some(inner) = inner.value() {
+                return self.build_node(node_id, path, label, ipld, inner, depth);
+            }
+        }
+        let type_hint = type_hint(schema);
         let display = self.format_node_display(label, ipld, schema);
         let cid = self.extract_cid(ipld);
 
-        let children = self.collect_children(node_id, ipld, schema, depth + 1)?;
+        // Bond targets are only read once the node is opened
+        let target = match (ipld, schema) {
+            (Ipld::Link(_), Structure::Bond(inner)) if inner.value().is_some() => {
+                Some(Target::Unloaded(inner.clone()))
+            }
+            _ => None,
+        };
+        let children = match target {
+            Some(_) => Vec::new(),
+            None => self.collect_children(node_id, &path, ipld, schema, depth + 1)?,
+        };
 
         self.nodes.insert(
             node_id.clone(),
@@ -225,15 +302,35 @@ impl TreeModel {
                 display,
                 depth,
                 children,
+                path,
+                target,
             },
         );
 
         Ok(())
     }
 
+    /// Builds the node for the child of `parent_id` at `key`.
+    fn build_child(
+        &mut self,
+        parent_id: &NodeId,
+        parent_path: &[String],
+        key: &str,
+        ipld: &Ipld,
+        schema: &Structure,
+        depth: usize,
+    ) -> Result<NodeId, Box<dyn std::error::Error>> {
+        let child_id = NodeId::child(parent_id.as_str(), key);
+        let mut path = parent_path.to_vec();
+        path.push(key.to_string());
+        self.build_node(&child_id, path, key, ipld, schema, depth)?;
+        Ok(child_id)
+    }
+
     fn collect_children(
         &mut self,
         parent_id: &NodeId,
+        path: &[String],
         ipld: &Ipld,
         schema: &Structure,
         depth: usize,
@@ -246,9 +343,14 @@ impl TreeModel {
                     for (name, field_schema_bond) in fields {
                         if let Some(fv) = map.get(name) {
                             if let Some(field_schema) = field_schema_bond.value() {
-                                let child_id = NodeId::child(parent_id.as_str(), name);
-                                self.build_node(&child_id, name, fv, field_schema, depth)?;
-                                children.push(child_id);
+                                children.push(self.build_child(
+                                    parent_id,
+                                    path,
+                                    name,
+                                    fv,
+                                    field_schema,
+                                    depth,
+                                )?);
                             }
                         }
                     }
@@ -259,9 +361,14 @@ impl TreeModel {
                     if let Some(inner_schema) = inner.value() {
                         for (i, elem) in arr.iter().enumerate() {
                             let idx = format!("[{}]", i);
-                            let child_id = NodeId::child(parent_id.as_str(), &idx);
-                            self.build_node(&child_id, &idx, elem, inner_schema, depth)?;
-                            children.push(child_id);
+                            children.push(self.build_child(
+                                parent_id,
+                                path,
+                                &idx,
+                                elem,
+                                inner_schema,
+                                depth,
+                            )?);
                         }
                     }
                 }
@@ -273,9 +380,14 @@ impl TreeModel {
                     {
                         if let Some(elem_schema) = elem_schema_bond.value() {
                             let idx = format!("[{}]", i);
-                            let child_id = NodeId::child(parent_id.as_str(), &idx);
-                            self.build_node(&child_id, &idx, elem_val, elem_schema, depth)?;
-                            children.push(child_id);
+                            children.push(self.build_child(
+                                parent_id,
+                                path,
+                                &idx,
+                                elem_val,
+                                elem_schema,
+                                depth,
+                            )?);
                         }
                     }
                 }
@@ -286,9 +398,14 @@ impl TreeModel {
                         if let Some((name, val)) = map.iter().next() {
                             if let Some(variant_schema_bond) = variants.get(name) {
                                 if let Some(variant_schema) = variant_schema_bond.value() {
-                                    let child_id = NodeId::child(parent_id.as_str(), name);
-                                    self.build_node(&child_id, name, val, variant_schema, depth)?;
-                                    children.push(child_id);
+                                    children.push(self.build_child(
+                                        parent_id,
+                                        path,
+                                        name,
+                                        val,
+                                        variant_schema,
+                                        depth,
+                                    )?);
                                 }
                             }
                         }
@@ -299,27 +416,7 @@ impl TreeModel {
                 if let Ipld::Map(map) = ipld {
                     if let Some(vs) = v.value() {
                         for (mk, mv) in map {
-                            let child_id = NodeId::child(parent_id.as_str(), mk);
-                            self.build_node(&child_id, mk, mv, vs, depth)?;
-                            children.push(child_id);
-                        }
-                    }
-                }
-            }
-            Structure::Bond(inner) => {
-                // For bonds, load the referenced value if available
-                if let Ipld::Link(target_cid) = ipld {
-                    if let Ok(Some(target_bytes)) = self.store.get(target_cid) {
-                        if let Ok(target_ipld) = parse_to_ipld(&target_bytes) {
-                            if let Some(inner_schema) = inner.value() {
-                                let nested = self.collect_children(
-                                    parent_id,
-                                    &target_ipld,
-                                    inner_schema,
-                                    depth,
-                                )?;
-                                children.extend(nested);
-                            }
+                            children.push(self.build_child(parent_id, path, mk, mv, vs, depth)?);
                         }
                     }
                 }
@@ -331,7 +428,7 @@ impl TreeModel {
     }
 
     fn format_node_display(&self, label: &str, ipld: &Ipld, schema: &Structure) -> String {
-        let type_hint = self.schema_to_type_hint(schema);
+        let type_hint = type_hint(schema);
 
         match (ipld, schema) {
             (Ipld::Link(cid), Structure::Bond(_)) => {
@@ -356,69 +453,6 @@ impl TreeModel {
         }
     }
 
-    fn schema_to_type_hint(&self, schema: &Structure) -> String {
-        match schema {
-            Structure::Bool => "Bool".to_string(),
-            Structure::Char => "Char".to_string(),
-            Structure::Unicode => "String".to_string(),
-            Structure::ByteString => "Bytes".to_string(),
-            Structure::Int(t) => format!("{:?}", t),
-            Structure::Float(t) => format!("{:?}", t),
-            Structure::Unit => "Unit".to_string(),
-            Structure::Sequence(inner) => {
-                let inner_hint = inner
-                    .value()
-                    .map(|s| self.schema_to_type_hint(s))
-                    .unwrap_or_else(|| "?".to_string());
-                format!("Seq<{}>", inner_hint)
-            }
-            Structure::Tuple(elems) => {
-                let hints: Vec<_> = elems
-                    .iter()
-                    .map(|e| {
-                        e.value()
-                            .map(|s| self.schema_to_type_hint(s))
-                            .unwrap_or_else(|| "?".to_string())
-                    })
-                    .collect();
-                format!("({})", hints.join(", "))
-            }
-            Structure::Record(fields) => {
-                let names: Vec<_> = fields.keys().cloned().collect();
-                if names.len() <= 3 {
-                    format!("Record{{{}}}", names.join(", "))
-                } else {
-                    format!("Record{{{}...}}", names[..2].join(", "))
-                }
-            }
-            Structure::Tagged(variants) => {
-                let names: Vec<_> = variants.keys().cloned().collect();
-                if names.len() <= 3 {
-                    format!("Tagged{{{}}}", names.join("|"))
-                } else {
-                    format!("Tagged{{{}|...}}", names[..2].join("|"))
-                }
-            }
-            Structure::Enum(variants) => {
-                if variants.len() <= 3 {
-                    format!("Enum{{{}}}", variants.join("|"))
-                } else {
-                    format!("Enum{{{}|...}}", variants[..2].join("|"))
-                }
-            }
-            Structure::Map { .. } => "Map".to_string(),
-            Structure::OrderedMap { .. } => "OrderedMap".to_string(),
-            Structure::Bond(inner) => {
-                let inner_hint = inner
-                    .value()
-                    .map(|s| self.schema_to_type_hint(s))
-                    .unwrap_or_else(|| "?".to_string());
-                format!("Bond<{}>", inner_hint)
-            }
-            Structure::SelfRef(n) => format!("SelfRef({})", n),
-        }
-    }
-
     fn extract_cid(&self, ipld: &Ipld) -> Option<Cid> {
         if let Ipld::Link(cid) = ipld {
             Some(*cid)
@@ -427,27 +461,151 @@ impl TreeModel {
         }
     }
 
-    /// Build tree items for tui-tree-widget.
-    pub fn tree_items(&self) -> Vec<TreeItem<'_, NodeId>> {
-        self.build_tree_items(&self.roots)
+    /// Build tree items for tui-tree-widget, with the `highlighted` nodes
+    /// styled as search matches.
+    pub fn tree_items(&self, highlighted: &HashSet<&NodeId>) -> Vec<TreeItem<'_, NodeId>> {
+        self.build_tree_items(&self.roots, highlighted)
     }
 
-    fn build_tree_items(&self, node_ids: &[NodeId]) -> Vec<TreeItem<'_, NodeId>> {
+    fn build_tree_items(
+        &self,
+        node_ids: &[NodeId],
+        highlighted: &HashSet<&NodeId>,
+    ) -> Vec<TreeItem<'_, NodeId>> {
         node_ids
             .iter()
-            .filter_map(|id| self.build_tree_item(id))
+            .filter_map(|id| self.build_tree_item(id, highlighted))
             .collect()
     }
 
-    fn build_tree_item(&self, node_id: &NodeId) -> Option<TreeItem<'_, NodeId>> {
+    fn build_tree_item(
+        &self,
+        node_id: &NodeId,
+        highlighted: &HashSet<&NodeId>,
+    ) -> Option<TreeItem<'_, NodeId>> {
         let node = self.nodes.get(node_id)?;
-
-        if node.children.is_empty() {
-            Some(TreeItem::new_leaf(node_id.clone(), node.display.as_str()))
+        let style = if highlighted.contains(node_id) {
+            Style::default().fg(Color::Black).bg(Color::Yellow)
         } else {
-            let children = self.build_tree_items(&node.children);
-            TreeItem::new(node_id.clone(), node.display.as_str(), children).ok()
+            Style::default()
+        };
+        let text = Line::styled(node.display.as_str(), style);
+
+        // Unloaded bonds get a placeholder child, so they can be opened
+        let placeholder = |text| {
+            vec![TreeItem::new_leaf(
+                NodeId::child(node_id.as_str(), "..."),
+                text,
+            )]
+        };
+        let children = match &node.target {
+            Some(Target::Unloaded(_) | Target::Loading(_)) => {
+                placeholder(Line::raw(format!("{} loading...", self.spinner())))
+            }
+            Some(Target::Failed(e)) => placeholder(Line::styled(
+                format!("error: {}", e),
+                Style::default().fg(Color::Red),
+            )),
+            _ if node.children.is_empty() => {
+                return Some(TreeItem::new_leaf(node_id.clone(), text))
+            }
+            _ => self.build_tree_items(&node.children, highlighted),
+        };
+        TreeItem::new(node_id.clone(), text, children).ok()
+    }
+
+    fn spinner(&self) -> char {
+        let frame = self.created.elapsed().as_millis() / 100;
+        SPINNER[frame as usize % SPINNER.len()]
+    }
+
+    /// Starts loading the target of a bond node, unless it's already loaded
+    /// or loading.
+    pub fn load_children(&mut self, node_id: &NodeId) {
+        let Some(node) = self.nodes.get_mut(node_id) else {
+            return;
+        };
+        let (Some(Target::Unloaded(schema)), Some(cid)) = (&node.target, node.cid) else {
+            return;
+        };
+        node.target = Some(match self.loader.requests.send((node_id.clone(), cid)) {
+            Ok(()) => Target::Loading(schema.clone()),
+            Err(_) => Target::Failed("loader thread stopped".to_string()),
+        });
+    }
+
+    /// Adds the children of the bond targets loaded since the last call.
+    /// Results for nodes no longer in the tree are dropped.
+    pub fn poll(&mut self) {
+        while let Ok((node_id, cid, result)) = self.loader.results.try_recv() {
+            let Some(node) = self.nodes.get(&node_id) else {
+                continue;
+            };
+            let Some(Target::Loading(schema)) = &node.target else {
+                continue;
+            };
+            if node.cid != Some(cid) {
+                continue;
+            }
+            let (schema, path, depth) = (schema.clone(), node.path.clone(), node.depth);
+            let children = result.map_err(Into::into).and_then(|ipld| {
+                let schema = schema.value().expect("checked when the node was built");
+                self.collect_children(&node_id, &path, &ipld, schema, depth + 1)
+            });
+            let node = self.nodes.get_mut(&node_id).expect("node was found above");
+            node.target = Some(match children {
+                Ok(children) => {
+                    node.children = children;
+                    Target::Loaded
+                }
+                Err(e) => Target::Failed(e.to_string()),
+            });
+        }
+    }
+
+    /// Finds the loaded nodes whose label, string value or CID contains
+    /// `query`, ignoring case. Returns the path from the root to each match,
+    /// in display order.
+    pub fn search(&self, query: &str) -> Vec<Vec<NodeId>> {
+        let query = query.to_lowercase();
+        self.find(|node| {
+            node.display.to_lowercase().contains(&query)
+                || matches!(&node.ipld, Some(Ipld::String(s)) if s.to_lowercase().contains(&query))
+                || node.cid.is_some_and(|cid| cid.to_string().contains(&query))
+        })
+    }
+
+    /// Finds the loaded nodes a path such as `items[*].name` selects from
+    /// the current root, checking it against the root's schema first.
+    pub fn select(&self, text: &str) -> Result<Vec<Vec<NodeId>>, Box<dyn std::error::Error>> {
+        let schema = self
+            .schemas
+            .get::<Structure>(&self.root_schema_cid)
+            .ok_or_else(|| format!("schema not found: {}", self.root_schema_cid))?;
+        let path = Path::compile(text, &Bond::from_cell(schema))?;
+        Ok(self.find(|node| path.matches(node.path.as_slice())))
+    }
+
+    /// Returns the path from the root to each loaded node `found` accepts,
+    /// in display order.
+    fn find(&self, found: impl Fn(&NodeData) -> bool) -> Vec<Vec<NodeId>> {
+        let mut matches = Vec::new();
+        let mut stack: Vec<Vec<NodeId>> =
+            self.roots.iter().rev().map(|id| vec![id.clone()]).collect();
+        while let Some(path) = stack.pop() {
+            let Some(node) = path.last().and_then(|id| self.nodes.get(id)) else {
+                continue;
+            };
+            for child in node.children.iter().rev() {
+                let mut child_path = path.clone();
+                child_path.push(child.clone());
+                stack.push(child_path);
+            }
+            if found(node) {
+                matches.push(path);
+            }
         }
+        matches
     }
 
     /// Get node data by ID.
@@ -524,6 +682,139 @@ impl TreeModel {
         Ok(true)
     }
 
+    /// Replaces the value of a leaf node with `input`, parsed according to
+    /// its schema, and writes new versions of the values from the root down
+    /// to it. Views the new root, with the old one as the previous
+    /// breadcrumb, and returns its CID.
+    pub fn edit_leaf(
+        &mut self,
+        node_id: &NodeId,
+        input: &str,
+    ) -> Result<Cid, Box<dyn std::error::Error>> {
+        let path = match self.nodes.get(node_id) {
+            Some(node) => node.path.clone(),
+            None => return Err(format!("no such node: {}", node_id.as_str()).into()),
+        };
+        let bytes = self
+            .store
+            .get(&self.root_cid)?
+            .ok_or_else(|| format!("value not found: {}", self.root_cid))?;
+        let schema_cell = self.load_schema(self.root_schema_cid)?;
+
+        let mut batch = Batch::new();
+        let root = self.rewrite(
+            &parse_to_ipld(&bytes)?,
+            schema_cell.value(),
+            &path,
+            input,
+            &mut batch,
+        )?;
+        let root_cid = put_block(&mut batch, &root)?;
+        self.store.write_batch(&batch)?;
+
+        self.breadcrumbs.push(Breadcrumb {
+            cid: self.root_cid,
+            schema_cid: self.root_schema_cid,
+            label: short_cid(&self.root_cid),
+        });
+        self.root_cid = root_cid;
+        self.rebuild_tree()?;
+        Ok(root_cid)
+    }
+
+    /// Returns `ipld` with the value at `path` replaced, adding the new
+    /// versions of bond targets on the way to `batch`.
+    fn rewrite(
+        &self,
+        ipld: &Ipld,
+        schema: &Structure,
+        path: &[String],
+        input: &str,
+        batch: &mut Batch,
+    ) -> Result<Ipld, Box<dyn std::error::Error>> {
+        if let Structure::Defaulted(inner) = schema {
+            let inner = inner.value().ok_or("schema not loaded")?;
+            return self.rewrite(ipld, inner, path, input, batch);
+        }
+        let Some((key, rest)) = path.split_first() else {
+            return parse_leaf(input, schema);
+        };
+        let missing = || format!("no value at {:?}", key);
+        let mut ipld = ipld.clone();
+        match (&mut ipld, schema) {
+            (Ipld::Link(cid), Structure::Bond(inner)) => {
+                // Bonds are expanded in place, so the key applies to the target
+                let inner = inner.value().ok_or("schema not loaded")?;
+                let bytes = self
+                    .store
+                    .get(cid)?
+                    .ok_or_else(|| format!("value not found: {}", cid))?;
+                let target = self.rewrite(&parse_to_ipld(&bytes)?, inner, path, input, batch)?;
+                *cid = put_block(batch, &target)?;
+            }
+            (Ipld::Map(map), Structure::Record(fields) | Structure::Tagged(fields)) => {
+                let field = fields
+                    .get(key)
+                    .and_then(|f| f.value())
+                    .ok_or_else(missing)?;
+                let value = map.get_mut(key).ok_or_else(missing)?;
+                *value = self.rewrite(value, field, rest, input, batch)?;
+            }
+            (
+                Ipld::Map(map),
+                Structure::Map { value: v, .. } | Structure::OrderedMap { value: v, .. },
+            ) => {
+                let v = v.value().ok_or("schema not loaded")?;
+                let value = map.get_mut(key).ok_or_else(missing)?;
+                *value = self.rewrite(value, v, rest, input, batch)?;
+            }
+            (Ipld::List(items), Structure::Sequence(_) | Structure::Tuple(_)) => {
+                let index: usize = key
+                    .strip_prefix('[')
+                    .and_then(|k| k.strip_suffix(']'))
+                    .and_then(|k| k.parse().ok())
+                    .ok_or_else(missing)?;
+                let item_schema = match schema {
+                    Structure::Sequence(inner) => inner.value(),
+                    Structure::Tuple(elems) => elems.get(index).and_then(|e| e.value()),
+                    _ => None,
+                };
+                let item_schema = item_schema.ok_or("schema not loaded")?;
+                let item = items.get_mut(index).ok_or_else(missing)?;
+                *item = self.rewrite(item, item_schema, rest, input, batch)?;
+            }
+            _ => return Err(missing().into()),
+        }
+        Ok(ipld)
+    }
+
+    /// Returns the DAG-CBOR of a node: its target's block for a bond, the
+    /// root block for the root, or otherwise the value encoded on its own,
+    /// which has no CID.
+    pub fn raw_bytes(
+        &self,
+        node_id: &NodeId,
+    ) -> Result<(Option<Cid>, Vec<u8>), Box<dyn std::error::Error>> {
+        let node = self
+            .nodes
+            .get(node_id)
+            .ok_or_else(|| format!("no such node: {}", node_id.as_str()))?;
+        let cid = match node.cid {
+            Some(cid) => cid,
+            None if self.roots.contains(node_id) => self.root_cid,
+            None => {
+                let ipld = node.ipld.as_ref().ok_or("value not loaded")?;
+                let bytes = canonicalize(&serde_ipld_dagcbor::to_vec(ipld)?)?;
+                return Ok((None, bytes));
+            }
+        };
+        let bytes = self
+            .store
+            .get(&cid)?
+            .ok_or_else(|| format!("value not found: {}", cid))?;
+        Ok((Some(cid), bytes))
+    }
+
     /// Get breadcrumb path string.
     pub fn breadcrumb_path(&self) -> String {
         let mut parts: Vec<String> = self.breadcrumbs.iter().map(|b| b.label.clone()).collect();
@@ -532,7 +823,7 @@ impl TreeModel {
     }
 
     /// Access the store.
-    pub fn store(&self) -> &AnyStore {
+    pub fn store(&self) -> &PxStore {
         &self.store
     }
 
@@ -552,8 +843,46 @@ impl TreeModel {
     }
 }
 
+/// Parses edited text as a value of a primitive schema.
+fn parse_leaf(input: &str, schema: &Structure) -> Result<Ipld, Box<dyn std::error::Error>> {
+    let invalid = || format!("not a valid {}: {:?}", type_hint(schema), input);
+    match schema {
+        Structure::Unicode => Ok(Ipld::String(input.to_string())),
+        Structure::Char if input.chars().count() == 1 => Ok(Ipld::String(input.to_string())),
+        Structure::Bool => Ok(Ipld::Bool(input.parse().map_err(|_| invalid())?)),
+        Structure::Int(int) => {
+            let n: i128 = input.parse().map_err(|_| invalid())?;
+            let in_range = match int {
+                IntType::U8 => u8::try_from(n).is_ok(),
+                IntType::U16 => u16::try_from(n).is_ok(),
+                IntType::U32 => u32::try_from(n).is_ok(),
+                IntType::U64 => u64::try_from(n).is_ok(),
+                IntType::I8 => i8::try_from(n).is_ok(),
+                IntType::I16 => i16::try_from(n).is_ok(),
+                IntType::I32 => i32::try_from(n).is_ok(),
+                IntType::I64 => i64::try_from(n).is_ok(),
+                // Encoded as bytes rather than integers
+                IntType::U128 | IntType::I128 => false,
+            };
+            if !in_range {
+                return Err(invalid().into());
+            }
+            Ok(Ipld::Integer(n))
+        }
+        _ => Err(format!("{} values can't be edited", type_hint(schema)).into()),
+    }
+}
+
+/// Encodes a value and adds it to `batch`, returning its CID.
+fn put_block(batch: &mut Batch, ipld: &Ipld) -> Result<Cid, Box<dyn std::error::Error>> {
+    let bytes = canonicalize(&serde_ipld_dagcbor::to_vec(ipld)?)?;
+    let cid = compute_cid(&bytes);
+    batch.put(cid, bytes);
+    Ok(cid)
+}
+
 /// Format a CID as a short string.
-fn short_cid(cid: &Cid) -> String {
+pub fn short_cid(cid: &Cid) -> String {
     let s = cid.to_string();
     if has_more_than_n_graphemes(&s, 12) {
         format!("{}...", truncate_str(&s, 12))
@@ -561,3 +890,67 @@ fn short_cid(cid: &Cid) -> String {
         s
     }
 }
+
+/// Human-readable summary of a schema, such as `Seq<Bond<String>>`.
+pub fn type_hint(schema: &Structure) -> String {
+    match schema {
+        Structure::Bool => "Bool".to_string(),
+        Structure::Char => "Char".to_string(),
+        Structure::Unicode => "String".to_string(),
+        Structure::ByteString => "Bytes".to_string(),
+        Structure::Int(t) => format!("{:?}", t),
+        Structure::Float(t) => format!("{:?}", t),
+        Structure::Unit => "Unit".to_string(),
+        Structure::Sequence(inner) => {
+            let inner_hint = inner
+                .value()
+                .map(type_hint)
+                .unwrap_or_else(|| "?".to_string());
+            format!("Seq<{}>", inner_hint)
+        }
+        Structure::Tuple(elems) => {
+            let hints: Vec<_> = elems
+                .iter()
+                .map(|e| e.value().map(type_hint).unwrap_or_else(|| "?".to_string()))
+                .collect();
+            format!("({})", hints.join(", "))
+        }
+        Structure::Record(fields) => {
+            let names: Vec<_> = fields.keys().cloned().collect();
+            if names.len() <= 3 {
+                format!("Record{{{}}}", names.join(", "))
+            } else {
+                format!("Record{{{}...}}", names[..2].join(", "))
+            }
+        }
+        Structure::Tagged(variants) => {
+            let names: Vec<_> = variants.keys().cloned().collect();
+            if names.len() <= 3 {
+                format!("Tagged{{{}}}", names.join("|"))
+            } else {
+                format!("Tagged{{{}|...}}", names[..2].join("|"))
+            }
+        }
+        Structure::Enum(variants) => {
+            if variants.len() <= 3 {
+                format!("Enum{{{}}}", variants.join("|"))
+            } else {
+                format!("Enum{{{}|...}}", variants[..2].join("|"))
+            }
+        }
+        Structure::Map { .. } => "Map".to_string(),
+        Structure::OrderedMap { .. } => "OrderedMap".to_string(),
+        Structure::Bond(inner) => {
+            let inner_hint = inner
+                .value()
+                .map(type_hint)
+                .unwrap_or_else(|| "?".to_string());
+            format!("Bond<{}>", inner_hint)
+        }
+        Structure::SelfRef(n) => format!("SelfRef({})", n),
+        Structure::Defaulted(inner) => inner
+            .value()
+            .map(type_hint)
+            .unwrap_or_else(|| "?".to_string()),
+    }
+}
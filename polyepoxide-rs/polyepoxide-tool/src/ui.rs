@@ -4,12 +4,12 @@ use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Paragraph},
+    widgets::{Block, Borders, Paragraph, Wrap},
     Frame,
 };
 use tui_tree_widget::Tree;
 
-use crate::app::App;
+use crate::app::{App, Prompt};
 
 /// Render the TUI.
 pub fn render(frame: &mut Frame, app: &mut App) {
@@ -23,8 +23,17 @@ pub fn render(frame: &mut Frame, app: &mut App) {
         .split(frame.area());
 
     render_header(frame, app, chunks[0]);
-    render_tree(frame, app, chunks[1]);
-    render_help(frame, chunks[2]);
+    if app.raw.is_some() {
+        let panes = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(chunks[1]);
+        render_tree(frame, app, panes[0]);
+        render_raw(frame, app, panes[1]);
+    } else {
+        render_tree(frame, app, chunks[1]);
+    }
+    render_help(frame, app, chunks[2]);
 }
 
 fn render_header(frame: &mut Frame, app: &App, area: Rect) {
@@ -67,7 +76,12 @@ fn render_tree(frame: &mut Frame, app: &mut App, area: Rect) {
     let inner = block.inner(area);
     frame.render_widget(block, area);
 
-    let items = app.tree.tree_items();
+    let highlighted = app
+        .search_matches
+        .iter()
+        .filter_map(|path| path.last())
+        .collect();
+    let items = app.tree.tree_items(&highlighted);
     let tree = Tree::new(&items)
         .expect("unique identifiers")
         .highlight_style(
@@ -82,7 +96,20 @@ fn render_tree(frame: &mut Frame, app: &mut App, area: Rect) {
     frame.render_stateful_widget(tree, inner, &mut app.tree_state);
 }
 
-fn render_help(frame: &mut Frame, area: Rect) {
+fn render_raw(frame: &mut Frame, app: &App, area: Rect) {
+    let Some((_, lines)) = &app.raw else {
+        return;
+    };
+    let block = Block::default().borders(Borders::ALL).title(" Raw ");
+    let lines: Vec<Line> = lines.iter().map(|line| Line::raw(line.as_str())).collect();
+    let raw = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false })
+        .scroll((app.raw_scroll, 0));
+    frame.render_widget(raw, area);
+}
+
+fn render_help(frame: &mut Frame, app: &App, area: Rect) {
     let block = Block::default()
         .borders(Borders::ALL)
         .style(Style::default().fg(Color::DarkGray));
@@ -90,7 +117,19 @@ fn render_help(frame: &mut Frame, area: Rect) {
     let inner = block.inner(area);
     frame.render_widget(block, area);
 
-    let help_spans = vec![
+    if let Some((prompt, line)) = &app.input {
+        let label = match prompt {
+            Prompt::Search => "/",
+            Prompt::Select => ":",
+            Prompt::Edit => "New value: ",
+        };
+        let prompt =
+            Paragraph::new(format!("{}{}", label, line)).style(Style::default().fg(Color::White));
+        frame.render_widget(prompt, inner);
+        return;
+    }
+
+    let mut help_spans = vec![
         Span::styled("↑↓", Style::default().fg(Color::Yellow)),
         Span::raw(" Navigate  "),
         Span::styled("Enter", Style::default().fg(Color::Yellow)),
@@ -105,9 +144,24 @@ fn render_help(frame: &mut Frame, area: Rect) {
         Span::raw(" Export JSON  "),
         Span::styled("y", Style::default().fg(Color::Yellow)),
         Span::raw(" Export YAML  "),
+        Span::styled("i", Style::default().fg(Color::Yellow)),
+        Span::raw(" Edit  "),
+        Span::styled("r", Style::default().fg(Color::Yellow)),
+        Span::raw(" Raw  "),
+        Span::styled("/", Style::default().fg(Color::Yellow)),
+        Span::raw(" Search  "),
+        Span::styled(":", Style::default().fg(Color::Yellow)),
+        Span::raw(" Select  "),
         Span::styled("q", Style::default().fg(Color::Yellow)),
         Span::raw(" Quit"),
     ];
+    if !app.search_matches.is_empty() {
+        help_spans.push(Span::raw(format!(
+            "  [{}/{}, n/N next/prev]",
+            app.search_index + 1,
+            app.search_matches.len()
+        )));
+    }
 
     let help = Paragraph::new(Line::from(help_spans));
     frame.render_widget(help, inner);
@@ -0,0 +1,87 @@
+//! `px verify`: checking a store for corrupt values and missing bonds.
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use cid::Cid;
+use ipld_core::ipld::Ipld;
+use multihash_codetable::{Code, MultihashDigest};
+use polyepoxide_core::traverse::parse_to_ipld;
+use polyepoxide_core::{RefStore, Store};
+
+use crate::error::PxError;
+use crate::PxStore;
+
+/// Re-hashes every value and schema against its CID, decodes it and checks
+/// that everything it bonds to, and every ref, points to a stored CID.
+///
+/// Links in DAG-CBOR written by polyepoxide are always bonds, so all of them
+/// are checked, whether or not the schema of the value is known.
+///
+/// Prints one line per problem and fails if there were any.
+pub fn run(store: &PxStore, path: &Path) -> Result<String, PxError> {
+    let stored: HashSet<Cid> = store.list_cids()?.into_iter().collect();
+    let mut out = String::new();
+    let mut problems = 0;
+    let mut report = |line: String| {
+        out.push_str(&line);
+        out.push('\n');
+        problems += 1;
+    };
+
+    for cid in &stored {
+        // Deleted since it was listed
+        let Some(bytes) = store.get(cid)? else {
+            continue;
+        };
+        match Code::try_from(cid.hash().code()) {
+            Ok(code) if code.digest(&bytes) == *cid.hash() => {}
+            Ok(_) => report(format!("{}: hash doesn't match contents", cid)),
+            Err(_) => report(format!(
+                "{}: unsupported hash 0x{:x}",
+                cid,
+                cid.hash().code()
+            )),
+        }
+        let ipld = match parse_to_ipld(&bytes) {
+            Ok(ipld) => ipld,
+            Err(e) => {
+                report(format!("{}: {}", cid, e));
+                continue;
+            }
+        };
+        let mut links = Vec::new();
+        collect_links(&ipld, &mut links);
+        for link in links {
+            if !stored.contains(&link) {
+                report(format!("{}: bonds to missing {}", cid, link));
+            }
+        }
+    }
+    for (name, cid) in store.list_refs("")? {
+        if !stored.contains(&cid) {
+            report(format!("ref {:?}: points to missing {}", name, cid));
+        }
+    }
+
+    if problems > 0 {
+        print!("{}", out);
+        return Err(PxError::Corrupt {
+            problems,
+            path: path.to_path_buf(),
+        });
+    }
+    Ok(format!(
+        "{} blocks checked, no problems found\n",
+        stored.len()
+    ))
+}
+
+fn collect_links(ipld: &Ipld, links: &mut Vec<Cid>) {
+    match ipld {
+        Ipld::Link(cid) => links.push(*cid),
+        Ipld::List(items) => items.iter().for_each(|item| collect_links(item, links)),
+        Ipld::Map(map) => map.values().for_each(|value| collect_links(value, links)),
+        _ => {}
+    }
+}
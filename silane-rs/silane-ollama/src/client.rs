@@ -0,0 +1,203 @@
+use std::future::Future;
+
+use polyepoxide_llm::Message;
+use serde_json::Value;
+use silane_openrouter::{
+    CompletionProvider, OpenRouterRequest, build_request_body, parse_response,
+};
+use tracing::{debug, instrument};
+
+use crate::error::OllamaError;
+
+const DEFAULT_BASE_URL: &str = "http://localhost:11434";
+
+/// Client for a local Ollama server.
+pub struct OllamaClient {
+    http: reqwest::Client,
+    base_url: String,
+}
+
+impl Default for OllamaClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OllamaClient {
+    /// Creates a client for Ollama's default address.
+    pub fn new() -> Self {
+        Self::with_base_url(DEFAULT_BASE_URL)
+    }
+
+    /// Creates a client for a server at another address.
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.into(),
+        }
+    }
+
+    /// Executes a completion request.
+    ///
+    /// Returns an assistant Message with `previous` pointing to the conversation head.
+    #[instrument(skip(self, request), fields(model = %request.model))]
+    pub async fn complete(&self, request: &OpenRouterRequest) -> Result<Message, OllamaError> {
+        let mut body = build_request_body(request)?;
+        translate_reasoning(&mut body);
+
+        debug!("Sending request to Ollama");
+
+        let response = self
+            .http
+            .post(format!("{}/v1/chat/completions", self.base_url))
+            .json(&body)
+            .send()
+            .await?;
+        let response_body = check(response).await?;
+
+        debug!("Received successful response");
+
+        Ok(parse_response(
+            &response_body,
+            request.conversation_head.clone(),
+        )?)
+    }
+
+    /// Names of the models pulled to the server.
+    pub async fn list_models(&self) -> Result<Vec<String>, OllamaError> {
+        let response = self
+            .http
+            .get(format!("{}/api/tags", self.base_url))
+            .send()
+            .await?;
+        Ok(model_names(&check(response).await?))
+    }
+}
+
+impl CompletionProvider for OllamaClient {
+    type Error = OllamaError;
+
+    fn complete(
+        &self,
+        request: &OpenRouterRequest,
+    ) -> impl Future<Output = Result<Message, OllamaError>> + Send {
+        OllamaClient::complete(self, request)
+    }
+}
+
+async fn check(response: reqwest::Response) -> Result<Value, OllamaError> {
+    let status = response.status();
+    if !status.is_success() {
+        // Proxies and crashed servers may answer with plain text or nothing
+        let body = response.text().await.unwrap_or_default();
+        return Err(OllamaError::Api {
+            status: status.as_u16(),
+            message: error_message(&body),
+        });
+    }
+    Ok(response.json().await?)
+}
+
+/// The message of an error response, or its body if it isn't JSON.
+fn error_message(body: &str) -> String {
+    let Ok(body) = serde_json::from_str::<Value>(body) else {
+        return match body.trim() {
+            "" => "Unknown error".to_string(),
+            text => text.to_string(),
+        };
+    };
+    let error = body.get("error");
+    // The OpenAI-compatible API nests the message, the native API doesn't
+    error
+        .and_then(|e| e.get("message"))
+        .or(error)
+        .and_then(|m| m.as_str())
+        .unwrap_or("Unknown error")
+        .to_string()
+}
+
+/// Moves OpenRouter's `reasoning.effort` to the `reasoning_effort` Ollama
+/// reads; a reasoning token budget has no equivalent.
+fn translate_reasoning(body: &mut Value) {
+    let Some(reasoning) = body.as_object_mut().and_then(|b| b.remove("reasoning")) else {
+        return;
+    };
+    if let Some(effort) = reasoning.get("effort") {
+        body["reasoning_effort"] = effort.clone();
+    }
+}
+
+fn model_names(tags: &Value) -> Vec<String> {
+    tags.get("models")
+        .and_then(|m| m.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|m| m.get("name").and_then(|n| n.as_str()))
+        .map(String::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use polyepoxide_core::{Bond, Solvent};
+    use polyepoxide_llm::{ContentBlock, GenerationParams, MessageContent};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_client_creation() {
+        assert_eq!(OllamaClient::new().base_url, DEFAULT_BASE_URL);
+        let client = OllamaClient::with_base_url("http://gpu-box:11434");
+        assert_eq!(client.base_url, "http://gpu-box:11434");
+    }
+
+    #[test]
+    fn test_request_and_model_list() {
+        let mut solvent = Solvent::new();
+        let cell = solvent.add(Message {
+            content: MessageContent::User(vec![ContentBlock::Text("Hi".to_string())]),
+            metadata: None,
+            previous: None,
+        });
+        let request = OpenRouterRequest {
+            model: "llama3.2".to_string(),
+            conversation_head: Bond::from_cell(Arc::clone(&cell)),
+            params: Some(GenerationParams {
+                temperature: None,
+                top_p: None,
+                top_k: None,
+                max_tokens: None,
+                frequency_penalty: None,
+                presence_penalty: None,
+                stop: None,
+                min_p: None,
+                top_a: None,
+                repetition_penalty: None,
+                seed: None,
+                reasoning_effort: Some("low".to_string()),
+                reasoning_max_tokens: None,
+            }),
+            tools: vec![],
+            tool_choice: None,
+            context: None,
+        };
+        let mut body = build_request_body(&request).unwrap();
+        translate_reasoning(&mut body);
+        assert_eq!(body["reasoning_effort"], "low");
+        assert!(body.get("reasoning").is_none());
+
+        let tags = json!({ "models": [{ "name": "llama3.2:latest" }, { "name": "qwen3:8b" }] });
+        assert_eq!(model_names(&tags), ["llama3.2:latest", "qwen3:8b"]);
+    }
+
+    #[test]
+    fn test_error_message() {
+        let nested = r#"{"error": {"message": "model not found"}}"#;
+        assert_eq!(error_message(nested), "model not found");
+        let flat = r#"{"error": "out of memory"}"#;
+        assert_eq!(error_message(flat), "out of memory");
+        assert_eq!(error_message("502 Bad Gateway\n"), "502 Bad Gateway");
+        assert_eq!(error_message(""), "Unknown error");
+    }
+}
@@ -0,0 +1,14 @@
+use silane_openrouter::OpenRouterError;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum OllamaError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("API error: {status} - {message}")]
+    Api { status: u16, message: String },
+
+    #[error(transparent)]
+    Convert(#[from] OpenRouterError),
+}
@@ -0,0 +1,11 @@
+//! Ollama client for the polyepoxide ecosystem.
+//!
+//! Runs completions against a local Ollama server, so that conversations
+//! never leave the machine. Requests and responses go through Ollama's
+//! OpenAI-compatible API, reusing the OpenRouter conversions.
+
+mod client;
+mod error;
+
+pub use client::OllamaClient;
+pub use error::OllamaError;
@@ -129,6 +129,7 @@ mod tests {
             params: None,
             tools: vec![],
             tool_choice: None,
+            context: None,
         };
 
         let result = client.complete_with_solvent(&request, &mut solvent).await;
@@ -0,0 +1,366 @@
+//! Choosing which messages of a conversation fit a model's context window.
+//!
+//! Every policy keeps the system prompts and the latest message, and never
+//! starts the selection with tool results cut off from the call they answer.
+
+use polyepoxide_core::{oxide, Bond, Cid};
+use polyepoxide_llm::{ContentBlock, Message, MessageContent};
+
+use crate::error::OpenRouterError;
+use crate::provider::CompletionProvider;
+use crate::types::OpenRouterRequest;
+
+/// Characters assumed per token when estimating.
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Tokens of role markers and separators around each message.
+const MESSAGE_OVERHEAD: u64 = 4;
+
+/// Characters assumed for an image or a chunked file, whose size isn't
+/// known without loading it.
+const ATTACHMENT_CHARS: usize = 4096;
+
+const SUMMARY_PROMPT: &str = "Summarize the conversation so far for your own later \
+reference. Keep facts, decisions, open questions and anything the user asked to remember. \
+Reply with the summary only.";
+
+const SUMMARY_PREFIX: &str = "Summary of the earlier conversation:\n\n";
+
+/// Which messages are sent with a request.
+#[oxide]
+pub enum ContextPolicy {
+    /// The latest `messages` messages.
+    SlidingWindow { messages: u32 },
+    /// The latest messages whose estimated tokens fit `max_tokens`.
+    TokenBudget { max_tokens: u32 },
+    /// A summary made by [`summarize`] in place of the messages it covers,
+    /// followed by the later ones, of which at most the latest `messages`
+    /// or those fitting `max_tokens` with the summary are sent.
+    Summarized {
+        summary: Bond<Message>,
+        messages: Option<u32>,
+        max_tokens: Option<u32>,
+    },
+}
+
+impl ContextPolicy {
+    /// Sends `summary` in place of the messages it covers, keeping the
+    /// limit `policy` puts on the later ones.
+    pub fn summarized(summary: Bond<Message>, policy: Option<&ContextPolicy>) -> Self {
+        let (messages, max_tokens) = match policy {
+            Some(ContextPolicy::SlidingWindow { messages }) => (Some(*messages), None),
+            Some(ContextPolicy::TokenBudget { max_tokens }) => (None, Some(*max_tokens)),
+            Some(ContextPolicy::Summarized {
+                messages,
+                max_tokens,
+                ..
+            }) => (*messages, *max_tokens),
+            None => (None, None),
+        };
+        ContextPolicy::Summarized {
+            summary,
+            messages,
+            max_tokens,
+        }
+    }
+
+    /// Selects messages of the chain ending at `head`, oldest first.
+    pub fn select<'a>(
+        &'a self,
+        head: &'a Bond<Message>,
+    ) -> Result<Vec<&'a Message>, OpenRouterError> {
+        let chain = chain(head)?;
+        let mut summary = None;
+        let mut start = 0;
+        let (messages, max_tokens) = match self {
+            ContextPolicy::SlidingWindow { messages } => (Some(*messages), None),
+            ContextPolicy::TokenBudget { max_tokens } => (None, Some(*max_tokens)),
+            ContextPolicy::Summarized {
+                summary: bond,
+                messages,
+                max_tokens,
+            } => {
+                let message = bond
+                    .value()
+                    .ok_or_else(|| OpenRouterError::UnresolvedBond(bond.cid()))?;
+                let covered = message.previous.as_ref().map(|p| p.cid());
+                // A summary of another branch doesn't apply
+                if let Some(last) = chain.iter().position(|(cid, _)| Some(*cid) == covered) {
+                    summary = Some(message);
+                    start = last + 1;
+                }
+                (*messages, *max_tokens)
+            }
+        };
+
+        if let Some(messages) = messages {
+            start = start.max(chain.len().saturating_sub(messages as usize));
+        }
+        if let Some(max_tokens) = max_tokens {
+            let mut used: u64 = chain
+                .iter()
+                .map(|(_, m)| m)
+                .filter(|m| is_system(m))
+                .chain(summary.as_ref())
+                .map(|m| estimate_tokens(m))
+                .sum();
+            let mut fitting = chain.len();
+            for (i, (_, message)) in chain.iter().enumerate().skip(start).rev() {
+                if !is_system(message) {
+                    used += estimate_tokens(message);
+                }
+                if used > max_tokens as u64 && i + 1 < chain.len() {
+                    break;
+                }
+                fitting = i;
+            }
+            start = fitting;
+        }
+
+        let mut start = start.min(chain.len().saturating_sub(1));
+        while start > 0 && matches!(chain[start].1.content, MessageContent::ToolResult { .. }) {
+            start -= 1;
+        }
+        let (dropped, kept) = chain.split_at(start);
+        Ok(dropped
+            .iter()
+            .map(|(_, m)| *m)
+            .filter(|m| is_system(m))
+            .chain(summary)
+            .chain(kept.iter().map(|(_, m)| *m))
+            .collect())
+    }
+}
+
+/// Asks `model` to summarize the conversation ending at `upto`, as sent
+/// under `context`; with an earlier summary, only it and the messages after
+/// it are sent.
+///
+/// Returns a system message following `upto`, to be stored and used with
+/// `ContextPolicy::summarized`.
+pub async fn summarize<P: CompletionProvider>(
+    provider: &P,
+    model: &str,
+    upto: Bond<Message>,
+    context: Option<ContextPolicy>,
+) -> Result<Message, P::Error> {
+    let prompt = Message {
+        content: MessageContent::User(vec![ContentBlock::Text(SUMMARY_PROMPT.to_string())]),
+        metadata: None,
+        previous: Some(upto.clone()),
+    };
+    let request = OpenRouterRequest {
+        model: model.to_string(),
+        conversation_head: Bond::new(prompt),
+        params: None,
+        tools: vec![],
+        tool_choice: None,
+        context,
+    };
+    let reply = provider.complete(&request).await?;
+
+    let mut text = SUMMARY_PREFIX.to_string();
+    if let MessageContent::Assistant { blocks, .. } = &reply.content {
+        for block in blocks {
+            if let ContentBlock::Text(t) = block {
+                text.push_str(t);
+            }
+        }
+    }
+    Ok(Message {
+        content: MessageContent::System(vec![ContentBlock::Text(text)]),
+        metadata: reply.metadata,
+        previous: Some(upto),
+    })
+}
+
+/// Roughly estimates the tokens a message takes in a request.
+pub fn estimate_tokens(message: &Message) -> u64 {
+    let chars: usize = match &message.content {
+        MessageContent::System(blocks) | MessageContent::User(blocks) => {
+            blocks.iter().map(block_chars).sum()
+        }
+        MessageContent::Assistant { blocks, tool_calls } => {
+            blocks.iter().map(block_chars).sum::<usize>()
+                + tool_calls
+                    .iter()
+                    .map(|c| c.name.len() + c.arguments.len())
+                    .sum::<usize>()
+        }
+        MessageContent::ToolResult { result, .. } => result.len(),
+    };
+    MESSAGE_OVERHEAD + chars.div_ceil(CHARS_PER_TOKEN) as u64
+}
+
+fn block_chars(block: &ContentBlock) -> usize {
+    match block {
+        ContentBlock::Text(text) | ContentBlock::Thinking(text) => text.len(),
+        ContentBlock::Code { code, .. } => code.len(),
+        ContentBlock::File { data, .. } => data.0.len(),
+        ContentBlock::Image(_) | ContentBlock::ChunkedFile { .. } => ATTACHMENT_CHARS,
+    }
+}
+
+fn is_system(message: &Message) -> bool {
+    matches!(message.content, MessageContent::System(_))
+}
+
+/// The messages ending at `head` with their CIDs, oldest first.
+fn chain(head: &Bond<Message>) -> Result<Vec<(Cid, &Message)>, OpenRouterError> {
+    let mut messages = Vec::new();
+    let mut current = Some(head);
+    while let Some(bond) = current {
+        let message = bond
+            .value()
+            .ok_or_else(|| OpenRouterError::UnresolvedBond(bond.cid()))?;
+        messages.push((bond.cid(), message));
+        current = message.previous.as_ref();
+    }
+    messages.reverse();
+    Ok(messages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use polyepoxide_llm::ToolCall;
+
+    use crate::convert::collect_messages;
+
+    fn text(text: &str) -> Vec<ContentBlock> {
+        vec![ContentBlock::Text(text.to_string())]
+    }
+
+    fn push(head: &mut Option<Bond<Message>>, content: MessageContent) {
+        let previous = head.take();
+        *head = Some(Bond::new(Message {
+            content,
+            metadata: None,
+            previous,
+        }));
+    }
+
+    fn texts(messages: &[&Message]) -> Vec<String> {
+        messages
+            .iter()
+            .map(|m| match &m.content {
+                MessageContent::System(b) | MessageContent::User(b) => match &b[0] {
+                    ContentBlock::Text(t) => t.clone(),
+                    _ => unreachable!(),
+                },
+                MessageContent::Assistant { .. } => "call".to_string(),
+                MessageContent::ToolResult { result, .. } => result.clone(),
+            })
+            .collect()
+    }
+
+    /// A system prompt, a long question, and a tool call with its result.
+    fn conversation() -> Bond<Message> {
+        let mut head = None;
+        push(&mut head, MessageContent::System(text("be brief")));
+        push(&mut head, MessageContent::User(text(&"x".repeat(400))));
+        push(
+            &mut head,
+            MessageContent::Assistant {
+                blocks: vec![],
+                tool_calls: vec![ToolCall {
+                    id: "1".to_string(),
+                    name: "shell".to_string(),
+                    arguments: "{}".to_string(),
+                }],
+            },
+        );
+        push(
+            &mut head,
+            MessageContent::ToolResult {
+                tool_call_id: "1".to_string(),
+                result: "ok".to_string(),
+                is_error: false,
+            },
+        );
+        head.unwrap()
+    }
+
+    #[test]
+    fn policies_keep_system_prompts_and_tool_calls() {
+        let head = conversation();
+
+        let window = ContextPolicy::SlidingWindow { messages: 1 };
+        let selected = window.select(&head).unwrap();
+        assert_eq!(texts(&selected), ["be brief", "call", "ok"]);
+
+        let budget = ContextPolicy::TokenBudget { max_tokens: 50 };
+        assert_eq!(
+            texts(&budget.select(&head).unwrap()),
+            ["be brief", "call", "ok"]
+        );
+        let budget = ContextPolicy::TokenBudget { max_tokens: 200 };
+        assert_eq!(budget.select(&head).unwrap().len(), 4);
+
+        let call = head.value().unwrap().previous.as_ref().unwrap();
+        let question = call.value().unwrap().previous.clone().unwrap();
+        let summary = Bond::new(Message {
+            content: MessageContent::System(text("asked about x")),
+            metadata: None,
+            previous: Some(question),
+        });
+        let summarized = ContextPolicy::summarized(summary, None);
+        assert_eq!(
+            texts(&summarized.select(&head).unwrap()),
+            ["be brief", "asked about x", "call", "ok"]
+        );
+    }
+
+    #[test]
+    fn summary_keeps_limit_of_policy() {
+        let head = conversation();
+        let mut prompt = head.clone();
+        while let Some(previous) = prompt.value().unwrap().previous.clone() {
+            prompt = previous;
+        }
+        let summary = Bond::new(Message {
+            content: MessageContent::System(text("started")),
+            metadata: None,
+            previous: Some(prompt),
+        });
+
+        let unlimited = ContextPolicy::summarized(summary.clone(), None);
+        assert_eq!(unlimited.select(&head).unwrap().len(), 5);
+
+        let budget = ContextPolicy::TokenBudget { max_tokens: 50 };
+        let summarized = ContextPolicy::summarized(summary, Some(&budget));
+        assert_eq!(
+            texts(&summarized.select(&head).unwrap()),
+            ["be brief", "started", "call", "ok"]
+        );
+    }
+
+    struct Echo;
+
+    impl CompletionProvider for Echo {
+        type Error = OpenRouterError;
+
+        async fn complete(&self, request: &OpenRouterRequest) -> Result<Message, OpenRouterError> {
+            let messages = collect_messages(&request.conversation_head)?;
+            Ok(Message {
+                content: MessageContent::Assistant {
+                    blocks: text(&format!("{} messages", messages.len())),
+                    tool_calls: vec![],
+                },
+                metadata: None,
+                previous: Some(request.conversation_head.clone()),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn summary_follows_summarized_message() {
+        let head = conversation();
+        let summary = summarize(&Echo, "model", head.clone(), None).await.unwrap();
+        assert_eq!(summary.previous.unwrap().cid(), head.cid());
+        assert_eq!(
+            texts(&[&summary]),
+            [format!("{}5 messages", SUMMARY_PREFIX)]
+        );
+    }
+}
@@ -42,13 +42,7 @@ fn content_block_to_json(block: &ContentBlock) -> Value {
             })
         }
         ContentBlock::Image(ImageData::Embedded { media_type, data }) => {
-            let b64 = base64::engine::general_purpose::STANDARD.encode(data.as_bytes());
-            json!({
-                "type": "image_url",
-                "image_url": {
-                    "url": format!("data:{};base64,{}", media_type, b64)
-                }
-            })
+            embedded_image_to_json(media_type, data.as_bytes())
         }
         ContentBlock::Code { language, code } => {
             let lang = language.as_deref().unwrap_or("");
@@ -61,23 +55,21 @@ fn content_block_to_json(block: &ContentBlock) -> Value {
             name,
             mime_type,
             data,
-        } => {
-            // Represent file as text if possible
-            if let Ok(text) = std::str::from_utf8(data.as_bytes()) {
-                let mime = mime_type.as_deref().unwrap_or("text/plain");
-                json!({
-                    "type": "text",
-                    "text": format!("[File: {} ({})]:\n{}", name, mime, text)
-                })
-            } else {
-                let b64 = base64::engine::general_purpose::STANDARD.encode(data.as_bytes());
-                let mime = mime_type.as_deref().unwrap_or("application/octet-stream");
-                json!({
-                    "type": "text",
-                    "text": format!("[Binary file: {} ({})]: base64:{}", name, mime, b64)
-                })
+        } => file_to_json(name, mime_type.as_deref(), data.as_bytes()),
+        ContentBlock::ChunkedFile {
+            name,
+            mime_type,
+            data,
+        } => match data.value().and_then(|blob| blob.to_vec()) {
+            Some(bytes) if mime_type.starts_with("image/") => {
+                embedded_image_to_json(mime_type, &bytes)
             }
-        }
+            Some(bytes) => file_to_json(name, Some(mime_type), &bytes),
+            None => json!({
+                "type": "text",
+                "text": format!("[File: {} ({})]: not loaded", name, mime_type)
+            }),
+        },
         ContentBlock::Thinking(text) => json!({
             "type": "text",
             "text": format!("<thinking>\n{}\n</thinking>", text)
@@ -85,6 +77,34 @@ fn content_block_to_json(block: &ContentBlock) -> Value {
     }
 }
 
+fn embedded_image_to_json(media_type: &str, data: &[u8]) -> Value {
+    let b64 = base64::engine::general_purpose::STANDARD.encode(data);
+    json!({
+        "type": "image_url",
+        "image_url": {
+            "url": format!("data:{};base64,{}", media_type, b64)
+        }
+    })
+}
+
+fn file_to_json(name: &str, mime_type: Option<&str>, data: &[u8]) -> Value {
+    // Represent file as text if possible
+    if let Ok(text) = std::str::from_utf8(data) {
+        let mime = mime_type.unwrap_or("text/plain");
+        json!({
+            "type": "text",
+            "text": format!("[File: {} ({})]:\n{}", name, mime, text)
+        })
+    } else {
+        let b64 = base64::engine::general_purpose::STANDARD.encode(data);
+        let mime = mime_type.unwrap_or("application/octet-stream");
+        json!({
+            "type": "text",
+            "text": format!("[Binary file: {} ({})]: base64:{}", name, mime, b64)
+        })
+    }
+}
+
 /// Converts a ToolCall to OpenRouter JSON format.
 fn tool_call_to_json(call: &ToolCall) -> Value {
     json!({
@@ -175,7 +195,10 @@ fn tool_choice_to_json(choice: &ToolChoice) -> Value {
 
 /// Builds the full OpenRouter API request body.
 pub fn build_request_body(request: &OpenRouterRequest) -> Result<Value, OpenRouterError> {
-    let messages = collect_messages(&request.conversation_head)?;
+    let messages = match &request.context {
+        Some(policy) => policy.select(&request.conversation_head)?,
+        None => collect_messages(&request.conversation_head)?,
+    };
     let messages_json: Vec<Value> = messages.iter().map(|m| message_to_json(m)).collect();
 
     let mut body = json!({
@@ -33,6 +33,7 @@
 //!         params: None,
 //!         tools: vec![],
 //!         tool_choice: None,
+//!         context: None,
 //!     };
 //!
 //!     let response = client.complete_with_solvent(&request, &mut solvent).await.unwrap();
@@ -40,11 +41,15 @@
 //! ```
 
 mod client;
+mod context;
 mod convert;
 mod error;
+mod provider;
 mod types;
 
 pub use client::OpenRouterClient;
+pub use context::{estimate_tokens, summarize, ContextPolicy};
 pub use convert::{build_request_body, collect_messages, parse_response};
 pub use error::OpenRouterError;
+pub use provider::CompletionProvider;
 pub use types::{OpenRouterRequest, ToolChoice, ToolDefinition};
@@ -0,0 +1,34 @@
+use std::future::Future;
+
+use polyepoxide_llm::Message;
+
+use crate::client::OpenRouterClient;
+use crate::error::OpenRouterError;
+use crate::types::OpenRouterRequest;
+
+/// A service that continues conversations.
+///
+/// Requests use the OpenRouter request type, which other providers translate
+/// as needed.
+pub trait CompletionProvider {
+    type Error: std::error::Error + Send + Sync + 'static;
+
+    /// Executes a completion request.
+    ///
+    /// Returns an assistant Message with `previous` pointing to the conversation head.
+    fn complete(
+        &self,
+        request: &OpenRouterRequest,
+    ) -> impl Future<Output = Result<Message, Self::Error>> + Send;
+}
+
+impl CompletionProvider for OpenRouterClient {
+    type Error = OpenRouterError;
+
+    fn complete(
+        &self,
+        request: &OpenRouterRequest,
+    ) -> impl Future<Output = Result<Message, OpenRouterError>> + Send {
+        OpenRouterClient::complete(self, request)
+    }
+}
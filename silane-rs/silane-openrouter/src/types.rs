@@ -1,6 +1,8 @@
 use polyepoxide_core::{oxide, Bond};
 use polyepoxide_llm::{GenerationParams, Message};
 
+use crate::context::ContextPolicy;
+
 /// Definition of a tool that can be used by the model.
 #[oxide]
 pub struct ToolDefinition {
@@ -31,4 +33,6 @@ pub struct OpenRouterRequest {
     pub tools: Vec<ToolDefinition>,
     /// Tool choice strategy.
     pub tool_choice: Option<ToolChoice>,
+    /// Messages to send; the whole conversation if not given.
+    pub context: Option<ContextPolicy>,
 }
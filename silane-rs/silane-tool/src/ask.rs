@@ -0,0 +1,87 @@
+//! One-shot questions from the command line, stored like chat messages.
+
+use std::io::Read;
+use std::sync::Arc;
+
+use cid::Cid;
+use polyepoxide_core::{Bond, RefStore};
+use polyepoxide_llm::{ContentBlock, Message, MessageContent};
+use silane_openrouter::{CompletionProvider, OpenRouterRequest};
+
+use crate::chat::AnyClient;
+use crate::config::load_config;
+use crate::conversations::auto_name;
+use crate::error::SihError;
+use crate::store::{AppContext, conversation_ref};
+use crate::usage;
+
+/// Sends `question`, or stdin if not given, as the next message after
+/// `continue_from`. Prints the reply's text to stdout and the new head CID
+/// to stderr, and records both messages under `name`, or a name made up
+/// like in `sih chat`.
+pub async fn run(
+    mut ctx: AppContext,
+    client: AnyClient,
+    model: Option<String>,
+    question: Option<String>,
+    continue_from: Option<Cid>,
+    name: Option<String>,
+) -> Result<(), SihError> {
+    let question = match question {
+        Some(question) => question,
+        None => {
+            let mut question = String::new();
+            std::io::stdin().read_to_string(&mut question)?;
+            question
+        }
+    };
+    let model = match model {
+        Some(model) => model,
+        None => client
+            .models()
+            .await?
+            .into_iter()
+            .next()
+            .ok_or(SihError::NoModels)?,
+    };
+    let previous = continue_from
+        .map(|cid| ctx.load_conversation(&cid).map(Bond::from_cell))
+        .transpose()?;
+
+    let prompt = ctx.solvent.add(Message {
+        content: MessageContent::User(vec![ContentBlock::Text(question)]),
+        metadata: None,
+        previous,
+    });
+    ctx.persist_message(&prompt)?;
+    let config = load_config();
+    let request = OpenRouterRequest {
+        model,
+        conversation_head: Bond::from_cell(Arc::clone(&prompt)),
+        params: None,
+        tools: Vec::new(),
+        tool_choice: None,
+        context: config.context_policy(),
+    };
+    let reply = ctx.solvent.add(client.complete(&request).await?);
+    ctx.persist_message(&reply)?;
+
+    let name = name.unwrap_or_else(|| auto_name(&prompt.cid()));
+    ctx.store.set_ref(&conversation_ref(&name), &reply.cid())?;
+    let ledger = usage::ledger(&mut ctx, Some(&name), &reply, &config.pricing)?;
+    usage::record(&ctx, &name, &ledger)?;
+
+    if let MessageContent::Assistant { blocks, .. } = &reply.value().content {
+        for block in blocks {
+            match block {
+                ContentBlock::Text(text) => println!("{}", text),
+                ContentBlock::Code { language, code } => {
+                    println!("```{}\n{}\n```", language.as_deref().unwrap_or(""), code)
+                }
+                _ => {}
+            }
+        }
+    }
+    eprintln!("{}", reply.cid());
+    Ok(())
+}
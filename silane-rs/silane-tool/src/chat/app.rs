@@ -1,129 +1,229 @@
 use cid::Cid;
-use polyepoxide_core::{Bond, Cell, Oxide, Solvent, Store};
-use polyepoxide_llm::{ContentBlock, GenerationParams, Message, MessageContent};
-use silane_openrouter::{OpenRouterClient, OpenRouterError, OpenRouterRequest};
+use polyepoxide_core::json::{load_schema, to_json, to_json_untyped};
+use polyepoxide_core::{Bond, Cell, IngestPolicy, RefStore, Solvent};
+use polyepoxide_llm::{
+    ContentBlock, CostLedger, GenerationParams, Message, MessageContent, ModelPricing, ToolCall,
+};
+use silane_openrouter::{CompletionProvider, ContextPolicy, OpenRouterRequest, summarize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::str::FromStr;
 use std::sync::Arc;
-use tokio::sync::{oneshot, Mutex};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::AbortHandle;
 
+use super::command::{self, SlashCommand};
+use super::provider::{AnyClient, AnyClientError};
+use crate::config::load_config;
+use crate::conversations::{self, Conversation};
 use crate::error::SihError;
-use crate::store::{AnyStore, AppContext};
-
-const AVAILABLE_MODELS: &[&str] = &[
-    "openai/gpt-4o",
-    "openai/gpt-4o-mini",
-    "openai/o1",
-    "openai/o1-mini",
-    "anthropic/claude-3.5-sonnet",
-    "anthropic/claude-3-haiku",
-    "google/gemini-2.0-flash-001",
-    "google/gemini-2.0-flash-thinking-exp:free",
-    "deepseek/deepseek-r1",
-    "deepseek/deepseek-chat",
-];
+use crate::store::{AppContext, conversation_ref, summary_ref};
+use crate::tools::ToolRegistry;
+use crate::usage;
 
 const REASONING_OPTIONS: &[Option<&str>] = &[None, Some("low"), Some("medium"), Some("high")];
 
+/// Model replies in a row that may call tools before the loop is stopped.
+const MAX_TOOL_ROUNDS: usize = 16;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AppMode {
     Chat,
     SelectModel,
     SelectReasoning,
+    SelectConversation,
+    SelectMessage,
+    /// Waiting for the user to approve the calls in `pending_tools`.
+    ConfirmTools,
     Loading,
 }
 
+/// Replies generated for one user message with `/regenerate`.
+pub struct Generations {
+    /// The user message replied to.
+    pub prompt: Cid,
+    /// The head each reply led to. The current one's is brought up to date
+    /// when flipping away from it.
+    pub heads: Vec<Arc<Cell<Message>>>,
+    pub current: usize,
+}
+
+/// Replies of several models to one prompt, shown side by side.
+pub struct Comparison {
+    pub prompt: Arc<Cell<Message>>,
+    /// Each model with its stored reply, or the error asking it, once known.
+    pub replies: Vec<(String, Option<Result<Arc<Cell<Message>>, String>>)>,
+}
+
 pub struct ChatApp {
     pub mode: AppMode,
     pub should_quit: bool,
-    pub store: AnyStore,
-    pub solvent: Solvent,
+    pub ctx: AppContext,
     pub conversation_head: Option<Arc<Cell<Message>>>,
+    /// Name the conversation is recorded under, once known.
+    pub name: Option<String>,
     pub input: String,
     pub cursor_pos: usize,
     pub model: String,
+    /// Models offered by the model picker.
+    pub models: Vec<String>,
     pub reasoning_effort: Option<String>,
     pub messages_scroll: u16,
-    pub client: Arc<Mutex<OpenRouterClient>>,
-    pub response_rx: Option<oneshot::Receiver<Result<Message, OpenRouterError>>>,
+    pub client: Arc<AnyClient>,
+    pub response_rx: Option<oneshot::Receiver<Result<Message, AnyClientError>>>,
+    /// Summary being made by `/summarize`.
+    pub summary_rx: Option<oneshot::Receiver<Result<Message, AnyClientError>>>,
+    /// Messages sent with each request; all of them if not set.
+    pub context: Option<ContextPolicy>,
+    pub tools: Arc<ToolRegistry>,
+    /// Results of the tool calls being run, in call order.
+    pub tool_rx: Option<oneshot::Receiver<Vec<MessageContent>>>,
+    /// Task running the tool calls; aborting it kills their processes.
+    pub tool_task: Option<AbortHandle>,
+    /// Calls of the model's last reply awaiting the user's approval.
+    pub pending_tools: Vec<ToolCall>,
+    /// Rounds of tool calls since the user's last message.
+    pub tool_rounds: usize,
     pub last_error: Option<String>,
+    /// Blocks added with `/include`, sent with the next message.
+    pub included: Vec<ContentBlock>,
+    /// Earlier user message being edited; the next message replaces it on a
+    /// new branch.
+    pub editing: Option<Arc<Cell<Message>>>,
+    /// Messages sent this session that started a new branch.
+    pub branched: HashSet<Cid>,
+    pub generations: Option<Generations>,
+    /// Models each prompt is sent to at once with `/compare`; empty when
+    /// not comparing.
+    pub compare_models: Vec<String>,
+    /// Replies to the last prompt sent while comparing.
+    pub comparison: Option<Comparison>,
+    /// Replies of compared models as they arrive, by index into
+    /// `compare_models`.
+    pub compare_rx: Option<mpsc::UnboundedReceiver<(usize, Result<Message, AnyClientError>)>>,
+    /// Tasks asking the compared models.
+    pub compare_tasks: Vec<AbortHandle>,
+    /// Index into `get_messages` of the message selected for editing.
+    pub selected_message: usize,
+    /// Usage and cost of the current branch.
+    pub usage: Option<Arc<Cell<CostLedger>>>,
+    pub pricing: HashMap<String, ModelPricing>,
+    /// Screening of files added with `/attach`.
+    pub ingest: IngestPolicy,
 
     // Popup state
     pub popup_selected: usize,
+    /// Conversations listed by the conversation picker.
+    pub conversations: Vec<Conversation>,
 }
 
 impl ChatApp {
     pub fn new(
         mut ctx: AppContext,
-        client: OpenRouterClient,
+        client: AnyClient,
         model: String,
         reasoning_effort: Option<String>,
         continue_from: Option<Cid>,
+        name: Option<String>,
+        tools: ToolRegistry,
     ) -> Result<Self, SihError> {
         let conversation_head = if let Some(cid) = continue_from {
-            Some(Self::load_conversation(&mut ctx.solvent, &ctx.store, &cid)?)
+            Some(ctx.load_conversation(&cid)?)
         } else {
             None
         };
+        if let (Some(name), Some(head)) = (&name, &conversation_head) {
+            ctx.store.set_ref(&conversation_ref(name), &head.cid())?;
+        }
+        let config = load_config();
+        let context = context_policy(&mut ctx, name.as_deref(), config.context_policy());
+        let ingest = config.ingest_policy();
+        let pricing = config.pricing;
+        let usage = conversation_head
+            .as_ref()
+            .map(|head| usage::ledger(&mut ctx, name.as_deref(), head, &pricing))
+            .transpose()?;
 
         Ok(Self {
             mode: AppMode::Chat,
             should_quit: false,
-            store: ctx.store,
-            solvent: ctx.solvent,
+            ctx,
             conversation_head,
+            name,
             input: String::new(),
             cursor_pos: 0,
+            models: vec![model.clone()],
             model,
             reasoning_effort,
             messages_scroll: 0,
-            client: Arc::new(Mutex::new(client)),
+            client: Arc::new(client),
             response_rx: None,
+            summary_rx: None,
+            context,
+            tools: Arc::new(tools),
+            tool_rx: None,
+            tool_task: None,
+            pending_tools: Vec::new(),
+            tool_rounds: 0,
             last_error: None,
+            included: Vec::new(),
+            editing: None,
+            branched: HashSet::new(),
+            generations: None,
+            compare_models: Vec::new(),
+            comparison: None,
+            compare_rx: None,
+            compare_tasks: Vec::new(),
+            selected_message: 0,
+            usage,
+            pricing,
+            ingest,
             popup_selected: 0,
+            conversations: Vec::new(),
         })
     }
 
-    fn load_conversation(
-        solvent: &mut Solvent,
-        store: &AnyStore,
-        cid: &Cid,
-    ) -> Result<Arc<Cell<Message>>, SihError> {
-        Self::load_message_recursive(solvent, store, cid)
-    }
-
-    fn load_message_recursive(
-        solvent: &mut Solvent,
-        store: &AnyStore,
-        cid: &Cid,
-    ) -> Result<Arc<Cell<Message>>, SihError> {
-        // Check if already loaded
-        if let Some(cell) = solvent.get::<Message>(cid) {
-            return Ok(cell);
-        }
-
-        // Load from store
-        let bytes = store
-            .get(cid)?
-            .ok_or_else(|| SihError::MessageNotFound(*cid))?;
-
-        let message: Message =
-            Oxide::from_bytes(&bytes).map_err(|e| SihError::DecodeError(e.to_string()))?;
-
-        // Recursively load previous messages first (to ensure they're in solvent)
-        if let Some(ref prev_bond) = message.previous {
-            Self::load_message_recursive(solvent, store, &prev_bond.cid())?;
-        }
-
-        // Now add this message to solvent (previous bonds will be resolved)
-        Ok(solvent.add(message))
+    /// Persists a message and records it as the head of the conversation,
+    /// along with the conversation's usage up to it. Replies generated next
+    /// to the branch's own are counted in the usage too.
+    fn persist_message(&mut self, cell: &Arc<Cell<Message>>) -> Result<(), SihError> {
+        self.ctx.persist_message(cell)?;
+        let name = self
+            .name
+            .get_or_insert_with(|| conversations::auto_name(&cell.cid()));
+        self.ctx
+            .store
+            .set_ref(&conversation_ref(name), &cell.cid())?;
+        let siblings = self.generated_replies();
+        let previous = self.usage.as_ref().map(|ledger| ledger.value());
+        let ledger = usage::extend(&mut self.ctx, previous, cell, &siblings, &self.pricing);
+        usage::record(&self.ctx, name, &ledger)?;
+        self.usage = Some(ledger);
+        Ok(())
     }
 
-    fn persist_message(&self, cell: &Cell<Message>) -> Result<(), SihError> {
-        self.solvent.persist_cell(cell, &self.store)?;
-        Ok(())
+    /// The replies to the prompt of `generations`, whichever heads they
+    /// have led to since.
+    fn generated_replies(&self) -> Vec<Bond<Message>> {
+        let Some(generations) = &self.generations else {
+            return Vec::new();
+        };
+        let reply = |head: &Arc<Cell<Message>>| {
+            let mut current = Bond::from_cell(Arc::clone(head));
+            loop {
+                let previous = current.value()?.previous.clone()?;
+                if previous.cid() == generations.prompt {
+                    return Some(current);
+                }
+                current = previous;
+            }
+        };
+        generations.heads.iter().filter_map(reply).collect()
     }
 
-    pub fn available_models() -> &'static [&'static str] {
-        AVAILABLE_MODELS
+    pub fn with_models(mut self, models: Vec<String>) -> Self {
+        self.models = models;
+        self
     }
 
     pub fn reasoning_options() -> &'static [Option<&'static str>] {
@@ -132,17 +232,41 @@ impl ChatApp {
 
     pub fn send_message(&mut self) {
         let text = self.input.trim().to_string();
-        if text.is_empty() {
+        if let Some(command) = text.strip_prefix('/') {
+            self.last_error = self.run_command(command).err();
+            self.input.clear();
+            self.cursor_pos = 0;
+            return;
+        }
+        if text.is_empty() && self.included.is_empty() {
             return;
         }
 
         // Create user message
+        let mut blocks = std::mem::take(&mut self.included);
+        if !text.is_empty() {
+            blocks.push(ContentBlock::Text(text));
+        }
+        let edited = self.editing.take();
+        let previous = match &edited {
+            Some(edited) => edited.value().previous.clone(),
+            None => self
+                .conversation_head
+                .as_ref()
+                .map(|c| Bond::from_cell(Arc::clone(c))),
+        };
         let user_msg = Message {
-            content: MessageContent::User(vec![ContentBlock::Text(text)]),
+            content: MessageContent::User(blocks),
             metadata: None,
-            previous: self.conversation_head.as_ref().map(|c| Bond::from_cell(Arc::clone(c))),
+            previous,
         };
-        let user_cell = self.solvent.add(user_msg);
+        let user_cell = self.ctx.solvent.add(user_msg);
+        if edited.is_some() {
+            // The conversation's ref keeps the original branch
+            self.name = None;
+            self.branched.insert(user_cell.cid());
+            self.generations = None;
+        }
 
         // Persist user message to store
         if let Err(e) = self.persist_message(&user_cell) {
@@ -156,35 +280,23 @@ impl ChatApp {
         self.input.clear();
         self.cursor_pos = 0;
 
-        // Prepare request
-        let request = OpenRouterRequest {
-            model: self.model.clone(),
-            conversation_head: Bond::from_cell(Arc::clone(&user_cell)),
-            params: self.reasoning_effort.as_ref().map(|effort| GenerationParams {
-                temperature: None,
-                top_p: None,
-                top_k: None,
-                max_tokens: None,
-                frequency_penalty: None,
-                presence_penalty: None,
-                stop: None,
-                min_p: None,
-                top_a: None,
-                repetition_penalty: None,
-                seed: None,
-                reasoning_effort: Some(effort.clone()),
-                reasoning_max_tokens: None,
-            }),
-            tools: vec![],
-            tool_choice: None,
-        };
+        self.tool_rounds = 0;
+        self.comparison = None;
+        match self.compare_models.is_empty() {
+            true => self.request_completion(user_cell),
+            false => self.request_comparison(user_cell),
+        }
+    }
+
+    /// Asks the model to continue the conversation from `head`.
+    fn request_completion(&mut self, head: Arc<Cell<Message>>) {
+        let request = self.request(self.model.clone(), head);
 
         // Spawn async task
         let (tx, rx) = oneshot::channel();
         let client = Arc::clone(&self.client);
 
         tokio::spawn(async move {
-            let client = client.lock().await;
             let result = client.complete(&request).await;
             let _ = tx.send(result);
         });
@@ -194,11 +306,322 @@ impl ChatApp {
         self.last_error = None;
     }
 
+    /// Sends the conversation up to `prompt` to every compared model at
+    /// once. Tools aren't offered, as each reply would need its own rounds
+    /// of calls.
+    fn request_comparison(&mut self, prompt: Arc<Cell<Message>>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut tasks = Vec::new();
+        for (index, model) in self.compare_models.iter().enumerate() {
+            let request = OpenRouterRequest {
+                tools: Vec::new(),
+                ..self.request(model.clone(), Arc::clone(&prompt))
+            };
+            let client = Arc::clone(&self.client);
+            let tx = tx.clone();
+            let task = tokio::spawn(async move {
+                let _ = tx.send((index, client.complete(&request).await));
+            });
+            tasks.push(task.abort_handle());
+        }
+
+        let replies = self.compare_models.iter().map(|m| (m.clone(), None));
+        self.comparison = Some(Comparison {
+            prompt,
+            replies: replies.collect(),
+        });
+        self.compare_rx = Some(rx);
+        self.compare_tasks = tasks;
+        self.mode = AppMode::Loading;
+        self.last_error = None;
+    }
+
+    /// Stores replies of compared models as they arrive. Once all are in,
+    /// the first becomes the conversation's head, and the others can be
+    /// flipped to like regenerated replies.
+    pub fn poll_comparison(&mut self) {
+        let Some(ref mut rx) = self.compare_rx else {
+            return;
+        };
+        let mut received = Vec::new();
+        // The channel closes once every request has finished
+        let finished = loop {
+            match rx.try_recv() {
+                Ok(reply) => received.push(reply),
+                Err(mpsc::error::TryRecvError::Empty) => break false,
+                Err(mpsc::error::TryRecvError::Disconnected) => break true,
+            }
+        };
+        for (index, result) in received {
+            let reply = result.map_err(|e| e.to_string()).and_then(|message| {
+                let cell = self.ctx.solvent.add(message);
+                self.ctx
+                    .persist_message(&cell)
+                    .map_err(|e| format!("Failed to persist reply: {}", e))?;
+                Ok(cell)
+            });
+            if let Some(comparison) = &mut self.comparison {
+                comparison.replies[index].1 = Some(reply);
+            }
+        }
+        if !finished {
+            return;
+        }
+        self.compare_rx = None;
+        self.compare_tasks.clear();
+        self.mode = AppMode::Chat;
+
+        let Some(comparison) = &self.comparison else {
+            return;
+        };
+        let prompt = comparison.prompt.cid();
+        let heads: Vec<_> = comparison
+            .replies
+            .iter()
+            .filter_map(|(_, reply)| reply.as_ref()?.as_ref().ok())
+            .cloned()
+            .collect();
+        let Some(first) = heads.first().cloned() else {
+            self.last_error = Some("No model replied".to_string());
+            return;
+        };
+        self.generations = Some(Generations {
+            prompt,
+            heads,
+            current: 0,
+        });
+        if let Err(e) = self.persist_message(&first) {
+            self.last_error = Some(format!("Failed to persist response: {}", e));
+        }
+        self.conversation_head = Some(first);
+        self.messages_scroll = 0;
+    }
+
+    /// Stops asking the compared models. Replies already stored are kept
+    /// in the store, but none becomes the conversation's head.
+    pub fn cancel_comparison(&mut self) {
+        for task in self.compare_tasks.drain(..) {
+            task.abort();
+        }
+        self.compare_rx = None;
+        self.comparison = None;
+    }
+
+    /// A request for `model` to continue the conversation from `head`.
+    fn request(&self, model: String, head: Arc<Cell<Message>>) -> OpenRouterRequest {
+        OpenRouterRequest {
+            model,
+            conversation_head: Bond::from_cell(head),
+            params: self
+                .reasoning_effort
+                .as_ref()
+                .map(|effort| GenerationParams {
+                    temperature: None,
+                    top_p: None,
+                    top_k: None,
+                    max_tokens: None,
+                    frequency_penalty: None,
+                    presence_penalty: None,
+                    stop: None,
+                    min_p: None,
+                    top_a: None,
+                    repetition_penalty: None,
+                    seed: None,
+                    reasoning_effort: Some(effort.clone()),
+                    reasoning_max_tokens: None,
+                }),
+            tools: self.tools.definitions(),
+            tool_choice: None,
+            context: self.context.clone(),
+        }
+    }
+
+    fn run_command(&mut self, command: &str) -> Result<(), String> {
+        match command::parse(command)? {
+            SlashCommand::Include {
+                target,
+                schema,
+                depth,
+            } => {
+                let block = self
+                    .render_value(&target, schema.as_deref(), depth)
+                    .map_err(|e| e.to_string())?;
+                self.included.push(block);
+            }
+            SlashCommand::Attach { path } => {
+                let block =
+                    attach_file(Path::new(&path), &self.ingest).map_err(|e| e.to_string())?;
+                self.included.push(block);
+            }
+            SlashCommand::Summarize { keep } => self.summarize(keep)?,
+            SlashCommand::Regenerate => self.regenerate()?,
+            SlashCommand::Compare { models } => self.compare_models = models,
+        }
+        Ok(())
+    }
+
+    /// Asks for another reply to the latest user message, on a branch next
+    /// to the replies before it.
+    pub fn regenerate(&mut self) -> Result<(), String> {
+        let messages = self.get_messages();
+        let prompt = messages
+            .iter()
+            .rposition(|c| is_user(c))
+            .map(|i| Arc::clone(messages[i]))
+            .ok_or("no message to reply to")?;
+        let head = Arc::clone(messages[messages.len() - 1]);
+
+        let mut generations = match self.generations.take() {
+            Some(generations) if generations.prompt == prompt.cid() => generations,
+            _ => Generations {
+                prompt: prompt.cid(),
+                heads: vec![Arc::clone(&head)],
+                current: 0,
+            },
+        };
+        generations.heads[generations.current] = Arc::clone(&head);
+        // A prompt still waiting for its reply gets it in its own place
+        if head.cid() != prompt.cid() {
+            generations.heads.push(Arc::clone(&prompt));
+            generations.current = generations.heads.len() - 1;
+        }
+        self.generations = Some(generations);
+        self.comparison = None;
+
+        self.conversation_head = Some(Arc::clone(&prompt));
+        self.tool_rounds = 0;
+        self.request_completion(prompt);
+        Ok(())
+    }
+
+    /// Shows the reply generated `step` places after the current one, or
+    /// before it if negative, and records it as the conversation's head.
+    pub fn flip_generation(&mut self, step: isize) {
+        let (Some(generations), Some(head)) = (&mut self.generations, &self.conversation_head)
+        else {
+            return;
+        };
+        let Some(target) = generations
+            .current
+            .checked_add_signed(step)
+            .filter(|&i| i < generations.heads.len())
+        else {
+            return;
+        };
+        generations.heads[generations.current] = Arc::clone(head);
+        generations.current = target;
+        let head = Arc::clone(&generations.heads[target]);
+
+        if let Err(e) = self.persist_message(&head) {
+            self.last_error = Some(format!("Failed to record reply: {}", e));
+        }
+        self.conversation_head = Some(head);
+        self.messages_scroll = 0;
+    }
+
+    /// Summarizes all but the latest `keep` messages in the background.
+    fn summarize(&mut self, keep: usize) -> Result<(), String> {
+        let messages = self.get_messages();
+        let upto = messages
+            .len()
+            .checked_sub(keep + 1)
+            .map(|i| Bond::from_cell(Arc::clone(messages[i])))
+            .ok_or("nothing to summarize")?;
+
+        let (tx, rx) = oneshot::channel();
+        let client = Arc::clone(&self.client);
+        let model = self.model.clone();
+        let context = self.context.clone();
+
+        tokio::spawn(async move {
+            let result = summarize(&*client, &model, upto, context).await;
+            let _ = tx.send(result);
+        });
+
+        self.summary_rx = Some(rx);
+        self.mode = AppMode::Loading;
+        Ok(())
+    }
+
+    /// Stores a finished summary as the conversation's and sends it in place
+    /// of the messages it covers from then on.
+    pub fn poll_summary(&mut self) {
+        let Some(ref mut rx) = self.summary_rx else {
+            return;
+        };
+        let result = match rx.try_recv() {
+            Ok(result) => result.map_err(|e| format!("Failed to summarize: {}", e)),
+            Err(oneshot::error::TryRecvError::Empty) => return,
+            Err(oneshot::error::TryRecvError::Closed) => Err("Summary cancelled".to_string()),
+        };
+        self.summary_rx = None;
+        self.mode = AppMode::Chat;
+
+        let summary = result.and_then(|message| {
+            let cell = self.ctx.solvent.add(message);
+            self.ctx
+                .persist_message(&cell)
+                .map_err(|e| format!("Failed to persist summary: {}", e))?;
+            if let Some(name) = &self.name {
+                self.ctx
+                    .store
+                    .set_ref(&summary_ref(name), &cell.cid())
+                    .map_err(|e| format!("Failed to record summary: {}", e))?;
+            }
+            Ok(cell)
+        });
+        match summary {
+            Ok(cell) => {
+                let summary = Bond::from_cell(cell);
+                self.context = Some(ContextPolicy::summarized(summary, self.context.as_ref()));
+            }
+            Err(e) => self.last_error = Some(e),
+        }
+    }
+
+    /// Renders a stored value as a JSON code block. Without a schema, every
+    /// link is expanded, not just bonds.
+    fn render_value(
+        &self,
+        target: &str,
+        schema: Option<&str>,
+        depth: usize,
+    ) -> Result<ContentBlock, SihError> {
+        let cid = match Cid::from_str(target) {
+            Ok(cid) => cid,
+            Err(_) => self
+                .ctx
+                .store
+                .get_ref(target)?
+                .ok_or_else(|| SihError::RefNotFound(target.to_string()))?,
+        };
+        let json = match schema {
+            Some(schema) => {
+                let schema_cid = Cid::from_str(schema).map_err(|source| SihError::InvalidCid {
+                    input: schema.to_string(),
+                    source,
+                })?;
+                let mut schemas = Solvent::new();
+                load_schema(&self.ctx.store, &mut schemas, schema_cid)?;
+                to_json(&self.ctx.store, &schemas, cid, schema_cid, depth)?
+            }
+            None => to_json_untyped(&self.ctx.store, cid, depth)?,
+        };
+        Ok(ContentBlock::Code {
+            language: Some("json".to_string()),
+            code: format!("{:#}", json),
+        })
+    }
+
     pub fn poll_response(&mut self) {
         if let Some(ref mut rx) = self.response_rx {
             match rx.try_recv() {
                 Ok(Ok(message)) => {
-                    let cell = self.solvent.add(message);
+                    let tool_calls = match &message.content {
+                        MessageContent::Assistant { tool_calls, .. } => tool_calls.clone(),
+                        _ => Vec::new(),
+                    };
+                    let cell = self.ctx.solvent.add(message);
 
                     // Persist assistant message to store
                     if let Err(e) = self.persist_message(&cell) {
@@ -207,8 +630,18 @@ impl ChatApp {
 
                     self.conversation_head = Some(cell);
                     self.response_rx = None;
-                    self.mode = AppMode::Chat;
                     self.messages_scroll = 0; // Scroll to bottom
+                    if tool_calls.is_empty() {
+                        self.mode = AppMode::Chat;
+                    } else if self.tool_rounds >= MAX_TOOL_ROUNDS {
+                        self.last_error = Some(format!(
+                            "Stopped after {} rounds of tool calls",
+                            MAX_TOOL_ROUNDS
+                        ));
+                        self.mode = AppMode::Chat;
+                    } else {
+                        self.request_tools(tool_calls);
+                    }
                 }
                 Ok(Err(e)) => {
                     self.last_error = Some(format!("{}", e));
@@ -227,10 +660,117 @@ impl ChatApp {
         }
     }
 
+    /// Runs the calls of the model's last reply, first asking the user to
+    /// approve them if any tool requires it.
+    fn request_tools(&mut self, calls: Vec<ToolCall>) {
+        if calls.iter().any(|call| self.tools.needs_confirmation(call)) {
+            self.pending_tools = calls;
+            self.mode = AppMode::ConfirmTools;
+        } else {
+            self.run_tools(calls);
+        }
+    }
+
+    /// Runs the calls the user approved.
+    pub fn confirm_tools(&mut self) {
+        let calls = std::mem::take(&mut self.pending_tools);
+        self.mode = AppMode::Loading;
+        self.run_tools(calls);
+    }
+
+    /// Tells the model the user declined to run its calls.
+    pub fn decline_tools(&mut self) {
+        let results = std::mem::take(&mut self.pending_tools)
+            .into_iter()
+            .map(|call| MessageContent::ToolResult {
+                tool_call_id: call.id,
+                result: "the user declined to run this call".to_string(),
+                is_error: true,
+            })
+            .collect();
+        self.mode = AppMode::Loading;
+        self.store_tool_results(results);
+    }
+
+    /// Runs the calls of the model's last reply in the background.
+    fn run_tools(&mut self, calls: Vec<ToolCall>) {
+        let (tx, rx) = oneshot::channel();
+        let tools = Arc::clone(&self.tools);
+
+        let task = tokio::spawn(async move {
+            let mut results = Vec::new();
+            for call in &calls {
+                results.push(tools.execute(call).await);
+            }
+            let _ = tx.send(results);
+        });
+
+        self.tool_rx = Some(rx);
+        self.tool_task = Some(task.abort_handle());
+    }
+
+    /// Stops the running tool calls, killing the processes they started.
+    pub fn cancel_tools(&mut self) {
+        if let Some(task) = self.tool_task.take() {
+            task.abort();
+        }
+        self.tool_rx = None;
+    }
+
+    /// Stores finished tool results in the conversation and hands them back
+    /// to the model.
+    pub fn poll_tools(&mut self) {
+        let Some(ref mut rx) = self.tool_rx else {
+            return;
+        };
+        let results = match rx.try_recv() {
+            Ok(results) => results,
+            Err(oneshot::error::TryRecvError::Empty) => return,
+            Err(oneshot::error::TryRecvError::Closed) => {
+                self.last_error = Some("Tool calls cancelled".to_string());
+                self.tool_rx = None;
+                self.tool_task = None;
+                self.mode = AppMode::Chat;
+                return;
+            }
+        };
+        self.tool_rx = None;
+        self.tool_task = None;
+        self.store_tool_results(results);
+    }
+
+    /// Stores tool results in the conversation and hands them back to the
+    /// model.
+    fn store_tool_results(&mut self, results: Vec<MessageContent>) {
+        for content in results {
+            let message = Message {
+                content,
+                metadata: None,
+                previous: self
+                    .conversation_head
+                    .as_ref()
+                    .map(|c| Bond::from_cell(Arc::clone(c))),
+            };
+            let cell = self.ctx.solvent.add(message);
+            if let Err(e) = self.persist_message(&cell) {
+                self.last_error = Some(format!("Failed to persist tool result: {}", e));
+                self.mode = AppMode::Chat;
+                return;
+            }
+            self.conversation_head = Some(cell);
+        }
+
+        self.tool_rounds += 1;
+        if let Some(head) = self.conversation_head.clone() {
+            self.request_completion(head);
+        }
+    }
+
     pub fn open_model_picker(&mut self) {
-        self.popup_selected = AVAILABLE_MODELS
+        self.popup_selected = self
+            .models
             .iter()
-            .position(|&m| m == self.model)
+            .position(|m| *m == self.model)
             .unwrap_or(0);
         self.mode = AppMode::SelectModel;
     }
@@ -243,6 +783,111 @@ impl ChatApp {
         self.mode = AppMode::SelectReasoning;
     }
 
+    pub fn open_conversation_picker(&mut self) {
+        match conversations::list(&mut self.ctx) {
+            Ok(list) => self.conversations = list,
+            Err(e) => {
+                self.last_error = Some(format!("Failed to list conversations: {}", e));
+                return;
+            }
+        }
+        self.popup_selected = self
+            .conversations
+            .iter()
+            .position(|c| Some(&c.name) == self.name.as_ref())
+            .unwrap_or(0);
+        self.mode = AppMode::SelectConversation;
+    }
+
+    /// Switches to the selected conversation. Resuming adds new messages to
+    /// it; forking starts a new conversation from its last message.
+    pub fn switch_conversation(&mut self, fork: bool) {
+        let Some(conversation) = self.conversations.get(self.popup_selected) else {
+            return;
+        };
+        let (name, head) = (conversation.name.clone(), conversation.head);
+        match self.ctx.load_conversation(&head) {
+            Ok(cell) => {
+                self.last_error = None;
+                // A fork's usage starts from that of the conversation it forks
+                self.usage = match usage::ledger(&mut self.ctx, Some(&name), &cell, &self.pricing) {
+                    Ok(ledger) => Some(ledger),
+                    Err(e) => {
+                        self.last_error = Some(format!("Failed to count usage: {}", e));
+                        None
+                    }
+                };
+                self.conversation_head = Some(cell);
+                // A fork starts from the same messages, so their summary applies
+                self.context =
+                    context_policy(&mut self.ctx, Some(&name), load_config().context_policy());
+                self.name = (!fork).then_some(name);
+                self.editing = None;
+                self.generations = None;
+                self.comparison = None;
+                self.included.clear();
+                self.messages_scroll = 0;
+            }
+            Err(e) => self.last_error = Some(format!("Failed to load conversation: {}", e)),
+        }
+        self.close_popup();
+    }
+
+    /// Starts selecting an earlier user message to edit, from the latest.
+    pub fn open_message_selection(&mut self) {
+        let latest = self.get_messages().iter().rposition(|c| is_user(c));
+        if let Some(index) = latest {
+            self.selected_message = index;
+            self.mode = AppMode::SelectMessage;
+        }
+    }
+
+    pub fn select_previous_message(&mut self) {
+        let messages = self.get_messages();
+        let before = &messages[..self.selected_message];
+        if let Some(index) = before.iter().rposition(|c| is_user(c)) {
+            self.selected_message = index;
+        }
+    }
+
+    pub fn select_next_message(&mut self) {
+        let messages = self.get_messages();
+        let after = self.selected_message + 1;
+        if let Some(offset) = messages[after..].iter().position(|c| is_user(c)) {
+            self.selected_message = after + offset;
+        }
+    }
+
+    /// Puts the selected message into the input, to be sent as a new branch
+    /// from the message before it.
+    pub fn edit_selected_message(&mut self) {
+        let messages = self.get_messages();
+        let Some(cell) = messages.get(self.selected_message).map(|c| Arc::clone(c)) else {
+            return;
+        };
+        if let MessageContent::User(blocks) = &cell.value().content {
+            let mut texts = Vec::new();
+            self.included.clear();
+            for block in blocks {
+                match block {
+                    ContentBlock::Text(text) => texts.push(text.as_str()),
+                    other => self.included.push(other.clone()),
+                }
+            }
+            self.input = texts.join(" ");
+            self.cursor_pos = self.input.len();
+            self.editing = Some(cell);
+        }
+        self.mode = AppMode::Chat;
+    }
+
+    pub fn cancel_edit(&mut self) {
+        self.editing = None;
+        self.included.clear();
+        self.input.clear();
+        self.cursor_pos = 0;
+    }
+
     pub fn close_popup(&mut self) {
         self.mode = AppMode::Chat;
     }
@@ -255,8 +900,9 @@ impl ChatApp {
 
     pub fn popup_down(&mut self) {
         let max = match self.mode {
-            AppMode::SelectModel => AVAILABLE_MODELS.len() - 1,
+            AppMode::SelectModel => self.models.len().saturating_sub(1),
             AppMode::SelectReasoning => REASONING_OPTIONS.len() - 1,
+            AppMode::SelectConversation => self.conversations.len().saturating_sub(1),
             _ => 0,
         };
         if self.popup_selected < max {
@@ -267,11 +913,14 @@ impl ChatApp {
     pub fn popup_select(&mut self) {
         match self.mode {
             AppMode::SelectModel => {
-                self.model = AVAILABLE_MODELS[self.popup_selected].to_string();
+                if let Some(model) = self.models.get(self.popup_selected) {
+                    self.model = model.clone();
+                }
             }
             AppMode::SelectReasoning => {
                 self.reasoning_effort = REASONING_OPTIONS[self.popup_selected].map(String::from);
             }
+            AppMode::SelectConversation => self.switch_conversation(false),
             _ => {}
         }
         self.close_popup();
@@ -340,12 +989,12 @@ impl ChatApp {
         self.conversation_head.as_ref().map(|c| c.cid())
     }
 
-    pub fn get_messages(&self) -> Vec<&Message> {
+    pub fn get_messages(&self) -> Vec<&Arc<Cell<Message>>> {
         let mut messages = Vec::new();
         let mut current: Option<&Arc<Cell<Message>>> = self.conversation_head.as_ref();
 
         while let Some(cell) = current {
-            messages.push(cell.value());
+            messages.push(cell);
             current = cell
                 .value()
                 .previous
@@ -358,3 +1007,54 @@ impl ChatApp {
     }
 }
 
+/// `policy` with the stored summary of the conversation `name`, if any,
+/// sent in place of the messages it covers.
+fn context_policy(
+    ctx: &mut AppContext,
+    name: Option<&str>,
+    policy: Option<ContextPolicy>,
+) -> Option<ContextPolicy> {
+    let summary = name
+        .and_then(|name| ctx.store.get_ref(&summary_ref(name)).ok().flatten())
+        // Like a lost summary, one that can't be read leaves all messages sent
+        .and_then(|cid| ctx.load_conversation(&cid).ok())
+        .map(Bond::from_cell);
+    match summary {
+        Some(summary) => Some(ContextPolicy::summarized(summary, policy.as_ref())),
+        None => policy,
+    }
+}
+
+fn is_user(cell: &Cell<Message>) -> bool {
+    matches!(cell.value().content, MessageContent::User(_))
+}
+
+/// Reads a file into an image or file block, named after the file, once
+/// `policy` accepts it.
+fn attach_file(path: &Path, policy: &IngestPolicy) -> Result<ContentBlock, SihError> {
+    let data = std::fs::read(path)?;
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mime_type = mime_type(path, &data);
+    Ok(ContentBlock::attachment(policy, name, mime_type, data)?)
+}
+
+/// Guesses a MIME type from the file extension, then from whether the
+/// content is text.
+fn mime_type(path: &Path, data: &[u8]) -> &'static str {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    match extension.to_ascii_lowercase().as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "pdf" => "application/pdf",
+        "json" => "application/json",
+        "csv" => "text/csv",
+        "md" => "text/markdown",
+        _ if std::str::from_utf8(data).is_ok() => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
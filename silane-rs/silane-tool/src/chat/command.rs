@@ -0,0 +1,87 @@
+//! Slash commands typed into the chat input, handled locally.
+
+/// Bond levels expanded by `/include` unless `--depth` is given.
+pub const DEFAULT_INCLUDE_DEPTH: usize = 2;
+
+const INCLUDE_USAGE: &str = "usage: /include <cid-or-ref> [--schema <cid>] [--depth N]";
+const ATTACH_USAGE: &str = "usage: /attach <path>";
+const SUMMARIZE_USAGE: &str = "usage: /summarize [messages to keep]";
+const COMPARE_USAGE: &str = "usage: /compare <model> <model>..., or /compare alone to stop";
+
+/// Latest messages left out of a summary unless a number is given.
+pub const DEFAULT_SUMMARY_KEEP: usize = 4;
+
+pub enum SlashCommand {
+    /// Add a stored value to the next message, rendered as JSON.
+    Include {
+        target: String,
+        schema: Option<String>,
+        depth: usize,
+    },
+    /// Add a file or image to the next message.
+    Attach { path: String },
+    /// Send a summary in place of all but the latest `keep` messages.
+    Summarize { keep: usize },
+    /// Ask for another reply to the latest user message.
+    Regenerate,
+    /// Send each following message to all of `models`, or stop comparing if
+    /// empty.
+    Compare { models: Vec<String> },
+}
+
+/// Parses a command, without its leading `/`.
+pub fn parse(command: &str) -> Result<SlashCommand, String> {
+    let mut words = command.split_whitespace();
+    match words.next() {
+        Some("include") => {
+            let mut target = None;
+            let mut schema = None;
+            let mut depth = DEFAULT_INCLUDE_DEPTH;
+            while let Some(word) = words.next() {
+                match word {
+                    "--depth" => {
+                        depth = words
+                            .next()
+                            .and_then(|n| n.parse().ok())
+                            .ok_or("--depth needs a number")?;
+                    }
+                    "--schema" => schema = Some(words.next().ok_or(INCLUDE_USAGE)?.to_string()),
+                    _ if target.is_none() => target = Some(word.to_string()),
+                    _ => return Err(INCLUDE_USAGE.to_string()),
+                }
+            }
+            Ok(SlashCommand::Include {
+                target: target.ok_or(INCLUDE_USAGE)?,
+                schema,
+                depth,
+            })
+        }
+        Some("attach") => {
+            // Paths may contain spaces
+            let path = command.trim_start()["attach".len()..].trim();
+            if path.is_empty() {
+                return Err(ATTACH_USAGE.to_string());
+            }
+            Ok(SlashCommand::Attach {
+                path: path.to_string(),
+            })
+        }
+        Some("summarize") => {
+            let keep = match words.next() {
+                Some(n) => n.parse().map_err(|_| SUMMARIZE_USAGE)?,
+                None => DEFAULT_SUMMARY_KEEP,
+            };
+            Ok(SlashCommand::Summarize { keep })
+        }
+        Some("regenerate") => Ok(SlashCommand::Regenerate),
+        Some("compare") => {
+            let models: Vec<String> = words.map(String::from).collect();
+            if models.len() == 1 {
+                return Err(COMPARE_USAGE.to_string());
+            }
+            Ok(SlashCommand::Compare { models })
+        }
+        Some(other) => Err(format!("unknown command: /{}", other)),
+        None => Err(INCLUDE_USAGE.to_string()),
+    }
+}
@@ -12,12 +12,22 @@ fn handle_key(app: &mut ChatApp, key: KeyEvent) {
     match app.mode {
         AppMode::Chat => handle_chat_key(app, key),
         AppMode::Loading => handle_loading_key(app, key),
-        AppMode::SelectModel | AppMode::SelectReasoning => handle_popup_key(app, key),
+        AppMode::SelectMessage => handle_selection_key(app, key),
+        AppMode::ConfirmTools => handle_confirm_key(app, key),
+        AppMode::SelectModel | AppMode::SelectReasoning | AppMode::SelectConversation => {
+            handle_popup_key(app, key)
+        }
     }
 }
 
 fn handle_chat_key(app: &mut ChatApp, key: KeyEvent) {
     match (key.code, key.modifiers) {
+        (KeyCode::Esc, _) if app.editing.is_some() => {
+            app.cancel_edit();
+        }
+        (KeyCode::Esc, _) if app.comparison.is_some() => {
+            app.comparison = None;
+        }
         (KeyCode::Esc, _) => {
             app.should_quit = true;
         }
@@ -27,6 +37,15 @@ fn handle_chat_key(app: &mut ChatApp, key: KeyEvent) {
         (KeyCode::F(3), _) => {
             app.open_reasoning_picker();
         }
+        (KeyCode::F(4), _) => {
+            app.open_conversation_picker();
+        }
+        (KeyCode::F(5), _) => {
+            app.open_message_selection();
+        }
+        (KeyCode::F(6), _) => {
+            app.last_error = app.regenerate().err();
+        }
         (KeyCode::Enter, KeyModifiers::NONE) => {
             app.send_message();
         }
@@ -42,6 +61,12 @@ fn handle_chat_key(app: &mut ChatApp, key: KeyEvent) {
         (KeyCode::Delete, _) => {
             app.input_delete();
         }
+        (KeyCode::Left, KeyModifiers::CONTROL) => {
+            app.flip_generation(-1);
+        }
+        (KeyCode::Right, KeyModifiers::CONTROL) => {
+            app.flip_generation(1);
+        }
         (KeyCode::Left, _) => {
             app.input_left();
         }
@@ -64,17 +89,41 @@ fn handle_chat_key(app: &mut ChatApp, key: KeyEvent) {
 fn handle_loading_key(app: &mut ChatApp, key: KeyEvent) {
     if key.code == KeyCode::Esc {
         app.response_rx = None;
+        app.summary_rx = None;
+        app.cancel_tools();
+        app.cancel_comparison();
         app.mode = AppMode::Chat;
         app.last_error = Some("Request cancelled".to_string());
     }
 }
 
+fn handle_confirm_key(app: &mut ChatApp, key: KeyEvent) {
+    match key.code {
+        KeyCode::Char('y') | KeyCode::Enter => app.confirm_tools(),
+        KeyCode::Char('n') | KeyCode::Esc => app.decline_tools(),
+        _ => {}
+    }
+}
+
+fn handle_selection_key(app: &mut ChatApp, key: KeyEvent) {
+    match key.code {
+        KeyCode::Esc => app.mode = AppMode::Chat,
+        KeyCode::Enter => app.edit_selected_message(),
+        KeyCode::Up => app.select_previous_message(),
+        KeyCode::Down => app.select_next_message(),
+        _ => {}
+    }
+}
+
 fn handle_popup_key(app: &mut ChatApp, key: KeyEvent) {
     match key.code {
         KeyCode::Esc => app.close_popup(),
         KeyCode::Enter => app.popup_select(),
         KeyCode::Up => app.popup_up(),
         KeyCode::Down => app.popup_down(),
+        KeyCode::Char('f') if app.mode == AppMode::SelectConversation => {
+            app.switch_conversation(true)
+        }
         _ => {}
     }
 }
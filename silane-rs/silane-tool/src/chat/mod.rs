@@ -1,5 +1,7 @@
 mod app;
+mod command;
 mod input;
+mod provider;
 mod ui;
 
 use std::io;
@@ -12,20 +14,28 @@ use crossterm::{
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::prelude::*;
-use silane_openrouter::OpenRouterClient;
 
 pub use app::ChatApp;
+pub use provider::AnyClient;
 
 use crate::error::SihError;
 use crate::store::AppContext;
+use crate::tools::ToolRegistry;
 
 pub async fn run(
     ctx: AppContext,
-    client: OpenRouterClient,
-    model: String,
+    client: AnyClient,
+    model: Option<String>,
     reasoning_effort: Option<String>,
     continue_from: Option<Cid>,
+    name: Option<String>,
+    tools: ToolRegistry,
 ) -> Result<(), SihError> {
+    let models = client.models().await?;
+    let model = model
+        .or_else(|| models.first().cloned())
+        .ok_or(SihError::NoModels)?;
+
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -34,7 +44,8 @@ pub async fn run(
     let mut terminal = Terminal::new(backend)?;
 
     // Create app
-    let mut app = ChatApp::new(ctx, client, model, reasoning_effort, continue_from)?;
+    let app = ChatApp::new(ctx, client, model, reasoning_effort, continue_from, name, tools)?;
+    let mut app = app.with_models(models);
 
     // Run event loop
     let result = run_loop(&mut terminal, &mut app).await;
@@ -54,7 +65,10 @@ pub async fn run(
     // Print final conversation CID
     if let Some(cid) = final_cid {
         println!("Conversation CID: {}", cid);
-        println!("To continue: sih chat --continue-from {}", cid);
+        match &app.name {
+            Some(name) => println!("To continue: sih chat --name {}", name),
+            None => println!("To continue: sih chat --continue-from {}", cid),
+        }
     }
 
     result
@@ -75,6 +89,9 @@ async fn run_loop(
 
         // Check for async response
         app.poll_response();
+        app.poll_tools();
+        app.poll_summary();
+        app.poll_comparison();
 
         if app.should_quit {
             break;
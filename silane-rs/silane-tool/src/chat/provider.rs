@@ -0,0 +1,81 @@
+//! The completion providers a chat can use.
+
+use polyepoxide_llm::Message;
+use silane_ollama::{OllamaClient, OllamaError};
+use silane_openrouter::{CompletionProvider, OpenRouterClient, OpenRouterError, OpenRouterRequest};
+use thiserror::Error;
+
+use crate::config::{ProviderType, load_api_key, load_config};
+use crate::error::SihError;
+
+/// Models offered for OpenRouter, which serves too many to list them all.
+const OPENROUTER_MODELS: &[&str] = &[
+    "openai/gpt-4o",
+    "openai/gpt-4o-mini",
+    "openai/o1",
+    "openai/o1-mini",
+    "anthropic/claude-3.5-sonnet",
+    "anthropic/claude-3-haiku",
+    "google/gemini-2.0-flash-001",
+    "google/gemini-2.0-flash-thinking-exp:free",
+    "deepseek/deepseek-r1",
+    "deepseek/deepseek-chat",
+];
+
+#[derive(Debug, Error)]
+pub enum AnyClientError {
+    #[error(transparent)]
+    OpenRouter(#[from] OpenRouterError),
+    #[error(transparent)]
+    Ollama(#[from] OllamaError),
+}
+
+impl From<AnyClientError> for SihError {
+    fn from(e: AnyClientError) -> Self {
+        match e {
+            AnyClientError::OpenRouter(e) => e.into(),
+            AnyClientError::Ollama(e) => e.into(),
+        }
+    }
+}
+
+pub enum AnyClient {
+    OpenRouter(OpenRouterClient),
+    Ollama(OllamaClient),
+}
+
+impl AnyClient {
+    /// Creates a client for `provider`, or the provider in the config file.
+    pub fn open(provider: Option<ProviderType>) -> Result<Self, SihError> {
+        let config = load_config();
+        let client = match provider.unwrap_or(config.provider) {
+            ProviderType::OpenRouter => Self::OpenRouter(OpenRouterClient::new(load_api_key()?)),
+            ProviderType::Ollama => Self::Ollama(match config.ollama_url {
+                Some(url) => OllamaClient::with_base_url(url),
+                None => OllamaClient::new(),
+            }),
+        };
+        Ok(client)
+    }
+
+    /// Models offered by the model picker, the default first.
+    pub async fn models(&self) -> Result<Vec<String>, OllamaError> {
+        match self {
+            AnyClient::OpenRouter(_) => {
+                Ok(OPENROUTER_MODELS.iter().map(|m| m.to_string()).collect())
+            }
+            AnyClient::Ollama(c) => c.list_models().await,
+        }
+    }
+}
+
+impl CompletionProvider for AnyClient {
+    type Error = AnyClientError;
+
+    async fn complete(&self, request: &OpenRouterRequest) -> Result<Message, AnyClientError> {
+        match self {
+            AnyClient::OpenRouter(c) => Ok(c.complete(request).await?),
+            AnyClient::Ollama(c) => Ok(c.complete(request).await?),
+        }
+    }
+}
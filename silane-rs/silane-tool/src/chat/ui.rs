@@ -1,4 +1,4 @@
-use polyepoxide_llm::{ContentBlock, MessageContent};
+use polyepoxide_llm::{ContentBlock, Message, MessageContent};
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
@@ -7,7 +7,7 @@ use ratatui::{
     Frame,
 };
 
-use super::app::{AppMode, ChatApp};
+use super::app::{AppMode, ChatApp, Comparison};
 
 pub fn render(frame: &mut Frame, app: &ChatApp) {
     let chunks = Layout::default()
@@ -21,7 +21,10 @@ pub fn render(frame: &mut Frame, app: &ChatApp) {
         .split(frame.area());
 
     render_header(frame, app, chunks[0]);
-    render_messages(frame, app, chunks[1]);
+    match &app.comparison {
+        Some(comparison) => render_comparison(frame, app, comparison, chunks[1]),
+        None => render_messages(frame, app, chunks[1]),
+    }
     render_input(frame, app, chunks[2]);
     render_status_bar(frame, app, chunks[3]);
 
@@ -29,6 +32,8 @@ pub fn render(frame: &mut Frame, app: &ChatApp) {
     match app.mode {
         AppMode::SelectModel => render_model_popup(frame, app),
         AppMode::SelectReasoning => render_reasoning_popup(frame, app),
+        AppMode::SelectConversation => render_conversation_popup(frame, app),
+        AppMode::ConfirmTools => render_confirm_popup(frame, app),
         _ => {}
     }
 }
@@ -44,7 +49,20 @@ fn render_header(frame: &mut Frame, app: &ChatApp, area: Rect) {
         None => String::new(),
     };
 
-    let title = format!("sih chat - {}{}{}", app.model, reasoning_text, cid_text);
+    let usage_text = match &app.usage {
+        Some(ledger) => {
+            let ledger = ledger.value();
+            format!("  {} tokens ${:.4}", ledger.spent_totals().total_tokens(), ledger.spent())
+        }
+        None => String::new(),
+    };
+
+    let model = match app.compare_models.len() {
+        0 => app.model.clone(),
+        n => format!("comparing {} models", n),
+    };
+
+    let title = format!("sih chat - {}{}{}{}", model, reasoning_text, usage_text, cid_text);
 
     let header = Paragraph::new(title).style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD));
 
@@ -55,81 +73,47 @@ fn render_messages(frame: &mut Frame, app: &ChatApp, area: Rect) {
     let messages = app.get_messages();
     let mut lines: Vec<Line> = Vec::new();
 
-    for msg in messages {
-        let (role, style, content_blocks) = match &msg.content {
-            MessageContent::User(blocks) => ("User", Style::default().fg(Color::Green), blocks.as_slice()),
-            MessageContent::Assistant { blocks, .. } => {
+    for (index, cell) in messages.into_iter().enumerate() {
+        let msg = cell.value();
+        let (role, style) = match &msg.content {
+            MessageContent::User(_) => ("User", Style::default().fg(Color::Green)),
+            MessageContent::Assistant { .. } => {
                 let model_name = msg
                     .metadata
                     .as_ref()
                     .and_then(|m| m.model.as_ref())
                     .map(|m| m.as_str())
                     .unwrap_or("Assistant");
-                (model_name, Style::default().fg(Color::Blue), blocks.as_slice())
+                (model_name, Style::default().fg(Color::Blue))
             }
-            MessageContent::System(blocks) => ("System", Style::default().fg(Color::Yellow), blocks.as_slice()),
+            MessageContent::System(_) => ("System", Style::default().fg(Color::Yellow)),
             MessageContent::ToolResult { .. } => continue, // Skip tool results in display
         };
 
         // Role header
-        lines.push(Line::from(Span::styled(format!("{}:", role), style.add_modifier(Modifier::BOLD))));
-
-        // Content blocks
-        for block in content_blocks {
-            match block {
-                ContentBlock::Text(text) => {
-                    for line in text.lines() {
-                        lines.push(Line::from(format!("  {}", line)));
-                    }
-                }
-                ContentBlock::Thinking(text) => {
-                    lines.push(Line::from(Span::styled(
-                        "  [Thinking]",
-                        Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC),
-                    )));
-                    for line in text.lines() {
-                        lines.push(Line::from(Span::styled(
-                            format!("  {}", line),
-                            Style::default().fg(Color::DarkGray),
-                        )));
-                    }
-                }
-                ContentBlock::Code { language, code } => {
-                    let lang = language.as_deref().unwrap_or("code");
-                    lines.push(Line::from(Span::styled(
-                        format!("  ```{}", lang),
-                        Style::default().fg(Color::Magenta),
-                    )));
-                    for line in code.lines() {
-                        lines.push(Line::from(Span::styled(
-                            format!("  {}", line),
-                            Style::default().fg(Color::White),
-                        )));
-                    }
-                    lines.push(Line::from(Span::styled("  ```", Style::default().fg(Color::Magenta))));
-                }
-                ContentBlock::Image(_) => {
-                    lines.push(Line::from(Span::styled(
-                        "  [Image]",
-                        Style::default().fg(Color::DarkGray),
-                    )));
-                }
-                ContentBlock::File { name, .. } => {
-                    lines.push(Line::from(Span::styled(
-                        format!("  [File: {}]", name),
-                        Style::default().fg(Color::DarkGray),
-                    )));
-                }
-            }
+        let mut header_style = style.add_modifier(Modifier::BOLD);
+        if app.mode == AppMode::SelectMessage && index == app.selected_message {
+            header_style = header_style.add_modifier(Modifier::REVERSED);
         }
+        let branch = if app.branched.contains(&cell.cid()) { " (edited, new branch)" } else { "" };
+        let generation = match &app.generations {
+            Some(g) if g.heads.len() > 1 && msg.previous.as_ref().map(|p| p.cid()) == Some(g.prompt) => {
+                format!(" (reply {}/{}, Ctrl+←/→ to flip)", g.current + 1, g.heads.len())
+            }
+            _ => String::new(),
+        };
+        lines.push(Line::from(Span::styled(format!("{}:{}{}", role, branch, generation), header_style)));
+
+        push_content(&mut lines, msg);
 
         lines.push(Line::from("")); // Empty line between messages
     }
 
     // Loading indicator
     if app.mode == AppMode::Loading {
+        let status = if app.tool_rx.is_some() { "Running tools..." } else { "Waiting for response..." };
         lines.push(Line::from(Span::styled(
-            "Waiting for response...",
+            status,
             Style::default().fg(Color::Yellow).add_modifier(Modifier::ITALIC),
         )));
     }
@@ -161,8 +145,112 @@ fn render_messages(frame: &mut Frame, app: &ChatApp, area: Rect) {
     frame.render_widget(paragraph, area);
 }
 
+/// Shows the replies of a comparison side by side, the one the conversation
+/// goes on from highlighted.
+fn render_comparison(frame: &mut Frame, app: &ChatApp, comparison: &Comparison, area: Rect) {
+    let count = comparison.replies.len() as u32;
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(comparison.replies.iter().map(|_| Constraint::Ratio(1, count)))
+        .split(area);
+
+    for ((model, reply), column) in comparison.replies.iter().zip(columns.iter()) {
+        let mut lines = Vec::new();
+        let mut border_style = Style::default();
+        match reply {
+            None => lines.push(Line::from(Span::styled(
+                "Waiting for response...",
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::ITALIC),
+            ))),
+            Some(Ok(cell)) => {
+                if Some(cell.cid()) == app.conversation_cid() {
+                    border_style = border_style.fg(Color::Green);
+                }
+                push_content(&mut lines, cell.value());
+            }
+            Some(Err(error)) => lines.push(Line::from(Span::styled(
+                format!("Error: {}", error),
+                Style::default().fg(Color::Red),
+            ))),
+        }
+
+        let block = Block::default().borders(Borders::ALL).border_style(border_style).title(model.as_str());
+        let paragraph = Paragraph::new(Text::from(lines)).block(block).wrap(Wrap { trim: false });
+        frame.render_widget(paragraph, *column);
+    }
+}
+
+/// Adds the lines showing a message's blocks and tool calls.
+fn push_content(lines: &mut Vec<Line>, msg: &Message) {
+    let (blocks, tool_calls) = match &msg.content {
+        MessageContent::System(blocks) | MessageContent::User(blocks) => (blocks, &[][..]),
+        MessageContent::Assistant { blocks, tool_calls } => (blocks, tool_calls.as_slice()),
+        MessageContent::ToolResult { .. } => return,
+    };
+    for block in blocks {
+        match block {
+            ContentBlock::Text(text) => {
+                for line in text.lines() {
+                    lines.push(Line::from(format!("  {}", line)));
+                }
+            }
+            ContentBlock::Thinking(text) => {
+                lines.push(Line::from(Span::styled(
+                    "  [Thinking]",
+                    Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC),
+                )));
+                for line in text.lines() {
+                    lines.push(Line::from(Span::styled(
+                        format!("  {}", line),
+                        Style::default().fg(Color::DarkGray),
+                    )));
+                }
+            }
+            ContentBlock::Code { language, code } => {
+                let lang = language.as_deref().unwrap_or("code");
+                lines.push(Line::from(Span::styled(
+                    format!("  ```{}", lang),
+                    Style::default().fg(Color::Magenta),
+                )));
+                for line in code.lines() {
+                    lines.push(Line::from(Span::styled(
+                        format!("  {}", line),
+                        Style::default().fg(Color::White),
+                    )));
+                }
+                lines.push(Line::from(Span::styled("  ```", Style::default().fg(Color::Magenta))));
+            }
+            ContentBlock::Image(_) => {
+                lines.push(Line::from(Span::styled(
+                    "  [Image]",
+                    Style::default().fg(Color::DarkGray),
+                )));
+            }
+            ContentBlock::File { name, .. } | ContentBlock::ChunkedFile { name, .. } => {
+                lines.push(Line::from(Span::styled(
+                    format!("  [File: {}]", name),
+                    Style::default().fg(Color::DarkGray),
+                )));
+            }
+        }
+    }
+
+    for call in tool_calls {
+        lines.push(Line::from(Span::styled(
+            format!("  [Tool: {} {}]", call.name, call.arguments),
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+}
+
 fn render_input(frame: &mut Frame, app: &ChatApp, area: Rect) {
-    let input_block = Block::default().borders(Borders::ALL).title("Input");
+    let title = match (app.included.len(), app.editing.is_some()) {
+        (0, false) => "Input".to_string(),
+        (n, false) => format!("Input ({} included)", n),
+        (0, true) => "Edit (sent as a new branch)".to_string(),
+        (n, true) => format!("Edit (sent as a new branch, {} included)", n),
+    };
+    let input_block = Block::default().borders(Borders::ALL).title(title);
 
     let display_text = if app.input.is_empty() {
         "Type your message here...".to_string()
@@ -194,9 +282,20 @@ fn render_input(frame: &mut Frame, app: &ChatApp, area: Rect) {
 
 fn render_status_bar(frame: &mut Frame, app: &ChatApp, area: Rect) {
     let status = match app.mode {
-        AppMode::Chat => "Enter: Send  F2: Model  F3: Reasoning  Ctrl+↑/↓: Scroll  Esc: Quit",
+        AppMode::Chat => {
+            if app.editing.is_some() {
+                "Enter: Send as new branch  Esc: Cancel edit"
+            } else if app.comparison.is_some() {
+                "Enter: Send  Ctrl+←/→: Choose reply to go on from  Esc: Close comparison"
+            } else {
+                "Enter: Send  /include, /attach: Add value or file  /summarize: Shorten context  /compare: Ask several models  F2: Model  F3: Reasoning  F4: Conversations  F5: Edit earlier  F6, /regenerate: New reply  Ctrl+↑/↓: Scroll  Esc: Quit"
+            }
+        }
         AppMode::Loading => "Waiting for response...  Esc: Cancel",
         AppMode::SelectModel | AppMode::SelectReasoning => "↑/↓: Navigate  Enter: Select  Esc: Cancel",
+        AppMode::SelectConversation => "↑/↓: Navigate  Enter: Resume  F: Fork  Esc: Cancel",
+        AppMode::SelectMessage => "↑/↓: Select message  Enter: Edit  Esc: Cancel",
+        AppMode::ConfirmTools => "y/Enter: Run the calls  n/Esc: Decline",
     };
 
     let status_bar = Paragraph::new(status).style(Style::default().fg(Color::DarkGray));
@@ -204,8 +303,8 @@ fn render_status_bar(frame: &mut Frame, app: &ChatApp, area: Rect) {
     frame.render_widget(status_bar, area);
 }
 
-fn render_popup(frame: &mut Frame, title: &str, items: Vec<ListItem>, selected: usize) {
-    let area = centered_rect(40, 50, frame.area());
+fn render_popup(frame: &mut Frame, title: &str, items: Vec<ListItem>, selected: usize, width: u16) {
+    let area = centered_rect(width, 50, frame.area());
 
     frame.render_widget(Clear, area);
 
@@ -221,7 +320,8 @@ fn render_popup(frame: &mut Frame, title: &str, items: Vec<ListItem>, selected:
 }
 
 fn render_model_popup(frame: &mut Frame, app: &ChatApp) {
-    let items: Vec<ListItem> = ChatApp::available_models()
+    let items: Vec<ListItem> = app
+        .models
         .iter()
         .map(|model| {
             let style = if *model == app.model {
@@ -234,7 +334,7 @@ fn render_model_popup(frame: &mut Frame, app: &ChatApp) {
         })
         .collect();
 
-    render_popup(frame, "Select Model", items, app.popup_selected);
+    render_popup(frame, "Select Model", items, app.popup_selected, 40);
 }
 
 fn render_reasoning_popup(frame: &mut Frame, app: &ChatApp) {
@@ -253,7 +353,59 @@ fn render_reasoning_popup(frame: &mut Frame, app: &ChatApp) {
         })
         .collect();
 
-    render_popup(frame, "Select Reasoning Effort", items, app.popup_selected);
+    render_popup(frame, "Select Reasoning Effort", items, app.popup_selected, 40);
+}
+
+fn render_conversation_popup(frame: &mut Frame, app: &ChatApp) {
+    let items: Vec<ListItem> = app
+        .conversations
+        .iter()
+        .map(|conversation| {
+            let is_current = Some(&conversation.name) == app.name.as_ref();
+            let style = if is_current {
+                Style::default().fg(Color::Green)
+            } else {
+                Style::default()
+            };
+            ListItem::new(vec![
+                Line::from(Span::styled(
+                    format!("{} ({} messages)", conversation.name, conversation.messages),
+                    style.add_modifier(Modifier::BOLD),
+                )),
+                Line::from(format!("  {}", conversation.first)),
+                Line::from(Span::styled(
+                    format!("  … {}", conversation.last),
+                    Style::default().fg(Color::DarkGray),
+                )),
+            ])
+            .style(style)
+        })
+        .collect();
+
+    render_popup(frame, "Conversations", items, app.popup_selected, 80);
+}
+
+/// Shows the tool calls waiting for the user's approval with their
+/// arguments, the ones needing it highlighted.
+fn render_confirm_popup(frame: &mut Frame, app: &ChatApp) {
+    let area = centered_rect(80, 50, frame.area());
+    frame.render_widget(Clear, area);
+
+    let mut lines = Vec::new();
+    for call in &app.pending_tools {
+        let style = if app.tools.needs_confirmation(call) {
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().add_modifier(Modifier::BOLD)
+        };
+        lines.push(Line::from(Span::styled(call.name.clone(), style)));
+        lines.push(Line::from(call.arguments.clone()));
+        lines.push(Line::from(""));
+    }
+    let paragraph = Paragraph::new(Text::from(lines))
+        .block(Block::default().borders(Borders::ALL).title("Run these tool calls?"))
+        .wrap(Wrap { trim: false });
+    frame.render_widget(paragraph, area);
 }
 
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
@@ -1,6 +1,10 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
 
+use polyepoxide_core::IngestPolicy;
+use polyepoxide_llm::ModelPricing;
 use serde::Deserialize;
+use silane_openrouter::ContextPolicy;
 
 use crate::error::SihError;
 use crate::store::{default_store_path, StoreType};
@@ -10,6 +14,89 @@ pub struct Config {
     pub openrouter_api_key: Option<String>,
     #[serde(default)]
     pub store: StoreConfig,
+    /// Prices per million tokens, by model name.
+    #[serde(default)]
+    pub pricing: HashMap<String, ModelPricing>,
+    /// Provider `sih chat` uses unless `--provider` is given.
+    #[serde(default)]
+    pub provider: ProviderType,
+    /// Address of the Ollama server, if not Ollama's default.
+    pub ollama_url: Option<String>,
+    /// Latest messages sent with each chat request.
+    pub context_messages: Option<u32>,
+    /// Estimated tokens of the messages sent with each chat request; takes
+    /// precedence over `context_messages`.
+    pub context_tokens: Option<u32>,
+    /// Screening of files added with `/attach`.
+    #[serde(default)]
+    pub attachments: AttachmentConfig,
+}
+
+impl Config {
+    /// The configured context policy; without one, whole conversations are sent.
+    pub fn context_policy(&self) -> Option<ContextPolicy> {
+        match (self.context_tokens, self.context_messages) {
+            (Some(max_tokens), _) => Some(ContextPolicy::TokenBudget { max_tokens }),
+            (None, Some(messages)) => Some(ContextPolicy::SlidingWindow { messages }),
+            (None, None) => None,
+        }
+    }
+
+    /// The policy attached files are screened with; without an
+    /// `attachments` table, every file is accepted.
+    pub fn ingest_policy(&self) -> IngestPolicy {
+        let attachments = &self.attachments;
+        let mut policy =
+            IngestPolicy::new().with_allowed_mime_types(attachments.allowed_types.iter().cloned());
+        if let Some(max_size) = attachments.max_size {
+            policy = policy.with_max_size(max_size);
+        }
+        if !attachments.scanner.is_empty() {
+            policy = policy.with_scanner(attachments.scanner.iter().cloned());
+        }
+        if let Some(dir) = &attachments.quarantine_dir {
+            policy = policy.with_quarantine_dir(dir);
+        }
+        policy
+    }
+}
+
+/// The `attachments` table of the config file.
+#[derive(Debug, Deserialize, Default)]
+pub struct AttachmentConfig {
+    /// Largest file accepted, in bytes.
+    pub max_size: Option<usize>,
+    /// MIME patterns such as `image/png` or `image/*`; empty allows all.
+    #[serde(default)]
+    pub allowed_types: Vec<String>,
+    /// Scanner command and arguments; files are piped to its stdin and exit
+    /// status 0 means clean.
+    #[serde(default)]
+    pub scanner: Vec<String>,
+    /// Where files flagged by the scanner are kept.
+    pub quarantine_dir: Option<PathBuf>,
+}
+
+/// Where completions are requested from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderType {
+    #[default]
+    OpenRouter,
+    /// A local Ollama server; conversations stay on the machine.
+    Ollama,
+}
+
+impl std::str::FromStr for ProviderType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "openrouter" => Ok(ProviderType::OpenRouter),
+            "ollama" => Ok(ProviderType::Ollama),
+            _ => Err(format!("unknown provider: {}", s)),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
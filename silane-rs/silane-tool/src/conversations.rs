@@ -0,0 +1,162 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use cid::Cid;
+use polyepoxide_core::{Cell, RefStore};
+use polyepoxide_llm::{ContentBlock, Message, MessageContent};
+
+use crate::error::SihError;
+use crate::store::{AppContext, CONVERSATION_REFS, DATE_INDEX, MODEL_INDEX};
+
+/// Characters of a message shown in a preview.
+const PREVIEW_CHARS: usize = 40;
+
+/// Characters of a message CID naming an unnamed conversation.
+const AUTO_NAME_CHARS: usize = 12;
+
+/// A stored conversation and a preview of it.
+pub struct Conversation {
+    pub name: String,
+    pub head: Cid,
+    pub messages: usize,
+    /// The first user message, or the error loading the conversation.
+    pub first: String,
+    pub last: String,
+}
+
+/// Lists the named conversations, loading each to preview it. A
+/// conversation that can't be loaded is listed with the error.
+pub fn list(ctx: &mut AppContext) -> Result<Vec<Conversation>, SihError> {
+    let refs = ctx.store.list_refs(CONVERSATION_REFS)?;
+    let mut conversations = Vec::new();
+    for (name, head) in refs {
+        let name = name[CONVERSATION_REFS.len()..].to_string();
+        let conversation = match ctx.load_conversation(&head) {
+            Ok(cell) => {
+                let messages = chain(&cell);
+                let first = messages
+                    .iter()
+                    .find(|m| matches!(m.content, MessageContent::User(_)))
+                    .or(messages.first());
+                Conversation {
+                    name,
+                    head,
+                    messages: messages.len(),
+                    first: first.map(|m| preview(m)).unwrap_or_default(),
+                    last: preview(cell.value()),
+                }
+            }
+            Err(e) => Conversation {
+                name,
+                head,
+                messages: 0,
+                first: format!("unreadable: {}", e),
+                last: String::new(),
+            },
+        };
+        conversations.push(conversation);
+    }
+    Ok(conversations)
+}
+
+/// A message found through an index.
+pub struct FoundMessage {
+    pub cid: Cid,
+    pub model: Option<String>,
+    pub timestamp_ms: Option<u64>,
+    pub preview: String,
+}
+
+/// Finds the messages of a model, or of a UTC day given as `YYYY-MM-DD` or
+/// a prefix of it such as `YYYY-MM`, oldest first. Messages match both when
+/// both are given.
+pub fn find(
+    ctx: &mut AppContext,
+    model: Option<&str>,
+    date: Option<&str>,
+) -> Result<Vec<FoundMessage>, SihError> {
+    let by_model = model
+        .map(|model| ctx.indexes.find(&ctx.store, MODEL_INDEX, model))
+        .transpose()?;
+    let by_date = date
+        .map(|date| ctx.indexes.find_prefix(&ctx.store, DATE_INDEX, date))
+        .transpose()?;
+    let cids = match (by_model, by_date) {
+        (Some(by_model), Some(by_date)) => {
+            let by_date: HashSet<Cid> = by_date.into_iter().collect();
+            by_model
+                .into_iter()
+                .filter(|cid| by_date.contains(cid))
+                .collect()
+        }
+        (Some(cids), None) | (None, Some(cids)) => cids,
+        (None, None) => Vec::new(),
+    };
+
+    let mut found = Vec::new();
+    for cid in cids {
+        let cell = ctx.load_message(&cid)?;
+        let metadata = cell.value().metadata.as_ref();
+        found.push(FoundMessage {
+            cid,
+            model: metadata.and_then(|m| m.model.clone()),
+            timestamp_ms: metadata.and_then(|m| m.timestamp_ms),
+            preview: preview(cell.value()),
+        });
+    }
+    found.sort_by_key(|message| message.timestamp_ms);
+    Ok(found)
+}
+
+/// Indexes every message of the named conversations, such as ones stored
+/// before messages were indexed, and returns the number of entries written.
+pub fn reindex(ctx: &mut AppContext) -> Result<usize, SihError> {
+    let mut written = 0;
+    for (_, head) in ctx.store.list_refs(CONVERSATION_REFS)? {
+        let cell = ctx.load_conversation(&head)?;
+        written += ctx.indexes.index_cell(&cell, &ctx.store)?;
+    }
+    Ok(written)
+}
+
+/// Names a conversation not given a name after a message in it, so that it
+/// is listed and can be resumed.
+pub fn auto_name(message: &Cid) -> String {
+    let cid = message.to_string();
+    cid[cid.len().saturating_sub(AUTO_NAME_CHARS)..].to_string()
+}
+
+/// The messages ending at `head`, oldest first.
+fn chain(head: &Arc<Cell<Message>>) -> Vec<&Message> {
+    let mut messages = Vec::new();
+    let mut current = Some(head);
+    while let Some(cell) = current {
+        messages.push(cell.value());
+        current = cell.value().previous.as_ref().and_then(|b| b.cell());
+    }
+    messages.reverse();
+    messages
+}
+
+/// The start of a message's text, on one line.
+fn preview(message: &Message) -> String {
+    let text = match &message.content {
+        MessageContent::System(blocks)
+        | MessageContent::User(blocks)
+        | MessageContent::Assistant { blocks, .. } => blocks
+            .iter()
+            .find_map(|block| match block {
+                ContentBlock::Text(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .unwrap_or(""),
+        MessageContent::ToolResult { result, .. } => result,
+    };
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= PREVIEW_CHARS {
+        return text;
+    }
+    let mut preview: String = text.chars().take(PREVIEW_CHARS - 1).collect();
+    preview.push('…');
+    preview
+}
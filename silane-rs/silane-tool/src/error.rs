@@ -1,30 +1,114 @@
+use std::path::PathBuf;
+
+use miette::Diagnostic;
 use thiserror::Error;
 
-use crate::store::AnyStoreError;
+use crate::store::{AnyStoreError, StoreType};
 
-#[derive(Debug, Error)]
+#[derive(Debug, Error, Diagnostic)]
 pub enum SihError {
-    #[error("API key not found. Set OPENROUTER_API_KEY or configure ~/.config/silane/config.toml")]
+    #[error("API key not found")]
+    #[diagnostic(
+        code(sih::api_key_not_found),
+        help("set OPENROUTER_API_KEY, or add `openrouter_api_key = \"...\"` to ~/.config/silane/config.toml")
+    )]
     ApiKeyNotFound,
 
     #[error("Config error: {0}")]
+    #[diagnostic(code(sih::config))]
     Config(#[from] toml::de::Error),
 
     #[error("IO error: {0}")]
+    #[diagnostic(code(sih::io))]
     Io(#[from] std::io::Error),
 
+    #[error("Failed to open {store_type} store at {}", .path.display())]
+    #[diagnostic(
+        code(sih::open_store),
+        help("check --store and --store-type, and that no other process holds the store open")
+    )]
+    OpenStore {
+        store_type: StoreType,
+        path: PathBuf,
+        #[source]
+        source: AnyStoreError,
+    },
+
     #[error("Store error: {0}")]
+    #[diagnostic(code(sih::store))]
     Store(#[from] AnyStoreError),
 
-    #[error("Invalid CID: {0}")]
-    InvalidCid(#[from] cid::Error),
+    #[error("Persist error: {0}")]
+    #[diagnostic(code(sih::persist))]
+    Persist(#[from] polyepoxide_core::PersistError<AnyStoreError>),
+
+    #[error("Index error: {0}")]
+    #[diagnostic(code(sih::index))]
+    Index(#[from] polyepoxide_core::IndexError<AnyStoreError>),
+
+    #[error("Invalid CID: {input:?}")]
+    #[diagnostic(
+        code(sih::invalid_cid),
+        help("message CIDs are printed by `sih chat` when a conversation ends")
+    )]
+    InvalidCid {
+        input: String,
+        #[source]
+        source: cid::Error,
+    },
+
+    #[error("Message {cid} not found in {}", .path.display())]
+    #[diagnostic(
+        code(sih::message_not_found),
+        help("check that --store points to the store the conversation was saved in")
+    )]
+    MessageNotFound { cid: cid::Cid, path: PathBuf },
+
+    #[error("Failed to decode message {cid}: {message}")]
+    #[diagnostic(
+        code(sih::decode),
+        help("the CID may refer to something other than a chat message")
+    )]
+    DecodeError { cid: cid::Cid, message: String },
 
-    #[error("Message not found: {0}")]
-    MessageNotFound(cid::Cid),
+    #[error("Export error: {0}")]
+    #[diagnostic(
+        code(sih::export),
+        help("export loads whole conversations; make sure every message is in this store")
+    )]
+    Export(#[from] polyepoxide_llm::ExportError),
 
-    #[error("Failed to decode message: {0}")]
-    DecodeError(String),
+    #[error("Import error: {0}")]
+    #[diagnostic(
+        code(sih::import),
+        help("pass the conversations.json from the export, and the --format it was made by")
+    )]
+    Import(#[from] polyepoxide_llm::ImportError),
+
+    #[error("No ref named {0:?}")]
+    #[diagnostic(code(sih::ref_not_found), help("pass a CID, or the full name of a ref"))]
+    RefNotFound(String),
+
+    #[error("Attachment rejected: {0}")]
+    #[diagnostic(code(sih::ingest))]
+    Ingest(#[from] polyepoxide_core::IngestError),
+
+    #[error("Render error: {0}")]
+    #[diagnostic(code(sih::render))]
+    Render(#[from] polyepoxide_core::json::JsonError<AnyStoreError>),
 
     #[error("OpenRouter error: {0}")]
+    #[diagnostic(code(sih::openrouter))]
     OpenRouter(#[from] silane_openrouter::OpenRouterError),
+
+    #[error("Ollama error: {0}")]
+    #[diagnostic(
+        code(sih::ollama),
+        help("check that Ollama is running, e.g. with `ollama serve`")
+    )]
+    Ollama(#[from] silane_ollama::OllamaError),
+
+    #[error("The provider offers no models")]
+    #[diagnostic(code(sih::no_models), help("pull a model with `ollama pull <model>`"))]
+    NoModels,
 }
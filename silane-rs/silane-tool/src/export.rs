@@ -0,0 +1,49 @@
+use std::path::PathBuf;
+
+use cid::Cid;
+use clap::ValueEnum;
+use polyepoxide_core::Bond;
+use polyepoxide_llm::{ExportOptions, export_html, export_json, export_jsonl, export_markdown};
+
+use crate::error::SihError;
+use crate::store::AppContext;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ExportFormat {
+    /// Chat-format JSONL, one conversation per line
+    Jsonl,
+    /// Readable Markdown transcript
+    Markdown,
+    /// Standalone HTML page
+    Html,
+    /// JSON transcript with message CIDs and metadata
+    Json,
+}
+
+/// Exports the branches ending at `heads` to `output`, or stdout. Transcript
+/// formats keep everything and ignore `options`.
+pub fn run(
+    mut ctx: AppContext,
+    heads: &[Cid],
+    format: ExportFormat,
+    options: &ExportOptions,
+    output: Option<PathBuf>,
+) -> Result<(), SihError> {
+    let heads = heads
+        .iter()
+        .map(|cid| Ok(Bond::from_cell(ctx.load_conversation(cid)?)))
+        .collect::<Result<Vec<_>, SihError>>()?;
+
+    let data = match format {
+        ExportFormat::Jsonl => export_jsonl(&heads, options)?,
+        ExportFormat::Markdown => export_markdown(&heads)?,
+        ExportFormat::Html => export_html(&heads)?,
+        ExportFormat::Json => export_json(&heads)?,
+    };
+
+    match output {
+        Some(path) => std::fs::write(path, data)?,
+        None => print!("{}", data),
+    }
+    Ok(())
+}
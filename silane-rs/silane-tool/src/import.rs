@@ -0,0 +1,71 @@
+use std::path::Path;
+
+use clap::ValueEnum;
+use polyepoxide_core::RefStore;
+use polyepoxide_llm::{ImportedConversation, import_anthropic, import_chatgpt};
+
+use crate::conversations::auto_name;
+use crate::error::SihError;
+use crate::store::{AppContext, conversation_ref};
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ImportFormat {
+    /// ChatGPT's conversations.json
+    Chatgpt,
+    /// conversations.json of a Claude data export
+    Anthropic,
+}
+
+/// Stores the conversations of an export. Each is named after its title,
+/// and its other branches after their last messages, like edited branches
+/// in `sih chat`. Importing the same export again changes nothing.
+pub fn run(mut ctx: AppContext, path: &Path, format: ImportFormat) -> Result<(), SihError> {
+    let json = std::fs::read_to_string(path)?;
+    let conversations = match format {
+        ImportFormat::Chatgpt => import_chatgpt(&json)?,
+        ImportFormat::Anthropic => import_anthropic(&json)?,
+    };
+    for conversation in conversations {
+        if let Some(name) = store(&mut ctx, &conversation)? {
+            println!("{}\t{} branches", name, conversation.heads.len());
+        }
+    }
+    Ok(())
+}
+
+/// Persists a conversation's branches and names them, returning the name of
+/// the first.
+fn store(
+    ctx: &mut AppContext,
+    conversation: &ImportedConversation,
+) -> Result<Option<String>, SihError> {
+    let mut first = None;
+    for (i, head) in conversation.heads.iter().enumerate() {
+        let Some(message) = head.value() else {
+            continue;
+        };
+        let cell = ctx.solvent.add(message.clone());
+        // Imported branches are stored whole, so every message is indexed
+        ctx.indexes.persist_cell(&ctx.solvent, &cell, &ctx.store)?;
+        let name = match (i, &conversation.title) {
+            (0, Some(title)) => free_name(ctx, title, head.cid())?,
+            _ => auto_name(&head.cid()),
+        };
+        ctx.store.set_ref(&conversation_ref(&name), &head.cid())?;
+        first.get_or_insert(name);
+    }
+    Ok(first)
+}
+
+/// `title`, or `title (2)`, `title (3)`, … if a different conversation
+/// already has the name.
+fn free_name(ctx: &AppContext, title: &str, head: cid::Cid) -> Result<String, SihError> {
+    let mut name = title.to_string();
+    for n in 2.. {
+        match ctx.store.get_ref(&conversation_ref(&name))? {
+            Some(cid) if cid != head => name = format!("{} ({})", title, n),
+            _ => break,
+        }
+    }
+    Ok(name)
+}
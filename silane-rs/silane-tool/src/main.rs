@@ -1,19 +1,31 @@
 mod config;
+mod conversations;
 mod error;
+mod export;
+mod import;
 mod store;
+mod usage;
 
+#[cfg(feature = "chat")]
+mod ask;
 #[cfg(feature = "chat")]
 mod chat;
+#[cfg(feature = "chat")]
+mod tools;
 
 use std::path::PathBuf;
 use std::str::FromStr;
 
 use cid::Cid;
 use clap::{Parser, Subcommand};
-use silane_openrouter::OpenRouterClient;
+use polyepoxide_core::RefStore;
+use polyepoxide_llm::ExportOptions;
 
-use crate::config::{load_api_key, resolve_store_config};
-use crate::store::{AppContext, StoreType};
+use crate::config::{ProviderType, resolve_store_config};
+use crate::error::SihError;
+use crate::export::ExportFormat;
+use crate::import::ImportFormat;
+use crate::store::{AppContext, StoreType, conversation_ref};
 
 #[derive(Parser)]
 #[command(name = "sih")]
@@ -36,44 +48,254 @@ enum Command {
     #[cfg(feature = "chat")]
     /// Start an interactive chat session
     Chat {
-        /// Continue from existing conversation (Message CID)
+        /// Continue from a message CID or named conversation, leaving the
+        /// original as it is; combine with --name to fork it
         #[arg(long)]
         continue_from: Option<String>,
 
-        /// Model to use
-        #[arg(short, long, default_value = "openai/gpt-4o")]
-        model: String,
+        /// Name the conversation; resumes it if the name is taken, and
+        /// records each new message under the name. Unnamed conversations
+        /// are named after their first new message
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Where to send the conversation: openrouter, or ollama to keep it
+        /// on this machine. Defaults to `provider` in the config file
+        #[arg(long)]
+        provider: Option<ProviderType>,
+
+        /// Model to use; defaults to the first one the provider offers
+        #[arg(short, long)]
+        model: Option<String>,
 
         /// Reasoning effort: low, medium, high
         #[arg(long)]
         reasoning: Option<String>,
+
+        /// Let the model run shell commands, read files and fetch URLs
+        #[arg(long)]
+        tools: bool,
+    },
+
+    #[cfg(feature = "chat")]
+    /// Ask a single question and print the reply, e.g. from scripts. The
+    /// reply's CID is printed to stderr
+    Ask {
+        /// The question; read from stdin if not given
+        question: Option<String>,
+
+        /// Continue from a message CID or named conversation
+        #[arg(long)]
+        continue_from: Option<String>,
+
+        /// Record the question and reply under this conversation name,
+        /// continuing it if no --continue-from is given
+        #[arg(long)]
+        name: Option<String>,
+
+        /// Where to send the question: openrouter or ollama
+        #[arg(long)]
+        provider: Option<ProviderType>,
+
+        /// Model to use; defaults to the first one the provider offers
+        #[arg(short, long)]
+        model: Option<String>,
+    },
+
+    /// List conversations with previews of their first and last messages
+    Conversations,
+
+    /// List messages by the model that wrote them or the day they were
+    /// written, oldest first: CID, model, timestamp in milliseconds and a
+    /// preview
+    Find {
+        /// Model as recorded in replies, e.g. openai/gpt-4o
+        #[arg(long, required_unless_present = "date")]
+        model: Option<String>,
+
+        /// UTC day as YYYY-MM-DD, or a prefix such as YYYY-MM for a month
+        #[arg(long)]
+        date: Option<String>,
+    },
+
+    /// Index the messages of every named conversation, such as ones stored
+    /// before messages were indexed
+    Reindex,
+
+    /// Show token usage and cost of conversations, per model. Prices are
+    /// read from the `pricing` table of the config file
+    Usage {
+        /// Conversation names; all conversations if none are given
+        names: Vec<String>,
+    },
+
+    /// Export conversation branches as a fine-tuning dataset or a readable
+    /// transcript
+    Export {
+        /// Head message CIDs, one exported conversation each
+        #[arg(required_unless_present = "cids")]
+        heads: Vec<String>,
+
+        /// Head message CID, as an alternative to the positional arguments
+        #[arg(long = "cid")]
+        cids: Vec<String>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "jsonl")]
+        format: ExportFormat,
+
+        /// Write to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Leave out system prompts (jsonl only)
+        #[arg(long)]
+        no_system: bool,
+
+        /// Keep thinking blocks (jsonl only)
+        #[arg(long)]
+        thinking: bool,
+
+        /// Keep tool calls and tool results (jsonl only)
+        #[arg(long)]
+        tools: bool,
+    },
+
+    /// Import conversations exported from ChatGPT or Claude, keeping their
+    /// branches
+    Import {
+        /// The export's conversations.json
+        path: PathBuf,
+
+        /// Which application the export is from
+        #[arg(long, value_enum)]
+        format: ImportFormat,
     },
 }
 
 #[tokio::main]
-async fn main() -> anyhow::Result<()> {
+async fn main() -> miette::Result<()> {
     let cli = Cli::parse();
 
     let (store_type, store_path) = resolve_store_config(cli.store_type, cli.store);
-    let ctx = AppContext::open(store_type, store_path)?;
+    let mut ctx = AppContext::open(store_type, store_path)?;
 
     match cli.command {
         #[cfg(feature = "chat")]
         Command::Chat {
             continue_from,
+            name,
+            provider,
             model,
             reasoning,
+            tools,
         } => {
-            let api_key = load_api_key()?;
-            let client = OpenRouterClient::new(api_key);
+            let client = chat::AnyClient::open(provider)?;
+            let continue_cid = resolve_continuation(&ctx, continue_from, &name)?;
 
-            let continue_cid = continue_from
-                .map(|s| Cid::from_str(&s))
-                .transpose()?;
+            let tools = match tools {
+                true => tools::ToolRegistry::with_builtins(),
+                false => tools::ToolRegistry::new(),
+            };
+            chat::run(ctx, client, model, reasoning, continue_cid, name, tools).await?;
+        }
+        #[cfg(feature = "chat")]
+        Command::Ask {
+            question,
+            continue_from,
+            name,
+            provider,
+            model,
+        } => {
+            let client = chat::AnyClient::open(provider)?;
+            let continue_cid = resolve_continuation(&ctx, continue_from, &name)?;
+            ask::run(ctx, client, model, question, continue_cid, name).await?;
+        }
+        Command::Conversations => {
+            for conversation in conversations::list(&mut ctx)? {
+                println!(
+                    "{}\t{}\t{} messages\t{}\t{}",
+                    conversation.name,
+                    conversation.head,
+                    conversation.messages,
+                    conversation.first,
+                    conversation.last
+                );
+            }
+        }
+        Command::Find { model, date } => {
+            for message in conversations::find(&mut ctx, model.as_deref(), date.as_deref())? {
+                let timestamp = message.timestamp_ms.map(|ms| ms.to_string());
+                println!(
+                    "{}\t{}\t{}\t{}",
+                    message.cid,
+                    message.model.as_deref().unwrap_or("-"),
+                    timestamp.as_deref().unwrap_or("-"),
+                    message.preview
+                );
+            }
+        }
+        Command::Reindex => {
+            let written = conversations::reindex(&mut ctx)?;
+            eprintln!("Wrote {} index entries", written);
+        }
+        Command::Usage { names } => usage::run(ctx, names)?,
+        Command::Export {
+            heads,
+            cids,
+            format,
+            output,
+            no_system,
+            thinking,
+            tools,
+        } => {
+            let heads = heads
+                .iter()
+                .chain(&cids)
+                .map(|s| parse_cid(s))
+                .collect::<Result<Vec<_>, _>>()?;
+            let options = ExportOptions::new()
+                .with_system(!no_system)
+                .with_thinking(thinking)
+                .with_tools(tools);
 
-            chat::run(ctx, client, model, reasoning, continue_cid).await?;
+            export::run(ctx, &heads, format, &options, output)?;
         }
+        Command::Import { path, format } => import::run(ctx, &path, format)?,
     }
 
     Ok(())
 }
+
+/// The message to continue from: the one given, or the last one of the
+/// named conversation.
+#[cfg(feature = "chat")]
+fn resolve_continuation(
+    ctx: &AppContext,
+    continue_from: Option<String>,
+    name: &Option<String>,
+) -> Result<Option<Cid>, SihError> {
+    match (continue_from, name) {
+        (Some(head), _) => resolve_head(ctx, &head).map(Some),
+        (None, Some(name)) => Ok(ctx.store.get_ref(&conversation_ref(name))?),
+        (None, None) => Ok(None),
+    }
+}
+
+/// Reads a message CID, or the name of a conversation.
+#[cfg(feature = "chat")]
+fn resolve_head(ctx: &AppContext, input: &str) -> Result<Cid, SihError> {
+    if let Ok(cid) = Cid::from_str(input) {
+        return Ok(cid);
+    }
+    ctx.store
+        .get_ref(&conversation_ref(input))?
+        .ok_or_else(|| SihError::RefNotFound(conversation_ref(input)))
+}
+
+fn parse_cid(input: &str) -> Result<Cid, SihError> {
+    Cid::from_str(input).map_err(|source| SihError::InvalidCid {
+        input: input.to_string(),
+        source,
+    })
+}
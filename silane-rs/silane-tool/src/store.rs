@@ -1,100 +1,126 @@
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
+use std::sync::Arc;
 
 use cid::Cid;
-use polyepoxide_core::{Solvent, Store};
-use polyepoxide_fjall::FjallStore;
-use polyepoxide_rocks::RocksStore;
-use serde::Deserialize;
-use thiserror::Error;
-
-#[derive(Debug, Error)]
-pub enum AnyStoreError {
-    #[error("fjall error: {0}")]
-    Fjall(#[from] polyepoxide_fjall::FjallError),
-    #[error("rocks error: {0}")]
-    Rocks(#[from] polyepoxide_rocks::RocksError),
-}
+use polyepoxide_core::{Cell, Indexes, LoadError, Solvent, SolventError};
+use polyepoxide_llm::Message;
 
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
-#[serde(rename_all = "lowercase")]
-pub enum StoreType {
-    #[default]
-    Fjall,
-    Rocks,
-}
+use crate::error::SihError;
 
-impl std::str::FromStr for StoreType {
-    type Err = String;
+pub use polyepoxide_any::{AnyStore, AnyStoreError, StoreType};
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_lowercase().as_str() {
-            "fjall" => Ok(StoreType::Fjall),
-            "rocks" | "rocksdb" => Ok(StoreType::Rocks),
-            _ => Err(format!("unknown store type: {}", s)),
-        }
-    }
-}
+/// Index of messages by the model that generated them.
+pub const MODEL_INDEX: &str = "message-model";
 
-impl std::fmt::Display for StoreType {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            StoreType::Fjall => write!(f, "fjall"),
-            StoreType::Rocks => write!(f, "rocks"),
-        }
-    }
-}
+/// Index of messages by the UTC day they were written, as `YYYY-MM-DD`.
+pub const DATE_INDEX: &str = "message-date";
 
-pub enum AnyStore {
-    Fjall(FjallStore),
-    Rocks(RocksStore),
+pub struct AppContext {
+    pub store: AnyStore,
+    pub solvent: Solvent,
+    pub indexes: Indexes,
+    pub path: PathBuf,
 }
 
-impl AnyStore {
-    pub fn open(store_type: StoreType, path: impl AsRef<Path>) -> Result<Self, AnyStoreError> {
-        match store_type {
-            StoreType::Fjall => Ok(Self::Fjall(FjallStore::open(path)?)),
-            StoreType::Rocks => Ok(Self::Rocks(RocksStore::open(path)?)),
-        }
+impl AppContext {
+    pub fn open(store_type: StoreType, store_path: PathBuf) -> Result<Self, SihError> {
+        let store =
+            AnyStore::open(store_type, &store_path).map_err(|source| SihError::OpenStore {
+                store_type,
+                path: store_path.clone(),
+                source,
+            })?;
+        let solvent = Solvent::new();
+        let mut indexes = Indexes::new();
+        indexes
+            .define::<Message>(MODEL_INDEX, "metadata[*].model[*]")
+            .expect("messages have a model");
+        indexes
+            .define_with::<Message>(DATE_INDEX, "metadata[*].timestamp_ms[*]", day)
+            .expect("messages have a timestamp");
+
+        Ok(Self {
+            store,
+            solvent,
+            indexes,
+            path: store_path,
+        })
     }
-}
 
-impl Store for AnyStore {
-    type Error = AnyStoreError;
+    /// Persists a message whose predecessors are stored already, and
+    /// indexes it.
+    pub fn persist_message(&self, cell: &Arc<Cell<Message>>) -> Result<(), SihError> {
+        self.solvent.persist_cell(cell, &self.store)?;
+        self.indexes
+            .index_value::<Message>(&cell.cid(), &self.store)?;
+        Ok(())
+    }
 
-    fn get(&self, cid: &Cid) -> Result<Option<Vec<u8>>, Self::Error> {
-        match self {
-            AnyStore::Fjall(s) => s.get(cid).map_err(Into::into),
-            AnyStore::Rocks(s) => s.get(cid).map_err(Into::into),
-        }
+    /// Loads a message and all its predecessors into the solvent.
+    pub fn load_conversation(&mut self, cid: &Cid) -> Result<Arc<Cell<Message>>, SihError> {
+        self.load(cid, usize::MAX)
     }
 
-    fn put(&self, cid: &Cid, value: &[u8]) -> Result<(), Self::Error> {
-        match self {
-            AnyStore::Fjall(s) => s.put(cid, value).map_err(Into::into),
-            AnyStore::Rocks(s) => s.put(cid, value).map_err(Into::into),
-        }
+    /// Loads a message without its predecessors.
+    pub fn load_message(&mut self, cid: &Cid) -> Result<Arc<Cell<Message>>, SihError> {
+        self.load(cid, 0)
     }
 
-    fn has(&self, cid: &Cid) -> Result<bool, Self::Error> {
-        match self {
-            AnyStore::Fjall(s) => s.has(cid).map_err(Into::into),
-            AnyStore::Rocks(s) => s.has(cid).map_err(Into::into),
-        }
+    fn load(&mut self, cid: &Cid, depth: usize) -> Result<Arc<Cell<Message>>, SihError> {
+        self.solvent
+            .load(cid, &self.store, depth)
+            .map_err(|e| match e {
+                LoadError::Store(e) => e.into(),
+                LoadError::Solvent(SolventError::NotFound(cid)) => SihError::MessageNotFound {
+                    cid,
+                    path: self.path.clone(),
+                },
+                LoadError::Solvent(e) => SihError::DecodeError {
+                    cid: *cid,
+                    message: e.to_string(),
+                },
+            })
     }
 }
 
-pub struct AppContext {
-    pub store: AnyStore,
-    pub solvent: Solvent,
+/// Prefix of the refs naming conversations.
+pub const CONVERSATION_REFS: &str = "chat/";
+
+/// Name of the ref holding a named conversation's latest message.
+pub fn conversation_ref(name: &str) -> String {
+    format!("{}{}", CONVERSATION_REFS, name)
 }
 
-impl AppContext {
-    pub fn open(store_type: StoreType, store_path: PathBuf) -> Result<Self, AnyStoreError> {
-        let store = AnyStore::open(store_type, &store_path)?;
-        let solvent = Solvent::new();
+/// Name of the ref holding a named conversation's `CostLedger`.
+pub fn usage_ref(name: &str) -> String {
+    format!("usage/{}", name)
+}
 
-        Ok(Self { store, solvent })
-    }
+/// Name of the ref holding the latest summary made of a named conversation.
+pub fn summary_ref(name: &str) -> String {
+    format!("summary/{}", name)
+}
+
+/// The UTC day of a timestamp in milliseconds, as `YYYY-MM-DD`.
+fn day(timestamp_ms: &str) -> Option<String> {
+    let days = timestamp_ms.parse::<i64>().ok()?.div_euclid(86_400_000);
+    // Civil date from days since 1970-01-01, in eras of 400 years starting
+    // on March 1st so that leap days fall at the end of a year
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    Some(format!("{:04}-{:02}-{:02}", year, month, day))
 }
 
 pub fn default_store_path() -> PathBuf {
@@ -103,3 +129,16 @@ pub fn default_store_path() -> PathBuf {
         .join("silane")
         .join("store")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn days_of_timestamps() {
+        assert_eq!(day("0").as_deref(), Some("1970-01-01"));
+        assert_eq!(day("951782400000").as_deref(), Some("2000-02-29"));
+        assert_eq!(day("1718409599999").as_deref(), Some("2024-06-14"));
+        assert_eq!(day("soon"), None);
+    }
+}
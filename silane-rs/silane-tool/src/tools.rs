@@ -0,0 +1,293 @@
+//! Tools the model may call during a chat, and running its calls.
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
+use polyepoxide_llm::{MessageContent, ToolCall};
+use serde_json::{Value, json};
+use silane_openrouter::ToolDefinition;
+
+/// Longest tool output sent back to the model; the rest is cut off.
+const MAX_OUTPUT: usize = 64 * 1024;
+
+/// How long a shell command or HTTP request may run.
+const TOOL_TIMEOUT: Duration = Duration::from_secs(60);
+
+type ToolFuture = Pin<Box<dyn Future<Output = Result<String, String>> + Send>>;
+
+struct Tool {
+    description: String,
+    parameters: Value,
+    /// Whether the user must approve each call before it runs.
+    confirm: bool,
+    run: Box<dyn Fn(Value) -> ToolFuture + Send + Sync>,
+}
+
+/// Tools offered to the model, by name.
+#[derive(Default)]
+pub struct ToolRegistry {
+    tools: BTreeMap<String, Tool>,
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A registry with the built-in tools: `shell`, `read_file` and
+    /// `http_fetch`. They act with the user's permissions, so `shell` calls
+    /// need confirming; fetched pages could otherwise inject commands.
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        registry.register(
+            "shell",
+            "Run a shell command and return its exit status and output.",
+            json!({
+                "type": "object",
+                "properties": { "command": { "type": "string" } },
+                "required": ["command"]
+            }),
+            shell,
+        );
+        registry.require_confirmation("shell");
+        registry.register(
+            "read_file",
+            "Read a UTF-8 text file.",
+            json!({
+                "type": "object",
+                "properties": { "path": { "type": "string" } },
+                "required": ["path"]
+            }),
+            read_file,
+        );
+        registry.register(
+            "http_fetch",
+            "Fetch a URL with GET and return the response body.",
+            json!({
+                "type": "object",
+                "properties": { "url": { "type": "string" } },
+                "required": ["url"]
+            }),
+            http_fetch,
+        );
+        registry
+    }
+
+    /// Adds a tool taking arguments described by the JSON schema
+    /// `parameters`. `run` returns the output for the model, or an error
+    /// message it is told about instead. Replaces any tool of the same name.
+    pub fn register<F, Fut>(&mut self, name: &str, description: &str, parameters: Value, run: F)
+    where
+        F: Fn(Value) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<String, String>> + Send + 'static,
+    {
+        let tool = Tool {
+            description: description.to_string(),
+            parameters,
+            confirm: false,
+            run: Box::new(move |arguments| Box::pin(run(arguments))),
+        };
+        self.tools.insert(name.to_string(), tool);
+    }
+
+    /// Makes calls of the tool wait for the user's approval.
+    pub fn require_confirmation(&mut self, name: &str) {
+        if let Some(tool) = self.tools.get_mut(name) {
+            tool.confirm = true;
+        }
+    }
+
+    /// Whether the user must approve the call before it's executed.
+    pub fn needs_confirmation(&self, call: &ToolCall) -> bool {
+        self.tools.get(&call.name).is_some_and(|tool| tool.confirm)
+    }
+
+    /// The tools as sent with a completion request.
+    pub fn definitions(&self) -> Vec<ToolDefinition> {
+        self.tools
+            .iter()
+            .map(|(name, tool)| ToolDefinition {
+                name: name.clone(),
+                description: Some(tool.description.clone()),
+                parameters: tool.parameters.to_string(),
+            })
+            .collect()
+    }
+
+    /// Runs a call, returning the result to store as the next message.
+    /// Unknown tools and malformed arguments are reported to the model as
+    /// errors.
+    pub async fn execute(&self, call: &ToolCall) -> MessageContent {
+        let output = match (
+            self.tools.get(&call.name),
+            serde_json::from_str::<Value>(&call.arguments),
+        ) {
+            (None, _) => Err(format!("unknown tool: {}", call.name)),
+            (Some(_), Err(e)) => Err(format!("invalid arguments: {}", e)),
+            (Some(tool), Ok(arguments)) => (tool.run)(arguments).await,
+        };
+        let is_error = output.is_err();
+        MessageContent::ToolResult {
+            tool_call_id: call.id.clone(),
+            result: truncate(output.unwrap_or_else(|e| e)),
+            is_error,
+        }
+    }
+}
+
+fn truncate(mut output: String) -> String {
+    if output.len() > MAX_OUTPUT {
+        let mut end = MAX_OUTPUT;
+        while !output.is_char_boundary(end) {
+            end -= 1;
+        }
+        output.truncate(end);
+        output.push_str("\n[output truncated]");
+    }
+    output
+}
+
+fn string_argument(arguments: &Value, name: &str) -> Result<String, String> {
+    arguments
+        .get(name)
+        .and_then(Value::as_str)
+        .map(String::from)
+        .ok_or_else(|| format!("missing string argument {:?}", name))
+}
+
+async fn shell(arguments: Value) -> Result<String, String> {
+    let command = string_argument(&arguments, "command")?;
+    let output = tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg(&command)
+        .kill_on_drop(true)
+        .output();
+    let output = tokio::time::timeout(TOOL_TIMEOUT, output)
+        .await
+        .map_err(|_| format!("timed out after {:?}", TOOL_TIMEOUT))?
+        .map_err(|e| e.to_string())?;
+    Ok(format!(
+        "exit status: {}\nstdout:\n{}\nstderr:\n{}",
+        output.status,
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    ))
+}
+
+async fn read_file(arguments: Value) -> Result<String, String> {
+    let path = string_argument(&arguments, "path")?;
+    tokio::fs::read_to_string(&path)
+        .await
+        .map_err(|e| format!("{}: {}", path, e))
+}
+
+async fn http_fetch(arguments: Value) -> Result<String, String> {
+    let url = string_argument(&arguments, "url")?;
+    let client = reqwest::Client::builder()
+        .timeout(TOOL_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let response = client.get(&url).send().await.map_err(|e| e.to_string())?;
+    let status = response.status();
+    let body = response.text().await.map_err(|e| e.to_string())?;
+    if status.is_success() {
+        Ok(body)
+    } else {
+        Err(format!("HTTP {}: {}", status, body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn repeat(arguments: Value) -> Result<String, String> {
+        let text = string_argument(&arguments, "text")?;
+        let times = arguments.get("times").and_then(Value::as_u64).unwrap_or(1);
+        Ok(text.repeat(times as usize))
+    }
+
+    fn registry() -> ToolRegistry {
+        let mut registry = ToolRegistry::new();
+        registry.register(
+            "repeat",
+            "Repeat text.",
+            json!({
+                "type": "object",
+                "properties": { "text": { "type": "string" }, "times": { "type": "integer" } },
+                "required": ["text"]
+            }),
+            repeat,
+        );
+        registry
+    }
+
+    async fn execute(registry: &ToolRegistry, name: &str, arguments: &str) -> (String, bool) {
+        let call = ToolCall {
+            id: "call_1".to_string(),
+            name: name.to_string(),
+            arguments: arguments.to_string(),
+        };
+        match registry.execute(&call).await {
+            MessageContent::ToolResult {
+                tool_call_id,
+                result,
+                is_error,
+            } => {
+                assert_eq!(tool_call_id, "call_1");
+                (result, is_error)
+            }
+            _ => panic!("Expected ToolResult"),
+        }
+    }
+
+    #[tokio::test]
+    async fn unknown_tools_are_reported() {
+        let (result, is_error) = execute(&registry(), "delete_everything", "{}").await;
+        assert!(is_error);
+        assert_eq!(result, "unknown tool: delete_everything");
+    }
+
+    #[tokio::test]
+    async fn invalid_arguments_are_reported() {
+        let registry = registry();
+        let (result, is_error) = execute(&registry, "repeat", "{\"text\": ").await;
+        assert!(is_error);
+        assert!(result.starts_with("invalid arguments: "));
+
+        let (result, is_error) = execute(&registry, "repeat", "{\"times\": 2}").await;
+        assert!(is_error);
+        assert_eq!(result, "missing string argument \"text\"");
+
+        let (result, is_error) =
+            execute(&registry, "repeat", "{\"text\": \"ab\", \"times\": 2}").await;
+        assert!(!is_error);
+        assert_eq!(result, "abab");
+    }
+
+    #[tokio::test]
+    async fn long_output_is_truncated_at_a_char_boundary() {
+        let arguments = json!({ "text": "éa", "times": MAX_OUTPUT }).to_string();
+        let (result, is_error) = execute(&registry(), "repeat", &arguments).await;
+        assert!(!is_error);
+        let kept = result.strip_suffix("\n[output truncated]").unwrap();
+        // Byte MAX_OUTPUT falls inside an 'é'
+        assert_eq!(kept.len(), MAX_OUTPUT - 1);
+        assert!(kept.ends_with('a'));
+    }
+
+    #[test]
+    fn shell_calls_need_confirmation() {
+        let registry = ToolRegistry::with_builtins();
+        let call = |name: &str| ToolCall {
+            id: "call_1".to_string(),
+            name: name.to_string(),
+            arguments: "{}".to_string(),
+        };
+        assert!(registry.needs_confirmation(&call("shell")));
+        assert!(!registry.needs_confirmation(&call("read_file")));
+        assert!(!registry.needs_confirmation(&call("unknown")));
+    }
+}
@@ -0,0 +1,114 @@
+//! Running token usage and cost of conversations, stored next to them.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use polyepoxide_core::{Bond, Cell, RefStore};
+use polyepoxide_llm::{CostLedger, Message, ModelPricing};
+
+use crate::config::load_config;
+use crate::error::SihError;
+use crate::store::{AppContext, CONVERSATION_REFS, conversation_ref, usage_ref};
+
+/// Prints the usage of the named conversations, or of all of them, per
+/// model. Stored ledgers are brought up to date on the way.
+pub fn run(mut ctx: AppContext, names: Vec<String>) -> Result<(), SihError> {
+    let pricing = load_config().pricing;
+    let names = match names.is_empty() {
+        true => ctx
+            .store
+            .list_refs(CONVERSATION_REFS)?
+            .into_iter()
+            .map(|(name, _)| name[CONVERSATION_REFS.len()..].to_string())
+            .collect(),
+        false => names,
+    };
+
+    for name in names {
+        let head = ctx
+            .store
+            .get_ref(&conversation_ref(&name))?
+            .ok_or_else(|| SihError::RefNotFound(conversation_ref(&name)))?;
+        let head = ctx.load_conversation(&head)?;
+        let cell = ledger(&mut ctx, Some(&name), &head, &pricing)?;
+        record(&ctx, &name, &cell)?;
+
+        let ledger = cell.value();
+        println!(
+            "{}\t{} messages\t{} tokens\t${:.4}",
+            name,
+            ledger.messages,
+            ledger.spent_totals().total_tokens(),
+            ledger.spent()
+        );
+        for (model, entry) in &ledger.by_model {
+            let model = match model.as_str() {
+                "" => "(no model)",
+                model => model,
+            };
+            println!(
+                "  {}\t{} in\t{} out\t{} cache read\t{} cache write\t${:.4}",
+                model,
+                entry.usage.input_tokens,
+                entry.usage.output_tokens,
+                entry.usage.cache_read_tokens,
+                entry.usage.cache_creation_tokens,
+                entry.cost
+            );
+        }
+        if !ledger.siblings.is_empty() {
+            let cost: f64 = ledger.siblings.iter().map(|s| s.entry.cost).sum();
+            println!(
+                "  {} other replies\t{} tokens\t${:.4}",
+                ledger.siblings.len(),
+                ledger.spent_totals().total_tokens() - ledger.totals().total_tokens(),
+                cost
+            );
+        }
+    }
+    Ok(())
+}
+
+/// The ledger of the branch ending at `head`. If the conversation `name`
+/// has a stored ledger, only messages after it are counted.
+pub fn ledger(
+    ctx: &mut AppContext,
+    name: Option<&str>,
+    head: &Arc<Cell<Message>>,
+    pricing: &HashMap<String, ModelPricing>,
+) -> Result<Arc<Cell<CostLedger>>, SihError> {
+    let stored = match name {
+        Some(name) => ctx.store.get_ref(&usage_ref(name))?,
+        None => None,
+    };
+    // The ledger only saves recounting, so one that can't be read is ignored
+    let stored = stored.and_then(|cid| ctx.solvent.load::<CostLedger>(&cid, &ctx.store, 0).ok());
+    let previous = stored.as_ref().map(|cell| cell.value());
+    Ok(extend(ctx, previous, head, &[], pricing))
+}
+
+/// `previous` with the messages up to `head` counted, or the whole branch
+/// counted if there's no previous ledger. `siblings` are counted as replies
+/// left off the branch.
+pub fn extend(
+    ctx: &mut AppContext,
+    previous: Option<&CostLedger>,
+    head: &Arc<Cell<Message>>,
+    siblings: &[Bond<Message>],
+    pricing: &HashMap<String, ModelPricing>,
+) -> Arc<Cell<CostLedger>> {
+    let head = Bond::from_cell(Arc::clone(head));
+    let mut ledger = match previous {
+        Some(previous) => previous.extend(head, pricing),
+        None => CostLedger::new(head, pricing),
+    };
+    ledger.add_siblings(siblings, pricing);
+    ctx.solvent.add(ledger)
+}
+
+/// Stores a ledger as the conversation's usage.
+pub fn record(ctx: &AppContext, name: &str, ledger: &Cell<CostLedger>) -> Result<(), SihError> {
+    ctx.solvent.persist_cell(ledger, &ctx.store)?;
+    ctx.store.set_ref(&usage_ref(name), &ledger.cid())?;
+    Ok(())
+}